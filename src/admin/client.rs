@@ -0,0 +1,356 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+//! Client for the admin API of a running mock, for test suites that start
+//! the mock as a separate process instead of embedding it.
+//!
+//! Each method wraps one admin endpoint and fails with
+//! [`MockError::Http`] when the request fails or the mock answers with an
+//! error status, carrying the mock's message.
+//!
+//! ```rust,no_run
+//! use raps_mock::admin::Client;
+//! use raps_mock::state::jobs::JobKind;
+//! use serde_json::json;
+//!
+//! # async fn example() -> raps_mock::Result<()> {
+//! let admin = Client::new("http://localhost:3000");
+//! admin.reset().await?;
+//! let _: serde_json::Value = admin.seed("buckets", &json!({ "bucket_key": "designs" })).await?;
+//! admin.fail_next(JobKind::Translation, 1, "Corrupt file").await?;
+//! assert!(admin.requests().await?.is_empty());
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Stubs are read from `--stubs-dir` at startup; there are no admin
+//! endpoints for them to wrap.
+
+use super::ADMIN_PREFIX;
+use crate::error::{MockError, Result};
+use crate::state::clock::ClockStatus;
+use crate::state::jobs::{JobKind, JobTiming};
+use crate::state::journal::JournalEntry;
+use crate::state::translations::TranslationRule;
+use crate::state::webhooks::FailedDelivery;
+use reqwest::{Method, RequestBuilder, Response};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Client for the `/__admin` endpoints of a mock
+#[derive(Debug, Clone)]
+pub struct Client {
+    http: reqwest::Client,
+    /// Base URL of the mock, without a trailing slash
+    base_url: String,
+}
+
+impl Client {
+    /// Client for the mock at `base_url`, e.g. `http://localhost:3000`
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self::with_http_client(reqwest::Client::new(), base_url)
+    }
+
+    /// Client sending its requests through `http`
+    pub fn with_http_client(http: reqwest::Client, base_url: impl Into<String>) -> Self {
+        Self {
+            http,
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+        }
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        self.http
+            .request(method, format!("{}{}{}", self.base_url, ADMIN_PREFIX, path))
+    }
+
+    /// Send `request`, turning transport errors and error statuses into
+    /// [`MockError::Http`]
+    async fn send(request: RequestBuilder) -> Result<Response> {
+        let response = request
+            .send()
+            .await
+            .map_err(|e| MockError::Http(e.to_string()))?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+        let body = response.text().await.unwrap_or_default();
+        let message = serde_json::from_str::<Value>(&body)
+            .ok()
+            .and_then(|v| v.get("message").and_then(Value::as_str).map(String::from))
+            .unwrap_or(body);
+        Err(MockError::Http(format!("{}: {}", status, message)))
+    }
+
+    async fn json<T: DeserializeOwned>(request: RequestBuilder) -> Result<T> {
+        Self::send(request)
+            .await?
+            .json()
+            .await
+            .map_err(|e| MockError::Http(e.to_string()))
+    }
+
+    /// The value of `field` in a JSON object response
+    async fn field<T: DeserializeOwned>(request: RequestBuilder, field: &str) -> Result<T> {
+        let mut body: Value = Self::json(request).await?;
+        serde_json::from_value(body[field].take()).map_err(|e| MockError::Http(e.to_string()))
+    }
+
+    /// Put the state back as a freshly started server has it
+    pub async fn reset(&self) -> Result<()> {
+        Self::send(self.request(Method::POST, "/reset")).await?;
+        Ok(())
+    }
+
+    /// Reset the state, then apply the `--seed-dir` fixtures again. Returns
+    /// the number of records seeded.
+    pub async fn reset_and_seed(&self) -> Result<usize> {
+        Self::field(
+            self.request(Method::POST, "/reset")
+                .query(&[("seed", "true")]),
+            "seeded",
+        )
+        .await
+    }
+
+    /// Stored records of `kind` (`buckets`, `buckets/{bucketKey}/objects`,
+    /// `hubs`, `projects/{projectId}/issues`, `jobs`, `hooks`, `tokens`)
+    pub async fn records<T: DeserializeOwned>(&self, kind: &str) -> Result<Vec<T>> {
+        let field = kind.rsplit('/').next().unwrap_or(kind);
+        Self::field(
+            self.request(Method::GET, &format!("/state/{}", kind)),
+            field,
+        )
+        .await
+    }
+
+    /// Store a record of `kind` directly, returning it as stored. Fields
+    /// `record` leaves out default like a freshly created record's.
+    pub async fn seed<T: DeserializeOwned>(
+        &self,
+        kind: &str,
+        record: &impl Serialize,
+    ) -> Result<T> {
+        Self::json(
+            self.request(Method::POST, &format!("/state/{}", kind))
+                .json(record),
+        )
+        .await
+    }
+
+    /// Delete the stored record of `kind` with `id`
+    pub async fn delete_record(&self, kind: &str, id: &str) -> Result<()> {
+        Self::send(self.request(Method::DELETE, &format!("/state/{}/{}", kind, id))).await?;
+        Ok(())
+    }
+
+    /// Capture every stored record under `name`
+    pub async fn take_snapshot(&self, name: &str) -> Result<()> {
+        Self::send(self.request(Method::POST, &format!("/snapshots/{}", name))).await?;
+        Ok(())
+    }
+
+    /// Replace the state with the snapshot `name`
+    pub async fn restore_snapshot(&self, name: &str) -> Result<()> {
+        Self::send(self.request(Method::POST, &format!("/snapshots/{}/restore", name))).await?;
+        Ok(())
+    }
+
+    /// Requests the mock received, oldest first
+    pub async fn requests(&self) -> Result<Vec<JournalEntry>> {
+        Self::field(self.request(Method::GET, "/requests"), "requests").await
+    }
+
+    pub async fn clear_requests(&self) -> Result<()> {
+        Self::send(self.request(Method::DELETE, "/requests")).await?;
+        Ok(())
+    }
+
+    /// Make the token `access_token` expire now; its refresh token still
+    /// works
+    pub async fn expire_token(&self, access_token: &str) -> Result<()> {
+        Self::send(self.request(Method::POST, &format!("/tokens/{}/expire", access_token))).await?;
+        Ok(())
+    }
+
+    /// Phase durations per job kind
+    pub async fn job_timings(&self) -> Result<HashMap<JobKind, JobTiming>> {
+        Self::json(self.request(Method::GET, "/jobs/config")).await
+    }
+
+    /// Set the phase durations of new jobs of `kind`
+    pub async fn set_job_timing(&self, kind: JobKind, timing: JobTiming) -> Result<()> {
+        Self::send(
+            self.request(Method::PUT, "/jobs/config")
+                .json(&HashMap::from([(kind, timing)])),
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Make the next `count` jobs of `kind` fail with `message`
+    pub async fn fail_next(&self, kind: JobKind, count: u32, message: &str) -> Result<()> {
+        Self::send(self.request(Method::POST, "/jobs/fail-next").json(&json!({
+            "kind": kind,
+            "count": count,
+            "message": message
+        })))
+        .await?;
+        Ok(())
+    }
+
+    /// Finish the running job `job_id` right away
+    pub async fn complete_job(&self, job_id: &str) -> Result<()> {
+        Self::send(self.request(Method::POST, &format!("/jobs/{}/complete", job_id))).await?;
+        Ok(())
+    }
+
+    /// Fail the running job `job_id` right away with `message`
+    pub async fn fail_job(&self, job_id: &str, message: &str) -> Result<()> {
+        Self::send(
+            self.request(Method::POST, &format!("/jobs/{}/fail", job_id))
+                .json(&json!({ "message": message })),
+        )
+        .await?;
+        Ok(())
+    }
+
+    pub async fn translation_rules(&self) -> Result<Vec<TranslationRule>> {
+        Self::json(self.request(Method::GET, "/jobs/translation-rules")).await
+    }
+
+    /// Replace the rules deciding how translations of matching URNs end
+    pub async fn set_translation_rules(&self, rules: &[TranslationRule]) -> Result<()> {
+        Self::send(
+            self.request(Method::PUT, "/jobs/translation-rules")
+                .json(rules),
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Sign notifications to the hooks app `client_id` creates with `secret`
+    pub async fn set_webhook_secret(&self, client_id: &str, secret: &str) -> Result<()> {
+        Self::send(
+            self.request(Method::PUT, &format!("/webhooks/secrets/{}", client_id))
+                .json(&json!({ "token": secret })),
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Deliver an event to the hooks subscribed to it, returning their IDs.
+    /// `payload` overrides fields of the event's default payload.
+    pub async fn trigger_webhook(
+        &self,
+        system: &str,
+        event: &str,
+        payload: &impl Serialize,
+    ) -> Result<Vec<String>> {
+        Self::field(
+            self.request(Method::POST, "/webhooks/trigger")
+                .json(&json!({ "system": system, "event": event, "payload": payload })),
+            "hooks",
+        )
+        .await
+    }
+
+    /// Webhook notifications no delivery attempt got through, oldest first
+    pub async fn failed_deliveries(&self) -> Result<Vec<FailedDelivery>> {
+        Self::field(
+            self.request(Method::GET, "/webhooks/deliveries"),
+            "deliveries",
+        )
+        .await
+    }
+
+    /// Replace the latency rules, given as `--latency` takes them
+    pub async fn set_latency(&self, rules: &[&str]) -> Result<()> {
+        Self::send(
+            self.request(Method::PUT, "/latency")
+                .json(&json!({ "rules": rules })),
+        )
+        .await?;
+        Ok(())
+    }
+
+    pub async fn clock(&self) -> Result<ClockStatus> {
+        Self::json(self.request(Method::GET, "/clock")).await
+    }
+
+    /// Stop the virtual clock at `at` (epoch millis), or where it stands
+    pub async fn freeze_clock(&self, at: Option<i64>) -> Result<ClockStatus> {
+        Self::json(
+            self.request(Method::POST, "/clock/freeze")
+                .json(&json!({ "at": at })),
+        )
+        .await
+    }
+
+    /// Move the virtual clock `by` forward
+    pub async fn advance_clock(&self, by: Duration) -> Result<ClockStatus> {
+        let millis = i64::try_from(by.as_millis()).unwrap_or(i64::MAX);
+        Self::json(
+            self.request(Method::POST, "/clock/advance")
+                .json(&json!({ "millis": millis })),
+        )
+        .await
+    }
+
+    /// Let the virtual clock follow the system clock again
+    pub async fn reset_clock(&self) -> Result<ClockStatus> {
+        Self::json(self.request(Method::DELETE, "/clock")).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::buckets::BucketInfo;
+    use crate::testing::TestServer;
+
+    #[tokio::test]
+    async fn test_client_drives_the_admin_api() {
+        let server = TestServer::start_with_openapi_dir("/nonexistent".into())
+            .await
+            .unwrap();
+        let admin = Client::new(format!("{}/", server.url));
+
+        let bucket: BucketInfo = admin
+            .seed("buckets", &json!({ "bucket_key": "designs" }))
+            .await
+            .unwrap();
+        assert_eq!(bucket.bucket_key, "designs");
+        admin.take_snapshot("golden").await.unwrap();
+        admin.reset().await.unwrap();
+        assert!(admin.records::<Value>("buckets").await.unwrap().is_empty());
+        admin.restore_snapshot("golden").await.unwrap();
+        let buckets: Vec<BucketInfo> = admin.records("buckets").await.unwrap();
+        assert_eq!(buckets.len(), 1);
+
+        let frozen = admin.freeze_clock(Some(1_000_000)).await.unwrap();
+        assert!(frozen.frozen);
+        let advanced = admin.advance_clock(Duration::from_secs(60)).await.unwrap();
+        assert_eq!(advanced.now, 1_060_000);
+
+        let error = admin.complete_job("no-such-job").await.unwrap_err();
+        assert!(matches!(error, MockError::Http(message) if message.starts_with("409")));
+        assert!(admin.reset_and_seed().await.is_err());
+
+        reqwest::get(format!("{}/oss/v2/buckets", server.url))
+            .await
+            .unwrap();
+        let requests = admin.requests().await.unwrap();
+        assert_eq!(requests.last().unwrap().path, "/oss/v2/buckets");
+        admin.clear_requests().await.unwrap();
+        assert!(admin.requests().await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,141 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+//! Virtual clock control, served under `/__admin/clock`.
+//!
+//! Token expiry, upload session expiry, job progress and record timestamps
+//! all read the [`MockClock`](crate::state::clock::MockClock), so a test can
+//! freeze it and move it past a token's `expires_at` instead of sleeping.
+//! Every endpoint answers with the clock's status.
+//!
+//! | Path | Methods |
+//! |------|---------|
+//! | `/clock` | `GET`, `DELETE` (follow the system clock again) |
+//! | `/clock/freeze` | `POST` (`{"at": epochMillis}`, optional) |
+//! | `/clock/resume` | `POST` |
+//! | `/clock/advance` | `POST` (`{"seconds": n}` or `{"millis": n}`) |
+
+use super::{bad_request, state_unavailable};
+use crate::state::StateManager;
+use crate::state::clock::ClockStatus;
+use axum::{
+    Extension, Router,
+    extract::Json,
+    http::StatusCode,
+    response::{IntoResponse, Json as JsonResponse, Response},
+    routing::{get, post},
+};
+use serde::Deserialize;
+
+/// Build the clock router (nested under `/clock`)
+pub fn router(state: Option<StateManager>) -> Router {
+    let Some(state) = state else {
+        return Router::new().fallback(|| async { state_unavailable() });
+    };
+
+    Router::new()
+        .route("/", get(clock_status).delete(reset_clock))
+        .route("/freeze", post(freeze_clock))
+        .route("/resume", post(resume_clock))
+        .route("/advance", post(advance_clock))
+        .layer(Extension(state))
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct FreezeRequest {
+    /// Time to stop at (epoch millis); the current time when absent
+    at: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AdvanceRequest {
+    seconds: Option<i64>,
+    millis: Option<i64>,
+}
+
+fn status_response(status: ClockStatus) -> Response {
+    (StatusCode::OK, JsonResponse(status)).into_response()
+}
+
+async fn clock_status(Extension(state): Extension<StateManager>) -> Response {
+    status_response(state.clock.status())
+}
+
+async fn reset_clock(Extension(state): Extension<StateManager>) -> Response {
+    state.clock.reset();
+    status_response(state.clock.status())
+}
+
+async fn freeze_clock(
+    Extension(state): Extension<StateManager>,
+    body: Option<Json<FreezeRequest>>,
+) -> Response {
+    let Json(request) = body.unwrap_or_default();
+    status_response(state.clock.freeze(request.at))
+}
+
+async fn resume_clock(Extension(state): Extension<StateManager>) -> Response {
+    status_response(state.clock.resume())
+}
+
+async fn advance_clock(
+    Extension(state): Extension<StateManager>,
+    Json(request): Json<AdvanceRequest>,
+) -> Response {
+    let millis = match (request.seconds, request.millis) {
+        (Some(seconds), None) => seconds.saturating_mul(1000),
+        (None, Some(millis)) => millis,
+        _ => return bad_request("Give either seconds or millis"),
+    };
+    status_response(state.clock.advance(millis))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::{Body, to_bytes};
+    use axum::http::Request;
+    use serde_json::Value;
+    use tower::ServiceExt;
+
+    async fn send(app: &Router, method: &str, uri: &str, body: &str) -> (StatusCode, Value) {
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (
+            status,
+            serde_json::from_slice(&bytes).unwrap_or(Value::Null),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_advancing_the_clock_expires_tokens() {
+        let state = StateManager::new();
+        let app = router(Some(state.clone()));
+        let (status, body) = send(&app, "POST", "/freeze", "").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["frozen"], true);
+        let token = state.auth.generate_token("client", 3600, None);
+
+        let (_, body) = send(&app, "POST", "/advance", r#"{"seconds": 3599}"#).await;
+        assert!(state.auth.lookup_token(&token.access_token).is_some());
+        let frozen_at = body["now"].as_i64().unwrap();
+        assert_eq!(state.clock.now_millis(), frozen_at);
+
+        send(&app, "POST", "/advance", r#"{"millis": 1000}"#).await;
+        assert!(state.auth.lookup_token(&token.access_token).is_none());
+
+        let (status, _) = send(&app, "POST", "/advance", "{}").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let (_, body) = send(&app, "DELETE", "/", "").await;
+        assert_eq!(body["frozen"], false);
+        assert!(state.auth.lookup_token(&token.access_token).is_some());
+    }
+}
@@ -0,0 +1,100 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+//! Server-sent events stream of state changes, served at `/__admin/events`.
+//!
+//! Each change to a bucket, object, issue, job or hook is sent as an event
+//! named after the resource and action (`bucket.created`, `job.deleted`)
+//! whose data is the [`StateEvent`] as JSON. A subscriber too slow to keep
+//! up receives a `lagged` event with the number of events it missed.
+
+use super::state_unavailable;
+use crate::state::StateManager;
+use crate::state::events::StateEvent;
+use axum::{
+    Extension, Router,
+    response::{
+        IntoResponse, Response,
+        sse::{Event, KeepAlive, Sse},
+    },
+    routing::get,
+};
+use tokio::sync::broadcast::error::RecvError;
+
+/// Build the events router (nested under `/events`)
+pub fn router(state: Option<StateManager>) -> Router {
+    let Some(state) = state else {
+        return Router::new().fallback(|| async { state_unavailable() });
+    };
+
+    Router::new()
+        .route("/", get(stream_events))
+        .layer(Extension(state))
+}
+
+async fn stream_events(Extension(state): Extension<StateManager>) -> Response {
+    let receiver = state.events.subscribe();
+    let events = futures_util::stream::unfold(receiver, |mut receiver| async move {
+        let event = match receiver.recv().await {
+            Ok(event) => sse_event(&event),
+            Err(RecvError::Lagged(missed)) => {
+                Ok(Event::default().event("lagged").data(missed.to_string()))
+            }
+            Err(RecvError::Closed) => return None,
+        };
+        Some((event, receiver))
+    });
+    Sse::new(events)
+        .keep_alive(KeepAlive::default())
+        .into_response()
+}
+
+fn sse_event(event: &StateEvent) -> Result<Event, axum::Error> {
+    Event::default().event(event.name()).json_data(event)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::audit::{AuditAction, Change};
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode, header};
+    use futures_util::StreamExt;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_changes_are_streamed_to_subscribers() {
+        let state = StateManager::new();
+        let app = router(Some(state.clone()));
+        assert!(!state.events.has_subscribers());
+
+        let request = Request::get("/").body(Body::empty()).unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            "text/event-stream"
+        );
+        assert!(state.events.has_subscribers());
+
+        let change = |kind: &str, id: &str| Change {
+            kind: kind.into(),
+            id: id.into(),
+            action: AuditAction::Created,
+            old: None,
+            new: Some(serde_json::json!({ "bucket_key": id })),
+        };
+        // Tokens are not streamed
+        state.events.publish(
+            7,
+            &[change("tokens", "secret"), change("buckets", "streamed")],
+        );
+
+        let mut body = response.into_body().into_data_stream();
+        let chunk = body.next().await.unwrap().unwrap();
+        let text = String::from_utf8(chunk.to_vec()).unwrap();
+        assert!(text.starts_with("event: bucket.created\n"), "{}", text);
+        assert!(text.contains(r#""id":"streamed""#), "{}", text);
+        assert!(text.contains(r#""timestamp":7"#), "{}", text);
+    }
+}
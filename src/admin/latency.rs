@@ -0,0 +1,70 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+//! Artificial latency, served at `/__admin/latency`.
+//!
+//! Rules are given as `--latency` takes them: `MS`, `MIN..MAX` or
+//! `normal:MEAN,STDDEV`, optionally prefixed with `[METHOD] /route/pattern=`.
+//! Unlike most admin endpoints these work without state too.
+//!
+//! | Path | Methods |
+//! |------|---------|
+//! | `/latency` | `GET`, `PUT` (`{"rules": [...]}`, replacing them), `DELETE` |
+
+use super::bad_request;
+use crate::config::LatencyRule;
+use crate::middleware::latency::LatencyInjector;
+use axum::{
+    Extension, Router,
+    extract::Json,
+    http::StatusCode,
+    response::{IntoResponse, Json as JsonResponse, Response},
+    routing::get,
+};
+use serde::Deserialize;
+use serde_json::json;
+use std::sync::Arc;
+
+/// Build the latency router (merged at the admin root)
+pub fn router(latency: Arc<LatencyInjector>) -> Router {
+    Router::new()
+        .route(
+            "/latency",
+            get(list_rules).put(set_rules).delete(clear_rules),
+        )
+        .layer(Extension(latency))
+}
+
+#[derive(Debug, Deserialize)]
+struct RulesRequest {
+    rules: Vec<String>,
+}
+
+async fn list_rules(Extension(latency): Extension<Arc<LatencyInjector>>) -> Response {
+    rules_response(&latency)
+}
+
+async fn set_rules(
+    Extension(latency): Extension<Arc<LatencyInjector>>,
+    Json(request): Json<RulesRequest>,
+) -> Response {
+    let rules: Result<Vec<LatencyRule>, String> =
+        request.rules.iter().map(|rule| rule.parse()).collect();
+    match rules {
+        Ok(rules) => {
+            latency.set_rules(rules);
+            rules_response(&latency)
+        }
+        Err(message) => bad_request(&message),
+    }
+}
+
+async fn clear_rules(Extension(latency): Extension<Arc<LatencyInjector>>) -> Response {
+    latency.set_rules(Vec::new());
+    StatusCode::NO_CONTENT.into_response()
+}
+
+fn rules_response(latency: &LatencyInjector) -> Response {
+    let rules: Vec<String> = latency.rules().iter().map(ToString::to_string).collect();
+    (StatusCode::OK, JsonResponse(json!({ "rules": rules }))).into_response()
+}
@@ -0,0 +1,923 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+//! Admin API for inspecting and controlling the mock, served under `/__admin`.
+//!
+//! These endpoints are not part of APS; they exist so test harnesses can look
+//! behind the curtain without going through the public endpoints.
+
+mod client;
+mod clock;
+mod events;
+mod latency;
+mod namespaces;
+mod snapshots;
+mod state;
+mod webhooks;
+
+use crate::har::Har;
+use crate::metrics::Metrics;
+use crate::middleware::latency::LatencyInjector;
+use crate::openapi::schemas::SchemaRegistry;
+use crate::server::catalog::RouteCatalog;
+use crate::state::StateManager;
+use crate::state::jobs::{JobKind, JobOutcome, JobTiming};
+use crate::state::journal::RequestMatcher;
+use crate::state::synthetic;
+use crate::state::templates::ProjectTemplate;
+use crate::state::translations::TranslationRule;
+use axum::{
+    Extension, Router,
+    extract::{Json, Path, Query},
+    http::StatusCode,
+    response::{IntoResponse, Json as JsonResponse, Response},
+    routing::{get, post},
+};
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+pub use client::Client;
+
+/// Path prefix the admin router is mounted under
+pub const ADMIN_PREFIX: &str = "/__admin";
+
+/// Every admin route as method and path below [`ADMIN_PREFIX`], for route
+/// listings; keep in step with [`router`]
+pub const ROUTES: &[(&str, &str)] = &[
+    ("GET", "/metrics"),
+    ("GET", "/routes"),
+    ("GET", "/routes/skipped"),
+    ("GET", "/schemas"),
+    ("GET", "/schemas/:service/:name"),
+    ("GET", "/requests"),
+    ("DELETE", "/requests"),
+    ("GET", "/requests/har"),
+    ("GET", "/audit"),
+    ("DELETE", "/audit"),
+    ("GET", "/tokens/usage"),
+    ("DELETE", "/tokens/usage"),
+    ("POST", "/tokens/:access_token/expire"),
+    ("GET", "/uploads"),
+    ("POST", "/uploads/purge"),
+    ("GET", "/jobs"),
+    ("GET", "/jobs/notifications"),
+    ("GET", "/jobs/config"),
+    ("PUT", "/jobs/config"),
+    ("GET", "/jobs/translation-rules"),
+    ("PUT", "/jobs/translation-rules"),
+    ("POST", "/jobs/fail-next"),
+    ("POST", "/jobs/:job_id/:action"),
+    ("POST", "/projects"),
+    ("POST", "/synthetic/:kind"),
+    ("POST", "/reset"),
+    ("GET", "/latency"),
+    ("PUT", "/latency"),
+    ("DELETE", "/latency"),
+    ("GET", "/clock"),
+    ("DELETE", "/clock"),
+    ("POST", "/clock/freeze"),
+    ("POST", "/clock/resume"),
+    ("POST", "/clock/advance"),
+    ("GET", "/events"),
+    ("GET", "/namespaces"),
+    ("DELETE", "/namespaces/:name"),
+    ("GET", "/snapshots"),
+    ("POST", "/snapshots/:name"),
+    ("DELETE", "/snapshots/:name"),
+    ("POST", "/snapshots/:name/restore"),
+    ("GET", "/state/buckets"),
+    ("POST", "/state/buckets"),
+    ("DELETE", "/state/buckets/:bucket_key"),
+    ("GET", "/state/buckets/:bucket_key/objects"),
+    ("POST", "/state/buckets/:bucket_key/objects"),
+    ("DELETE", "/state/buckets/:bucket_key/objects/:object_key"),
+    ("GET", "/state/hubs"),
+    ("POST", "/state/hubs"),
+    ("DELETE", "/state/hubs/:hub_id"),
+    ("GET", "/state/projects/:project_id/issues"),
+    ("POST", "/state/projects/:project_id/issues"),
+    ("DELETE", "/state/projects/:project_id/issues/:issue_id"),
+    ("GET", "/state/jobs"),
+    ("POST", "/state/jobs"),
+    ("DELETE", "/state/jobs/:job_id"),
+    ("GET", "/state/hooks"),
+    ("POST", "/state/hooks"),
+    ("DELETE", "/state/hooks/:hook_id"),
+    ("GET", "/state/tokens"),
+    ("POST", "/state/tokens"),
+    ("DELETE", "/state/tokens/:access_token"),
+    ("GET", "/state/users"),
+    ("POST", "/state/users"),
+    ("DELETE", "/state/users/:user_id"),
+    ("GET", "/state/clients"),
+    ("POST", "/state/clients"),
+    ("DELETE", "/state/clients/:client_id"),
+    ("GET", "/webhooks/deliveries"),
+    ("DELETE", "/webhooks/deliveries"),
+    ("POST", "/webhooks/trigger"),
+    ("GET", "/webhooks/secrets"),
+    ("PUT", "/webhooks/secrets/:client_id"),
+    ("DELETE", "/webhooks/secrets/:client_id"),
+];
+
+/// Build the admin router (mounted under [`ADMIN_PREFIX`])
+pub fn router(
+    state: Option<StateManager>,
+    metrics: Arc<Metrics>,
+    schemas: Arc<SchemaRegistry>,
+    project_template: Option<Arc<ProjectTemplate>>,
+    seed_dir: Option<PathBuf>,
+    latency: Arc<LatencyInjector>,
+) -> Router {
+    let uploads_state = state.clone();
+    let purge_state = state.clone();
+    let requests_state = state.clone();
+    let clear_requests_state = state.clone();
+    let har_state = state.clone();
+    let audit_state = state.clone();
+    let clear_audit_state = state.clone();
+    let jobs_state = state.clone();
+    let job_notifications_state = state.clone();
+    let job_config_state = state.clone();
+    let update_job_config_state = state.clone();
+    let fail_next_state = state.clone();
+    let translation_rules_state = state.clone();
+    let update_translation_rules_state = state.clone();
+    let settle_state = state.clone();
+    let projects_state = state.clone();
+    let token_usage_state = state.clone();
+    let clear_token_usage_state = state.clone();
+    let expire_token_state = state.clone();
+    let synthetic_state = state.clone();
+    let reset_state = state.clone();
+    let schema_names = schemas.clone();
+    let clock_router = clock::router(state.clone());
+    let events_router = events::router(state.clone());
+    let webhooks_router = webhooks::router(state.clone());
+    let snapshots_router = snapshots::router(state.clone());
+    let state_router = state::router(state);
+
+    Router::new()
+        .route(
+            "/metrics",
+            get(move || {
+                let metrics = metrics.clone();
+                async move {
+                    (
+                        StatusCode::OK,
+                        [("Content-Type", "text/plain; version=0.0.4")],
+                        metrics.render(),
+                    )
+                        .into_response()
+                }
+            }),
+        )
+        .route(
+            "/routes",
+            get(|Extension(catalog): Extension<Arc<RouteCatalog>>| async move {
+                let groups: Vec<_> = catalog
+                    .by_tag()
+                    .into_iter()
+                    .map(|(tag, routes)| {
+                        let routes: Vec<_> = routes
+                            .into_iter()
+                            .map(|route| {
+                                json!({
+                                    "method": route.method.as_str(),
+                                    "path": route.path,
+                                    "operationId": route.operation_id,
+                                    "source": route.source.as_str(),
+                                    "origin": route.origin
+                                })
+                            })
+                            .collect();
+                        json!({ "tag": tag, "routes": routes })
+                    })
+                    .collect();
+                (StatusCode::OK, JsonResponse(json!({ "groups": groups }))).into_response()
+            }),
+        )
+        .route(
+            "/routes/skipped",
+            get(|Extension(catalog): Extension<Arc<RouteCatalog>>| async move {
+                let skipped: Vec<_> = catalog
+                    .skipped()
+                    .iter()
+                    .map(|route| {
+                        json!({
+                            "method": route.method,
+                            "path": route.path,
+                            "origin": route.origin,
+                            "reason": route.reason.as_str(),
+                            "detail": route.detail
+                        })
+                    })
+                    .collect();
+                (StatusCode::OK, JsonResponse(json!({ "skipped": skipped }))).into_response()
+            }),
+        )
+        .route(
+            "/schemas",
+            get(move || {
+                let schemas = schema_names.clone();
+                async move {
+                    let names: Vec<_> = schemas
+                        .names()
+                        .into_iter()
+                        .map(|(service, name)| json!({ "service": service, "name": name }))
+                        .collect();
+                    (StatusCode::OK, JsonResponse(json!({ "schemas": names }))).into_response()
+                }
+            }),
+        )
+        .route(
+            "/schemas/:service/:name",
+            get(
+                move |Path((service, name)): Path<(String, String)>,
+                      Query(query): Query<SchemaQuery>| {
+                    let schemas = schemas.clone();
+                    async move {
+                        let Some(schema) = schemas.json_schema(&service, &name) else {
+                            return (
+                                StatusCode::NOT_FOUND,
+                                JsonResponse(json!({
+                                    "message": format!("Schema {}/{} not found", service, name)
+                                })),
+                            )
+                                .into_response();
+                        };
+                        let disposition = if query.download {
+                            format!("attachment; filename=\"{}.schema.json\"", name)
+                        } else {
+                            "inline".to_string()
+                        };
+                        (
+                            StatusCode::OK,
+                            [
+                                ("Content-Type", "application/schema+json".to_string()),
+                                ("Content-Disposition", disposition),
+                            ],
+                            JsonResponse(schema),
+                        )
+                            .into_response()
+                    }
+                },
+            ),
+        )
+        .route(
+            "/requests",
+            get(move |Query(matcher): Query<RequestMatcher>| {
+                let state_inner = requests_state.clone();
+                async move {
+                    let Some(state_manager) = state_inner else {
+                        return state_unavailable();
+                    };
+                    if let Err(message) = matcher.validate() {
+                        return bad_request(&message);
+                    }
+                    let requests = state_manager.journal.find(&matcher);
+                    (
+                        StatusCode::OK,
+                        JsonResponse(json!({ "requests": requests })),
+                    )
+                        .into_response()
+                }
+            })
+            .delete(move || {
+                let state_inner = clear_requests_state.clone();
+                async move {
+                    let Some(state_manager) = state_inner else {
+                        return state_unavailable();
+                    };
+                    state_manager.journal.clear();
+                    StatusCode::NO_CONTENT.into_response()
+                }
+            }),
+        )
+        .route(
+            "/requests/har",
+            get(move || {
+                let state_inner = har_state.clone();
+                async move {
+                    let Some(state_manager) = state_inner else {
+                        return state_unavailable();
+                    };
+                    let har = Har::from_journal(&state_manager.journal.entries());
+                    (StatusCode::OK, JsonResponse(har)).into_response()
+                }
+            }),
+        )
+        .route(
+            "/audit",
+            get(move |Query(query): Query<AuditQuery>| {
+                let state_inner = audit_state.clone();
+                async move {
+                    let Some(state_manager) = state_inner else {
+                        return state_unavailable();
+                    };
+                    let entries = state_manager.audit.entries(query.since);
+                    (
+                        StatusCode::OK,
+                        JsonResponse(json!({
+                            "enabled": state_manager.audit.is_enabled(),
+                            "entries": entries
+                        })),
+                    )
+                        .into_response()
+                }
+            })
+            .delete(move || {
+                let state_inner = clear_audit_state.clone();
+                async move {
+                    let Some(state_manager) = state_inner else {
+                        return state_unavailable();
+                    };
+                    state_manager.audit.clear();
+                    StatusCode::NO_CONTENT.into_response()
+                }
+            }),
+        )
+        .route(
+            "/tokens/usage",
+            get(move || {
+                let state_inner = token_usage_state.clone();
+                async move {
+                    let Some(state_manager) = state_inner else {
+                        return state_unavailable();
+                    };
+                    let tokens = state_manager.auth.usage();
+                    (StatusCode::OK, JsonResponse(json!({ "tokens": tokens }))).into_response()
+                }
+            })
+            .delete(move || {
+                let state_inner = clear_token_usage_state.clone();
+                async move {
+                    let Some(state_manager) = state_inner else {
+                        return state_unavailable();
+                    };
+                    state_manager.auth.clear_usage();
+                    StatusCode::NO_CONTENT.into_response()
+                }
+            }),
+        )
+        .route(
+            "/tokens/:access_token/expire",
+            post(move |Path(access_token): Path<String>| {
+                let state_inner = expire_token_state.clone();
+                async move {
+                    let Some(state_manager) = state_inner else {
+                        return state_unavailable();
+                    };
+                    if state_manager.auth.expire_token(&access_token) {
+                        StatusCode::NO_CONTENT.into_response()
+                    } else {
+                        not_found("Token not found")
+                    }
+                }
+            }),
+        )
+        .route(
+            "/uploads",
+            get(move || {
+                let state_inner = uploads_state.clone();
+                async move {
+                    let Some(state_manager) = state_inner else {
+                        return state_unavailable();
+                    };
+                    let now = state_manager.clock.now_millis();
+                    let uploads: Vec<_> = state_manager
+                        .uploads
+                        .list_sessions()
+                        .into_iter()
+                        .map(|s| {
+                            json!({
+                                "uploadKey": s.upload_key,
+                                "bucketKey": s.bucket_key,
+                                "objectKey": s.object_key,
+                                "parts": s.parts,
+                                "uploadedParts": s.uploaded_parts.len(),
+                                "uploadedSize": s.uploaded_size(),
+                                "createdAt": s.created_at,
+                                "expiresAt": s.expires_at,
+                                "expired": s.is_expired(now)
+                            })
+                        })
+                        .collect();
+                    (StatusCode::OK, JsonResponse(json!({ "uploads": uploads }))).into_response()
+                }
+            }),
+        )
+        .route(
+            "/uploads/purge",
+            post(move || {
+                let state_inner = purge_state.clone();
+                async move {
+                    let Some(state_manager) = state_inner else {
+                        return state_unavailable();
+                    };
+                    let purged = state_manager.uploads.purge_expired();
+                    (StatusCode::OK, JsonResponse(json!({ "purged": purged }))).into_response()
+                }
+            }),
+        )
+        .route(
+            "/jobs",
+            get(move |Query(query): Query<JobsQuery>| {
+                let state_inner = jobs_state.clone();
+                async move {
+                    let Some(state_manager) = state_inner else {
+                        return state_unavailable();
+                    };
+                    let kind = match query.kind.as_deref().map(str::parse::<JobKind>) {
+                        Some(Err(e)) => return bad_request(&e),
+                        Some(Ok(kind)) => Some(kind),
+                        None => None,
+                    };
+                    let now = state_manager.clock.now_millis();
+                    let jobs: Vec<_> = state_manager
+                        .jobs
+                        .list(kind)
+                        .into_iter()
+                        .map(|job| {
+                            let status = job.status_at(now);
+                            json!({
+                                "id": job.id,
+                                "kind": job.kind,
+                                "key": job.key,
+                                "createdAt": job.created_at,
+                                "status": status.phase,
+                                "progress": status.progress,
+                                "message": status.message
+                            })
+                        })
+                        .collect();
+                    (StatusCode::OK, JsonResponse(json!({ "jobs": jobs }))).into_response()
+                }
+            }),
+        )
+        .route(
+            "/jobs/notifications",
+            get(move |Query(query): Query<JobNotificationsQuery>| {
+                let state_inner = job_notifications_state.clone();
+                async move {
+                    let Some(state_manager) = state_inner else {
+                        return state_unavailable();
+                    };
+                    let kind = match query.kind.as_deref().map(str::parse::<JobKind>) {
+                        Some(Err(e)) => return bad_request(&e),
+                        Some(Ok(kind)) => Some(kind),
+                        None => None,
+                    };
+                    // Catch up with jobs that moved on since the watcher last looked
+                    state_manager.jobs.observe();
+                    let notifications: Vec<_> = state_manager
+                        .jobs
+                        .notifications(query.since)
+                        .into_iter()
+                        .filter(|n| kind.is_none_or(|kind| n.kind == kind))
+                        .collect();
+                    (
+                        StatusCode::OK,
+                        JsonResponse(json!({ "notifications": notifications })),
+                    )
+                        .into_response()
+                }
+            }),
+        )
+        .route(
+            "/jobs/config",
+            get(move || {
+                let state_inner = job_config_state.clone();
+                async move {
+                    let Some(state_manager) = state_inner else {
+                        return state_unavailable();
+                    };
+                    let timings: HashMap<JobKind, JobTiming> = JobKind::ALL
+                        .into_iter()
+                        .map(|kind| (kind, state_manager.jobs.timing(kind)))
+                        .collect();
+                    (StatusCode::OK, JsonResponse(json!(timings))).into_response()
+                }
+            })
+            .put(move |Json(timings): Json<HashMap<JobKind, JobTiming>>| {
+                let state_inner = update_job_config_state.clone();
+                async move {
+                    let Some(state_manager) = state_inner else {
+                        return state_unavailable();
+                    };
+                    for (kind, timing) in timings {
+                        state_manager.jobs.set_timing(kind, timing);
+                    }
+                    StatusCode::NO_CONTENT.into_response()
+                }
+            }),
+        )
+        .route(
+            "/jobs/translation-rules",
+            get(move || {
+                let state_inner = translation_rules_state.clone();
+                async move {
+                    let Some(state_manager) = state_inner else {
+                        return state_unavailable();
+                    };
+                    let rules = state_manager.translations.rules();
+                    (StatusCode::OK, JsonResponse(json!(rules))).into_response()
+                }
+            })
+            .put(move |Json(rules): Json<Vec<TranslationRule>>| {
+                let state_inner = update_translation_rules_state.clone();
+                async move {
+                    let Some(state_manager) = state_inner else {
+                        return state_unavailable();
+                    };
+                    state_manager.translations.set_rules(rules);
+                    StatusCode::NO_CONTENT.into_response()
+                }
+            }),
+        )
+        .route(
+            "/jobs/fail-next",
+            post(move |Json(request): Json<FailNextRequest>| {
+                let state_inner = fail_next_state.clone();
+                async move {
+                    let Some(state_manager) = state_inner else {
+                        return state_unavailable();
+                    };
+                    state_manager
+                        .jobs
+                        .fail_next(request.kind, request.count, request.message);
+                    StatusCode::NO_CONTENT.into_response()
+                }
+            }),
+        )
+        .route(
+            "/jobs/:job_id/:action",
+            post(
+                move |Path((job_id, action)): Path<(String, String)>,
+                      body: Option<Json<SettleRequest>>| {
+                    let state_inner = settle_state.clone();
+                    async move {
+                        let Some(state_manager) = state_inner else {
+                            return state_unavailable();
+                        };
+                        let outcome = match action.as_str() {
+                            "complete" => JobOutcome::Success,
+                            "fail" => JobOutcome::Failed {
+                                message: body
+                                    .and_then(|Json(b)| b.message)
+                                    .unwrap_or_else(|| DEFAULT_FAILURE_MESSAGE.to_string()),
+                            },
+                            "timeout" => JobOutcome::Timeout {
+                                message: body
+                                    .and_then(|Json(b)| b.message)
+                                    .unwrap_or_else(|| DEFAULT_TIMEOUT_MESSAGE.to_string()),
+                            },
+                            _ => {
+                                return bad_request(
+                                    "Action must be 'complete', 'fail' or 'timeout'",
+                                );
+                            }
+                        };
+                        if state_manager.jobs.settle(&job_id, outcome) {
+                            StatusCode::NO_CONTENT.into_response()
+                        } else {
+                            (
+                                StatusCode::CONFLICT,
+                                JsonResponse(json!({
+                                    "message": format!("Job {} is unknown or already finished", job_id)
+                                })),
+                            )
+                                .into_response()
+                        }
+                    }
+                },
+            ),
+        )
+        .route(
+            "/projects",
+            post(move |Json(request): Json<CreateProjectRequest>| {
+                let state_inner = projects_state.clone();
+                let template = project_template.clone();
+                async move {
+                    let Some(state_manager) = state_inner else {
+                        return state_unavailable();
+                    };
+                    let Some(project) = state_manager.create_project(
+                        &request.hub_id,
+                        request.name,
+                        template.as_deref(),
+                    ) else {
+                        return (
+                            StatusCode::NOT_FOUND,
+                            JsonResponse(json!({
+                                "message": format!("Hub {} not found", request.hub_id)
+                            })),
+                        )
+                            .into_response();
+                    };
+                    let (folders, models, issues) =
+                        template.map(|t| t.counts()).unwrap_or_default();
+                    (
+                        StatusCode::CREATED,
+                        JsonResponse(json!({
+                            "id": project.id,
+                            "hubId": project.hub_id,
+                            "name": project.name,
+                            "template": {
+                                "folders": folders,
+                                "models": models,
+                                "issues": issues
+                            }
+                        })),
+                    )
+                        .into_response()
+                }
+            }),
+        )
+        .route(
+            "/synthetic/:kind",
+            post(
+                move |Path(kind): Path<String>, Json(request): Json<SyntheticRequest>| {
+                    let state_inner = synthetic_state.clone();
+                    async move {
+                        let Some(state_manager) = state_inner else {
+                            return state_unavailable();
+                        };
+                        generate_synthetic(&state_manager, &kind, request)
+                    }
+                },
+            ),
+        )
+        .route(
+            "/reset",
+            post(move |Query(query): Query<ResetQuery>| {
+                let state_inner = reset_state.clone();
+                let seed_dir = seed_dir.clone();
+                async move {
+                    let Some(state_manager) = state_inner else {
+                        return state_unavailable();
+                    };
+                    reset(&state_manager, query.seed, seed_dir.as_deref())
+                }
+            }),
+        )
+        .merge(latency::router(latency))
+        .nest("/clock", clock_router)
+        .nest("/events", events_router)
+        .nest("/namespaces", namespaces::router())
+        .nest("/snapshots", snapshots_router)
+        .nest("/state", state_router)
+        .nest("/webhooks", webhooks_router)
+}
+
+#[derive(Debug, Deserialize)]
+struct AuditQuery {
+    /// Only entries recorded at or after this time (epoch millis)
+    since: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SyntheticRequest {
+    count: usize,
+    #[serde(default = "default_synthetic_prefix")]
+    prefix: String,
+    bucket_key: Option<String>,
+    project_id: Option<String>,
+    folder_id: Option<String>,
+}
+
+fn default_synthetic_prefix() -> String {
+    synthetic::DEFAULT_PREFIX.to_string()
+}
+
+/// Bulk-import `request.count` synthetic objects, issues or items
+fn generate_synthetic(state: &StateManager, kind: &str, request: SyntheticRequest) -> Response {
+    if request.count == 0 || request.count > synthetic::MAX_SYNTHETIC_RECORDS {
+        return bad_request(&format!(
+            "count must be between 1 and {}",
+            synthetic::MAX_SYNTHETIC_RECORDS
+        ));
+    }
+    let started = std::time::Instant::now();
+    let (created, parent) = match kind {
+        "objects" => {
+            let Some(bucket_key) = request.bucket_key else {
+                return bad_request("bucketKey is required");
+            };
+            if state.buckets.get_bucket(&bucket_key).is_none() {
+                return not_found(&format!("Bucket {} not found", bucket_key));
+            }
+            let objects = synthetic::objects(&bucket_key, &request.prefix, request.count);
+            (state.objects.import_objects(objects), bucket_key)
+        }
+        "issues" => {
+            let Some(project_id) = request.project_id else {
+                return bad_request("projectId is required");
+            };
+            let issues = synthetic::issues(&project_id, &request.prefix, request.count);
+            (state.issues.import_issues(issues), project_id)
+        }
+        "items" => {
+            let Some(folder_id) = request.folder_id else {
+                return bad_request("folderId is required");
+            };
+            let Some(folder) = state.projects.get_folder(&folder_id) else {
+                return not_found(&format!("Folder {} not found", folder_id));
+            };
+            let items = synthetic::items(&folder, &request.prefix, request.count);
+            (state.projects.import_items(items), folder_id)
+        }
+        _ => return bad_request("Kind must be 'objects', 'issues' or 'items'"),
+    };
+    tracing::info!(
+        "Generated {} synthetic {} in {} in {:?}",
+        created,
+        kind,
+        parent,
+        started.elapsed()
+    );
+    (
+        StatusCode::CREATED,
+        JsonResponse(json!({
+            "kind": kind,
+            "parent": parent,
+            "created": created,
+            "elapsedMs": started.elapsed().as_millis() as u64
+        })),
+    )
+        .into_response()
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CreateProjectRequest {
+    #[serde(default = "default_hub_id")]
+    hub_id: String,
+    name: String,
+}
+
+fn default_hub_id() -> String {
+    "b.default-hub".to_string()
+}
+
+/// Failure message for jobs failed without one
+const DEFAULT_FAILURE_MESSAGE: &str = "Simulated job failure";
+
+/// Failure message for jobs timed out without one
+const DEFAULT_TIMEOUT_MESSAGE: &str = "Simulated job timeout";
+
+#[derive(Debug, Deserialize)]
+struct SchemaQuery {
+    /// Serve as an attachment named `<name>.schema.json`
+    #[serde(default)]
+    download: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct JobsQuery {
+    kind: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct JobNotificationsQuery {
+    kind: Option<String>,
+    /// Only notifications after this sequence number
+    since: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct FailNextRequest {
+    kind: JobKind,
+    #[serde(default = "default_fail_count")]
+    count: u32,
+    #[serde(default = "default_failure_message")]
+    message: String,
+}
+
+fn default_fail_count() -> u32 {
+    1
+}
+
+fn default_failure_message() -> String {
+    DEFAULT_FAILURE_MESSAGE.to_string()
+}
+
+#[derive(Debug, Deserialize)]
+struct SettleRequest {
+    message: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ResetQuery {
+    /// Re-apply the `--seed-dir` fixtures after clearing
+    #[serde(default)]
+    seed: bool,
+}
+
+fn reset(state: &StateManager, seed: bool, seed_dir: Option<&std::path::Path>) -> Response {
+    // Read the fixtures first, so a broken file leaves the state alone
+    let fixtures = match (seed, seed_dir) {
+        (false, _) => None,
+        (true, None) => return bad_request("No --seed-dir is configured"),
+        (true, Some(dir)) => match crate::state::fixtures::Fixtures::load_dir(dir) {
+            Ok(fixtures) => Some(fixtures),
+            Err(e) => return bad_request(&e.to_string()),
+        },
+    };
+    state.reset();
+    let Some(fixtures) = fixtures else {
+        return StatusCode::NO_CONTENT.into_response();
+    };
+    match fixtures.apply(state) {
+        Ok(()) => (
+            StatusCode::OK,
+            JsonResponse(json!({ "seeded": fixtures.len() })),
+        )
+            .into_response(),
+        Err(e) => bad_request(&e.to_string()),
+    }
+}
+
+fn bad_request(message: &str) -> Response {
+    (
+        StatusCode::BAD_REQUEST,
+        JsonResponse(json!({ "message": message })),
+    )
+        .into_response()
+}
+
+fn not_found(message: &str) -> Response {
+    (
+        StatusCode::NOT_FOUND,
+        JsonResponse(json!({ "message": message })),
+    )
+        .into_response()
+}
+
+/// Response for state endpoints when the server runs in stateless mode
+fn state_unavailable() -> Response {
+    (
+        StatusCode::NOT_FOUND,
+        JsonResponse(json!({
+            "message": "State is not available in stateless mode"
+        })),
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::extract::Request;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_listed_routes_are_routed() {
+        let router = router(
+            Some(StateManager::new()),
+            Arc::new(Metrics::new()),
+            Arc::new(SchemaRegistry::from_routes(&[])),
+            None,
+            None,
+            Arc::new(LatencyInjector::new(Vec::new())),
+        );
+        for (method, path) in ROUTES {
+            let uri: Vec<&str> = path
+                .split('/')
+                .map(|segment| {
+                    if segment.starts_with(':') {
+                        "x"
+                    } else {
+                        segment
+                    }
+                })
+                .collect();
+            let request = Request::builder()
+                .method(*method)
+                .uri(uri.join("/"))
+                .body(Body::empty())
+                .unwrap();
+            let response = router.clone().oneshot(request).await.unwrap();
+            let status = response.status();
+            assert_ne!(
+                status,
+                StatusCode::METHOD_NOT_ALLOWED,
+                "{} {}",
+                method,
+                path
+            );
+            if status == StatusCode::NOT_FOUND {
+                // Handlers answer 404 with a body, unknown routes without
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                assert!(!body.is_empty(), "{} {} is not routed", method, path);
+            }
+        }
+    }
+}
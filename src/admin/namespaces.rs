@@ -0,0 +1,54 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+//! State namespaces (see [`crate::middleware::namespaces`]), served under
+//! `/__admin/namespaces`.
+//!
+//! | Path | Methods |
+//! |------|---------|
+//! | `/namespaces` | `GET` |
+//! | `/namespaces/:name` | `DELETE` |
+
+use super::{not_found, state_unavailable};
+use crate::middleware::namespaces::Namespaces;
+use axum::{
+    Extension, Router,
+    extract::Path,
+    http::StatusCode,
+    response::{IntoResponse, Json as JsonResponse, Response},
+    routing::{delete, get},
+};
+use serde_json::json;
+use std::sync::Arc;
+
+/// Build the namespaces router (nested under `/namespaces`)
+pub fn router() -> Router {
+    Router::new()
+        .route("/", get(list_namespaces))
+        .route("/:name", delete(delete_namespace))
+}
+
+async fn list_namespaces(namespaces: Option<Extension<Arc<Namespaces>>>) -> Response {
+    let Some(Extension(namespaces)) = namespaces else {
+        return state_unavailable();
+    };
+    (
+        StatusCode::OK,
+        JsonResponse(json!({ "namespaces": namespaces.names() })),
+    )
+        .into_response()
+}
+
+async fn delete_namespace(
+    namespaces: Option<Extension<Arc<Namespaces>>>,
+    Path(name): Path<String>,
+) -> Response {
+    let Some(Extension(namespaces)) = namespaces else {
+        return state_unavailable();
+    };
+    if namespaces.remove(&name) {
+        StatusCode::NO_CONTENT.into_response()
+    } else {
+        not_found(&format!("Namespace {} not found", name))
+    }
+}
@@ -0,0 +1,166 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+//! Named state snapshots, served under `/__admin/snapshots`.
+//!
+//! A snapshot captures every record a state file holds, so a test suite can
+//! build a "golden" state once and have each test restore it instead of
+//! rebuilding it through the API. Snapshots live in memory until deleted and
+//! survive restores; job timings, token usage and the request journal are not
+//! part of them.
+//!
+//! | Path | Methods |
+//! |------|---------|
+//! | `/snapshots` | `GET` |
+//! | `/snapshots/:name` | `POST` (capture), `DELETE` |
+//! | `/snapshots/:name/restore` | `POST` |
+
+use super::{not_found, state_unavailable};
+use crate::state::StateManager;
+use crate::state::persistence::NamedSnapshot;
+use axum::{
+    Extension, Router,
+    extract::Path,
+    http::StatusCode,
+    response::{IntoResponse, Json as JsonResponse, Response},
+    routing::{get, post},
+};
+use serde_json::{Value, json};
+
+/// Build the snapshots router (nested under `/snapshots`)
+pub fn router(state: Option<StateManager>) -> Router {
+    let Some(state) = state else {
+        return Router::new().fallback(|| async { state_unavailable() });
+    };
+
+    Router::new()
+        .route("/", get(list_snapshots))
+        .route("/:name", post(take_snapshot).delete(delete_snapshot))
+        .route("/:name/restore", post(restore_snapshot))
+        .layer(Extension(state))
+}
+
+async fn list_snapshots(Extension(state): Extension<StateManager>) -> Response {
+    let mut snapshots: Vec<NamedSnapshot> = state.snapshots.iter().map(|s| s.clone()).collect();
+    snapshots.sort_by(|a, b| a.name.cmp(&b.name));
+    let snapshots: Vec<Value> = snapshots.iter().map(snapshot_json).collect();
+    (
+        StatusCode::OK,
+        JsonResponse(json!({ "snapshots": snapshots })),
+    )
+        .into_response()
+}
+
+async fn take_snapshot(
+    Extension(state): Extension<StateManager>,
+    Path(name): Path<String>,
+) -> Response {
+    let snapshot = state.take_snapshot(&name);
+    (StatusCode::CREATED, JsonResponse(snapshot_json(&snapshot))).into_response()
+}
+
+async fn restore_snapshot(
+    Extension(state): Extension<StateManager>,
+    Path(name): Path<String>,
+) -> Response {
+    match state.restore_snapshot(&name) {
+        Ok(true) => StatusCode::NO_CONTENT.into_response(),
+        Ok(false) => not_found(&format!("Snapshot {} not found", name)),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            JsonResponse(json!({ "message": e.to_string() })),
+        )
+            .into_response(),
+    }
+}
+
+async fn delete_snapshot(
+    Extension(state): Extension<StateManager>,
+    Path(name): Path<String>,
+) -> Response {
+    match state.snapshots.remove(&name) {
+        Some(_) => StatusCode::NO_CONTENT.into_response(),
+        None => not_found(&format!("Snapshot {} not found", name)),
+    }
+}
+
+fn snapshot_json(snapshot: &NamedSnapshot) -> Value {
+    let file = &snapshot.file;
+    json!({
+        "name": snapshot.name,
+        "createdAt": snapshot.created_at,
+        "counts": {
+            "buckets": file.buckets.len(),
+            "objects": file.objects.len(),
+            "projects": file.projects.len(),
+            "items": file.items.len(),
+            "jobs": file.jobs.len(),
+            "issues": file.issues.len(),
+            "hooks": file.hooks.len(),
+            "tokens": file.tokens.len()
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::{Body, to_bytes};
+    use axum::http::Request;
+    use tower::ServiceExt;
+
+    async fn send(app: &Router, method: &str, uri: &str) -> (StatusCode, Value) {
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .body(Body::empty())
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (
+            status,
+            serde_json::from_slice(&bytes).unwrap_or(Value::Null),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_golden_state_is_restored_repeatedly() {
+        let state = StateManager::new();
+        let app = router(Some(state.clone()));
+        state
+            .buckets
+            .create_bucket("golden".into(), "transient".into());
+
+        let (status, snapshot) = send(&app, "POST", "/golden").await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(snapshot["counts"]["buckets"], 1);
+
+        for _ in 0..2 {
+            state
+                .buckets
+                .create_bucket("scratch".into(), "transient".into());
+            state.buckets.delete_bucket("golden");
+            let (status, _) = send(&app, "POST", "/golden/restore").await;
+            assert_eq!(status, StatusCode::NO_CONTENT);
+            let keys: Vec<String> = state
+                .buckets
+                .list_buckets()
+                .into_iter()
+                .map(|b| b.bucket_key)
+                .collect();
+            assert_eq!(keys, ["golden"]);
+        }
+
+        let (_, listed) = send(&app, "GET", "/").await;
+        assert_eq!(listed["snapshots"][0]["name"], "golden");
+        assert_eq!(
+            send(&app, "DELETE", "/golden").await.0,
+            StatusCode::NO_CONTENT
+        );
+        assert_eq!(
+            send(&app, "POST", "/golden/restore").await.0,
+            StatusCode::NOT_FOUND
+        );
+    }
+}
@@ -0,0 +1,569 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+//! Direct access to the mock's state, served under `/__admin/state`.
+//!
+//! Records are listed, created and deleted as stored, bypassing the business
+//! rules of the public endpoints: a bucket may be created with any key, an
+//! object without uploading it, a job in any phase. A created record only
+//! needs its identifying fields; the rest default like a freshly created one.
+//! Creating a record with an existing identifier replaces it.
+//!
+//! | Path | Methods |
+//! |------|---------|
+//! | `/state/buckets` | `GET`, `POST` |
+//! | `/state/buckets/:bucket_key` | `DELETE` |
+//! | `/state/buckets/:bucket_key/objects` | `GET`, `POST` |
+//! | `/state/buckets/:bucket_key/objects/:object_key` | `DELETE` |
+//! | `/state/hubs` | `GET`, `POST` |
+//! | `/state/hubs/:hub_id` | `DELETE` |
+//! | `/state/projects/:project_id/issues` | `GET`, `POST` |
+//! | `/state/projects/:project_id/issues/:issue_id` | `DELETE` |
+//! | `/state/jobs` | `GET`, `POST` |
+//! | `/state/jobs/:job_id` | `DELETE` |
+//! | `/state/hooks` | `GET`, `POST` |
+//! | `/state/hooks/:hook_id` | `DELETE` |
+//! | `/state/tokens` | `GET`, `POST` |
+//! | `/state/tokens/:access_token` | `DELETE` |
+//! | `/state/users` | `GET`, `POST` |
+//! | `/state/users/:user_id` | `DELETE` |
+//! | `/state/clients` | `GET`, `POST` |
+//! | `/state/clients/:client_id` | `DELETE` |
+//!
+//! Users and clients are settings rather than records: resets keep them.
+
+use super::{bad_request, not_found, state_unavailable};
+use crate::state::StateManager;
+use crate::state::auth::TokenInfo;
+use crate::state::buckets::BucketInfo;
+use crate::state::clients::ClientCredentials;
+use crate::state::issues::IssueInfo;
+use crate::state::jobs::{Job, JobKind};
+use crate::state::objects::ObjectInfo;
+use crate::state::projects::HubInfo;
+use crate::state::users::UserInfo;
+use crate::state::webhooks::WebhookSubscription;
+use axum::{
+    Extension, Router,
+    extract::{Json, Path},
+    http::StatusCode,
+    response::{IntoResponse, Json as JsonResponse, Response},
+    routing::{delete, get},
+};
+use serde::de::DeserializeOwned;
+use serde_json::{Value, json};
+
+/// Build the state router (nested under `/state`)
+pub fn router(state: Option<StateManager>) -> Router {
+    let Some(state) = state else {
+        return Router::new().fallback(|| async { state_unavailable() });
+    };
+
+    Router::new()
+        .route("/buckets", get(list_buckets).post(create_bucket))
+        .route("/buckets/:bucket_key", delete(delete_bucket))
+        .route(
+            "/buckets/:bucket_key/objects",
+            get(list_objects).post(create_object),
+        )
+        .route(
+            "/buckets/:bucket_key/objects/:object_key",
+            delete(delete_object),
+        )
+        .route("/hubs", get(list_hubs).post(create_hub))
+        .route("/hubs/:hub_id", delete(delete_hub))
+        .route(
+            "/projects/:project_id/issues",
+            get(list_issues).post(create_issue),
+        )
+        .route(
+            "/projects/:project_id/issues/:issue_id",
+            delete(delete_issue),
+        )
+        .route("/jobs", get(list_jobs).post(create_job))
+        .route("/jobs/:job_id", delete(delete_job))
+        .route("/hooks", get(list_hooks).post(create_hook))
+        .route("/hooks/:hook_id", delete(delete_hook))
+        .route("/tokens", get(list_tokens).post(create_token))
+        .route("/tokens/:access_token", delete(delete_token))
+        .route("/users", get(list_users).post(create_user))
+        .route("/users/:user_id", delete(delete_user))
+        .route("/clients", get(list_clients).post(create_client))
+        .route("/clients/:client_id", delete(delete_client))
+        .layer(Extension(state))
+}
+
+async fn list_buckets(Extension(state): Extension<StateManager>) -> Response {
+    listed("buckets", state.buckets.list_buckets())
+}
+
+async fn create_bucket(
+    Extension(state): Extension<StateManager>,
+    Json(body): Json<Value>,
+) -> Response {
+    let defaults = json!({
+        "bucket_owner": "mock-owner",
+        "created_date": state.clock.now_millis(),
+        "policy_key": "transient",
+        "permissions": []
+    });
+    match record::<BucketInfo>(defaults, body) {
+        Ok(bucket) => {
+            state.buckets.insert_bucket(bucket.clone());
+            created(bucket)
+        }
+        Err(message) => bad_request(&message),
+    }
+}
+
+async fn delete_bucket(
+    Extension(state): Extension<StateManager>,
+    Path(bucket_key): Path<String>,
+) -> Response {
+    deleted(
+        state.buckets.delete_bucket(&bucket_key),
+        &format!("Bucket {} not found", bucket_key),
+    )
+}
+
+async fn list_objects(
+    Extension(state): Extension<StateManager>,
+    Path(bucket_key): Path<String>,
+) -> Response {
+    listed("objects", state.objects.list_objects(&bucket_key))
+}
+
+async fn create_object(
+    Extension(state): Extension<StateManager>,
+    Path(bucket_key): Path<String>,
+    Json(mut body): Json<Value>,
+) -> Response {
+    let Some(object_key) = body.get("object_key").and_then(Value::as_str) else {
+        return bad_request("object_key is required");
+    };
+    let defaults = json!(ObjectInfo::new(&bucket_key, object_key, 0, None));
+    // The bucket in the path wins over one in the body
+    if let Some(fields) = body.as_object_mut() {
+        fields.insert("bucket_key".into(), json!(bucket_key));
+    }
+    match record::<ObjectInfo>(defaults, body) {
+        Ok(object) => {
+            state.objects.insert_object(object.clone());
+            created(object)
+        }
+        Err(message) => bad_request(&message),
+    }
+}
+
+async fn delete_object(
+    Extension(state): Extension<StateManager>,
+    Path((bucket_key, object_key)): Path<(String, String)>,
+) -> Response {
+    deleted(
+        state.objects.delete_object(&bucket_key, &object_key),
+        &format!("Object {}/{} not found", bucket_key, object_key),
+    )
+}
+
+async fn list_hubs(Extension(state): Extension<StateManager>) -> Response {
+    listed("hubs", state.projects.list_hubs())
+}
+
+async fn create_hub(
+    Extension(state): Extension<StateManager>,
+    Json(body): Json<Value>,
+) -> Response {
+    let defaults = json!({ "name": "Mock Hub", "region": "US" });
+    match record::<HubInfo>(defaults, body) {
+        Ok(hub) => {
+            state.projects.insert_hub(hub.clone());
+            created(hub)
+        }
+        Err(message) => bad_request(&message),
+    }
+}
+
+async fn delete_hub(
+    Extension(state): Extension<StateManager>,
+    Path(hub_id): Path<String>,
+) -> Response {
+    deleted(
+        state.projects.delete_hub(&hub_id),
+        &format!("Hub {} not found", hub_id),
+    )
+}
+
+async fn list_issues(
+    Extension(state): Extension<StateManager>,
+    Path(project_id): Path<String>,
+) -> Response {
+    listed("issues", state.issues.list_issues(&project_id))
+}
+
+async fn create_issue(
+    Extension(state): Extension<StateManager>,
+    Path(project_id): Path<String>,
+    Json(mut body): Json<Value>,
+) -> Response {
+    let defaults = json!({
+        "id": uuid::Uuid::new_v4().to_string(),
+        "description": null,
+        "status": "open",
+        "created_at": state.clock.now_millis()
+    });
+    if let Some(fields) = body.as_object_mut() {
+        fields.insert("project_id".into(), json!(project_id));
+    }
+    match record::<IssueInfo>(defaults, body) {
+        Ok(issue) => {
+            state.issues.insert_issue(issue.clone());
+            created(issue)
+        }
+        Err(message) => bad_request(&message),
+    }
+}
+
+async fn delete_issue(
+    Extension(state): Extension<StateManager>,
+    Path((project_id, issue_id)): Path<(String, String)>,
+) -> Response {
+    deleted(
+        state.issues.delete_issue(&project_id, &issue_id),
+        &format!("Issue {} not found in project {}", issue_id, project_id),
+    )
+}
+
+async fn list_jobs(Extension(state): Extension<StateManager>) -> Response {
+    listed("jobs", state.jobs.list(None))
+}
+
+async fn create_job(
+    Extension(state): Extension<StateManager>,
+    Json(body): Json<Value>,
+) -> Response {
+    let kind = match body
+        .get("kind")
+        .cloned()
+        .map(serde_json::from_value::<JobKind>)
+    {
+        Some(Ok(kind)) => kind,
+        Some(Err(e)) => return bad_request(&format!("Invalid job kind: {}", e)),
+        None => return bad_request("kind is required"),
+    };
+    // Jobs are stored camelCase, like the job status the public APIs derive
+    let defaults = json!({
+        "id": uuid::Uuid::new_v4().to_string(),
+        "key": "",
+        "createdAt": state.clock.now_millis(),
+        "timing": state.jobs.timing(kind),
+        "outcome": { "result": "success" }
+    });
+    match record::<Job>(defaults, body) {
+        Ok(job) => {
+            state.jobs.insert(job.clone());
+            created(job)
+        }
+        Err(message) => bad_request(&message),
+    }
+}
+
+async fn delete_job(
+    Extension(state): Extension<StateManager>,
+    Path(job_id): Path<String>,
+) -> Response {
+    deleted(
+        state.jobs.remove(&job_id),
+        &format!("Job {} not found", job_id),
+    )
+}
+
+async fn list_hooks(Extension(state): Extension<StateManager>) -> Response {
+    listed("hooks", state.webhooks.list_subscriptions())
+}
+
+async fn create_hook(
+    Extension(state): Extension<StateManager>,
+    Json(body): Json<Value>,
+) -> Response {
+    let defaults = json!({
+        "hook_id": uuid::Uuid::new_v4().to_string(),
+        "tenant": "mock-tenant",
+        "scope": {},
+        "status": "active",
+        "created_at": state.clock.now_millis()
+    });
+    match record::<WebhookSubscription>(defaults, body) {
+        Ok(hook) => {
+            state.webhooks.insert_subscription(hook.clone());
+            created(hook)
+        }
+        Err(message) => bad_request(&message),
+    }
+}
+
+async fn delete_hook(
+    Extension(state): Extension<StateManager>,
+    Path(hook_id): Path<String>,
+) -> Response {
+    deleted(
+        state.webhooks.delete_subscription(&hook_id),
+        &format!("Hook {} not found", hook_id),
+    )
+}
+
+async fn list_tokens(Extension(state): Extension<StateManager>) -> Response {
+    listed("tokens", state.auth.list_tokens())
+}
+
+async fn create_token(
+    Extension(state): Extension<StateManager>,
+    Json(body): Json<Value>,
+) -> Response {
+    let Some(client_id) = body.get("client_id").and_then(Value::as_str) else {
+        return bad_request("client_id is required");
+    };
+    let expires_in = body
+        .get("expires_in")
+        .and_then(Value::as_u64)
+        .unwrap_or_else(|| state.auth.expires_in());
+    let defaults = json!({
+        "access_token": format!("mock_token_{}_{}", client_id, uuid::Uuid::new_v4().simple()),
+        "token_type": "Bearer",
+        "expires_in": expires_in,
+        "expires_at": state.clock.now_millis() as u64 / 1000 + expires_in,
+        "refresh_token": null,
+        "scope": null
+    });
+    match record::<TokenInfo>(defaults, body) {
+        Ok(token) => {
+            state.auth.insert_token(token.clone());
+            created(token)
+        }
+        Err(message) => bad_request(&message),
+    }
+}
+
+async fn delete_token(
+    Extension(state): Extension<StateManager>,
+    Path(access_token): Path<String>,
+) -> Response {
+    let known = state
+        .auth
+        .list_tokens()
+        .iter()
+        .any(|token| token.access_token == access_token);
+    if known {
+        state.auth.revoke_token(&access_token);
+    }
+    deleted(known, "Token not found")
+}
+
+async fn list_users(Extension(state): Extension<StateManager>) -> Response {
+    listed("users", state.users.list_users())
+}
+
+async fn create_user(
+    Extension(state): Extension<StateManager>,
+    Json(body): Json<Value>,
+) -> Response {
+    let Some(id) = body.get("id").and_then(Value::as_str) else {
+        return bad_request("id is required");
+    };
+    let defaults = json!({
+        "name": id,
+        "email": format!("{}@example.com", id)
+    });
+    match record::<UserInfo>(defaults, body) {
+        Ok(user) => {
+            state.users.insert_user(user.clone());
+            created(user)
+        }
+        Err(message) => bad_request(&message),
+    }
+}
+
+async fn delete_user(
+    Extension(state): Extension<StateManager>,
+    Path(user_id): Path<String>,
+) -> Response {
+    deleted(
+        state.users.delete_user(&user_id),
+        &format!("User {} not found", user_id),
+    )
+}
+
+async fn list_clients(Extension(state): Extension<StateManager>) -> Response {
+    listed("clients", state.clients.list_clients())
+}
+
+async fn create_client(
+    Extension(state): Extension<StateManager>,
+    Json(body): Json<Value>,
+) -> Response {
+    match record::<ClientCredentials>(json!({ "scopes": [] }), body) {
+        Ok(client) => {
+            state.clients.insert_client(client.clone());
+            created(client)
+        }
+        Err(message) => bad_request(&message),
+    }
+}
+
+async fn delete_client(
+    Extension(state): Extension<StateManager>,
+    Path(client_id): Path<String>,
+) -> Response {
+    deleted(
+        state.clients.delete_client(&client_id),
+        &format!("Client {} not found", client_id),
+    )
+}
+
+/// A record from `body` with `defaults` for the fields it leaves out
+fn record<T: DeserializeOwned>(defaults: Value, body: Value) -> Result<T, String> {
+    let (Value::Object(mut merged), Value::Object(body)) = (defaults, body) else {
+        return Err("Expected a JSON object".to_string());
+    };
+    merged.extend(body);
+    serde_json::from_value(Value::Object(merged)).map_err(|e| format!("Invalid record: {}", e))
+}
+
+fn listed<T: serde::Serialize>(kind: &str, records: Vec<T>) -> Response {
+    (StatusCode::OK, JsonResponse(json!({ kind: records }))).into_response()
+}
+
+fn created<T: serde::Serialize>(record: T) -> Response {
+    (StatusCode::CREATED, JsonResponse(json!(record))).into_response()
+}
+
+fn deleted(found: bool, message: &str) -> Response {
+    if found {
+        StatusCode::NO_CONTENT.into_response()
+    } else {
+        not_found(message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::{Body, to_bytes};
+    use axum::http::Request;
+    use tower::ServiceExt;
+
+    async fn send(
+        app: &Router,
+        method: &str,
+        uri: &str,
+        body: Option<Value>,
+    ) -> (StatusCode, Value) {
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("content-type", "application/json")
+            .body(body.map_or_else(Body::empty, |b| Body::from(b.to_string())))
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (
+            status,
+            serde_json::from_slice(&bytes).unwrap_or(Value::Null),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_records_are_created_listed_and_deleted_directly() {
+        let state = StateManager::new();
+        let app = router(Some(state.clone()));
+
+        // Not a valid OSS bucket key, but the admin API does not care
+        let (status, bucket) = send(
+            &app,
+            "POST",
+            "/buckets",
+            Some(json!({ "bucket_key": "Not_Valid!" })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(bucket["bucket_owner"], "mock-owner");
+        assert!(state.buckets.get_bucket("Not_Valid!").is_some());
+
+        // Objects go into buckets that do not exist
+        let (status, _) = send(
+            &app,
+            "POST",
+            "/buckets/ghost/objects",
+            Some(json!({ "object_key": "model.rvt", "size": 42 })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+        let (_, objects) = send(&app, "GET", "/buckets/ghost/objects", None).await;
+        assert_eq!(objects["objects"][0]["size"], 42);
+        assert_eq!(
+            objects["objects"][0]["object_id"],
+            "urn:adsk.objects:os.object:ghost/model.rvt"
+        );
+
+        // A job that finished long ago
+        let (status, job) = send(
+            &app,
+            "POST",
+            "/jobs",
+            Some(json!({
+                "kind": "translation",
+                "key": "urn",
+                "createdAt": 0,
+                "outcome": { "result": "failed", "message": "boom" }
+            })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+        let job_id = job["id"].as_str().unwrap();
+        let status = state.jobs.status(job_id).unwrap();
+        assert_eq!(status.message.as_deref(), Some("boom"));
+
+        let (status, token) = send(
+            &app,
+            "POST",
+            "/tokens",
+            Some(json!({ "client_id": "ci", "access_token": "fixed", "scope": "data:read" })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(token["token_type"], "Bearer");
+        assert!(state.auth.validate_token("fixed"));
+
+        assert_eq!(
+            send(&app, "DELETE", "/tokens/fixed", None).await.0,
+            StatusCode::NO_CONTENT
+        );
+        assert!(!state.auth.validate_token("fixed"));
+        assert_eq!(
+            send(&app, "DELETE", "/buckets/ghost/objects/model.rvt", None)
+                .await
+                .0,
+            StatusCode::NO_CONTENT
+        );
+        assert_eq!(
+            send(&app, "DELETE", &format!("/jobs/{}", job_id), None)
+                .await
+                .0,
+            StatusCode::NO_CONTENT
+        );
+        assert_eq!(
+            send(&app, "DELETE", "/hubs/missing", None).await.0,
+            StatusCode::NOT_FOUND
+        );
+
+        let (status, error) = send(&app, "POST", "/hooks", Some(json!({ "tenant": "t" }))).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(error["message"].as_str().unwrap().contains("callback_url"));
+
+        let stateless = router(None);
+        assert_eq!(
+            send(&stateless, "GET", "/buckets", None).await.0,
+            StatusCode::NOT_FOUND
+        );
+    }
+}
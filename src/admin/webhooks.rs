@@ -0,0 +1,239 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+//! Webhook delivery control, served under `/__admin/webhooks`.
+//!
+//! Notifications to the hooks an app created are signed with the app's
+//! secret in an `x-adsk-signature` header. Secrets set here are the same
+//! ones the app would set through the Webhooks API; they survive resets.
+//!
+//! Deliveries the callback refuses or does not answer are retried with
+//! exponential backoff (`--webhook-attempts`, `--webhook-backoff-ms`); the
+//! notifications no attempt delivered are kept in a dead-letter list of the
+//! last 1,000.
+//!
+//! Events the mock does not produce by itself can be triggered: they go
+//! through the same delivery, to every active hook subscribed to the event
+//! whose scope covers it.
+//!
+//! | Path | Methods |
+//! |------|---------|
+//! | `/webhooks/secrets` | `GET` (client IDs of the apps with a secret) |
+//! | `/webhooks/secrets/{clientId}` | `PUT` (`{"token": secret}`), `DELETE` |
+//! | `/webhooks/deliveries` | `GET` (`?hookId=`), `DELETE` |
+//! | `/webhooks/trigger` | `POST` (`{"system", "event", "resourceUrn", "payload"}`) |
+
+use super::{bad_request, not_found, state_unavailable};
+use crate::state::StateManager;
+use crate::webhooks::delivery::{WebhookEvent, deliver, recipients};
+use axum::{
+    Extension, Router,
+    extract::{Json, Path, Query},
+    http::StatusCode,
+    response::{IntoResponse, Json as JsonResponse, Response},
+    routing::{get, post, put},
+};
+use serde::Deserialize;
+use serde_json::{Map, Value, json};
+
+/// Build the webhooks router (nested under `/webhooks`)
+pub fn router(state: Option<StateManager>) -> Router {
+    let Some(state) = state else {
+        return Router::new().fallback(|| async { state_unavailable() });
+    };
+
+    Router::new()
+        .route(
+            "/deliveries",
+            get(failed_deliveries).delete(clear_failed_deliveries),
+        )
+        .route("/trigger", post(trigger_event))
+        .route("/secrets", get(list_secrets))
+        .route("/secrets/:client_id", put(set_secret).delete(remove_secret))
+        .layer(Extension(state))
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DeliveriesQuery {
+    hook_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TriggerRequest {
+    system: String,
+    event: String,
+    /// Defaults to the payload's `source`, `URN` or `id`
+    resource_urn: Option<String>,
+    /// Fields overriding the event's default payload
+    #[serde(default)]
+    payload: Map<String, Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SecretRequest {
+    token: String,
+}
+
+async fn failed_deliveries(
+    Extension(state): Extension<StateManager>,
+    Query(query): Query<DeliveriesQuery>,
+) -> Response {
+    let deliveries = state.webhooks.dead_letters(query.hook_id.as_deref());
+    (
+        StatusCode::OK,
+        JsonResponse(json!({ "deliveries": deliveries })),
+    )
+        .into_response()
+}
+
+async fn clear_failed_deliveries(Extension(state): Extension<StateManager>) -> Response {
+    state.webhooks.clear_dead_letters();
+    StatusCode::NO_CONTENT.into_response()
+}
+
+/// Deliver an event to the hooks subscribed to it, answering with their IDs.
+/// The payload's `parentFolderUrn` and `projectId` are matched against the
+/// hooks' scope.
+async fn trigger_event(
+    Extension(state): Extension<StateManager>,
+    Json(request): Json<TriggerRequest>,
+) -> Response {
+    if request.system.is_empty() || request.event.is_empty() {
+        return bad_request("system and event are required");
+    }
+    let field = |name: &str| {
+        request
+            .payload
+            .get(name)
+            .and_then(Value::as_str)
+            .map(str::to_string)
+    };
+    let resource_urn = request
+        .resource_urn
+        .clone()
+        .or_else(|| field("source"))
+        .or_else(|| field("URN"))
+        .or_else(|| field("id"))
+        .unwrap_or_else(|| format!("urn:adsk.mock:event:{}", uuid::Uuid::new_v4()));
+    let event = WebhookEvent {
+        folder: field("parentFolderUrn"),
+        project: field("projectId"),
+        system: request.system,
+        event: request.event,
+        resource_urn,
+        details: request.payload,
+    };
+    let hooks: Vec<String> = recipients(&state.webhooks, &event)
+        .into_iter()
+        .map(|hook| hook.hook_id)
+        .collect();
+    deliver(&state.webhooks, &event);
+    (
+        StatusCode::ACCEPTED,
+        JsonResponse(json!({ "resourceUrn": event.resource_urn, "hooks": hooks })),
+    )
+        .into_response()
+}
+
+async fn list_secrets(Extension(state): Extension<StateManager>) -> Response {
+    (
+        StatusCode::OK,
+        JsonResponse(json!({ "clients": state.webhooks.secret_clients() })),
+    )
+        .into_response()
+}
+
+async fn set_secret(
+    Extension(state): Extension<StateManager>,
+    Path(client_id): Path<String>,
+    Json(request): Json<SecretRequest>,
+) -> Response {
+    if request.token.is_empty() {
+        return bad_request("token must not be empty");
+    }
+    state.webhooks.set_secret(&client_id, request.token);
+    StatusCode::NO_CONTENT.into_response()
+}
+
+async fn remove_secret(
+    Extension(state): Extension<StateManager>,
+    Path(client_id): Path<String>,
+) -> Response {
+    if state.webhooks.remove_secret(&client_id) {
+        StatusCode::NO_CONTENT.into_response()
+    } else {
+        not_found(&format!("App {} has no secret", client_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::webhooks::{RetryPolicy, WebhookScope};
+    use axum::body::{Body, to_bytes};
+    use axum::http::Request;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_triggered_events_reach_the_delivery_engine() {
+        let state = StateManager::new();
+        state.webhooks.set_retry_policy(RetryPolicy {
+            attempts: 1,
+            backoff_ms: 0,
+        });
+        let subscribe = |folder: &str| {
+            state.webhooks.create_subscription(
+                "data".into(),
+                "dm.lineage.reserved".into(),
+                "data".into(),
+                "http://127.0.0.1:9/callback".into(),
+                WebhookScope {
+                    folder: Some(folder.into()),
+                    ..WebhookScope::default()
+                },
+                None,
+            )
+        };
+        let hook = subscribe("urn:folder:a");
+        subscribe("urn:folder:b");
+
+        let request = Request::post("/trigger")
+            .header("content-type", "application/json")
+            .body(Body::from(
+                json!({
+                    "system": "data",
+                    "event": "dm.lineage.reserved",
+                    "payload": {
+                        "source": "urn:adsk.wipprod:fs.file:vf.abc?version=1",
+                        "parentFolderUrn": "urn:folder:a"
+                    }
+                })
+                .to_string(),
+            ))
+            .unwrap();
+        let response = router(Some(state.clone())).oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+        let body: Value =
+            serde_json::from_slice(&to_bytes(response.into_body(), usize::MAX).await.unwrap())
+                .unwrap();
+        assert_eq!(body["hooks"], json!([hook.hook_id]));
+
+        // The unreachable callback dead-letters the notification
+        let mut dead = Vec::new();
+        for _ in 0..100 {
+            dead = state.webhooks.dead_letters(None);
+            if !dead.is_empty() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        assert_eq!(dead.len(), 1);
+        assert_eq!(dead[0].hook_id, hook.hook_id);
+        assert_eq!(
+            dead[0].notification["resourceUrn"],
+            "urn:adsk.wipprod:fs.file:vf.abc?version=1"
+        );
+    }
+}
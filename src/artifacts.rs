@@ -0,0 +1,283 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+//! Generated artifacts: the CSV and ZIP files Data Connector extracts and
+//! Data Management downloads produce, and Model Derivative thumbnails.
+//!
+//! Files are generated when fetched, from the records stored at that time,
+//! so parsers downstream get small but valid files instead of empty bodies.
+//! A CSV's columns are the scalar properties of the spec schema describing
+//! its records, when that spec is loaded. ZIP entries are stored
+//! uncompressed. Thumbnails are PNGs of a solid color picked by the design's
+//! URN, with that URN written across them.
+
+use crate::openapi::schemas::SchemaRegistry;
+use flate2::Crc;
+use flate2::write::ZlibEncoder;
+use serde_json::Value;
+use std::io::{Cursor, Write};
+use zip::write::SimpleFileOptions;
+
+/// A CSV file of records of one kind
+pub struct Table<'a> {
+    /// Service and name of the component schema describing the records
+    pub schema: (&'a str, &'a str),
+    /// Columns used when the schema's spec is not loaded
+    pub fallback_columns: &'a [&'a str],
+}
+
+impl Table<'_> {
+    /// Column names: the scalar properties of the schema, in name order
+    pub fn columns(&self, schemas: &SchemaRegistry) -> Vec<String> {
+        let (service, name) = self.schema;
+        let properties = schemas
+            .json_schema(service, name)
+            .and_then(|schema| schema.get("properties").and_then(Value::as_object).cloned());
+        match properties {
+            Some(properties) => properties
+                .iter()
+                .filter(|(_, property)| is_scalar(property))
+                .map(|(name, _)| name.clone())
+                .collect(),
+            None => self
+                .fallback_columns
+                .iter()
+                .map(|column| column.to_string())
+                .collect(),
+        }
+    }
+
+    /// The CSV file of `records`: a header row, then one row per record
+    /// with the values of its properties named like the columns
+    pub fn csv(&self, schemas: &SchemaRegistry, records: &[Value]) -> csv::Result<Vec<u8>> {
+        let columns = self.columns(schemas);
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        writer.write_record(&columns)?;
+        for record in records {
+            writer.write_record(columns.iter().map(|column| cell(record.get(column))))?;
+        }
+        writer
+            .into_inner()
+            .map_err(|e| csv::Error::from(e.into_error()))
+    }
+}
+
+fn is_scalar(property: &Value) -> bool {
+    !matches!(
+        property.get("type").and_then(Value::as_str),
+        Some("array" | "object")
+    )
+}
+
+fn cell(value: Option<&Value>) -> String {
+    match value {
+        None | Some(Value::Null) => String::new(),
+        Some(Value::String(s)) => s.clone(),
+        Some(other) => other.to_string(),
+    }
+}
+
+/// A ZIP archive of `(name, contents)` files
+pub fn zip(files: &[(String, Vec<u8>)]) -> zip::result::ZipResult<Vec<u8>> {
+    let mut archive = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    let options = SimpleFileOptions::default().compression_method(zip::CompressionMethod::Stored);
+    for (name, contents) in files {
+        archive.start_file(name.as_str(), options)?;
+        archive.write_all(contents)?;
+    }
+    Ok(archive.finish()?.into_inner())
+}
+
+/// Glyphs of the thumbnail font: 5 rows of 3 pixels, the top row first and
+/// the left pixel in the high bit. Letters are drawn upper case; characters
+/// without a glyph are left blank.
+const GLYPHS: &[(char, [u8; 5])] = &[
+    ('A', [0b010, 0b101, 0b111, 0b101, 0b101]),
+    ('B', [0b110, 0b101, 0b110, 0b101, 0b110]),
+    ('C', [0b011, 0b100, 0b100, 0b100, 0b011]),
+    ('D', [0b110, 0b101, 0b101, 0b101, 0b110]),
+    ('E', [0b111, 0b100, 0b110, 0b100, 0b111]),
+    ('F', [0b111, 0b100, 0b110, 0b100, 0b100]),
+    ('G', [0b011, 0b100, 0b101, 0b101, 0b011]),
+    ('H', [0b101, 0b101, 0b111, 0b101, 0b101]),
+    ('I', [0b111, 0b010, 0b010, 0b010, 0b111]),
+    ('J', [0b001, 0b001, 0b001, 0b101, 0b010]),
+    ('K', [0b101, 0b101, 0b110, 0b101, 0b101]),
+    ('L', [0b100, 0b100, 0b100, 0b100, 0b111]),
+    ('M', [0b101, 0b111, 0b111, 0b101, 0b101]),
+    ('N', [0b110, 0b101, 0b101, 0b101, 0b101]),
+    ('O', [0b010, 0b101, 0b101, 0b101, 0b010]),
+    ('P', [0b110, 0b101, 0b110, 0b100, 0b100]),
+    ('Q', [0b010, 0b101, 0b101, 0b110, 0b011]),
+    ('R', [0b110, 0b101, 0b110, 0b101, 0b101]),
+    ('S', [0b011, 0b100, 0b010, 0b001, 0b110]),
+    ('T', [0b111, 0b010, 0b010, 0b010, 0b010]),
+    ('U', [0b101, 0b101, 0b101, 0b101, 0b111]),
+    ('V', [0b101, 0b101, 0b101, 0b101, 0b010]),
+    ('W', [0b101, 0b101, 0b111, 0b111, 0b101]),
+    ('X', [0b101, 0b101, 0b010, 0b101, 0b101]),
+    ('Y', [0b101, 0b101, 0b010, 0b010, 0b010]),
+    ('Z', [0b111, 0b001, 0b010, 0b100, 0b111]),
+    ('0', [0b111, 0b101, 0b101, 0b101, 0b111]),
+    ('1', [0b010, 0b110, 0b010, 0b010, 0b111]),
+    ('2', [0b110, 0b001, 0b010, 0b100, 0b111]),
+    ('3', [0b110, 0b001, 0b010, 0b001, 0b110]),
+    ('4', [0b101, 0b101, 0b111, 0b001, 0b001]),
+    ('5', [0b111, 0b100, 0b110, 0b001, 0b110]),
+    ('6', [0b011, 0b100, 0b111, 0b101, 0b111]),
+    ('7', [0b111, 0b001, 0b010, 0b010, 0b010]),
+    ('8', [0b111, 0b101, 0b111, 0b101, 0b111]),
+    ('9', [0b111, 0b101, 0b111, 0b001, 0b110]),
+    ('.', [0b000, 0b000, 0b000, 0b000, 0b010]),
+    (':', [0b000, 0b010, 0b000, 0b010, 0b000]),
+    ('-', [0b000, 0b000, 0b111, 0b000, 0b000]),
+    ('_', [0b000, 0b000, 0b000, 0b000, 0b111]),
+    ('/', [0b001, 0b001, 0b010, 0b100, 0b100]),
+];
+
+/// Width of a glyph plus the gap after it, in font pixels
+const GLYPH_ADVANCE: usize = 4;
+
+/// Blank space kept around the text, in image pixels
+const TEXT_MARGIN: usize = 4;
+
+fn glyph(c: char) -> [u8; 5] {
+    let c = c.to_ascii_uppercase();
+    GLYPHS
+        .iter()
+        .find(|(glyph, _)| *glyph == c)
+        .map(|(_, rows)| *rows)
+        .unwrap_or_default()
+}
+
+/// A `width` x `height` PNG thumbnail for the design `urn`: a solid color
+/// derived from the URN with the URN written in the middle, as large as
+/// fits. A URN too long for the image keeps its end, the file name.
+pub fn thumbnail(urn: &str, width: u32, height: u32) -> std::io::Result<Vec<u8>> {
+    let (w, h) = (width as usize, height as usize);
+    let hash = xxhash_rust::xxh3::xxh3_64(urn.as_bytes()).to_be_bytes();
+    let background = [hash[0], hash[1], hash[2]];
+    let luminance = 299 * u32::from(background[0])
+        + 587 * u32::from(background[1])
+        + 114 * u32::from(background[2]);
+    let ink = if luminance > 128_000 {
+        [0, 0, 0]
+    } else {
+        [255, 255, 255]
+    };
+
+    let mut pixels: Vec<[u8; 3]> = vec![background; w * h];
+    let room = w.saturating_sub(2 * TEXT_MARGIN);
+    let fits = (room + 1) / GLYPH_ADVANCE;
+    let chars: Vec<char> = urn.chars().collect();
+    let text = &chars[chars.len().saturating_sub(fits)..];
+    if !text.is_empty() {
+        let text_width = text.len() * GLYPH_ADVANCE - 1;
+        let scale = (room / text_width)
+            .min(h.saturating_sub(2 * TEXT_MARGIN) / 5)
+            .max(1);
+        let left = w.saturating_sub(text_width * scale) / 2;
+        let top = h.saturating_sub(5 * scale) / 2;
+        for (index, c) in text.iter().enumerate() {
+            for (row, bits) in glyph(*c).iter().enumerate() {
+                for column in 0..3 {
+                    if bits & (0b100 >> column) == 0 {
+                        continue;
+                    }
+                    let x0 = left + (index * GLYPH_ADVANCE + column) * scale;
+                    let y0 = top + row * scale;
+                    for y in y0..(y0 + scale).min(h) {
+                        for x in x0..(x0 + scale).min(w) {
+                            pixels[y * w + x] = ink;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    // Scanlines, each behind filter type 0 (none)
+    let mut encoder = ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+    for row in pixels.chunks(w.max(1)) {
+        encoder.write_all(&[0])?;
+        for pixel in row {
+            encoder.write_all(pixel)?;
+        }
+    }
+    let data = encoder.finish()?;
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&width.to_be_bytes());
+    header.extend_from_slice(&height.to_be_bytes());
+    // 8-bit RGB, default compression and filtering, no interlacing
+    header.extend_from_slice(&[8, 2, 0, 0, 0]);
+
+    let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+    for (kind, contents) in [(b"IHDR", header), (b"IDAT", data), (b"IEND", Vec::new())] {
+        png.extend_from_slice(&(contents.len() as u32).to_be_bytes());
+        png.extend_from_slice(kind);
+        png.extend_from_slice(&contents);
+        let mut crc = Crc::new();
+        crc.update(kind);
+        crc.update(&contents);
+        png.extend_from_slice(&crc.sum().to_be_bytes());
+    }
+    Ok(png)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn test_csv_columns_fall_back_without_the_spec() {
+        let table = Table {
+            schema: ("issues", "Issue"),
+            fallback_columns: &["id", "title", "status"],
+        };
+        let records = [
+            serde_json::json!({ "id": "1", "title": "Leak, east wing", "status": "open" }),
+            serde_json::json!({ "id": "2", "status": "closed", "extra": true }),
+        ];
+        let csv = table.csv(&SchemaRegistry::default(), &records).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "id,title,status\n1,\"Leak, east wing\",open\n2,,closed\n"
+        );
+
+        let archive = zip(&[("issues.csv".into(), b"id\n1\n".to_vec())]).unwrap();
+        let mut archive = zip::ZipArchive::new(Cursor::new(archive)).unwrap();
+        let mut contents = String::new();
+        archive
+            .by_name("issues.csv")
+            .unwrap()
+            .read_to_string(&mut contents)
+            .unwrap();
+        assert_eq!(contents, "id\n1\n");
+    }
+
+    #[test]
+    fn test_thumbnails_are_pngs_of_the_requested_size() {
+        let png = thumbnail("urn:adsk.objects:os.object:bucket/tower.rvt", 200, 100).unwrap();
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+        assert_eq!(&png[12..16], b"IHDR");
+        assert_eq!(u32::from_be_bytes(png[16..20].try_into().unwrap()), 200);
+        assert_eq!(u32::from_be_bytes(png[20..24].try_into().unwrap()), 100);
+
+        // One filter byte and 200 RGB pixels per row, in two colors
+        let length = u32::from_be_bytes(png[33..37].try_into().unwrap()) as usize;
+        assert_eq!(&png[37..41], b"IDAT");
+        let mut pixels = Vec::new();
+        flate2::read::ZlibDecoder::new(&png[41..41 + length])
+            .read_to_end(&mut pixels)
+            .unwrap();
+        assert_eq!(pixels.len(), 100 * (1 + 200 * 3));
+        let colors: std::collections::HashSet<&[u8]> = pixels
+            .chunks(1 + 200 * 3)
+            .flat_map(|row| row[1..].chunks(3))
+            .collect();
+        assert_eq!(colors.len(), 2);
+        assert!(png.ends_with(&[0xAE, 0x42, 0x60, 0x82]));
+    }
+}
@@ -1,8 +1,17 @@
 // SPDX-License-Identifier: Apache-2.0
 // Copyright 2024-2025 Dmytro Yemelianov
 
+use crate::openapi::filter::{SpecFilter, SpecGlob};
+use crate::pattern::RoutePattern;
+use crate::state::clients::ClientCredentials;
+use crate::state::translations::TranslationRule;
+use crate::state::users::UserInfo;
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+pub mod file;
 
 /// Mock server operation mode
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
@@ -29,32 +38,738 @@ impl std::str::FromStr for MockMode {
     }
 }
 
+/// How `--replay` matches requests to recordings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReplayMatch {
+    /// Method, path and query must match a recording
+    Exact,
+    /// Fall back to the recording of the same method and path with the
+    /// most query parameters in common
+    #[default]
+    Fuzzy,
+}
+
+impl std::str::FromStr for ReplayMatch {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "exact" => Ok(ReplayMatch::Exact),
+            "fuzzy" => Ok(ReplayMatch::Fuzzy),
+            _ => Err(format!(
+                "Invalid replay matching: {}. Use 'exact' or 'fuzzy'",
+                s
+            )),
+        }
+    }
+}
+
+/// APS service families whose operation mode can be set individually
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Service {
+    Authentication,
+    Oss,
+    DataManagement,
+    ModelDerivative,
+    Issues,
+    AccountAdmin,
+    Webhooks,
+    DataConnector,
+}
+
+impl Service {
+    pub const ALL: [Service; 8] = [
+        Service::Authentication,
+        Service::Oss,
+        Service::DataManagement,
+        Service::ModelDerivative,
+        Service::Issues,
+        Service::AccountAdmin,
+        Service::Webhooks,
+        Service::DataConnector,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Service::Authentication => "authentication",
+            Service::Oss => "oss",
+            Service::DataManagement => "data-management",
+            Service::ModelDerivative => "model-derivative",
+            Service::Issues => "issues",
+            Service::AccountAdmin => "account-admin",
+            Service::Webhooks => "webhooks",
+            Service::DataConnector => "data-connector",
+        }
+    }
+
+    /// Service a request path belongs to, by its leading segments
+    pub fn for_path(path: &str) -> Option<Service> {
+        let segments: Vec<&str> = path.trim_start_matches('/').splitn(3, '/').collect();
+        match segments.as_slice() {
+            ["authentication", ..] | ["userinfo"] | ["userprofile", ..] => {
+                Some(Service::Authentication)
+            }
+            // The signed-URL upload target stands in for OSS's S3 backend
+            ["oss", ..] | ["__mock", "s3", ..] => Some(Service::Oss),
+            ["project", ..] | ["data", ..] | ["__mock", "downloads", ..] => {
+                Some(Service::DataManagement)
+            }
+            ["modelderivative", ..] | ["__mock", "derivatives", ..] => {
+                Some(Service::ModelDerivative)
+            }
+            ["construction", "issues", ..] => Some(Service::Issues),
+            ["construction", "admin", ..] | ["hq", ..] => Some(Service::AccountAdmin),
+            ["webhooks", ..] => Some(Service::Webhooks),
+            ["data-connector", ..] | ["__mock", "data-connector", ..] => {
+                Some(Service::DataConnector)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for Service {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for Service {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let s = s.to_lowercase();
+        match s.as_str() {
+            "auth" => Ok(Service::Authentication),
+            "dm" => Ok(Service::DataManagement),
+            "md" => Ok(Service::ModelDerivative),
+            "admin" => Ok(Service::AccountAdmin),
+            "dc" => Ok(Service::DataConnector),
+            _ => Service::ALL
+                .into_iter()
+                .find(|service| service.as_str() == s)
+                .ok_or_else(|| {
+                    let names: Vec<&str> = Service::ALL.iter().map(|s| s.as_str()).collect();
+                    format!("Invalid service: {}. Use one of: {}", s, names.join(", "))
+                }),
+        }
+    }
+}
+
+/// Parse a `service=mode` override, e.g. `oss=stateful`
+pub fn parse_service_mode(s: &str) -> std::result::Result<(Service, MockMode), String> {
+    let (service, mode) = s
+        .split_once('=')
+        .ok_or_else(|| format!("Invalid service mode '{}': expected 'service=mode'", s))?;
+    Ok((service.trim().parse()?, mode.trim().parse()?))
+}
+
+/// Store the state is loaded from on startup and saved to on shutdown
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StateBackendSpec {
+    /// One JSON document (`file:PATH`, or `--state-file PATH`)
+    File(PathBuf),
+    /// A SQLite database with a table per record kind (`sqlite:PATH`)
+    Sqlite(PathBuf),
+    /// A Redis server several mock servers share (`redis://HOST[:PORT][/DB]`)
+    Redis(String),
+}
+
+impl StateBackendSpec {
+    /// Local file the state is kept in
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            StateBackendSpec::File(path) | StateBackendSpec::Sqlite(path) => Some(path),
+            StateBackendSpec::Redis(_) => None,
+        }
+    }
+
+    /// The same backend with its file, if any, at another path
+    pub fn with_path(self, path: PathBuf) -> Self {
+        match self {
+            StateBackendSpec::File(_) => StateBackendSpec::File(path),
+            StateBackendSpec::Sqlite(_) => StateBackendSpec::Sqlite(path),
+            StateBackendSpec::Redis(url) => StateBackendSpec::Redis(url),
+        }
+    }
+}
+
+impl std::str::FromStr for StateBackendSpec {
+    type Err = String;
+
+    /// Parse `file:PATH`, `sqlite:PATH` or a `redis://` / `rediss://` URL
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "Invalid state backend: {}. Use 'file:PATH', 'sqlite:PATH' or 'redis://HOST'",
+                s
+            )
+        };
+        let (kind, path) = s.split_once(':').ok_or_else(invalid)?;
+        if path.is_empty() {
+            return Err(invalid());
+        }
+        match kind.to_lowercase().as_str() {
+            "file" => Ok(StateBackendSpec::File(path.into())),
+            "sqlite" => Ok(StateBackendSpec::Sqlite(path.into())),
+            "redis" | "rediss" if path.starts_with("//") => {
+                Ok(StateBackendSpec::Redis(s.to_string()))
+            }
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// Where uploaded and generated content is kept
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum BlobStoreSpec {
+    /// In memory (`memory`)
+    #[default]
+    Memory,
+    /// One file per blob in a directory (`disk:DIR`)
+    Disk(PathBuf),
+}
+
+impl std::str::FromStr for BlobStoreSpec {
+    type Err = String;
+
+    /// Parse `memory` or `disk:DIR`
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("memory") {
+            return Ok(BlobStoreSpec::Memory);
+        }
+        match s.split_once(':') {
+            Some((kind, dir)) if kind.eq_ignore_ascii_case("disk") && !dir.is_empty() => {
+                Ok(BlobStoreSpec::Disk(dir.into()))
+            }
+            _ => Err(format!(
+                "Invalid blob store: {}. Use 'memory' or 'disk:DIR'",
+                s
+            )),
+        }
+    }
+}
+
+/// Header added to every response, or only to responses for matching routes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResponseHeaderRule {
+    /// Routes the header applies to; `None` applies it globally
+    pub route: Option<RoutePattern>,
+    pub name: String,
+    pub value: String,
+}
+
+impl std::str::FromStr for ResponseHeaderRule {
+    type Err = String;
+
+    /// Parse `Name: value`, or `[METHOD] /route/pattern=Name: value`
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        // A route prefix is a path or tag, optionally preceded by a method;
+        // anything else before a '=' belongs to the header value
+        let (route, header) = match s.split_once('=') {
+            Some((route, header)) if crate::pattern::looks_like_pattern(route) => {
+                (Some(route.parse::<RoutePattern>()?), header)
+            }
+            _ => (None, s),
+        };
+        let (name, value) = header
+            .split_once(':')
+            .ok_or_else(|| format!("Invalid response header '{}': expected 'Name: value'", s))?;
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(format!("Invalid response header '{}': empty name", s));
+        }
+        Ok(Self {
+            route,
+            name: name.to_string(),
+            value: value.trim().to_string(),
+        })
+    }
+}
+
+/// Cap on the requests routes matching a pattern serve at once
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConcurrencyLimit {
+    pub route: RoutePattern,
+    pub max_in_flight: usize,
+    /// Hold excess requests until a slot frees instead of answering 429
+    pub queue: bool,
+}
+
+impl std::str::FromStr for ConcurrencyLimit {
+    type Err = String;
+
+    /// Parse `[METHOD] /route/pattern=MAX`, or `...=MAX:queue`
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "Invalid concurrency limit '{}': expected '[METHOD] /route/pattern=MAX[:queue]'",
+                s
+            )
+        };
+        let (route, limit) = s.rsplit_once('=').ok_or_else(invalid)?;
+        let (max, queue) = match limit.trim().split_once(':') {
+            Some((max, "queue")) => (max, true),
+            Some(_) => return Err(invalid()),
+            None => (limit.trim(), false),
+        };
+        let max_in_flight: usize = max.trim().parse().map_err(|_| invalid())?;
+        if max_in_flight == 0 {
+            return Err(format!(
+                "Invalid concurrency limit '{}': the maximum must be at least 1",
+                s
+            ));
+        }
+        Ok(Self {
+            route: route.parse()?,
+            max_in_flight,
+            queue,
+        })
+    }
+}
+
+/// Requests each caller may make to routes matching a pattern per period
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RateLimit {
+    pub route: RoutePattern,
+    pub requests: u32,
+    pub period: Duration,
+}
+
+impl std::str::FromStr for RateLimit {
+    type Err = String;
+
+    /// Parse `[METHOD] /route/pattern=COUNT/UNIT`, the unit being `sec`,
+    /// `min` or `hour`
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "Invalid rate limit '{}': expected '[METHOD] /route/pattern=COUNT/sec|min|hour'",
+                s
+            )
+        };
+        let (route, limit) = s.rsplit_once('=').ok_or_else(invalid)?;
+        let (count, unit) = limit.trim().split_once('/').ok_or_else(invalid)?;
+        let requests: u32 = count.trim().parse().map_err(|_| invalid())?;
+        if requests == 0 {
+            return Err(format!(
+                "Invalid rate limit '{}': the count must be at least 1",
+                s
+            ));
+        }
+        let period = match unit.trim() {
+            "s" | "sec" | "second" => Duration::from_secs(1),
+            "m" | "min" | "minute" => Duration::from_secs(60),
+            "h" | "hour" => Duration::from_secs(60 * 60),
+            _ => return Err(invalid()),
+        };
+        Ok(Self {
+            route: route.parse()?,
+            requests,
+            period,
+        })
+    }
+}
+
+/// Upper bound for an injected delay, so a long tail cannot hang a test run
+pub const MAX_LATENCY: Duration = Duration::from_secs(5 * 60);
+
+/// Distribution of the artificial latency added to responses
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Latency {
+    /// Always this many milliseconds (`MS`)
+    Fixed(u64),
+    /// Anywhere between two bounds, in milliseconds (`MIN..MAX`)
+    Uniform { min: u64, max: u64 },
+    /// Normally distributed around a mean, in milliseconds
+    /// (`normal:MEAN,STDDEV`); never below zero
+    Normal { mean: f64, std_dev: f64 },
+}
+
+impl Latency {
+    /// Draw a delay from the distribution, at most [`MAX_LATENCY`]
+    pub fn sample(&self, rng: &mut impl rand::Rng) -> Duration {
+        let ms = match *self {
+            Latency::Fixed(ms) => ms as f64,
+            Latency::Uniform { min, max } => rng.gen_range(min..=max) as f64,
+            Latency::Normal { mean, std_dev } => {
+                // Box-Muller transform
+                let u1: f64 = 1.0 - rng.r#gen::<f64>();
+                let u2: f64 = rng.r#gen();
+                let z = (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos();
+                (mean + std_dev * z).max(0.0)
+            }
+        };
+        Duration::from_secs_f64((ms / 1000.0).min(MAX_LATENCY.as_secs_f64()))
+    }
+}
+
+impl std::str::FromStr for Latency {
+    type Err = String;
+
+    /// Parse `MS`, `MIN..MAX` or `normal:MEAN,STDDEV`
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "Invalid latency '{}': expected 'MS', 'MIN..MAX' or 'normal:MEAN,STDDEV'",
+                s
+            )
+        };
+        let s = s.trim();
+        if let Some(parameters) = s.strip_prefix("normal:") {
+            let (mean, std_dev) = parameters.split_once(',').ok_or_else(invalid)?;
+            let mean: f64 = mean.trim().parse().map_err(|_| invalid())?;
+            let std_dev: f64 = std_dev.trim().parse().map_err(|_| invalid())?;
+            if !(mean.is_finite() && std_dev.is_finite() && mean >= 0.0 && std_dev >= 0.0) {
+                return Err(invalid());
+            }
+            return Ok(Latency::Normal { mean, std_dev });
+        }
+        if let Some((min, max)) = s.split_once("..") {
+            let min: u64 = min.trim().parse().map_err(|_| invalid())?;
+            let max: u64 = max.trim().parse().map_err(|_| invalid())?;
+            if min > max {
+                return Err(format!(
+                    "Invalid latency '{}': the minimum exceeds the maximum",
+                    s
+                ));
+            }
+            return Ok(Latency::Uniform { min, max });
+        }
+        s.parse().map(Latency::Fixed).map_err(|_| invalid())
+    }
+}
+
+/// Latency added to every response, or only to responses for matching routes
+#[derive(Debug, Clone, PartialEq)]
+pub struct LatencyRule {
+    /// Routes the latency applies to; `None` applies it to every route no
+    /// other rule matches
+    pub route: Option<RoutePattern>,
+    pub latency: Latency,
+    source: String,
+}
+
+impl std::str::FromStr for LatencyRule {
+    type Err = String;
+
+    /// Parse `LATENCY`, or `[METHOD] /route/pattern=LATENCY`
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let (route, latency) = match s.rsplit_once('=') {
+            Some((route, latency)) => (Some(route.parse::<RoutePattern>()?), latency),
+            None => (None, s),
+        };
+        Ok(Self {
+            route,
+            latency: latency.parse()?,
+            source: s.trim().to_string(),
+        })
+    }
+}
+
+impl std::fmt::Display for LatencyRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
 /// Configuration for the mock server
 #[derive(Debug, Clone)]
 pub struct MockServerConfig {
     /// Server operation mode
     pub mode: MockMode,
+    /// Per-service overrides of `mode`
+    pub service_modes: HashMap<Service, MockMode>,
     /// Path to OpenAPI specifications directory
     pub openapi_dir: PathBuf,
-    /// Optional path to state persistence file
+    /// Further OpenAPI directories loaded after `openapi_dir`
+    pub extra_openapi_dirs: Vec<PathBuf>,
+    /// Only load spec files matching one of these globs (all when empty)
+    pub spec_include: Vec<SpecGlob>,
+    /// Skip spec files matching any of these globs
+    pub spec_exclude: Vec<SpecGlob>,
+    /// Optional directory parsed specs are cached in across startups
+    pub spec_cache_dir: Option<PathBuf>,
+    /// Optional JSON file the state is loaded from on startup and saved to
+    /// on shutdown
     pub state_file: Option<PathBuf>,
+    /// Optional store the state is kept in; takes precedence over
+    /// `state_file`
+    pub state_backend: Option<StateBackendSpec>,
+    /// Also save the state to its store at this interval, in seconds
+    pub state_autosave_secs: Option<u64>,
+    /// Where object contents and other blobs are kept
+    pub blob_store: BlobStoreSpec,
+    /// Evict the least recently used blobs once they exceed this many bytes
+    pub blob_store_max_bytes: Option<u64>,
     /// Enable verbose logging
     pub verbose: bool,
     /// Server host
     pub host: String,
     /// Server port
     pub port: u16,
+    /// Seed for synthesized response data; makes generated IDs, dates and
+    /// strings reproducible across runs
+    pub seed: Option<u64>,
+    /// Validate request parameters and bodies against the OpenAPI operation
+    /// definitions and reject invalid requests with a 400
+    pub validate_requests: bool,
+    /// Seconds the clock token expiry is checked against runs ahead of the
+    /// one tokens are issued with (negative: behind)
+    pub clock_skew_secs: i64,
+    /// Number of state mutations the audit log keeps; 0 disables it
+    pub audit_log: usize,
+    /// Lifetime of OSS signed upload sessions, in seconds
+    pub upload_expiration_secs: u64,
+    /// Interval between sweeps that drop expired upload sessions, in seconds
+    pub upload_gc_interval_secs: u64,
+    /// Duration and outcome of the translations of matching URNs
+    pub translation_rules: Vec<TranslationRule>,
+    /// Size of the placeholder files Model Derivative derivative downloads
+    /// serve, in bytes
+    pub derivative_size: u64,
+    /// Users 3-legged tokens can act for, besides the default one
+    pub users: Vec<UserInfo>,
+    /// Applications allowed to request tokens; any client ID gets one when
+    /// empty
+    pub clients: Vec<ClientCredentials>,
+    /// Lifetime of the tokens the token endpoint issues, in seconds
+    pub token_expires_in: u64,
+    /// Webhook deliveries tried before a notification is dead-lettered, the
+    /// first one included
+    pub webhook_attempts: u32,
+    /// Wait before retrying a refused webhook delivery, in milliseconds;
+    /// doubled for each further retry
+    pub webhook_backoff_ms: u64,
+    /// Optional stubs directory (`mappings/` plus `__files/` for body files)
+    pub stubs_dir: Option<PathBuf>,
+    /// Pact contract files whose interactions are served as stubs
+    pub pact_files: Vec<PathBuf>,
+    /// HAR captures whose entries are served as stubs
+    pub har_files: Vec<PathBuf>,
+    /// Postman collections whose saved examples are served as stubs
+    pub postman_files: Vec<PathBuf>,
+    /// Derive generated IDs, timestamps and synthesized data from a hash of
+    /// the request, so identical requests get byte-identical responses
+    pub deterministic: bool,
+    /// Headers injected into responses, e.g. gateway-added `x-ads-region`
+    pub response_headers: Vec<ResponseHeaderRule>,
+    /// Honour `X-Mock-Delay-Ms`, `X-Mock-Status` and `X-Mock-Scenario`
+    /// request headers
+    pub control_headers: bool,
+    /// Caps on the requests matching routes serve at once
+    pub concurrency_limits: Vec<ConcurrencyLimit>,
+    /// Request budgets per caller of the routes matching a pattern
+    pub rate_limits: Vec<RateLimit>,
+    /// Artificial latency added to responses
+    pub latency: Vec<LatencyRule>,
+    /// Optional YAML/JSON project template applied to projects created
+    /// through the admin API
+    pub project_template: Option<PathBuf>,
+    /// Optional directory of YAML/JSON fixtures loaded into the state at
+    /// startup
+    pub seed_dir: Option<PathBuf>,
+    /// Optional ndjson file the request journal is persisted to and reloaded
+    /// from on startup
+    pub journal_file: Option<PathBuf>,
+    /// Optional file the request journal is written to as a HAR log on
+    /// shutdown
+    pub har_out: Option<PathBuf>,
+    /// Real APS base URL requests without a stub or built-in handler are
+    /// forwarded to, e.g. `https://developer.api.autodesk.com`
+    pub proxy_base: Option<String>,
+    /// Directory forwarded exchanges are recorded into as stubs
+    pub record_dir: Option<PathBuf>,
+    /// Recording directory answering, offline, the requests `proxy_base`
+    /// would forward
+    pub replay_dir: Option<PathBuf>,
+    pub replay_match: ReplayMatch,
+    /// Give every app (client ID of the Bearer token) buckets, hubs,
+    /// issues, hooks and jobs of its own
+    pub isolate_clients: bool,
 }
 
 impl Default for MockServerConfig {
     fn default() -> Self {
         Self {
             mode: MockMode::default(),
+            service_modes: HashMap::new(),
             openapi_dir: PathBuf::from("../aps-sdk-openapi"),
+            extra_openapi_dirs: Vec::new(),
+            spec_include: Vec::new(),
+            spec_exclude: Vec::new(),
+            spec_cache_dir: None,
             state_file: None,
+            state_backend: None,
+            state_autosave_secs: None,
+            blob_store: BlobStoreSpec::Memory,
+            blob_store_max_bytes: None,
             verbose: false,
             host: "0.0.0.0".to_string(),
             port: 3000,
+            seed: None,
+            validate_requests: false,
+            clock_skew_secs: 0,
+            audit_log: 0,
+            upload_expiration_secs: 24 * 60 * 60,
+            upload_gc_interval_secs: 60,
+            translation_rules: Vec::new(),
+            derivative_size: 1024 * 1024,
+            users: Vec::new(),
+            clients: Vec::new(),
+            token_expires_in: crate::state::auth::DEFAULT_EXPIRES_IN,
+            webhook_attempts: 3,
+            webhook_backoff_ms: 1000,
+            stubs_dir: None,
+            pact_files: Vec::new(),
+            har_files: Vec::new(),
+            postman_files: Vec::new(),
+            deterministic: false,
+            response_headers: Vec::new(),
+            control_headers: false,
+            concurrency_limits: Vec::new(),
+            rate_limits: Vec::new(),
+            latency: Vec::new(),
+            journal_file: None,
+            har_out: None,
+            proxy_base: None,
+            record_dir: None,
+            replay_dir: None,
+            replay_match: ReplayMatch::default(),
+            isolate_clients: false,
+            project_template: None,
+            seed_dir: None,
         }
     }
 }
+
+impl MockServerConfig {
+    /// Operation mode of a service, falling back to the global `mode`
+    pub fn mode_for(&self, service: Service) -> MockMode {
+        self.service_modes
+            .get(&service)
+            .copied()
+            .unwrap_or(self.mode)
+    }
+
+    /// Every OpenAPI directory, in load order
+    pub fn openapi_dirs(&self) -> impl Iterator<Item = &PathBuf> {
+        std::iter::once(&self.openapi_dir).chain(&self.extra_openapi_dirs)
+    }
+
+    /// Filter built from `spec_include` and `spec_exclude`
+    pub fn spec_filter(&self) -> SpecFilter {
+        SpecFilter::new(self.spec_include.clone(), self.spec_exclude.clone())
+    }
+
+    /// Where the state is persisted: `state_backend`, else `state_file`
+    pub fn state_store(&self) -> Option<StateBackendSpec> {
+        self.state_backend
+            .clone()
+            .or_else(|| self.state_file.clone().map(StateBackendSpec::File))
+    }
+
+    /// Whether any service runs statefully, so in-memory state is needed
+    pub fn needs_state(&self) -> bool {
+        Service::ALL
+            .into_iter()
+            .any(|service| self.mode_for(service) == MockMode::Stateful)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_response_header_rules() {
+        let global: ResponseHeaderRule = "x-ads-region: US".parse().unwrap();
+        assert_eq!(global.route, None);
+        assert_eq!(
+            (global.name.as_str(), global.value.as_str()),
+            ("x-ads-region", "US")
+        );
+
+        let scoped: ResponseHeaderRule = "GET /oss/v2/buckets/:bucket_key=x-trace: a=b"
+            .parse()
+            .unwrap();
+        assert!(
+            scoped
+                .route
+                .unwrap()
+                .matches("GET", "/oss/v2/buckets/b1", &[])
+        );
+        assert_eq!(scoped.value, "a=b");
+
+        let tagged: ResponseHeaderRule = "tag:Model Derivative=x-ads-region: EMEA".parse().unwrap();
+        assert!(tagged.route.unwrap().is_tag());
+        assert_eq!(tagged.name, "x-ads-region");
+
+        assert!("no-colon".parse::<ResponseHeaderRule>().is_err());
+    }
+
+    #[test]
+    fn test_parse_concurrency_limits() {
+        let limit: ConcurrencyLimit = "PUT /oss/v2/buckets/*/objects/**=2:queue".parse().unwrap();
+        assert_eq!((limit.max_in_flight, limit.queue), (2, true));
+        assert!(
+            limit
+                .route
+                .matches("PUT", "/oss/v2/buckets/b1/objects/a/b", &[])
+        );
+
+        let limit: ConcurrencyLimit = "tag:Model Derivative=1".parse().unwrap();
+        assert!(!limit.queue);
+        assert!("/oss/**=0".parse::<ConcurrencyLimit>().is_err());
+        assert!("/oss/**=2:wait".parse::<ConcurrencyLimit>().is_err());
+        assert!("/oss/**".parse::<ConcurrencyLimit>().is_err());
+    }
+
+    #[test]
+    fn test_service_mode_overrides() {
+        let config = MockServerConfig {
+            mode: MockMode::Stateless,
+            service_modes: [parse_service_mode("oss=stateful").unwrap()].into(),
+            ..MockServerConfig::default()
+        };
+        assert_eq!(config.mode_for(Service::Oss), MockMode::Stateful);
+        assert_eq!(config.mode_for(Service::Issues), MockMode::Stateless);
+        assert!(config.needs_state());
+        assert_eq!(
+            Service::for_path("/construction/issues/v1/projects/p1/issues"),
+            Some(Service::Issues)
+        );
+        assert!(parse_service_mode("bim360=stateful").is_err());
+    }
+
+    #[test]
+    fn test_state_backend_specs() {
+        assert_eq!(
+            "sqlite:/var/lib/mock/state.db".parse(),
+            Ok(StateBackendSpec::Sqlite("/var/lib/mock/state.db".into()))
+        );
+        assert_eq!(
+            "redis://cache:6379/2".parse(),
+            Ok(StateBackendSpec::Redis("redis://cache:6379/2".into()))
+        );
+        assert!("redis:localhost".parse::<StateBackendSpec>().is_err());
+        assert!("sqlite:".parse::<StateBackendSpec>().is_err());
+
+        // The backend wins over a state file
+        let mut config = MockServerConfig {
+            state_file: Some("state.json".into()),
+            ..MockServerConfig::default()
+        };
+        assert_eq!(
+            config.state_store(),
+            Some(StateBackendSpec::File("state.json".into()))
+        );
+        config.state_backend = Some(StateBackendSpec::Sqlite("state.db".into()));
+        assert_eq!(
+            config.state_store(),
+            Some(StateBackendSpec::Sqlite("state.db".into()))
+        );
+    }
+}
@@ -0,0 +1,368 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+//! Configuration files (`raps-mock.toml`, `raps-mock.yaml`).
+//!
+//! Keys are the long CLI flag names (`openapi-dir`, `service-mode`, ...) plus
+//! settings without a flag, such as `upload-expiration-secs`. Repeatable
+//! flags take a list or a single value. Relative paths are resolved against
+//! the file's directory. The CLI applies a file first, then environment
+//! variables and flags on top.
+//!
+//! ```toml
+//! mode = "stateful"
+//! openapi-dir = ["specs", "extra-specs"]
+//! validate = true
+//! response-header = ["x-ads-region: EMEA"]
+//!
+//! [service-mode]
+//! model-derivative = "stateless"
+//! ```
+
+use crate::config::{
+    BlobStoreSpec, ConcurrencyLimit, LatencyRule, MockMode, MockServerConfig, RateLimit,
+    ReplayMatch, ResponseHeaderRule, Service, StateBackendSpec,
+};
+use crate::error::{MockError, Result};
+use crate::openapi::filter::SpecGlob;
+use crate::state::clients::ClientCredentials;
+use crate::state::translations::TranslationRule;
+use crate::state::users::UserInfo;
+use serde::{Deserialize, Deserializer};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// File names looked for in the working directory, in order
+pub const DEFAULT_FILE_NAMES: [&str; 3] = ["raps-mock.toml", "raps-mock.yaml", "raps-mock.yml"];
+
+/// Settings read from a configuration file; unset keys keep their defaults
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct ConfigFile {
+    mode: Option<Parsed<MockMode>>,
+    service_mode: Option<HashMap<Parsed<Service>, Parsed<MockMode>>>,
+    openapi_dir: Option<OneOrMany<PathBuf>>,
+    include: Option<OneOrMany<Parsed<SpecGlob>>>,
+    exclude: Option<OneOrMany<Parsed<SpecGlob>>>,
+    spec_cache: Option<PathBuf>,
+    state_file: Option<PathBuf>,
+    state_backend: Option<Parsed<StateBackendSpec>>,
+    state_autosave_secs: Option<u64>,
+    blob_store: Option<Parsed<BlobStoreSpec>>,
+    blob_store_max_bytes: Option<u64>,
+    verbose: Option<bool>,
+    host: Option<String>,
+    port: Option<u16>,
+    seed: Option<u64>,
+    deterministic: Option<bool>,
+    validate: Option<bool>,
+    stubs_dir: Option<PathBuf>,
+    pact: Option<OneOrMany<PathBuf>>,
+    har_in: Option<OneOrMany<PathBuf>>,
+    postman: Option<OneOrMany<PathBuf>>,
+    response_header: Option<OneOrMany<Parsed<ResponseHeaderRule>>>,
+    control_headers: Option<bool>,
+    max_in_flight: Option<OneOrMany<Parsed<ConcurrencyLimit>>>,
+    rate_limit: Option<OneOrMany<Parsed<RateLimit>>>,
+    latency: Option<OneOrMany<Parsed<LatencyRule>>>,
+    journal_file: Option<PathBuf>,
+    har_out: Option<PathBuf>,
+    proxy_base: Option<String>,
+    record: Option<PathBuf>,
+    replay: Option<PathBuf>,
+    replay_match: Option<Parsed<ReplayMatch>>,
+    isolate_clients: Option<bool>,
+    project_template: Option<PathBuf>,
+    seed_dir: Option<PathBuf>,
+    clock_skew_secs: Option<i64>,
+    token_expires_in: Option<u64>,
+    audit_log: Option<usize>,
+    upload_expiration_secs: Option<u64>,
+    upload_gc_interval_secs: Option<u64>,
+    translation_rules: Option<Vec<TranslationRule>>,
+    derivative_size: Option<u64>,
+    webhook_attempts: Option<u32>,
+    webhook_backoff_ms: Option<u64>,
+    users: Option<Vec<UserInfo>>,
+    clients: Option<Vec<ClientCredentials>>,
+    /// Directory relative paths are resolved against
+    #[serde(skip)]
+    base_dir: PathBuf,
+}
+
+impl ConfigFile {
+    /// Read a configuration file: TOML for `.toml` files, YAML (or JSON)
+    /// otherwise
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| MockError::Config(format!("{}: {}", path.display(), e)))?;
+        let is_toml = path.extension().is_some_and(|ext| ext == "toml");
+        let mut file: Self = if is_toml {
+            toml::from_str(&content)
+                .map_err(|e| MockError::Config(format!("{}: {}", path.display(), e)))?
+        } else {
+            serde_yaml::from_str(&content)
+                .map_err(|e| MockError::Config(format!("{}: {}", path.display(), e)))?
+        };
+        file.base_dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        Ok(file)
+    }
+
+    /// The first of [`DEFAULT_FILE_NAMES`] present in `dir`
+    pub fn discover(dir: &Path) -> Option<PathBuf> {
+        DEFAULT_FILE_NAMES
+            .iter()
+            .map(|name| dir.join(name))
+            .find(|path| path.is_file())
+    }
+
+    /// Overwrite the settings of `config` the file sets
+    pub fn apply(self, config: &mut MockServerConfig) {
+        let base_dir = self.base_dir;
+        let resolve = |path: PathBuf| base_dir.join(path);
+
+        if let Some(Parsed(mode)) = self.mode {
+            config.mode = mode;
+        }
+        if let Some(modes) = self.service_mode {
+            config
+                .service_modes
+                .extend(modes.into_iter().map(|(service, mode)| (service.0, mode.0)));
+        }
+        if let Some(dirs) = self.openapi_dir {
+            let mut dirs = dirs.into_vec().into_iter().map(resolve);
+            if let Some(first) = dirs.next() {
+                config.openapi_dir = first;
+                config.extra_openapi_dirs = dirs.collect();
+            }
+        }
+        if let Some(globs) = self.include {
+            config.spec_include = globs.into_vec().into_iter().map(|g| g.0).collect();
+        }
+        if let Some(globs) = self.exclude {
+            config.spec_exclude = globs.into_vec().into_iter().map(|g| g.0).collect();
+        }
+        if let Some(dir) = self.spec_cache {
+            config.spec_cache_dir = Some(resolve(dir));
+        }
+        if let Some(path) = self.state_file {
+            config.state_file = Some(resolve(path));
+        }
+        if let Some(Parsed(backend)) = self.state_backend {
+            config.state_backend = Some(match backend.path() {
+                Some(path) => {
+                    let path = resolve(path.to_path_buf());
+                    backend.with_path(path)
+                }
+                None => backend,
+            });
+        }
+        if let Some(secs) = self.state_autosave_secs {
+            config.state_autosave_secs = Some(secs);
+        }
+        if let Some(Parsed(store)) = self.blob_store {
+            config.blob_store = match store {
+                BlobStoreSpec::Disk(dir) => BlobStoreSpec::Disk(resolve(dir)),
+                memory => memory,
+            };
+        }
+        if let Some(bytes) = self.blob_store_max_bytes {
+            config.blob_store_max_bytes = Some(bytes);
+        }
+        if let Some(verbose) = self.verbose {
+            config.verbose = verbose;
+        }
+        if let Some(host) = self.host {
+            config.host = host;
+        }
+        if let Some(port) = self.port {
+            config.port = port;
+        }
+        if let Some(seed) = self.seed {
+            config.seed = Some(seed);
+        }
+        if let Some(deterministic) = self.deterministic {
+            config.deterministic = deterministic;
+        }
+        if let Some(validate) = self.validate {
+            config.validate_requests = validate;
+        }
+        if let Some(dir) = self.stubs_dir {
+            config.stubs_dir = Some(resolve(dir));
+        }
+        if let Some(files) = self.pact {
+            config.pact_files = files.into_vec().into_iter().map(resolve).collect();
+        }
+        if let Some(files) = self.har_in {
+            config.har_files = files.into_vec().into_iter().map(resolve).collect();
+        }
+        if let Some(files) = self.postman {
+            config.postman_files = files.into_vec().into_iter().map(resolve).collect();
+        }
+        if let Some(rules) = self.response_header {
+            config.response_headers = rules.into_vec().into_iter().map(|r| r.0).collect();
+        }
+        if let Some(control_headers) = self.control_headers {
+            config.control_headers = control_headers;
+        }
+        if let Some(limits) = self.max_in_flight {
+            config.concurrency_limits = limits.into_vec().into_iter().map(|l| l.0).collect();
+        }
+        if let Some(limits) = self.rate_limit {
+            config.rate_limits = limits.into_vec().into_iter().map(|l| l.0).collect();
+        }
+        if let Some(rules) = self.latency {
+            config.latency = rules.into_vec().into_iter().map(|r| r.0).collect();
+        }
+        if let Some(path) = self.journal_file {
+            config.journal_file = Some(resolve(path));
+        }
+        if let Some(path) = self.har_out {
+            config.har_out = Some(resolve(path));
+        }
+        if let Some(url) = self.proxy_base {
+            config.proxy_base = Some(url);
+        }
+        if let Some(dir) = self.record {
+            config.record_dir = Some(resolve(dir));
+        }
+        if let Some(dir) = self.replay {
+            config.replay_dir = Some(resolve(dir));
+        }
+        if let Some(Parsed(matching)) = self.replay_match {
+            config.replay_match = matching;
+        }
+        if let Some(isolate) = self.isolate_clients {
+            config.isolate_clients = isolate;
+        }
+        if let Some(path) = self.project_template {
+            config.project_template = Some(resolve(path));
+        }
+        if let Some(dir) = self.seed_dir {
+            config.seed_dir = Some(resolve(dir));
+        }
+        if let Some(secs) = self.clock_skew_secs {
+            config.clock_skew_secs = secs;
+        }
+        if let Some(secs) = self.token_expires_in {
+            config.token_expires_in = secs;
+        }
+        if let Some(entries) = self.audit_log {
+            config.audit_log = entries;
+        }
+        if let Some(secs) = self.upload_expiration_secs {
+            config.upload_expiration_secs = secs;
+        }
+        if let Some(secs) = self.upload_gc_interval_secs {
+            config.upload_gc_interval_secs = secs;
+        }
+        if let Some(rules) = self.translation_rules {
+            config.translation_rules = rules;
+        }
+        if let Some(bytes) = self.derivative_size {
+            config.derivative_size = bytes;
+        }
+        if let Some(attempts) = self.webhook_attempts {
+            config.webhook_attempts = attempts;
+        }
+        if let Some(ms) = self.webhook_backoff_ms {
+            config.webhook_backoff_ms = ms;
+        }
+        if let Some(users) = self.users {
+            config.users = users;
+        }
+        if let Some(clients) = self.clients {
+            config.clients = clients;
+        }
+    }
+}
+
+impl MockServerConfig {
+    /// The default configuration with a configuration file applied
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let mut config = Self::default();
+        ConfigFile::load(path)?.apply(&mut config);
+        Ok(config)
+    }
+}
+
+/// A value written in its string form, as on the command line
+#[derive(Debug, PartialEq, Eq, Hash)]
+struct Parsed<T>(T);
+
+impl<'de, T: FromStr<Err = String>> Deserialize<'de> for Parsed<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map(Parsed).map_err(serde::de::Error::custom)
+    }
+}
+
+/// A repeatable setting, given as a list or a single value
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum OneOrMany<T> {
+    One(T),
+    Many(Vec<T>),
+}
+
+impl<T> OneOrMany<T> {
+    fn into_vec(self) -> Vec<T> {
+        match self {
+            OneOrMany::One(value) => vec![value],
+            OneOrMany::Many(values) => values,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_toml_and_yaml_files_are_applied() {
+        let dir = tempfile::tempdir().unwrap();
+        let toml_path = dir.path().join("raps-mock.toml");
+        std::fs::write(
+            &toml_path,
+            r#"
+port = 4000
+openapi-dir = ["specs", "/abs/extra"]
+include = "oss/**"
+validate = true
+upload-expiration-secs = 60
+response-header = ["x-ads-region: EMEA"]
+
+[service-mode]
+md = "stateless"
+"#,
+        )
+        .unwrap();
+        assert_eq!(ConfigFile::discover(dir.path()), Some(toml_path.clone()));
+
+        let config = MockServerConfig::from_file(&toml_path).unwrap();
+        assert_eq!(config.port, 4000);
+        assert_eq!(config.openapi_dir, dir.path().join("specs"));
+        assert_eq!(config.extra_openapi_dirs, [PathBuf::from("/abs/extra")]);
+        assert_eq!(config.spec_include.len(), 1);
+        assert!(config.validate_requests);
+        assert_eq!(config.upload_expiration_secs, 60);
+        assert_eq!(config.response_headers[0].value, "EMEA");
+        assert_eq!(
+            config.mode_for(Service::ModelDerivative),
+            MockMode::Stateless
+        );
+        // Unset keys keep their defaults
+        assert_eq!(config.host, MockServerConfig::default().host);
+
+        let yaml_path = dir.path().join("raps-mock.yaml");
+        std::fs::write(&yaml_path, "mode: stateless\nseed: 7\n").unwrap();
+        let config = MockServerConfig::from_file(&yaml_path).unwrap();
+        assert_eq!((config.mode, config.seed), (MockMode::Stateless, Some(7)));
+
+        std::fs::write(&yaml_path, "mode: sometimes\n").unwrap();
+        assert!(MockServerConfig::from_file(&yaml_path).is_err());
+        std::fs::write(&yaml_path, "prot: 4000\n").unwrap();
+        assert!(MockServerConfig::from_file(&yaml_path).is_err());
+    }
+}
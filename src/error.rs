@@ -11,6 +11,18 @@ pub enum MockError {
 
     #[error("YAML parsing error: {0}")]
     Yaml(#[from] serde_yaml::Error),
+
+    #[error("Configuration error: {0}")]
+    Config(String),
+
+    #[error("HTTP error: {0}")]
+    Http(String),
+
+    #[error("State file error: {0}")]
+    State(String),
+
+    #[error("Verification failed: {0}")]
+    Verification(String),
 }
 
 pub type Result<T> = std::result::Result<T, MockError>;
@@ -1,42 +1,165 @@
 // SPDX-License-Identifier: Apache-2.0
 // Copyright 2024-2025 Dmytro Yemelianov
 
-use axum::response::Response;
-use dashmap::DashMap;
+//! Handlers registered from code (`MockServer::stub`).
+//!
+//! They answer matching requests ahead of the file stubs, the OpenAPI and
+//! the built-in routes, so a test can pin one response without a stubs
+//! directory:
+//!
+//! ```rust,no_run
+//! use axum::http::Method;
+//! use raps_mock::{MockServer, MockServerConfig};
+//! use serde_json::json;
+//!
+//! # async fn example() -> raps_mock::Result<()> {
+//! let server = MockServer::new(MockServerConfig::default()).await?;
+//! server
+//!     .stub(Method::GET, "/oss/v2/buckets/:key/details")
+//!     .match_query("limit", "10")
+//!     .respond(200, json!({ "bucketKey": "b1" }));
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Matching uses the file stubs' matchers ([`crate::stubs`]). Among handlers
+//! matching a request, the one registered last wins, so a test can override
+//! an earlier one.
+
+use crate::stubs::{
+    ContentPattern, QueryParameterMatcher, Stub, StubCall, StubMapping, StubRequest,
+};
+use axum::{
+    Json,
+    http::{HeaderMap, Method, StatusCode},
+    response::{IntoResponse, Response},
+};
 use serde_json::Value;
 use std::future::Future;
 use std::pin::Pin;
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
 
-/// Custom handler function type
+/// Custom handler function type; receives the request body parsed as JSON
 pub type HandlerFn =
     Arc<dyn Fn(Option<Value>) -> Pin<Box<dyn Future<Output = Response> + Send>> + Send + Sync>;
 
+struct CustomHandler {
+    /// `METHOD path`, as registered
+    route_key: String,
+    stub: Stub,
+    handler: HandlerFn,
+}
+
 /// Registry for custom handlers
 pub struct CustomHandlerRegistry {
-    handlers: DashMap<String, HandlerFn>,
+    /// In registration order
+    handlers: RwLock<Vec<CustomHandler>>,
 }
 
 impl CustomHandlerRegistry {
     pub fn new() -> Self {
         Self {
-            handlers: DashMap::new(),
+            handlers: RwLock::new(Vec::new()),
         }
     }
 
-    /// Register a custom handler for a route
+    /// Register a custom handler for a route key such as
+    /// `GET /oss/v2/buckets/:bucketKey/details`, matching every request to
+    /// the route
     pub fn register(&self, route_key: String, handler: HandlerFn) {
-        self.handlers.insert(route_key, handler);
+        let (method, path) = route_key.split_once(' ').unwrap_or(("ANY", &route_key));
+        let request = StubRequest {
+            method: Some(method.to_string()),
+            url_path_template: Some(path_template(path)),
+            ..StubRequest::default()
+        };
+        self.insert(route_key.clone(), request, handler);
+    }
+
+    /// Start a handler for requests of `method` to `path`, which may hold
+    /// `:param` or `{param}` segments; [`StubBuilder::respond`] registers it
+    pub fn stub(self: &Arc<Self>, method: Method, path: &str) -> StubBuilder {
+        StubBuilder {
+            registry: self.clone(),
+            route_key: format!("{} {}", method, path),
+            request: StubRequest {
+                method: Some(method.to_string()),
+                url_path_template: Some(path_template(path)),
+                ..StubRequest::default()
+            },
+        }
+    }
+
+    fn insert(&self, route_key: String, request: StubRequest, handler: HandlerFn) {
+        let mapping = StubMapping {
+            id: None,
+            name: Some(route_key.clone()),
+            priority: None,
+            scenario: None,
+            request,
+            response: Default::default(),
+        };
+        match Stub::compile(mapping) {
+            Ok(stub) => self.write().push(CustomHandler {
+                route_key,
+                stub,
+                handler,
+            }),
+            Err(e) => tracing::warn!("Skipping handler for {}: {}", route_key, e),
+        }
     }
 
     /// Check if a handler exists for a route
     pub fn has(&self, route_key: &str) -> bool {
-        self.handlers.contains_key(route_key)
+        self.read().iter().any(|h| h.route_key == route_key)
     }
 
-    /// Get a handler for a route
+    /// Get the handler registered last for a route
     pub fn get(&self, route_key: &str) -> Option<HandlerFn> {
-        self.handlers.get(route_key).map(|h| h.clone())
+        self.read()
+            .iter()
+            .rev()
+            .find(|h| h.route_key == route_key)
+            .map(|h| h.handler.clone())
+    }
+
+    pub fn len(&self) -> usize {
+        self.read().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.read().is_empty()
+    }
+
+    /// Remove every handler
+    pub fn clear(&self) {
+        self.write().clear();
+    }
+
+    /// The handler registered last among those matching a request; a
+    /// `body` of `None` (not buffered) matches no body matcher
+    pub fn find(
+        &self,
+        method: &str,
+        path: &str,
+        query: Option<&str>,
+        headers: &HeaderMap,
+        body: Option<&[u8]>,
+    ) -> Option<HandlerFn> {
+        let call = StubCall::new(method, path, query, headers, body);
+        self.read()
+            .iter()
+            .rev()
+            .find(|h| h.stub.matches(&call).is_some())
+            .map(|h| h.handler.clone())
+    }
+
+    fn read(&self) -> std::sync::RwLockReadGuard<'_, Vec<CustomHandler>> {
+        self.handlers.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn write(&self) -> std::sync::RwLockWriteGuard<'_, Vec<CustomHandler>> {
+        self.handlers.write().unwrap_or_else(|e| e.into_inner())
     }
 }
 
@@ -45,3 +168,196 @@ impl Default for CustomHandlerRegistry {
         Self::new()
     }
 }
+
+/// A handler being built; every `match_*` narrows the requests it answers
+#[must_use = "a stub is only registered by `respond` or `respond_with`"]
+pub struct StubBuilder {
+    registry: Arc<CustomHandlerRegistry>,
+    route_key: String,
+    request: StubRequest,
+}
+
+impl StubBuilder {
+    /// The query parameter has this value (or list item)
+    pub fn match_query(mut self, name: &str, value: &str) -> Self {
+        self.request.query_parameters.insert(
+            name.to_string(),
+            QueryParameterMatcher {
+                equal_to: Some(value.to_string()),
+                ..QueryParameterMatcher::default()
+            },
+        );
+        self
+    }
+
+    /// The query parameter is missing
+    pub fn match_query_absent(mut self, name: &str) -> Self {
+        self.request.query_parameters.insert(
+            name.to_string(),
+            QueryParameterMatcher {
+                absent: Some(true),
+                ..QueryParameterMatcher::default()
+            },
+        );
+        self
+    }
+
+    /// The header has this value
+    pub fn match_header(mut self, name: &str, value: &str) -> Self {
+        self.request.headers.insert(
+            name.to_ascii_lowercase(),
+            ContentPattern {
+                equal_to: Some(value.to_string()),
+                ..ContentPattern::default()
+            },
+        );
+        self
+    }
+
+    /// The body is JSON equal to `json`
+    pub fn match_body_json(mut self, json: Value) -> Self {
+        self.request.body_patterns.push(ContentPattern {
+            equal_to_json: Some(json),
+            ..ContentPattern::default()
+        });
+        self
+    }
+
+    /// The body contains `text`
+    pub fn match_body_contains(mut self, text: &str) -> Self {
+        self.request.body_patterns.push(ContentPattern {
+            contains: Some(text.to_string()),
+            ..ContentPattern::default()
+        });
+        self
+    }
+
+    /// Answer matching requests with `status` and a JSON body
+    pub fn respond(self, status: u16, json: Value) {
+        let status = StatusCode::from_u16(status).unwrap_or(StatusCode::OK);
+        self.respond_with(move |_| {
+            let json = json.clone();
+            async move { (status, Json(json)).into_response() }
+        });
+    }
+
+    /// Answer matching requests with `handler`, called with the request
+    /// body parsed as JSON
+    pub fn respond_with<F, Fut>(self, handler: F)
+    where
+        F: Fn(Option<Value>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Response> + Send + 'static,
+    {
+        let handler: HandlerFn = Arc::new(move |body| Box::pin(handler(body)));
+        self.registry.insert(self.route_key, self.request, handler);
+    }
+}
+
+/// The stub template of a path, turning `:param` segments into `{param}`
+fn path_template(path: &str) -> String {
+    path.split('/')
+        .map(|segment| match segment.strip_prefix(':') {
+            Some(name) => format!("{{{}}}", name),
+            None => segment.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::MockServerConfig;
+    use crate::server::MockServer;
+    use axum::body::Body;
+    use axum::extract::Request;
+    use axum::http::{Method, StatusCode};
+    use axum::response::IntoResponse;
+    use serde_json::{Value, json};
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_code_stubs_answer_matching_requests_ahead_of_routes() {
+        // An empty spec directory, so no embedded spec example answers either
+        let specs = tempfile::tempdir().unwrap();
+        let config = MockServerConfig {
+            openapi_dir: specs.path().to_path_buf(),
+            ..MockServerConfig::default()
+        };
+        let server = MockServer::new(config).await.unwrap();
+        let router = server.router();
+        let token = server
+            .state()
+            .unwrap()
+            .auth
+            .generate_token("client", 3600, None);
+        let call = |method: Method, uri: &str, body: Value| {
+            let request = Request::builder()
+                .method(method)
+                .uri(uri)
+                .header("authorization", format!("Bearer {}", token.access_token))
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap();
+            let router = router.clone();
+            async move {
+                let response = router.oneshot(request).await.unwrap();
+                let status = response.status();
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                (
+                    status,
+                    serde_json::from_slice::<Value>(&body).unwrap_or_default(),
+                )
+            }
+        };
+
+        server
+            .stub(Method::GET, "/oss/v2/buckets/:key/details")
+            .match_query("limit", "10")
+            .respond(200, json!({ "bucketKey": "stubbed" }));
+        server
+            .stub(Method::POST, "/oss/v2/buckets")
+            .match_body_json(json!({ "bucketKey": "b1", "policyKey": "transient" }))
+            .respond_with(|body| async move {
+                let key = body.unwrap()["bucketKey"].clone();
+                (StatusCode::CONFLICT, axum::Json(json!({ "key": key }))).into_response()
+            });
+
+        let (status, body) = call(
+            Method::GET,
+            "/oss/v2/buckets/b1/details?limit=10",
+            Value::Null,
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["bucketKey"], "stubbed");
+        // Unmatched requests reach the built-in handler
+        let (status, _) = call(Method::GET, "/oss/v2/buckets/b1/details", Value::Null).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let bucket = json!({ "bucketKey": "b1", "policyKey": "transient" });
+        let (status, body) = call(Method::POST, "/oss/v2/buckets", bucket).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(body["key"], "b1");
+        let other = json!({ "bucketKey": "b2", "policyKey": "transient" });
+        let (status, _) = call(Method::POST, "/oss/v2/buckets", other).await;
+        assert_eq!(status, StatusCode::OK);
+
+        // The handler registered last wins
+        server
+            .stub(Method::GET, "/oss/v2/buckets/{key}/details")
+            .respond(200, json!({ "bucketKey": "override" }));
+        let (_, body) = call(
+            Method::GET,
+            "/oss/v2/buckets/b1/details?limit=10",
+            Value::Null,
+        )
+        .await;
+        assert_eq!(body["bucketKey"], "override");
+        assert!(server.handlers().has("GET /oss/v2/buckets/{key}/details"));
+        server.handlers().clear();
+        let (status, _) = call(Method::GET, "/oss/v2/buckets/b9/details", Value::Null).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+}
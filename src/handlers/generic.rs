@@ -1,65 +1,125 @@
 // SPDX-License-Identifier: Apache-2.0
 // Copyright 2024-2025 Dmytro Yemelianov
 
+use crate::config::MockServerConfig;
+use crate::handlers::media;
+use crate::handlers::prefer::{PREFERENCE_APPLIED_HEADER, Preferences};
+use crate::handlers::synth::{SchemaSynthesizer, stable_hash};
+use crate::handlers::validation;
+use crate::middleware::control::MockOverrides;
+use crate::middleware::determinism::RequestFingerprint;
 use crate::openapi::types::RouteDefinition;
+use crate::templating::{self, TemplateContext};
 use axum::{
     Json,
-    http::StatusCode,
+    body::to_bytes,
+    extract::Request,
+    http::{HeaderValue, StatusCode, header::CONTENT_TYPE},
     response::{IntoResponse, Response},
 };
 use serde_json::json;
+use std::sync::Arc;
+
+/// Query parameter selecting a named example, like `Prefer: example=`
+pub const EXAMPLE_QUERY_PARAM: &str = "__example";
+
+/// Upper bound on request bodies buffered for validation
+const MAX_BODY_BYTES: usize = 16 * 1024 * 1024;
 
 /// Generic handler that serves mock responses based on OpenAPI definitions
 pub struct GenericHandler {
     route: RouteDefinition,
+    config: Arc<MockServerConfig>,
 }
 
 impl GenericHandler {
-    pub fn new(route: RouteDefinition) -> Self {
-        Self { route }
+    pub fn new(route: RouteDefinition, config: Arc<MockServerConfig>) -> Self {
+        Self { route, config }
     }
 
-    pub async fn handle(&self) -> Response {
+    pub async fn handle(&self, request: Request) -> Response {
         tracing::info!(
             "GenericHandler handling {} {}",
             self.route.method.as_str(),
             self.route.path
         );
+
+        let overrides = request
+            .extensions()
+            .get::<MockOverrides>()
+            .cloned()
+            .unwrap_or_default();
+        let preferences = Preferences::from_headers(request.headers());
+        let context = TemplateContext::new(
+            request.method().as_str(),
+            request.uri().path(),
+            request.uri().query(),
+            request.headers(),
+        )
+        .with_path_params(self.route.extract_path_params(request.uri().path()))
+        .with_fingerprint(request.extensions().get::<RequestFingerprint>());
+        let example_param = request.uri().query().and_then(|query| {
+            form_urlencoded::parse(query.as_bytes())
+                .find(|(name, _)| name == EXAMPLE_QUERY_PARAM)
+                .map(|(_, value)| value.into_owned())
+        });
+
+        if self.config.validate_requests
+            && let Err(response) = self.validate(request).await
+        {
+            return response;
+        }
+
+        // Named example: Prefer header, then query parameter, then scenario
+        let scenario = preferences
+            .example
+            .as_deref()
+            .or(example_param.as_deref())
+            .or(overrides.scenario.as_deref());
+
+        // A status forced through X-Mock-Status uses that response definition
+        if let Some(status) = overrides.status
+            && let Some(response) = self.response_for(status.as_str(), status, scenario, &context)
+        {
+            return response;
+        }
+
+        // `Prefer: code=404` selects the spec's response for that status
+        if let Some(status) = preferences.code {
+            let Some(mut response) = self
+                .response_for(status.as_str(), status, scenario, &context)
+                .or_else(|| self.response_for("default", status, scenario, &context))
+            else {
+                return (
+                    StatusCode::NOT_IMPLEMENTED,
+                    Json(json!({
+                        "message": format!(
+                            "No {} response defined for {} {}",
+                            status.as_str(),
+                            self.route.method.as_str(),
+                            self.route.path
+                        ),
+                        "operation_id": self.route.operation.operation_id
+                    })),
+                )
+                    .into_response();
+            };
+            if let Ok(applied) = HeaderValue::from_str(&format!("code={}", status.as_str())) {
+                response
+                    .headers_mut()
+                    .insert(PREFERENCE_APPLIED_HEADER, applied);
+            }
+            return response;
+        }
+
         // Try to find a successful response (200, 201, etc.)
         let success_codes = ["200", "201", "202", "204", "default"];
 
         for code in success_codes {
-            if let Some(response) = self.route.operation.responses.get(code) {
-                // Resolve reference if needed
-                let response_def = self.resolve_response(response);
-
-                if let Some(crate::openapi::types::Response::Definition {
-                    content: Some(content_map),
-                    ..
-                }) = response_def
-                {
-                    // Media types to check in order of priority
-                    let media_types = ["application/json", "application/vnd.api+json"];
-
-                    for mt in &media_types {
-                        if let Some(example) = content_map
-                            .get(*mt)
-                            .and_then(|media_type| self.extract_example(media_type))
-                        {
-                            return (StatusCode::OK, Json(example)).into_response();
-                        }
-                    }
-                }
-
-                if response_def.is_some() {
-                    // If it's 204 No Content, return empty body
-                    if code == "204" {
-                        return StatusCode::NO_CONTENT.into_response();
-                    }
-
-                    // Fallback for success without content
-                    return StatusCode::OK.into_response();
-                }
+            // Answer with the defined status, so e.g. a 202 keeps its Location
+            let status = code.parse().unwrap_or(StatusCode::OK);
+            if let Some(response) = self.response_for(code, status, scenario, &context) {
+                return response;
             }
         }
 
@@ -74,6 +134,195 @@ impl GenericHandler {
             .into_response()
     }
 
+    /// Build the mock response for a response code defined on the operation
+    fn response_for(
+        &self,
+        code: &str,
+        status: StatusCode,
+        scenario: Option<&str>,
+        context: &TemplateContext,
+    ) -> Option<Response> {
+        let response = self.route.operation.responses.get(code)?;
+        // Resolve reference if needed
+        let response_def = self.resolve_response(response)?;
+
+        let mut built = self.response_body(code, response_def, status, scenario, context);
+        self.apply_response_headers(response_def, &mut built, context);
+        Some(built)
+    }
+
+    fn response_body(
+        &self,
+        code: &str,
+        response_def: &crate::openapi::types::Response,
+        status: StatusCode,
+        scenario: Option<&str>,
+        context: &TemplateContext,
+    ) -> Response {
+        if let crate::openapi::types::Response::Definition {
+            content: Some(content_map),
+            ..
+        } = response_def
+        {
+            // Media types to check in order of priority
+            let media_types = ["application/json", "application/vnd.api+json"];
+
+            for mt in &media_types {
+                if let Some(mut example) = content_map
+                    .get(*mt)
+                    .and_then(|media_type| self.extract_example(media_type, scenario))
+                {
+                    // Examples may reference the request, e.g. `{{ path.bucketKey }}`
+                    templating::render_value(&mut example, context);
+                    return (status, Json(example)).into_response();
+                }
+            }
+
+            // No example anywhere: synthesize one from the schema
+            for mt in &media_types {
+                if let Some(schema) = content_map.get(*mt).and_then(|m| m.schema.as_ref()) {
+                    let mut synthesizer = SchemaSynthesizer::new(
+                        self.route.components.as_deref(),
+                        self.synthesis_seed(context),
+                    );
+                    return (status, Json(synthesizer.generate(schema))).into_response();
+                }
+            }
+
+            // Other media types: images, binary downloads, text, XML
+            let accept = context.headers.get("accept").map(String::as_str);
+            for (name, media_type) in media::candidates(content_map, accept) {
+                if media_types.contains(&name) {
+                    continue;
+                }
+                let body = match self.extract_example(media_type, scenario) {
+                    Some(example) => media::example_body(name, example, context),
+                    None => media::placeholder_body(name),
+                };
+                if let Some(body) = body {
+                    return (status, [(CONTENT_TYPE, name.to_string())], body).into_response();
+                }
+            }
+        }
+
+        // If it's 204 No Content, return empty body
+        if code == "204" {
+            return StatusCode::NO_CONTENT.into_response();
+        }
+
+        // Fallback for a response without content
+        status.into_response()
+    }
+
+    /// Emit the headers the response definition declares (`Location`,
+    /// `x-ads-region`, rate-limit headers, ...)
+    fn apply_response_headers(
+        &self,
+        response_def: &crate::openapi::types::Response,
+        response: &mut Response,
+        context: &TemplateContext,
+    ) {
+        let crate::openapi::types::Response::Definition {
+            headers: Some(headers),
+            ..
+        } = response_def
+        else {
+            return;
+        };
+
+        let mut synthesizer = SchemaSynthesizer::new(
+            self.route.components.as_deref(),
+            self.synthesis_seed(context),
+        );
+        for (name, header) in headers {
+            // OpenAPI says a Content-Type header definition is ignored
+            if name.eq_ignore_ascii_case("content-type") {
+                continue;
+            }
+            let Some(header) = self.resolve_header(header) else {
+                continue;
+            };
+            let Some(mut value) = header.example_value().or_else(|| {
+                header
+                    .schema()
+                    .map(|schema| synthesizer.generate_named(schema, name))
+                    .filter(|v| !v.is_null())
+            }) else {
+                continue;
+            };
+            templating::render_value(&mut value, context);
+            let value = match value {
+                serde_json::Value::String(s) => s,
+                other => other.to_string(),
+            };
+            if let (Ok(name), Ok(value)) = (
+                axum::http::HeaderName::try_from(name.as_str()),
+                HeaderValue::from_str(&value),
+            ) {
+                response.headers_mut().insert(name, value);
+            }
+        }
+    }
+
+    fn resolve_header<'a>(
+        &'a self,
+        header: &'a crate::openapi::types::Header,
+    ) -> Option<&'a crate::openapi::types::Header> {
+        match header {
+            crate::openapi::types::Header::Definition { .. } => Some(header),
+            crate::openapi::types::Header::Ref { ref_path } => {
+                let name = ref_path.split('/').next_back()?;
+                self.route
+                    .components
+                    .as_deref()?
+                    .headers
+                    .as_ref()?
+                    .get(name)
+            }
+        }
+    }
+
+    /// Validate the incoming request against the operation definition
+    async fn validate(&self, request: Request) -> std::result::Result<(), Response> {
+        let (parts, body) = request.into_parts();
+        let body = to_bytes(body, MAX_BODY_BYTES).await.map_err(|e| {
+            validation::bad_request_response(&[validation::ValidationError::new(
+                "body",
+                format!("could not read body: {}", e),
+            )])
+        })?;
+        let content_type = parts
+            .headers
+            .get(CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok());
+
+        let mut errors =
+            validation::validate_parameters(&self.route, parts.uri.path(), parts.uri.query());
+        errors.extend(validation::validate_body(&self.route, content_type, &body));
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(validation::bad_request_response(&errors))
+        }
+    }
+
+    /// Per-route seed derived from the configured seed, so every route gets
+    /// its own reproducible sequence. In deterministic mode the request
+    /// fingerprint (which covers the seed) is mixed in as well.
+    fn synthesis_seed(&self, context: &TemplateContext) -> Option<u64> {
+        let route = [
+            self.route.method.as_str().as_bytes(),
+            self.route.path.as_bytes(),
+        ];
+        match (context.fingerprint, self.config.seed) {
+            (Some(RequestFingerprint(hash)), _) => {
+                Some(stable_hash(&[&hash.to_le_bytes(), route[0], route[1]]))
+            }
+            (None, Some(seed)) => Some(stable_hash(&[&seed.to_le_bytes(), route[0], route[1]])),
+            (None, None) => None,
+        }
+    }
+
     fn resolve_response<'a>(
         &'a self,
         response: &'a crate::openapi::types::Response,
@@ -84,7 +333,7 @@ impl GenericHandler {
                 let name = ref_path.split('/').next_back()?;
                 self.route
                     .components
-                    .as_ref()?
+                    .as_deref()?
                     .responses
                     .as_ref()?
                     .get(name)
@@ -95,13 +344,22 @@ impl GenericHandler {
     fn extract_example(
         &self,
         media_type: &crate::openapi::types::MediaType,
+        scenario: Option<&str>,
     ) -> Option<serde_json::Value> {
+        // 0. A requested name (or scenario) selects a named example
+        if let Some(value) = scenario
+            .and_then(|name| media_type.examples.as_ref()?.get(name))
+            .and_then(|example| example.value.as_ref())
+        {
+            return Some(value.clone());
+        }
+
         // 1. Try direct example
         if let Some(ref example) = media_type.example {
             return Some(example.clone());
         }
 
-        // 2. Try first example from examples map
+        // 2. Try the first example in the examples map
         if let Some(value) = media_type
             .examples
             .as_ref()
@@ -131,7 +389,12 @@ impl GenericHandler {
         match schema {
             crate::openapi::types::Schema::Ref { ref_path } => {
                 let name = ref_path.split('/').next_back()?;
-                self.route.components.as_ref()?.schemas.as_ref()?.get(name)
+                self.route
+                    .components
+                    .as_deref()?
+                    .schemas
+                    .as_ref()?
+                    .get(name)
             }
             _ => Some(schema),
         }
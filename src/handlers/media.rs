@@ -0,0 +1,155 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+//! Response bodies for media types other than plain JSON: images and other
+//! binary payloads, text and XML.
+//!
+//! Examples of binary media types are base64 strings; text and XML examples
+//! are served as written (after templating). Without an example, PNG, text,
+//! XML and octet-stream responses get a small placeholder body.
+
+use crate::openapi::types::MediaType;
+use crate::templating::{self, TemplateContext};
+use base64::Engine as _;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// A valid 1x1 transparent PNG
+const PLACEHOLDER_PNG: &str =
+    "iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAQAAAC1HAwCAAAAC0lEQVR42mNkYAAAAAYAAjCB0C8AAAAASUVORK5CYII=";
+
+/// Media type essence, without parameters, lowercased
+fn essence(media_type: &str) -> String {
+    media_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase()
+}
+
+fn is_json(media_type: &str) -> bool {
+    let media_type = essence(media_type);
+    media_type == "application/json" || media_type.ends_with("+json")
+}
+
+fn is_text(media_type: &str) -> bool {
+    let media_type = essence(media_type);
+    media_type.starts_with("text/")
+        || media_type == "application/xml"
+        || media_type.ends_with("+xml")
+}
+
+/// Media types of a response that can be served, those the client lists in
+/// `Accept` first and wildcards (`image/*`) left out
+pub fn candidates<'a>(
+    content: &'a HashMap<String, MediaType>,
+    accept: Option<&str>,
+) -> Vec<(&'a str, &'a MediaType)> {
+    let accepted: Vec<String> = accept
+        .map(|accept| accept.split(',').map(essence).collect())
+        .unwrap_or_default();
+    let mut candidates: Vec<(&str, &MediaType)> = content
+        .iter()
+        .filter(|(name, _)| !name.contains('*'))
+        .map(|(name, media_type)| (name.as_str(), media_type))
+        .collect();
+    candidates.sort_by_key(|(name, _)| (!accepted.contains(&essence(name)), *name));
+    candidates
+}
+
+/// Body for an example of `media_type`. Returns `None` for a binary example
+/// that is not valid base64.
+pub fn example_body(
+    media_type: &str,
+    example: Value,
+    context: &TemplateContext,
+) -> Option<Vec<u8>> {
+    let mut example = example;
+    if is_json(media_type) {
+        templating::render_value(&mut example, context);
+        return serde_json::to_vec(&example).ok();
+    }
+    let text = match example {
+        Value::String(text) => text,
+        other => other.to_string(),
+    };
+    if is_text(media_type) {
+        let mut text = Value::String(text);
+        templating::render_value(&mut text, context);
+        return text.as_str().map(|s| s.as_bytes().to_vec());
+    }
+    match base64::engine::general_purpose::STANDARD.decode(text.trim()) {
+        Ok(bytes) => Some(bytes),
+        Err(e) => {
+            tracing::warn!("Binary {} example is not valid base64: {}", media_type, e);
+            None
+        }
+    }
+}
+
+/// Stand-in body for a media type without an example
+pub fn placeholder_body(media_type: &str) -> Option<Vec<u8>> {
+    let media_type = essence(media_type);
+    match media_type.as_str() {
+        "image/png" => base64::engine::general_purpose::STANDARD
+            .decode(PLACEHOLDER_PNG)
+            .ok(),
+        "application/octet-stream" => Some(b"raps-mock".to_vec()),
+        "application/xml" | "text/xml" => {
+            Some(b"<?xml version=\"1.0\" encoding=\"UTF-8\"?><mock/>".to_vec())
+        }
+        _ if media_type.ends_with("+xml") => {
+            Some(b"<?xml version=\"1.0\" encoding=\"UTF-8\"?><mock/>".to_vec())
+        }
+        _ if media_type.starts_with("text/") => Some(b"mock".to_vec()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderMap;
+
+    #[test]
+    fn test_non_json_bodies() {
+        let context = TemplateContext::new("GET", "/thumbnail", None, &HeaderMap::new())
+            .with_path_params([("urn".to_string(), "dXJu".to_string())]);
+
+        let png = placeholder_body("image/png").unwrap();
+        assert_eq!(&png[1..4], b"PNG");
+        assert_eq!(
+            example_body("image/png", Value::String("iVBORw0K".into()), &context).unwrap(),
+            png[..6].to_vec()
+        );
+        assert!(
+            example_body(
+                "application/octet-stream",
+                Value::String("%%".into()),
+                &context
+            )
+            .is_none()
+        );
+        assert_eq!(
+            example_body(
+                "application/xml",
+                Value::String("<urn>{{ path.urn }}</urn>".into()),
+                &context
+            ),
+            Some(b"<urn>dXJu</urn>".to_vec())
+        );
+
+        let content: HashMap<String, MediaType> = serde_json::from_value(serde_json::json!({
+            "application/octet-stream": {},
+            "image/png": {},
+            "image/*": {}
+        }))
+        .unwrap();
+        let names: Vec<&str> = candidates(&content, Some("image/png;q=0.9"))
+            .iter()
+            .map(|(name, _)| *name)
+            .collect();
+        assert_eq!(names, ["image/png", "application/octet-stream"]);
+    }
+}
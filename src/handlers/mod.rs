@@ -3,6 +3,11 @@
 
 pub mod custom;
 pub mod generic;
+pub mod media;
+pub mod prefer;
+pub mod query;
+pub mod synth;
+pub mod validation;
 
-pub use custom::CustomHandlerRegistry;
+pub use custom::{CustomHandlerRegistry, StubBuilder};
 pub use generic::GenericHandler;
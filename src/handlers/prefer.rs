@@ -0,0 +1,66 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+//! Prism-style `Prefer` request header (RFC 7240), e.g. `Prefer: code=404`
+//! or `Prefer: example=emptyList`.
+//!
+//! Unknown or malformed preferences are ignored, as the RFC requires.
+
+use axum::http::{HeaderMap, StatusCode, header::HeaderName};
+
+pub const PREFER_HEADER: HeaderName = HeaderName::from_static("prefer");
+pub const PREFERENCE_APPLIED_HEADER: HeaderName = HeaderName::from_static("preference-applied");
+
+/// Preferences understood by the generic handler
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Preferences {
+    /// `code=404`: answer with the operation's response for this status
+    pub code: Option<StatusCode>,
+    /// `example=emptyList`: answer with the named OpenAPI example
+    pub example: Option<String>,
+}
+
+impl Preferences {
+    /// Collect preferences from every `Prefer` header on the request
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let mut preferences = Self::default();
+        let values = headers
+            .get_all(PREFER_HEADER)
+            .iter()
+            .filter_map(|v| v.to_str().ok());
+        for preference in values.flat_map(|v| v.split([',', ';'])) {
+            let Some((name, value)) = preference.split_once('=') else {
+                continue;
+            };
+            let value = value.trim().trim_matches('"');
+            match name.trim().to_ascii_lowercase().as_str() {
+                "code" => preferences.code = value.parse().ok().or(preferences.code),
+                "example" if !value.is_empty() => preferences.example = Some(value.to_string()),
+                _ => {}
+            }
+        }
+        preferences
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_prefer_code() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            PREFER_HEADER,
+            "respond-async, code=\"409\"; example=conflict"
+                .parse()
+                .unwrap(),
+        );
+        let preferences = Preferences::from_headers(&headers);
+        assert_eq!(preferences.code, Some(StatusCode::CONFLICT));
+        assert_eq!(preferences.example.as_deref(), Some("conflict"));
+
+        headers.insert(PREFER_HEADER, "code=abc".parse().unwrap());
+        assert_eq!(Preferences::from_headers(&headers), Preferences::default());
+    }
+}
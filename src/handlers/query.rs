@@ -0,0 +1,74 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+//! Query strings with repeated and comma-separated parameters.
+//!
+//! APS filters come in both forms: `ids=1&ids=2` and `filter[id]=a,b,c`
+//! (OpenAPI's exploded and non-exploded `form` styles). [`QueryParams::values`]
+//! treats them alike, so validation and stub matching do not care which one
+//! a client sends.
+
+/// Decoded query parameters, in request order
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QueryParams {
+    pairs: Vec<(String, String)>,
+}
+
+impl QueryParams {
+    pub fn parse(query: Option<&str>) -> Self {
+        Self {
+            pairs: form_urlencoded::parse(query.unwrap_or_default().as_bytes())
+                .into_owned()
+                .collect(),
+        }
+    }
+
+    /// Whether the parameter appears at all, even without a value
+    pub fn contains(&self, name: &str) -> bool {
+        self.pairs.iter().any(|(key, _)| key == name)
+    }
+
+    /// The first value of a parameter
+    pub fn first(&self, name: &str) -> Option<&str> {
+        self.all(name).into_iter().next()
+    }
+
+    /// Every value of a repeated parameter, as sent
+    pub fn all(&self, name: &str) -> Vec<&str> {
+        self.pairs
+            .iter()
+            .filter(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+            .collect()
+    }
+
+    /// The values of an array parameter: repeated parameters and
+    /// comma-separated lists combined, empty items dropped
+    pub fn values(&self, name: &str) -> Vec<&str> {
+        self.all(name)
+            .into_iter()
+            .flat_map(|value| value.split(','))
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repeated_and_comma_separated_values() {
+        let query = QueryParams::parse(Some(
+            "ids=1&filter%5Bid%5D=a,b,,c&ids=2,3&page%5Blimit%5D=10&empty",
+        ));
+        assert_eq!(query.values("ids"), ["1", "2", "3"]);
+        assert_eq!(query.values("filter[id]"), ["a", "b", "c"]);
+        assert_eq!(query.all("ids"), ["1", "2,3"]);
+        assert_eq!(query.first("page[limit]"), Some("10"));
+        assert!(query.contains("empty"));
+        assert!(query.values("empty").is_empty());
+        assert!(!query.contains("missing"));
+    }
+}
@@ -0,0 +1,519 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+//! Request validation against OpenAPI operation definitions.
+
+use crate::handlers::query::QueryParams;
+use crate::openapi::types::{Components, Parameter, ParameterLocation, RouteDefinition, Schema};
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Json, Response},
+};
+use serde::Serialize;
+use serde_json::{Value, json};
+
+/// Maximum nesting depth, guards against recursive schemas
+const MAX_DEPTH: usize = 32;
+
+/// A single validation failure
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ValidationError {
+    /// Location of the offending value (e.g. `body.policyKey`)
+    pub field: String,
+    pub message: String,
+}
+
+impl ValidationError {
+    pub fn new(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            field: field.into(),
+            message: message.into(),
+        }
+    }
+}
+
+/// Validates JSON values against OpenAPI schemas
+pub struct SchemaValidator<'a> {
+    components: Option<&'a Components>,
+}
+
+impl<'a> SchemaValidator<'a> {
+    pub fn new(components: Option<&'a Components>) -> Self {
+        Self { components }
+    }
+
+    /// Validate a value, returning every violation found
+    pub fn validate(&self, schema: &Schema, value: &Value, field: &str) -> Vec<ValidationError> {
+        let mut errors = Vec::new();
+        self.validate_inner(schema, value, field, 0, &mut errors);
+        errors
+    }
+
+    fn resolve<'s>(&self, schema: &'s Schema) -> Option<&'s Schema>
+    where
+        'a: 's,
+    {
+        match schema {
+            Schema::Ref { ref_path } => {
+                let name = ref_path.split('/').next_back()?;
+                self.components?.schemas.as_ref()?.get(name)
+            }
+            _ => Some(schema),
+        }
+    }
+
+    fn validate_inner(
+        &self,
+        schema: &Schema,
+        value: &Value,
+        field: &str,
+        depth: usize,
+        errors: &mut Vec<ValidationError>,
+    ) {
+        if depth > MAX_DEPTH {
+            return;
+        }
+        // Unresolvable references are not the client's fault
+        let Some(Schema::Object {
+            type_name,
+            items,
+            properties,
+            required,
+            enum_values,
+            nullable,
+            all_of,
+            one_of,
+            any_of,
+            ..
+        }) = self.resolve(schema)
+        else {
+            return;
+        };
+
+        if value.is_null() {
+            if nullable.unwrap_or(false) || type_name.is_none() {
+                return;
+            }
+            errors.push(ValidationError::new(field, "must not be null"));
+            return;
+        }
+
+        if let Some(expected) = type_name.as_deref()
+            && !matches_type(expected, value)
+        {
+            errors.push(ValidationError::new(
+                field,
+                format!("expected {}, found {}", expected, json_type_name(value)),
+            ));
+            return;
+        }
+
+        if let Some(allowed) = enum_values
+            && !allowed.is_empty()
+            && !allowed.contains(value)
+        {
+            let allowed: Vec<String> = allowed.iter().map(|v| v.to_string()).collect();
+            errors.push(ValidationError::new(
+                field,
+                format!("must be one of [{}]", allowed.join(", ")),
+            ));
+        }
+
+        if let Value::Object(map) = value {
+            for name in required.iter().flatten() {
+                if !map.contains_key(name) {
+                    errors.push(ValidationError::new(join_field(field, name), "is required"));
+                }
+            }
+            for (name, prop_schema) in properties.iter().flatten() {
+                if let Some(prop_value) = map.get(name) {
+                    self.validate_inner(
+                        prop_schema,
+                        prop_value,
+                        &join_field(field, name),
+                        depth + 1,
+                        errors,
+                    );
+                }
+            }
+        }
+
+        if let (Value::Array(values), Some(item_schema)) = (value, items) {
+            for (index, item) in values.iter().enumerate() {
+                self.validate_inner(
+                    item_schema,
+                    item,
+                    &format!("{}[{}]", field, index),
+                    depth + 1,
+                    errors,
+                );
+            }
+        }
+
+        for part in all_of.iter().flatten() {
+            self.validate_inner(part, value, field, depth + 1, errors);
+        }
+
+        // oneOf is checked like anyOf: APS schemas rarely carry discriminators,
+        // so overlapping alternatives would otherwise reject valid payloads
+        for (keyword, alternatives) in [("oneOf", one_of), ("anyOf", any_of)] {
+            let Some(alternatives) = alternatives.as_ref().filter(|a| !a.is_empty()) else {
+                continue;
+            };
+            let matched = alternatives.iter().any(|alternative| {
+                let mut alternative_errors = Vec::new();
+                self.validate_inner(
+                    alternative,
+                    value,
+                    field,
+                    depth + 1,
+                    &mut alternative_errors,
+                );
+                alternative_errors.is_empty()
+            });
+            if !matched {
+                errors.push(ValidationError::new(
+                    field,
+                    format!("does not match any schema in {}", keyword),
+                ));
+            }
+        }
+    }
+}
+
+fn join_field(parent: &str, name: &str) -> String {
+    if parent.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}.{}", parent, name)
+    }
+}
+
+fn matches_type(expected: &str, value: &Value) -> bool {
+    match expected {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "number" => value.is_number(),
+        "boolean" => value.is_boolean(),
+        _ => true,
+    }
+}
+
+fn json_type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_f64() => "number",
+        Value::Number(_) => "integer",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// Validate a request body against the operation's `requestBody` definition
+pub fn validate_body(
+    route: &RouteDefinition,
+    content_type: Option<&str>,
+    body: &[u8],
+) -> Vec<ValidationError> {
+    let Some(request_body) = &route.operation.request_body else {
+        return Vec::new();
+    };
+
+    if body.is_empty() {
+        return if request_body.required.unwrap_or(false) {
+            vec![ValidationError::new("body", "request body is required")]
+        } else {
+            Vec::new()
+        };
+    }
+
+    // Only JSON payloads are validated; other media types pass through
+    let media_type = content_type
+        .and_then(|ct| ct.split(';').next())
+        .map(|ct| ct.trim().to_lowercase())
+        .unwrap_or_else(|| "application/json".to_string());
+    if !media_type.ends_with("json") {
+        return Vec::new();
+    }
+    let Some(schema) = request_body
+        .content
+        .get(&media_type)
+        .or_else(|| request_body.content.get("application/json"))
+        .and_then(|m| m.schema.as_ref())
+    else {
+        return Vec::new();
+    };
+
+    let value: Value = match serde_json::from_slice(body) {
+        Ok(value) => value,
+        Err(e) => {
+            return vec![ValidationError::new(
+                "body",
+                format!("malformed JSON: {}", e),
+            )];
+        }
+    };
+
+    SchemaValidator::new(route.components.as_deref()).validate(schema, &value, "body")
+}
+
+/// Validate required query and path parameters (and their types)
+pub fn validate_parameters(
+    route: &RouteDefinition,
+    request_path: &str,
+    query: Option<&str>,
+) -> Vec<ValidationError> {
+    let Some(parameters) = &route.operation.parameters else {
+        return Vec::new();
+    };
+
+    let query = QueryParams::parse(query);
+    let path_params = route.extract_path_params(request_path);
+    let validator = SchemaValidator::new(route.components.as_deref());
+    let mut errors = Vec::new();
+
+    for parameter in parameters {
+        let Parameter::Definition {
+            name,
+            location,
+            required,
+            schema,
+            ..
+        } = parameter
+        else {
+            continue;
+        };
+
+        let (kind, value) = match location {
+            ParameterLocation::Query => ("query", query.first(name)),
+            ParameterLocation::Path => ("path", path_params.get(name).map(|v| v.as_str())),
+            _ => continue,
+        };
+        let field = format!("{}.{}", kind, name);
+
+        let Some(value) = value else {
+            if required.unwrap_or(false) || matches!(location, ParameterLocation::Path) {
+                errors.push(ValidationError::new(
+                    field,
+                    format!("{} parameter '{}' is required", kind, name),
+                ));
+            }
+            continue;
+        };
+
+        let Some(schema) = schema else {
+            continue;
+        };
+        let typed = match validator.resolve(schema) {
+            // `ids=1&ids=2` or `ids=1,2`
+            Some(Schema::Object {
+                type_name: Some(type_name),
+                items,
+                ..
+            }) if type_name == "array" => {
+                let values = match location {
+                    ParameterLocation::Query => query.values(name),
+                    _ => value.split(',').collect(),
+                };
+                let items = items.as_deref().and_then(|items| validator.resolve(items));
+                Value::Array(
+                    values
+                        .into_iter()
+                        .map(|item| coerce_parameter(items, item))
+                        .collect(),
+                )
+            }
+            resolved => coerce_parameter(resolved, value),
+        };
+        errors.extend(validator.validate(schema, &typed, &field));
+    }
+
+    errors
+}
+
+/// Convert a raw parameter string into the JSON type its schema declares, so
+/// it can be checked with the regular schema validator
+fn coerce_parameter(schema: Option<&Schema>, raw: &str) -> Value {
+    let type_name = match schema {
+        Some(Schema::Object { type_name, .. }) => type_name.as_deref(),
+        _ => None,
+    };
+    match type_name {
+        Some("integer") => raw
+            .parse::<i64>()
+            .map(Value::from)
+            .unwrap_or_else(|_| Value::String(raw.to_string())),
+        Some("number") => raw
+            .parse::<f64>()
+            .map(Value::from)
+            .unwrap_or_else(|_| Value::String(raw.to_string())),
+        Some("boolean") => match raw {
+            "true" => Value::Bool(true),
+            "false" => Value::Bool(false),
+            _ => Value::String(raw.to_string()),
+        },
+        _ => Value::String(raw.to_string()),
+    }
+}
+
+/// APS-style 400 response listing validation failures
+pub fn bad_request_response(errors: &[ValidationError]) -> Response {
+    let summary: Vec<String> = errors
+        .iter()
+        .map(|e| format!("{} {}", e.field, e.message))
+        .collect();
+    (
+        StatusCode::BAD_REQUEST,
+        Json(json!({
+            "developerMessage": format!("Request validation failed: {}", summary.join("; ")),
+            "errorCode": "ERR_BAD_INPUT",
+            "details": errors
+        })),
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::openapi::types::{HttpMethod, Operation};
+
+    fn bucket_schema() -> Schema {
+        serde_yaml::from_str(
+            r#"
+type: object
+required: [bucketKey, policyKey]
+properties:
+  bucketKey:
+    type: string
+  policyKey:
+    type: string
+    enum: [transient, temporary, persistent]
+"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_valid_body_passes() {
+        let body = json!({ "bucketKey": "b1", "policyKey": "transient" });
+        let errors = SchemaValidator::new(None).validate(&bucket_schema(), &body, "body");
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn test_reports_missing_wrong_type_and_enum() {
+        let body = json!({ "bucketKey": 5, "policyKey": "forever" });
+        let errors = SchemaValidator::new(None).validate(&bucket_schema(), &body, "body");
+        let mut fields: Vec<&str> = errors.iter().map(|e| e.field.as_str()).collect();
+        fields.sort();
+        assert_eq!(fields, ["body.bucketKey", "body.policyKey"]);
+
+        let errors = SchemaValidator::new(None).validate(&bucket_schema(), &json!({}), "body");
+        assert_eq!(errors.len(), 2);
+        assert!(errors.iter().all(|e| e.message == "is required"));
+    }
+
+    #[test]
+    fn test_composed_schemas() {
+        let schema: Schema = serde_yaml::from_str(
+            r#"
+allOf:
+  - type: object
+    required: [id]
+  - type: object
+    properties:
+      status:
+        oneOf:
+          - type: string
+            enum: [open, closed]
+          - type: integer
+"#,
+        )
+        .unwrap();
+        let validator = SchemaValidator::new(None);
+        assert!(
+            validator
+                .validate(&schema, &json!({ "id": "1", "status": 2 }), "body")
+                .is_empty()
+        );
+
+        let errors = validator.validate(&schema, &json!({ "status": "draft" }), "body");
+        let fields: Vec<&str> = errors.iter().map(|e| e.field.as_str()).collect();
+        assert_eq!(fields, ["body.id", "body.status"]);
+    }
+
+    #[test]
+    fn test_query_parameters_are_required_and_typed() {
+        let operation: Operation = serde_yaml::from_str(
+            r#"
+parameters:
+  - name: bucketKey
+    in: path
+    required: true
+    schema:
+      type: string
+  - name: limit
+    in: query
+    required: true
+    schema:
+      type: integer
+responses: {}
+"#,
+        )
+        .unwrap();
+        let route = RouteDefinition {
+            method: HttpMethod::Get,
+            path: "/buckets/{bucketKey}/objects".to_string(),
+            operation,
+            path_pattern: "/buckets/:bucket_key/objects".to_string(),
+            components: None,
+            spec: None,
+        };
+
+        let errors = validate_parameters(&route, "/buckets/b1/objects", None);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].field, "query.limit");
+
+        let errors = validate_parameters(&route, "/buckets/b1/objects", Some("limit=ten"));
+        assert_eq!(errors[0].message, "expected integer, found string");
+
+        assert!(validate_parameters(&route, "/buckets/b1/objects", Some("limit=10")).is_empty());
+    }
+
+    #[test]
+    fn test_array_query_parameters_accept_both_forms() {
+        let operation: Operation = serde_yaml::from_str(
+            r#"
+parameters:
+  - name: filter[id]
+    in: query
+    schema:
+      type: array
+      items:
+        type: integer
+responses: {}
+"#,
+        )
+        .unwrap();
+        let route = RouteDefinition {
+            method: HttpMethod::Get,
+            path: "/issues".to_string(),
+            operation,
+            path_pattern: "/issues".to_string(),
+            components: None,
+            spec: None,
+        };
+
+        for query in ["filter[id]=1,2,3", "filter[id]=1&filter[id]=2,3"] {
+            assert!(validate_parameters(&route, "/issues", Some(query)).is_empty());
+        }
+        let errors = validate_parameters(&route, "/issues", Some("filter[id]=1&filter[id]=x"));
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].field, "query.filter[id][1]");
+    }
+}
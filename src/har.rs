@@ -0,0 +1,448 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+//! HTTP Archive (HAR 1.2) support.
+//!
+//! The request journal exports as a HAR log ([`Har::from_journal`]), served
+//! at `/__admin/requests/har` and written to `--har-out` on shutdown, so the
+//! traffic of a test run opens in browser devtools or can be archived.
+//! Bodies appear as far as the journal recorded them.
+//!
+//! HAR captures of real APS traffic are served back as stubs (`--har-in`,
+//! [`Har::to_stubs`]): every entry answers requests with exactly its method,
+//! path and query, ahead of the OpenAPI and built-in routes, so a production
+//! incident can be replayed locally. A request captured several times is
+//! answered with its first response.
+
+use crate::error::{MockError, Result};
+use crate::state::journal::JournalEntry;
+use crate::stubs::{StubMapping, StubRequest, StubResponse};
+use axum::http::StatusCode;
+use chrono::{TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Host requests without a `Host` header are attributed to
+const DEFAULT_HOST: &str = "localhost";
+
+/// Response headers that described the captured transfer rather than the
+/// response, left out of stubs
+pub(crate) const TRANSFER_HEADERS: [&str; 4] = [
+    "content-length",
+    "content-encoding",
+    "transfer-encoding",
+    "connection",
+];
+
+/// A HAR file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Har {
+    pub log: HarLog,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HarLog {
+    pub version: String,
+    pub creator: HarCreator,
+    #[serde(default)]
+    pub entries: Vec<HarEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HarCreator {
+    pub name: String,
+    pub version: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HarEntry {
+    /// ISO 8601 time the request started
+    pub started_date_time: String,
+    /// Total time, in milliseconds
+    pub time: f64,
+    pub request: HarRequest,
+    pub response: HarResponse,
+    #[serde(default)]
+    pub cache: serde_json::Value,
+    #[serde(default)]
+    pub timings: HarTimings,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HarRequest {
+    pub method: String,
+    /// Absolute URL, query included
+    pub url: String,
+    #[serde(default = "http_version")]
+    pub http_version: String,
+    #[serde(default)]
+    pub cookies: Vec<serde_json::Value>,
+    #[serde(default)]
+    pub headers: Vec<HarHeader>,
+    #[serde(default)]
+    pub query_string: Vec<HarHeader>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post_data: Option<HarPostData>,
+    #[serde(default = "unknown_size")]
+    pub headers_size: i64,
+    #[serde(default = "unknown_size")]
+    pub body_size: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HarResponse {
+    pub status: u16,
+    #[serde(default)]
+    pub status_text: String,
+    #[serde(default = "http_version")]
+    pub http_version: String,
+    #[serde(default)]
+    pub cookies: Vec<serde_json::Value>,
+    #[serde(default)]
+    pub headers: Vec<HarHeader>,
+    pub content: HarContent,
+    #[serde(default, rename = "redirectURL")]
+    pub redirect_url: String,
+    #[serde(default = "unknown_size")]
+    pub headers_size: i64,
+    #[serde(default = "unknown_size")]
+    pub body_size: i64,
+}
+
+/// A header or query parameter
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HarHeader {
+    pub name: String,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HarPostData {
+    #[serde(default)]
+    pub mime_type: String,
+    #[serde(default)]
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HarContent {
+    #[serde(default)]
+    pub size: i64,
+    #[serde(default)]
+    pub mime_type: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    /// `base64` when `text` is encoded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<String>,
+}
+
+/// Phases of an entry's time, in milliseconds (-1: not applicable)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HarTimings {
+    pub send: f64,
+    pub wait: f64,
+    pub receive: f64,
+}
+
+fn http_version() -> String {
+    "HTTP/1.1".to_string()
+}
+
+fn unknown_size() -> i64 {
+    -1
+}
+
+impl Har {
+    /// The journal's requests and responses as a HAR log, oldest first
+    pub fn from_journal(entries: &[JournalEntry]) -> Self {
+        Self {
+            log: HarLog {
+                version: "1.2".to_string(),
+                creator: HarCreator {
+                    name: env!("CARGO_PKG_NAME").to_string(),
+                    version: env!("CARGO_PKG_VERSION").to_string(),
+                },
+                entries: entries.iter().map(har_entry).collect(),
+            },
+        }
+    }
+
+    /// Read a HAR file
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+        serde_json::from_str(&content)
+            .map_err(|e| MockError::Config(format!("Invalid HAR file {}: {}", path.display(), e)))
+    }
+
+    /// Turn every entry into a stub answering its exact request with the
+    /// captured response
+    pub fn to_stubs(&self) -> Vec<StubMapping> {
+        self.log
+            .entries
+            .iter()
+            .map(|entry| {
+                let content = &entry.response.content;
+                let (body, base64_body) = match content.encoding.as_deref() {
+                    Some("base64") => (None, content.text.clone()),
+                    _ => (content.text.clone(), None),
+                };
+                StubMapping {
+                    id: None,
+                    name: Some(format!(
+                        "{} {} (HAR {})",
+                        entry.request.method, entry.request.url, entry.started_date_time
+                    )),
+                    priority: None,
+                    scenario: None,
+                    request: StubRequest {
+                        method: Some(entry.request.method.to_uppercase()),
+                        url: Some(path_and_query(&entry.request.url).to_string()),
+                        ..StubRequest::default()
+                    },
+                    response: StubResponse {
+                        status: entry.response.status,
+                        headers: entry
+                            .response
+                            .headers
+                            .iter()
+                            .filter(|h| {
+                                !TRANSFER_HEADERS
+                                    .iter()
+                                    .any(|t| h.name.eq_ignore_ascii_case(t))
+                            })
+                            .map(|h| (h.name.to_lowercase(), h.value.clone()))
+                            .collect(),
+                        body,
+                        base64_body,
+                        ..StubResponse::default()
+                    },
+                }
+            })
+            .collect()
+    }
+
+    /// Write the HAR log to `path` as JSON
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| MockError::Config(format!("Cannot serialize HAR log: {}", e)))?;
+        std::fs::write(path, json)?;
+        Ok(())
+    }
+}
+
+/// The path and query of an absolute URL, e.g. `/oss/v2/buckets?limit=10`
+/// of `https://developer.api.autodesk.com/oss/v2/buckets?limit=10`
+fn path_and_query(url: &str) -> &str {
+    let Some((_, rest)) = url.split_once("://") else {
+        return url;
+    };
+    let url = rest.find('/').map_or("/", |start| &rest[start..]);
+    // Fragments are never sent
+    url.split('#').next().unwrap_or(url)
+}
+
+fn har_entry(entry: &JournalEntry) -> HarEntry {
+    let host = entry
+        .headers
+        .get("host")
+        .map(String::as_str)
+        .unwrap_or(DEFAULT_HOST);
+    let url = match entry.query {
+        Some(ref query) => format!("http://{}{}?{}", host, entry.path, query),
+        None => format!("http://{}{}", host, entry.path),
+    };
+    let query_string = entry
+        .query
+        .as_deref()
+        .map(|query| {
+            form_urlencoded::parse(query.as_bytes())
+                .map(|(name, value)| HarHeader {
+                    name: name.into_owned(),
+                    value: value.into_owned(),
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    let started = Utc
+        .timestamp_millis_opt(entry.timestamp)
+        .single()
+        .unwrap_or_default();
+
+    HarEntry {
+        started_date_time: started.to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+        time: entry.duration_ms as f64,
+        request: HarRequest {
+            method: entry.method.clone(),
+            url,
+            http_version: http_version(),
+            cookies: Vec::new(),
+            headers: har_headers(&entry.headers),
+            query_string,
+            post_data: entry.body.as_ref().map(|text| HarPostData {
+                mime_type: content_type(&entry.headers),
+                text: text.clone(),
+            }),
+            headers_size: -1,
+            body_size: body_size(&entry.headers, entry.body.as_deref()),
+        },
+        response: HarResponse {
+            status: entry.status,
+            status_text: StatusCode::from_u16(entry.status)
+                .ok()
+                .and_then(|status| status.canonical_reason())
+                .unwrap_or_default()
+                .to_string(),
+            http_version: http_version(),
+            cookies: Vec::new(),
+            headers: har_headers(&entry.response_headers),
+            content: HarContent {
+                size: entry.response_body.as_ref().map_or(-1, |b| b.len() as i64),
+                mime_type: content_type(&entry.response_headers),
+                text: entry.response_body.clone(),
+                encoding: None,
+            },
+            redirect_url: entry
+                .response_headers
+                .get("location")
+                .cloned()
+                .unwrap_or_default(),
+            headers_size: -1,
+            body_size: body_size(&entry.response_headers, entry.response_body.as_deref()),
+        },
+        cache: serde_json::json!({}),
+        timings: HarTimings {
+            send: 0.0,
+            wait: entry.duration_ms as f64,
+            receive: 0.0,
+        },
+    }
+}
+
+fn har_headers(headers: &BTreeMap<String, String>) -> Vec<HarHeader> {
+    headers
+        .iter()
+        .map(|(name, value)| HarHeader {
+            name: name.clone(),
+            value: value.clone(),
+        })
+        .collect()
+}
+
+fn content_type(headers: &BTreeMap<String, String>) -> String {
+    headers.get("content-type").cloned().unwrap_or_default()
+}
+
+/// Size of a body: as recorded, as declared, or -1 when unknown
+fn body_size(headers: &BTreeMap<String, String>, body: Option<&str>) -> i64 {
+    match body {
+        Some(body) => body.len() as i64,
+        None => headers
+            .get("content-length")
+            .and_then(|length| length.parse().ok())
+            .unwrap_or(-1),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_journal_exports_as_har() {
+        let entry = JournalEntry {
+            id: "1".to_string(),
+            timestamp: 1_700_000_000_000,
+            method: "POST".to_string(),
+            path: "/oss/v2/buckets".to_string(),
+            query: Some("region=EMEA&x=a%20b".to_string()),
+            headers: BTreeMap::from([
+                ("host".to_string(), "localhost:3000".to_string()),
+                ("content-type".to_string(), "application/json".to_string()),
+            ]),
+            body: Some(r#"{"bucketKey":"b1"}"#.to_string()),
+            status: 200,
+            response_headers: BTreeMap::from([(
+                "content-type".to_string(),
+                "application/json".to_string(),
+            )]),
+            response_body: Some(r#"{"bucketKey":"b1"}"#.to_string()),
+            duration_ms: 12,
+        };
+        let har = serde_json::to_value(Har::from_journal(&[entry])).unwrap();
+        assert_eq!(har["log"]["version"], "1.2");
+        let entry = &har["log"]["entries"][0];
+        assert_eq!(entry["startedDateTime"], "2023-11-14T22:13:20.000Z");
+        assert_eq!(
+            entry["request"]["url"],
+            "http://localhost:3000/oss/v2/buckets?region=EMEA&x=a%20b"
+        );
+        assert_eq!(entry["request"]["queryString"][1]["value"], "a b");
+        assert_eq!(entry["request"]["postData"]["mimeType"], "application/json");
+        assert_eq!(entry["response"]["statusText"], "OK");
+        assert_eq!(entry["response"]["content"]["size"], 18);
+        assert_eq!(entry["response"]["redirectURL"], "");
+    }
+
+    #[test]
+    fn test_har_entries_become_exact_stubs() {
+        let har: Har = serde_json::from_value(serde_json::json!({
+            "log": {
+                "version": "1.2",
+                "creator": { "name": "devtools", "version": "1" },
+                "entries": [{
+                    "startedDateTime": "2024-05-01T10:00:00.000Z",
+                    "time": 120.5,
+                    "request": {
+                        "method": "get",
+                        "url": "https://developer.api.autodesk.com/oss/v2/buckets?limit=1#top"
+                    },
+                    "response": {
+                        "status": 404,
+                        "headers": [
+                            { "name": "Content-Type", "value": "application/json" },
+                            { "name": "Content-Encoding", "value": "gzip" }
+                        ],
+                        "content": {
+                            "mimeType": "application/json",
+                            "text": "{\"reason\":\"Bucket not found\"}"
+                        }
+                    }
+                }, {
+                    "startedDateTime": "2024-05-01T10:00:01.000Z",
+                    "time": 3,
+                    "request": { "method": "GET", "url": "https://example.com" },
+                    "response": {
+                        "status": 200,
+                        "content": { "size": 3, "text": "AAEC", "encoding": "base64" }
+                    }
+                }]
+            }
+        }))
+        .unwrap();
+
+        let stubs = har.to_stubs();
+        assert_eq!(stubs[0].request.method.as_deref(), Some("GET"));
+        assert_eq!(
+            stubs[0].request.url.as_deref(),
+            Some("/oss/v2/buckets?limit=1")
+        );
+        assert_eq!(stubs[0].response.status, 404);
+        assert_eq!(
+            stubs[0].response.headers,
+            BTreeMap::from([("content-type".to_string(), "application/json".to_string())])
+        );
+        assert_eq!(
+            stubs[0].response.body.as_deref(),
+            Some(r#"{"reason":"Bucket not found"}"#)
+        );
+        assert_eq!(stubs[1].request.url.as_deref(), Some("/"));
+        assert_eq!(stubs[1].response.base64_body.as_deref(), Some("AAEC"));
+    }
+}
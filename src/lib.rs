@@ -6,14 +6,26 @@
 //! This library provides a mock server that can automatically generate routes
 //! from OpenAPI 3.0 specifications and serve mock responses.
 
+pub mod admin;
+pub mod artifacts;
 pub mod config;
 pub mod error;
 pub mod handlers;
+pub mod har;
+pub mod metrics;
 pub mod middleware;
 pub mod openapi;
+pub mod pact;
+pub mod pattern;
+pub mod postman;
+pub mod recordings;
 pub mod server;
 pub mod state;
+pub mod stubs;
+pub mod templating;
 pub mod testing;
+pub mod token;
+pub mod webhooks;
 
 pub use config::{MockMode, MockServerConfig};
 pub use error::{MockError, Result};
@@ -1,7 +1,15 @@
 // SPDX-License-Identifier: Apache-2.0
 // Copyright 2024-2025 Dmytro Yemelianov
 
-use clap::Parser;
+use clap::parser::ValueSource;
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand};
+use raps_mock::config::file::ConfigFile;
+use raps_mock::config::{
+    BlobStoreSpec, ConcurrencyLimit, LatencyRule, RateLimit, ReplayMatch, ResponseHeaderRule,
+    Service, StateBackendSpec, parse_service_mode,
+};
+use raps_mock::openapi::filter::SpecGlob;
+use raps_mock::server::catalog::RouteCatalog;
 use raps_mock::{MockMode, MockServer, MockServerConfig};
 use std::path::PathBuf;
 use tracing::{Level, info};
@@ -11,63 +19,707 @@ use tracing::{Level, info};
 #[command(about = "Mock server for Autodesk Platform Services (APS) APIs")]
 #[command(version)]
 struct Cli {
+    /// Configuration file (TOML or YAML). Defaults to raps-mock.toml,
+    /// raps-mock.yaml or raps-mock.yml in the working directory, if present
+    #[arg(long, value_name = "FILE", env = "RAPS_MOCK_CONFIG", global = true)]
+    config: Option<PathBuf>,
+
     /// Server port
-    #[arg(short, long, default_value = "3000")]
+    #[arg(short, long, default_value = "3000", env = "RAPS_MOCK_PORT")]
     port: u16,
 
     /// Server host
-    #[arg(short = 'H', long, default_value = "0.0.0.0")]
+    #[arg(short = 'H', long, default_value = "0.0.0.0", env = "RAPS_MOCK_HOST")]
     host: String,
 
     /// Operation mode: stateless or stateful
-    #[arg(short, long, default_value = "stateful")]
+    #[arg(
+        short,
+        long,
+        default_value = "stateful",
+        global = true,
+        env = "RAPS_MOCK_MODE"
+    )]
     mode: MockMode,
 
-    /// Path to OpenAPI specifications directory
-    #[arg(long, default_value = "../aps-sdk-openapi")]
-    openapi_dir: PathBuf,
+    /// Override the mode for one service, e.g. 'oss=stateful' or 'issues=stateless'.
+    /// Services: authentication, oss, data-management, model-derivative, issues,
+    /// account-admin, webhooks, data-connector. Repeatable.
+    #[arg(long = "service-mode", value_name = "SERVICE=MODE", value_parser = parse_service_mode, global = true, env = "RAPS_MOCK_SERVICE_MODE")]
+    service_modes: Vec<(Service, MockMode)>,
 
-    /// Path to state persistence file (optional)
-    #[arg(long)]
+    /// Path to OpenAPI specifications directory. Repeatable; specs from all
+    /// directories are loaded
+    #[arg(
+        long = "openapi-dir",
+        default_value = "../aps-sdk-openapi",
+        env = "RAPS_MOCK_OPENAPI_DIR",
+        global = true
+    )]
+    openapi_dirs: Vec<PathBuf>,
+
+    /// Only load spec files whose path (relative to its directory) matches
+    /// this glob, e.g. 'oss/**' or 'model-derivative'. Repeatable
+    #[arg(
+        long = "include",
+        value_name = "GLOB",
+        global = true,
+        env = "RAPS_MOCK_INCLUDE"
+    )]
+    spec_include: Vec<SpecGlob>,
+
+    /// Skip spec files matching this glob, e.g. '**/*-beta.yaml'. Repeatable
+    #[arg(
+        long = "exclude",
+        value_name = "GLOB",
+        global = true,
+        env = "RAPS_MOCK_EXCLUDE"
+    )]
+    spec_exclude: Vec<SpecGlob>,
+
+    /// Cache parsed specs in this directory; later startups only re-parse
+    /// spec files that changed
+    #[arg(long, value_name = "DIR", global = true, env = "RAPS_MOCK_SPEC_CACHE")]
+    spec_cache: Option<PathBuf>,
+
+    /// JSON file the state is loaded from on startup and saved to on shutdown
+    #[arg(long, env = "RAPS_MOCK_STATE_FILE")]
     state_file: Option<PathBuf>,
 
+    /// Keep the state in `file:PATH` (like --state-file) or in a SQLite
+    /// database, `sqlite:PATH`
+    #[arg(
+        long,
+        value_name = "KIND:PATH",
+        env = "RAPS_MOCK_STATE_BACKEND",
+        conflicts_with = "state_file"
+    )]
+    state_backend: Option<StateBackendSpec>,
+
+    /// Also save the state to --state-file or --state-backend every SECS
+    /// seconds
+    #[arg(long, value_name = "SECS", env = "RAPS_MOCK_STATE_AUTOSAVE_SECS")]
+    state_autosave_secs: Option<u64>,
+
+    /// Check token expiry against a clock SECS ahead of the one tokens are
+    /// issued with (negative: behind), to reproduce clock drift between
+    /// client and server
+    #[arg(
+        long,
+        value_name = "SECS",
+        env = "RAPS_MOCK_CLOCK_SKEW_SECS",
+        allow_negative_numbers = true
+    )]
+    clock_skew_secs: Option<i64>,
+
+    /// Lifetime of the tokens the token endpoint issues, in seconds
+    /// (default: 3600)
+    #[arg(long, value_name = "SECS", env = "RAPS_MOCK_TOKEN_EXPIRES_IN")]
+    token_expires_in: Option<u64>,
+
+    /// Record state mutations in an audit log keeping the last ENTRIES,
+    /// served under /__admin/audit
+    #[arg(long, value_name = "ENTRIES", env = "RAPS_MOCK_AUDIT_LOG")]
+    audit_log: Option<usize>,
+
+    /// Size of the placeholder files Model Derivative derivative downloads
+    /// serve, in bytes
+    #[arg(long, value_name = "BYTES", env = "RAPS_MOCK_DERIVATIVE_SIZE")]
+    derivative_size: Option<u64>,
+
+    /// Try webhook deliveries the callback refuses up to ATTEMPTS times in
+    /// all before dead-lettering them
+    #[arg(long, value_name = "ATTEMPTS", env = "RAPS_MOCK_WEBHOOK_ATTEMPTS")]
+    webhook_attempts: Option<u32>,
+
+    /// Wait MS before retrying a refused webhook delivery, twice as long
+    /// before each further retry
+    #[arg(long, value_name = "MS", env = "RAPS_MOCK_WEBHOOK_BACKOFF_MS")]
+    webhook_backoff_ms: Option<u64>,
+
+    /// Keep object contents and other blobs in `memory` or in files under
+    /// `disk:DIR`
+    #[arg(long, value_name = "STORE", env = "RAPS_MOCK_BLOB_STORE")]
+    blob_store: Option<BlobStoreSpec>,
+
+    /// Evict the least recently used blobs once they exceed BYTES in total
+    #[arg(long, value_name = "BYTES", env = "RAPS_MOCK_BLOB_STORE_MAX_BYTES")]
+    blob_store_max_bytes: Option<u64>,
+
     /// Enable verbose logging
-    #[arg(short, long)]
+    #[arg(short, long, global = true, env = "RAPS_MOCK_VERBOSE")]
     verbose: bool,
+
+    /// Seed for synthesized response data (reproducible IDs, dates, strings)
+    #[arg(long, env = "RAPS_MOCK_SEED")]
+    seed: Option<u64>,
+
+    /// Derive generated IDs, timestamps and data from a hash of the request,
+    /// so identical requests get byte-identical responses
+    #[arg(long, env = "RAPS_MOCK_DETERMINISTIC")]
+    deterministic: bool,
+
+    /// Validate requests against the OpenAPI specs and reject invalid ones with 400
+    #[arg(long, env = "RAPS_MOCK_VALIDATE")]
+    validate: bool,
+
+    /// Stubs directory with `mappings/` and `__files/` (stubs take priority over specs)
+    #[arg(long, env = "RAPS_MOCK_STUBS_DIR")]
+    stubs_dir: Option<PathBuf>,
+
+    /// Pact contract file whose interactions are served as stubs. Repeatable.
+    #[arg(long = "pact", value_name = "FILE", env = "RAPS_MOCK_PACT")]
+    pact_files: Vec<PathBuf>,
+
+    /// HAR capture whose request/response pairs are served as exact-match
+    /// stubs (taking priority over specs). Repeatable.
+    #[arg(long = "har-in", value_name = "FILE", env = "RAPS_MOCK_HAR_IN")]
+    har_files: Vec<PathBuf>,
+
+    /// Postman collection whose saved example responses are served as stubs
+    /// (taking priority over specs). Repeatable.
+    #[arg(long = "postman", value_name = "FILE", env = "RAPS_MOCK_POSTMAN")]
+    postman_files: Vec<PathBuf>,
+
+    /// Verify a Pact contract against the mock instead of serving, exiting
+    /// non-zero if any interaction does not match. Repeatable.
+    #[arg(long = "verify-pact", value_name = "FILE")]
+    verify_pacts: Vec<PathBuf>,
+
+    /// Diff the mock against responses recorded from the real APIs in this
+    /// directory and exit instead of serving, non-zero if any diverge
+    #[arg(long, value_name = "DIR")]
+    diff_recordings: Option<PathBuf>,
+
+    /// Header to add to responses: 'Name: value', or '[METHOD] /route/pattern=Name: value'
+    /// to scope it (`*` matches a segment, `**` any depth). Repeatable.
+    #[arg(
+        long = "response-header",
+        value_name = "HEADER",
+        env = "RAPS_MOCK_RESPONSE_HEADER"
+    )]
+    response_headers: Vec<ResponseHeaderRule>,
+
+    /// Honour X-Mock-Delay-Ms, X-Mock-Status and X-Mock-Scenario request headers
+    #[arg(long, env = "RAPS_MOCK_CONTROL_HEADERS")]
+    control_headers: bool,
+
+    /// Cap the requests routes matching a pattern serve at once:
+    /// '[METHOD] /route/pattern=MAX' answers excess requests with 429,
+    /// '...=MAX:queue' holds them until a slot frees. Repeatable.
+    #[arg(
+        long = "max-in-flight",
+        value_name = "LIMIT",
+        env = "RAPS_MOCK_MAX_IN_FLIGHT"
+    )]
+    concurrency_limits: Vec<ConcurrencyLimit>,
+
+    /// Limit the requests each client makes to routes matching a pattern:
+    /// '[METHOD] /route/pattern=COUNT/sec|min|hour' answers requests over
+    /// the budget with 429 and Retry-After. Repeatable.
+    #[arg(
+        long = "rate-limit",
+        value_name = "LIMIT",
+        env = "RAPS_MOCK_RATE_LIMIT"
+    )]
+    rate_limits: Vec<RateLimit>,
+
+    /// Delay responses: 'MS', 'MIN..MAX' (uniform) or 'normal:MEAN,STDDEV',
+    /// optionally for routes matching '[METHOD] /route/pattern=' only.
+    /// Repeatable.
+    #[arg(long, value_name = "LATENCY", env = "RAPS_MOCK_LATENCY")]
+    latency: Vec<LatencyRule>,
+
+    /// Persist the request journal to this ndjson file and reload it on startup
+    #[arg(long, env = "RAPS_MOCK_JOURNAL_FILE")]
+    journal_file: Option<PathBuf>,
+
+    /// Write the request journal to this file as a HAR 1.2 log on shutdown
+    #[arg(long, value_name = "FILE", env = "RAPS_MOCK_HAR_OUT")]
+    har_out: Option<PathBuf>,
+
+    /// Forward requests no stub or built-in stateful handler serves to this
+    /// base URL, e.g. https://developer.api.autodesk.com, with their
+    /// Authorization header, and relay the responses
+    #[arg(long, value_name = "URL", env = "RAPS_MOCK_PROXY_BASE")]
+    proxy_base: Option<String>,
+
+    /// Record every exchange forwarded to --proxy-base into this directory
+    /// as stubs (secrets redacted), for later use with --stubs-dir
+    #[arg(long = "record", value_name = "DIR", env = "RAPS_MOCK_RECORD")]
+    record_dir: Option<PathBuf>,
+
+    /// Answer the requests --proxy-base would forward from the recordings
+    /// in this directory instead, with 501 for unrecorded ones
+    #[arg(long = "replay", value_name = "DIR", env = "RAPS_MOCK_REPLAY")]
+    replay_dir: Option<PathBuf>,
+
+    /// How --replay matches requests: 'exact' (method, path and query) or
+    /// 'fuzzy' (falling back to the closest query of the same path)
+    #[arg(long, default_value = "fuzzy", env = "RAPS_MOCK_REPLAY_MATCH")]
+    replay_match: ReplayMatch,
+
+    /// Partition buckets, hubs, issues, hooks and jobs by the client ID of
+    /// the Bearer token, so apps only see their own resources
+    #[arg(long, env = "RAPS_MOCK_ISOLATE_CLIENTS")]
+    isolate_clients: bool,
+
+    /// YAML/JSON template (folders, models, issues) for projects created via the admin API
+    #[arg(long, env = "RAPS_MOCK_PROJECT_TEMPLATE")]
+    project_template: Option<PathBuf>,
+
+    /// Directory of YAML/JSON fixtures (buckets, objects, hubs, projects, issues,
+    /// hooks) loaded into the state at startup
+    #[arg(long, value_name = "DIR", env = "RAPS_MOCK_SEED_DIR")]
+    seed_dir: Option<PathBuf>,
+
+    /// Print every registered route at startup
+    #[arg(long)]
+    print_routes: bool,
+
+    /// Parse the specs, build the routes, load the state and seed fixtures,
+    /// print the resulting plan as JSON and exit without binding the port
+    #[arg(long)]
+    dry_run: bool,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// List every registered route with its method, source (openapi,
+    /// builtin or admin), spec or route pack and operationId, then exit
+    Routes,
+
+    /// Mint an access token and print it, for use with curl or Postman
+    Token {
+        /// Client ID the token is issued to
+        #[arg(long, default_value = "raps-mock-cli")]
+        client_id: String,
+
+        /// Scope to request, e.g. 'data:read'. Repeatable; defaults to every
+        /// scope the mock checks
+        #[arg(long = "scope", value_name = "SCOPE")]
+        scopes: Vec<String>,
+
+        /// URL of the running mock to mint the token with
+        #[arg(long, default_value = "http://localhost:3000")]
+        url: String,
+
+        /// Generate the token locally instead of asking a running mock. Only
+        /// accepted where authentication is stateless
+        #[arg(long)]
+        offline: bool,
+
+        /// Print the whole token response as JSON instead of the bare token
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Convert a WireMock root directory (`mappings/` and `__files/`) into a
+    /// stubs directory for --stubs-dir, reporting the mappings that could
+    /// not be converted, then exit
+    ImportWiremock {
+        /// WireMock root directory
+        #[arg(value_name = "DIR")]
+        dir: PathBuf,
+
+        /// Stubs directory to write
+        #[arg(long, value_name = "DIR")]
+        out: PathBuf,
+    },
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let cli = Cli::parse();
+    let matches = Cli::command().get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    // Flags and RAPS_MOCK_* variables override the configuration file
+    let given = |id: &str| {
+        matches!(
+            matches.value_source(id),
+            Some(ValueSource::CommandLine | ValueSource::EnvVariable)
+        )
+    };
+
+    let config_path = match cli.config {
+        Some(ref path) => Some(path.clone()),
+        None => ConfigFile::discover(&std::env::current_dir()?),
+    };
+    let mut config = MockServerConfig::default();
+    if let Some(ref path) = config_path {
+        ConfigFile::load(path)?.apply(&mut config);
+    }
+    if given("mode") {
+        config.mode = cli.mode;
+    }
+    config.service_modes.extend(cli.service_modes);
+    if given("openapi_dirs") {
+        let mut openapi_dirs = cli.openapi_dirs.into_iter();
+        config.openapi_dir = openapi_dirs.next().unwrap_or_default();
+        config.extra_openapi_dirs = openapi_dirs.collect();
+    }
+    if given("spec_include") {
+        config.spec_include = cli.spec_include;
+    }
+    if given("spec_exclude") {
+        config.spec_exclude = cli.spec_exclude;
+    }
+    if given("spec_cache") {
+        config.spec_cache_dir = cli.spec_cache;
+    }
+    if given("state_file") {
+        config.state_file = cli.state_file;
+    }
+    if given("state_backend") {
+        config.state_backend = cli.state_backend;
+    }
+    if given("verbose") {
+        config.verbose = cli.verbose;
+    }
+    if given("host") {
+        config.host = cli.host;
+    }
+    if given("port") {
+        config.port = cli.port;
+    }
+    if given("seed") {
+        config.seed = cli.seed;
+    }
+    if given("validate") {
+        config.validate_requests = cli.validate;
+    }
+    if given("deterministic") {
+        config.deterministic = cli.deterministic;
+    }
+    if given("stubs_dir") {
+        config.stubs_dir = cli.stubs_dir;
+    }
+    if given("pact_files") {
+        config.pact_files = cli.pact_files;
+    }
+    if given("har_files") {
+        config.har_files = cli.har_files;
+    }
+    if given("postman_files") {
+        config.postman_files = cli.postman_files;
+    }
+    if given("response_headers") {
+        config.response_headers = cli.response_headers;
+    }
+    if given("control_headers") {
+        config.control_headers = cli.control_headers;
+    }
+    if given("concurrency_limits") {
+        config.concurrency_limits = cli.concurrency_limits;
+    }
+    if given("rate_limits") {
+        config.rate_limits = cli.rate_limits;
+    }
+    if given("latency") {
+        config.latency = cli.latency;
+    }
+    if given("clock_skew_secs")
+        && let Some(secs) = cli.clock_skew_secs
+    {
+        config.clock_skew_secs = secs;
+    }
+    if given("token_expires_in")
+        && let Some(secs) = cli.token_expires_in
+    {
+        config.token_expires_in = secs;
+    }
+    if given("audit_log")
+        && let Some(entries) = cli.audit_log
+    {
+        config.audit_log = entries;
+    }
+    if given("derivative_size")
+        && let Some(bytes) = cli.derivative_size
+    {
+        config.derivative_size = bytes;
+    }
+    if given("webhook_attempts")
+        && let Some(attempts) = cli.webhook_attempts
+    {
+        config.webhook_attempts = attempts;
+    }
+    if given("webhook_backoff_ms")
+        && let Some(ms) = cli.webhook_backoff_ms
+    {
+        config.webhook_backoff_ms = ms;
+    }
+    if given("state_autosave_secs") {
+        config.state_autosave_secs = cli.state_autosave_secs;
+    }
+    if given("blob_store")
+        && let Some(store) = cli.blob_store
+    {
+        config.blob_store = store;
+    }
+    if given("blob_store_max_bytes") {
+        config.blob_store_max_bytes = cli.blob_store_max_bytes;
+    }
+    if given("journal_file") {
+        config.journal_file = cli.journal_file;
+    }
+    if given("har_out") {
+        config.har_out = cli.har_out;
+    }
+    if given("proxy_base") {
+        config.proxy_base = cli.proxy_base;
+    }
+    if given("record_dir") {
+        config.record_dir = cli.record_dir;
+    }
+    if given("replay_dir") {
+        config.replay_dir = cli.replay_dir;
+    }
+    if given("replay_match") {
+        config.replay_match = cli.replay_match;
+    }
+    if given("isolate_clients") {
+        config.isolate_clients = cli.isolate_clients;
+    }
+    if given("project_template") {
+        config.project_template = cli.project_template;
+    }
+    if given("seed_dir") {
+        config.seed_dir = cli.seed_dir;
+    }
 
     // Initialize tracing
-    let level = if cli.verbose {
+    let level = if config.verbose {
         Level::DEBUG
     } else {
         Level::INFO
     };
 
-    tracing_subscriber::fmt()
+    let subscriber = tracing_subscriber::fmt()
         .with_max_level(level)
-        .with_target(false)
-        .init();
+        .with_target(false);
+    // Subcommands and dry runs print their results on stdout
+    if cli.command.is_some() || cli.dry_run {
+        subscriber.with_writer(std::io::stderr).init();
+    } else {
+        subscriber.init();
+    }
+
+    if let Some(Command::Token {
+        client_id,
+        scopes,
+        url,
+        offline,
+        json,
+    }) = &cli.command
+    {
+        let scope = if scopes.is_empty() {
+            raps_mock::pact::VERIFIER_SCOPES.to_string()
+        } else {
+            scopes.join(" ")
+        };
+        let token = if *offline {
+            raps_mock::token::generate(client_id, Some(&scope))
+        } else {
+            raps_mock::token::fetch(url, client_id, Some(&scope)).await?
+        };
+        if *json {
+            println!("{}", serde_json::to_string_pretty(&token)?);
+        } else {
+            println!("{}", token["access_token"].as_str().unwrap_or_default());
+        }
+        return Ok(());
+    }
+
+    if let Some(Command::ImportWiremock { dir, out }) = &cli.command {
+        let report = raps_mock::stubs::wiremock::import(dir, out)?;
+        for skipped in &report.skipped {
+            println!("skipped {}", skipped);
+        }
+        for warning in &report.warnings {
+            println!("warning {}", warning);
+        }
+        println!(
+            "Imported {} mappings into {} ({} skipped)",
+            report.imported,
+            out.display(),
+            report.skipped.len()
+        );
+        return Ok(());
+    }
 
     info!("Starting raps-mock server");
-    info!("Mode: {:?}", cli.mode);
-    info!("OpenAPI directory: {}", cli.openapi_dir.display());
-
-    let config = MockServerConfig {
-        mode: cli.mode,
-        openapi_dir: cli.openapi_dir,
-        state_file: cli.state_file,
-        verbose: cli.verbose,
-        host: cli.host.clone(),
-        port: cli.port,
-    };
+    if let Some(ref path) = config_path {
+        info!("Configuration file: {}", path.display());
+    }
+    info!("Mode: {:?}", config.mode);
+    for (service, mode) in &config.service_modes {
+        info!("Mode for {}: {:?}", service, mode);
+    }
+    for dir in config.openapi_dirs() {
+        info!("OpenAPI directory: {}", dir.display());
+    }
+    for glob in &config.spec_include {
+        info!("Including specs matching {}", glob);
+    }
+    for glob in &config.spec_exclude {
+        info!("Excluding specs matching {}", glob);
+    }
+    if let Some(ref dir) = config.spec_cache_dir {
+        info!("Spec cache: {}", dir.display());
+    }
 
+    let addr = format!("{}:{}", config.host, config.port);
     let server = MockServer::new(config).await?;
-    let addr = format!("{}:{}", cli.host, cli.port);
+
+    if cli.dry_run {
+        println!("{}", serde_json::to_string_pretty(&server.plan())?);
+        return Ok(());
+    }
+
+    if let Some(Command::Routes) = cli.command {
+        print_routes(server.catalog());
+        return Ok(());
+    }
+    if cli.print_routes {
+        print_routes(server.catalog());
+    }
+
+    if let Some(ref dir) = cli.diff_recordings {
+        let recordings = raps_mock::recordings::load_dir(dir)?;
+        let token = raps_mock::pact::issue_token(server.router()).await;
+        let diffs =
+            raps_mock::recordings::diff(server.router(), &recordings, token.as_deref()).await;
+        let diverged = diffs.iter().filter(|d| !d.divergences.is_empty()).count();
+        for diff in &diffs {
+            let outcome = if diff.divergences.is_empty() {
+                "OK"
+            } else {
+                "DIVERGED"
+            };
+            println!("{} {} ... {}", diff.method, diff.url, outcome);
+            for divergence in &diff.divergences {
+                println!("    {}", divergence);
+            }
+        }
+        println!("{} of {} recordings diverge", diverged, diffs.len());
+        std::process::exit(if diverged == 0 { 0 } else { 1 });
+    }
+
+    if !cli.verify_pacts.is_empty() {
+        let token = raps_mock::pact::issue_token(server.router()).await;
+        let mut passed = true;
+        for path in &cli.verify_pacts {
+            let pact = raps_mock::pact::PactFile::load(path)?;
+            let report = raps_mock::pact::verify(server.router(), &pact, token.as_deref()).await;
+            println!(
+                "Verifying pact between {} and {} ({})",
+                report.consumer,
+                report.provider,
+                path.display()
+            );
+            for result in &report.interactions {
+                let outcome = if result.mismatches.is_empty() {
+                    "OK"
+                } else {
+                    "FAILED"
+                };
+                println!("  {} ... {}", result.description, outcome);
+                for mismatch in &result.mismatches {
+                    println!("      {}", mismatch);
+                }
+            }
+            passed &= report.passed();
+        }
+        std::process::exit(if passed { 0 } else { 1 });
+    }
+
     server.start(&addr).await?;
 
     Ok(())
 }
+
+/// Print the routes as a table sorted by path and method
+fn print_routes(catalog: &RouteCatalog) {
+    print!("{}", route_table(catalog));
+}
+
+/// The registered routes, admin routes included, as a table sorted by path
+/// and method
+fn route_table(catalog: &RouteCatalog) -> String {
+    let mut rows: Vec<[String; 5]> = catalog
+        .routes()
+        .iter()
+        .map(|route| {
+            [
+                route.method.as_str().to_string(),
+                route.path.clone(),
+                route.source.as_str().to_string(),
+                route.origin.clone().unwrap_or_else(|| "-".to_string()),
+                route
+                    .operation_id
+                    .clone()
+                    .unwrap_or_else(|| "-".to_string()),
+            ]
+        })
+        .chain(raps_mock::admin::ROUTES.iter().map(|(method, path)| {
+            [
+                method.to_string(),
+                format!("{}{}", raps_mock::admin::ADMIN_PREFIX, path),
+                "admin".to_string(),
+                "-".to_string(),
+                "-".to_string(),
+            ]
+        }))
+        .collect();
+    rows.sort_by(|a, b| (&a[1], &a[0]).cmp(&(&b[1], &b[0])));
+    let header = ["METHOD", "PATH", "SOURCE", "ORIGIN", "OPERATION"].map(String::from);
+    let mut widths = header.clone().map(|cell| cell.len());
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
+    }
+    let mut table = String::new();
+    for row in std::iter::once(&header).chain(&rows) {
+        let line: Vec<String> = row
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| format!("{:width$}", cell, width = width))
+            .collect();
+        table.push_str(line.join("  ").trim_end());
+        table.push('\n');
+    }
+    table.push_str(&format!("{} routes\n", rows.len()));
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_route_table_lists_aps_and_admin_routes() {
+        let config = MockServerConfig {
+            openapi_dir: "/nonexistent".into(),
+            ..MockServerConfig::default()
+        };
+        let server = MockServer::new(config).await.unwrap();
+        let table = route_table(server.catalog());
+        let row = |method: &str, path: &str, source: &str| {
+            table.lines().any(|line| {
+                let cells: Vec<&str> = line.split_whitespace().collect();
+                cells.get(..3) == Some(&[method, path, source][..])
+            })
+        };
+
+        assert!(table.starts_with("METHOD"));
+        assert!(row("GET", "/oss/v2/buckets", "builtin"));
+        assert!(row("POST", "/authentication/v2/token", "builtin"));
+        assert!(row("POST", "/__admin/reset", "admin"));
+        assert!(row("DELETE", "/__admin/namespaces/:name", "admin"));
+        let count = server.catalog().routes().len() + raps_mock::admin::ROUTES.len();
+        assert!(table.ends_with(&format!("{} routes\n", count)));
+    }
+}
@@ -0,0 +1,129 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+//! Request metrics, rendered in the Prometheus text exposition format.
+
+use dashmap::DashMap;
+use std::fmt::Write as _;
+use std::time::Duration;
+
+/// Labels a request is counted under
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct RequestLabels {
+    pub method: String,
+    /// Path template, not the concrete path, to keep cardinality bounded
+    pub route: String,
+    /// OpenAPI operationId, empty when the route has none
+    pub operation_id: String,
+    /// Comma-separated OpenAPI tags of the operation
+    pub tags: String,
+    pub status: u16,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct RequestStats {
+    count: u64,
+    duration_secs: f64,
+}
+
+/// Request counters and latency totals
+#[derive(Debug, Default)]
+pub struct Metrics {
+    requests: DashMap<RequestLabels, RequestStats>,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a completed request
+    pub fn record(&self, labels: RequestLabels, duration: Duration) {
+        let mut stats = self.requests.entry(labels).or_default();
+        stats.count += 1;
+        stats.duration_secs += duration.as_secs_f64();
+    }
+
+    /// Render all metrics in the Prometheus text format
+    pub fn render(&self) -> String {
+        let mut entries: Vec<(RequestLabels, RequestStats)> = self
+            .requests
+            .iter()
+            .map(|e| (e.key().clone(), *e.value()))
+            .collect();
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let mut out = String::new();
+        out.push_str("# HELP raps_mock_http_requests_total Total HTTP requests handled.\n");
+        out.push_str("# TYPE raps_mock_http_requests_total counter\n");
+        for (labels, stats) in &entries {
+            let _ = writeln!(
+                out,
+                "raps_mock_http_requests_total{{{}}} {}",
+                format_labels(labels),
+                stats.count
+            );
+        }
+
+        out.push_str(
+            "# HELP raps_mock_http_request_duration_seconds Time spent handling HTTP requests.\n",
+        );
+        out.push_str("# TYPE raps_mock_http_request_duration_seconds summary\n");
+        for (labels, stats) in &entries {
+            let labels = format_labels(labels);
+            let _ = writeln!(
+                out,
+                "raps_mock_http_request_duration_seconds_sum{{{}}} {}",
+                labels, stats.duration_secs
+            );
+            let _ = writeln!(
+                out,
+                "raps_mock_http_request_duration_seconds_count{{{}}} {}",
+                labels, stats.count
+            );
+        }
+        out
+    }
+}
+
+fn format_labels(labels: &RequestLabels) -> String {
+    format!(
+        "method=\"{}\",route=\"{}\",operation_id=\"{}\",tags=\"{}\",status=\"{}\"",
+        escape_label(&labels.method),
+        escape_label(&labels.route),
+        escape_label(&labels.operation_id),
+        escape_label(&labels.tags),
+        labels.status
+    )
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_includes_operation_id() {
+        let metrics = Metrics::new();
+        let labels = RequestLabels {
+            method: "GET".to_string(),
+            route: "/oss/v2/buckets/:bucket_key/details".to_string(),
+            operation_id: "get_bucket_details".to_string(),
+            tags: "Buckets".to_string(),
+            status: 200,
+        };
+        metrics.record(labels.clone(), Duration::from_millis(5));
+        metrics.record(labels, Duration::from_millis(5));
+
+        let text = metrics.render();
+        assert!(text.contains(
+            "raps_mock_http_requests_total{method=\"GET\",route=\"/oss/v2/buckets/:bucket_key/details\",operation_id=\"get_bucket_details\",tags=\"Buckets\",status=\"200\"} 2"
+        ));
+    }
+}
@@ -0,0 +1,124 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+use crate::state::StateManager;
+use crate::state::audit::{AuditContext, diff};
+use crate::state::backend::{Records, records};
+use crate::state::persistence::StateFile;
+use crate::webhooks::delivery::{WebhookEvent, deliver};
+use axum::{
+    Extension,
+    extract::Request,
+    http::{Method, header::AUTHORIZATION},
+    middleware::Next,
+    response::Response,
+};
+
+/// Middleware that records the state changes of every mutating request in
+/// the audit log, publishes them to event subscribers and delivers the
+/// webhook events they amount to. Mutating requests run one at a time while
+/// any of these wants them, so each change is attributed
+/// to the request that made it.
+pub async fn audit_middleware(
+    state: Option<Extension<StateManager>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(Extension(state_manager)) = state else {
+        return next.run(request).await;
+    };
+    let wanted = state_manager.audit.is_enabled()
+        || state_manager.events.has_subscribers()
+        || state_manager.webhooks.has_active();
+    if !wanted
+        || matches!(
+            *request.method(),
+            Method::GET | Method::HEAD | Method::OPTIONS
+        )
+    {
+        return next.run(request).await;
+    }
+
+    let path = request.uri().path().to_string();
+    let actor = if path.starts_with(crate::admin::ADMIN_PREFIX) {
+        "admin".to_string()
+    } else {
+        request
+            .headers()
+            .get(AUTHORIZATION)
+            .and_then(|h| h.to_str().ok())
+            .and_then(|s| s.strip_prefix("Bearer "))
+            .and_then(|token| state_manager.auth.lookup_token(token))
+            .map(|info| info.client_id)
+            .unwrap_or_else(|| "anonymous".to_string())
+    };
+    let context = AuditContext {
+        timestamp: chrono::Utc::now().timestamp_millis(),
+        actor,
+        method: request.method().as_str().to_string(),
+        path,
+    };
+
+    let _writer = state_manager.audit.writer.lock().await;
+    let before = capture(&state_manager);
+    let response = next.run(request).await;
+    let after = capture(&state_manager);
+    let changes = diff(&before, &after);
+    state_manager.audit.record_changes(&context, &changes);
+    state_manager.events.publish(context.timestamp, &changes);
+    for event in WebhookEvent::from_changes(&changes) {
+        deliver(&state_manager.webhooks, &event);
+    }
+    response
+}
+
+fn capture(state: &StateManager) -> Records {
+    records(&StateFile::capture_without_contents(state))
+        .inspect_err(|e| tracing::warn!("Failed to capture the state for the audit log: {}", e))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::audit::AuditAction;
+    use axum::body::Body;
+    use axum::routing::post;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_mutations_are_attributed_to_the_token_client() {
+        let state = StateManager::new();
+        state.audit.set_capacity(100);
+        let token = state.auth.generate_token("my-app", 3600, None);
+        let handler_state = state.clone();
+        let app = axum::Router::new()
+            .route(
+                "/buckets",
+                post(move || async move {
+                    handler_state
+                        .buckets
+                        .create_bucket("audited".into(), "transient".into());
+                    "created"
+                }),
+            )
+            .layer(axum::middleware::from_fn(audit_middleware))
+            .layer(Extension(state.clone()));
+
+        let request = Request::post("/buckets")
+            .header(AUTHORIZATION, format!("Bearer {}", token.access_token))
+            .body(Body::empty())
+            .unwrap();
+        app.oneshot(request).await.unwrap();
+
+        let entries = state.audit.entries(None);
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].actor, "my-app");
+        assert_eq!(
+            (entries[0].kind.as_str(), entries[0].id.as_str()),
+            ("buckets", "audited")
+        );
+        assert_eq!(entries[0].action, AuditAction::Created);
+        assert_eq!(entries[0].new.as_ref().unwrap()["bucket_key"], "audited");
+    }
+}
@@ -1,23 +1,55 @@
 // SPDX-License-Identifier: Apache-2.0
 // Copyright 2024-2025 Dmytro Yemelianov
 
+use crate::config::{MockMode, MockServerConfig, Service};
+use crate::server::catalog::RouteCatalog;
 use crate::state::StateManager;
 use axum::{
     Extension,
-    extract::Request,
+    extract::{MatchedPath, Request},
     http::{StatusCode, header::AUTHORIZATION},
     middleware::Next,
     response::Response,
 };
+use std::sync::Arc;
 
-/// Middleware to validate Bearer tokens
+/// Middleware to validate Bearer tokens on routes whose security
+/// requirements call for one
 pub async fn auth_middleware(
     state: Option<Extension<StateManager>>,
+    catalog: Option<Extension<Arc<RouteCatalog>>>,
+    config: Option<Extension<Arc<MockServerConfig>>>,
     request: Request,
     next: Next,
 ) -> Response {
-    // Skip auth for token endpoint
-    if request.uri().path() == "/authentication/v2/token" {
+    // Skip auth for the mock's own endpoints: the admin API and the
+    // signed-URL upload targets (S3 URLs carry no Bearer token)
+    let path = request.uri().path();
+    if path.starts_with(crate::admin::ADMIN_PREFIX) || path.starts_with("/__mock/") {
+        return next.run(request).await;
+    }
+
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .zip(catalog.as_ref())
+        .and_then(|(matched, Extension(catalog))| {
+            catalog.get(matched.as_str(), request.method().as_str())
+        })
+        .cloned();
+
+    // Operations declared without (Bearer) security are public
+    if route.as_ref().is_some_and(|route| !route.requires_auth) {
+        return next.run(request).await;
+    }
+
+    // Forwarded requests carry a real APS token, which the upstream checks;
+    // replayed ones were recorded with one
+    if config
+        .as_ref()
+        .is_some_and(|Extension(config)| config.proxy_base.is_some() || config.replay_dir.is_some())
+        && crate::middleware::proxy::forwards(route.as_ref())
+    {
         return next.run(request).await;
     }
 
@@ -29,13 +61,23 @@ pub async fn auth_middleware(
         .and_then(|s| s.strip_prefix("Bearer "));
 
     if let Some(token) = token {
-        // Validate token against state if available
-        if let Some(Extension(ref state_manager)) = state {
-            if state_manager.auth.validate_token(token) {
-                return next.run(request).await;
+        // Validate token against state if tokens are issued statefully
+        let auth_stateful = config.is_none_or(|Extension(config)| {
+            config.mode_for(Service::Authentication) == MockMode::Stateful
+        });
+        if let Some(Extension(ref state_manager)) = state.filter(|_| auth_stateful) {
+            let info = state_manager.auth.lookup_token(token);
+            state_manager.auth.record_usage(token, info.as_ref());
+            let Some(info) = info else {
+                // Token validation failed
+                return unauthorized_response(
+                    "The access token provided is invalid or has expired.",
+                );
+            };
+            if let Some(route) = route.filter(|r| !r.allows_scope(info.scope.as_deref())) {
+                return forbidden_response(&route.required_scopes);
             }
-            // Token validation failed
-            return unauthorized_response("The access token provided is invalid or has expired.");
+            return next.run(request).await;
         }
         // No state manager (stateless mode) - accept any Bearer token
         return next.run(request).await;
@@ -60,3 +102,28 @@ fn unauthorized_response(message: &str) -> Response {
         // Response::builder() with valid status and headers cannot fail
         .expect("Failed to build unauthorized response")
 }
+
+/// 403 for a valid token that lacks the scopes the operation declares
+fn forbidden_response(required_scopes: &[Vec<String>]) -> Response {
+    let required = required_scopes
+        .iter()
+        .map(|set| set.join(" "))
+        .collect::<Vec<_>>()
+        .join(" or ");
+    Response::builder()
+        .status(StatusCode::FORBIDDEN)
+        .header("Content-Type", "application/json")
+        .body(
+            serde_json::json!({
+                "developerMessage": format!(
+                    "The access token does not have the required scope(s): {}",
+                    required
+                ),
+                "errorCode": "AUTH-012"
+            })
+            .to_string()
+            .into(),
+        )
+        // Response::builder() with valid status and headers cannot fail
+        .expect("Failed to build forbidden response")
+}
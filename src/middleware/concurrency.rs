@@ -0,0 +1,153 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+//! Per-route concurrency limits (`--max-in-flight`), so client connection
+//! pools and head-of-line blocking can be observed against the mock.
+//!
+//! Each limit has its own slots, shared by every route its pattern matches.
+//! A request matching several limits needs a slot in each. Slots are held
+//! until the response is produced, including any `X-Mock-Delay-Ms` delay.
+
+use crate::config::ConcurrencyLimit;
+use crate::server::catalog::RouteCatalog;
+use axum::{
+    Extension, Json,
+    extract::{MatchedPath, Request},
+    http::{StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde_json::json;
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+
+/// Slots of every configured limit
+pub struct ConcurrencyLimiter {
+    limits: Vec<(ConcurrencyLimit, Arc<Semaphore>)>,
+}
+
+impl ConcurrencyLimiter {
+    pub fn new(limits: &[ConcurrencyLimit]) -> Self {
+        Self {
+            limits: limits
+                .iter()
+                .map(|limit| (limit.clone(), Arc::new(Semaphore::new(limit.max_in_flight))))
+                .collect(),
+        }
+    }
+}
+
+/// Middleware holding a slot of every matching limit while the request is
+/// served
+pub async fn concurrency_middleware(
+    limiter: Option<Extension<Arc<ConcurrencyLimiter>>>,
+    catalog: Option<Extension<Arc<RouteCatalog>>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(Extension(limiter)) = limiter else {
+        return next.run(request).await;
+    };
+
+    let method = request.method().as_str().to_string();
+    let path = request.uri().path().to_string();
+    let tags = request
+        .extensions()
+        .get::<MatchedPath>()
+        .zip(catalog.as_ref())
+        .and_then(|(route, Extension(catalog))| catalog.get(route.as_str(), &method))
+        .map(|route| route.tags.clone())
+        .unwrap_or_default();
+
+    let mut permits = Vec::new();
+    for (limit, slots) in &limiter.limits {
+        if !limit.route.matches(&method, &path, &tags) {
+            continue;
+        }
+        let permit = if limit.queue {
+            slots.clone().acquire_owned().await.ok()
+        } else {
+            match slots.clone().try_acquire_owned() {
+                Ok(permit) => Some(permit),
+                Err(_) => return too_many_requests(limit),
+            }
+        };
+        permits.extend(permit);
+    }
+
+    let response = next.run(request).await;
+    drop(permits);
+    response
+}
+
+fn too_many_requests(limit: &ConcurrencyLimit) -> Response {
+    (
+        StatusCode::TOO_MANY_REQUESTS,
+        [(header::RETRY_AFTER, "1")],
+        Json(json!({
+            "developerMessage": format!(
+                "Too many concurrent requests: at most {} in flight",
+                limit.max_in_flight
+            ),
+            "errorCode": "TOO_MANY_REQUESTS"
+        })),
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::routing::get;
+    use std::time::Duration;
+    use tower::ServiceExt;
+
+    fn router(limit: &str) -> axum::Router {
+        let limits = vec![limit.parse::<ConcurrencyLimit>().unwrap()];
+        axum::Router::new()
+            .route(
+                "/slow",
+                get(|| async {
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                    "done"
+                }),
+            )
+            .route("/fast", get(|| async { "done" }))
+            .layer(axum::middleware::from_fn(concurrency_middleware))
+            .layer(Extension(Arc::new(ConcurrencyLimiter::new(&limits))))
+    }
+
+    async fn statuses(router: &axum::Router, paths: [&str; 3]) -> Vec<StatusCode> {
+        let call = |path: &str| {
+            let request = Request::get(path).body(Body::empty()).unwrap();
+            router.clone().oneshot(request)
+        };
+        let (a, b, c) = tokio::join!(call(paths[0]), call(paths[1]), call(paths[2]));
+        [a, b, c].into_iter().map(|r| r.unwrap().status()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_excess_requests_are_rejected_or_queued() {
+        let rejecting = router("GET /slow=1");
+        assert_eq!(
+            statuses(&rejecting, ["/slow", "/slow", "/fast"]).await,
+            [
+                StatusCode::OK,
+                StatusCode::TOO_MANY_REQUESTS,
+                StatusCode::OK
+            ]
+        );
+        // Slots free up once responses are produced
+        assert_eq!(
+            statuses(&rejecting, ["/slow", "/fast", "/fast"]).await,
+            [StatusCode::OK; 3]
+        );
+
+        let queueing = router("/slow=1:queue");
+        assert_eq!(
+            statuses(&queueing, ["/slow", "/slow", "/slow"]).await,
+            [StatusCode::OK; 3]
+        );
+    }
+}
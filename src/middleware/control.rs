@@ -0,0 +1,203 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+//! Per-request control headers (`X-Mock-*`), enabled with `--control-headers`.
+//!
+//! - `X-Mock-Delay-Ms`: wait before dispatching the request
+//! - `X-Mock-Status`: answer with this status, using the operation's response
+//!   definition for it when the spec has one
+//! - `X-Mock-Scenario`: select a named OpenAPI example or a stub declaring
+//!   that scenario
+//!
+//! The headers are stripped before the request reaches the handlers.
+
+use crate::config::MockServerConfig;
+use axum::{
+    Extension, Json,
+    extract::Request,
+    http::{HeaderMap, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde_json::json;
+use std::sync::Arc;
+use std::time::Duration;
+
+pub const DELAY_HEADER: &str = "x-mock-delay-ms";
+pub const STATUS_HEADER: &str = "x-mock-status";
+pub const SCENARIO_HEADER: &str = "x-mock-scenario";
+
+/// Upper bound for `X-Mock-Delay-Ms`, so a typo cannot hang a test run
+const MAX_DELAY_MS: u64 = 5 * 60 * 1000;
+
+/// Overrides requested through control headers, stored as a request extension
+#[derive(Debug, Clone, Default)]
+pub struct MockOverrides {
+    pub status: Option<StatusCode>,
+    pub scenario: Option<String>,
+}
+
+/// Middleware that applies `X-Mock-*` control headers
+pub async fn control_headers_middleware(
+    config: Option<Extension<Arc<MockServerConfig>>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    if !config.is_some_and(|Extension(c)| c.control_headers) {
+        return next.run(request).await;
+    }
+
+    let headers = request.headers_mut();
+    let delay = match take_header(headers, DELAY_HEADER).map(|v| v.parse::<u64>()) {
+        Some(Ok(ms)) => Some(Duration::from_millis(ms.min(MAX_DELAY_MS))),
+        Some(Err(_)) => return invalid_header(DELAY_HEADER, "expected milliseconds"),
+        None => None,
+    };
+    let status = match take_header(headers, STATUS_HEADER).map(|v| v.parse::<StatusCode>()) {
+        Some(Ok(status)) => Some(status),
+        Some(Err(_)) => return invalid_header(STATUS_HEADER, "expected an HTTP status code"),
+        None => None,
+    };
+    let scenario = take_header(headers, SCENARIO_HEADER);
+
+    if let Some(delay) = delay {
+        tokio::time::sleep(delay).await;
+    }
+
+    request
+        .extensions_mut()
+        .insert(MockOverrides { status, scenario });
+    let mut response = next.run(request).await;
+
+    // Handlers without a definition for the forced status still honour it
+    if let Some(status) = status
+        && response.status() != status
+    {
+        if status.is_client_error() || status.is_server_error() {
+            response = simulated_error(status);
+        } else {
+            *response.status_mut() = status;
+        }
+    }
+    response
+}
+
+fn take_header(headers: &mut HeaderMap, name: &str) -> Option<String> {
+    headers
+        .remove(name)
+        .and_then(|v| v.to_str().ok().map(|s| s.trim().to_string()))
+}
+
+fn invalid_header(name: &str, expected: &str) -> Response {
+    (
+        StatusCode::BAD_REQUEST,
+        Json(json!({
+            "developerMessage": format!("Invalid {} header: {}", name, expected),
+            "errorCode": "ERR_BAD_INPUT"
+        })),
+    )
+        .into_response()
+}
+
+fn simulated_error(status: StatusCode) -> Response {
+    (
+        status,
+        Json(json!({
+            "developerMessage": format!(
+                "Simulated {} response",
+                status.canonical_reason().unwrap_or("error")
+            ),
+            "errorCode": format!("MOCK-{}", status.as_u16())
+        })),
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::routing::get;
+    use tower::ServiceExt;
+
+    fn router(control_headers: bool) -> axum::Router {
+        let config = MockServerConfig {
+            control_headers,
+            ..MockServerConfig::default()
+        };
+        axum::Router::new()
+            .route(
+                "/overrides",
+                get(|overrides: Option<Extension<MockOverrides>>| async move {
+                    overrides
+                        .and_then(|Extension(o)| o.scenario)
+                        .unwrap_or_default()
+                }),
+            )
+            .layer(axum::middleware::from_fn(control_headers_middleware))
+            .layer(Extension(Arc::new(config)))
+    }
+
+    async fn call(router: &axum::Router, headers: &[(&str, &str)]) -> (StatusCode, Vec<u8>) {
+        let mut request = Request::get("/overrides");
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+        let response = router
+            .clone()
+            .oneshot(request.body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, body.to_vec())
+    }
+
+    #[tokio::test]
+    async fn test_control_headers_force_statuses_and_reject_bad_values() {
+        let (router, disabled) = (router(true), router(false));
+
+        let (status, body) = call(&router, &[(SCENARIO_HEADER, "empty")]).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, b"empty");
+        let (status, _) = call(&router, &[(STATUS_HEADER, "202")]).await;
+        assert_eq!(status, StatusCode::ACCEPTED);
+        // Handlers without the forced error status answer a simulated error
+        let (status, body) = call(&router, &[(STATUS_HEADER, "503")]).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["errorCode"], "MOCK-503");
+
+        for (name, value) in [
+            (STATUS_HEADER, "teapot"),
+            (STATUS_HEADER, "1000"),
+            (DELAY_HEADER, "-5"),
+            (DELAY_HEADER, "soon"),
+        ] {
+            let (status, body) = call(&router, &[(name, value)]).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST, "{}: {}", name, value);
+            let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(body["errorCode"], "ERR_BAD_INPUT");
+        }
+
+        // Ignored unless enabled
+        let (status, _) = call(&disabled, &[(STATUS_HEADER, "503")]).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_delays_are_capped() {
+        let router = router(true);
+
+        let started = tokio::time::Instant::now();
+        let (status, _) = call(&router, &[(DELAY_HEADER, "1500")]).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(started.elapsed(), Duration::from_millis(1500));
+
+        let started = tokio::time::Instant::now();
+        call(&router, &[(DELAY_HEADER, &u64::MAX.to_string())]).await;
+        assert_eq!(started.elapsed(), Duration::from_millis(MAX_DELAY_MS));
+    }
+}
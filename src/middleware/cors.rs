@@ -1,12 +1,45 @@
 // SPDX-License-Identifier: Apache-2.0
 // Copyright 2024-2025 Dmytro Yemelianov
 
-use tower_http::cors::{Any, CorsLayer};
-
-/// CORS middleware configuration
-pub fn cors_middleware() -> CorsLayer {
-    CorsLayer::new()
-        .allow_origin(Any)
-        .allow_methods(Any)
-        .allow_headers(Any)
+//! Permissive CORS. Only real preflights (`OPTIONS` with an
+//! `Access-Control-Request-Method` header) are answered here; other
+//! `OPTIONS` requests reach the routes, so specs can declare `options`
+//! operations.
+
+use axum::{
+    extract::Request,
+    http::{HeaderMap, HeaderValue, Method, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+const VARY: &str = "origin, access-control-request-method, access-control-request-headers";
+
+/// CORS middleware allowing any origin, method and header
+pub async fn cors_middleware(request: Request, next: Next) -> Response {
+    let preflight = request.method() == Method::OPTIONS
+        && request
+            .headers()
+            .contains_key(header::ACCESS_CONTROL_REQUEST_METHOD);
+
+    let mut response = if preflight {
+        let mut response = ().into_response();
+        let headers = response.headers_mut();
+        let any = HeaderValue::from_static("*");
+        headers.insert(header::ACCESS_CONTROL_ALLOW_METHODS, any.clone());
+        headers.insert(header::ACCESS_CONTROL_ALLOW_HEADERS, any);
+        response
+    } else {
+        next.run(request).await
+    };
+    allow_any_origin(response.headers_mut());
+    response
+}
+
+fn allow_any_origin(headers: &mut HeaderMap) {
+    headers.append(header::VARY, HeaderValue::from_static(VARY));
+    headers.insert(
+        header::ACCESS_CONTROL_ALLOW_ORIGIN,
+        HeaderValue::from_static("*"),
+    );
 }
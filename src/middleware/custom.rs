@@ -0,0 +1,36 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+use crate::handlers::CustomHandlerRegistry;
+use crate::middleware::stubs::buffer_body;
+use axum::{Extension, extract::Request, middleware::Next, response::Response};
+use std::sync::Arc;
+
+/// Middleware that answers requests matching a handler registered from
+/// code, ahead of the file stubs and all routes
+pub async fn custom_handlers_middleware(
+    handlers: Option<Extension<Arc<CustomHandlerRegistry>>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(Extension(handlers)) = handlers.filter(|Extension(h)| !h.is_empty()) else {
+        return next.run(request).await;
+    };
+
+    // Handlers get the body, and may match on it
+    let (request, bytes) = buffer_body(request, true).await;
+    let handler = handlers.find(
+        request.method().as_str(),
+        request.uri().path(),
+        request.uri().query(),
+        request.headers(),
+        bytes.as_deref(),
+    );
+    match handler {
+        Some(handler) => {
+            let json = bytes.and_then(|bytes| serde_json::from_slice(&bytes).ok());
+            handler(json).await
+        }
+        None => next.run(request).await,
+    }
+}
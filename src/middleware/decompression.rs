@@ -0,0 +1,167 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+//! Transparent decompression of `Content-Encoding: gzip`/`deflate` request
+//! bodies, so handlers, validators, stubs and the request fingerprint all see
+//! plain JSON.
+
+use axum::{
+    body::{Body, Bytes, to_bytes},
+    extract::Request,
+    http::{HeaderMap, StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use flate2::read::{DeflateDecoder, GzDecoder, ZlibDecoder};
+use std::io::Read;
+
+/// Maximum size of a request body, before and after decompression
+const MAX_BODY_BYTES: usize = 16 * 1024 * 1024;
+
+/// Middleware that decodes compressed request bodies and drops the
+/// `Content-Encoding` header
+pub async fn decompression_middleware(request: Request, next: Next) -> Response {
+    let Some(encodings) = content_encodings(request.headers()) else {
+        return next.run(request).await;
+    };
+
+    let (mut parts, body) = request.into_parts();
+    let mut body = match to_bytes(body, MAX_BODY_BYTES).await {
+        Ok(body) => body,
+        Err(e) => {
+            return (
+                StatusCode::PAYLOAD_TOO_LARGE,
+                format!("Could not read request body: {}", e),
+            )
+                .into_response();
+        }
+    };
+    // Encodings are listed in the order they were applied
+    for encoding in encodings.iter().rev() {
+        body = match decode(encoding, &body) {
+            Ok(decoded) => decoded,
+            Err(DecodeError::Unsupported) => {
+                return (
+                    StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                    format!("Unsupported Content-Encoding: {}", encoding),
+                )
+                    .into_response();
+            }
+            Err(DecodeError::TooLarge) => {
+                return (
+                    StatusCode::PAYLOAD_TOO_LARGE,
+                    "Decompressed request body is too large",
+                )
+                    .into_response();
+            }
+            Err(DecodeError::Invalid(e)) => {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    format!("Could not decode {} request body: {}", encoding, e),
+                )
+                    .into_response();
+            }
+        };
+    }
+
+    parts.headers.remove(header::CONTENT_ENCODING);
+    parts
+        .headers
+        .insert(header::CONTENT_LENGTH, body.len().into());
+    next.run(Request::from_parts(parts, Body::from(body))).await
+}
+
+/// The request's non-identity content codings, `None` when it has none
+fn content_encodings(headers: &HeaderMap) -> Option<Vec<String>> {
+    let encodings: Vec<String> = headers
+        .get_all(header::CONTENT_ENCODING)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|coding| coding.trim().to_ascii_lowercase())
+        .filter(|coding| !coding.is_empty() && coding != "identity")
+        .collect();
+    (!encodings.is_empty()).then_some(encodings)
+}
+
+enum DecodeError {
+    Unsupported,
+    TooLarge,
+    Invalid(std::io::Error),
+}
+
+fn decode(encoding: &str, body: &[u8]) -> Result<Bytes, DecodeError> {
+    match encoding {
+        "gzip" | "x-gzip" => read_limited(GzDecoder::new(body)),
+        // HTTP's deflate is zlib-wrapped, but some clients send raw deflate
+        "deflate" => read_limited(ZlibDecoder::new(body))
+            .or_else(|_| read_limited(DeflateDecoder::new(body))),
+        _ => Err(DecodeError::Unsupported),
+    }
+}
+
+fn read_limited(decoder: impl Read) -> Result<Bytes, DecodeError> {
+    let mut decoded = Vec::new();
+    decoder
+        .take(MAX_BODY_BYTES as u64 + 1)
+        .read_to_end(&mut decoded)
+        .map_err(DecodeError::Invalid)?;
+    if decoded.len() > MAX_BODY_BYTES {
+        return Err(DecodeError::TooLarge);
+    }
+    Ok(decoded.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{Router, routing::post};
+    use flate2::{Compression, write::GzEncoder, write::ZlibEncoder};
+    use std::io::Write;
+    use tower::ServiceExt;
+
+    fn echo() -> Router {
+        Router::new()
+            .route("/echo", post(|body: String| async move { body }))
+            .layer(axum::middleware::from_fn(decompression_middleware))
+    }
+
+    async fn send(encoding: &str, body: Vec<u8>) -> (StatusCode, String) {
+        let request = Request::post("/echo")
+            .header("content-encoding", encoding)
+            .body(Body::from(body))
+            .unwrap();
+        let response = echo().oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, String::from_utf8_lossy(&body).into_owned())
+    }
+
+    #[tokio::test]
+    async fn test_compressed_bodies_are_decoded() {
+        let json = br#"{"bucketKey":"compressed"}"#;
+        let mut gzip = GzEncoder::new(Vec::new(), Compression::default());
+        gzip.write_all(json).unwrap();
+        let mut zlib = ZlibEncoder::new(Vec::new(), Compression::default());
+        zlib.write_all(json).unwrap();
+
+        let expected = String::from_utf8_lossy(json).into_owned();
+        assert_eq!(
+            send("gzip", gzip.finish().unwrap()).await,
+            (StatusCode::OK, expected.clone())
+        );
+        assert_eq!(
+            send("deflate", zlib.finish().unwrap()).await,
+            (StatusCode::OK, expected.clone())
+        );
+        assert_eq!(
+            send("identity", json.to_vec()).await,
+            (StatusCode::OK, expected)
+        );
+        assert_eq!(send("gzip", json.to_vec()).await.0, StatusCode::BAD_REQUEST);
+        assert_eq!(
+            send("br", json.to_vec()).await.0,
+            StatusCode::UNSUPPORTED_MEDIA_TYPE
+        );
+    }
+}
@@ -0,0 +1,117 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+//! Deterministic responses, enabled with `--deterministic`.
+//!
+//! Every request gets a fingerprint hashed from its method, path, query and
+//! body (and the `--seed`, if any). Generated IDs, timestamps and synthesized
+//! data are derived from it instead of the clock and entropy, so repeating an
+//! identical request yields a byte-identical body.
+
+use crate::config::MockServerConfig;
+use crate::handlers::synth::stable_hash;
+use axum::{
+    Extension,
+    body::{Body, to_bytes},
+    extract::Request,
+    http::StatusCode,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, TimeZone, Utc};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::sync::Arc;
+
+/// Largest request body hashed into the fingerprint
+const MAX_BODY_BYTES: usize = 16 * 1024 * 1024;
+
+/// Reference instant deterministic timestamps are spread after
+/// (2024-01-01T00:00:00Z)
+const EPOCH_SECS: i64 = 1_704_067_200;
+
+/// Hash of a request, stored as a request extension in deterministic mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RequestFingerprint(pub u64);
+
+impl RequestFingerprint {
+    pub fn compute(seed: Option<u64>, method: &str, uri: &str, body: &[u8]) -> Self {
+        Self(stable_hash(&[
+            &seed.unwrap_or_default().to_le_bytes(),
+            method.as_bytes(),
+            uri.as_bytes(),
+            body,
+        ]))
+    }
+
+    /// Timestamp standing in for "now", within a year after the reference instant
+    pub fn now(&self) -> DateTime<Utc> {
+        let offset_ms = (self.0 % (365 * 86_400 * 1000)) as i64;
+        Utc.timestamp_opt(EPOCH_SECS, 0).unwrap() + chrono::Duration::milliseconds(offset_ms)
+    }
+
+    /// UUID standing in for a random one
+    pub fn uuid(&self) -> uuid::Uuid {
+        uuid::Builder::from_random_bytes(StdRng::seed_from_u64(self.0).r#gen()).into_uuid()
+    }
+}
+
+/// Current time, or the fingerprint's stand-in in deterministic mode
+pub fn now(fingerprint: Option<&RequestFingerprint>) -> DateTime<Utc> {
+    fingerprint.map_or_else(Utc::now, RequestFingerprint::now)
+}
+
+/// Fresh UUID, or the fingerprint's stand-in in deterministic mode
+pub fn uuid(fingerprint: Option<&RequestFingerprint>) -> uuid::Uuid {
+    fingerprint.map_or_else(uuid::Uuid::new_v4, RequestFingerprint::uuid)
+}
+
+/// Middleware that fingerprints requests when `--deterministic` is set
+pub async fn determinism_middleware(
+    config: Option<Extension<Arc<MockServerConfig>>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(Extension(config)) = config.filter(|Extension(c)| c.deterministic) else {
+        return next.run(request).await;
+    };
+
+    let (mut parts, body) = request.into_parts();
+    let body = match to_bytes(body, MAX_BODY_BYTES).await {
+        Ok(body) => body,
+        Err(e) => {
+            return (
+                StatusCode::PAYLOAD_TOO_LARGE,
+                format!("Could not read request body: {}", e),
+            )
+                .into_response();
+        }
+    };
+    let uri = parts
+        .uri
+        .path_and_query()
+        .map(|pq| pq.as_str())
+        .unwrap_or("/");
+    let fingerprint = RequestFingerprint::compute(config.seed, parts.method.as_str(), uri, &body);
+    parts.extensions.insert(fingerprint);
+    next.run(Request::from_parts(parts, Body::from(body))).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fingerprint_depends_on_request_only() {
+        let a = RequestFingerprint::compute(None, "POST", "/oss/v2/buckets", b"{\"a\":1}");
+        let b = RequestFingerprint::compute(None, "POST", "/oss/v2/buckets", b"{\"a\":1}");
+        let c = RequestFingerprint::compute(None, "POST", "/oss/v2/buckets", b"{\"a\":2}");
+        assert_eq!((a.now(), a.uuid()), (b.now(), b.uuid()));
+        assert_ne!(a.uuid(), c.uuid());
+        assert_ne!(
+            a,
+            RequestFingerprint::compute(Some(7), "POST", "/oss/v2/buckets", b"{\"a\":1}")
+        );
+        assert!(a.now() >= Utc.timestamp_opt(EPOCH_SECS, 0).unwrap());
+    }
+}
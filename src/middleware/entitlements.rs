@@ -0,0 +1,93 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+use crate::config::{MockMode, MockServerConfig, Service};
+use crate::state::StateManager;
+use crate::state::entitlements::Product;
+use axum::{Extension, extract::Request, http::StatusCode, middleware::Next, response::Response};
+use std::sync::Arc;
+
+/// Middleware that rejects requests to services whose ACC product is not
+/// activated on the target project
+pub async fn entitlements_middleware(
+    state: Option<Extension<StateManager>>,
+    config: Option<Extension<Arc<MockServerConfig>>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(Extension(state_manager)) = state else {
+        return next.run(request).await;
+    };
+
+    // Stateless services have no project state to check against
+    if let (Some(Extension(config)), Some(service)) =
+        (config, Service::for_path(request.uri().path()))
+        && config.mode_for(service) == MockMode::Stateless
+    {
+        return next.run(request).await;
+    }
+
+    if let Some((product, project_id)) = required_product(request.uri().path())
+        && !state_manager.entitlements.is_active(&project_id, product)
+    {
+        return not_activated_response(product, &project_id);
+    }
+
+    next.run(request).await
+}
+
+/// Determine which product (and project) a request path depends on
+fn required_product(path: &str) -> Option<(Product, String)> {
+    let segments: Vec<&str> = path.trim_start_matches('/').split('/').collect();
+
+    match segments.as_slice() {
+        ["construction", "issues", _, "projects", project_id, ..] => {
+            Some((Product::Build, project_id.to_string()))
+        }
+        ["cost", _, "containers", container_id, ..] => {
+            Some((Product::Cost, container_id.to_string()))
+        }
+        ["data", _, "projects", project_id, ..]
+        | ["project", _, "hubs", _, "projects", project_id, ..] => {
+            Some((Product::Docs, project_id.to_string()))
+        }
+        _ => None,
+    }
+}
+
+fn not_activated_response(product: Product, project_id: &str) -> Response {
+    // Data Management hides projects without Docs entirely, while the
+    // product-specific services answer with a 403.
+    let (status, body) = match product {
+        Product::Docs => (
+            StatusCode::NOT_FOUND,
+            serde_json::json!({
+                "jsonapi": { "version": "1.0" },
+                "errors": [{
+                    "status": "404",
+                    "code": "NOT_FOUND",
+                    "title": "Not Found",
+                    "detail": format!("Project {} not found", project_id)
+                }]
+            }),
+        ),
+        Product::Build | Product::Cost => (
+            StatusCode::FORBIDDEN,
+            serde_json::json!({
+                "developerMessage": format!(
+                    "The {} product is not activated for project {}.",
+                    product.display_name(),
+                    project_id
+                ),
+                "errorCode": "ERR_PRODUCT_NOT_ACTIVATED"
+            }),
+        ),
+    };
+
+    Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .body(body.to_string().into())
+        // Response::builder() with valid status and headers cannot fail
+        .expect("Failed to build entitlement response")
+}
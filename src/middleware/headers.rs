@@ -0,0 +1,62 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+use crate::config::MockServerConfig;
+use crate::server::catalog::RouteCatalog;
+use axum::{
+    Extension,
+    extract::{MatchedPath, Request},
+    http::{HeaderName, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use std::sync::Arc;
+
+/// Middleware that adds the configured response headers, replicating
+/// headers a gateway would add in front of the real services. Global rules
+/// apply first so route-specific ones win on conflicts.
+pub async fn response_headers_middleware(
+    config: Option<Extension<Arc<MockServerConfig>>>,
+    catalog: Option<Extension<Arc<RouteCatalog>>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(Extension(config)) = config.filter(|c| !c.response_headers.is_empty()) else {
+        return next.run(request).await;
+    };
+
+    let method = request.method().as_str().to_string();
+    let path = request.uri().path().to_string();
+    let tags = request
+        .extensions()
+        .get::<MatchedPath>()
+        .zip(catalog.as_ref())
+        .and_then(|(route, Extension(catalog))| catalog.get(route.as_str(), &method))
+        .map(|route| route.tags.clone())
+        .unwrap_or_default();
+    let mut response = next.run(request).await;
+
+    let (global, scoped): (Vec<_>, Vec<_>) = config
+        .response_headers
+        .iter()
+        .partition(|rule| rule.route.is_none());
+    let matching = scoped.into_iter().filter(|rule| {
+        rule.route
+            .as_ref()
+            .is_some_and(|r| r.matches(&method, &path, &tags))
+    });
+
+    let headers = response.headers_mut();
+    for rule in global.into_iter().chain(matching) {
+        match (
+            HeaderName::from_bytes(rule.name.as_bytes()),
+            HeaderValue::from_str(&rule.value),
+        ) {
+            (Ok(name), Ok(value)) => {
+                headers.insert(name, value);
+            }
+            _ => tracing::warn!("Skipping invalid response header {}", rule.name),
+        }
+    }
+    response
+}
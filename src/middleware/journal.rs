@@ -0,0 +1,164 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+use crate::state::StateManager;
+use crate::state::journal::{JournalEntry, MAX_RECORDED_BODY};
+use crate::stubs::record::{REDACTED, is_secret_header, redact_body, redact_query};
+use axum::{
+    Extension,
+    body::{Body, HttpBody},
+    extract::Request,
+    http::{HeaderMap, header},
+    middleware::Next,
+    response::Response,
+};
+use std::collections::BTreeMap;
+use std::time::Instant;
+
+/// Middleware that records every request (except admin calls) and its
+/// response in the request journal. Bodies are buffered and recorded only
+/// when their length is known and small enough, so uploads and downloads
+/// keep streaming. Credentials, cookies and secret query parameters and body
+/// fields are redacted as in recordings, so journal dumps and HAR exports
+/// hold no live secrets, e.g. of `--proxy-base` traffic.
+pub async fn journal_middleware(
+    state: Option<Extension<StateManager>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(Extension(state_manager)) = state else {
+        return next.run(request).await;
+    };
+    if request.uri().path().starts_with(crate::admin::ADMIN_PREFIX) {
+        return next.run(request).await;
+    }
+
+    let timestamp = chrono::Utc::now().timestamp_millis();
+    let method = request.method().as_str().to_string();
+    let path = request.uri().path().to_string();
+    let query = request.uri().query().map(redact_query);
+    let headers = header_map(request.headers());
+
+    let declared = request
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok()?.parse::<u64>().ok());
+    let (parts, body) = request.into_parts();
+    let (body, recorded_body) = record_body(body, declared).await;
+    let request = Request::from_parts(parts, body);
+
+    let started = Instant::now();
+    let response = next.run(request).await;
+    let response_headers = header_map(response.headers());
+    let (parts, body) = response.into_parts();
+    let exact = body.size_hint().exact();
+    let (body, response_body) = record_body(body, exact).await;
+    let response = Response::from_parts(parts, body);
+
+    let recorded_body = recorded_body.map(|body| redact_body(&body, content_type(&headers)));
+    let response_body =
+        response_body.map(|body| redact_body(&body, content_type(&response_headers)));
+    state_manager.journal.record(JournalEntry {
+        id: uuid::Uuid::new_v4().to_string(),
+        timestamp,
+        method,
+        path,
+        query,
+        headers,
+        body: recorded_body,
+        status: response.status().as_u16(),
+        response_headers,
+        response_body,
+        duration_ms: started.elapsed().as_millis() as u64,
+    });
+    response
+}
+
+fn header_map(headers: &HeaderMap) -> BTreeMap<String, String> {
+    headers
+        .iter()
+        .filter_map(|(name, value)| {
+            let value = if is_secret_header(name.as_str()) {
+                REDACTED.to_string()
+            } else {
+                value.to_str().ok()?.to_string()
+            };
+            Some((name.as_str().to_string(), value))
+        })
+        .collect()
+}
+
+fn content_type(headers: &BTreeMap<String, String>) -> &str {
+    headers
+        .get(header::CONTENT_TYPE.as_str())
+        .map_or("", String::as_str)
+}
+
+/// Buffer `body` if its length is known and small enough to record,
+/// returning it (as it was, or rebuilt) and its text if it is UTF-8
+async fn record_body(body: Body, length: Option<u64>) -> (Body, Option<String>) {
+    if !length.is_some_and(|length| length > 0 && length <= MAX_RECORDED_BODY) {
+        return (body, None);
+    }
+    match axum::body::to_bytes(body, MAX_RECORDED_BODY as usize).await {
+        Ok(bytes) => {
+            let text = String::from_utf8(bytes.to_vec()).ok();
+            (Body::from(bytes), text)
+        }
+        Err(_) => (Body::empty(), None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::har::Har;
+    use axum::routing::post;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_secrets_are_redacted_from_the_journal() {
+        let state = StateManager::new();
+        let router = axum::Router::new()
+            .route(
+                "/authentication/v2/token",
+                post(|| async {
+                    axum::Json(serde_json::json!({
+                        "access_token": "live-token",
+                        "token_type": "Bearer"
+                    }))
+                }),
+            )
+            .layer(axum::middleware::from_fn(journal_middleware))
+            .layer(Extension(state.clone()));
+        let request = Request::post("/authentication/v2/token?region=US&token=t1")
+            .header(header::AUTHORIZATION, "Basic Y2k6Y3M=")
+            .header(header::COOKIE, "session=abc")
+            .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded");
+        let form = "grant_type=client_credentials&client_id=ci&client_secret=cs";
+        let request = request
+            .header(header::CONTENT_LENGTH, form.len())
+            .body(Body::from(form))
+            .unwrap();
+        router.oneshot(request).await.unwrap();
+
+        let entries = state.journal.entries();
+        let entry = &entries[0];
+        assert_eq!(entry.headers["authorization"], REDACTED);
+        assert_eq!(entry.headers["cookie"], REDACTED);
+        assert_eq!(entry.query.as_deref(), Some("region=US&token=REDACTED"));
+        assert_eq!(
+            entry.body.as_deref(),
+            Some("grant_type=client_credentials&client_id=ci&client_secret=REDACTED")
+        );
+        let response: serde_json::Value =
+            serde_json::from_str(entry.response_body.as_deref().unwrap()).unwrap();
+        assert_eq!(response["access_token"], REDACTED);
+        assert_eq!(response["token_type"], "Bearer");
+
+        let har = serde_json::to_string(&Har::from_journal(&entries)).unwrap();
+        for secret in ["Y2k6Y3M=", "abc", "t1", "=cs", "live-token"] {
+            assert!(!har.contains(secret), "HAR holds {}", secret);
+        }
+    }
+}
@@ -0,0 +1,154 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+//! Artificial latency (`--latency`, `/__admin/latency`), so client timeouts
+//! and loading states can be exercised against realistic response times.
+//!
+//! The first rule whose route pattern matches the request decides its
+//! delay; requests no routed rule matches get the last global rule's. The
+//! delay is drawn anew for every request and added before it is dispatched,
+//! on top of any `X-Mock-Delay-Ms`. Admin routes are never delayed.
+
+use crate::admin::ADMIN_PREFIX;
+use crate::config::{Latency, LatencyRule};
+use crate::server::catalog::RouteCatalog;
+use axum::{
+    Extension,
+    extract::{MatchedPath, Request},
+    middleware::Next,
+    response::Response,
+};
+use std::sync::{Arc, RwLock};
+
+/// Latency rules in effect, replaceable while the server runs
+#[derive(Debug, Default)]
+pub struct LatencyInjector {
+    rules: RwLock<Vec<LatencyRule>>,
+}
+
+impl LatencyInjector {
+    pub fn new(rules: Vec<LatencyRule>) -> Self {
+        Self {
+            rules: RwLock::new(rules),
+        }
+    }
+
+    pub fn rules(&self) -> Vec<LatencyRule> {
+        self.rules.read().unwrap().clone()
+    }
+
+    pub fn set_rules(&self, rules: Vec<LatencyRule>) {
+        *self.rules.write().unwrap() = rules;
+    }
+
+    /// Distribution of the delay of a request to `path`
+    fn latency_for(&self, method: &str, path: &str, tags: &[String]) -> Option<Latency> {
+        let rules = self.rules.read().unwrap();
+        rules
+            .iter()
+            .find(|rule| {
+                rule.route
+                    .as_ref()
+                    .is_some_and(|route| route.matches(method, path, tags))
+            })
+            .or_else(|| rules.iter().rev().find(|rule| rule.route.is_none()))
+            .map(|rule| rule.latency)
+    }
+}
+
+/// Middleware delaying requests by the latency of the rule matching them
+pub async fn latency_middleware(
+    injector: Option<Extension<Arc<LatencyInjector>>>,
+    catalog: Option<Extension<Arc<RouteCatalog>>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(Extension(injector)) = injector else {
+        return next.run(request).await;
+    };
+
+    let method = request.method().as_str().to_string();
+    let path = request.uri().path().to_string();
+    if path.starts_with(ADMIN_PREFIX) {
+        return next.run(request).await;
+    }
+    let tags = request
+        .extensions()
+        .get::<MatchedPath>()
+        .zip(catalog.as_ref())
+        .and_then(|(route, Extension(catalog))| catalog.get(route.as_str(), &method))
+        .map(|route| route.tags.clone())
+        .unwrap_or_default();
+
+    if let Some(latency) = injector.latency_for(&method, &path, &tags) {
+        let delay = latency.sample(&mut rand::thread_rng());
+        tokio::time::sleep(delay).await;
+    }
+    next.run(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    #[test]
+    fn test_routed_rules_take_precedence_over_global_ones() {
+        let rules = [
+            "GET /oss/**=100..200",
+            "normal:300,50",
+            "/modelderivative/**=2000",
+        ]
+        .iter()
+        .map(|rule| rule.parse::<LatencyRule>().unwrap())
+        .collect();
+        let injector = LatencyInjector::new(rules);
+
+        assert_eq!(
+            injector.latency_for("GET", "/oss/v2/buckets", &[]),
+            Some(Latency::Uniform { min: 100, max: 200 })
+        );
+        assert_eq!(
+            injector.latency_for("GET", "/modelderivative/v2/designdata/x/manifest", &[]),
+            Some(Latency::Fixed(2000))
+        );
+        assert_eq!(
+            injector.latency_for("POST", "/oss/v2/buckets", &[]),
+            Some(Latency::Normal {
+                mean: 300.0,
+                std_dev: 50.0
+            })
+        );
+        injector.set_rules(Vec::new());
+        assert_eq!(injector.latency_for("GET", "/oss/v2/buckets", &[]), None);
+
+        let mut rng = StdRng::seed_from_u64(7);
+        for _ in 0..100 {
+            let delay = Latency::Uniform { min: 100, max: 200 }.sample(&mut rng);
+            assert!((100..=200).contains(&delay.as_millis()));
+        }
+        let mean = (0..1000)
+            .map(|_| {
+                Latency::Normal {
+                    mean: 300.0,
+                    std_dev: 50.0,
+                }
+                .sample(&mut rng)
+                .as_secs_f64()
+            })
+            .sum::<f64>();
+        assert!((0.28..0.32).contains(&(mean / 1000.0)));
+        assert!("200..100".parse::<Latency>().is_err());
+        assert!("normal:300".parse::<Latency>().is_err());
+        assert!("normal:inf,0".parse::<Latency>().is_err());
+        assert!("normal:300,NaN".parse::<Latency>().is_err());
+        // Delays are capped instead of overflowing a Duration
+        let huge: Latency = "normal:1e300,0".parse().unwrap();
+        assert_eq!(huge.sample(&mut rng), crate::config::MAX_LATENCY);
+        assert_eq!(
+            Latency::Fixed(u64::MAX).sample(&mut rng),
+            crate::config::MAX_LATENCY
+        );
+    }
+}
@@ -0,0 +1,62 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+use crate::metrics::{Metrics, RequestLabels};
+use crate::server::catalog::RouteCatalog;
+use axum::{
+    Extension,
+    extract::{MatchedPath, Request},
+    middleware::Next,
+    response::Response,
+};
+use std::sync::Arc;
+use std::time::Instant;
+use tracing::Instrument;
+
+/// Middleware that records request metrics and wraps each request in a
+/// tracing span, labelled with the route template, OpenAPI operationId and
+/// tags
+pub async fn metrics_middleware(
+    metrics: Option<Extension<Arc<Metrics>>>,
+    catalog: Option<Extension<Arc<RouteCatalog>>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let method = request.method().as_str().to_string();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|p| p.as_str().to_string())
+        .unwrap_or_else(|| "unmatched".to_string());
+    let route_info = catalog
+        .as_ref()
+        .and_then(|Extension(c)| c.get(&route, &method));
+    let operation_id = route_info
+        .and_then(|r| r.operation_id.clone())
+        .unwrap_or_default();
+    let tags = route_info.map(|r| r.tags.join(",")).unwrap_or_default();
+
+    let span = tracing::info_span!(
+        "request",
+        method = %method,
+        route = %route,
+        operation_id = %operation_id,
+        tags = %tags
+    );
+    let started = Instant::now();
+    let response = next.run(request).instrument(span).await;
+
+    if let Some(Extension(metrics)) = metrics {
+        metrics.record(
+            RequestLabels {
+                method,
+                route,
+                operation_id,
+                tags,
+                status: response.status().as_u16(),
+            },
+            started.elapsed(),
+        );
+    }
+    response
+}
@@ -1,8 +1,42 @@
 // SPDX-License-Identifier: Apache-2.0
 // Copyright 2024-2025 Dmytro Yemelianov
 
+pub mod audit;
 pub mod auth;
+pub mod concurrency;
+pub mod control;
 pub mod cors;
+pub mod custom;
+pub mod decompression;
+pub mod determinism;
+pub mod entitlements;
+pub mod headers;
+pub mod journal;
+pub mod latency;
+pub mod metrics;
+pub mod namespaces;
+pub mod proxy;
+pub mod rate_limit;
+pub mod shared_state;
+pub mod stubs;
+pub mod tenants;
 
+pub use audit::audit_middleware;
 pub use auth::auth_middleware;
+pub use concurrency::concurrency_middleware;
+pub use control::control_headers_middleware;
 pub use cors::cors_middleware;
+pub use custom::custom_handlers_middleware;
+pub use decompression::decompression_middleware;
+pub use determinism::determinism_middleware;
+pub use entitlements::entitlements_middleware;
+pub use headers::response_headers_middleware;
+pub use journal::journal_middleware;
+pub use latency::latency_middleware;
+pub use metrics::metrics_middleware;
+pub use namespaces::namespace_middleware;
+pub use proxy::proxy_middleware;
+pub use rate_limit::rate_limit_middleware;
+pub use shared_state::shared_state_middleware;
+pub use stubs::stubs_middleware;
+pub use tenants::tenant_middleware;
@@ -0,0 +1,242 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+//! Per-test state isolation through the `x-mock-namespace` header.
+//!
+//! A request naming a namespace is served by a router of its own, over a
+//! state of its own, created on the first request naming it. Parallel test
+//! workers sharing one mock thus never see each other's buckets, tokens or
+//! jobs; each starts from the state the server started with (configured
+//! users, clients and `--seed-dir` fixtures). `DELETE
+//! /__admin/namespaces/:name` drops a namespace; the admin API of a
+//! namespace is reached by sending the header to it, too.
+
+use crate::error::Result;
+use crate::state::StateManager;
+use axum::{
+    Extension, Json, Router,
+    extract::{MatchedPath, Request},
+    http::StatusCode,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use dashmap::DashMap;
+use serde_json::json;
+use std::sync::Arc;
+use tower::ServiceExt;
+
+/// Request header naming the namespace
+pub const NAMESPACE_HEADER: &str = "x-mock-namespace";
+
+/// Longest namespace name
+const MAX_NAME: usize = 128;
+
+/// Builds the state and router of a new namespace
+pub type NamespaceBuilder = Box<dyn Fn() -> Result<(StateManager, Router)> + Send + Sync>;
+
+/// The namespaces created so far, by name
+pub struct Namespaces {
+    namespaces: DashMap<String, (StateManager, Router)>,
+    build: NamespaceBuilder,
+}
+
+impl Namespaces {
+    pub fn new(build: NamespaceBuilder) -> Self {
+        Self {
+            namespaces: DashMap::new(),
+            build,
+        }
+    }
+
+    /// The state of a namespace, creating the namespace if needed
+    pub fn state(&self, name: &str) -> Result<StateManager> {
+        self.get_or_create(name).map(|(state, _)| state)
+    }
+
+    pub(crate) fn get_or_create(&self, name: &str) -> Result<(StateManager, Router)> {
+        let entry = self
+            .namespaces
+            .entry(name.to_string())
+            .or_try_insert_with(|| {
+                tracing::info!("Creating namespace {}", name);
+                (self.build)()
+            })?;
+        Ok(entry.value().clone())
+    }
+
+    /// Drop a namespace and its state; `false` if there is none
+    pub fn remove(&self, name: &str) -> bool {
+        let removed = self.namespaces.remove(name).is_some();
+        if removed {
+            tracing::info!("Dropped namespace {}", name);
+        }
+        removed
+    }
+
+    /// Drop every namespace
+    pub fn clear(&self) {
+        self.namespaces.clear();
+    }
+
+    /// The states of the namespaces, by name
+    pub(crate) fn states(&self) -> Vec<(String, StateManager)> {
+        let mut states: Vec<_> = self
+            .namespaces
+            .iter()
+            .map(|e| (e.key().clone(), e.value().0.clone()))
+            .collect();
+        states.sort_by(|a, b| a.0.cmp(&b.0));
+        states
+    }
+
+    /// Names of the namespaces, sorted
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.namespaces.iter().map(|e| e.key().clone()).collect();
+        names.sort();
+        names
+    }
+}
+
+/// Whether a namespace name is usable, also as a path segment
+pub fn valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= MAX_NAME
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+/// Middleware handing requests with a namespace header to the namespace's
+/// router
+pub async fn namespace_middleware(
+    namespaces: Option<Extension<Arc<Namespaces>>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let (Some(Extension(namespaces)), Some(name)) =
+        (namespaces, request.headers().get(NAMESPACE_HEADER))
+    else {
+        return next.run(request).await;
+    };
+    // Namespaces are managed from outside of them
+    let managing = format!("{}/namespaces", crate::admin::ADMIN_PREFIX);
+    if request.uri().path().starts_with(&managing) {
+        return next.run(request).await;
+    }
+    let Some(name) = name.to_str().ok().filter(|name| valid_name(name)) else {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "developerMessage": format!(
+                    "Invalid {}: use up to {} letters, digits, '-', '_' and '.'",
+                    NAMESPACE_HEADER, MAX_NAME
+                ),
+                "errorCode": "INVALID_NAMESPACE"
+            })),
+        )
+            .into_response();
+    };
+    let name = name.to_string();
+    match namespaces.get_or_create(&name) {
+        Ok((_, router)) => {
+            // Routed afresh, not as if nested below the server's route
+            request.extensions_mut().remove::<MatchedPath>();
+            match router.oneshot(request).await {
+                Ok(response) => response,
+                Err(never) => match never {},
+            }
+        }
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({ "message": format!("Cannot create namespace {}: {}", name, e) })),
+        )
+            .into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::MockServerConfig;
+    use crate::server::MockServer;
+    use axum::body::Body;
+    use serde_json::Value;
+
+    #[tokio::test]
+    async fn test_namespaces_isolate_state_until_dropped() {
+        let config = MockServerConfig {
+            openapi_dir: "/nonexistent".into(),
+            ..MockServerConfig::default()
+        };
+        let server = MockServer::new(config).await.unwrap();
+        let namespaces = server.namespaces().unwrap();
+        let token_b = namespaces
+            .state("test-b")
+            .unwrap()
+            .auth
+            .generate_token("client", 3600, Some("bucket:read".into()))
+            .access_token;
+        let call = |method: &str, uri: &str, namespace: &str, token: &str, body: Value| {
+            let request = Request::builder()
+                .method(method)
+                .uri(uri)
+                .header(NAMESPACE_HEADER, namespace)
+                .header("authorization", format!("Bearer {}", token))
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap();
+            let router = server.router();
+            async move {
+                let response = router.oneshot(request).await.unwrap();
+                let status = response.status();
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                (
+                    status,
+                    serde_json::from_slice::<Value>(&body).unwrap_or_default(),
+                )
+            }
+        };
+
+        // Public routes are served in namespaces too
+        let token = Request::post("/authentication/v2/token")
+            .header(NAMESPACE_HEADER, "test-a")
+            .header("content-type", "application/x-www-form-urlencoded")
+            .body(Body::from(
+                "client_id=ci&grant_type=client_credentials&scope=bucket:read%20bucket:create",
+            ))
+            .unwrap();
+        let token = server.router().oneshot(token).await.unwrap();
+        let token = axum::body::to_bytes(token.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let token: Value = serde_json::from_slice(&token).unwrap();
+        let token_a = token["access_token"].as_str().unwrap().to_string();
+
+        let bucket = json!({ "bucketKey": "shared-name", "policyKey": "transient" });
+        let (status, _) = call("POST", "/oss/v2/buckets", "test-a", &token_a, bucket).await;
+        assert_eq!(status, StatusCode::OK);
+        let (_, body) = call("GET", "/oss/v2/buckets", "test-a", &token_a, Value::Null).await;
+        assert_eq!(body["items"].as_array().unwrap().len(), 1);
+        let (_, body) = call("GET", "/oss/v2/buckets", "test-b", &token_b, Value::Null).await;
+        assert_eq!(body["items"].as_array().unwrap().len(), 0);
+        assert!(server.state().unwrap().buckets.list_buckets().is_empty());
+        // Tokens are namespaced too
+        let (status, _) = call("GET", "/oss/v2/buckets", "test-b", &token_a, Value::Null).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (status, _) = call("GET", "/oss/v2/buckets", "a/b", &token_a, Value::Null).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let (status, body) = call("GET", "/__admin/namespaces", "test-a", "", Value::Null).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["namespaces"], json!(["test-a", "test-b"]));
+        let drop_a = || call("DELETE", "/__admin/namespaces/test-a", "", "", Value::Null);
+        assert_eq!(drop_a().await.0, StatusCode::NO_CONTENT);
+        assert_eq!(drop_a().await.0, StatusCode::NOT_FOUND);
+        assert_eq!(namespaces.names(), ["test-b"]);
+        // A dropped namespace comes back empty
+        let (status, _) = call("GET", "/oss/v2/buckets", "test-a", &token_a, Value::Null).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+}
@@ -0,0 +1,330 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+//! Passthrough to the real APS (`--proxy-base`).
+//!
+//! Requests no stub answers and no built-in stateful handler serves, i.e.
+//! those the mock would otherwise answer from an OpenAPI spec or with a 404,
+//! are forwarded to the upstream and its response relayed as it is. The
+//! `Authorization` header goes along, so forwarded requests need a token
+//! the upstream accepts and the mock leaves authenticating them to it.
+//!
+//! Hybrid setups, where the services under test are mocked and the rest hit
+//! the real APIs, follow from this: stateful services and stubs stay local.
+//! With `--record`, forwarded exchanges are also written out as stubs
+//! ([`crate::stubs::record`]); with `--replay`, the requests that would be
+//! forwarded are answered from such recordings instead
+//! ([`crate::stubs::replay`]).
+
+use crate::server::catalog::{RouteCatalog, RouteInfo, RouteSource};
+use crate::stubs::record::Recorder;
+use crate::stubs::replay::Replay;
+use axum::{
+    Extension, Json,
+    body::Body,
+    extract::{MatchedPath, Request},
+    http::{HeaderMap, HeaderName, HeaderValue, StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde_json::json;
+use std::sync::Arc;
+
+/// Headers describing a single connection rather than the message, not
+/// forwarded in either direction
+const HOP_BY_HOP: [HeaderName; 8] = [
+    header::CONNECTION,
+    header::HOST,
+    header::CONTENT_LENGTH,
+    header::TRANSFER_ENCODING,
+    header::TE,
+    header::TRAILER,
+    header::UPGRADE,
+    header::PROXY_AUTHORIZATION,
+];
+
+/// The upstream requests are forwarded to
+pub struct Upstream {
+    base: reqwest::Url,
+    client: reqwest::Client,
+    recorder: Option<Recorder>,
+}
+
+impl Upstream {
+    /// An upstream at `base`, e.g. `https://developer.api.autodesk.com`
+    pub fn new(base: &str) -> Result<Self, String> {
+        let base = reqwest::Url::parse(base).map_err(|e| format!("{}: {}", base, e))?;
+        if !matches!(base.scheme(), "http" | "https") {
+            return Err(format!("{}: not an HTTP URL", base));
+        }
+        Ok(Self {
+            base,
+            client: reqwest::Client::new(),
+            recorder: None,
+        })
+    }
+
+    /// Also record every forwarded exchange
+    pub fn with_recorder(mut self, recorder: Recorder) -> Self {
+        self.recorder = Some(recorder);
+        self
+    }
+
+    pub fn base(&self) -> &reqwest::Url {
+        &self.base
+    }
+
+    /// The upstream URL of a request's path and query, below the base's path
+    fn url(&self, path_and_query: &str) -> String {
+        format!(
+            "{}{}",
+            self.base.as_str().trim_end_matches('/'),
+            path_and_query
+        )
+    }
+
+    /// Send a request upstream and relay the response; an unreachable
+    /// upstream answers 502
+    pub async fn forward(&self, request: Request) -> Response {
+        let (parts, body) = request.into_parts();
+        let path_and_query = parts
+            .uri
+            .path_and_query()
+            .map_or("/", |p| p.as_str())
+            .to_string();
+        // Uploads are buffered, which is fine at test sizes
+        let body = match axum::body::to_bytes(body, usize::MAX).await {
+            Ok(body) => body,
+            Err(e) => return bad_gateway(format!("Cannot read request body: {}", e)),
+        };
+        let url = self.url(&path_and_query);
+        // reqwest has its own `http` types; methods and headers go over as bytes
+        let Ok(method) = reqwest::Method::from_bytes(parts.method.as_str().as_bytes()) else {
+            return bad_gateway(format!("Cannot forward method {}", parts.method));
+        };
+        let mut headers = end_to_end(parts.headers);
+        // Recordings are redacted, so their bodies must arrive uncompressed
+        if self.recorder.is_some() {
+            headers.remove(header::ACCEPT_ENCODING);
+        }
+        let mut request = self.client.request(method, &url).body(body);
+        for (name, value) in &headers {
+            request = request.header(name.as_str(), value.as_bytes());
+        }
+        let upstream = request.send().await;
+        let upstream = match upstream {
+            Ok(upstream) => upstream,
+            Err(e) => return bad_gateway(format!("Upstream {} failed: {}", url, e)),
+        };
+
+        let status =
+            StatusCode::from_u16(upstream.status().as_u16()).unwrap_or(StatusCode::BAD_GATEWAY);
+        let mut headers = HeaderMap::new();
+        for (name, value) in upstream.headers() {
+            if let (Ok(name), Ok(value)) = (
+                HeaderName::from_bytes(name.as_str().as_bytes()),
+                HeaderValue::from_bytes(value.as_bytes()),
+            ) {
+                headers.append(name, value);
+            }
+        }
+        let headers = end_to_end(headers);
+        let body = match upstream.bytes().await {
+            Ok(body) => body,
+            Err(e) => return bad_gateway(format!("Upstream {} failed: {}", url, e)),
+        };
+        tracing::debug!("Forwarded to {} ({})", url, status);
+        if let Some(recorder) = &self.recorder {
+            recorder.record(
+                parts.method.as_str(),
+                &path_and_query,
+                status.as_u16(),
+                &headers,
+                &body,
+            );
+        }
+        let mut response = Response::new(Body::from(body));
+        *response.status_mut() = status;
+        *response.headers_mut() = headers;
+        response
+    }
+}
+
+/// Whether a request for `route` (`None`: no route matched) is forwarded
+/// rather than served by a built-in handler
+pub fn forwards(route: Option<&RouteInfo>) -> bool {
+    route.is_none_or(|route| route.source == RouteSource::OpenApi)
+}
+
+fn end_to_end(mut headers: HeaderMap) -> HeaderMap {
+    for name in &HOP_BY_HOP {
+        headers.remove(name);
+    }
+    headers
+}
+
+fn bad_gateway(message: String) -> Response {
+    tracing::warn!("{}", message);
+    (
+        StatusCode::BAD_GATEWAY,
+        Json(json!({ "developerMessage": message, "errorCode": "BAD_GATEWAY" })),
+    )
+        .into_response()
+}
+
+/// Middleware forwarding requests without a built-in handler upstream, or
+/// replaying them; runs inside the stubs, which answer first
+pub async fn proxy_middleware(
+    upstream: Option<Extension<Arc<Upstream>>>,
+    replay: Option<Extension<Arc<Replay>>>,
+    catalog: Option<Extension<Arc<RouteCatalog>>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if upstream.is_none() && replay.is_none() {
+        return next.run(request).await;
+    }
+    let path = request.uri().path();
+    if path.starts_with(crate::admin::ADMIN_PREFIX) || path.starts_with("/__mock/") {
+        return next.run(request).await;
+    }
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .zip(catalog.as_ref())
+        .and_then(|(matched, Extension(catalog))| {
+            catalog.get(matched.as_str(), request.method().as_str())
+        });
+    if !forwards(route) {
+        return next.run(request).await;
+    }
+    match (upstream, replay) {
+        (Some(Extension(upstream)), _) => upstream.forward(request).await,
+        (None, Some(Extension(replay))) => replay.answer(request).await,
+        (None, None) => next.run(request).await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::openapi::types::HttpMethod;
+    use axum::routing::get;
+    use flate2::{Compression, write::GzEncoder};
+    use std::io::Write;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_requests_without_builtin_handler_are_forwarded() {
+        // An upstream echoing what it received
+        let echo = axum::Router::new().fallback(|request: Request| async move {
+            let authorization = request
+                .headers()
+                .get(header::AUTHORIZATION)
+                .and_then(|v| v.to_str().ok())
+                .unwrap_or_default()
+                .to_string();
+            (
+                StatusCode::ACCEPTED,
+                format!("{} {}", request.uri(), authorization),
+            )
+        });
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, echo).await });
+
+        let mut catalog = RouteCatalog::new();
+        catalog.register(RouteInfo::builtin("/oss/v2/buckets", HttpMethod::Get));
+        let router = axum::Router::new()
+            .route("/oss/v2/buckets", get(|| async { "local" }))
+            .layer(axum::middleware::from_fn(proxy_middleware))
+            .layer(Extension(Arc::new(catalog)))
+            .layer(Extension(Arc::new(
+                Upstream::new(&format!("http://{}", address)).unwrap(),
+            )));
+        let call = |path: &str| {
+            let request = Request::get(path)
+                .header(header::AUTHORIZATION, "Bearer real-aps-token")
+                .body(Body::empty())
+                .unwrap();
+            router.clone().oneshot(request)
+        };
+        let text = |response: Response| async {
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            String::from_utf8(body.to_vec()).unwrap()
+        };
+
+        let local = call("/oss/v2/buckets").await.unwrap();
+        assert_eq!(local.status(), StatusCode::OK);
+        assert_eq!(text(local).await, "local");
+
+        let forwarded = call("/construction/issues/v1/projects/p1/issues?limit=1")
+            .await
+            .unwrap();
+        assert_eq!(forwarded.status(), StatusCode::ACCEPTED);
+        assert_eq!(
+            text(forwarded).await,
+            "/construction/issues/v1/projects/p1/issues?limit=1 Bearer real-aps-token"
+        );
+
+        assert!(Upstream::new("ftp://example.com").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_recorded_responses_are_requested_uncompressed() {
+        // An upstream gzipping its responses for clients accepting it
+        let upstream = axum::Router::new().fallback(|headers: HeaderMap| async move {
+            let json = br#"{"access_token":"live-secret"}"#;
+            let gzip = headers
+                .get(header::ACCEPT_ENCODING)
+                .and_then(|v| v.to_str().ok())
+                .is_some_and(|v| v.contains("gzip"));
+            let mut response = if gzip {
+                let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(json).unwrap();
+                let mut response = Response::new(Body::from(encoder.finish().unwrap()));
+                response
+                    .headers_mut()
+                    .insert(header::CONTENT_ENCODING, HeaderValue::from_static("gzip"));
+                response
+            } else {
+                Response::new(Body::from(&json[..]))
+            };
+            response.headers_mut().insert(
+                header::CONTENT_TYPE,
+                HeaderValue::from_static("application/json"),
+            );
+            response
+        });
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, upstream).await });
+
+        let dir = tempfile::tempdir().unwrap();
+        let upstream = Upstream::new(&format!("http://{}", address))
+            .unwrap()
+            .with_recorder(Recorder::new(dir.path()).unwrap());
+        let router = axum::Router::new()
+            .layer(axum::middleware::from_fn(proxy_middleware))
+            .layer(Extension(Arc::new(upstream)));
+        let request = Request::post("/authentication/v2/token")
+            .header(header::ACCEPT_ENCODING, "gzip, deflate")
+            .body(Body::empty())
+            .unwrap();
+        let response = router.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(!response.headers().contains_key(header::CONTENT_ENCODING));
+
+        let mapping = std::fs::read_dir(dir.path().join(crate::stubs::MAPPINGS_DIR))
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap()
+            .path();
+        let mapping = std::fs::read_to_string(mapping).unwrap();
+        assert!(mapping.contains(crate::stubs::record::REDACTED));
+        assert!(!mapping.contains("live-secret"));
+    }
+}
@@ -0,0 +1,264 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+//! Per-caller rate limits (`--rate-limit`), so SDK backoff on 429 responses
+//! can be exercised against the mock.
+//!
+//! Each limit is a token bucket per caller: the client ID of the Bearer
+//! token when the mock issued it, the token itself otherwise, and one shared
+//! bucket for requests without a token. Buckets start full and refill
+//! evenly over the limit's period, so a caller may burst up to the count and
+//! then makes a request every period/count. A request matching several
+//! limits needs budget left in each, and a rejected request spends none.
+//! Callers idle for the longest period are forgotten, their buckets being
+//! full again by then. Admin routes are never limited.
+
+use crate::admin::ADMIN_PREFIX;
+use crate::config::RateLimit;
+use crate::server::catalog::RouteCatalog;
+use crate::state::StateManager;
+use axum::{
+    Extension, Json,
+    extract::{MatchedPath, Request},
+    http::{StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use dashmap::DashMap;
+use serde_json::json;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Budget left to one caller under one limit
+struct Bucket {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+/// Budget left to one caller under every limit, by limit
+struct Caller {
+    buckets: Vec<Bucket>,
+    seen_at: Instant,
+}
+
+/// Buckets of every configured limit, by caller
+pub struct RateLimiter {
+    limits: Vec<RateLimit>,
+    callers: DashMap<String, Caller>,
+    /// After this long without requests a caller's buckets are full again
+    idle_after: Duration,
+    swept_at: Mutex<Instant>,
+}
+
+impl RateLimiter {
+    pub fn new(limits: &[RateLimit]) -> Self {
+        Self {
+            limits: limits.to_vec(),
+            callers: DashMap::new(),
+            idle_after: limits.iter().map(|l| l.period).max().unwrap_or_default(),
+            swept_at: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Take a request from the buckets of `caller` under the limits
+    /// `matching`, or return the limit whose bucket takes longest to have
+    /// one available and how long that is; nothing is taken then
+    fn take(&self, matching: &[usize], caller: &str) -> Result<(), (usize, Duration)> {
+        let now = Instant::now();
+        let mut caller = self
+            .callers
+            .entry(caller.to_string())
+            .or_insert_with(|| Caller {
+                buckets: self
+                    .limits
+                    .iter()
+                    .map(|limit| Bucket {
+                        tokens: f64::from(limit.requests),
+                        refilled_at: now,
+                    })
+                    .collect(),
+                seen_at: now,
+            });
+        caller.seen_at = now;
+        let mut wait: Option<(usize, Duration)> = None;
+        for &index in matching {
+            let limit = &self.limits[index];
+            let capacity = f64::from(limit.requests);
+            let rate = capacity / limit.period.as_secs_f64();
+            let bucket = &mut caller.buckets[index];
+            let elapsed = now.duration_since(bucket.refilled_at).as_secs_f64();
+            bucket.tokens = (bucket.tokens + elapsed * rate).min(capacity);
+            bucket.refilled_at = now;
+            if bucket.tokens < 1.0 {
+                let needed = Duration::from_secs_f64((1.0 - bucket.tokens) / rate);
+                if wait.is_none_or(|(_, longest)| needed > longest) {
+                    wait = Some((index, needed));
+                }
+            }
+        }
+        if let Some(wait) = wait {
+            return Err(wait);
+        }
+        for &index in matching {
+            caller.buckets[index].tokens -= 1.0;
+        }
+        Ok(())
+    }
+
+    /// Forget the callers idle since before `now - idle_after`, at most once
+    /// per `idle_after`
+    fn sweep(&self, now: Instant) {
+        {
+            let mut swept_at = self.swept_at.lock().unwrap_or_else(|e| e.into_inner());
+            if now.saturating_duration_since(*swept_at) < self.idle_after {
+                return;
+            }
+            *swept_at = now;
+        }
+        self.callers
+            .retain(|_, caller| now.saturating_duration_since(caller.seen_at) < self.idle_after);
+    }
+}
+
+/// Middleware answering 429 once a caller has used up the budget of a
+/// matching limit
+pub async fn rate_limit_middleware(
+    limiter: Option<Extension<Arc<RateLimiter>>>,
+    catalog: Option<Extension<Arc<RouteCatalog>>>,
+    state: Option<Extension<StateManager>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(Extension(limiter)) = limiter else {
+        return next.run(request).await;
+    };
+
+    let method = request.method().as_str().to_string();
+    let path = request.uri().path().to_string();
+    if path.starts_with(ADMIN_PREFIX) {
+        return next.run(request).await;
+    }
+    let tags = request
+        .extensions()
+        .get::<MatchedPath>()
+        .zip(catalog.as_ref())
+        .and_then(|(route, Extension(catalog))| catalog.get(route.as_str(), &method))
+        .map(|route| route.tags.clone())
+        .unwrap_or_default();
+    let token = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|s| s.strip_prefix("Bearer "));
+    let caller = match token {
+        Some(token) => state
+            .and_then(|Extension(state)| state.auth.lookup_token(token))
+            .map(|info| info.client_id)
+            .unwrap_or_else(|| token.to_string()),
+        None => String::new(),
+    };
+
+    let matching: Vec<usize> = (0..limiter.limits.len())
+        .filter(|&index| limiter.limits[index].route.matches(&method, &path, &tags))
+        .collect();
+    if matching.is_empty() {
+        return next.run(request).await;
+    }
+    limiter.sweep(Instant::now());
+    if let Err((index, wait)) = limiter.take(&matching, &caller) {
+        return too_many_requests(&limiter.limits[index], wait);
+    }
+    next.run(request).await
+}
+
+fn too_many_requests(limit: &RateLimit, wait: Duration) -> Response {
+    let retry_after = wait.as_secs_f64().ceil().max(1.0) as u64;
+    (
+        StatusCode::TOO_MANY_REQUESTS,
+        [(header::RETRY_AFTER, retry_after.to_string())],
+        Json(json!({
+            "developerMessage": format!(
+                "Rate limit exceeded: at most {} requests per {} seconds",
+                limit.requests,
+                limit.period.as_secs()
+            ),
+            "errorCode": "TOO_MANY_REQUESTS"
+        })),
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::routing::get;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_callers_get_their_own_budget() {
+        let limits = vec!["GET /limited=2/min".parse::<RateLimit>().unwrap()];
+        let router = axum::Router::new()
+            .route("/limited", get(|| async { "done" }))
+            .route("/free", get(|| async { "done" }))
+            .layer(axum::middleware::from_fn(rate_limit_middleware))
+            .layer(Extension(Arc::new(RateLimiter::new(&limits))));
+        let call = |path: &str, token: &str| {
+            let request = Request::get(path)
+                .header(header::AUTHORIZATION, format!("Bearer {}", token))
+                .body(Body::empty())
+                .unwrap();
+            router.clone().oneshot(request)
+        };
+
+        for _ in 0..2 {
+            assert_eq!(
+                call("/limited", "a").await.unwrap().status(),
+                StatusCode::OK
+            );
+        }
+        let rejected = call("/limited", "a").await.unwrap();
+        assert_eq!(rejected.status(), StatusCode::TOO_MANY_REQUESTS);
+        // A request refills every 30 seconds
+        assert_eq!(rejected.headers()[header::RETRY_AFTER], "30");
+
+        assert_eq!(
+            call("/limited", "b").await.unwrap().status(),
+            StatusCode::OK
+        );
+        assert_eq!(call("/free", "a").await.unwrap().status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_rejected_requests_spend_no_budget() {
+        let limits = vec![
+            "/**=3/min".parse::<RateLimit>().unwrap(),
+            "GET /limited=1/min".parse::<RateLimit>().unwrap(),
+        ];
+        let limiter = Arc::new(RateLimiter::new(&limits));
+        let router = axum::Router::new()
+            .route("/limited", get(|| async { "done" }))
+            .route("/free", get(|| async { "done" }))
+            .layer(axum::middleware::from_fn(rate_limit_middleware))
+            .layer(Extension(limiter.clone()));
+        let call = |path: &str| {
+            let request = Request::get(path)
+                .header(header::AUTHORIZATION, "Bearer a")
+                .body(Body::empty())
+                .unwrap();
+            let router = router.clone();
+            async move { router.oneshot(request).await.unwrap().status() }
+        };
+
+        assert_eq!(call("/limited").await, StatusCode::OK);
+        assert_eq!(call("/limited").await, StatusCode::TOO_MANY_REQUESTS);
+        // The rejection left two requests under the first limit
+        assert_eq!(call("/free").await, StatusCode::OK);
+        assert_eq!(call("/free").await, StatusCode::OK);
+        assert_eq!(call("/free").await, StatusCode::TOO_MANY_REQUESTS);
+
+        assert_eq!(limiter.callers.len(), 1);
+        limiter.sweep(Instant::now() + Duration::from_secs(60));
+        assert!(limiter.callers.is_empty());
+    }
+}
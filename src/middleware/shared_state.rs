@@ -0,0 +1,228 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+//! Keeps the state of several mock servers behind a load balancer in step
+//! through a shared [`StateBackend`] such as Redis.
+//!
+//! Before a request is handled the state is reloaded if another server saved
+//! since; after a request that may change state (anything but GET, HEAD and
+//! OPTIONS) it is saved. Changing requests run one at a time per server, so
+//! a reload never drops a change that has not been saved yet.
+
+use crate::state::StateManager;
+use crate::state::backend::StateBackend;
+use axum::{
+    Extension,
+    extract::Request,
+    http::Method,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde_json::json;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+/// State kept in step with a shared backend
+pub struct SharedState {
+    state: StateManager,
+    backend: Arc<dyn StateBackend>,
+    /// Held for writing while the state is reloaded or changed
+    lock: RwLock<()>,
+}
+
+impl SharedState {
+    pub fn new(state: StateManager, backend: Arc<dyn StateBackend>) -> Self {
+        Self {
+            state,
+            backend,
+            lock: RwLock::new(()),
+        }
+    }
+
+    /// Reload the state if another server saved; call with the write lock
+    async fn refresh(&self) -> crate::error::Result<()> {
+        let (state, backend) = (self.state.clone(), self.backend.clone());
+        run_blocking(move || {
+            if backend.is_stale()? {
+                tracing::debug!("Reloading the state saved by another server");
+                state.reload_from(backend.as_ref())?;
+            }
+            Ok(())
+        })
+        .await
+    }
+
+    async fn save(&self) -> crate::error::Result<()> {
+        let (state, backend) = (self.state.clone(), self.backend.clone());
+        run_blocking(move || state.save_to(backend.as_ref())).await
+    }
+
+    async fn is_stale(&self) -> crate::error::Result<bool> {
+        let backend = self.backend.clone();
+        run_blocking(move || backend.is_stale()).await
+    }
+}
+
+/// Run backend I/O off the async workers
+async fn run_blocking<T: Send + 'static>(
+    task: impl FnOnce() -> crate::error::Result<T> + Send + 'static,
+) -> crate::error::Result<T> {
+    tokio::task::spawn_blocking(task)
+        .await
+        .map_err(|e| crate::error::MockError::State(e.to_string()))?
+}
+
+/// Middleware reloading the state before and saving it after requests
+pub async fn shared_state_middleware(
+    shared: Option<Extension<Arc<SharedState>>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(Extension(shared)) = shared else {
+        return next.run(request).await;
+    };
+    let changes = !matches!(
+        *request.method(),
+        Method::GET | Method::HEAD | Method::OPTIONS
+    );
+
+    if changes {
+        let _guard = shared.lock.write().await;
+        if let Err(e) = shared.refresh().await {
+            return unavailable(e);
+        }
+        let response = next.run(request).await;
+        if let Err(e) = shared.save().await {
+            tracing::warn!("Saving the shared state failed: {}", e);
+        }
+        return response;
+    }
+
+    match shared.is_stale().await {
+        Ok(false) => {}
+        Ok(true) => {
+            let _guard = shared.lock.write().await;
+            if let Err(e) = shared.refresh().await {
+                return unavailable(e);
+            }
+        }
+        Err(e) => return unavailable(e),
+    }
+    let _guard = shared.lock.read().await;
+    next.run(request).await
+}
+
+fn unavailable(e: crate::error::MockError) -> Response {
+    tracing::warn!("Reading the shared state failed: {}", e);
+    (
+        axum::http::StatusCode::SERVICE_UNAVAILABLE,
+        axum::Json(json!({ "message": format!("Shared state unavailable: {}", e) })),
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::persistence::StateFile;
+    use axum::body::Body;
+    use axum::routing::{get, post};
+    use std::sync::Mutex;
+    use tower::ServiceExt;
+
+    /// An in-memory store two servers share, bumping a revision per save
+    #[derive(Default)]
+    struct Store {
+        saved: Mutex<(Option<serde_json::Value>, u64)>,
+    }
+
+    struct Handle {
+        store: Arc<Store>,
+        seen: Mutex<u64>,
+    }
+
+    impl StateBackend for Handle {
+        fn load(&self) -> crate::error::Result<Option<StateFile>> {
+            let saved = self.store.saved.lock().unwrap();
+            *self.seen.lock().unwrap() = saved.1;
+            Ok(saved
+                .0
+                .clone()
+                .map(|file| serde_json::from_value(file).unwrap()))
+        }
+
+        fn save(&self, state: &StateFile) -> crate::error::Result<()> {
+            let mut saved = self.store.saved.lock().unwrap();
+            *saved = (Some(serde_json::to_value(state).unwrap()), saved.1 + 1);
+            *self.seen.lock().unwrap() = saved.1;
+            Ok(())
+        }
+
+        fn location(&self) -> String {
+            "shared".into()
+        }
+
+        fn is_shared(&self) -> bool {
+            true
+        }
+
+        fn is_stale(&self) -> crate::error::Result<bool> {
+            Ok(self.store.saved.lock().unwrap().1 != *self.seen.lock().unwrap())
+        }
+    }
+
+    fn server(store: &Arc<Store>) -> axum::Router {
+        let state = StateManager::new();
+        let backend = Arc::new(Handle {
+            store: store.clone(),
+            seen: Mutex::new(0),
+        });
+        let buckets = state.clone();
+        let created = state.clone();
+        axum::Router::new()
+            .route(
+                "/buckets",
+                get(move || {
+                    let buckets = buckets.clone();
+                    async move { buckets.buckets.list_buckets().len().to_string() }
+                }),
+            )
+            .route(
+                "/buckets/:key",
+                post(
+                    move |axum::extract::Path(key): axum::extract::Path<String>| {
+                        let created = created.clone();
+                        async move {
+                            created.buckets.create_bucket(key, "transient".into());
+                        }
+                    },
+                ),
+            )
+            .layer(axum::middleware::from_fn(shared_state_middleware))
+            .layer(Extension(Arc::new(SharedState::new(state, backend))))
+    }
+
+    async fn call(router: &axum::Router, method: Method, uri: &str) -> String {
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .body(Body::empty())
+            .unwrap();
+        let response = router.clone().oneshot(request).await.unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        String::from_utf8(body.to_vec()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_servers_see_each_others_changes() {
+        let store = Arc::new(Store::default());
+        let (first, second) = (server(&store), server(&store));
+
+        call(&first, Method::POST, "/buckets/one").await;
+        assert_eq!(call(&second, Method::GET, "/buckets").await, "1");
+        call(&second, Method::POST, "/buckets/two").await;
+        assert_eq!(call(&first, Method::GET, "/buckets").await, "2");
+    }
+}
@@ -0,0 +1,76 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+use crate::middleware::control::MockOverrides;
+use crate::middleware::determinism::RequestFingerprint;
+use crate::stubs::StubStore;
+use crate::templating::TemplateContext;
+use axum::{
+    Extension,
+    body::{Body, Bytes, HttpBody},
+    extract::Request,
+    middleware::Next,
+    response::Response,
+};
+use std::sync::Arc;
+
+/// Largest request body buffered to match `bodyPatterns` against; larger
+/// bodies, and bodies of unknown length, match no body pattern
+const MAX_MATCHED_BODY: u64 = 16 * 1024 * 1024;
+
+/// Buffer the body of a request if `wanted` and it is small enough to
+/// match, handing on a copy in the request
+pub(crate) async fn buffer_body(request: Request, wanted: bool) -> (Request, Option<Bytes>) {
+    let (parts, body) = request.into_parts();
+    let fits = body
+        .size_hint()
+        .upper()
+        .is_some_and(|upper| upper <= MAX_MATCHED_BODY);
+    let (body, bytes) = if wanted && fits {
+        match axum::body::to_bytes(body, MAX_MATCHED_BODY as usize).await {
+            Ok(bytes) => (Body::from(bytes.clone()), Some(bytes)),
+            Err(_) => (Body::empty(), None),
+        }
+    } else {
+        (body, None)
+    };
+    (Request::from_parts(parts, body), bytes)
+}
+
+/// Middleware that answers requests matching a loaded stub, ahead of the
+/// OpenAPI and built-in routes
+pub async fn stubs_middleware(
+    stubs: Option<Extension<Arc<StubStore>>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(Extension(stubs)) = stubs else {
+        return next.run(request).await;
+    };
+
+    // Body patterns need the body
+    let (request, bytes) = buffer_body(request, stubs.matches_bodies()).await;
+
+    let method = request.method().as_str();
+    let path = request.uri().path();
+    let query = request.uri().query();
+    let scenario = request
+        .extensions()
+        .get::<MockOverrides>()
+        .and_then(|o| o.scenario.as_deref());
+    match stubs.find(
+        method,
+        path,
+        query,
+        request.headers(),
+        bytes.as_deref(),
+        scenario,
+    ) {
+        Some(stub) => {
+            let context = TemplateContext::new(method, path, query, request.headers())
+                .with_fingerprint(request.extensions().get::<RequestFingerprint>());
+            stubs.respond(&stub, context).await
+        }
+        None => next.run(request).await,
+    }
+}
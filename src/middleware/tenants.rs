@@ -0,0 +1,227 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+//! Per-app state isolation (`--isolate-clients`).
+//!
+//! As in APS, where buckets belong to the app that created them, every app
+//! (the client ID of the Bearer token) gets buckets, objects, hubs, issues,
+//! hooks and jobs of its own, in a [`StateManager::partition`] created on
+//! its first request. Tokens, clients, users, the clock and the request
+//! journal stay shared, so tokens are issued as usual and `verify` sees
+//! every app's requests.
+//!
+//! Requests without a valid token and the admin API see the default state,
+//! which also holds the `--seed-dir` fixtures. The exception are the
+//! token-less URLs the mock hands out (`/__mock/...` downloads and signed
+//! S3 uploads), which go to the app holding the upload or job they name.
+//! `POST /__admin/reset` drops the apps' resources along with the rest.
+
+use crate::middleware::namespaces::Namespaces;
+use crate::state::StateManager;
+use axum::{
+    Extension, Router,
+    extract::{MatchedPath, Request},
+    http::{Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::sync::Arc;
+use tower::ServiceExt;
+
+/// The resources of each app, by client ID
+pub struct Tenants {
+    /// The default state, which issues and resolves the tokens
+    state: StateManager,
+    partitions: Namespaces,
+}
+
+impl Tenants {
+    pub fn new(state: &StateManager, partitions: Namespaces) -> Self {
+        Self {
+            state: state.clone(),
+            partitions,
+        }
+    }
+
+    /// The state of an app, creating it if needed
+    pub fn state(&self, client_id: &str) -> crate::error::Result<StateManager> {
+        self.partitions.state(client_id)
+    }
+
+    /// Client IDs of the apps that made requests, sorted
+    pub fn client_ids(&self) -> Vec<String> {
+        self.partitions.names()
+    }
+
+    async fn dispatch(&self, client_id: &str, mut request: Request) -> Response {
+        match self.partitions.get_or_create(client_id) {
+            Ok((_, router)) => {
+                // Routed afresh, not as if nested below the server's route
+                request.extensions_mut().remove::<MatchedPath>();
+                call(router, request).await
+            }
+            Err(e) => {
+                tracing::error!("Cannot create the state of {}: {}", client_id, e);
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+        }
+    }
+
+    /// Hand a token-less request to the app that minted its URL, or to the
+    /// default state when no app did
+    async fn dispatch_unowned(&self, request: Request, next: Next) -> Response {
+        let path = request.uri().path();
+        let owner = self
+            .partitions
+            .states()
+            .into_iter()
+            .find(|(_, state)| crate::server::packs::minted_by(state, path));
+        match owner {
+            Some((client_id, _)) => self.dispatch(&client_id, request).await,
+            None => next.run(request).await,
+        }
+    }
+}
+
+async fn call(router: Router, request: Request) -> Response {
+    match router.oneshot(request).await {
+        Ok(response) => response,
+        Err(never) => match never {},
+    }
+}
+
+/// Middleware handing requests of an app to the app's state
+pub async fn tenant_middleware(
+    tenants: Option<Extension<Arc<Tenants>>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(Extension(tenants)) = tenants else {
+        return next.run(request).await;
+    };
+    let path = request.uri().path();
+    if path.starts_with(crate::admin::ADMIN_PREFIX) {
+        let reset = request.method() == Method::POST
+            && path == format!("{}/reset", crate::admin::ADMIN_PREFIX);
+        let response = next.run(request).await;
+        if reset && response.status().is_success() {
+            tenants.partitions.clear();
+        }
+        return response;
+    }
+    if let Some(client_id) = crate::server::packs::request_client(&tenants.state, request.headers())
+    {
+        return tenants.dispatch(&client_id, request).await;
+    }
+    if path.starts_with("/__mock/") {
+        return tenants.dispatch_unowned(request, next).await;
+    }
+    next.run(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::MockServerConfig;
+    use crate::server::MockServer;
+    use axum::body::Body;
+    use serde_json::{Value, json};
+
+    #[tokio::test]
+    async fn test_apps_only_see_their_own_buckets() {
+        let config = MockServerConfig {
+            openapi_dir: "/nonexistent".into(),
+            isolate_clients: true,
+            ..MockServerConfig::default()
+        };
+        let server = MockServer::new(config).await.unwrap();
+        let state = server.state().unwrap();
+        let token = |client_id: &str| {
+            state
+                .auth
+                .generate_token(
+                    client_id,
+                    3600,
+                    Some("bucket:read bucket:create data:write".into()),
+                )
+                .access_token
+        };
+        let (token_a, token_b) = (token("app-a"), token("app-b"));
+        let call = |method: Method, uri: &str, token: &str, body: Value| {
+            let request = Request::builder()
+                .method(method)
+                .uri(uri)
+                .header("authorization", format!("Bearer {}", token))
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap();
+            let router = server.router();
+            async move {
+                let response = router.oneshot(request).await.unwrap();
+                let status = response.status();
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                (
+                    status,
+                    serde_json::from_slice::<Value>(&body).unwrap_or_default(),
+                )
+            }
+        };
+        let buckets = |token| call(Method::GET, "/oss/v2/buckets", token, Value::Null);
+
+        let bucket = json!({ "bucketKey": "designs", "policyKey": "transient" });
+        let (status, _) = call(Method::POST, "/oss/v2/buckets", &token_a, bucket.clone()).await;
+        assert_eq!(status, StatusCode::OK);
+        // Bucket keys are unique per app only
+        let (status, _) = call(Method::POST, "/oss/v2/buckets", &token_b, bucket).await;
+        assert_eq!(status, StatusCode::OK);
+        let only_a = json!({ "bucketKey": "only-a", "policyKey": "transient" });
+        call(Method::POST, "/oss/v2/buckets", &token_a, only_a).await;
+        assert_eq!(
+            buckets(&token_a).await.1["items"].as_array().unwrap().len(),
+            2
+        );
+        assert_eq!(
+            buckets(&token_b).await.1["items"].as_array().unwrap().len(),
+            1
+        );
+        assert!(state.buckets.list_buckets().is_empty());
+        let tenants = server.tenants().unwrap();
+        assert_eq!(tenants.client_ids(), ["app-a", "app-b"]);
+        // The journal is shared
+        assert_eq!(state.journal.entries().len(), 5);
+
+        // Signed upload URLs carry no token, and reach the app handing them out
+        let (_, body) = call(
+            Method::GET,
+            "/oss/v2/buckets/designs/objects/model.rvt/signeds3upload",
+            &token_b,
+            Value::Null,
+        )
+        .await;
+        let url = body["urls"][0].as_str().unwrap();
+        let upload = &url[url.find("/__mock/").unwrap()..];
+        let (status, _) = call(Method::PUT, upload, "", json!("part")).await;
+        assert_eq!(status, StatusCode::OK);
+        let journaled: Vec<_> = state
+            .journal
+            .entries()
+            .into_iter()
+            .filter(|entry| entry.path == upload)
+            .map(|entry| entry.status)
+            .collect();
+        assert_eq!(journaled, [200]);
+
+        let (status, _) = call(Method::POST, "/__admin/reset", "", Value::Null).await;
+        assert!(status.is_success());
+        assert!(tenants.client_ids().is_empty());
+        assert_eq!(
+            buckets(&token("app-a")).await.1["items"]
+                .as_array()
+                .unwrap()
+                .len(),
+            0
+        );
+    }
+}
@@ -0,0 +1,203 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+//! On-disk cache of parsed specs, so repeated startups skip YAML parsing.
+//!
+//! Each spec file gets a MessagePack entry holding the parsed [`OpenApiSpec`]
+//! and a stamp (mtime, size, xxh3 hash) of the file and of every file its
+//! `$ref`s pulled in. An entry is used while all stamps still match: when a
+//! file's mtime changed but its content hash did not (e.g. after a fresh
+//! checkout) the entry is still valid. Entries written by another raps-mock
+//! version are ignored.
+
+use crate::openapi::types::OpenApiSpec;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use xxhash_rust::xxh3::xxh3_64;
+
+const ENTRY_EXTENSION: &str = "msgpack";
+
+/// Directory of cached specs
+#[derive(Debug, Clone)]
+pub struct SpecCache {
+    dir: PathBuf,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    version: String,
+    sources: Vec<FileStamp>,
+    spec: OpenApiSpec,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct FileStamp {
+    path: PathBuf,
+    modified_nanos: u128,
+    len: u64,
+    hash: u64,
+}
+
+impl FileStamp {
+    fn of(path: &Path) -> Option<Self> {
+        let metadata = fs::metadata(path).ok()?;
+        Some(Self {
+            path: path.to_path_buf(),
+            modified_nanos: modified_nanos(&metadata)?,
+            len: metadata.len(),
+            hash: xxh3_64(&fs::read(path).ok()?),
+        })
+    }
+
+    /// Whether the file still has the stamped content; only hashes it when
+    /// the mtime or size changed
+    fn is_current(&self) -> bool {
+        let Ok(metadata) = fs::metadata(&self.path) else {
+            return false;
+        };
+        if metadata.len() != self.len {
+            return false;
+        }
+        if modified_nanos(&metadata) == Some(self.modified_nanos) {
+            return true;
+        }
+        fs::read(&self.path).is_ok_and(|content| xxh3_64(&content) == self.hash)
+    }
+}
+
+fn modified_nanos(metadata: &fs::Metadata) -> Option<u128> {
+    Some(
+        metadata
+            .modified()
+            .ok()?
+            .duration_since(UNIX_EPOCH)
+            .ok()?
+            .as_nanos(),
+    )
+}
+
+impl SpecCache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// The cached spec parsed from `path`, if none of its sources changed
+    pub fn get(&self, path: &Path) -> Option<OpenApiSpec> {
+        let bytes = fs::read(self.entry_path(path)?).ok()?;
+        let entry: Entry = match rmp_serde::from_slice(&bytes) {
+            Ok(entry) => entry,
+            Err(e) => {
+                tracing::debug!(
+                    "Ignoring unreadable cache entry for {}: {}",
+                    path.display(),
+                    e
+                );
+                return None;
+            }
+        };
+        (entry.version == env!("CARGO_PKG_VERSION")
+            && entry.sources.iter().all(FileStamp::is_current))
+        .then_some(entry.spec)
+    }
+
+    /// Cache the spec parsed from `path`, which also read `sources`.
+    /// Failures are logged, a missing entry only costs a parse.
+    pub fn put(&self, path: &Path, sources: &[PathBuf], spec: &OpenApiSpec) {
+        if let Err(e) = self.write(path, sources, spec) {
+            tracing::warn!("Failed to cache {}: {}", path.display(), e);
+        }
+    }
+
+    fn write(&self, path: &Path, sources: &[PathBuf], spec: &OpenApiSpec) -> std::io::Result<()> {
+        let Some(entry_path) = self.entry_path(path) else {
+            return Ok(());
+        };
+        let sources = std::iter::once(path)
+            .chain(sources.iter().map(PathBuf::as_path))
+            .map(|source| {
+                FileStamp::of(source).ok_or_else(|| {
+                    std::io::Error::other(format!("cannot stamp {}", source.display()))
+                })
+            })
+            .collect::<std::io::Result<_>>()?;
+        let entry = Entry {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            sources,
+            spec: spec.clone(),
+        };
+        let bytes = rmp_serde::to_vec_named(&entry).map_err(std::io::Error::other)?;
+
+        fs::create_dir_all(&self.dir)?;
+        // Written aside and renamed, so concurrent startups never read a
+        // partial entry
+        let partial = entry_path.with_extension(format!("{}.partial", std::process::id()));
+        fs::write(&partial, bytes)?;
+        fs::rename(&partial, &entry_path)
+    }
+
+    /// Entry file for a spec, named after the hash of its canonical path
+    fn entry_path(&self, path: &Path) -> Option<PathBuf> {
+        let canonical = path.canonicalize().ok()?;
+        let key = xxh3_64(canonical.to_string_lossy().as_bytes());
+        Some(self.dir.join(format!("{:016x}.{}", key, ENTRY_EXTENSION)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::openapi::OpenApiParser;
+
+    #[test]
+    fn test_entries_are_invalidated_by_source_changes() {
+        let specs = tempfile::tempdir().unwrap();
+        let cache = SpecCache::new(specs.path().join("cache"));
+        let spec_path = specs.path().join("oss.yaml");
+        let schema_path = specs.path().join("bucket.yaml");
+        fs::write(
+            &spec_path,
+            r##"
+openapi: 3.0.0
+info: { title: OSS, version: "2.0" }
+paths:
+  /oss/v2/buckets:
+    get:
+      operationId: get-buckets
+      responses:
+        "200":
+          description: OK
+          content:
+            application/json:
+              schema: { $ref: "./bucket.yaml#/Bucket" }
+"##,
+        )
+        .unwrap();
+        fs::write(&schema_path, "Bucket: { type: object }\n").unwrap();
+
+        assert!(cache.get(&spec_path).is_none());
+        let (spec, sources) = OpenApiParser::parse_file_with_sources(&spec_path).unwrap();
+        assert_eq!(sources, [schema_path.canonicalize().unwrap()]);
+        cache.put(&spec_path, &sources, &spec);
+
+        let cached = cache.get(&spec_path).expect("cache hit");
+        let routes = OpenApiParser::extract_routes(&cached);
+        assert_eq!(
+            routes[0].operation.operation_id.as_deref(),
+            Some("get-buckets")
+        );
+        assert!(
+            cached
+                .components
+                .unwrap()
+                .schemas
+                .unwrap()
+                .contains_key("Bucket")
+        );
+
+        // A referenced file changing invalidates the entry
+        fs::write(&schema_path, "Bucket: { type: integer }\n").unwrap();
+        assert!(cache.get(&spec_path).is_none());
+    }
+}
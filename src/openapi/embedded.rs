@@ -0,0 +1,114 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+//! The APS spec snapshot in `specs/`, compiled into the binary by the
+//! `embedded-specs` feature.
+//!
+//! The specs are written out to a directory before parsing, so cross-file
+//! `$ref`s resolve exactly as they do for an on-disk spec directory.
+
+use include_dir::{Dir, include_dir};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+static SPECS: Dir<'static> = include_dir!("$CARGO_MANIFEST_DIR/specs");
+
+/// Write the embedded specs to a per-version directory under the system temp
+/// dir and return its path
+pub fn extract() -> std::io::Result<PathBuf> {
+    let root = std::env::temp_dir().join(format!("raps-mock-specs-{}", env!("CARGO_PKG_VERSION")));
+    write_dir(&SPECS, &root)?;
+    Ok(root)
+}
+
+fn write_dir(dir: &Dir<'_>, root: &Path) -> std::io::Result<()> {
+    fs::create_dir_all(root.join(dir.path()))?;
+    for file in dir.files() {
+        let path = root.join(file.path());
+        if fs::read(&path).is_ok_and(|existing| existing == file.contents()) {
+            continue;
+        }
+        // Concurrent servers may extract at the same time; rename so no one
+        // reads a half-written file
+        let partial = path.with_extension(format!("{}.partial", std::process::id()));
+        fs::write(&partial, file.contents())?;
+        fs::rename(&partial, &path)?;
+    }
+    for child in dir.dirs() {
+        write_dir(child, root)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::MockServerConfig;
+    use crate::openapi::OpenApiParser;
+    use crate::server::MockServer;
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use serde_json::Value;
+    use tower::ServiceExt;
+
+    #[test]
+    fn test_embedded_specs_parse() {
+        let dir = super::extract().unwrap();
+        let specs = OpenApiParser::parse_directory(&dir).unwrap();
+        assert!(specs.iter().any(|(name, _)| name == "oss/oss"));
+        let routes: usize = specs
+            .iter()
+            .map(|(_, spec)| OpenApiParser::extract_routes(spec).len())
+            .sum();
+        assert!(routes > 30, "only {} routes", routes);
+    }
+
+    #[tokio::test]
+    async fn test_embedded_routes_need_a_minted_token() {
+        let config = MockServerConfig {
+            openapi_dir: "/nonexistent".into(),
+            ..MockServerConfig::default()
+        };
+        let server = MockServer::new(config).await.unwrap();
+        let token = |scope: &'static str| {
+            let request = Request::post("/authentication/v2/token")
+                .header("content-type", "application/x-www-form-urlencoded")
+                .body(Body::from(format!(
+                    "client_id=ci&client_secret=cs&grant_type=client_credentials&scope={}",
+                    scope
+                )))
+                .unwrap();
+            let router = server.router();
+            async move {
+                let response = router.oneshot(request).await.unwrap();
+                assert_eq!(response.status(), StatusCode::OK);
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                let body: Value = serde_json::from_slice(&body).unwrap();
+                body["access_token"].as_str().unwrap().to_string()
+            }
+        };
+        let buckets = |token: Option<String>| {
+            let mut request = Request::get("/oss/v2/buckets");
+            if let Some(token) = token {
+                request = request.header("authorization", format!("Bearer {}", token));
+            }
+            server
+                .router()
+                .oneshot(request.body(Body::empty()).unwrap())
+        };
+
+        // Tokens come from the stateful auth pack, not a spec example
+        let route = server.catalog().get("/authentication/v2/token", "POST");
+        assert_eq!(route.unwrap().origin.as_deref(), Some("authentication"));
+        // The spec operation is served, and needs its scope
+        let route = server.catalog().get("/oss/v2/buckets", "GET").unwrap();
+        assert_eq!(route.operation_id.as_deref(), Some("get-buckets"));
+        let response = buckets(None).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let response = buckets(Some(token("data:read").await)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let response = buckets(Some(token("bucket:read").await)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
@@ -0,0 +1,120 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+//! Selection of the spec files to load (`--include` / `--exclude`).
+//!
+//! Globs are matched against a file's path relative to its OpenAPI
+//! directory: `*` matches within one path component, `**` across
+//! components and `?` one character. A glob without `/` is matched against
+//! every component instead (file names with and without extension), so
+//! `oss*` selects both `oss/` directories and `oss.yaml` files.
+
+use regex::Regex;
+use std::path::Path;
+use std::str::FromStr;
+
+/// A glob selecting spec files
+#[derive(Debug, Clone)]
+pub struct SpecGlob {
+    regex: Regex,
+    /// Whether the glob is matched against single components
+    component: bool,
+    source: String,
+}
+
+impl SpecGlob {
+    fn matches(&self, relative: &Path) -> bool {
+        let path = relative.to_string_lossy().replace('\\', "/");
+        if !self.component {
+            return self.regex.is_match(&path);
+        }
+        let stem = relative.file_stem().map(|s| s.to_string_lossy());
+        path.split('/')
+            .chain(stem.as_deref())
+            .any(|component| self.regex.is_match(component))
+    }
+}
+
+impl FromStr for SpecGlob {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let glob = s.trim().trim_start_matches("./");
+        if glob.is_empty() {
+            return Err("Empty spec glob".to_string());
+        }
+        let mut pattern = String::from("^");
+        let mut chars = glob.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '*' if chars.peek() == Some(&'*') => {
+                    chars.next();
+                    // `**/` also matches no directory at all
+                    if chars.peek() == Some(&'/') {
+                        chars.next();
+                        pattern.push_str("(?:.*/)?");
+                    } else {
+                        pattern.push_str(".*");
+                    }
+                }
+                '*' => pattern.push_str("[^/]*"),
+                '?' => pattern.push_str("[^/]"),
+                c => pattern.push_str(&regex::escape(&c.to_string())),
+            }
+        }
+        pattern.push('$');
+        Ok(Self {
+            regex: Regex::new(&pattern).map_err(|e| format!("Invalid glob '{}': {}", s, e))?,
+            component: !glob.contains('/'),
+            source: s.to_string(),
+        })
+    }
+}
+
+impl std::fmt::Display for SpecGlob {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+/// Include and exclude globs; with no includes every file is included
+#[derive(Debug, Clone, Default)]
+pub struct SpecFilter {
+    pub include: Vec<SpecGlob>,
+    pub exclude: Vec<SpecGlob>,
+}
+
+impl SpecFilter {
+    pub fn new(include: Vec<SpecGlob>, exclude: Vec<SpecGlob>) -> Self {
+        Self { include, exclude }
+    }
+
+    /// Whether the file at `relative` (to its OpenAPI directory) is loaded
+    pub fn allows(&self, relative: &Path) -> bool {
+        (self.include.is_empty() || self.include.iter().any(|g| g.matches(relative)))
+            && !self.exclude.iter().any(|g| g.matches(relative))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn globs(globs: &[&str]) -> Vec<SpecGlob> {
+        globs.iter().map(|g| g.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn test_include_and_exclude_globs() {
+        let filter = SpecFilter::new(
+            globs(&["oss*", "model-derivative/**"]),
+            globs(&["**/*-beta.yaml"]),
+        );
+        assert!(filter.allows(Path::new("oss/oss.yaml")));
+        assert!(filter.allows(Path::new("oss.yaml")));
+        assert!(filter.allows(Path::new("model-derivative/v2/md.yaml")));
+        assert!(!filter.allows(Path::new("model-derivative/md-beta.yaml")));
+        assert!(!filter.allows(Path::new("issues/issues.yaml")));
+        assert!(SpecFilter::default().allows(Path::new("issues/issues.yaml")));
+    }
+}
@@ -1,7 +1,14 @@
 // SPDX-License-Identifier: Apache-2.0
 // Copyright 2024-2025 Dmytro Yemelianov
 
+pub mod cache;
+#[cfg(feature = "embedded-specs")]
+pub mod embedded;
+pub mod filter;
 pub mod parser;
+pub mod refs;
+pub mod schemas;
+pub mod swagger;
 pub mod types;
 
 pub use parser::OpenApiParser;
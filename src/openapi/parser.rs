@@ -2,11 +2,18 @@
 // Copyright 2024-2025 Dmytro Yemelianov
 
 use crate::error::Result;
-use crate::openapi::types::{HttpMethod, OpenApiSpec, RouteDefinition};
+use crate::openapi::cache::SpecCache;
+use crate::openapi::filter::SpecFilter;
+use crate::openapi::types::{
+    HttpMethod, OpenApiSpec, Operation, Parameter, PathItem, RouteDefinition,
+};
+use crate::openapi::{refs, swagger};
 use regex::Regex;
 use std::fs;
-use std::path::Path;
-use std::sync::LazyLock;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, LazyLock};
+use std::time::{Duration, Instant};
 
 /// Regex to convert OpenAPI path params {param} to Axum format :param
 static PATH_PARAM_REGEX: LazyLock<Regex> =
@@ -16,71 +23,221 @@ static PATH_PARAM_REGEX: LazyLock<Regex> =
 static CAMEL_CASE_REGEX: LazyLock<Regex> =
     LazyLock::new(|| Regex::new(r"([a-z])([A-Z])").expect("Invalid camelCase regex"));
 
+/// A spec file, its parsed spec (and whether it came from the cache) and the
+/// time loading it took
+type ParsedFile<'a> = (&'a Path, Result<(OpenApiSpec, bool)>, Duration);
+
+/// The specs parsed from a directory
+pub struct ParsedDirectory {
+    /// Specs by name (path relative to the directory, without extension)
+    pub specs: Vec<(String, OpenApiSpec)>,
+    /// Spec files that failed to parse, with the error
+    pub failures: Vec<(PathBuf, String)>,
+}
+
 /// Parser for OpenAPI 3.0 specifications
 pub struct OpenApiParser;
 
 impl OpenApiParser {
     /// Parse all OpenAPI specs from a directory
     pub fn parse_directory(dir: &Path) -> Result<Vec<(String, OpenApiSpec)>> {
+        Self::parse_directory_filtered(dir, &SpecFilter::default())
+    }
+
+    /// Parse the OpenAPI specs from a directory that `filter` lets through
+    pub fn parse_directory_filtered(
+        dir: &Path,
+        filter: &SpecFilter,
+    ) -> Result<Vec<(String, OpenApiSpec)>> {
+        Self::parse_directory_cached(dir, filter, None)
+    }
+
+    /// Like [`Self::parse_directory_filtered`], taking unchanged specs from
+    /// `cache` and caching the ones that had to be parsed
+    pub fn parse_directory_cached(
+        dir: &Path,
+        filter: &SpecFilter,
+        cache: Option<&SpecCache>,
+    ) -> Result<Vec<(String, OpenApiSpec)>> {
+        Ok(Self::parse_directory_reporting(dir, filter, cache)?.specs)
+    }
+
+    /// Like [`Self::parse_directory_cached`], also returning the spec files
+    /// that failed to parse with their errors
+    pub fn parse_directory_reporting(
+        dir: &Path,
+        filter: &SpecFilter,
+        cache: Option<&SpecCache>,
+    ) -> Result<ParsedDirectory> {
         let mut specs = Vec::new();
+        let mut failures = Vec::new();
 
         if !dir.exists() {
             tracing::warn!("OpenAPI directory does not exist: {}", dir.display());
-            return Ok(specs);
+            return Ok(ParsedDirectory { specs, failures });
         }
 
-        Self::walk_dir(dir, dir, &mut specs)?;
+        let mut files = Vec::new();
+        Self::walk_dir(dir, dir, filter, &mut files)?;
+        // Sorted so the order specs register routes in is stable
+        files.sort();
 
-        Ok(specs)
+        let started = Instant::now();
+        let mut cached = 0;
+        for (path, result, elapsed) in Self::parse_files(&files, cache) {
+            match result {
+                Ok((spec, from_cache)) => {
+                    let rel_path = path.strip_prefix(dir).unwrap_or(path);
+                    let name = rel_path
+                        .to_string_lossy()
+                        .replace('\\', "/")
+                        .replace(".yaml", "")
+                        .replace(".yml", "")
+                        .replace(".json", "");
+                    if from_cache {
+                        cached += 1;
+                        tracing::debug!("Loaded {} from cache in {:?}", name, elapsed);
+                    } else {
+                        tracing::debug!("Parsed {} in {:?}", name, elapsed);
+                    }
+                    specs.push((name, spec));
+                }
+                Err(e) if Self::is_fragment(path) => {
+                    // Schema files pulled in through cross-file $refs
+                    tracing::debug!("Skipping non-spec file {}: {}", path.display(), e);
+                }
+                Err(e) => {
+                    tracing::warn!("Failed to parse {}: {}", path.display(), e);
+                    failures.push((path.to_path_buf(), e.to_string()));
+                }
+            }
+        }
+        tracing::info!(
+            "Parsed {} of {} files in {} in {:?} ({} cached)",
+            specs.len(),
+            files.len(),
+            dir.display(),
+            started.elapsed(),
+            cached
+        );
+
+        Ok(ParsedDirectory { specs, failures })
     }
 
+    /// Spec files below `current_dir` that `filter` lets through
     fn walk_dir(
         base_dir: &Path,
         current_dir: &Path,
-        specs: &mut Vec<(String, OpenApiSpec)>,
+        filter: &SpecFilter,
+        files: &mut Vec<PathBuf>,
     ) -> Result<()> {
         for entry in fs::read_dir(current_dir)? {
             let entry = entry?;
             let path = entry.path();
 
             if path.is_dir() {
-                Self::walk_dir(base_dir, &path, specs)?;
+                Self::walk_dir(base_dir, &path, filter, files)?;
             } else if path
                 .extension()
                 .is_some_and(|ext| ext == "yaml" || ext == "yml" || ext == "json")
+                && filter.allows(path.strip_prefix(base_dir).unwrap_or(&path))
             {
-                match Self::parse_file(&path) {
-                    Ok(spec) => {
-                        let rel_path = path.strip_prefix(base_dir).unwrap_or(&path);
-                        let name = rel_path
-                            .to_string_lossy()
-                            .replace('\\', "/")
-                            .replace(".yaml", "")
-                            .replace(".yml", "")
-                            .replace(".json", "");
-                        specs.push((name, spec));
-                    }
-                    Err(e) => {
-                        tracing::warn!("Failed to parse {}: {}", path.display(), e);
-                    }
-                }
+                files.push(path);
             }
         }
         Ok(())
     }
 
-    /// Parse a single OpenAPI YAML file
+    /// Parse files on up to one thread per core, returning each file's
+    /// result (and whether it came from `cache`) and parse time in the order
+    /// given
+    fn parse_files<'a>(files: &'a [PathBuf], cache: Option<&SpecCache>) -> Vec<ParsedFile<'a>> {
+        let workers = std::thread::available_parallelism()
+            .map_or(1, |n| n.get())
+            .min(files.len());
+        let next = AtomicUsize::new(0);
+        let mut results: Vec<_> = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..workers)
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut parsed = Vec::new();
+                        loop {
+                            let index = next.fetch_add(1, Ordering::Relaxed);
+                            let Some(path) = files.get(index) else {
+                                break parsed;
+                            };
+                            let started = Instant::now();
+                            let result = Self::load_file(path, cache);
+                            parsed.push((index, path.as_path(), result, started.elapsed()));
+                        }
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .flat_map(|handle| handle.join().expect("spec parser thread panicked"))
+                .collect()
+        });
+        results.sort_by_key(|(index, ..)| *index);
+        results
+            .into_iter()
+            .map(|(_, path, result, elapsed)| (path, result, elapsed))
+            .collect()
+    }
+
+    /// A spec from `cache`, or parsed and then cached
+    fn load_file(path: &Path, cache: Option<&SpecCache>) -> Result<(OpenApiSpec, bool)> {
+        let Some(cache) = cache else {
+            return Ok((Self::parse_file(path)?, false));
+        };
+        if let Some(spec) = cache.get(path) {
+            return Ok((spec, true));
+        }
+        let (spec, sources) = Self::parse_file_with_sources(path)?;
+        cache.put(path, &sources, &spec);
+        Ok((spec, false))
+    }
+
+    /// Parse a single OpenAPI (or Swagger 2.0) YAML file, resolving `$ref`s
+    /// to sibling files
     pub fn parse_file(path: &Path) -> Result<OpenApiSpec> {
+        Ok(Self::parse_file_with_sources(path)?.0)
+    }
+
+    /// Like [`Self::parse_file`], also returning the other files its `$ref`s
+    /// were resolved from
+    pub fn parse_file_with_sources(path: &Path) -> Result<(OpenApiSpec, Vec<PathBuf>)> {
         let content = fs::read_to_string(path)?;
-        let spec: OpenApiSpec = serde_yaml::from_str(&content)?;
-        Ok(spec)
+        let mut document: serde_yaml::Value = serde_yaml::from_str(&content)?;
+        if swagger::is_swagger2(&document) {
+            tracing::debug!("Converting Swagger 2.0 document {}", path.display());
+            document = swagger::convert(document);
+        }
+        let sources = refs::resolve_external_refs(&mut document, path);
+        let spec: OpenApiSpec = serde_yaml::from_value(document)?;
+        Ok((spec, sources))
+    }
+
+    /// Whether a file is a YAML/JSON document without an `openapi` (or
+    /// `swagger`) version, i.e. a fragment referenced from other specs rather
+    /// than a spec itself
+    fn is_fragment(path: &Path) -> bool {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_yaml::from_str::<serde_yaml::Value>(&content).ok())
+            .is_some_and(|document| {
+                document.get("openapi").is_none() && document.get("swagger").is_none()
+            })
     }
 
     /// Extract route definitions from an OpenAPI spec
     pub fn extract_routes(spec: &OpenApiSpec) -> Vec<RouteDefinition> {
         let mut routes = Vec::new();
 
+        let base_path = spec.base_path();
+        let components = spec.components.clone().map(Arc::new);
         for (path, path_item) in &spec.paths {
+            let path = &Self::join_base_path(&base_path, path);
             let path_pattern = Self::convert_path_to_pattern(path);
 
             // Extract GET operation
@@ -88,9 +245,10 @@ impl OpenApiParser {
                 routes.push(RouteDefinition {
                     method: HttpMethod::Get,
                     path: path.clone(),
-                    operation: op.clone(),
+                    operation: Self::resolve_operation(op, path_item, spec),
                     path_pattern: path_pattern.clone(),
-                    components: spec.components.clone(),
+                    components: components.clone(),
+                    spec: None,
                 });
             }
 
@@ -99,9 +257,10 @@ impl OpenApiParser {
                 routes.push(RouteDefinition {
                     method: HttpMethod::Post,
                     path: path.clone(),
-                    operation: op.clone(),
+                    operation: Self::resolve_operation(op, path_item, spec),
                     path_pattern: path_pattern.clone(),
-                    components: spec.components.clone(),
+                    components: components.clone(),
+                    spec: None,
                 });
             }
 
@@ -110,9 +269,10 @@ impl OpenApiParser {
                 routes.push(RouteDefinition {
                     method: HttpMethod::Put,
                     path: path.clone(),
-                    operation: op.clone(),
+                    operation: Self::resolve_operation(op, path_item, spec),
                     path_pattern: path_pattern.clone(),
-                    components: spec.components.clone(),
+                    components: components.clone(),
+                    spec: None,
                 });
             }
 
@@ -121,9 +281,10 @@ impl OpenApiParser {
                 routes.push(RouteDefinition {
                     method: HttpMethod::Delete,
                     path: path.clone(),
-                    operation: op.clone(),
+                    operation: Self::resolve_operation(op, path_item, spec),
                     path_pattern: path_pattern.clone(),
-                    components: spec.components.clone(),
+                    components: components.clone(),
+                    spec: None,
                 });
             }
 
@@ -132,9 +293,34 @@ impl OpenApiParser {
                 routes.push(RouteDefinition {
                     method: HttpMethod::Patch,
                     path: path.clone(),
-                    operation: op.clone(),
+                    operation: Self::resolve_operation(op, path_item, spec),
+                    path_pattern: path_pattern.clone(),
+                    components: components.clone(),
+                    spec: None,
+                });
+            }
+
+            // Extract HEAD operation (e.g. OSS existence checks)
+            if let Some(op) = &path_item.head {
+                routes.push(RouteDefinition {
+                    method: HttpMethod::Head,
+                    path: path.clone(),
+                    operation: Self::resolve_operation(op, path_item, spec),
                     path_pattern: path_pattern.clone(),
-                    components: spec.components.clone(),
+                    components: components.clone(),
+                    spec: None,
+                });
+            }
+
+            // Extract OPTIONS operation
+            if let Some(op) = &path_item.options {
+                routes.push(RouteDefinition {
+                    method: HttpMethod::Options,
+                    path: path.clone(),
+                    operation: Self::resolve_operation(op, path_item, spec),
+                    path_pattern: path_pattern.clone(),
+                    components: components.clone(),
+                    spec: None,
                 });
             }
         }
@@ -142,9 +328,79 @@ impl OpenApiParser {
         routes
     }
 
+    /// Operations of a spec for methods the mock does not serve, as method
+    /// and path pattern
+    pub fn unsupported_operations(spec: &OpenApiSpec) -> Vec<(&'static str, String)> {
+        let base_path = spec.base_path();
+        spec.paths
+            .iter()
+            .filter(|(_, path_item)| path_item.trace.is_some())
+            .map(|(path, _)| {
+                let path = Self::join_base_path(&base_path, path);
+                ("TRACE", Self::convert_path_to_pattern(&path))
+            })
+            .collect()
+    }
+
+    /// Merge path-level parameters into an operation, dereference parameter
+    /// `$ref`s against `components.parameters` and apply the spec-level
+    /// security default
+    fn resolve_operation(op: &Operation, path_item: &PathItem, spec: &OpenApiSpec) -> Operation {
+        let components = spec.components.as_ref();
+        let resolve = |parameter: &Parameter| -> Parameter {
+            match parameter {
+                Parameter::Ref { ref_path } => {
+                    let resolved = ref_path
+                        .strip_prefix("#/components/parameters/")
+                        .and_then(|name| components?.parameters.as_ref()?.get(name));
+                    match resolved {
+                        Some(definition) => definition.clone(),
+                        None => {
+                            tracing::warn!("Unresolved parameter reference: {}", ref_path);
+                            parameter.clone()
+                        }
+                    }
+                }
+                definition => definition.clone(),
+            }
+        };
+
+        let mut parameters: Vec<Parameter> = op.parameters.iter().flatten().map(resolve).collect();
+
+        // Operation-level parameters override path-level ones with the same name and location
+        for shared in path_item.parameters.iter().flatten().map(resolve) {
+            let overridden = shared
+                .key()
+                .is_some_and(|key| parameters.iter().any(|p| p.key() == Some(key)));
+            if !overridden {
+                parameters.push(shared);
+            }
+        }
+
+        let mut operation = op.clone();
+        operation.parameters = (!parameters.is_empty()).then_some(parameters);
+        if operation.security.is_none() {
+            operation.security = spec.security.clone();
+        }
+        operation
+    }
+
     /// Convert OpenAPI path pattern to Axum-compatible pattern
     /// e.g., /buckets/{bucketKey} -> /buckets/:bucket_key
     /// Normalizes parameter names to snake_case to avoid Axum routing conflicts
+    /// Prefix a path with the server base path, unless the spec already
+    /// spells out the full path
+    fn join_base_path(base_path: &str, path: &str) -> String {
+        let already_prefixed = path
+            .strip_prefix(base_path)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'));
+        if base_path.is_empty() || already_prefixed {
+            path.to_string()
+        } else {
+            format!("{}{}", base_path, path)
+        }
+    }
+
     fn convert_path_to_pattern(path: &str) -> String {
         // OpenAPI uses {param}, Axum uses :param
         // Also normalize camelCase to snake_case to avoid conflicts like :hubId vs :hub_id
@@ -159,3 +415,282 @@ impl OpenApiParser {
             .to_string()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::openapi::types::ParameterLocation;
+
+    #[test]
+    fn test_parameter_refs_and_path_parameters_are_resolved() {
+        let spec: OpenApiSpec = serde_yaml::from_str(
+            r##"
+openapi: 3.0.0
+info:
+  title: OSS
+  version: "2.0"
+paths:
+  /buckets/{bucketKey}/objects:
+    parameters:
+      - $ref: "#/components/parameters/BucketKey"
+      - name: limit
+        in: query
+        schema:
+          type: integer
+    get:
+      parameters:
+        - $ref: "#/components/parameters/Limit"
+      responses: {}
+components:
+  parameters:
+    BucketKey:
+      name: bucketKey
+      in: path
+      required: true
+    Limit:
+      name: limit
+      in: query
+      required: true
+"##,
+        )
+        .unwrap();
+
+        let routes = OpenApiParser::extract_routes(&spec);
+        let parameters = routes[0].operation.parameters.as_ref().unwrap();
+        let keys: Vec<_> = parameters.iter().filter_map(|p| p.key()).collect();
+        assert_eq!(parameters.len(), 2);
+        assert!(keys.contains(&("bucketKey", &ParameterLocation::Path)));
+        // The operation-level (required) limit wins over the path-level one
+        assert!(matches!(
+            &parameters[0],
+            Parameter::Definition { name, required: Some(true), .. } if name == "limit"
+        ));
+    }
+
+    #[test]
+    fn test_operations_inherit_global_security() {
+        let spec: OpenApiSpec = serde_yaml::from_str(
+            r##"
+openapi: 3.0.0
+info:
+  title: Model Derivative
+  version: "2.0"
+security:
+  - oauth: [data:read]
+paths:
+  /manifest:
+    get:
+      operationId: getManifest
+      responses: {}
+  /formats:
+    get:
+      operationId: getFormats
+      security: []
+      responses: {}
+  /optional:
+    get:
+      operationId: optionalAuth
+      security:
+        - oauth: [data:read]
+        - {}
+      responses: {}
+  /basic:
+    get:
+      operationId: basicAuth
+      security:
+        - basic: []
+      responses: {}
+components:
+  securitySchemes:
+    oauth:
+      type: oauth2
+      flows: {}
+    basic:
+      type: http
+      scheme: basic
+"##,
+        )
+        .unwrap();
+
+        let requires = |id: &str| {
+            OpenApiParser::extract_routes(&spec)
+                .iter()
+                .find(|r| r.operation.operation_id.as_deref() == Some(id))
+                .unwrap()
+                .requires_bearer_token()
+        };
+        assert!(requires("getManifest"));
+        assert!(!requires("getFormats"));
+        assert!(!requires("optionalAuth"));
+        assert!(!requires("basicAuth"));
+
+        let manifest = OpenApiParser::extract_routes(&spec)
+            .into_iter()
+            .find(|r| r.operation.operation_id.as_deref() == Some("getManifest"))
+            .unwrap();
+        assert_eq!(
+            manifest.required_scopes(),
+            vec![vec!["data:read".to_string()]]
+        );
+    }
+
+    #[test]
+    fn test_response_headers_are_parsed() {
+        let spec: OpenApiSpec = serde_yaml::from_str(
+            r##"
+openapi: 3.0.0
+info:
+  title: Model Derivative
+  version: "2.0"
+paths:
+  /jobs:
+    post:
+      responses:
+        "202":
+          description: Accepted
+          headers:
+            Location:
+              schema:
+                type: string
+                example: /manifest
+            x-ads-region:
+              $ref: "#/components/headers/Region"
+components:
+  headers:
+    Region:
+      schema:
+        type: string
+      example: US
+"##,
+        )
+        .unwrap();
+
+        let routes = OpenApiParser::extract_routes(&spec);
+        let Some(crate::openapi::types::Response::Definition {
+            headers: Some(headers),
+            ..
+        }) = routes[0].operation.responses.get("202")
+        else {
+            panic!("202 response should have headers");
+        };
+        assert_eq!(
+            headers["Location"].example_value(),
+            Some(serde_json::json!("/manifest"))
+        );
+        assert!(matches!(
+            headers["x-ads-region"],
+            crate::openapi::types::Header::Ref { .. }
+        ));
+        let region = &spec.components.as_ref().unwrap().headers.as_ref().unwrap()["Region"];
+        assert_eq!(region.example_value(), Some(serde_json::json!("US")));
+    }
+
+    #[test]
+    fn test_head_and_options_operations_are_extracted() {
+        let spec: OpenApiSpec = serde_yaml::from_str(
+            r##"
+openapi: 3.0.0
+info:
+  title: OSS
+  version: "2.0"
+paths:
+  /buckets/{bucketKey}/objects/{objectKey}/details:
+    get:
+      responses:
+        "200":
+          description: OK
+    head:
+      responses:
+        "200":
+          description: OK
+    options:
+      responses:
+        "204":
+          description: No Content
+components:
+  schemas:
+    ObjectDetails: { type: object }
+"##,
+        )
+        .unwrap();
+
+        let routes = OpenApiParser::extract_routes(&spec);
+        let mut methods: Vec<&str> = routes.iter().map(|route| route.method.as_str()).collect();
+        methods.sort();
+        assert_eq!(methods, ["GET", "HEAD", "OPTIONS"]);
+        // The spec's components are shared, not copied per route
+        let components = routes[0].components.as_ref().unwrap();
+        assert!(
+            routes
+                .iter()
+                .all(|route| Arc::ptr_eq(route.components.as_ref().unwrap(), components))
+        );
+    }
+
+    #[test]
+    fn test_server_base_path_prefixes_routes() {
+        let spec: OpenApiSpec = serde_yaml::from_str(
+            r##"
+openapi: 3.0.0
+info:
+  title: OSS
+  version: "2.0"
+servers:
+  - url: https://developer.api.autodesk.com/{service}/v2/
+    variables:
+      service:
+        default: oss
+paths:
+  /buckets/{bucketKey}:
+    get:
+      responses: {}
+  /oss/v2/buckets:
+    get:
+      responses: {}
+"##,
+        )
+        .unwrap();
+        assert_eq!(spec.base_path(), "/oss/v2");
+
+        let mut paths: Vec<String> = OpenApiParser::extract_routes(&spec)
+            .into_iter()
+            .map(|route| route.path_pattern)
+            .collect();
+        paths.sort();
+        assert_eq!(paths, ["/oss/v2/buckets", "/oss/v2/buckets/:bucket_key"]);
+    }
+
+    #[test]
+    fn test_directory_is_parsed_concurrently_in_stable_order() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("common")).unwrap();
+        fs::write(
+            dir.path().join("common/bucket.yaml"),
+            "type: object\nproperties:\n  bucketKey: { type: string }\n",
+        )
+        .unwrap();
+        for i in 0..12 {
+            fs::write(
+                dir.path().join(format!("api{:02}.yaml", i)),
+                format!(
+                    "openapi: 3.0.0\ninfo: {{ title: API {i}, version: '1' }}\npaths:\n  /api{i}:\n    get:\n      responses:\n        '200':\n          description: OK\n          content:\n            application/json:\n              schema: {{ $ref: 'common/bucket.yaml' }}\n"
+                ),
+            )
+            .unwrap();
+        }
+
+        let specs = OpenApiParser::parse_directory(dir.path()).unwrap();
+        let names: Vec<&str> = specs.iter().map(|(name, _)| name.as_str()).collect();
+        let expected: Vec<String> = (0..12).map(|i| format!("api{:02}", i)).collect();
+        assert_eq!(names, expected);
+        let schemas = specs[3]
+            .1
+            .components
+            .as_ref()
+            .unwrap()
+            .schemas
+            .as_ref()
+            .unwrap();
+        assert!(schemas.contains_key("bucket"));
+    }
+}
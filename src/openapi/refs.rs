@@ -0,0 +1,375 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+//! Resolution of cross-file `$ref`s (e.g. `./schemas/bucket.yaml#/Bucket`).
+//!
+//! External schemas and parameters are imported into the root document's
+//! `components` and their references rewritten to local ones, so the rest of
+//! the mock only ever deals with `#/components/...` references. Anything else
+//! (responses, request bodies, ...) is inlined in place.
+
+use serde_yaml::{Mapping, Value};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Where a `$ref` appears, which decides how it is merged
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Context {
+    Schema,
+    Parameter,
+    Other,
+}
+
+impl Context {
+    fn component_section(self) -> Option<&'static str> {
+        match self {
+            Context::Schema => Some("schemas"),
+            Context::Parameter => Some("parameters"),
+            Context::Other => None,
+        }
+    }
+
+    fn child(self, key: &str) -> Context {
+        match (self, key) {
+            (Context::Schema, _) => Context::Schema,
+            (
+                _,
+                "schema"
+                | "schemas"
+                | "items"
+                | "properties"
+                | "additionalProperties"
+                | "allOf"
+                | "oneOf"
+                | "anyOf"
+                | "not",
+            ) => Context::Schema,
+            (_, "parameters") => Context::Parameter,
+            (ctx, _) => ctx,
+        }
+    }
+}
+
+/// Resolve every external `$ref` in `root`, which was loaded from `root_file`.
+/// Returns the other files that were read, sorted.
+pub fn resolve_external_refs(root: &mut Value, root_file: &Path) -> Vec<PathBuf> {
+    let root_file = root_file
+        .canonicalize()
+        .unwrap_or_else(|_| root_file.to_path_buf());
+    let mut resolver = RefResolver::new(root, root_file.clone());
+    resolver.walk(root, &root_file, Context::Other);
+    let mut sources: Vec<_> = resolver.documents.keys().cloned().collect();
+    sources.sort();
+    resolver.merge_into(root);
+    sources
+}
+
+struct RefResolver {
+    root_file: PathBuf,
+    documents: HashMap<PathBuf, Value>,
+    /// (section, file, pointer) -> imported component name
+    imported: HashMap<(&'static str, PathBuf, String), String>,
+    used_names: HashMap<&'static str, HashSet<String>>,
+    /// Imported components, in import order
+    components: Vec<(&'static str, String, Value)>,
+    /// Refs currently being inlined, to break cycles
+    inlining: Vec<(PathBuf, String)>,
+}
+
+impl RefResolver {
+    fn new(root: &Value, root_file: PathBuf) -> Self {
+        let mut used_names = HashMap::new();
+        for section in ["schemas", "parameters"] {
+            let names = root
+                .get("components")
+                .and_then(|c| c.get(section))
+                .and_then(|s| s.as_mapping())
+                .map(|m| {
+                    m.keys()
+                        .filter_map(|k| k.as_str().map(String::from))
+                        .collect()
+                })
+                .unwrap_or_default();
+            used_names.insert(section, names);
+        }
+        Self {
+            root_file,
+            documents: HashMap::new(),
+            imported: HashMap::new(),
+            used_names,
+            components: Vec::new(),
+            inlining: Vec::new(),
+        }
+    }
+
+    fn walk(&mut self, value: &mut Value, file: &Path, ctx: Context) {
+        match value {
+            Value::Mapping(map) => {
+                if let Some(reference) = map.get("$ref").and_then(|r| r.as_str()) {
+                    let reference = reference.to_string();
+                    if let Some(replacement) = self.resolve(&reference, file, ctx) {
+                        *value = replacement;
+                    }
+                    return;
+                }
+                for (key, child) in map.iter_mut() {
+                    let child_ctx = key.as_str().map_or(ctx, |k| ctx.child(k));
+                    self.walk(child, file, child_ctx);
+                }
+            }
+            Value::Sequence(items) => {
+                for item in items {
+                    self.walk(item, file, ctx);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Returns the value that should replace the `$ref` object, if any
+    fn resolve(&mut self, reference: &str, file: &Path, ctx: Context) -> Option<Value> {
+        let (file_part, pointer) = reference.split_once('#').unwrap_or((reference, ""));
+        if file_part.starts_with("http://") || file_part.starts_with("https://") {
+            tracing::warn!("Remote $ref is not supported: {}", reference);
+            return None;
+        }
+
+        let target = if file_part.is_empty() {
+            file.to_path_buf()
+        } else {
+            let joined = file.parent().unwrap_or(Path::new(".")).join(file_part);
+            match joined.canonicalize() {
+                Ok(path) => path,
+                Err(e) => {
+                    tracing::warn!("Unresolved $ref {}: {}", reference, e);
+                    return None;
+                }
+            }
+        };
+
+        // References into the root document stay local
+        if target == self.root_file {
+            if file_part.is_empty() {
+                return None;
+            }
+            return Some(ref_value(&format!("#{}", pointer)));
+        }
+
+        match ctx.component_section() {
+            Some(section) => {
+                let name = self.import(section, &target, pointer, ctx)?;
+                Some(ref_value(&format!("#/components/{}/{}", section, name)))
+            }
+            None => self.inline(&target, pointer),
+        }
+    }
+
+    fn import(
+        &mut self,
+        section: &'static str,
+        file: &Path,
+        pointer: &str,
+        ctx: Context,
+    ) -> Option<String> {
+        let key = (section, file.to_path_buf(), pointer.to_string());
+        if let Some(name) = self.imported.get(&key) {
+            return Some(name.clone());
+        }
+
+        let mut value = self.lookup(file, pointer)?;
+        let base = pointer
+            .rsplit('/')
+            .next()
+            .filter(|s| !s.is_empty())
+            .map(unescape_pointer)
+            .or_else(|| file.file_stem().map(|s| s.to_string_lossy().into_owned()))
+            .unwrap_or_else(|| "External".to_string());
+        let name = self.unique_name(section, &base);
+
+        // Register before walking so recursive schemas terminate
+        self.imported.insert(key, name.clone());
+        self.walk(&mut value, file, ctx);
+        self.components.push((section, name.clone(), value));
+        Some(name)
+    }
+
+    fn inline(&mut self, file: &Path, pointer: &str) -> Option<Value> {
+        let key = (file.to_path_buf(), pointer.to_string());
+        if self.inlining.contains(&key) {
+            tracing::warn!(
+                "Circular $ref to {}#{} left unresolved",
+                file.display(),
+                pointer
+            );
+            return None;
+        }
+
+        let mut value = self.lookup(file, pointer)?;
+        self.inlining.push(key);
+        self.walk(&mut value, file, Context::Other);
+        self.inlining.pop();
+        Some(value)
+    }
+
+    fn lookup(&mut self, file: &Path, pointer: &str) -> Option<Value> {
+        if !self.documents.contains_key(file) {
+            let document = fs::read_to_string(file)
+                .map_err(|e| e.to_string())
+                .and_then(|content| {
+                    serde_yaml::from_str::<Value>(&content).map_err(|e| e.to_string())
+                });
+            match document {
+                Ok(document) => {
+                    self.documents.insert(file.to_path_buf(), document);
+                }
+                Err(e) => {
+                    tracing::warn!("Failed to load $ref target {}: {}", file.display(), e);
+                    return None;
+                }
+            }
+        }
+
+        let document = self.documents.get(file)?;
+        let found = resolve_pointer(document, pointer);
+        if found.is_none() {
+            tracing::warn!("Unresolved $ref {}#{}", file.display(), pointer);
+        }
+        found.cloned()
+    }
+
+    fn unique_name(&mut self, section: &'static str, base: &str) -> String {
+        let used = self.used_names.entry(section).or_default();
+        let mut name = base.to_string();
+        let mut suffix = 2;
+        while used.contains(&name) {
+            name = format!("{}{}", base, suffix);
+            suffix += 1;
+        }
+        used.insert(name.clone());
+        name
+    }
+
+    fn merge_into(self, root: &mut Value) {
+        if self.components.is_empty() {
+            return;
+        }
+        let Some(root_map) = root.as_mapping_mut() else {
+            return;
+        };
+        let components = root_map
+            .entry(Value::from("components"))
+            .or_insert_with(|| Value::Mapping(Mapping::new()));
+        let Some(components) = components.as_mapping_mut() else {
+            return;
+        };
+        for (section, name, value) in self.components {
+            let entries = components
+                .entry(Value::from(section))
+                .or_insert_with(|| Value::Mapping(Mapping::new()));
+            if let Some(entries) = entries.as_mapping_mut() {
+                entries.insert(Value::from(name), value);
+            }
+        }
+    }
+}
+
+fn ref_value(reference: &str) -> Value {
+    let mut map = Mapping::new();
+    map.insert(Value::from("$ref"), Value::from(reference));
+    Value::Mapping(map)
+}
+
+fn unescape_pointer(segment: &str) -> String {
+    segment.replace("~1", "/").replace("~0", "~")
+}
+
+/// Resolve a JSON pointer (`/a/b/0`) against a YAML document
+fn resolve_pointer<'v>(document: &'v Value, pointer: &str) -> Option<&'v Value> {
+    let mut current = document;
+    for segment in pointer.split('/').skip(1) {
+        let segment = unescape_pointer(segment);
+        current = match current {
+            Value::Mapping(map) => map.iter().find_map(|(k, v)| {
+                let matches = match k {
+                    Value::String(s) => *s == segment,
+                    Value::Number(n) => n.to_string() == segment,
+                    _ => false,
+                };
+                matches.then_some(v)
+            })?,
+            Value::Sequence(items) => items.get(segment.parse::<usize>().ok()?)?,
+            _ => return None,
+        };
+    }
+    Some(current)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_external_schema_refs_are_imported() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("schemas")).unwrap();
+        fs::write(
+            dir.path().join("schemas/bucket.yaml"),
+            r##"
+Bucket:
+  type: object
+  properties:
+    policy:
+      $ref: "#/Policy"
+    owner:
+      $ref: "./owner.yaml"
+Policy:
+  type: string
+  enum: [transient]
+"##,
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("schemas/owner.yaml"),
+            "type: object\nproperties:\n  id:\n    type: string\n",
+        )
+        .unwrap();
+        let root_file = dir.path().join("oss.yaml");
+        fs::write(&root_file, "").unwrap();
+
+        let mut root: Value = serde_yaml::from_str(
+            r##"
+paths:
+  /buckets:
+    get:
+      responses:
+        "200":
+          content:
+            application/json:
+              schema:
+                $ref: "./schemas/bucket.yaml#/Bucket"
+"##,
+        )
+        .unwrap();
+        resolve_external_refs(&mut root, &root_file);
+
+        let schema = resolve_pointer(
+            &root,
+            "/paths/~1buckets/get/responses/200/content/application~1json/schema/$ref",
+        );
+        assert_eq!(
+            schema.and_then(|v| v.as_str()),
+            Some("#/components/schemas/Bucket")
+        );
+        let schemas = &root["components"]["schemas"];
+        assert_eq!(
+            schemas["Bucket"]["properties"]["policy"]["$ref"].as_str(),
+            Some("#/components/schemas/Policy")
+        );
+        assert_eq!(
+            schemas["Bucket"]["properties"]["owner"]["$ref"].as_str(),
+            Some("#/components/schemas/owner")
+        );
+        assert_eq!(schemas["owner"]["type"].as_str(), Some("object"));
+    }
+}
@@ -0,0 +1,276 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+//! Registry of the component schemas response data is generated from,
+//! served by the admin API as standalone JSON Schema documents.
+//!
+//! Schemas are exported as the mock parsed them: keywords the generator does
+//! not understand (`pattern`, `minimum`, ...) are absent, so the document
+//! describes exactly what synthesized data can look like. `$ref`s are
+//! inlined; recursive schemas refer back through `$defs`.
+
+use crate::config::Service;
+use crate::openapi::types::{Components, RouteDefinition, Schema};
+use serde_json::{Map, Value, json};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+const JSON_SCHEMA_DIALECT: &str = "https://json-schema.org/draft/2020-12/schema";
+const COMPONENT_PREFIX: &str = "#/components/schemas/";
+
+/// Component schemas by service and name
+#[derive(Debug, Default)]
+pub struct SchemaRegistry {
+    services: BTreeMap<String, BTreeMap<String, SpecSchemas>>,
+}
+
+/// A schema of a spec, whose components its references resolve against
+#[derive(Debug)]
+struct SpecSchemas {
+    name: String,
+    components: Arc<Components>,
+}
+
+impl SchemaRegistry {
+    /// Collect the component schemas of every route's spec. Specs are
+    /// filed under the service their paths belong to; when two specs of a
+    /// service define the same name, the first one wins.
+    pub fn from_routes<'a>(routes: impl IntoIterator<Item = &'a RouteDefinition>) -> Self {
+        let mut registry = Self::default();
+        for route in routes {
+            let Some(components) = &route.components else {
+                continue;
+            };
+            let Some(schemas) = &components.schemas else {
+                continue;
+            };
+            let service = service_name(&route.path);
+            let entries = registry.services.entry(service).or_default();
+            // Routes of one spec share its components; file them once
+            if schemas.keys().all(|name| entries.contains_key(name)) {
+                continue;
+            }
+            for name in schemas.keys() {
+                entries.entry(name.clone()).or_insert_with(|| SpecSchemas {
+                    name: name.clone(),
+                    components: components.clone(),
+                });
+            }
+        }
+        registry
+    }
+
+    /// `(service, name)` of every registered schema, sorted
+    pub fn names(&self) -> Vec<(&str, &str)> {
+        self.services
+            .iter()
+            .flat_map(|(service, schemas)| {
+                schemas
+                    .keys()
+                    .map(move |name| (service.as_str(), name.as_str()))
+            })
+            .collect()
+    }
+
+    /// A schema as a JSON Schema (draft 2020-12) document
+    pub fn json_schema(&self, service: &str, name: &str) -> Option<Value> {
+        let entry = self.services.get(service)?.get(name)?;
+        let components = entry.components.schemas.as_ref()?;
+        let mut converter = Converter {
+            components,
+            stack: vec![name.to_string()],
+            defs: BTreeMap::new(),
+        };
+        let mut document = match converter.convert(components.get(&entry.name)?) {
+            Value::Object(map) => map,
+            other => Map::from_iter([("allOf".to_string(), json!([other]))]),
+        };
+        document.insert("$schema".into(), json!(JSON_SCHEMA_DIALECT));
+        document.insert("title".into(), json!(name));
+        if !converter.defs.is_empty() {
+            document.insert("$defs".into(), json!(converter.defs));
+        }
+        Some(Value::Object(document))
+    }
+}
+
+/// Service name for a route path, falling back to its first segment for
+/// APIs without a [`Service`]
+pub(crate) fn service_name(path: &str) -> String {
+    match Service::for_path(path) {
+        Some(service) => service.as_str().to_string(),
+        None => path
+            .trim_start_matches('/')
+            .split('/')
+            .next()
+            .unwrap_or_default()
+            .to_string(),
+    }
+}
+
+struct Converter<'a> {
+    components: &'a HashMap<String, Schema>,
+    /// Names of the schemas being inlined, to detect recursion
+    stack: Vec<String>,
+    defs: BTreeMap<String, Value>,
+}
+
+impl Converter<'_> {
+    fn convert(&mut self, schema: &Schema) -> Value {
+        match schema {
+            Schema::Ref { ref_path } => self.reference(ref_path),
+            Schema::Object {
+                type_name,
+                format,
+                items,
+                properties,
+                required,
+                enum_values,
+                example,
+                nullable,
+                all_of,
+                one_of,
+                any_of,
+            } => {
+                let mut out = Map::new();
+                if let Some(type_name) = type_name {
+                    out.insert(
+                        "type".into(),
+                        if *nullable == Some(true) {
+                            json!([type_name, "null"])
+                        } else {
+                            json!(type_name)
+                        },
+                    );
+                }
+                if let Some(format) = format {
+                    out.insert("format".into(), json!(format));
+                }
+                if let Some(properties) = properties {
+                    let properties: BTreeMap<&String, Value> = properties
+                        .iter()
+                        .map(|(name, schema)| (name, self.convert(schema)))
+                        .collect();
+                    out.insert("properties".into(), json!(properties));
+                }
+                if let Some(required) = required {
+                    out.insert("required".into(), json!(required));
+                }
+                if let Some(items) = items {
+                    out.insert("items".into(), self.convert(items));
+                }
+                if let Some(values) = enum_values {
+                    out.insert("enum".into(), json!(values));
+                }
+                if let Some(example) = example {
+                    out.insert("examples".into(), json!([example]));
+                }
+                for (keyword, schemas) in [("allOf", all_of), ("oneOf", one_of), ("anyOf", any_of)]
+                {
+                    if let Some(schemas) = schemas {
+                        let schemas: Vec<Value> = schemas.iter().map(|s| self.convert(s)).collect();
+                        out.insert(keyword.into(), json!(schemas));
+                    }
+                }
+                Value::Object(out)
+            }
+        }
+    }
+
+    fn reference(&mut self, ref_path: &str) -> Value {
+        let Some(name) = ref_path.strip_prefix(COMPONENT_PREFIX) else {
+            // Left unresolved by the parser; keep it as written
+            return json!({ "$ref": ref_path });
+        };
+        if self.stack.iter().any(|n| n == name) {
+            if self.stack[0] == name {
+                return json!({ "$ref": "#" });
+            }
+            if !self.defs.contains_key(name) {
+                // Reserve the slot first, the definition may refer to itself
+                self.defs.insert(name.to_string(), Value::Null);
+                let definition = self.inline(name);
+                self.defs.insert(name.to_string(), definition);
+            }
+            return json!({ "$ref": format!("#/$defs/{}", name) });
+        }
+        self.inline(name)
+    }
+
+    fn inline(&mut self, name: &str) -> Value {
+        let Some(schema) = self.components.get(name) else {
+            return json!({ "$ref": format!("{}{}", COMPONENT_PREFIX, name) });
+        };
+        self.stack.push(name.to_string());
+        let value = self.convert(schema);
+        self.stack.pop();
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::openapi::OpenApiParser;
+    use crate::openapi::types::OpenApiSpec;
+
+    #[test]
+    fn test_json_schema_inlines_refs_and_nullable() {
+        let spec: OpenApiSpec = serde_yaml::from_str(
+            r##"
+openapi: 3.0.0
+info: { title: OSS, version: "2.0" }
+paths:
+  /oss/v2/buckets:
+    get:
+      responses:
+        "200": { description: OK }
+components:
+  schemas:
+    Bucket:
+      type: object
+      required: [bucketKey]
+      properties:
+        bucketKey: { type: string, example: my-bucket }
+        region: { type: string, nullable: true }
+        permissions:
+          type: array
+          items: { $ref: "#/components/schemas/Permission" }
+    Permission:
+      type: object
+      properties:
+        access: { type: string, enum: [full, read] }
+    Folder:
+      type: object
+      properties:
+        children:
+          type: array
+          items: { $ref: "#/components/schemas/Folder" }
+"##,
+        )
+        .unwrap();
+        let registry = SchemaRegistry::from_routes(&OpenApiParser::extract_routes(&spec));
+
+        assert_eq!(
+            registry.names(),
+            [("oss", "Bucket"), ("oss", "Folder"), ("oss", "Permission")]
+        );
+        let bucket = registry.json_schema("oss", "Bucket").unwrap();
+        assert_eq!(bucket["$schema"], JSON_SCHEMA_DIALECT);
+        assert_eq!(
+            bucket["properties"]["region"]["type"],
+            json!(["string", "null"])
+        );
+        assert_eq!(
+            bucket["properties"]["bucketKey"]["examples"],
+            json!(["my-bucket"])
+        );
+        assert_eq!(
+            bucket["properties"]["permissions"]["items"]["properties"]["access"]["enum"],
+            json!(["full", "read"])
+        );
+        let folder = registry.json_schema("oss", "Folder").unwrap();
+        assert_eq!(folder["properties"]["children"]["items"]["$ref"], "#");
+        assert!(registry.json_schema("issues", "Bucket").is_none());
+    }
+}
@@ -0,0 +1,536 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+//! Up-conversion of Swagger 2.0 documents to OpenAPI 3.0.
+//!
+//! Works on the raw document, before it is deserialized into
+//! [`OpenApiSpec`](crate::openapi::types::OpenApiSpec). Covers what the mock
+//! uses: definitions, shared parameters and responses, body and form
+//! parameters, `produces`/`consumes`, response examples and security
+//! definitions.
+
+use serde_yaml::{Mapping, Value};
+
+const OPERATION_KEYS: &[&str] = &["get", "put", "post", "delete", "patch", "options", "head"];
+
+/// Parameter fields that move into `schema` in OpenAPI 3
+const SCHEMA_KEYS: &[&str] = &[
+    "type",
+    "format",
+    "items",
+    "enum",
+    "default",
+    "minimum",
+    "maximum",
+    "minLength",
+    "maxLength",
+    "pattern",
+];
+
+/// Whether a document is a Swagger 2.0 document
+pub fn is_swagger2(document: &Value) -> bool {
+    document
+        .get("swagger")
+        .and_then(value_as_string)
+        .is_some_and(|v| v.starts_with('2'))
+}
+
+/// Convert a Swagger 2.0 document into an equivalent OpenAPI 3.0 document
+pub fn convert(document: Value) -> Value {
+    let Value::Mapping(mut doc) = document else {
+        return document;
+    };
+
+    let produces = media_types(doc.get("produces"));
+    let consumes = media_types(doc.get("consumes"));
+
+    let mut out = Mapping::new();
+    out.insert("openapi".into(), "3.0.0".into());
+    for key in ["info", "tags", "security", "externalDocs"] {
+        if let Some(value) = doc.remove(key) {
+            out.insert(key.into(), value);
+        }
+    }
+    if let Some(servers) = servers(&doc) {
+        out.insert("servers".into(), servers);
+    }
+
+    let mut paths = Mapping::new();
+    if let Some(Value::Mapping(swagger_paths)) = doc.remove("paths") {
+        for (path, item) in swagger_paths {
+            if let Value::Mapping(item) = item {
+                paths.insert(path, convert_path_item(item, &produces, &consumes));
+            }
+        }
+    }
+    out.insert("paths".into(), Value::Mapping(paths));
+
+    let mut components = Mapping::new();
+    if let Some(definitions) = doc.remove("definitions") {
+        components.insert("schemas".into(), definitions);
+    }
+    if let Some(Value::Mapping(parameters)) = doc.remove("parameters") {
+        // Shared body parameters have no OpenAPI 3 equivalent and are dropped
+        let converted: Mapping = parameters
+            .into_iter()
+            .filter_map(|(name, p)| match p {
+                Value::Mapping(p) if !is_body_param(&p) => Some((name, convert_parameter(p))),
+                _ => None,
+            })
+            .collect();
+        components.insert("parameters".into(), Value::Mapping(converted));
+    }
+    if let Some(Value::Mapping(responses)) = doc.remove("responses") {
+        let converted: Mapping = responses
+            .into_iter()
+            .map(|(name, r)| (name, convert_response(r, &produces)))
+            .collect();
+        components.insert("responses".into(), Value::Mapping(converted));
+    }
+    if let Some(Value::Mapping(schemes)) = doc.remove("securityDefinitions") {
+        let converted: Mapping = schemes
+            .into_iter()
+            .map(|(name, s)| (name, convert_security_scheme(s)))
+            .collect();
+        components.insert("securitySchemes".into(), Value::Mapping(converted));
+    }
+    if !components.is_empty() {
+        out.insert("components".into(), Value::Mapping(components));
+    }
+
+    let mut out = Value::Mapping(out);
+    rewrite_refs(&mut out);
+    out
+}
+
+fn servers(doc: &Mapping) -> Option<Value> {
+    let host = doc.get("host").and_then(|h| h.as_str());
+    let base_path = doc.get("basePath").and_then(|b| b.as_str()).unwrap_or("");
+    let url = match host {
+        Some(host) => {
+            let scheme = doc
+                .get("schemes")
+                .and_then(|s| s.as_sequence())
+                .and_then(|s| s.first())
+                .and_then(|s| s.as_str())
+                .unwrap_or("https");
+            format!("{}://{}{}", scheme, host, base_path)
+        }
+        None if !base_path.is_empty() => base_path.to_string(),
+        None => return None,
+    };
+    let mut server = Mapping::new();
+    server.insert("url".into(), url.into());
+    Some(Value::Sequence(vec![Value::Mapping(server)]))
+}
+
+fn convert_path_item(mut item: Mapping, produces: &[String], consumes: &[String]) -> Value {
+    // Path-level body/form parameters become part of each operation's body
+    let shared: Vec<Value> = match item.remove("parameters") {
+        Some(Value::Sequence(parameters)) => parameters,
+        _ => Vec::new(),
+    };
+    let (shared_body, shared_params): (Vec<Value>, Vec<Value>) =
+        shared.into_iter().partition(is_body_or_form);
+
+    let mut out = Mapping::new();
+    if !shared_params.is_empty() {
+        let converted = shared_params
+            .into_iter()
+            .map(convert_parameter_value)
+            .collect();
+        out.insert("parameters".into(), Value::Sequence(converted));
+    }
+    for key in OPERATION_KEYS {
+        if let Some(Value::Mapping(operation)) = item.remove(*key) {
+            let converted = convert_operation(operation, &shared_body, produces, consumes);
+            out.insert((*key).into(), converted);
+        }
+    }
+    Value::Mapping(out)
+}
+
+fn convert_operation(
+    mut operation: Mapping,
+    shared_body: &[Value],
+    produces: &[String],
+    consumes: &[String],
+) -> Value {
+    let produces = operation
+        .remove("produces")
+        .map(|p| media_types(Some(&p)))
+        .filter(|p| !p.is_empty())
+        .unwrap_or_else(|| produces.to_vec());
+    let consumes = operation
+        .remove("consumes")
+        .map(|c| media_types(Some(&c)))
+        .filter(|c| !c.is_empty())
+        .unwrap_or_else(|| consumes.to_vec());
+
+    let parameters: Vec<Value> = match operation.remove("parameters") {
+        Some(Value::Sequence(parameters)) => parameters,
+        _ => Vec::new(),
+    };
+    let (body, params): (Vec<Value>, Vec<Value>) = shared_body
+        .iter()
+        .cloned()
+        .chain(parameters)
+        .partition(is_body_or_form);
+
+    if !params.is_empty() {
+        let converted = params.into_iter().map(convert_parameter_value).collect();
+        operation.insert("parameters".into(), Value::Sequence(converted));
+    }
+    if let Some(request_body) = request_body(&body, &consumes) {
+        operation.insert("requestBody".into(), request_body);
+    }
+    if let Some(Value::Mapping(responses)) = operation.remove("responses") {
+        let converted: Mapping = responses
+            .into_iter()
+            .map(|(code, r)| (code, convert_response(r, &produces)))
+            .collect();
+        operation.insert("responses".into(), Value::Mapping(converted));
+    }
+    Value::Mapping(operation)
+}
+
+fn is_body_param(parameter: &Mapping) -> bool {
+    parameter.get("in").and_then(|v| v.as_str()) == Some("body")
+}
+
+fn is_body_or_form(parameter: &Value) -> bool {
+    matches!(
+        parameter.get("in").and_then(|v| v.as_str()),
+        Some("body") | Some("formData")
+    )
+}
+
+fn request_body(parameters: &[Value], consumes: &[String]) -> Option<Value> {
+    if let Some(Value::Mapping(body)) = parameters
+        .iter()
+        .find(|p| p.get("in").and_then(|v| v.as_str()) == Some("body"))
+    {
+        let schema = body
+            .get("schema")
+            .cloned()
+            .unwrap_or(Value::Mapping(Mapping::new()));
+        let media_types = if consumes.is_empty() {
+            vec!["application/json".to_string()]
+        } else {
+            consumes.to_vec()
+        };
+        let mut out = Mapping::new();
+        if let Some(description) = body.get("description") {
+            out.insert("description".into(), description.clone());
+        }
+        if let Some(required) = body.get("required") {
+            out.insert("required".into(), required.clone());
+        }
+        out.insert("content".into(), content(&media_types, Some(schema), None));
+        return Some(Value::Mapping(out));
+    }
+
+    // formData parameters become properties of a form schema
+    let form: Vec<&Mapping> = parameters.iter().filter_map(|p| p.as_mapping()).collect();
+    if form.is_empty() {
+        return None;
+    }
+    let mut properties = Mapping::new();
+    let mut required = Vec::new();
+    let mut has_file = false;
+    for parameter in &form {
+        let Some(name) = parameter.get("name").cloned() else {
+            continue;
+        };
+        let mut schema = parameter_schema(parameter);
+        if schema.get("type").and_then(|t| t.as_str()) == Some("file") {
+            has_file = true;
+            schema.insert("type".into(), "string".into());
+            schema.insert("format".into(), "binary".into());
+        }
+        if parameter.get("required").and_then(|r| r.as_bool()) == Some(true) {
+            required.push(name.clone());
+        }
+        properties.insert(name, Value::Mapping(schema));
+    }
+    let mut schema = Mapping::new();
+    schema.insert("type".into(), "object".into());
+    schema.insert("properties".into(), Value::Mapping(properties));
+    if !required.is_empty() {
+        schema.insert("required".into(), Value::Sequence(required));
+    }
+    let media_type = if has_file || consumes.iter().any(|c| c == "multipart/form-data") {
+        "multipart/form-data"
+    } else {
+        "application/x-www-form-urlencoded"
+    };
+    let mut out = Mapping::new();
+    out.insert(
+        "content".into(),
+        content(
+            &[media_type.to_string()],
+            Some(Value::Mapping(schema)),
+            None,
+        ),
+    );
+    Some(Value::Mapping(out))
+}
+
+fn convert_parameter_value(parameter: Value) -> Value {
+    match parameter {
+        Value::Mapping(parameter) => convert_parameter(parameter),
+        other => other,
+    }
+}
+
+fn convert_parameter(parameter: Mapping) -> Value {
+    if parameter.contains_key("$ref") {
+        return Value::Mapping(parameter);
+    }
+    let schema = parameter_schema(&parameter);
+    let mut out: Mapping = parameter
+        .into_iter()
+        .filter(|(k, _)| {
+            k.as_str()
+                .is_some_and(|k| !SCHEMA_KEYS.contains(&k) && k != "collectionFormat")
+        })
+        .collect();
+    if !schema.is_empty() {
+        out.insert("schema".into(), Value::Mapping(schema));
+    }
+    Value::Mapping(out)
+}
+
+fn parameter_schema(parameter: &Mapping) -> Mapping {
+    SCHEMA_KEYS
+        .iter()
+        .filter_map(|key| Some(((*key).into(), parameter.get(*key)?.clone())))
+        .collect()
+}
+
+fn convert_response(response: Value, produces: &[String]) -> Value {
+    let Value::Mapping(mut response) = response else {
+        return response;
+    };
+    if response.contains_key("$ref") {
+        return Value::Mapping(response);
+    }
+
+    let schema = response.remove("schema");
+    let examples = response.remove("examples");
+    if schema.is_some() || examples.is_some() {
+        let mut media_types = if produces.is_empty() {
+            vec!["application/json".to_string()]
+        } else {
+            produces.to_vec()
+        };
+        // Examples keyed by media type the operation doesn't declare still count
+        if let Some(Value::Mapping(examples)) = &examples {
+            for key in examples.keys().filter_map(|k| k.as_str()) {
+                if !media_types.iter().any(|m| m == key) {
+                    media_types.push(key.to_string());
+                }
+            }
+        }
+        response.insert(
+            "content".into(),
+            content(&media_types, schema, examples.as_ref()),
+        );
+    }
+    if !response.contains_key("description") {
+        response.insert("description".into(), "".into());
+    }
+    if let Some(Value::Mapping(headers)) = response.remove("headers") {
+        let converted: Mapping = headers
+            .into_iter()
+            .map(|(name, header)| {
+                let schema = header
+                    .as_mapping()
+                    .map(parameter_schema)
+                    .unwrap_or_default();
+                let mut out = Mapping::new();
+                if let Some(description) = header.get("description") {
+                    out.insert("description".into(), description.clone());
+                }
+                out.insert("schema".into(), Value::Mapping(schema));
+                (name, Value::Mapping(out))
+            })
+            .collect();
+        response.insert("headers".into(), Value::Mapping(converted));
+    }
+    Value::Mapping(response)
+}
+
+fn content(media_types: &[String], schema: Option<Value>, examples: Option<&Value>) -> Value {
+    let content: Mapping = media_types
+        .iter()
+        .map(|media_type| {
+            let mut entry = Mapping::new();
+            if let Some(schema) = &schema {
+                entry.insert("schema".into(), schema.clone());
+            }
+            if let Some(example) = examples.and_then(|e| e.get(media_type.as_str())) {
+                entry.insert("example".into(), example.clone());
+            }
+            (media_type.as_str().into(), Value::Mapping(entry))
+        })
+        .collect();
+    Value::Mapping(content)
+}
+
+fn convert_security_scheme(scheme: Value) -> Value {
+    let Value::Mapping(mut scheme) = scheme else {
+        return scheme;
+    };
+    match scheme.get("type").and_then(|t| t.as_str()) {
+        Some("basic") => {
+            scheme.insert("type".into(), "http".into());
+            scheme.insert("scheme".into(), "basic".into());
+        }
+        Some("oauth2") => {
+            let flow_name = match scheme.remove("flow").as_ref().and_then(|f| f.as_str()) {
+                Some("accessCode") => "authorizationCode",
+                Some("application") => "clientCredentials",
+                Some("implicit") => "implicit",
+                _ => "password",
+            };
+            let mut flow = Mapping::new();
+            for key in ["authorizationUrl", "tokenUrl", "scopes"] {
+                if let Some(value) = scheme.remove(key) {
+                    flow.insert(key.into(), value);
+                }
+            }
+            if !flow.contains_key("scopes") {
+                flow.insert("scopes".into(), Value::Mapping(Mapping::new()));
+            }
+            let mut flows = Mapping::new();
+            flows.insert(flow_name.into(), Value::Mapping(flow));
+            scheme.insert("flows".into(), Value::Mapping(flows));
+        }
+        _ => {}
+    }
+    Value::Mapping(scheme)
+}
+
+/// Point `#/definitions`, `#/parameters` and `#/responses` references at
+/// their new home under `#/components`
+fn rewrite_refs(value: &mut Value) {
+    match value {
+        Value::Mapping(map) => {
+            for (key, child) in map.iter_mut() {
+                if key.as_str() == Some("$ref")
+                    && let Value::String(reference) = child
+                {
+                    for (from, to) in [
+                        ("#/definitions/", "#/components/schemas/"),
+                        ("#/parameters/", "#/components/parameters/"),
+                        ("#/responses/", "#/components/responses/"),
+                    ] {
+                        if reference.contains(from) {
+                            *reference = reference.replacen(from, to, 1);
+                            break;
+                        }
+                    }
+                } else {
+                    rewrite_refs(child);
+                }
+            }
+        }
+        Value::Sequence(items) => items.iter_mut().for_each(rewrite_refs),
+        _ => {}
+    }
+}
+
+fn media_types(value: Option<&Value>) -> Vec<String> {
+    value
+        .and_then(|v| v.as_sequence())
+        .map(|s| {
+            s.iter()
+                .filter_map(|m| m.as_str().map(String::from))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn value_as_string(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::openapi::OpenApiParser;
+    use crate::openapi::types::{OpenApiSpec, Parameter, Schema};
+
+    #[test]
+    fn test_convert_swagger2_document() {
+        let document: Value = serde_yaml::from_str(
+            r##"
+swagger: "2.0"
+info: { title: Legacy OSS, version: "1.0" }
+basePath: /oss/v1
+produces: [application/json]
+paths:
+  /buckets/{bucketKey}:
+    parameters:
+      - { name: bucketKey, in: path, required: true, type: string }
+    post:
+      operationId: createBucket
+      parameters:
+        - { name: body, in: body, required: true, schema: { $ref: "#/definitions/Bucket" } }
+      responses:
+        "200":
+          description: OK
+          schema: { $ref: "#/definitions/Bucket" }
+          examples:
+            application/json: { bucketKey: legacy }
+definitions:
+  Bucket:
+    type: object
+    properties:
+      bucketKey: { type: string }
+"##,
+        )
+        .unwrap();
+        assert!(is_swagger2(&document));
+
+        let spec: OpenApiSpec = serde_yaml::from_value(convert(document)).unwrap();
+        assert_eq!(spec.servers.as_ref().unwrap()[0].url, "/oss/v1");
+        assert!(
+            spec.components
+                .as_ref()
+                .unwrap()
+                .schemas
+                .as_ref()
+                .unwrap()
+                .contains_key("Bucket")
+        );
+
+        let routes = OpenApiParser::extract_routes(&spec);
+        let operation = &routes[0].operation;
+        let request_body = operation.request_body.as_ref().unwrap();
+        assert!(matches!(
+            request_body.content["application/json"].schema,
+            Some(Schema::Ref { ref ref_path }) if ref_path == "#/components/schemas/Bucket"
+        ));
+        assert!(matches!(
+            &operation.parameters.as_ref().unwrap()[0],
+            Parameter::Definition { name, schema: Some(_), .. } if name == "bucketKey"
+        ));
+        let crate::openapi::types::Response::Definition {
+            content: Some(content),
+            ..
+        } = &operation.responses["200"]
+        else {
+            panic!("expected response content");
+        };
+        assert_eq!(
+            content["application/json"].example,
+            Some(serde_json::json!({ "bucketKey": "legacy" }))
+        );
+    }
+}
@@ -1,8 +1,10 @@
 // SPDX-License-Identifier: Apache-2.0
 // Copyright 2024-2025 Dmytro Yemelianov
 
+use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::sync::Arc;
 
 /// OpenAPI 3.0 specification structure
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -12,6 +14,29 @@ pub struct OpenApiSpec {
     pub servers: Option<Vec<Server>>,
     pub paths: HashMap<String, PathItem>,
     pub components: Option<Components>,
+    /// Default security requirements for operations that declare none
+    pub security: Option<Vec<SecurityRequirement>>,
+}
+
+impl OpenApiSpec {
+    /// Path of the first server URL (`/oss/v2` for
+    /// `https://developer.api.autodesk.com/oss/v2`), without a trailing
+    /// slash; empty when the spec has no server or it sits at the root
+    pub fn base_path(&self) -> String {
+        let Some(server) = self.servers.as_ref().and_then(|s| s.first()) else {
+            return String::new();
+        };
+        let mut url = server.url.clone();
+        // Server variables take their default values
+        for (name, variable) in server.variables.iter().flatten() {
+            url = url.replace(&format!("{{{}}}", name), &variable.default);
+        }
+        let path = match url.split_once("://") {
+            Some((_, rest)) => rest.find('/').map_or("", |i| &rest[i..]),
+            None => url.as_str(),
+        };
+        path.trim_end_matches('/').to_string()
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -25,6 +50,12 @@ pub struct Info {
 pub struct Server {
     pub url: String,
     pub description: Option<String>,
+    pub variables: Option<HashMap<String, ServerVariable>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServerVariable {
+    pub default: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -39,9 +70,19 @@ pub struct PathItem {
     pub delete: Option<Operation>,
     #[serde(rename = "patch")]
     pub patch: Option<Operation>,
+    #[serde(rename = "head")]
+    pub head: Option<Operation>,
+    #[serde(rename = "options")]
+    pub options: Option<Operation>,
+    /// Parsed only to report it: the mock serves no `TRACE` operations
+    #[serde(rename = "trace")]
+    pub trace: Option<Operation>,
+    /// Parameters shared by every operation on this path
+    pub parameters: Option<Vec<Parameter>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct Operation {
     pub operation_id: Option<String>,
     pub summary: Option<String>,
@@ -70,7 +111,17 @@ pub enum Parameter {
     },
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl Parameter {
+    /// Name and location of a parameter definition (`None` for references)
+    pub fn key(&self) -> Option<(&str, &ParameterLocation)> {
+        match self {
+            Parameter::Definition { name, location, .. } => Some((name, location)),
+            Parameter::Ref { .. } => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum ParameterLocation {
     Query,
@@ -90,7 +141,8 @@ pub struct RequestBody {
 pub struct MediaType {
     pub schema: Option<Schema>,
     pub example: Option<serde_json::Value>,
-    pub examples: Option<HashMap<String, Example>>,
+    /// Named examples, in document order
+    pub examples: Option<IndexMap<String, Example>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -110,9 +162,63 @@ pub enum Response {
     Definition {
         description: String,
         content: Option<HashMap<String, MediaType>>,
+        headers: Option<HashMap<String, Header>>,
     },
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Header {
+    Ref {
+        #[serde(rename = "$ref")]
+        ref_path: String,
+    },
+    Definition {
+        description: Option<String>,
+        schema: Option<Box<Schema>>,
+        example: Option<serde_json::Value>,
+        examples: Option<IndexMap<String, Example>>,
+    },
+}
+
+impl Header {
+    /// Example value of the header: its `example`, its first `examples`
+    /// entry, or the example on its schema
+    pub fn example_value(&self) -> Option<serde_json::Value> {
+        let Header::Definition {
+            schema,
+            example,
+            examples,
+            ..
+        } = self
+        else {
+            return None;
+        };
+        example
+            .clone()
+            .or_else(|| {
+                examples
+                    .as_ref()?
+                    .values()
+                    .find_map(|example| example.value.clone())
+            })
+            .or_else(|| match schema.as_deref() {
+                Some(Schema::Object { example, .. }) => example.clone(),
+                _ => None,
+            })
+    }
+
+    pub fn schema(&self) -> Option<&Schema> {
+        match self {
+            Header::Definition { schema, .. } => schema.as_deref(),
+            Header::Ref { .. } => None,
+        }
+    }
+}
+
+// Schemas are parsed once at startup; boxing the object variant would only
+// complicate every pattern match
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum Schema {
@@ -127,15 +233,29 @@ pub enum Schema {
         items: Option<Box<Schema>>,
         properties: Option<HashMap<String, Schema>>,
         required: Option<Vec<String>>,
+        #[serde(rename = "enum")]
         enum_values: Option<Vec<serde_json::Value>>,
         example: Option<serde_json::Value>,
+        nullable: Option<bool>,
+        /// Value must match every listed schema (inheritance)
+        #[serde(rename = "allOf")]
+        all_of: Option<Vec<Schema>>,
+        /// Value must match exactly one listed schema
+        #[serde(rename = "oneOf")]
+        one_of: Option<Vec<Schema>>,
+        /// Value must match at least one listed schema
+        #[serde(rename = "anyOf")]
+        any_of: Option<Vec<Schema>>,
     },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct Components {
     pub schemas: Option<HashMap<String, Schema>>,
     pub responses: Option<HashMap<String, Response>>,
+    pub parameters: Option<HashMap<String, Parameter>>,
+    pub headers: Option<HashMap<String, Header>>,
     pub security_schemes: Option<HashMap<String, SecurityScheme>>,
 }
 
@@ -154,15 +274,34 @@ pub enum SecurityScheme {
         location: String,
         name: String,
     },
+    Http {
+        #[serde(rename = "type")]
+        type_name: String,
+        /// `bearer`, `basic`, ...
+        scheme: String,
+    },
+}
+
+impl SecurityScheme {
+    /// Whether the scheme is satisfied by an OAuth Bearer token
+    pub fn uses_bearer_token(&self) -> bool {
+        match self {
+            SecurityScheme::OAuth2 { .. } => true,
+            SecurityScheme::ApiKey { .. } => false,
+            SecurityScheme::Http { scheme, .. } => scheme.eq_ignore_ascii_case("bearer"),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct OAuth2Flows {
     pub authorization_code: Option<OAuth2Flow>,
     pub client_credentials: Option<OAuth2Flow>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct OAuth2Flow {
     pub authorization_url: Option<String>,
     pub token_url: Option<String>,
@@ -182,7 +321,78 @@ pub struct RouteDefinition {
     pub path: String,
     pub operation: Operation,
     pub path_pattern: String, // With :param placeholders
-    pub components: Option<Components>,
+    /// Components of the route's spec, shared by all of its routes
+    pub components: Option<Arc<Components>>,
+    /// Name of the spec file the route comes from (e.g. `oss/oss`), if known
+    pub spec: Option<String>,
+}
+
+impl RouteDefinition {
+    /// Whether the operation requires a Bearer token. It does unless its
+    /// (inherited) security is empty or one of its alternatives can be met
+    /// without a Bearer token, e.g. `security: [{}]` or a basic-auth scheme.
+    pub fn requires_bearer_token(&self) -> bool {
+        let Some(requirements) = self.operation.security.as_ref() else {
+            return false;
+        };
+        !requirements.is_empty()
+            && requirements.iter().all(|alternative| {
+                alternative
+                    .requirements
+                    .keys()
+                    .any(|name| self.is_bearer_scheme(name))
+            })
+    }
+
+    /// OAuth scopes a Bearer token must hold, one set per security
+    /// alternative; a token satisfying any one set is authorized
+    pub fn required_scopes(&self) -> Vec<Vec<String>> {
+        if !self.requires_bearer_token() {
+            return Vec::new();
+        }
+        self.operation
+            .security
+            .iter()
+            .flatten()
+            .map(|alternative| {
+                alternative
+                    .requirements
+                    .iter()
+                    .filter(|(name, _)| self.is_bearer_scheme(name))
+                    .flat_map(|(_, scopes)| scopes.iter().cloned())
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn is_bearer_scheme(&self, name: &str) -> bool {
+        // Undeclared schemes are assumed to be OAuth
+        self.components
+            .as_ref()
+            .and_then(|c| c.security_schemes.as_ref())
+            .and_then(|s| s.get(name))
+            .is_none_or(|scheme| scheme.uses_bearer_token())
+    }
+
+    /// Extract path parameter values from a request path, keyed by the
+    /// parameter names used in the OpenAPI spec
+    pub fn extract_path_params(&self, request_path: &str) -> HashMap<String, String> {
+        let template = self.path.trim_matches('/').split('/');
+        let actual = request_path.trim_matches('/').split('/');
+        // Routes may be mounted under a prefix; align from the end
+        let template: Vec<&str> = template.collect();
+        let actual: Vec<&str> = actual.collect();
+        let offset = actual.len().saturating_sub(template.len());
+
+        template
+            .iter()
+            .zip(actual.iter().skip(offset))
+            .filter_map(|(segment, value)| {
+                let name = segment.strip_prefix('{')?.strip_suffix('}')?;
+                Some((name.to_string(), value.to_string()))
+            })
+            .collect()
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -192,6 +402,8 @@ pub enum HttpMethod {
     Put,
     Delete,
     Patch,
+    Head,
+    Options,
 }
 
 impl HttpMethod {
@@ -202,6 +414,8 @@ impl HttpMethod {
             HttpMethod::Put => "PUT",
             HttpMethod::Delete => "DELETE",
             HttpMethod::Patch => "PATCH",
+            HttpMethod::Head => "HEAD",
+            HttpMethod::Options => "OPTIONS",
         }
     }
 }
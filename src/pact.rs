@@ -0,0 +1,609 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+//! Pact contract support (specification v2 and v3).
+//!
+//! Pact files recorded by consumer tests can be used two ways:
+//!
+//! - as stub sources: every interaction becomes a stub answering its request
+//!   with the recorded response ([`PactFile::to_stubs`])
+//! - as contracts: every interaction is replayed against the mock and its
+//!   response checked against the recorded one ([`verify`]), so consumer
+//!   expectations are checked against the OpenAPI-driven behaviour
+
+use crate::error::Result;
+use crate::stubs::{StubMapping, StubRequest, StubResponse};
+use axum::{
+    Router,
+    body::{Body, to_bytes},
+    http::{HeaderName, HeaderValue, Request},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use tower::ServiceExt;
+
+/// Upper bound on response bodies read during verification
+const MAX_BODY_BYTES: usize = 16 * 1024 * 1024;
+
+/// A Pact contract between a consumer and a provider
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PactFile {
+    pub consumer: Pacticipant,
+    pub provider: Pacticipant,
+    #[serde(default)]
+    pub interactions: Vec<Interaction>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Pacticipant {
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Interaction {
+    pub description: String,
+    /// v2 provider state
+    #[serde(default)]
+    pub provider_state: Option<String>,
+    /// v3 provider states
+    #[serde(default)]
+    pub provider_states: Vec<ProviderState>,
+    pub request: PactRequest,
+    pub response: PactResponse,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProviderState {
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PactRequest {
+    pub method: String,
+    pub path: String,
+    #[serde(default)]
+    pub query: Option<PactQuery>,
+    #[serde(default)]
+    pub headers: BTreeMap<String, Value>,
+    #[serde(default)]
+    pub body: Option<Value>,
+}
+
+/// Query string (v2) or parameter map (v3)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum PactQuery {
+    Raw(String),
+    Params(BTreeMap<String, Vec<String>>),
+}
+
+impl PactQuery {
+    fn to_query_string(&self) -> String {
+        match self {
+            PactQuery::Raw(raw) => raw.clone(),
+            PactQuery::Params(params) => {
+                let mut serializer = form_urlencoded::Serializer::new(String::new());
+                for (name, values) in params {
+                    for value in values {
+                        serializer.append_pair(name, value);
+                    }
+                }
+                serializer.finish()
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PactResponse {
+    pub status: u16,
+    #[serde(default)]
+    pub headers: BTreeMap<String, Value>,
+    #[serde(default)]
+    pub body: Option<Value>,
+    #[serde(default)]
+    pub matching_rules: Option<Value>,
+}
+
+/// Header values may be recorded as a string or a list of strings
+fn header_value(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Array(items) => items
+            .iter()
+            .map(header_value)
+            .collect::<Vec<_>>()
+            .join(", "),
+        other => other.to_string(),
+    }
+}
+
+impl Interaction {
+    /// Request path with its query string, if any
+    fn url(&self) -> String {
+        match self.request.query.as_ref().map(PactQuery::to_query_string) {
+            Some(query) if !query.is_empty() => format!("{}?{}", self.request.path, query),
+            _ => self.request.path.clone(),
+        }
+    }
+}
+
+impl PactFile {
+    /// Load a Pact file (JSON)
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+        Ok(serde_yaml::from_str(&content)?)
+    }
+
+    /// Turn every interaction into a stub answering with its recorded response
+    pub fn to_stubs(&self) -> Vec<StubMapping> {
+        self.interactions
+            .iter()
+            .map(|interaction| {
+                let (url, url_path) = match interaction.request.query {
+                    Some(_) => (Some(interaction.url()), None),
+                    None => (None, Some(interaction.request.path.clone())),
+                };
+                let (body, json_body) = match &interaction.response.body {
+                    Some(Value::String(text)) => (Some(text.clone()), None),
+                    Some(json) => (None, Some(json.clone())),
+                    None => (None, None),
+                };
+                StubMapping {
+                    id: None,
+                    name: Some(format!(
+                        "{} (pact {} -> {})",
+                        interaction.description, self.consumer.name, self.provider.name
+                    )),
+                    priority: None,
+                    scenario: None,
+                    request: StubRequest {
+                        method: Some(interaction.request.method.to_uppercase()),
+                        url,
+                        url_path,
+                        ..StubRequest::default()
+                    },
+                    response: StubResponse {
+                        status: interaction.response.status,
+                        headers: interaction
+                            .response
+                            .headers
+                            .iter()
+                            .map(|(name, value)| (name.clone(), header_value(value)))
+                            .collect(),
+                        body,
+                        json_body,
+                        ..StubResponse::default()
+                    },
+                }
+            })
+            .collect()
+    }
+}
+
+/// Outcome of verifying one interaction
+#[derive(Debug, Clone, Serialize)]
+pub struct InteractionResult {
+    pub description: String,
+    /// Differences between the recorded and the actual response
+    pub mismatches: Vec<String>,
+}
+
+/// Outcome of verifying a Pact file
+#[derive(Debug, Clone, Serialize)]
+pub struct VerificationReport {
+    pub consumer: String,
+    pub provider: String,
+    pub interactions: Vec<InteractionResult>,
+}
+
+impl VerificationReport {
+    pub fn passed(&self) -> bool {
+        self.interactions.iter().all(|i| i.mismatches.is_empty())
+    }
+}
+
+/// Replay every interaction against `router` and compare the responses.
+/// `bearer_token` replaces the recorded `Authorization` header, since tokens
+/// captured in consumer tests are not valid against the mock.
+pub async fn verify(
+    router: Router,
+    pact: &PactFile,
+    bearer_token: Option<&str>,
+) -> VerificationReport {
+    let mut interactions = Vec::new();
+    for interaction in &pact.interactions {
+        let mismatches = match verify_interaction(router.clone(), interaction, bearer_token).await {
+            Ok(mismatches) => mismatches,
+            Err(e) => vec![e],
+        };
+        interactions.push(InteractionResult {
+            description: interaction.description.clone(),
+            mismatches,
+        });
+    }
+    VerificationReport {
+        consumer: pact.consumer.name.clone(),
+        provider: pact.provider.name.clone(),
+        interactions,
+    }
+}
+
+async fn verify_interaction(
+    router: Router,
+    interaction: &Interaction,
+    bearer_token: Option<&str>,
+) -> std::result::Result<Vec<String>, String> {
+    let recorded = &interaction.request;
+    let mut builder = Request::builder()
+        .method(recorded.method.to_uppercase().as_str())
+        .uri(interaction.url());
+    for (name, value) in &recorded.headers {
+        if bearer_token.is_some() && name.eq_ignore_ascii_case("authorization") {
+            continue;
+        }
+        builder = builder.header(name.as_str(), header_value(value));
+    }
+    if let Some(token) = bearer_token {
+        builder = builder.header("authorization", format!("Bearer {}", token));
+    }
+    let body = match &recorded.body {
+        None => Body::empty(),
+        Some(Value::String(text)) => Body::from(text.clone()),
+        Some(json) => {
+            if !recorded
+                .headers
+                .keys()
+                .any(|name| name.eq_ignore_ascii_case("content-type"))
+            {
+                builder = builder.header("content-type", "application/json");
+            }
+            Body::from(json.to_string())
+        }
+    };
+    let request = builder
+        .body(body)
+        .map_err(|e| format!("Invalid request: {}", e))?;
+
+    let response = router
+        .oneshot(request)
+        .await
+        .map_err(|e| format!("Request failed: {}", e))?;
+
+    let expected = &interaction.response;
+    let mut mismatches = Vec::new();
+    if response.status().as_u16() != expected.status {
+        mismatches.push(format!(
+            "expected status {} but got {}",
+            expected.status,
+            response.status().as_u16()
+        ));
+    }
+    for (name, value) in &expected.headers {
+        let expected_value = header_value(value);
+        let actual = HeaderName::try_from(name.as_str())
+            .ok()
+            .and_then(|name| response.headers().get(name))
+            .and_then(|v: &HeaderValue| v.to_str().ok())
+            .map(str::to_string);
+        match actual {
+            // Parameters such as `; charset=utf-8` are not significant
+            Some(actual)
+                if actual == expected_value
+                    || actual.split(';').next().map(str::trim)
+                        == expected_value.split(';').next().map(str::trim) => {}
+            Some(actual) => mismatches.push(format!(
+                "header {}: expected {:?} but got {:?}",
+                name, expected_value, actual
+            )),
+            None => mismatches.push(format!("header {} is missing", name)),
+        }
+    }
+
+    if let Some(expected_body) = &expected.body {
+        let bytes = to_bytes(response.into_body(), MAX_BODY_BYTES)
+            .await
+            .map_err(|e| format!("Cannot read response body: {}", e))?;
+        let actual_body = serde_json::from_slice::<Value>(&bytes)
+            .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(&bytes).into_owned()));
+        let rules = MatchingRules::from_response(expected.matching_rules.as_ref());
+        compare(expected_body, &actual_body, "$", &rules, &mut mismatches);
+    }
+    Ok(mismatches)
+}
+
+/// Matchers relaxing the default equality check
+#[derive(Debug, Clone, PartialEq)]
+enum Matcher {
+    /// Same JSON type as the recorded value
+    Type {
+        min: Option<usize>,
+        max: Option<usize>,
+    },
+    Regex(String),
+}
+
+/// Body matching rules keyed by path segments (`$`, `items`, `*`, `0`)
+#[derive(Debug, Default)]
+struct MatchingRules {
+    rules: Vec<(Vec<String>, Vec<Matcher>)>,
+}
+
+impl MatchingRules {
+    /// Read v2 (`{"$.body.a": {...}}`) or v3 (`{"body": {"$.a": {"matchers": [...]}}}`) rules
+    fn from_response(rules: Option<&Value>) -> Self {
+        let mut parsed = Vec::new();
+        let Some(Value::Object(rules)) = rules else {
+            return Self::default();
+        };
+        if let Some(Value::Object(body)) = rules.get("body") {
+            for (path, rule) in body {
+                let matchers = rule
+                    .get("matchers")
+                    .and_then(Value::as_array)
+                    .map(|m| m.iter().filter_map(Self::matcher).collect())
+                    .unwrap_or_default();
+                parsed.push((Self::segments(path), matchers));
+            }
+        } else {
+            for (path, rule) in rules {
+                if let Some(body_path) = path.strip_prefix("$.body") {
+                    let matchers = Self::matcher(rule).into_iter().collect();
+                    parsed.push((Self::segments(&format!("${}", body_path)), matchers));
+                }
+            }
+        }
+        Self { rules: parsed }
+    }
+
+    fn matcher(rule: &Value) -> Option<Matcher> {
+        let min = rule.get("min").and_then(Value::as_u64).map(|n| n as usize);
+        let max = rule.get("max").and_then(Value::as_u64).map(|n| n as usize);
+        match rule.get("match").and_then(Value::as_str) {
+            Some("regex") => Some(Matcher::Regex(rule.get("regex")?.as_str()?.to_string())),
+            Some("type") | None if min.is_some() || max.is_some() => {
+                Some(Matcher::Type { min, max })
+            }
+            Some("type") => Some(Matcher::Type {
+                min: None,
+                max: None,
+            }),
+            _ => None,
+        }
+    }
+
+    /// `$.items[*].id` -> `["$", "items", "*", "id"]`
+    fn segments(path: &str) -> Vec<String> {
+        path.replace('[', ".")
+            .replace(']', "")
+            .replace(['\'', '"'], "")
+            .split('.')
+            .filter(|s| !s.is_empty())
+            .map(str::to_string)
+            .collect()
+    }
+
+    fn for_path(&self, path: &str) -> Option<&[Matcher]> {
+        let segments = Self::segments(path);
+        self.rules
+            .iter()
+            .find(|(rule, _)| {
+                rule.len() == segments.len()
+                    && rule.iter().zip(&segments).all(|(r, s)| r == "*" || r == s)
+            })
+            .map(|(_, matchers)| matchers.as_slice())
+    }
+}
+
+fn json_type(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// Compare a recorded body against the actual one. Objects may carry extra
+/// keys; everything else must be equal unless a matching rule relaxes it.
+fn compare(
+    expected: &Value,
+    actual: &Value,
+    path: &str,
+    rules: &MatchingRules,
+    mismatches: &mut Vec<String>,
+) {
+    let matchers = rules.for_path(path).unwrap_or_default();
+    let by_type = matchers.iter().any(|m| matches!(m, Matcher::Type { .. }));
+
+    for matcher in matchers {
+        match matcher {
+            Matcher::Regex(pattern) => {
+                let text = match actual {
+                    Value::String(s) => s.clone(),
+                    other => other.to_string(),
+                };
+                match regex::Regex::new(&format!("^(?:{})$", pattern)) {
+                    Ok(regex) if regex.is_match(&text) => {}
+                    Ok(_) => mismatches
+                        .push(format!("{}: {:?} does not match /{}/", path, text, pattern)),
+                    Err(e) => mismatches.push(format!("{}: invalid regex: {}", path, e)),
+                }
+                return;
+            }
+            Matcher::Type { min, max } => {
+                if let Value::Array(items) = actual {
+                    if min.is_some_and(|min| items.len() < min) {
+                        mismatches.push(format!(
+                            "{}: expected at least {} items",
+                            path,
+                            min.unwrap_or_default()
+                        ));
+                    }
+                    if max.is_some_and(|max| items.len() > max) {
+                        mismatches.push(format!(
+                            "{}: expected at most {} items",
+                            path,
+                            max.unwrap_or_default()
+                        ));
+                    }
+                }
+            }
+        }
+    }
+
+    if json_type(expected) != json_type(actual) {
+        mismatches.push(format!(
+            "{}: expected {} but got {}",
+            path,
+            json_type(expected),
+            json_type(actual)
+        ));
+        return;
+    }
+
+    match (expected, actual) {
+        (Value::Object(expected), Value::Object(actual)) => {
+            for (key, expected_value) in expected {
+                let child = format!("{}.{}", path, key);
+                match actual.get(key) {
+                    Some(actual_value) => {
+                        compare(expected_value, actual_value, &child, rules, mismatches)
+                    }
+                    None => mismatches.push(format!("{}: missing", child)),
+                }
+            }
+        }
+        (Value::Array(expected), Value::Array(actual)) => {
+            if by_type {
+                // Every element must look like the recorded example element
+                if let Some(template) = expected.first() {
+                    for (index, item) in actual.iter().enumerate() {
+                        compare(
+                            template,
+                            item,
+                            &format!("{}[{}]", path, index),
+                            rules,
+                            mismatches,
+                        );
+                    }
+                }
+                return;
+            }
+            if expected.len() != actual.len() {
+                mismatches.push(format!(
+                    "{}: expected {} items but got {}",
+                    path,
+                    expected.len(),
+                    actual.len()
+                ));
+                return;
+            }
+            for (index, (e, a)) in expected.iter().zip(actual).enumerate() {
+                compare(e, a, &format!("{}[{}]", path, index), rules, mismatches);
+            }
+        }
+        _ if by_type => {}
+        _ if expected != actual => mismatches.push(format!(
+            "{}: expected {} but got {}",
+            path, expected, actual
+        )),
+        _ => {}
+    }
+}
+
+/// Scopes requested for the token used during verification, so scope checks
+/// do not get in the way
+pub const VERIFIER_SCOPES: &str = "data:read data:write data:create data:search bucket:create bucket:read bucket:update bucket:delete account:read account:write code:all viewables:read user-profile:read";
+
+/// Issue a token through the mock's own token endpoint
+pub async fn issue_token(router: Router) -> Option<String> {
+    let body = serde_json::json!({
+        "client_id": "pact-verifier",
+        "client_secret": "pact-verifier",
+        "grant_type": "client_credentials",
+        "scope": VERIFIER_SCOPES
+    });
+    let request = Request::builder()
+        .method("POST")
+        .uri("/authentication/v2/token")
+        .header("content-type", "application/json")
+        .body(Body::from(body.to_string()))
+        .ok()?;
+    let response = router.oneshot(request).await.ok()?;
+    let bytes = to_bytes(response.into_body(), MAX_BODY_BYTES).await.ok()?;
+    let token: HashMap<String, Value> = serde_json::from_slice(&bytes).ok()?;
+    token
+        .get("access_token")
+        .and_then(Value::as_str)
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{Json, routing::get};
+
+    fn sample_pact() -> PactFile {
+        serde_json::from_value(serde_json::json!({
+            "consumer": { "name": "raps-cli" },
+            "provider": { "name": "oss" },
+            "interactions": [{
+                "description": "list buckets",
+                "request": { "method": "GET", "path": "/oss/v2/buckets", "query": "limit=1" },
+                "response": {
+                    "status": 200,
+                    "headers": { "Content-Type": "application/json" },
+                    "body": { "items": [{ "bucketKey": "recorded", "policyKey": "transient" }] },
+                    "matchingRules": {
+                        "$.body.items": { "min": 1 },
+                        "$.body.items[*].bucketKey": { "match": "type" }
+                    }
+                }
+            }]
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_verify_applies_matching_rules() {
+        let pact = sample_pact();
+        let router = Router::new().route(
+            "/oss/v2/buckets",
+            get(|| async {
+                Json(serde_json::json!({
+                    "items": [
+                        { "bucketKey": "a", "policyKey": "transient", "extra": 1 },
+                        { "bucketKey": "b", "policyKey": "transient" }
+                    ]
+                }))
+            }),
+        );
+        let report = verify(router, &pact, None).await;
+        assert!(report.passed(), "{:?}", report.interactions);
+
+        let failing = Router::new().route(
+            "/oss/v2/buckets",
+            get(|| async { Json(serde_json::json!({ "items": [{ "bucketKey": 1 }] })) }),
+        );
+        let report = verify(failing, &pact, None).await;
+        let mismatches = &report.interactions[0].mismatches;
+        assert!(
+            mismatches
+                .contains(&"$.items[0].bucketKey: expected string but got number".to_string())
+        );
+        assert!(mismatches.contains(&"$.items[0].policyKey: missing".to_string()));
+
+        let stubs = pact.to_stubs();
+        assert_eq!(
+            stubs[0].request.url.as_deref(),
+            Some("/oss/v2/buckets?limit=1")
+        );
+    }
+}
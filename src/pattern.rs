@@ -0,0 +1,177 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+//! Route patterns used to scope per-route configuration.
+//!
+//! A pattern is an optional HTTP method followed by a path whose segments are
+//! literals, `*` (any single segment, as do `:param` and `{param}`) or `**`
+//! (any number of segments), e.g. `GET /oss/v2/buckets/*/objects/**`.
+//! Instead of a path, `tag:NAME` selects the routes whose OpenAPI operation
+//! carries that tag, e.g. `tag:Model Derivative`.
+
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Literal(String),
+    Any,
+    AnyDepth,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Target {
+    Path(Vec<Segment>),
+    /// OpenAPI tag, compared case-insensitively
+    Tag(String),
+}
+
+/// A method and path (or tag) pattern
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RoutePattern {
+    method: Option<String>,
+    target: Target,
+    source: String,
+}
+
+impl RoutePattern {
+    /// Whether a request matches the pattern. `tags` are those of the
+    /// operation the request was routed to.
+    pub fn matches(&self, method: &str, path: &str, tags: &[String]) -> bool {
+        if let Some(expected) = &self.method
+            && !expected.eq_ignore_ascii_case(method)
+        {
+            return false;
+        }
+        match &self.target {
+            Target::Path(segments) => {
+                let path: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+                matches_segments(segments, &path)
+            }
+            Target::Tag(tag) => tags.iter().any(|t| t.eq_ignore_ascii_case(tag)),
+        }
+    }
+
+    /// Whether the pattern selects routes by tag rather than path
+    pub fn is_tag(&self) -> bool {
+        matches!(self.target, Target::Tag(_))
+    }
+}
+
+/// Whether `s` starts like a pattern: `/path`, `tag:NAME`, or either after a
+/// method
+pub fn looks_like_pattern(s: &str) -> bool {
+    let s = s.trim();
+    let target = match s.split_once(char::is_whitespace) {
+        Some((method, rest)) if !is_tag(method) => rest.trim(),
+        _ => s,
+    };
+    target.starts_with('/') || is_tag(target)
+}
+
+fn is_tag(s: &str) -> bool {
+    s.get(..4)
+        .is_some_and(|prefix| prefix.eq_ignore_ascii_case("tag:"))
+}
+
+fn matches_segments(pattern: &[Segment], path: &[&str]) -> bool {
+    match (pattern.first(), path.first()) {
+        (None, None) => true,
+        (Some(Segment::AnyDepth), _) => {
+            // `**` consumes zero or more segments
+            (0..=path.len()).any(|skip| matches_segments(&pattern[1..], &path[skip..]))
+        }
+        (Some(Segment::Any), Some(_)) => matches_segments(&pattern[1..], &path[1..]),
+        (Some(Segment::Literal(literal)), Some(segment)) if literal == segment => {
+            matches_segments(&pattern[1..], &path[1..])
+        }
+        _ => false,
+    }
+}
+
+impl FromStr for RoutePattern {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (method, path) = match s.split_once(char::is_whitespace) {
+            Some((method, path)) if !is_tag(method) => (Some(method.to_uppercase()), path.trim()),
+            _ => (None, s),
+        };
+        if is_tag(path) {
+            let tag = path[4..].trim();
+            if tag.is_empty() {
+                return Err(format!("Invalid route pattern '{}': empty tag", s));
+            }
+            return Ok(Self {
+                method,
+                target: Target::Tag(tag.to_string()),
+                source: s.to_string(),
+            });
+        }
+        if !path.starts_with('/') {
+            return Err(format!(
+                "Invalid route pattern '{}': path must start with '/'",
+                s
+            ));
+        }
+
+        let segments = path
+            .split('/')
+            .filter(|s| !s.is_empty())
+            .map(|segment| match segment {
+                "**" => Segment::AnyDepth,
+                "*" => Segment::Any,
+                s if s.starts_with(':') || (s.starts_with('{') && s.ends_with('}')) => Segment::Any,
+                s => Segment::Literal(s.to_string()),
+            })
+            .collect();
+
+        Ok(Self {
+            method,
+            target: Target::Path(segments),
+            source: s.to_string(),
+        })
+    }
+}
+
+impl fmt::Display for RoutePattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pattern_matching() {
+        let pattern: RoutePattern = "/oss/v2/buckets/*/objects/**".parse().unwrap();
+        assert!(pattern.matches("GET", "/oss/v2/buckets/b1/objects", &[]));
+        assert!(pattern.matches("PUT", "/oss/v2/buckets/b1/objects/a/b", &[]));
+        assert!(!pattern.matches("GET", "/oss/v2/buckets/b1", &[]));
+
+        let pattern: RoutePattern = "get /oss/v2/buckets/{bucketKey}".parse().unwrap();
+        assert!(pattern.matches("GET", "/oss/v2/buckets/b1", &[]));
+        assert!(!pattern.matches("DELETE", "/oss/v2/buckets/b1", &[]));
+        assert!("oss/v2".parse::<RoutePattern>().is_err());
+    }
+
+    #[test]
+    fn test_tag_patterns() {
+        let tags = ["Model Derivative".to_string()];
+        let pattern: RoutePattern = "tag:model derivative".parse().unwrap();
+        assert!(pattern.is_tag());
+        assert!(pattern.matches("POST", "/modelderivative/v2/designdata/job", &tags));
+        assert!(!pattern.matches("GET", "/oss/v2/buckets", &[]));
+
+        let pattern: RoutePattern = "GET tag:Model Derivative".parse().unwrap();
+        assert!(pattern.matches("GET", "/any", &tags));
+        assert!(!pattern.matches("POST", "/any", &tags));
+        assert!("tag:".parse::<RoutePattern>().is_err());
+        assert!(looks_like_pattern("tag:Model Derivative"));
+        assert!(looks_like_pattern("GET /oss/**"));
+        assert!(!looks_like_pattern("X-Note: a"));
+    }
+}
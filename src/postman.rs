@@ -0,0 +1,460 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+//! Postman collections (v2.0 and v2.1) as stub sources.
+//!
+//! Every saved example response of a collection becomes a stub answering the
+//! example's request ([`PostmanCollection::to_stubs`]), so a workflow
+//! documented in Postman turns into a mock as it is:
+//!
+//! - the path matches with `:name` segments, and `{{variable}}` segments the
+//!   collection does not define, as wildcards; the query parameters of the
+//!   example must be present with the same values
+//! - `{{variable}}`s of the collection are substituted in the path, query and
+//!   body, and the `{{$guid}}`, `{{$randomUUID}}` and `{{$isoTimestamp}}`
+//!   dynamic variables are rendered per request
+//! - like on a Postman mock server, the `x-mock-response-name` and
+//!   `x-mock-response-code` request headers select an example by name or
+//!   status; otherwise a request gets the first successful example
+
+use crate::error::{MockError, Result};
+use crate::har::TRANSFER_HEADERS;
+use crate::stubs::{ContentPattern, DEFAULT_PRIORITY, QueryParameterMatcher};
+use crate::stubs::{StubMapping, StubRequest, StubResponse};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Request header selecting an example by name
+pub const RESPONSE_NAME_HEADER: &str = "x-mock-response-name";
+/// Request header selecting an example by status code
+pub const RESPONSE_CODE_HEADER: &str = "x-mock-response-code";
+
+/// Postman dynamic variables and the template values they render as
+const DYNAMIC_VARIABLES: [(&str, &str); 3] = [
+    ("{{$guid}}", "{{ uuid }}"),
+    ("{{$randomUUID}}", "{{ uuid }}"),
+    ("{{$isoTimestamp}}", "{{ now }}"),
+];
+
+/// A Postman collection
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PostmanCollection {
+    pub info: PostmanInfo,
+    #[serde(default)]
+    pub item: Vec<PostmanItem>,
+    #[serde(default)]
+    pub variable: Vec<PostmanVariable>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PostmanInfo {
+    pub name: String,
+}
+
+/// A request, or a folder of further items
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PostmanItem {
+    #[serde(default)]
+    pub name: String,
+    /// Items of a folder
+    #[serde(default)]
+    pub item: Vec<PostmanItem>,
+    #[serde(default)]
+    pub request: Option<PostmanRequest>,
+    /// Saved example responses
+    #[serde(default)]
+    pub response: Vec<PostmanExample>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PostmanVariable {
+    pub key: String,
+    #[serde(default)]
+    pub value: Value,
+    #[serde(default)]
+    pub disabled: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PostmanRequest {
+    #[serde(default)]
+    pub method: Option<String>,
+    #[serde(default)]
+    pub url: Option<PostmanUrl>,
+}
+
+/// A URL, as a string or taken apart
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum PostmanUrl {
+    Raw(String),
+    Parts {
+        #[serde(default)]
+        raw: Option<String>,
+        /// Segments, as strings or `{"value": ...}` objects
+        #[serde(default)]
+        path: Option<Vec<Value>>,
+        #[serde(default)]
+        query: Vec<PostmanQuery>,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PostmanQuery {
+    #[serde(default)]
+    pub key: Option<String>,
+    #[serde(default)]
+    pub value: Option<String>,
+    #[serde(default)]
+    pub disabled: bool,
+}
+
+/// A saved example response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PostmanExample {
+    #[serde(default)]
+    pub name: String,
+    /// The request the example answers; the item's request when missing
+    #[serde(default)]
+    pub original_request: Option<PostmanRequest>,
+    #[serde(default)]
+    pub code: Option<u16>,
+    #[serde(default)]
+    pub header: Option<Vec<PostmanHeader>>,
+    #[serde(default)]
+    pub body: Option<String>,
+    #[serde(default, rename = "_postman_previewlanguage")]
+    pub preview_language: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PostmanHeader {
+    pub key: String,
+    #[serde(default)]
+    pub value: String,
+    #[serde(default)]
+    pub disabled: bool,
+}
+
+impl PostmanCollection {
+    /// Load a collection exported from Postman
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+        serde_json::from_str(&content).map_err(|e| {
+            MockError::Config(format!(
+                "Invalid Postman collection {}: {}",
+                path.display(),
+                e
+            ))
+        })
+    }
+
+    /// Number of saved examples, i.e. of requests answered
+    pub fn example_count(&self) -> usize {
+        fn count(items: &[PostmanItem]) -> usize {
+            items
+                .iter()
+                .map(|item| item.response.len() + count(&item.item))
+                .sum()
+        }
+        count(&self.item)
+    }
+
+    /// Turn every saved example into stubs: one answering requests that
+    /// select it by name, one answering those selecting it by status, and
+    /// one answering requests that select nothing, ranked so successful
+    /// examples come first
+    pub fn to_stubs(&self) -> Vec<StubMapping> {
+        let mut stubs = Vec::new();
+        self.collect_stubs(&self.item, &self.info.name, &mut stubs);
+        stubs
+    }
+
+    fn collect_stubs(&self, items: &[PostmanItem], parent: &str, stubs: &mut Vec<StubMapping>) {
+        for item in items {
+            let name = format!("{} / {}", parent, item.name);
+            self.collect_stubs(&item.item, &name, stubs);
+            if item.response.is_empty() {
+                continue;
+            }
+            for example in &item.response {
+                let Some(request) = example
+                    .original_request
+                    .as_ref()
+                    .or(item.request.as_ref())
+                    .map(|request| self.stub_request(request))
+                else {
+                    continue;
+                };
+                let response = self.stub_response(example);
+                let name = format!("{} / {} (Postman)", name, example.name);
+                let code = example.code.unwrap_or(200).to_string();
+                for (header, value) in [
+                    (RESPONSE_NAME_HEADER, example.name.as_str()),
+                    (RESPONSE_CODE_HEADER, code.as_str()),
+                ] {
+                    let mut request = request.clone();
+                    request.headers.insert(
+                        header.to_string(),
+                        ContentPattern {
+                            equal_to: Some(value.to_string()),
+                            ..ContentPattern::default()
+                        },
+                    );
+                    // Ahead of the default example of the same request
+                    stubs.push(StubMapping {
+                        id: None,
+                        name: Some(name.clone()),
+                        priority: Some(DEFAULT_PRIORITY - 1),
+                        scenario: None,
+                        request,
+                        response: response.clone(),
+                    });
+                }
+                // Without a selection the first successful example of a
+                // request wins, then its first example
+                let successful = example.code.is_none_or(|code| (200..300).contains(&code));
+                stubs.push(StubMapping {
+                    id: None,
+                    name: Some(name),
+                    priority: (!successful).then_some(DEFAULT_PRIORITY + 1),
+                    scenario: None,
+                    request,
+                    response,
+                });
+            }
+        }
+    }
+
+    fn stub_request(&self, request: &PostmanRequest) -> StubRequest {
+        let (segments, query): (Vec<String>, Vec<(String, String)>) = match &request.url {
+            Some(PostmanUrl::Parts {
+                path: Some(path),
+                query,
+                ..
+            }) => (
+                path.iter()
+                    .filter_map(|segment| match segment {
+                        Value::String(s) => Some(s.clone()),
+                        other => other.get("value")?.as_str().map(str::to_string),
+                    })
+                    .collect(),
+                query
+                    .iter()
+                    .filter(|q| !q.disabled)
+                    .filter_map(|q| Some((q.key.clone()?, q.value.clone().unwrap_or_default())))
+                    .collect(),
+            ),
+            Some(PostmanUrl::Parts { raw: Some(raw), .. }) | Some(PostmanUrl::Raw(raw)) => {
+                parse_raw(raw)
+            }
+            _ => (Vec::new(), Vec::new()),
+        };
+
+        let mut templated = false;
+        let path: Vec<String> = segments
+            .iter()
+            .map(|segment| self.resolve(segment))
+            .flat_map(|segment| {
+                segment
+                    .split('/')
+                    .filter(|s| !s.is_empty())
+                    .map(|s| {
+                        let variable = s
+                            .strip_prefix(':')
+                            .or_else(|| s.strip_prefix("{{")?.strip_suffix("}}"));
+                        match variable {
+                            Some(name) => {
+                                templated = true;
+                                format!("{{{}}}", name)
+                            }
+                            None => s.to_string(),
+                        }
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        let path = format!("/{}", path.join("/"));
+
+        let mut query_parameters = BTreeMap::new();
+        for (key, value) in query {
+            let value = self.resolve(&value);
+            let matcher = if value.contains("{{") {
+                QueryParameterMatcher {
+                    absent: Some(false),
+                    ..QueryParameterMatcher::default()
+                }
+            } else {
+                QueryParameterMatcher {
+                    equal_to: Some(value),
+                    ..QueryParameterMatcher::default()
+                }
+            };
+            query_parameters.insert(key, matcher);
+        }
+
+        let (url_path, url_path_template) = if templated {
+            (None, Some(path))
+        } else {
+            (Some(path), None)
+        };
+        StubRequest {
+            method: Some(request.method.as_deref().unwrap_or("GET").to_uppercase()),
+            url_path,
+            url_path_template,
+            query_parameters,
+            ..StubRequest::default()
+        }
+    }
+
+    fn stub_response(&self, example: &PostmanExample) -> StubResponse {
+        let mut headers: BTreeMap<String, String> = example
+            .header
+            .iter()
+            .flatten()
+            .filter(|h| !h.disabled)
+            .filter(|h| {
+                !TRANSFER_HEADERS
+                    .iter()
+                    .any(|t| h.key.eq_ignore_ascii_case(t))
+            })
+            .map(|h| (h.key.to_lowercase(), h.value.clone()))
+            .collect();
+        if !headers.contains_key("content-type")
+            && example.preview_language.as_deref() == Some("json")
+        {
+            headers.insert("content-type".to_string(), "application/json".to_string());
+        }
+
+        let mut template = false;
+        let body = example.body.as_deref().map(|body| {
+            let mut body = self.resolve(body);
+            for (variable, rendered) in DYNAMIC_VARIABLES {
+                if body.contains(variable) {
+                    body = body.replace(variable, rendered);
+                    template = true;
+                }
+            }
+            body
+        });
+        StubResponse {
+            status: example.code.unwrap_or(200),
+            headers,
+            body,
+            template,
+            ..StubResponse::default()
+        }
+    }
+
+    /// Substitute the collection's variables in `text`, leaving unknown ones
+    fn resolve(&self, text: &str) -> String {
+        let mut resolved = text.to_string();
+        for variable in self.variable.iter().filter(|v| !v.disabled) {
+            let value = match &variable.value {
+                Value::String(s) => s.clone(),
+                Value::Null => continue,
+                other => other.to_string(),
+            };
+            resolved = resolved.replace(&format!("{{{{{}}}}}", variable.key), &value);
+        }
+        resolved
+    }
+}
+
+/// Path segments and query parameters of a raw URL such as
+/// `{{baseUrl}}/oss/v2/buckets?limit=10`. A leading variable or scheme and
+/// host are not part of the path.
+fn parse_raw(raw: &str) -> (Vec<String>, Vec<(String, String)>) {
+    let (path, query) = raw.split_once('?').unwrap_or((raw, ""));
+    let path = match path.split_once("://") {
+        Some((_, rest)) => rest.find('/').map_or("", |start| &rest[start..]),
+        None if path.starts_with("{{") => path.find("}}").map_or(path, |end| &path[end + 2..]),
+        None => path,
+    };
+    let segments = path
+        .split('/')
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+        .collect();
+    let query = form_urlencoded::parse(query.as_bytes())
+        .map(|(k, v)| (k.into_owned(), v.into_owned()))
+        .collect();
+    (segments, query)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stubs::StubStore;
+    use crate::templating::TemplateContext;
+    use axum::http::{HeaderMap, HeaderValue, StatusCode};
+    use std::path::PathBuf;
+
+    #[tokio::test]
+    async fn test_examples_become_selectable_stubs() {
+        let collection: PostmanCollection = serde_json::from_value(serde_json::json!({
+            "info": { "name": "APS" },
+            "variable": [{ "key": "baseUrl", "value": "https://developer.api.autodesk.com" }],
+            "item": [{
+                "name": "OSS",
+                "item": [{
+                    "name": "Bucket details",
+                    "request": {
+                        "method": "GET",
+                        "url": {
+                            "raw": "{{baseUrl}}/oss/v2/buckets/:bucketKey/details",
+                            "path": ["oss", "v2", "buckets", ":bucketKey", "details"]
+                        }
+                    },
+                    "response": [
+                        {
+                            "name": "Missing",
+                            "code": 404,
+                            "body": "{\"reason\":\"Bucket not found\"}"
+                        },
+                        {
+                            "name": "Found",
+                            "code": 200,
+                            "header": [{ "key": "Content-Type", "value": "application/json" }],
+                            "body": "{\"bucketKey\":\"{{ path.bucketKey }}\",\"requestId\":\"{{$guid}}\"}"
+                        }
+                    ]
+                }]
+            }]
+        }))
+        .unwrap();
+        assert_eq!(collection.example_count(), 2);
+
+        let store = StubStore::new(collection.to_stubs(), PathBuf::new());
+        let path = "/oss/v2/buckets/b1/details";
+        let answer = |headers: HeaderMap| {
+            let store = &store;
+            async move {
+                let stub = store.find("GET", path, None, &headers, None, None).unwrap();
+                let context = TemplateContext::new("GET", path, None, &headers);
+                store.respond(&stub, context).await
+            }
+        };
+
+        let response = answer(HeaderMap::new()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body: Value = serde_json::from_slice(
+            &axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(body["bucketKey"], "b1");
+        assert_eq!(body["requestId"].as_str().unwrap().len(), 36);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(RESPONSE_CODE_HEADER, HeaderValue::from_static("404"));
+        assert_eq!(answer(headers).await.status(), StatusCode::NOT_FOUND);
+        let mut headers = HeaderMap::new();
+        headers.insert(RESPONSE_NAME_HEADER, HeaderValue::from_static("Missing"));
+        assert_eq!(answer(headers).await.status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,359 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+//! Structural diffing of the mock against responses recorded from the real
+//! APS APIs.
+//!
+//! A recordings directory holds JSON or YAML files, each with one recording
+//! or a `{"recordings": [...]}` list:
+//!
+//! ```yaml
+//! request:
+//!   method: GET
+//!   url: /oss/v2/buckets?limit=1
+//! response:
+//!   status: 200
+//!   headers: { x-ads-region: US }
+//!   body: { items: [{ bucketKey: real, createdDate: 1700000000000, policyKey: transient }] }
+//! ```
+//!
+//! Every request is replayed against the mock and the responses compared by
+//! shape: status, header names, JSON keys and value types. Values themselves
+//! are not compared, since IDs and dates differ between runs anyway.
+
+use crate::error::Result;
+use axum::{
+    Router,
+    body::{Body, to_bytes},
+    http::Request,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tower::ServiceExt;
+
+/// Upper bound on mock response bodies read for comparison
+const MAX_BODY_BYTES: usize = 16 * 1024 * 1024;
+
+/// Headers that vary per connection or server and say nothing about fidelity
+const IGNORED_HEADERS: &[&str] = &[
+    "connection",
+    "content-length",
+    "date",
+    "keep-alive",
+    "server",
+    "set-cookie",
+    "strict-transport-security",
+    "transfer-encoding",
+    "via",
+    "x-amz-cf-id",
+    "x-amz-cf-pop",
+    "x-cache",
+];
+
+/// A request made to the real API and the response it got
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Recording {
+    pub request: RecordedRequest,
+    pub response: RecordedResponse,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedRequest {
+    pub method: String,
+    /// Path and query
+    pub url: String,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    #[serde(default)]
+    pub body: Option<Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedResponse {
+    pub status: u16,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    #[serde(default)]
+    pub body: Option<Value>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RecordingFile {
+    Many { recordings: Vec<Recording> },
+    One(Box<Recording>),
+}
+
+/// Load every recording below `dir`, in file name order
+pub fn load_dir(dir: &Path) -> Result<Vec<Recording>> {
+    let mut entries: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .collect();
+    entries.sort();
+
+    let mut recordings = Vec::new();
+    for path in entries {
+        if path.is_dir() {
+            recordings.extend(load_dir(&path)?);
+            continue;
+        }
+        if !path
+            .extension()
+            .is_some_and(|ext| ext == "json" || ext == "yaml" || ext == "yml")
+        {
+            continue;
+        }
+        let content = fs::read_to_string(&path)?;
+        match serde_yaml::from_str::<RecordingFile>(&content) {
+            Ok(RecordingFile::Many { recordings: many }) => recordings.extend(many),
+            Ok(RecordingFile::One(one)) => recordings.push(*one),
+            Err(e) => tracing::warn!("Failed to parse recording {}: {}", path.display(), e),
+        }
+    }
+    Ok(recordings)
+}
+
+/// A structural difference between the recorded and the mock response
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum Divergence {
+    Status {
+        recorded: u16,
+        mock: u16,
+    },
+    /// Header the real API sends but the mock does not
+    MissingHeader {
+        name: String,
+    },
+    /// Field the real API returns but the mock does not
+    MissingField {
+        path: String,
+    },
+    /// Field only the mock returns
+    ExtraField {
+        path: String,
+    },
+    TypeMismatch {
+        path: String,
+        recorded: String,
+        mock: String,
+    },
+}
+
+impl std::fmt::Display for Divergence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Divergence::Status { recorded, mock } => {
+                write!(f, "status: recorded {} but mock {}", recorded, mock)
+            }
+            Divergence::MissingHeader { name } => write!(f, "header {} missing in mock", name),
+            Divergence::MissingField { path } => write!(f, "{}: missing in mock", path),
+            Divergence::ExtraField { path } => write!(f, "{}: only in mock", path),
+            Divergence::TypeMismatch {
+                path,
+                recorded,
+                mock,
+            } => write!(f, "{}: recorded {} but mock {}", path, recorded, mock),
+        }
+    }
+}
+
+/// Divergences found for one recording
+#[derive(Debug, Clone, Serialize)]
+pub struct RecordingDiff {
+    pub method: String,
+    pub url: String,
+    pub divergences: Vec<Divergence>,
+}
+
+/// Replay every recording against `router` and diff the responses.
+/// `bearer_token` replaces recorded `Authorization` headers.
+pub async fn diff(
+    router: Router,
+    recordings: &[Recording],
+    bearer_token: Option<&str>,
+) -> Vec<RecordingDiff> {
+    let mut diffs = Vec::new();
+    for recording in recordings {
+        let divergences = match replay(router.clone(), &recording.request, bearer_token).await {
+            Some((status, headers, body)) => {
+                compare_response(&recording.response, status, &headers, body.as_ref())
+            }
+            None => vec![Divergence::Status {
+                recorded: recording.response.status,
+                mock: 0,
+            }],
+        };
+        diffs.push(RecordingDiff {
+            method: recording.request.method.to_uppercase(),
+            url: recording.request.url.clone(),
+            divergences,
+        });
+    }
+    diffs
+}
+
+async fn replay(
+    router: Router,
+    recorded: &RecordedRequest,
+    bearer_token: Option<&str>,
+) -> Option<(u16, axum::http::HeaderMap, Option<Value>)> {
+    let mut builder = Request::builder()
+        .method(recorded.method.to_uppercase().as_str())
+        .uri(&recorded.url);
+    for (name, value) in &recorded.headers {
+        if bearer_token.is_some() && name.eq_ignore_ascii_case("authorization") {
+            continue;
+        }
+        builder = builder.header(name.as_str(), value.as_str());
+    }
+    if let Some(token) = bearer_token {
+        builder = builder.header("authorization", format!("Bearer {}", token));
+    }
+    let body = match &recorded.body {
+        None => Body::empty(),
+        Some(Value::String(text)) => Body::from(text.clone()),
+        Some(json) => {
+            if !recorded
+                .headers
+                .keys()
+                .any(|name| name.eq_ignore_ascii_case("content-type"))
+            {
+                builder = builder.header("content-type", "application/json");
+            }
+            Body::from(json.to_string())
+        }
+    };
+    let response = router.oneshot(builder.body(body).ok()?).await.ok()?;
+    let status = response.status().as_u16();
+    let headers = response.headers().clone();
+    let bytes = to_bytes(response.into_body(), MAX_BODY_BYTES).await.ok()?;
+    let body = (!bytes.is_empty()).then(|| {
+        serde_json::from_slice(&bytes)
+            .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(&bytes).into_owned()))
+    });
+    Some((status, headers, body))
+}
+
+fn compare_response(
+    recorded: &RecordedResponse,
+    status: u16,
+    headers: &axum::http::HeaderMap,
+    body: Option<&Value>,
+) -> Vec<Divergence> {
+    let mut divergences = Vec::new();
+    if recorded.status != status {
+        divergences.push(Divergence::Status {
+            recorded: recorded.status,
+            mock: status,
+        });
+    }
+    for name in recorded.headers.keys() {
+        let lower = name.to_ascii_lowercase();
+        if !IGNORED_HEADERS.contains(&lower.as_str()) && !headers.contains_key(lower.as_str()) {
+            divergences.push(Divergence::MissingHeader { name: lower });
+        }
+    }
+    match (&recorded.body, body) {
+        (Some(recorded), Some(mock)) => compare_shape(recorded, mock, "$", &mut divergences),
+        (Some(recorded), None) => divergences.push(Divergence::TypeMismatch {
+            path: "$".into(),
+            recorded: type_name(recorded).into(),
+            mock: "empty body".into(),
+        }),
+        _ => {}
+    }
+    divergences
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// Compare the shape of two JSON values. Nulls match anything, since
+/// optional fields are often null on one side only.
+fn compare_shape(recorded: &Value, mock: &Value, path: &str, out: &mut Vec<Divergence>) {
+    match (recorded, mock) {
+        (Value::Null, _) | (_, Value::Null) => {}
+        (Value::Object(recorded), Value::Object(mock)) => {
+            for (key, value) in recorded {
+                let child = format!("{}.{}", path, key);
+                match mock.get(key) {
+                    Some(mock_value) => compare_shape(value, mock_value, &child, out),
+                    None => out.push(Divergence::MissingField { path: child }),
+                }
+            }
+            for key in mock.keys().filter(|key| !recorded.contains_key(*key)) {
+                out.push(Divergence::ExtraField {
+                    path: format!("{}.{}", path, key),
+                });
+            }
+        }
+        (Value::Array(recorded), Value::Array(mock)) => {
+            // Element shapes, not counts: compare the first of each
+            if let (Some(recorded), Some(mock)) = (recorded.first(), mock.first()) {
+                compare_shape(recorded, mock, &format!("{}[]", path), out);
+            }
+        }
+        _ if type_name(recorded) != type_name(mock) => out.push(Divergence::TypeMismatch {
+            path: path.to_string(),
+            recorded: type_name(recorded).into(),
+            mock: type_name(mock).into(),
+        }),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{Json, routing::get};
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_diff_reports_structural_divergences() {
+        let recording: Recording = serde_json::from_value(json!({
+            "request": { "method": "GET", "url": "/oss/v2/buckets" },
+            "response": {
+                "status": 200,
+                "headers": { "x-ads-region": "US", "Date": "Mon, 01 Jan 2024 00:00:00 GMT" },
+                "body": {
+                    "items": [{ "bucketKey": "real", "createdDate": 1700000000000_i64, "region": null }],
+                    "next": "/oss/v2/buckets?startAt=real"
+                }
+            }
+        }))
+        .unwrap();
+        let router = Router::new().route(
+            "/oss/v2/buckets",
+            get(|| async {
+                Json(json!({
+                    "items": [{ "bucket_key": "mock", "createdDate": "2024-01-01", "region": "US" }]
+                }))
+            }),
+        );
+
+        let diffs = diff(router, &[recording], None).await;
+        let divergences: Vec<String> = diffs[0].divergences.iter().map(|d| d.to_string()).collect();
+        assert_eq!(
+            divergences,
+            [
+                "header x-ads-region missing in mock",
+                "$.items[].bucketKey: missing in mock",
+                "$.items[].createdDate: recorded number but mock string",
+                "$.items[].bucket_key: only in mock",
+                "$.next: missing in mock",
+            ]
+        );
+    }
+}
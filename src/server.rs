@@ -1,74 +1,659 @@
 // SPDX-License-Identifier: Apache-2.0
 // Copyright 2024-2025 Dmytro Yemelianov
 
-use crate::config::{MockMode, MockServerConfig};
-use crate::error::Result;
+use crate::config::MockServerConfig;
+use crate::error::{MockError, Result};
+use crate::handlers::{CustomHandlerRegistry, StubBuilder};
+use crate::middleware::namespaces::{NamespaceBuilder, Namespaces, namespace_middleware};
+use crate::middleware::shared_state::{SharedState, shared_state_middleware};
+use crate::middleware::tenants::{Tenants, tenant_middleware};
 use crate::openapi::OpenApiParser;
+use crate::openapi::cache::SpecCache;
+use crate::server::catalog::{RouteCatalog, SkipReason, SkippedRoute};
+use crate::server::packs::RoutePack;
 use crate::state::StateManager;
+use crate::state::backend::StateBackend;
+use crate::state::journal::{JournalEntry, RequestMatcher, Times};
+use crate::state::webhooks::RetryPolicy;
+use crate::webhooks::delivery::{WebhookEvent, deliver};
 use axum::Router;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
 use tokio::net::TcpListener;
 
+pub mod catalog;
+pub mod consistency;
+pub mod packs;
+pub mod params;
 mod router;
 
 /// Mock server for APS APIs
 pub struct MockServer {
-    #[allow(dead_code)]
     config: MockServerConfig,
-    #[allow(dead_code)]
     state: Option<StateManager>,
+    /// Where the state is persisted, if anywhere
+    backend: Option<Arc<dyn StateBackend>>,
     router: Router,
+    catalog: Arc<RouteCatalog>,
+    /// Handlers registered with `stub`, shared with the router
+    handlers: Arc<CustomHandlerRegistry>,
+    /// State partitions selected with `x-mock-namespace`, when stateful
+    namespaces: Option<Arc<Namespaces>>,
+    /// State partitions of the apps, with `--isolate-clients`
+    tenants: Option<Arc<Tenants>>,
 }
 
 impl MockServer {
     /// Create a new mock server with the given configuration
     pub async fn new(config: MockServerConfig) -> Result<Self> {
+        Self::with_route_packs(config, Vec::new()).await
+    }
+
+    /// Create a mock server that also serves the routes of `packs`. Their
+    /// routes take precedence over the built-in ones, not over spec routes.
+    pub async fn with_route_packs(
+        config: MockServerConfig,
+        packs: Vec<Arc<dyn RoutePack>>,
+    ) -> Result<Self> {
         // Parse OpenAPI specs
-        let specs = OpenApiParser::parse_directory(&config.openapi_dir)?;
-        tracing::info!("Parsed {} OpenAPI specifications", specs.len());
+        let filter = config.spec_filter();
+        let cache = config.spec_cache_dir.as_ref().map(SpecCache::new);
+        let dirs = spec_dirs(&config)?;
+        let mut specs = Vec::new();
+        let mut skipped = Vec::new();
+        for dir in &dirs {
+            let parsed = OpenApiParser::parse_directory_reporting(dir, &filter, cache.as_ref())?;
+            specs.extend(parsed.specs);
+            skipped.extend(
+                parsed
+                    .failures
+                    .into_iter()
+                    .map(|(path, error)| SkippedRoute {
+                        method: None,
+                        path: None,
+                        origin: Some(path.display().to_string()),
+                        reason: SkipReason::ParseFailure,
+                        detail: error,
+                    }),
+            );
+        }
+        if specs.is_empty() {
+            tracing::warn!(
+                "No OpenAPI specs found in {}; only built-in routes are served. Pass --openapi-dir \
+                 or build with --features embedded-specs",
+                dirs.iter()
+                    .map(|dir| dir.display().to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
+        tracing::info!("Loaded {} OpenAPI specifications", specs.len());
 
         // Extract all routes
         let mut all_routes = Vec::new();
         for (name, spec) in specs {
-            let routes = OpenApiParser::extract_routes(&spec);
+            let mut routes = OpenApiParser::extract_routes(&spec);
             tracing::debug!("Extracted {} routes from {}", routes.len(), name);
+            for (method, path) in OpenApiParser::unsupported_operations(&spec) {
+                skipped.push(SkippedRoute::route(
+                    method,
+                    &path,
+                    Some(name.clone()),
+                    SkipReason::UnsupportedMethod,
+                    format!("{} operations are not served", method),
+                ));
+            }
+            for route in &mut routes {
+                route.spec = Some(name.clone());
+            }
             all_routes.extend(routes);
         }
 
-        // Create state manager if in stateful mode
-        let state = if config.mode == MockMode::Stateful {
-            let state_manager = StateManager::new();
-            if let Some(ref state_file) = config.state_file {
-                state_manager.load_from_file(state_file)?;
+        // Create state manager if any service runs statefully
+        let mut backend = None;
+        let state = if config.needs_state() {
+            let blobs = crate::state::blobs::open(&config.blob_store, config.blob_store_max_bytes)?;
+            let state_manager = configured_state(&config, blobs);
+            if let Some(spec) = config.state_store() {
+                let opened = crate::state::backend::open(&spec)?;
+                state_manager.load_from(opened.as_ref())?;
+                backend = Some(opened);
             }
+            seed(&state_manager, &config)?;
+            if let Some(ref journal_file) = config.journal_file {
+                let loaded = state_manager.journal.persist_to(journal_file)?;
+                tracing::info!(
+                    "Request journal persisted to {} ({} entries loaded)",
+                    journal_file.display(),
+                    loaded
+                );
+            }
+            spawn_upload_gc(&state_manager, config.upload_gc_interval_secs);
+            spawn_job_watcher(&state_manager);
             Some(state_manager)
         } else {
+            if config.seed_dir.is_some() {
+                tracing::warn!("Ignoring --seed-dir: no service runs statefully");
+            }
             None
         };
 
         // Build router using submodule
-        let router = crate::server::router::build_router(all_routes, state.clone())?;
+        let (mut router, catalog) = crate::server::router::build_router(
+            all_routes.clone(),
+            skipped.clone(),
+            state.clone(),
+            &config,
+            &packs,
+        )?;
+        let handlers = Arc::new(CustomHandlerRegistry::new());
+        router = router.layer(axum::Extension(handlers.clone()));
+        if let (Some(state), Some(backend)) = (&state, &backend)
+            && backend.is_shared()
+        {
+            tracing::info!(
+                "Sharing the state with other servers via {}",
+                backend.location()
+            );
+            let shared = SharedState::new(state.clone(), backend.clone());
+            router = router
+                .layer(axum::middleware::from_fn(shared_state_middleware))
+                .layer(axum::Extension(Arc::new(shared)));
+        }
+        let partition = |new_state: StateFactory| {
+            partition_router(
+                all_routes.clone(),
+                skipped.clone(),
+                config.clone(),
+                packs.clone(),
+                handlers.clone(),
+                new_state,
+            )
+        };
+        let tenants = match state {
+            Some(ref state) if config.isolate_clients => {
+                let main = state.clone();
+                let tenants = Arc::new(Tenants::new(
+                    state,
+                    Namespaces::new(partition(Box::new(move |config| {
+                        let blobs = Arc::new(crate::state::blobs::MemoryBlobStore::new());
+                        let state = main.partition(blobs);
+                        configure_resources(&state, config);
+                        Ok(state)
+                    }))),
+                ));
+                router = router
+                    .layer(axum::middleware::from_fn(tenant_middleware))
+                    .layer(axum::Extension(tenants.clone()));
+                Some(tenants)
+            }
+            _ => None,
+        };
+        // Outermost, so namespaced requests see nothing of the default state.
+        // Namespaces start as the server did, minus persisted records; their
+        // blobs are kept in memory and their journal is not persisted.
+        let namespaces = state.is_some().then(|| {
+            Arc::new(Namespaces::new(partition(Box::new(|config| {
+                let blobs = Arc::new(crate::state::blobs::MemoryBlobStore::new());
+                let state = configured_state(config, blobs);
+                seed(&state, config)?;
+                Ok(state)
+            }))))
+        });
+        if let Some(ref namespaces) = namespaces {
+            router = router
+                .layer(axum::middleware::from_fn(namespace_middleware))
+                .layer(axum::Extension(namespaces.clone()));
+        }
 
         Ok(Self {
             config,
             state,
+            backend,
             router,
+            catalog,
+            handlers,
+            namespaces,
+            tenants,
         })
     }
 
-    /// Start the server and listen on the given address
+    /// Start the server and listen on the given address until Ctrl-C or
+    /// SIGTERM, then save the state to its store and the request journal to
+    /// `--har-out`, if they are configured
     pub async fn start(&self, addr: &str) -> Result<()> {
         let listener = TcpListener::bind(addr).await?;
         tracing::info!("Server listening on {}", addr);
 
+        let autosave = self.spawn_autosave();
         axum::serve(listener, self.router.clone())
+            .with_graceful_shutdown(shutdown_signal())
             .await
             .map_err(|e| crate::error::MockError::Io(std::io::Error::other(e.to_string())))?;
+        if let Some(autosave) = autosave {
+            autosave.abort();
+        }
+
+        self.save_state()?;
+        self.export_har()
+    }
 
+    /// Write the request journal to `--har-out` as a HAR log; does nothing
+    /// without one or in stateless mode
+    pub fn export_har(&self) -> Result<()> {
+        if let (Some(state), Some(path)) = (&self.state, &self.config.har_out) {
+            let entries = state.journal.entries();
+            crate::har::Har::from_journal(&entries).save(path)?;
+            tracing::info!("Wrote {} requests to {}", entries.len(), path.display());
+        }
+        Ok(())
+    }
+
+    /// Save the state to the configured state file or backend; does nothing
+    /// without one or in stateless mode
+    pub fn save_state(&self) -> Result<()> {
+        if let (Some(state), Some(backend)) = (&self.state, &self.backend) {
+            state.save_to(backend.as_ref())?;
+            tracing::info!("State saved to {}", backend.location());
+        }
         Ok(())
     }
 
+    /// Save the state every `state_autosave_secs` while serving
+    fn spawn_autosave(&self) -> Option<tokio::task::JoinHandle<()>> {
+        let state = self.state.clone()?;
+        let backend = self.backend.clone()?;
+        let interval = Duration::from_secs(self.config.state_autosave_secs?.max(1));
+        Some(tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            // The first tick completes immediately
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                let (state, backend) = (state.clone(), backend.clone());
+                let saved =
+                    tokio::task::spawn_blocking(move || state.save_to(backend.as_ref())).await;
+                match saved {
+                    Ok(Ok(())) => tracing::debug!("State autosaved"),
+                    Ok(Err(e)) => tracing::warn!("Autosaving the state failed: {}", e),
+                    Err(e) => tracing::warn!("Autosaving the state failed: {}", e),
+                }
+            }
+        }))
+    }
+
     /// Expose a clone of the router for embedding or tests
     pub fn router(&self) -> Router {
         self.router.clone()
     }
+
+    /// Answer requests of `method` to `path` (with `:param` or `{param}`
+    /// segments) from code, ahead of the stubs, specs and built-in routes,
+    /// e.g. `server.stub(Method::GET, "/oss/v2/buckets/:key/details")
+    /// .match_query("limit", "10").respond(200, json!({...}))`. Takes effect
+    /// on routers cloned before, too.
+    pub fn stub(&self, method: axum::http::Method, path: &str) -> StubBuilder {
+        self.handlers.stub(method, path)
+    }
+
+    /// The handlers registered with `stub`, e.g. to `clear` them between tests
+    pub fn handlers(&self) -> &Arc<CustomHandlerRegistry> {
+        &self.handlers
+    }
+
+    /// The state partitions requests select with the `x-mock-namespace`
+    /// header, `None` when every service is stateless
+    pub fn namespaces(&self) -> Option<&Namespaces> {
+        self.namespaces.as_deref()
+    }
+
+    /// The resources of each app, `None` unless `--isolate-clients` is on
+    pub fn tenants(&self) -> Option<&Tenants> {
+        self.tenants.as_deref()
+    }
+
+    /// Every registered route, spec routes first
+    pub fn catalog(&self) -> &RouteCatalog {
+        &self.catalog
+    }
+
+    /// State shared with the router, `None` when every service is stateless
+    pub fn state(&self) -> Option<&StateManager> {
+        self.state.as_ref()
+    }
+
+    /// Requests the server received, oldest first; empty when every
+    /// service is stateless
+    pub fn received_requests(&self) -> Vec<JournalEntry> {
+        self.state
+            .as_ref()
+            .map(|state| state.journal.entries())
+            .unwrap_or_default()
+    }
+
+    /// Check that `matcher` selects a number of received requests `times`
+    /// allows, e.g. `1`, `2..` or `..=3`
+    pub fn verify(&self, matcher: &RequestMatcher, times: impl Into<Times>) -> Result<()> {
+        verify_requests(self.state.as_ref(), matcher, times.into())
+    }
+
+    /// What the server would serve, as JSON (`--dry-run`): the services and
+    /// their modes, every route, the behaviors the config turns on and the
+    /// records the state starts with
+    pub fn plan(&self) -> serde_json::Value {
+        use crate::config::{MockMode, Service};
+        use serde_json::json;
+
+        let config = &self.config;
+        let mode = |mode: MockMode| match mode {
+            MockMode::Stateful => "stateful",
+            MockMode::Stateless => "stateless",
+        };
+        let services: serde_json::Map<String, serde_json::Value> = Service::ALL
+            .into_iter()
+            .map(|service| (service.to_string(), mode(config.mode_for(service)).into()))
+            .collect();
+
+        let mut routes: Vec<_> = self.catalog.routes().iter().collect();
+        routes.sort_by(|a, b| (&a.path, a.method.as_str()).cmp(&(&b.path, b.method.as_str())));
+        let routes: Vec<_> = routes
+            .into_iter()
+            .map(|route| {
+                json!({
+                    "method": route.method.as_str(),
+                    "path": route.path,
+                    "source": route.source.as_str(),
+                    "origin": route.origin,
+                    "operationId": route.operation_id,
+                    "tags": route.tags,
+                    "requiresAuth": route.requires_auth
+                })
+            })
+            .collect();
+
+        let response_headers: Vec<_> = config
+            .response_headers
+            .iter()
+            .map(|rule| {
+                json!({
+                    "route": rule.route.as_ref().map(|r| r.to_string()),
+                    "name": rule.name,
+                    "value": rule.value
+                })
+            })
+            .collect();
+        let concurrency_limits: Vec<_> = config
+            .concurrency_limits
+            .iter()
+            .map(|limit| {
+                json!({
+                    "route": limit.route.to_string(),
+                    "maxInFlight": limit.max_in_flight,
+                    "queue": limit.queue
+                })
+            })
+            .collect();
+        let rate_limits: Vec<_> = config
+            .rate_limits
+            .iter()
+            .map(|limit| {
+                json!({
+                    "route": limit.route.to_string(),
+                    "requests": limit.requests,
+                    "periodSecs": limit.period.as_secs()
+                })
+            })
+            .collect();
+        let behaviors = json!({
+            "validateRequests": config.validate_requests,
+            "deterministic": config.deterministic,
+            "seed": config.seed,
+            "controlHeaders": config.control_headers,
+            "responseHeaders": response_headers,
+            "concurrencyLimits": concurrency_limits,
+            "rateLimits": rate_limits,
+            "latency": config.latency.iter().map(|r| r.to_string()).collect::<Vec<_>>(),
+            "clockSkewSecs": config.clock_skew_secs,
+            "tokenExpiresIn": config.token_expires_in,
+            "auditLog": config.audit_log,
+            "stubsDir": config.stubs_dir,
+            "pactFiles": config.pact_files,
+            "harFiles": config.har_files,
+            "postmanFiles": config.postman_files,
+            "proxyBase": config.proxy_base,
+            "recordDir": config.record_dir,
+            "replayDir": config.replay_dir,
+            "replayMatch": config.replay_match,
+            "isolateClients": config.isolate_clients,
+            "projectTemplate": config.project_template,
+            "uploadExpirationSecs": config.upload_expiration_secs,
+            "translationRules": config.translation_rules,
+            "derivativeSize": config.derivative_size,
+            "users": config.users.iter().map(|u| &u.id).collect::<Vec<_>>(),
+            "clients": config.clients.iter().map(|c| &c.client_id).collect::<Vec<_>>(),
+            "webhookAttempts": config.webhook_attempts,
+            "webhookBackoffMs": config.webhook_backoff_ms
+        });
+
+        let state = self.state.as_ref().map(|state| {
+            let file = crate::state::persistence::StateFile::capture(state);
+            json!({
+                "store": self.backend.as_ref().map(|b| b.location()),
+                "shared": self.backend.as_ref().is_some_and(|b| b.is_shared()),
+                "autosaveSecs": config.state_autosave_secs,
+                "seedDir": config.seed_dir,
+                "journalFile": config.journal_file,
+                "harOut": config.har_out,
+                "records": {
+                    "buckets": file.buckets.len(),
+                    "objects": file.objects.len(),
+                    "hubs": file.hubs.len(),
+                    "projects": file.projects.len(),
+                    "items": file.items.len(),
+                    "jobs": file.jobs.len(),
+                    "issues": file.issues.len(),
+                    "hooks": file.hooks.len(),
+                    "tokens": file.tokens.len()
+                }
+            })
+        });
+
+        json!({
+            "listen": format!("{}:{}", config.host, config.port),
+            "services": services,
+            "routes": routes,
+            "routeCount": self.catalog.routes().len(),
+            "behaviors": behaviors,
+            "state": state
+        })
+    }
+}
+
+/// Directories to parse specs from: the configured ones, or the embedded APS
+/// specs when none of them exists and the `embedded-specs` feature is on
+fn spec_dirs(config: &MockServerConfig) -> Result<Vec<PathBuf>> {
+    let dirs: Vec<PathBuf> = config.openapi_dirs().cloned().collect();
+    #[cfg(feature = "embedded-specs")]
+    if !dirs.iter().any(|dir| dir.exists()) {
+        tracing::info!("No OpenAPI directory found, using the embedded APS specs");
+        return Ok(vec![crate::openapi::embedded::extract()?]);
+    }
+    Ok(dirs)
+}
+
+/// Check the journal of `state` for the requests `matcher` selects
+pub(crate) fn verify_requests(
+    state: Option<&StateManager>,
+    matcher: &RequestMatcher,
+    times: Times,
+) -> Result<()> {
+    let Some(state) = state else {
+        return Err(MockError::Verification(
+            "requests are only recorded while a service is stateful".to_string(),
+        ));
+    };
+    state
+        .journal
+        .verify(matcher, times)
+        .map_err(MockError::Verification)
+}
+
+/// Resolves on Ctrl-C or, on Unix, SIGTERM
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            tracing::warn!("Cannot listen for Ctrl-C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                tracing::warn!("Cannot listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+    tracing::info!("Shutting down");
+}
+
+/// A state set up as the configuration says, before persisted records and
+/// fixtures are loaded
+fn configured_state(
+    config: &MockServerConfig,
+    blobs: Arc<dyn crate::state::blobs::BlobStore>,
+) -> StateManager {
+    let state_manager = StateManager::with_blob_store(blobs);
+    state_manager.auth.set_clock_skew(config.clock_skew_secs);
+    state_manager.auth.set_expires_in(config.token_expires_in);
+    state_manager.audit.set_capacity(config.audit_log);
+    for user in &config.users {
+        state_manager.users.insert_user(user.clone());
+    }
+    for client in &config.clients {
+        state_manager.clients.insert_client(client.clone());
+    }
+    configure_resources(&state_manager, config);
+    state_manager
+}
+
+/// Apply the configuration of the stores a [`StateManager::partition`]
+/// has of its own
+fn configure_resources(state: &StateManager, config: &MockServerConfig) {
+    state
+        .translations
+        .set_rules(config.translation_rules.clone());
+    state.webhooks.set_retry_policy(RetryPolicy {
+        attempts: config.webhook_attempts,
+        backoff_ms: config.webhook_backoff_ms,
+    });
+}
+
+/// Load the `--seed-dir` fixtures into a state
+fn seed(state: &StateManager, config: &MockServerConfig) -> Result<()> {
+    if let Some(ref seed_dir) = config.seed_dir {
+        let fixtures = crate::state::fixtures::Fixtures::load_dir(seed_dir)?;
+        fixtures.apply(state)?;
+        tracing::info!(
+            "Seeded {} records from {}",
+            fixtures.len(),
+            seed_dir.display()
+        );
+    }
+    Ok(())
+}
+
+/// Makes the state of a partition from the configuration
+type StateFactory = Box<dyn Fn(&MockServerConfig) -> Result<StateManager> + Send + Sync>;
+
+/// Build the routers of partitions (namespaces, tenants): the routes of the
+/// server, over the state `new_state` makes from the configuration
+fn partition_router(
+    routes: Vec<crate::openapi::types::RouteDefinition>,
+    skipped: Vec<SkippedRoute>,
+    config: MockServerConfig,
+    packs: Vec<Arc<dyn RoutePack>>,
+    handlers: Arc<CustomHandlerRegistry>,
+    new_state: StateFactory,
+) -> NamespaceBuilder {
+    Box::new(move || {
+        let state = new_state(&config)?;
+        spawn_upload_gc(&state, config.upload_gc_interval_secs);
+        spawn_job_watcher(&state);
+        let (router, _) = crate::server::router::build_router(
+            routes.clone(),
+            skipped.clone(),
+            Some(state.clone()),
+            &config,
+            &packs,
+        )?;
+        Ok((state, router.layer(axum::Extension(handlers.clone()))))
+    })
+}
+
+/// How often the job watcher looks for job progress to notify
+const JOB_WATCH_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Periodically record notifications of job progress and deliver the
+/// translation events among them to webhooks. Like the upload GC, the task
+/// stops once the jobs it watches have been dropped.
+fn spawn_job_watcher(state: &StateManager) {
+    let jobs = Arc::downgrade(&state.jobs);
+    let hooks = Arc::downgrade(&state.webhooks);
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(JOB_WATCH_INTERVAL);
+        // Notifications the admin feed observes are delivered too
+        let mut delivered = None;
+        loop {
+            ticker.tick().await;
+            let (Some(jobs), Some(hooks)) = (jobs.upgrade(), hooks.upgrade()) else {
+                break;
+            };
+            jobs.observe();
+            for notification in jobs.notifications(delivered) {
+                tracing::debug!(
+                    "Job {} ({}) {:?} at {}%",
+                    notification.job_id,
+                    notification.kind.as_str(),
+                    notification.phase,
+                    notification.progress
+                );
+                delivered = Some(notification.seq);
+                if let Some(event) = WebhookEvent::from_job(&notification) {
+                    deliver(&hooks, &event);
+                }
+            }
+        }
+    });
+}
+
+/// Periodically drop expired upload sessions. The task holds a weak reference
+/// and stops once the state it sweeps has been dropped.
+fn spawn_upload_gc(state: &StateManager, interval_secs: u64) {
+    let uploads = Arc::downgrade(&state.uploads);
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(Duration::from_secs(interval_secs.max(1)));
+        loop {
+            ticker.tick().await;
+            let Some(uploads) = uploads.upgrade() else {
+                break;
+            };
+            let purged = uploads.purge_expired();
+            if purged > 0 {
+                tracing::debug!("Purged {} expired upload sessions", purged);
+            }
+        }
+    });
 }
@@ -0,0 +1,356 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+//! Catalog of the routes the server has registered, keyed by path template,
+//! and of the ones it left out.
+
+use crate::openapi::types::{HttpMethod, RouteDefinition};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+
+/// Where a route came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RouteSource {
+    /// Generated from an OpenAPI specification
+    OpenApi,
+    /// Built-in stateful handler
+    Builtin,
+}
+
+impl RouteSource {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RouteSource::OpenApi => "openapi",
+            RouteSource::Builtin => "builtin",
+        }
+    }
+}
+
+/// Metadata about a registered route
+#[derive(Debug, Clone)]
+pub struct RouteInfo {
+    pub method: HttpMethod,
+    /// Axum path template (e.g. `/oss/v2/buckets/:bucket_key`)
+    pub path: String,
+    pub operation_id: Option<String>,
+    pub source: RouteSource,
+    /// Whether requests must carry a valid Bearer token
+    pub requires_auth: bool,
+    /// OAuth scope sets, any one of which authorizes a token
+    pub required_scopes: Vec<Vec<String>>,
+    /// OpenAPI tags of the operation
+    pub tags: Vec<String>,
+    /// Spec the route was generated from, or the pack that contributed it
+    pub origin: Option<String>,
+}
+
+impl RouteInfo {
+    pub fn from_definition(route: &RouteDefinition) -> Self {
+        Self {
+            method: route.method,
+            path: route.path_pattern.clone(),
+            operation_id: route.operation.operation_id.clone(),
+            source: RouteSource::OpenApi,
+            requires_auth: route.requires_bearer_token(),
+            required_scopes: route.required_scopes(),
+            tags: route.operation.tags.clone().unwrap_or_default(),
+            origin: route.spec.clone(),
+        }
+    }
+
+    pub fn builtin(path: &str, method: HttpMethod) -> Self {
+        Self {
+            method,
+            path: path.to_string(),
+            operation_id: None,
+            source: RouteSource::Builtin,
+            requires_auth: true,
+            required_scopes: Vec::new(),
+            tags: Vec::new(),
+            origin: None,
+        }
+    }
+
+    /// Whether a token with the given space-separated scope may call the route
+    pub fn allows_scope(&self, scope: Option<&str>) -> bool {
+        let granted: Vec<&str> = scope.unwrap_or_default().split_whitespace().collect();
+        self.required_scopes.is_empty()
+            || self
+                .required_scopes
+                .iter()
+                .any(|set| set.iter().all(|s| granted.contains(&s.as_str())))
+    }
+
+    /// Where the route came from, for log and skip messages
+    fn provenance(&self) -> String {
+        match (self.source, &self.origin) {
+            (RouteSource::OpenApi, Some(spec)) => format!("spec {}", spec),
+            (RouteSource::Builtin, Some(pack)) => format!("the {} pack", pack),
+            (source, None) => format!("a {} route", source.as_str()),
+        }
+    }
+
+    /// Mark the route as callable without a Bearer token
+    pub fn public(mut self) -> Self {
+        self.requires_auth = false;
+        self
+    }
+}
+
+/// Why a route was left out at startup
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SkipReason {
+    /// Another spec or pack registered the same method and path first
+    Duplicate,
+    /// The path cannot be routed next to one registered earlier
+    Conflict,
+    /// A route pack serves the operation instead of its spec
+    Overridden,
+    /// The spec declares an operation for a method that is not served
+    UnsupportedMethod,
+    /// The spec file could not be parsed, so none of its routes exist
+    ParseFailure,
+}
+
+impl SkipReason {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SkipReason::Duplicate => "duplicate",
+            SkipReason::Conflict => "conflict",
+            SkipReason::Overridden => "overridden",
+            SkipReason::UnsupportedMethod => "unsupported-method",
+            SkipReason::ParseFailure => "parse-failure",
+        }
+    }
+}
+
+/// A route (or whole spec file) that was not registered
+#[derive(Debug, Clone)]
+pub struct SkippedRoute {
+    pub method: Option<String>,
+    pub path: Option<String>,
+    /// Spec or pack the route came from, or the spec file that failed
+    pub origin: Option<String>,
+    pub reason: SkipReason,
+    pub detail: String,
+}
+
+impl SkippedRoute {
+    pub fn route(
+        method: &str,
+        path: &str,
+        origin: Option<String>,
+        reason: SkipReason,
+        detail: impl Into<String>,
+    ) -> Self {
+        Self {
+            method: Some(method.to_string()),
+            path: Some(path.to_string()),
+            origin,
+            reason,
+            detail: detail.into(),
+        }
+    }
+}
+
+/// The paths routed so far, as axum matches them
+#[derive(Default)]
+struct Paths {
+    matcher: matchit::Router<()>,
+    seen: HashSet<String>,
+}
+
+impl fmt::Debug for Paths {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Paths").field("seen", &self.seen).finish()
+    }
+}
+
+/// Registered routes, in registration order, and the routes left out
+#[derive(Debug, Default)]
+pub struct RouteCatalog {
+    routes: Vec<RouteInfo>,
+    index: HashMap<(String, HttpMethod), usize>,
+    paths: Paths,
+    skipped: Vec<SkippedRoute>,
+}
+
+impl RouteCatalog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a route, returns false if the path and method are already taken
+    pub fn insert(&mut self, info: RouteInfo) -> bool {
+        let key = (info.path.clone(), info.method);
+        if self.index.contains_key(&key) {
+            return false;
+        }
+        self.index.insert(key, self.routes.len());
+        self.routes.push(info);
+        true
+    }
+
+    /// Add a route, recording it as a skipped duplicate (and returning
+    /// false) if the path and method are already taken
+    pub fn register(&mut self, info: RouteInfo) -> bool {
+        let key = (info.path.clone(), info.method);
+        if let Some(&i) = self.index.get(&key) {
+            let detail = format!("already served by {}", self.routes[i].provenance());
+            self.skip(SkippedRoute::route(
+                info.method.as_str(),
+                &info.path,
+                info.origin,
+                SkipReason::Duplicate,
+                detail,
+            ));
+            return false;
+        }
+        self.insert(info)
+    }
+
+    /// Look up a route by its path template and HTTP method name. `HEAD`
+    /// falls back to the `GET` route, which axum also serves `HEAD` with.
+    pub fn get(&self, path: &str, method: &str) -> Option<&RouteInfo> {
+        let method = parse_method(method)?;
+        let lookup = |method| self.index.get(&(path.to_string(), method));
+        lookup(method)
+            .or_else(|| (method == HttpMethod::Head).then(|| lookup(HttpMethod::Get))?)
+            .map(|&i| &self.routes[i])
+    }
+
+    pub fn routes(&self) -> &[RouteInfo] {
+        &self.routes
+    }
+
+    /// Claim `path` for routing; the error says which earlier path it
+    /// conflicts with, since axum panics on such paths. Paths already
+    /// claimed (by a route with another method) are accepted again.
+    pub fn claim_path(&mut self, path: &str) -> Result<(), String> {
+        if self.paths.seen.contains(path) {
+            return Ok(());
+        }
+        self.paths
+            .matcher
+            .insert(path, ())
+            .map_err(|e| e.to_string())?;
+        self.paths.seen.insert(path.to_string());
+        Ok(())
+    }
+
+    /// Record a route that was left out
+    pub fn skip(&mut self, route: SkippedRoute) {
+        tracing::debug!(
+            "Skipping {} {} ({}): {}",
+            route.method.as_deref().unwrap_or("*"),
+            route.path.as_deref().unwrap_or("*"),
+            route.reason.as_str(),
+            route.detail
+        );
+        self.skipped.push(route);
+    }
+
+    /// Routes left out, in the order they were met
+    pub fn skipped(&self) -> &[SkippedRoute] {
+        &self.skipped
+    }
+
+    /// Routes grouped by tag, tags in alphabetical order and untagged routes
+    /// (`None`) last. A route with several tags appears in each group.
+    pub fn by_tag(&self) -> Vec<(Option<&str>, Vec<&RouteInfo>)> {
+        let mut groups: BTreeMap<&str, Vec<&RouteInfo>> = BTreeMap::new();
+        let mut untagged = Vec::new();
+        for route in &self.routes {
+            if route.tags.is_empty() {
+                untagged.push(route);
+            }
+            for tag in &route.tags {
+                groups.entry(tag.as_str()).or_default().push(route);
+            }
+        }
+        let mut grouped: Vec<_> = groups
+            .into_iter()
+            .map(|(tag, routes)| (Some(tag), routes))
+            .collect();
+        if !untagged.is_empty() {
+            grouped.push((None, untagged));
+        }
+        grouped
+    }
+}
+
+fn parse_method(method: &str) -> Option<HttpMethod> {
+    match method {
+        "GET" => Some(HttpMethod::Get),
+        "POST" => Some(HttpMethod::Post),
+        "PUT" => Some(HttpMethod::Put),
+        "DELETE" => Some(HttpMethod::Delete),
+        "PATCH" => Some(HttpMethod::Patch),
+        "HEAD" => Some(HttpMethod::Head),
+        "OPTIONS" => Some(HttpMethod::Options),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_any_scope_set_authorizes() {
+        let mut route = RouteInfo::builtin("/oss/v2/buckets", HttpMethod::Post);
+        route.required_scopes = vec![
+            vec!["bucket:create".into(), "data:write".into()],
+            vec!["data:create".into()],
+        ];
+
+        assert!(route.allows_scope(Some("data:read data:write bucket:create")));
+        assert!(route.allows_scope(Some("data:create")));
+        assert!(!route.allows_scope(Some("data:write")));
+        assert!(!route.allows_scope(None));
+    }
+
+    #[test]
+    fn test_routes_grouped_by_tag() {
+        let mut catalog = RouteCatalog::new();
+        let tagged = |path: &str, tags: &[&str]| {
+            let mut route = RouteInfo::builtin(path, HttpMethod::Get);
+            route.tags = tags.iter().map(|t| t.to_string()).collect();
+            route
+        };
+        catalog.insert(tagged("/oss/v2/buckets", &["Buckets"]));
+        catalog.insert(tagged("/health", &[]));
+        catalog.insert(tagged(
+            "/oss/v2/buckets/:bucket_key/objects",
+            &["Objects", "Buckets"],
+        ));
+
+        let groups: Vec<(Option<&str>, Vec<&str>)> = catalog
+            .by_tag()
+            .into_iter()
+            .map(|(tag, routes)| (tag, routes.iter().map(|r| r.path.as_str()).collect()))
+            .collect();
+        assert_eq!(
+            groups,
+            [
+                (
+                    Some("Buckets"),
+                    vec!["/oss/v2/buckets", "/oss/v2/buckets/:bucket_key/objects"]
+                ),
+                (Some("Objects"), vec!["/oss/v2/buckets/:bucket_key/objects"]),
+                (None, vec!["/health"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_paths_axum_would_refuse_are_rejected() {
+        let mut catalog = RouteCatalog::new();
+        assert!(catalog.claim_path("/files/:id").is_ok());
+        // Another method on the same path
+        assert!(catalog.claim_path("/files/:id").is_ok());
+        assert!(catalog.claim_path("/files/:id/versions").is_ok());
+        assert!(catalog.claim_path("/files/*rest").is_err());
+        assert!(catalog.claim_path("/files/latest").is_ok());
+    }
+}
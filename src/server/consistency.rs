@@ -0,0 +1,158 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+//! Startup check that the hand-written pack routes still line up with the
+//! loaded specs, so curated handlers do not silently drift from the official
+//! OpenAPI definitions.
+//!
+//! Only routes of services with at least one spec route loaded are checked;
+//! with `--include oss` the other services' packs are not reported.
+
+use crate::openapi::schemas::service_name;
+use crate::openapi::types::HttpMethod;
+use crate::server::catalog::{RouteCatalog, RouteSource};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+/// How a pack route differs from the loaded specs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Drift {
+    /// No loaded spec defines the path
+    PathNotInSpecs,
+    /// A spec defines the path, but not for this method
+    MethodNotInSpecs,
+    /// A spec defines the path with differently named parameters
+    ParamNames { spec_path: String },
+}
+
+/// A pack route that drifted from the specs
+#[derive(Debug, Clone)]
+pub struct RouteDrift {
+    pub pack: String,
+    pub method: HttpMethod,
+    pub path: String,
+    pub drift: Drift,
+}
+
+impl fmt::Display for RouteDrift {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} route {} {} ",
+            self.pack,
+            self.method.as_str(),
+            self.path
+        )?;
+        match &self.drift {
+            Drift::PathNotInSpecs => f.write_str("is not defined by any loaded spec"),
+            Drift::MethodNotInSpecs => f.write_str("has a spec path but no spec operation"),
+            Drift::ParamNames { spec_path } => {
+                write!(f, "names its parameters differently than {}", spec_path)
+            }
+        }
+    }
+}
+
+/// The spec routes of a catalog, indexed for [`SpecRoutes::check`]
+#[derive(Debug, Default)]
+pub struct SpecRoutes {
+    methods: HashMap<String, Vec<HttpMethod>>,
+    /// Templates by shape (parameter names erased)
+    shapes: HashMap<String, String>,
+    services: HashSet<String>,
+}
+
+impl SpecRoutes {
+    pub fn from_catalog(catalog: &RouteCatalog) -> Self {
+        let mut routes = Self::default();
+        for route in catalog.routes() {
+            if route.source == RouteSource::OpenApi {
+                routes.insert(&route.path, route.method);
+            }
+        }
+        routes
+    }
+
+    /// Add a spec route (an axum path template)
+    pub fn insert(&mut self, path: &str, method: HttpMethod) {
+        self.methods
+            .entry(path.to_string())
+            .or_default()
+            .push(method);
+        self.shapes
+            .entry(shape(path))
+            .or_insert_with(|| path.to_string());
+        self.services.insert(service_name(path));
+    }
+
+    /// How a route differs from the specs, `None` when a spec defines it
+    /// or its service has no specs loaded
+    pub fn check(&self, path: &str, method: HttpMethod) -> Option<Drift> {
+        // Mock-only routes (the signed-URL upload target) have no spec
+        if path.starts_with("/__mock/") || !self.services.contains(&service_name(path)) {
+            return None;
+        }
+        match self.methods.get(path) {
+            Some(methods) if methods.contains(&method) => None,
+            Some(_) => Some(Drift::MethodNotInSpecs),
+            None => match self.shapes.get(&shape(path)) {
+                Some(spec_path) => Some(Drift::ParamNames {
+                    spec_path: spec_path.clone(),
+                }),
+                None => Some(Drift::PathNotInSpecs),
+            },
+        }
+    }
+}
+
+/// A path template with its parameter names erased
+pub(crate) fn shape(path: &str) -> String {
+    path.split('/')
+        .map(|segment| match segment.chars().next() {
+            Some(c @ (':' | '*')) => c.to_string(),
+            _ => segment.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::catalog::RouteInfo;
+
+    #[test]
+    fn test_pack_routes_are_checked_against_spec_routes() {
+        let mut catalog = RouteCatalog::new();
+        for (path, method) in [
+            ("/oss/v2/buckets", HttpMethod::Get),
+            ("/oss/v2/buckets/:bucket/details", HttpMethod::Get),
+        ] {
+            let mut info = RouteInfo::builtin(path, method);
+            info.source = RouteSource::OpenApi;
+            catalog.insert(info);
+        }
+        let specs = SpecRoutes::from_catalog(&catalog);
+
+        assert_eq!(specs.check("/oss/v2/buckets", HttpMethod::Get), None);
+        assert_eq!(
+            specs.check("/oss/v2/buckets", HttpMethod::Post),
+            Some(Drift::MethodNotInSpecs)
+        );
+        assert_eq!(
+            specs.check("/oss/v2/buckets/:bucket/objects", HttpMethod::Get),
+            Some(Drift::PathNotInSpecs)
+        );
+        assert_eq!(
+            specs.check("/oss/v2/buckets/:bucket_key/details", HttpMethod::Get),
+            Some(Drift::ParamNames {
+                spec_path: "/oss/v2/buckets/:bucket/details".into()
+            })
+        );
+        // No Model Derivative spec loaded
+        assert_eq!(
+            specs.check("/modelderivative/v2/designdata/formats", HttpMethod::Get),
+            None
+        );
+    }
+}
@@ -0,0 +1,148 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+//! ACC Account Admin projects.
+
+use axum::{
+    extract::{Json, Path},
+    response::{IntoResponse, Json as JsonResponse},
+    routing::{get, patch},
+};
+use serde_json::{Value, json};
+
+use crate::config::Service;
+use crate::openapi::types::HttpMethod;
+use crate::server::packs::{PackContext, PackRoute, RoutePack, StateRequirement};
+use crate::state::StateManager;
+
+/// Built-in ACC Account Admin projects
+#[derive(Debug, Default)]
+pub struct AccountAdminPack;
+
+impl RoutePack for AccountAdminPack {
+    fn name(&self) -> &str {
+        "account-admin"
+    }
+
+    fn state_requirement(&self) -> StateRequirement {
+        StateRequirement::Service(Service::AccountAdmin)
+    }
+
+    fn routes(&self, context: &PackContext<'_>) -> Vec<PackRoute> {
+        let mut routes = Vec::new();
+
+        let admin_state = context.state.clone();
+        routes.push(
+            PackRoute::new(
+                "/construction/admin/v1/projects/:project_id",
+                HttpMethod::Get,
+                get(move |Path(project_id): Path<String>| {
+                    let state_inner = admin_state.clone();
+                    async move {
+                        if let Some(ref state_manager) = state_inner {
+                            (
+                                axum::http::StatusCode::OK,
+                                JsonResponse(acc_project_json(state_manager, &project_id)),
+                            )
+                                .into_response()
+                        } else {
+                            (
+                                axum::http::StatusCode::OK,
+                                JsonResponse(json!({
+                                    "id": project_id,
+                                    "name": "Mock Project",
+                                    "products": []
+                                })),
+                            )
+                                .into_response()
+                        }
+                    }
+                }),
+            )
+            .overrides_spec(),
+        );
+
+        let admin_state = context.state.clone();
+        routes.push(PackRoute::new(
+            "/construction/admin/v1/projects/:project_id",
+            HttpMethod::Patch,
+            patch(
+                move |Path(project_id): Path<String>, Json(body_value): Json<Value>| {
+                    let state_inner = admin_state.clone();
+                    async move {
+                        if let Some(ref state_manager) = state_inner {
+                            let products = body_value
+                                .get("products")
+                                .and_then(|v| v.as_array())
+                                .cloned()
+                                .unwrap_or_default();
+
+                            for product in products {
+                                let key = product.get("key").and_then(|v| v.as_str()).unwrap_or("");
+                                let Ok(key) = key.parse::<crate::state::entitlements::Product>() else {
+                                    return (
+                                        axum::http::StatusCode::BAD_REQUEST,
+                                        JsonResponse(json!({
+                                            "developerMessage": format!("Unknown product key: {}", key),
+                                            "errorCode": "ERR_BAD_INPUT"
+                                        })),
+                                    )
+                                        .into_response();
+                                };
+                                let status = match product.get("status").and_then(|v| v.as_str()) {
+                                    Some("inactive") => {
+                                        crate::state::entitlements::ProductStatus::Inactive
+                                    }
+                                    _ => crate::state::entitlements::ProductStatus::Active,
+                                };
+                                state_manager
+                                    .entitlements
+                                    .set_product_status(&project_id, key, status);
+                            }
+
+                            (
+                                axum::http::StatusCode::OK,
+                                JsonResponse(acc_project_json(state_manager, &project_id)),
+                            )
+                                .into_response()
+                        } else {
+                            (
+                                axum::http::StatusCode::OK,
+                                JsonResponse(json!({ "id": project_id, "products": [] })),
+                            )
+                                .into_response()
+                        }
+                    }
+                },
+            ),
+        ));
+
+        routes
+    }
+}
+
+fn acc_project_json(state_manager: &StateManager, project_id: &str) -> Value {
+    let name = state_manager
+        .projects
+        .get_project(project_id)
+        .or_else(|| {
+            state_manager
+                .projects
+                .get_project(&format!("b.{}", project_id))
+        })
+        .map(|p| p.name)
+        .unwrap_or_else(|| project_id.to_string());
+
+    let products: Vec<Value> = state_manager
+        .entitlements
+        .get_products(project_id)
+        .into_iter()
+        .map(|(product, status)| json!({ "key": product.as_str(), "status": status }))
+        .collect();
+
+    json!({
+        "id": project_id.strip_prefix("b.").unwrap_or(project_id),
+        "name": name,
+        "products": products
+    })
+}
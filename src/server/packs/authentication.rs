@@ -0,0 +1,616 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+//! OAuth token and introspection endpoints, and the profile of the user a
+//! 3-legged token acts for (`/userinfo`, and the legacy
+//! `/userprofile/v1/users/@me`).
+//!
+//! Token requests are read like the real endpoint reads them: an
+//! `application/x-www-form-urlencoded` body with the client credentials in a
+//! Basic `Authorization` header, as the APS SDKs send them. JSON bodies and
+//! `client_id`/`client_secret` body parameters are accepted too. Once any
+//! client is registered (see [`crate::state::clients`]), only registered
+//! clients get tokens, and only for the scopes they are permitted.
+
+use axum::{
+    body::Bytes,
+    http::{HeaderMap, header},
+    response::{IntoResponse, Json as JsonResponse, Response},
+    routing::{get, post},
+};
+use base64::Engine as _;
+use serde_json::{Map, Value, json};
+
+use crate::config::Service;
+use crate::openapi::types::HttpMethod;
+use crate::server::packs::{PackContext, PackRoute, RoutePack, StateRequirement, request_user};
+use crate::state::users::{DEFAULT_USER_ID, UserInfo, default_user};
+
+/// Built-in OAuth token endpoint
+#[derive(Debug, Default)]
+pub struct AuthenticationPack;
+
+impl RoutePack for AuthenticationPack {
+    fn name(&self) -> &str {
+        "authentication"
+    }
+
+    fn state_requirement(&self) -> StateRequirement {
+        StateRequirement::Service(Service::Authentication)
+    }
+
+    fn routes(&self, context: &PackContext<'_>) -> Vec<PackRoute> {
+        let mut routes = Vec::new();
+
+        let auth_state = context.state.clone();
+        let stateless_expires_in = context.config.token_expires_in;
+        routes.push(
+            PackRoute::new(
+                "/authentication/v2/token",
+                HttpMethod::Post,
+                post(move |headers: HeaderMap, body: Bytes| {
+                    let state_inner = auth_state.clone();
+                    async move {
+                        let body_value = match token_request(&headers, &body) {
+                            Ok(parameters) => Value::Object(parameters),
+                            Err(message) => return invalid_request(&message),
+                        };
+                        if let Some(ref state_manager) = state_inner {
+                            let basic = basic_credentials(&headers);
+                            let client_id = body_value
+                                .get("client_id")
+                                .and_then(|v| v.as_str())
+                                .or(basic.as_ref().map(|(id, _)| id.as_str()))
+                                .unwrap_or("default-client");
+                            let client_secret = body_value
+                                .get("client_secret")
+                                .and_then(|v| v.as_str())
+                                .or(basic.as_ref().map(|(_, secret)| secret.as_str()))
+                                .unwrap_or_default();
+
+                            let scope = body_value
+                                .get("scope")
+                                .and_then(|v| v.as_str())
+                                .map(|s| s.to_string());
+
+                            if state_manager.clients.is_enforced() {
+                                let Some(client) =
+                                    state_manager.clients.authenticate(client_id, client_secret)
+                                else {
+                                    return invalid_client();
+                                };
+                                let forbidden =
+                                    client.forbidden_scopes(scope.as_deref().unwrap_or_default());
+                                if !forbidden.is_empty() {
+                                    return (
+                                        axum::http::StatusCode::BAD_REQUEST,
+                                        JsonResponse(json!({
+                                            "error": "invalid_scope",
+                                            "error_description": format!(
+                                                "The client is not permitted the scope: {}",
+                                                forbidden.join(" ")
+                                            )
+                                        })),
+                                    )
+                                        .into_response();
+                                }
+                            }
+
+                            let grant_type = body_value
+                                .get("grant_type")
+                                .and_then(|v| v.as_str())
+                                .unwrap_or("client_credentials");
+
+                            let token = match grant_type {
+                                "authorization_code" => {
+                                    // The code names the user who signed in
+                                    let user_id = body_value
+                                        .get("code")
+                                        .and_then(|v| v.as_str())
+                                        .filter(|code| state_manager.users.get_user(code).is_some())
+                                        .unwrap_or(DEFAULT_USER_ID);
+                                    state_manager.auth.generate_three_legged_token(
+                                        client_id,
+                                        state_manager.auth.expires_in(),
+                                        scope,
+                                        user_id,
+                                    )
+                                }
+                                "refresh_token" => {
+                                    let refresh_token = body_value
+                                        .get("refresh_token")
+                                        .and_then(|v| v.as_str())
+                                        .unwrap_or_default();
+                                    match state_manager.auth.refresh_token(
+                                        refresh_token,
+                                        scope.as_deref(),
+                                        state_manager.auth.expires_in(),
+                                    ) {
+                                        Ok(token) => token,
+                                        Err(e) => {
+                                            return (
+                                                axum::http::StatusCode::BAD_REQUEST,
+                                                JsonResponse(json!({
+                                                    "error": e.code(),
+                                                    "error_description": e.to_string()
+                                                })),
+                                            )
+                                                .into_response();
+                                        }
+                                    }
+                                }
+                                _ => state_manager.auth.generate_token(
+                                    client_id,
+                                    state_manager.auth.expires_in(),
+                                    scope,
+                                ),
+                            };
+
+                            let mut response = json!({
+                                "access_token": token.access_token,
+                                "token_type": token.token_type,
+                                "expires_in": token.expires_in
+                            });
+                            // Only 3-legged tokens can be refreshed
+                            if token.three_legged {
+                                response["refresh_token"] = json!(token.refresh_token);
+                                response["scope"] = json!(token.scope);
+                            }
+                            (axum::http::StatusCode::OK, JsonResponse(response)).into_response()
+                        } else {
+                            (
+                                axum::http::StatusCode::OK,
+                                JsonResponse(json!({
+                                    "access_token": "mock-token",
+                                    "token_type": "Bearer",
+                                    "expires_in": stateless_expires_in
+                                })),
+                            )
+                                .into_response()
+                        }
+                    }
+                }),
+            )
+            .overrides_spec()
+            .public(),
+        );
+
+        // Describes a token to services that do not validate it themselves;
+        // unknown, revoked and expired tokens are just inactive
+        let auth_state = context.state.clone();
+        routes.push(
+            PackRoute::new(
+                "/authentication/v2/introspect",
+                HttpMethod::Post,
+                post(move |headers: HeaderMap, body: Bytes| {
+                    let state_inner = auth_state.clone();
+                    async move {
+                        let parameters = match token_request(&headers, &body) {
+                            Ok(parameters) => parameters,
+                            Err(message) => return invalid_request(&message),
+                        };
+                        let Some(token) = parameters.get("token").and_then(Value::as_str) else {
+                            return invalid_request("token is required");
+                        };
+                        let info = match state_inner {
+                            Some(ref state_manager) => state_manager.auth.lookup_token(token),
+                            None => None,
+                        };
+                        let Some(info) = info else {
+                            return (
+                                axum::http::StatusCode::OK,
+                                JsonResponse(json!({ "active": false })),
+                            )
+                                .into_response();
+                        };
+                        let mut response = json!({
+                            "active": true,
+                            "scope": info.scope.unwrap_or_default(),
+                            "client_id": info.client_id,
+                            "exp": info.expires_at,
+                            "token_type": info.token_type
+                        });
+                        if let Some(user_id) = info.user_id {
+                            response["userid"] = json!(user_id);
+                        }
+                        (axum::http::StatusCode::OK, JsonResponse(response)).into_response()
+                    }
+                }),
+            )
+            .overrides_spec()
+            .public(),
+        );
+
+        for (path, profile) in [
+            ("/userinfo", openid_claims as fn(&UserInfo) -> Value),
+            ("/userprofile/v1/users/@me", legacy_profile),
+        ] {
+            let auth_state = context.state.clone();
+            routes.push(PackRoute::new(
+                path,
+                HttpMethod::Get,
+                get(move |headers: HeaderMap| {
+                    let state_inner = auth_state.clone();
+                    async move {
+                        let user = match state_inner {
+                            Some(ref state_manager) => request_user(state_manager, &headers),
+                            None => Some(default_user()),
+                        };
+                        match user {
+                            Some(user) => {
+                                (axum::http::StatusCode::OK, JsonResponse(profile(&user)))
+                                    .into_response()
+                            }
+                            None => (
+                                axum::http::StatusCode::FORBIDDEN,
+                                JsonResponse(json!({
+                                    "error": "insufficient_scope",
+                                    "error_description":
+                                        "The profile is only available to 3-legged tokens"
+                                })),
+                            )
+                                .into_response(),
+                        }
+                    }
+                }),
+            ));
+        }
+
+        routes
+    }
+}
+
+/// OpenID Connect claims of `user`, as `/userinfo` returns them
+fn openid_claims(user: &UserInfo) -> Value {
+    json!({
+        "sub": user.id,
+        "name": user.name,
+        "given_name": user.given_name(),
+        "family_name": user.family_name(),
+        "preferred_username": user.username(),
+        "email": user.email,
+        "email_verified": true,
+        "profile": "https://profile.autodesk.com",
+        "picture": picture(user, 120),
+        "locale": user.locale(),
+        "updated_at": 0
+    })
+}
+
+/// `user` as the legacy `users/@me` endpoint describes it
+fn legacy_profile(user: &UserInfo) -> Value {
+    let locale = user.locale();
+    json!({
+        "userId": user.id,
+        "userName": user.username(),
+        "emailId": user.email,
+        "firstName": user.given_name(),
+        "lastName": user.family_name(),
+        "emailVerified": true,
+        "2FaEnabled": false,
+        "countryCode": locale.rsplit('-').next().unwrap_or_default(),
+        "language": locale.split('-').next().unwrap_or_default(),
+        "optin": false,
+        "lastModified": "1970-01-01T00:00:00.000Z",
+        "profileImages": {
+            "sizeX20": picture(user, 20),
+            "sizeX40": picture(user, 40),
+            "sizeX50": picture(user, 50),
+            "sizeX58": picture(user, 58),
+            "sizeX80": picture(user, 80),
+            "sizeX120": picture(user, 120),
+            "sizeX140": picture(user, 140),
+            "sizeX160": picture(user, 160),
+            "sizeX176": picture(user, 176),
+            "sizeX240": picture(user, 240),
+            "sizeX360": picture(user, 360)
+        }
+    })
+}
+
+fn picture(user: &UserInfo, size: u32) -> String {
+    format!(
+        "https://images.profile.autodesk.com/{}/profilepictures/x{}.jpg",
+        user.id, size
+    )
+}
+
+/// Parameters of a token request: the form-encoded body, or the JSON one
+/// when the request says so (or gives no content type but a JSON object)
+fn token_request(headers: &HeaderMap, body: &[u8]) -> Result<Map<String, Value>, String> {
+    let json = match headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+    {
+        Some(content_type) => content_type.starts_with("application/json"),
+        None => body.trim_ascii_start().starts_with(b"{"),
+    };
+    if json {
+        return match serde_json::from_slice(body) {
+            Ok(Value::Object(parameters)) => Ok(parameters),
+            Ok(_) => Err("The request body must be an object".to_string()),
+            Err(e) => Err(format!("The request body is not valid JSON: {}", e)),
+        };
+    }
+    Ok(form_urlencoded::parse(body)
+        .map(|(key, value)| (key.into_owned(), Value::from(value.into_owned())))
+        .collect())
+}
+
+/// Client ID and secret of the credentials in a Basic `Authorization` header
+fn basic_credentials(headers: &HeaderMap) -> Option<(String, String)> {
+    let encoded = headers
+        .get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Basic ")?;
+    let decoded = base64::engine::general_purpose::STANDARD
+        .decode(encoded.trim())
+        .ok()?;
+    let credentials = String::from_utf8(decoded).ok()?;
+    let (client_id, secret) = credentials.split_once(':')?;
+    if client_id.is_empty() {
+        return None;
+    }
+    Some((client_id.to_string(), secret.to_string()))
+}
+
+/// The 401 the real endpoint answers unknown clients and wrong secrets with
+fn invalid_client() -> Response {
+    (
+        axum::http::StatusCode::UNAUTHORIZED,
+        JsonResponse(json!({
+            "developerMessage": "The client_id specified does not have access to the api product",
+            "moreInfo": "https://aps.autodesk.com/en/docs/oauth/v2/developers_guide/error_handling/#error-codes",
+            "errorCode": "AUTH-001"
+        })),
+    )
+        .into_response()
+}
+
+fn invalid_request(message: &str) -> Response {
+    (
+        axum::http::StatusCode::BAD_REQUEST,
+        JsonResponse(json!({
+            "error": "invalid_request",
+            "error_description": message
+        })),
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::MockServerConfig;
+    use crate::state::StateManager;
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_tokens_are_issued_for_form_and_basic_auth_requests() {
+        let state = StateManager::new();
+        let (router, _) = crate::server::router::build_router(
+            Vec::new(),
+            Vec::new(),
+            Some(state.clone()),
+            &MockServerConfig::default(),
+            &[],
+        )
+        .unwrap();
+        let send = |content_type: &str, authorization: Option<String>, body: &str| {
+            let mut request = Request::post("/authentication/v2/token")
+                .header(header::CONTENT_TYPE, content_type);
+            if let Some(authorization) = authorization {
+                request = request.header(header::AUTHORIZATION, authorization);
+            }
+            let request = request.body(Body::from(body.to_string())).unwrap();
+            let router = router.clone();
+            async move {
+                let response = router.oneshot(request).await.unwrap();
+                let status = response.status();
+                let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                (status, serde_json::from_slice::<Value>(&bytes).unwrap())
+            }
+        };
+        let client = |token: &Value| {
+            let access_token = token["access_token"].as_str().unwrap();
+            state.auth.lookup_token(access_token).unwrap()
+        };
+
+        let basic = format!(
+            "Basic {}",
+            base64::engine::general_purpose::STANDARD.encode("sdk-app:s3cret")
+        );
+        let (status, token) = send(
+            "application/x-www-form-urlencoded",
+            Some(basic),
+            "grant_type=client_credentials&scope=data%3Aread%20bucket%3Acreate",
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let info = client(&token);
+        assert_eq!(info.client_id, "sdk-app");
+        assert_eq!(info.scope.as_deref(), Some("data:read bucket:create"));
+
+        let (_, token) = send(
+            "application/x-www-form-urlencoded",
+            None,
+            "client_id=form-app&client_secret=x&grant_type=client_credentials",
+        )
+        .await;
+        assert_eq!(client(&token).client_id, "form-app");
+
+        let (_, token) = send("application/json", None, r#"{"client_id": "json-app"}"#).await;
+        assert_eq!(client(&token).client_id, "json-app");
+
+        let (status, error) = send("application/json", None, "client_id=json-app").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(error["error"], "invalid_request");
+    }
+
+    #[tokio::test]
+    async fn test_userinfo_describes_the_token_user() {
+        let state = StateManager::new();
+        state.users.insert_user(UserInfo {
+            id: "ada".into(),
+            name: "Ada King Lovelace".into(),
+            email: "ada@example.com".into(),
+            first_name: None,
+            last_name: None,
+            username: Some("ada.l".into()),
+            locale: Some("en-GB".into()),
+        });
+        let (router, _) = crate::server::router::build_router(
+            Vec::new(),
+            Vec::new(),
+            Some(state.clone()),
+            &MockServerConfig::default(),
+            &[],
+        )
+        .unwrap();
+        let get = |path: &str, token: &str| {
+            let request = Request::get(path)
+                .header(header::AUTHORIZATION, format!("Bearer {}", token))
+                .body(Body::empty())
+                .unwrap();
+            let router = router.clone();
+            async move {
+                let response = router.oneshot(request).await.unwrap();
+                let status = response.status();
+                let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                (status, serde_json::from_slice::<Value>(&bytes).unwrap())
+            }
+        };
+
+        let token = state.auth.generate_three_legged_token(
+            "app",
+            3600,
+            Some("user-profile:read".into()),
+            "ada",
+        );
+        let (status, claims) = get("/userinfo", &token.access_token).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(claims["sub"], "ada");
+        assert_eq!(claims["given_name"], "Ada");
+        assert_eq!(claims["family_name"], "King Lovelace");
+        assert_eq!(claims["preferred_username"], "ada.l");
+        assert_eq!(claims["locale"], "en-GB");
+
+        let (_, profile) = get("/userprofile/v1/users/@me", &token.access_token).await;
+        assert_eq!(profile["userId"], "ada");
+        assert_eq!(profile["emailId"], "ada@example.com");
+        assert_eq!(profile["countryCode"], "GB");
+
+        let two_legged = state.auth.generate_token("app", 3600, None);
+        let (status, _) = get("/userinfo", &two_legged.access_token).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_introspection_reports_live_tokens_only() {
+        let state = StateManager::new();
+        let (router, _) = crate::server::router::build_router(
+            Vec::new(),
+            Vec::new(),
+            Some(state.clone()),
+            &MockServerConfig::default(),
+            &[],
+        )
+        .unwrap();
+        let introspect = |token: &str| {
+            let request = Request::post("/authentication/v2/introspect")
+                .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+                .body(Body::from(format!("token={}", token)))
+                .unwrap();
+            let router = router.clone();
+            async move {
+                let response = router.oneshot(request).await.unwrap();
+                let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                serde_json::from_slice::<Value>(&bytes).unwrap()
+            }
+        };
+
+        state.clock.freeze(None);
+        let token = state
+            .auth
+            .generate_token("app", 60, Some("data:read".into()));
+        let active = introspect(&token.access_token).await;
+        assert_eq!(active["active"], true);
+        assert_eq!(active["client_id"], "app");
+        assert_eq!(active["scope"], "data:read");
+        assert_eq!(active["exp"], token.expires_at);
+
+        state.clock.advance(61_000);
+        assert_eq!(
+            introspect(&token.access_token).await,
+            json!({ "active": false })
+        );
+        assert_eq!(introspect("unknown").await, json!({ "active": false }));
+    }
+
+    #[tokio::test]
+    async fn test_registered_clients_are_enforced() {
+        let state = StateManager::new();
+        let (router, _) = crate::server::router::build_router(
+            Vec::new(),
+            Vec::new(),
+            Some(state.clone()),
+            &MockServerConfig::default(),
+            &[],
+        )
+        .unwrap();
+        let request_token = |body: &str| {
+            let request = Request::post("/authentication/v2/token")
+                .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+                .body(Body::from(body.to_string()))
+                .unwrap();
+            let router = router.clone();
+            async move {
+                let response = router.oneshot(request).await.unwrap();
+                let status = response.status();
+                let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                (status, serde_json::from_slice::<Value>(&bytes).unwrap())
+            }
+        };
+
+        state
+            .clients
+            .insert_client(crate::state::clients::ClientCredentials {
+                client_id: "ci".into(),
+                client_secret: "s3cret".into(),
+                scopes: vec!["data:read".into(), "data:write".into()],
+            });
+
+        let (status, _) =
+            request_token("client_id=ci&client_secret=s3cret&scope=data%3Aread").await;
+        assert_eq!(status, StatusCode::OK);
+
+        let (status, error) = request_token("client_id=ci&client_secret=wrong").await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(error["errorCode"], "AUTH-001");
+        let (status, _) = request_token("client_id=other&client_secret=s3cret").await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let (status, error) =
+            request_token("client_id=ci&client_secret=s3cret&scope=data%3Aread%20bucket%3Adelete")
+                .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(error["error"], "invalid_scope");
+        assert!(
+            error["error_description"]
+                .as_str()
+                .unwrap()
+                .ends_with("bucket:delete")
+        );
+    }
+}
@@ -0,0 +1,511 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+//! Data Connector extract requests and the jobs they run.
+//!
+//! Every request runs one extract job right away, simulated by the shared
+//! job engine: clients poll the job until its `status` is `complete`, or
+//! follow the progress notifications the engine records for it. A complete
+//! job's data is a CSV per table and an archive of them all, fetched through
+//! signed URLs.
+
+use axum::{
+    extract::{Json, Path},
+    http::HeaderMap,
+    response::{IntoResponse, Json as JsonResponse, Response},
+    routing::{get, post},
+};
+use serde_json::{Value, json};
+
+use crate::artifacts::{self, Table};
+use crate::config::Service;
+use crate::openapi::schemas::SchemaRegistry;
+use crate::openapi::types::HttpMethod;
+use crate::server::packs::{PackContext, PackRoute, RoutePack, StateRequirement};
+use crate::state::StateManager;
+use crate::state::jobs::{Job, JobKind, JobPhase, JobStatus};
+
+/// Built-in Data Connector requests and jobs
+#[derive(Debug, Default)]
+pub struct DataConnectorPack;
+
+impl RoutePack for DataConnectorPack {
+    fn name(&self) -> &str {
+        "data-connector"
+    }
+
+    fn state_requirement(&self) -> StateRequirement {
+        StateRequirement::Service(Service::DataConnector)
+    }
+
+    fn routes(&self, context: &PackContext<'_>) -> Vec<PackRoute> {
+        let mut routes = Vec::new();
+
+        let dc_state = context.state.clone();
+        routes.push(PackRoute::new(
+            "/data-connector/v1/accounts/:account_id/requests",
+            HttpMethod::Post,
+            post(
+                move |Path(account_id): Path<String>, Json(body): Json<Value>| {
+                    let state_inner = dc_state.clone();
+                    async move {
+                        let request_id = uuid::Uuid::new_v4().to_string();
+                        let created_at = match state_inner {
+                            Some(ref state_manager) => {
+                                state_manager
+                                    .jobs
+                                    .start(JobKind::DataExtract, job_key(&account_id, &request_id))
+                                    .created_at
+                            }
+                            None => chrono::Utc::now().timestamp_millis(),
+                        };
+                        let field =
+                            |name: &str, default: Value| body.get(name).cloned().unwrap_or(default);
+                        (
+                            axum::http::StatusCode::CREATED,
+                            JsonResponse(json!({
+                                "id": request_id,
+                                "accountId": account_id,
+                                "description": field("description", Value::Null),
+                                "isActive": true,
+                                "scheduleInterval": field("scheduleInterval", json!("ONE_TIME")),
+                                "serviceGroups": field("serviceGroups", json!(["all"])),
+                                "callbackUrl": field("callbackUrl", Value::Null),
+                                "createdBy": crate::state::users::DEFAULT_USER_ID,
+                                "createdAt": millis_to_rfc3339(created_at)
+                            })),
+                        )
+                            .into_response()
+                    }
+                },
+            ),
+        ));
+
+        let dc_state = context.state.clone();
+        routes.push(PackRoute::new(
+            "/data-connector/v1/accounts/:account_id/requests/:request_id/jobs",
+            HttpMethod::Get,
+            get(
+                move |Path((account_id, request_id)): Path<(String, String)>| {
+                    let state_inner = dc_state.clone();
+                    async move {
+                        let key = job_key(&account_id, &request_id);
+                        let results: Vec<Value> = state_inner
+                            .iter()
+                            .flat_map(|state_manager| {
+                                state_manager
+                                    .jobs
+                                    .list(Some(JobKind::DataExtract))
+                                    .into_iter()
+                                    .filter(|job| job.key == key)
+                                    .filter_map(|job| {
+                                        let status = state_manager.jobs.status(&job.id)?;
+                                        Some(job_json(&job, &status))
+                                    })
+                            })
+                            .collect();
+                        (
+                            axum::http::StatusCode::OK,
+                            JsonResponse(json!({
+                                "pagination": {
+                                    "limit": results.len(),
+                                    "offset": 0,
+                                    "totalResults": results.len()
+                                },
+                                "results": results
+                            })),
+                        )
+                            .into_response()
+                    }
+                },
+            ),
+        ));
+
+        let dc_state = context.state.clone();
+        routes.push(PackRoute::new(
+            "/data-connector/v1/accounts/:account_id/jobs/:job_id",
+            HttpMethod::Get,
+            get(move |Path((account_id, job_id)): Path<(String, String)>| {
+                let state_inner = dc_state.clone();
+                async move {
+                    let found = state_inner.as_ref().and_then(|state_manager| {
+                        let job = state_manager.jobs.get(&job_id)?;
+                        let status = state_manager.jobs.status(&job_id)?;
+                        let in_account = job
+                            .key
+                            .split_once('/')
+                            .is_some_and(|(account, _)| account == account_id);
+                        (job.kind == JobKind::DataExtract && in_account).then_some((job, status))
+                    });
+                    match found {
+                        Some((job, status)) => (
+                            axum::http::StatusCode::OK,
+                            JsonResponse(job_json(&job, &status)),
+                        )
+                            .into_response(),
+                        None => not_found(&format!("Job {} not found", job_id)),
+                    }
+                }
+            }),
+        ));
+
+        let dc_state = context.state.clone();
+        let dc_schemas = context.schemas.clone();
+        routes.push(PackRoute::new(
+            "/data-connector/v1/accounts/:account_id/jobs/:job_id/data-listing",
+            HttpMethod::Get,
+            get(move |Path((account_id, job_id)): Path<(String, String)>| {
+                let state_inner = dc_state.clone();
+                let schemas = dc_schemas.clone();
+                async move {
+                    let Some(state_manager) =
+                        completed_extract(state_inner.as_ref(), &account_id, &job_id)
+                    else {
+                        return not_found(&format!("Job {} has no data", job_id));
+                    };
+                    match extract_files(state_manager, &schemas) {
+                        Ok(files) => {
+                            let listing: Vec<Value> = files
+                                    .iter()
+                                    .map(|(name, contents)| {
+                                        json!({ "name": name, "size": contents.len() })
+                                    })
+                                    .collect();
+                            (axum::http::StatusCode::OK, JsonResponse(json!(listing)))
+                                .into_response()
+                        }
+                        Err(e) => extract_failed(&e),
+                    }
+                }
+            }),
+        ));
+
+        // A signed URL to the file, served below
+        let dc_state = context.state.clone();
+        let dc_schemas = context.schemas.clone();
+        routes.push(PackRoute::new(
+            "/data-connector/v1/accounts/:account_id/jobs/:job_id/data/:name",
+            HttpMethod::Get,
+            get(
+                move |Path((account_id, job_id, name)): Path<(String, String, String)>,
+                      headers: HeaderMap| {
+                    let state_inner = dc_state.clone();
+                    let schemas = dc_schemas.clone();
+                    async move {
+                        let Some(state_manager) =
+                            completed_extract(state_inner.as_ref(), &account_id, &job_id)
+                        else {
+                            return not_found(&format!("Job {} has no data", job_id));
+                        };
+                        let exists = extract_files(state_manager, &schemas)
+                            .is_ok_and(|files| files.iter().any(|(file, _)| *file == name));
+                        if !exists {
+                            return not_found(&format!("Job {} has no file {}", job_id, name));
+                        }
+                        let host = headers
+                            .get(axum::http::header::HOST)
+                            .and_then(|h| h.to_str().ok())
+                            .unwrap_or("localhost");
+                        (
+                            axum::http::StatusCode::OK,
+                            JsonResponse(json!({
+                                "name": name,
+                                "signedUrl": format!(
+                                    "http://{}/__mock/data-connector/{}/{}/{}",
+                                    host, account_id, job_id, name
+                                )
+                            })),
+                        )
+                            .into_response()
+                    }
+                },
+            ),
+        ));
+
+        // Target of the signed URLs handed out above
+        let dc_state = context.state.clone();
+        let dc_schemas = context.schemas.clone();
+        routes.push(
+            PackRoute::new(
+                "/__mock/data-connector/:account_id/:job_id/:name",
+                HttpMethod::Get,
+                get(
+                    move |Path((account_id, job_id, name)): Path<(String, String, String)>| {
+                        let state_inner = dc_state.clone();
+                        let schemas = dc_schemas.clone();
+                        async move {
+                            let Some(state_manager) =
+                                completed_extract(state_inner.as_ref(), &account_id, &job_id)
+                            else {
+                                return not_found(&format!("Job {} has no data", job_id));
+                            };
+                            let files = match extract_files(state_manager, &schemas) {
+                                Ok(files) => files,
+                                Err(e) => return extract_failed(&e),
+                            };
+                            match files.into_iter().find(|(file, _)| *file == name) {
+                                Some((file, contents)) => {
+                                    let content_type = if file.ends_with(".zip") {
+                                        "application/zip"
+                                    } else {
+                                        "text/csv"
+                                    };
+                                    (
+                                        axum::http::StatusCode::OK,
+                                        [(axum::http::header::CONTENT_TYPE, content_type)],
+                                        contents,
+                                    )
+                                        .into_response()
+                                }
+                                None => not_found(&format!("Job {} has no file {}", job_id, name)),
+                            }
+                        }
+                    },
+                ),
+            )
+            .public(),
+        );
+
+        routes
+    }
+}
+
+/// Tables every extract holds, by file name
+const EXTRACT_TABLES: [(&str, Table<'static>); 2] = [
+    (
+        "issues_issues.csv",
+        Table {
+            schema: ("issues", "Issue"),
+            fallback_columns: &["id", "title", "description", "status", "createdAt"],
+        },
+    ),
+    (
+        "admin_projects.csv",
+        Table {
+            schema: ("account-admin", "Project"),
+            fallback_columns: &["id", "name", "status"],
+        },
+    ),
+];
+
+/// Name of the archive holding every table of an extract
+const EXTRACT_ARCHIVE: &str = "autodesk_data_extract.zip";
+
+/// The state, if `job_id` is an extract of `account_id` that succeeded
+fn completed_extract<'a>(
+    state: Option<&'a StateManager>,
+    account_id: &str,
+    job_id: &str,
+) -> Option<&'a StateManager> {
+    let state_manager = state?;
+    let job = state_manager.jobs.get(job_id)?;
+    let status = state_manager.jobs.status(job_id)?;
+    let in_account = job
+        .key
+        .split_once('/')
+        .is_some_and(|(account, _)| account == account_id);
+    (job.kind == JobKind::DataExtract && in_account && status.phase == JobPhase::Success)
+        .then_some(state_manager)
+}
+
+/// The files of an extract: a CSV per table of the records stored now,
+/// then an archive of them all
+fn extract_files(
+    state_manager: &StateManager,
+    schemas: &SchemaRegistry,
+) -> Result<Vec<(String, Vec<u8>)>, String> {
+    let mut files = Vec::new();
+    for (name, table) in &EXTRACT_TABLES {
+        let records = match table.schema.0 {
+            "issues" => state_manager
+                .issues
+                .iter()
+                .map(|issue| {
+                    json!({
+                        "id": issue.id,
+                        "title": issue.title,
+                        "description": issue.description,
+                        "status": issue.status,
+                        "createdAt": millis_to_rfc3339(issue.created_at)
+                    })
+                })
+                .collect(),
+            _ => state_manager
+                .projects
+                .all_projects()
+                .into_iter()
+                .map(
+                    |project| json!({ "id": project.id, "name": project.name, "status": "active" }),
+                )
+                .collect::<Vec<_>>(),
+        };
+        let csv = table.csv(schemas, &records).map_err(|e| e.to_string())?;
+        files.push((name.to_string(), csv));
+    }
+    let archive = artifacts::zip(&files).map_err(|e| e.to_string())?;
+    files.push((EXTRACT_ARCHIVE.to_string(), archive));
+    Ok(files)
+}
+
+fn extract_failed(error: &str) -> Response {
+    (
+        axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+        JsonResponse(json!({ "code": "INTERNAL_ERROR", "detail": error })),
+    )
+        .into_response()
+}
+
+/// Key of an extract job: the account, then the request that runs it
+fn job_key(account_id: &str, request_id: &str) -> String {
+    format!("{}/{}", account_id, request_id)
+}
+
+fn job_json(job: &Job, status: &JobStatus) -> Value {
+    let (account_id, request_id) = job.key.split_once('/').unwrap_or(("", &job.key));
+    let (state, completion) = match status.phase {
+        JobPhase::Pending => ("queued", None),
+        JobPhase::InProgress => ("running", None),
+        JobPhase::Success => ("complete", Some("success")),
+        JobPhase::Failed | JobPhase::Timeout => ("complete", Some("failed")),
+    };
+    json!({
+        "id": job.id,
+        "requestId": request_id,
+        "accountId": account_id,
+        "createdBy": crate::state::users::DEFAULT_USER_ID,
+        "createdAt": millis_to_rfc3339(job.created_at),
+        "status": state,
+        "completionStatus": completion,
+        "progress": status.progress,
+        "message": status.message
+    })
+}
+
+fn millis_to_rfc3339(millis: i64) -> String {
+    chrono::DateTime::from_timestamp_millis(millis)
+        .unwrap_or_default()
+        .to_rfc3339()
+}
+
+fn not_found(detail: &str) -> Response {
+    (
+        axum::http::StatusCode::NOT_FOUND,
+        JsonResponse(json!({ "code": "NOT_FOUND", "detail": detail })),
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::MockServerConfig;
+    use crate::state::StateManager;
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use serde_json::Value;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_extract_jobs_are_polled_until_complete() {
+        let state = StateManager::new();
+        state.clock.freeze(None);
+        let (router, _) = crate::server::router::build_router(
+            Vec::new(),
+            Vec::new(),
+            Some(state.clone()),
+            &MockServerConfig::default(),
+            &[],
+        )
+        .unwrap();
+        let token = state.auth.generate_token("client", 3600, None);
+        let send = |request: Request<Body>| {
+            let router = router.clone();
+            async move {
+                let response = router.oneshot(request).await.unwrap();
+                let status = response.status();
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                (status, serde_json::from_slice::<Value>(&body).unwrap())
+            }
+        };
+        let bearer = format!("Bearer {}", token.access_token);
+
+        let (status, request) = send(
+            Request::post("/data-connector/v1/accounts/acct/requests")
+                .header("authorization", &bearer)
+                .header("content-type", "application/json")
+                .body(Body::from(r#"{"description": "Nightly"}"#))
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(request["description"], "Nightly");
+
+        let jobs_uri = format!(
+            "/data-connector/v1/accounts/acct/requests/{}/jobs",
+            request["id"].as_str().unwrap()
+        );
+        let list = || {
+            Request::get(&jobs_uri)
+                .header("authorization", &bearer)
+                .body(Body::empty())
+                .unwrap()
+        };
+        let (_, jobs) = send(list()).await;
+        assert_eq!(jobs["results"][0]["status"], "queued");
+        let job_id = jobs["results"][0]["id"].as_str().unwrap().to_string();
+        assert_eq!(state.jobs.observe().len(), 1);
+
+        state.clock.advance(60_000);
+        let (status, job) = send(
+            Request::get(format!("/data-connector/v1/accounts/acct/jobs/{}", job_id))
+                .header("authorization", &bearer)
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            (job["status"].as_str(), job["completionStatus"].as_str()),
+            (Some("complete"), Some("success"))
+        );
+        let finished = state.jobs.observe();
+        assert_eq!(finished[0].event, crate::state::jobs::JobEvent::Finished);
+
+        // The data: a CSV per table and an archive of them
+        let (_, listing) = send(
+            Request::get(format!(
+                "/data-connector/v1/accounts/acct/jobs/{}/data-listing",
+                job_id
+            ))
+            .header("authorization", &bearer)
+            .body(Body::empty())
+            .unwrap(),
+        )
+        .await;
+        let names: Vec<&str> = listing
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter_map(|file| file["name"].as_str())
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "issues_issues.csv",
+                "admin_projects.csv",
+                "autodesk_data_extract.zip"
+            ]
+        );
+
+        // Jobs belong to their account
+        let (status, _) = send(
+            Request::get(format!("/data-connector/v1/accounts/other/jobs/{}", job_id))
+                .header("authorization", &bearer)
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,880 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+//! Data Management hubs, projects, folders and items.
+
+use axum::{
+    extract::{Json, Path},
+    http::HeaderMap,
+    response::{IntoResponse, Json as JsonResponse, Response},
+    routing::{get, post},
+};
+use base64::Engine as _;
+use serde_json::{Value, json};
+
+use crate::artifacts;
+use crate::config::Service;
+use crate::openapi::types::HttpMethod;
+use crate::server::packs::model_derivative::export_formats;
+use crate::server::packs::{PackContext, PackRoute, RoutePack, StateRequirement, request_user};
+use crate::state::StateManager;
+use crate::state::jobs::{JobKind, JobPhase};
+use crate::state::projects::ItemInfo;
+use crate::state::translations::TranslationStatus;
+
+/// Built-in Data Management hubs, projects and folders
+#[derive(Debug, Default)]
+pub struct DataManagementPack;
+
+impl RoutePack for DataManagementPack {
+    fn name(&self) -> &str {
+        "data-management"
+    }
+
+    fn state_requirement(&self) -> StateRequirement {
+        StateRequirement::Service(Service::DataManagement)
+    }
+
+    fn routes(&self, context: &PackContext<'_>) -> Vec<PackRoute> {
+        let mut routes = Vec::new();
+
+        let dm_state = context.state.clone();
+        routes.push(
+            PackRoute::new(
+                "/project/v1/hubs",
+                HttpMethod::Get,
+                get(move || {
+                    let state_inner = dm_state.clone();
+                    async move {
+                        if let Some(ref state_manager) = state_inner {
+                            let hubs = state_manager.projects.list_hubs();
+                            let data: Vec<Value> = hubs
+                                .into_iter()
+                                .map(|h| {
+                                    json!({
+                                        "type": "hubs",
+                                        "id": h.id,
+                                        "attributes": {
+                                            "name": h.name,
+                                            "region": h.region
+                                        }
+                                    })
+                                })
+                                .collect();
+                            (
+                                axum::http::StatusCode::OK,
+                                JsonResponse(json!({
+                                    "jsonapi": { "version": "1.0" },
+                                    "data": data
+                                })),
+                            )
+                                .into_response()
+                        } else {
+                            (
+                                axum::http::StatusCode::OK,
+                                JsonResponse(json!({
+                                    "jsonapi": { "version": "1.0" },
+                                    "data": []
+                                })),
+                            )
+                                .into_response()
+                        }
+                    }
+                }),
+            )
+            .overrides_spec(),
+        );
+
+        let dm_state = context.state.clone();
+        routes.push(
+            PackRoute::new(
+                "/project/v1/hubs/:hub_id",
+                HttpMethod::Get,
+                get(move |Path(hub_id): Path<String>| {
+                    let state_inner = dm_state.clone();
+                    async move {
+                        if let Some(ref state_manager) = state_inner {
+                            if let Some(hub) = state_manager.projects.get_hub(&hub_id) {
+                                (
+                                    axum::http::StatusCode::OK,
+                                    JsonResponse(json!({
+                                        "jsonapi": { "version": "1.0" },
+                                        "data": {
+                                            "type": "hubs",
+                                            "id": hub.id,
+                                            "attributes": {
+                                                "name": hub.name,
+                                                "region": hub.region
+                                            }
+                                        }
+                                    })),
+                                )
+                                    .into_response()
+                            } else {
+                                (
+                                    axum::http::StatusCode::NOT_FOUND,
+                                    JsonResponse(json!({
+                                        "jsonapi": { "version": "1.0" },
+                                        "errors": [{
+                                            "status": "404",
+                                            "title": "Not Found",
+                                            "detail": format!("Hub {} not found", hub_id)
+                                        }]
+                                    })),
+                                )
+                                    .into_response()
+                            }
+                        } else {
+                            (
+                                axum::http::StatusCode::NOT_FOUND,
+                                JsonResponse(json!({
+                                    "jsonapi": { "version": "1.0" },
+                                    "errors": [{
+                                        "status": "404",
+                                        "title": "Not Found"
+                                    }]
+                                })),
+                            )
+                                .into_response()
+                        }
+                    }
+                }),
+            )
+            .overrides_spec(),
+        );
+
+        let dm_state = context.state.clone();
+        routes.push(
+            PackRoute::new(
+                "/project/v1/hubs/:hub_id/projects",
+                HttpMethod::Get,
+                get(move |Path(hub_id): Path<String>| {
+                    let state_inner = dm_state.clone();
+                    async move {
+                        if let Some(ref state_manager) = state_inner {
+                            let projects = state_manager.projects.list_projects(&hub_id);
+                            let data: Vec<Value> = projects
+                                .into_iter()
+                                .filter(|p| {
+                                    state_manager
+                                        .entitlements
+                                        .is_active(&p.id, crate::state::entitlements::Product::Docs)
+                                })
+                                .map(|p| {
+                                    json!({
+                                        "type": "projects",
+                                        "id": p.id,
+                                        "attributes": {
+                                            "name": p.name
+                                        }
+                                    })
+                                })
+                                .collect();
+                            (
+                                axum::http::StatusCode::OK,
+                                JsonResponse(json!({
+                                    "jsonapi": { "version": "1.0" },
+                                    "data": data
+                                })),
+                            )
+                                .into_response()
+                        } else {
+                            (
+                                axum::http::StatusCode::OK,
+                                JsonResponse(json!({
+                                    "jsonapi": { "version": "1.0" },
+                                    "data": []
+                                })),
+                            )
+                                .into_response()
+                        }
+                    }
+                }),
+            )
+            .overrides_spec(),
+        );
+
+        let dm_state = context.state.clone();
+        routes.push(
+            PackRoute::new(
+                "/project/v1/hubs/:hub_id/projects/:project_id/topFolders",
+                HttpMethod::Get,
+                get(move |Path((_hub_id, project_id)): Path<(String, String)>| {
+                    let state_inner = dm_state.clone();
+                    async move {
+                        let data: Vec<Value> = state_inner
+                            .map(|state_manager| {
+                                state_manager
+                                    .projects
+                                    .top_folders(&project_id)
+                                    .iter()
+                                    .map(folder_json)
+                                    .collect()
+                            })
+                            .unwrap_or_default();
+                        (
+                            axum::http::StatusCode::OK,
+                            JsonResponse(json!({
+                                "jsonapi": { "version": "1.0" },
+                                "data": data
+                            })),
+                        )
+                            .into_response()
+                    }
+                }),
+            )
+            .overrides_spec(),
+        );
+
+        let dm_state = context.state.clone();
+        routes.push(
+            PackRoute::new(
+                "/data/v1/projects/:project_id/folders/:folder_id/contents",
+                HttpMethod::Get,
+                get(
+                    move |Path((_project_id, folder_id)): Path<(String, String)>| {
+                        let state_inner = dm_state.clone();
+                        async move {
+                            let Some(ref state_manager) = state_inner else {
+                                return (
+                                    axum::http::StatusCode::OK,
+                                    JsonResponse(json!({
+                                        "jsonapi": { "version": "1.0" },
+                                        "data": []
+                                    })),
+                                )
+                                    .into_response();
+                            };
+                            if state_manager.projects.get_folder(&folder_id).is_none() {
+                                return jsonapi_error(
+                                    axum::http::StatusCode::NOT_FOUND,
+                                    &format!("Folder {} not found", folder_id),
+                                );
+                            }
+
+                            let (folders, items) =
+                                state_manager.projects.folder_contents(&folder_id);
+                            let mut data: Vec<Value> = folders.iter().map(folder_json).collect();
+                            let mut included = Vec::new();
+                            for item in &items {
+                                data.push(item_json(state_manager, item));
+                                included.push(version_json(state_manager, item));
+                            }
+                            (
+                                axum::http::StatusCode::OK,
+                                JsonResponse(json!({
+                                    "jsonapi": { "version": "1.0" },
+                                    "data": data,
+                                    "included": included
+                                })),
+                            )
+                                .into_response()
+                        }
+                    },
+                ),
+            )
+            .overrides_spec(),
+        );
+
+        let dm_state = context.state.clone();
+        routes.push(PackRoute::new(
+            "/data/v1/projects/:project_id/items",
+            HttpMethod::Post,
+            post(
+                move |Path(project_id): Path<String>,
+                      headers: HeaderMap,
+                      Json(body): Json<Value>| {
+                    let state_inner = dm_state.clone();
+                    async move {
+                        let Some(ref state_manager) = state_inner else {
+                            return jsonapi_error(
+                                axum::http::StatusCode::NOT_IMPLEMENTED,
+                                "Items can only be created while Data Management is stateful",
+                            );
+                        };
+                        let name = body
+                            .pointer("/data/attributes/displayName")
+                            .and_then(Value::as_str);
+                        let folder_id = body
+                            .pointer("/data/relationships/parent/data/id")
+                            .and_then(Value::as_str);
+                        let storage_urn = body
+                            .pointer("/included/0/relationships/storage/data/id")
+                            .and_then(Value::as_str);
+                        let (Some(name), Some(folder_id), Some(storage_urn)) =
+                            (name, folder_id, storage_urn)
+                        else {
+                            return jsonapi_error(
+                                axum::http::StatusCode::BAD_REQUEST,
+                                "An item needs data.attributes.displayName, a parent folder \
+                                 and a first version with a storage object",
+                            );
+                        };
+                        if state_manager.projects.get_folder(folder_id).is_none() {
+                            return jsonapi_error(
+                                axum::http::StatusCode::NOT_FOUND,
+                                &format!("Folder {} not found", folder_id),
+                            );
+                        }
+
+                        let created_by = request_user(state_manager, &headers).map(|user| user.id);
+                        let item = state_manager.projects.create_item(
+                            &project_id,
+                            folder_id,
+                            name.to_string(),
+                            storage_urn.to_string(),
+                            created_by,
+                        );
+                        (
+                            axum::http::StatusCode::CREATED,
+                            JsonResponse(json!({
+                                "jsonapi": { "version": "1.0" },
+                                "data": item_json(state_manager, &item),
+                                "included": [version_json(state_manager, &item)]
+                            })),
+                        )
+                            .into_response()
+                    }
+                },
+            ),
+        ));
+
+        let dm_state = context.state.clone();
+        routes.push(PackRoute::new(
+            "/data/v1/projects/:project_id/versions/:version_id/downloadFormats",
+            HttpMethod::Get,
+            get(
+                move |Path((_project_id, version_id)): Path<(String, String)>| {
+                    let state_inner = dm_state.clone();
+                    async move {
+                        let Some(ref state_manager) = state_inner else {
+                            return (
+                                axum::http::StatusCode::OK,
+                                JsonResponse(json!({
+                                    "jsonapi": { "version": "1.0" },
+                                    "data": {
+                                        "type": "downloadFormats",
+                                        "id": version_id,
+                                        "attributes": {
+                                            "formats": [{ "fileType": "dwg" }, { "fileType": "ifc" }]
+                                        }
+                                    }
+                                })),
+                            )
+                                .into_response();
+                        };
+                        let Some(item) = state_manager.projects.get_version(&version_id) else {
+                            return jsonapi_error(
+                                axum::http::StatusCode::NOT_FOUND,
+                                &format!("Version {} not found", version_id),
+                            );
+                        };
+
+                        let formats: Vec<Value> = download_formats(state_manager, &item)
+                            .into_iter()
+                            .map(|file_type| json!({ "fileType": file_type }))
+                            .collect();
+                        (
+                            axum::http::StatusCode::OK,
+                            JsonResponse(json!({
+                                "jsonapi": { "version": "1.0" },
+                                "data": {
+                                    "type": "downloadFormats",
+                                    "id": version_id,
+                                    "attributes": { "formats": formats }
+                                }
+                            })),
+                        )
+                            .into_response()
+                    }
+                },
+            ),
+        ).overrides_spec());
+
+        let dm_state = context.state.clone();
+        routes.push(PackRoute::new(
+            "/data/v1/projects/:project_id/downloads",
+            HttpMethod::Post,
+            post(
+                move |Path(project_id): Path<String>, Json(body): Json<Value>| {
+                    let state_inner = dm_state.clone();
+                    async move {
+                        let Some(ref state_manager) = state_inner else {
+                            return jsonapi_error(
+                                axum::http::StatusCode::NOT_IMPLEMENTED,
+                                "Downloads can only be prepared while Data Management is stateful",
+                            );
+                        };
+                        let file_type = body
+                            .pointer("/data/attributes/format/fileType")
+                            .and_then(Value::as_str);
+                        let version_id = body
+                            .pointer("/data/relationships/source/data/id")
+                            .and_then(Value::as_str);
+                        let (Some(file_type), Some(version_id)) = (file_type, version_id) else {
+                            return jsonapi_error(
+                                axum::http::StatusCode::BAD_REQUEST,
+                                "A download needs data.attributes.format.fileType and a source version",
+                            );
+                        };
+                        let Some(item) = state_manager.projects.get_version(version_id) else {
+                            return jsonapi_error(
+                                axum::http::StatusCode::NOT_FOUND,
+                                &format!("Version {} not found", version_id),
+                            );
+                        };
+                        if !download_formats(state_manager, &item).contains(&file_type) {
+                            return jsonapi_error(
+                                axum::http::StatusCode::BAD_REQUEST,
+                                &format!("Version {} cannot be downloaded as {}", version_id, file_type),
+                            );
+                        }
+
+                        let job = state_manager
+                            .jobs
+                            .start(JobKind::Download, download_key(file_type, version_id));
+                        (
+                            axum::http::StatusCode::ACCEPTED,
+                            JsonResponse(json!({
+                                "jsonapi": { "version": "1.0" },
+                                "data": [download_job_json(&project_id, &job.id, JobPhase::Pending)]
+                            })),
+                        )
+                            .into_response()
+                    }
+                },
+            ),
+        ));
+
+        // Polled until the download is ready, then redirects to it
+        let dm_state = context.state.clone();
+        routes.push(PackRoute::new(
+            "/data/v1/projects/:project_id/jobs/:job_id",
+            HttpMethod::Get,
+            get(move |Path((project_id, job_id)): Path<(String, String)>| {
+                let state_inner = dm_state.clone();
+                async move {
+                    let status = state_inner
+                        .as_ref()
+                        .filter(|state_manager| {
+                            state_manager
+                                .jobs
+                                .get(&job_id)
+                                .is_some_and(|job| job.kind == JobKind::Download)
+                        })
+                        .and_then(|state_manager| state_manager.jobs.status(&job_id));
+                    let Some(status) = status else {
+                        return jsonapi_error(
+                            axum::http::StatusCode::NOT_FOUND,
+                            &format!("Job {} not found", job_id),
+                        );
+                    };
+                    if status.phase == JobPhase::Success {
+                        let location =
+                            format!("/data/v1/projects/{}/downloads/{}", project_id, job_id);
+                        return (
+                            axum::http::StatusCode::SEE_OTHER,
+                            [(axum::http::header::LOCATION, location)],
+                        )
+                            .into_response();
+                    }
+                    (
+                        axum::http::StatusCode::OK,
+                        JsonResponse(json!({
+                            "jsonapi": { "version": "1.0" },
+                            "data": download_job_json(&project_id, &job_id, status.phase)
+                        })),
+                    )
+                        .into_response()
+                }
+            }),
+        ));
+
+        let dm_state = context.state.clone();
+        routes.push(PackRoute::new(
+            "/data/v1/projects/:project_id/downloads/:download_id",
+            HttpMethod::Get,
+            get(
+                move |Path((project_id, download_id)): Path<(String, String)>,
+                      headers: HeaderMap| {
+                    let state_inner = dm_state.clone();
+                    async move {
+                        let Some((file_type, version_id)) =
+                            completed_download(state_inner.as_ref(), &download_id)
+                        else {
+                            return jsonapi_error(
+                                axum::http::StatusCode::NOT_FOUND,
+                                &format!("Download {} not found", download_id),
+                            );
+                        };
+                        let host = headers
+                            .get(axum::http::header::HOST)
+                            .and_then(|h| h.to_str().ok())
+                            .unwrap_or("localhost");
+                        (
+                            axum::http::StatusCode::OK,
+                            JsonResponse(json!({
+                                "jsonapi": { "version": "1.0" },
+                                "data": {
+                                    "type": "downloads",
+                                    "id": download_id,
+                                    "attributes": { "format": { "fileType": file_type } },
+                                    "relationships": {
+                                        "source": { "data": { "type": "versions", "id": version_id } },
+                                        "storage": {
+                                            "data": {
+                                                "type": "objects",
+                                                "id": format!(
+                                                    "urn:adsk.objects:os.object:downloads/{}.{}",
+                                                    download_id, file_type
+                                                )
+                                            },
+                                            "meta": {
+                                                "link": {
+                                                    "href": format!(
+                                                        "http://{}/__mock/downloads/{}",
+                                                        host, download_id
+                                                    )
+                                                }
+                                            }
+                                        }
+                                    },
+                                    "links": {
+                                        "self": {
+                                            "href": format!(
+                                                "/data/v1/projects/{}/downloads/{}",
+                                                project_id, download_id
+                                            )
+                                        }
+                                    }
+                                }
+                            })),
+                        )
+                            .into_response()
+                    }
+                },
+            ),
+        ));
+
+        // Target of the storage links handed out above: an archive holding
+        // the exported file
+        let dm_state = context.state.clone();
+        routes.push(
+            PackRoute::new(
+                "/__mock/downloads/:download_id",
+                HttpMethod::Get,
+                get(move |Path(download_id): Path<String>| {
+                    let state_inner = dm_state.clone();
+                    async move {
+                        let download = completed_download(state_inner.as_ref(), &download_id);
+                        let Some((state_manager, (file_type, version_id))) =
+                            state_inner.as_ref().zip(download)
+                        else {
+                            return jsonapi_error(
+                                axum::http::StatusCode::NOT_FOUND,
+                                &format!("Download {} not found", download_id),
+                            );
+                        };
+                        let name = state_manager
+                            .projects
+                            .get_version(&version_id)
+                            .map(|item| item.name)
+                            .unwrap_or_else(|| download_id.clone());
+                        let stem = name
+                            .rsplit_once('.')
+                            .map_or(name.as_str(), |(stem, _)| stem);
+                        let file_name = format!("{}.{}", stem, file_type.to_ascii_lowercase());
+                        let contents = format!("Mock {} export of {}\n", file_type, version_id);
+                        match artifacts::zip(&[(file_name, contents.into_bytes())]) {
+                            Ok(archive) => (
+                                axum::http::StatusCode::OK,
+                                [
+                                    (
+                                        axum::http::header::CONTENT_TYPE,
+                                        "application/zip".to_string(),
+                                    ),
+                                    (
+                                        axum::http::header::CONTENT_DISPOSITION,
+                                        format!("attachment; filename=\"{}.zip\"", stem),
+                                    ),
+                                ],
+                                archive,
+                            )
+                                .into_response(),
+                            Err(e) => jsonapi_error(
+                                axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                                &e.to_string(),
+                            ),
+                        }
+                    }
+                }),
+            )
+            .public(),
+        );
+
+        routes
+    }
+}
+
+/// File types a version can be downloaded as. Exports come from the
+/// derivatives, so a version offers none until its translation has succeeded.
+fn download_formats(state_manager: &StateManager, item: &ItemInfo) -> Vec<&'static str> {
+    let translated = [
+        item.storage_urn.clone(),
+        base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(&item.storage_urn),
+        base64::engine::general_purpose::STANDARD.encode(&item.storage_urn),
+    ]
+    .iter()
+    .filter_map(|urn| state_manager.translations.get_job(urn))
+    .any(|job| job.status == TranslationStatus::Success);
+    if translated {
+        export_formats(&item.name)
+    } else {
+        Vec::new()
+    }
+}
+
+/// File type and version of a download, once its job has succeeded
+fn completed_download(state: Option<&StateManager>, download_id: &str) -> Option<(String, String)> {
+    let state_manager = state?;
+    let job = state_manager.jobs.get(download_id)?;
+    let status = state_manager.jobs.status(download_id)?;
+    if job.kind != JobKind::Download || status.phase != JobPhase::Success {
+        return None;
+    }
+    let (file_type, version_id) = job.key.split_once(':')?;
+    Some((file_type.to_string(), version_id.to_string()))
+}
+
+/// Key of a download job: the file type, then the version it exports
+fn download_key(file_type: &str, version_id: &str) -> String {
+    format!("{}:{}", file_type, version_id)
+}
+
+fn download_job_json(project_id: &str, job_id: &str, phase: JobPhase) -> Value {
+    let status = match phase {
+        JobPhase::Pending => "queued",
+        JobPhase::InProgress => "processing",
+        JobPhase::Success => "success",
+        JobPhase::Failed | JobPhase::Timeout => "failed",
+    };
+    json!({
+        "type": "jobs",
+        "id": job_id,
+        "attributes": { "status": status },
+        "links": {
+            "self": { "href": format!("/data/v1/projects/{}/jobs/{}", project_id, job_id) }
+        }
+    })
+}
+
+fn item_json(state_manager: &StateManager, item: &ItemInfo) -> Value {
+    let mut attributes = json!({ "displayName": item.name });
+    add_attribution(state_manager, item, &mut attributes);
+    json!({
+        "type": "items",
+        "id": item.id,
+        "attributes": attributes,
+        "relationships": {
+            "tip": { "data": { "type": "versions", "id": item.version_id() } },
+            "parent": { "data": { "type": "folders", "id": item.folder_id } }
+        }
+    })
+}
+
+fn version_json(state_manager: &StateManager, item: &ItemInfo) -> Value {
+    let mut attributes = json!({ "name": item.name, "versionNumber": 1 });
+    add_attribution(state_manager, item, &mut attributes);
+    json!({
+        "type": "versions",
+        "id": item.version_id(),
+        "attributes": attributes,
+        "relationships": {
+            "storage": {
+                "data": { "type": "objects", "id": item.storage_urn }
+            },
+            "derivatives": {
+                "data": {
+                    "type": "derivatives",
+                    "id": base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(&item.storage_urn)
+                }
+            }
+        }
+    })
+}
+
+/// The creating (and, with a single version, last modifying) user of an
+/// item created with a 3-legged token
+fn add_attribution(state_manager: &StateManager, item: &ItemInfo, attributes: &mut Value) {
+    let Some(ref user_id) = item.created_by else {
+        return;
+    };
+    let name = state_manager
+        .users
+        .get_user(user_id)
+        .map(|user| user.name)
+        .unwrap_or_default();
+    attributes["createUserId"] = json!(user_id);
+    attributes["createUserName"] = json!(name);
+    attributes["lastModifiedUserId"] = json!(user_id);
+    attributes["lastModifiedUserName"] = json!(name);
+}
+
+fn jsonapi_error(status: axum::http::StatusCode, detail: &str) -> Response {
+    (
+        status,
+        JsonResponse(json!({
+            "jsonapi": { "version": "1.0" },
+            "errors": [{
+                "status": status.as_str(),
+                "title": status.canonical_reason().unwrap_or_default(),
+                "detail": detail
+            }]
+        })),
+    )
+        .into_response()
+}
+
+fn folder_json(folder: &crate::state::projects::FolderInfo) -> Value {
+    json!({
+        "type": "folders",
+        "id": folder.id,
+        "attributes": {
+            "name": folder.name,
+            "displayName": folder.name
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::MockServerConfig;
+    use crate::state::StateManager;
+    use crate::state::jobs::JobOutcome;
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use base64::Engine as _;
+    use serde_json::Value;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_download_formats_follow_the_translation() {
+        let state = StateManager::new();
+        let folder = state
+            .projects
+            .create_folder("b.project", None, "Plans".into());
+        let urn = "urn:adsk.objects:os.object:wip/tower.rvt";
+        let item = state.projects.create_item(
+            "b.project",
+            &folder.id,
+            "Tower.RVT".into(),
+            urn.into(),
+            None,
+        );
+        let (router, _) = crate::server::router::build_router(
+            Vec::new(),
+            Vec::new(),
+            Some(state.clone()),
+            &MockServerConfig::default(),
+            &[],
+        )
+        .unwrap();
+        let token = state
+            .auth
+            .generate_token("client", 3600, Some("data:read".into()));
+        let formats = |version_id: String| {
+            let router = router.clone();
+            let request = Request::get(format!(
+                "/data/v1/projects/b.project/versions/{}/downloadFormats",
+                form_urlencoded::byte_serialize(version_id.as_bytes()).collect::<String>()
+            ))
+            .header("authorization", format!("Bearer {}", token.access_token))
+            .body(Body::empty())
+            .unwrap();
+            async move {
+                let response = router.oneshot(request).await.unwrap();
+                let status = response.status();
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                let body: Value = serde_json::from_slice(&body).unwrap();
+                (status, body["data"]["attributes"]["formats"].clone())
+            }
+        };
+
+        // Nothing to export before the translation succeeds
+        let (status, body) = formats(item.version_id()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, serde_json::json!([]));
+
+        let encoded = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(urn);
+        let job = state.translations.create_job(encoded);
+        state.jobs.settle(&job.job_id, JobOutcome::Success);
+        let (_, body) = formats(item.version_id()).await;
+        assert_eq!(
+            body,
+            serde_json::json!([{ "fileType": "dwg" }, { "fileType": "ifc" }])
+        );
+
+        let (status, _) = formats(item.version_id().replace("version=1", "version=2")).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_items_are_attributed_to_the_token_user() {
+        let state = StateManager::new();
+        let folder = state
+            .projects
+            .create_folder("b.project", None, "Plans".into());
+        let (router, _) = crate::server::router::build_router(
+            Vec::new(),
+            Vec::new(),
+            Some(state.clone()),
+            &MockServerConfig::default(),
+            &[],
+        )
+        .unwrap();
+        let token = state.auth.generate_three_legged_token(
+            "client",
+            3600,
+            Some("data:read data:create".into()),
+            crate::state::users::DEFAULT_USER_ID,
+        );
+        let body = serde_json::json!({
+            "data": {
+                "type": "items",
+                "attributes": { "displayName": "Tower.RVT" },
+                "relationships": {
+                    "parent": { "data": { "type": "folders", "id": folder.id } }
+                }
+            },
+            "included": [{
+                "type": "versions",
+                "relationships": {
+                    "storage": {
+                        "data": { "type": "objects", "id": "urn:adsk.objects:os.object:wip/tower.rvt" }
+                    }
+                }
+            }]
+        });
+        let request = Request::post("/data/v1/projects/b.project/items")
+            .header("authorization", format!("Bearer {}", token.access_token))
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        let response = router.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["data"]["attributes"]["createUserId"], "mock-user");
+        assert_eq!(
+            body["included"][0]["attributes"]["createUserName"],
+            "Mock User"
+        );
+
+        let (_, items) = state.projects.folder_contents(&folder.id);
+        assert_eq!(items[0].created_by.as_deref(), Some("mock-user"));
+    }
+}
@@ -0,0 +1,145 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+//! ACC Issues.
+
+use axum::{
+    extract::{Json, Path},
+    http::HeaderMap,
+    response::{IntoResponse, Json as JsonResponse},
+    routing::{get, post},
+};
+use serde_json::{Value, json};
+
+use crate::config::Service;
+use crate::openapi::types::HttpMethod;
+use crate::server::packs::{PackContext, PackRoute, RoutePack, StateRequirement, request_user};
+use crate::state::issues::IssueInfo;
+
+/// Built-in ACC Issues
+#[derive(Debug, Default)]
+pub struct IssuesPack;
+
+impl RoutePack for IssuesPack {
+    fn name(&self) -> &str {
+        "issues"
+    }
+
+    fn state_requirement(&self) -> StateRequirement {
+        StateRequirement::Service(Service::Issues)
+    }
+
+    fn routes(&self, context: &PackContext<'_>) -> Vec<PackRoute> {
+        let mut routes = Vec::new();
+
+        let issues_state = context.state.clone();
+        routes.push(
+            PackRoute::new(
+                "/construction/issues/v1/projects/:project_id/issues",
+                HttpMethod::Get,
+                get(move |Path(project_id): Path<String>| {
+                    let state_inner = issues_state.clone();
+                    async move {
+                        if let Some(ref state_manager) = state_inner {
+                            let issues = state_manager.issues.list_issues(&project_id);
+                            let data: Vec<Value> = issues.iter().map(issue_json).collect();
+                            (
+                                axum::http::StatusCode::OK,
+                                JsonResponse(json!({ "data": data })),
+                            )
+                                .into_response()
+                        } else {
+                            (
+                                axum::http::StatusCode::OK,
+                                JsonResponse(json!({ "data": [] })),
+                            )
+                                .into_response()
+                        }
+                    }
+                }),
+            )
+            .overrides_spec(),
+        );
+
+        let issues_state = context.state.clone();
+        routes.push(
+            PackRoute::new(
+                "/construction/issues/v1/projects/:project_id/issues",
+                HttpMethod::Post,
+                post(
+                    move |Path(project_id): Path<String>,
+                          headers: HeaderMap,
+                          Json(body_value): Json<Value>| {
+                        let state_inner = issues_state.clone();
+                        async move {
+                            if let Some(ref state_manager) = state_inner {
+                                let title = body_value
+                                    .get("title")
+                                    .and_then(|v| v.as_str())
+                                    .unwrap_or("Untitled Issue")
+                                    .to_string();
+
+                                let description = body_value
+                                    .get("description")
+                                    .and_then(|v| v.as_str())
+                                    .map(|s| s.to_string());
+
+                                // Issues created with a 3-legged token are
+                                // attributed to its user, and assigned to them
+                                // unless the body says otherwise
+                                let created_by =
+                                    request_user(state_manager, &headers).map(|user| user.id);
+                                let assigned_to = body_value
+                                    .get("assignedTo")
+                                    .and_then(|v| v.as_str())
+                                    .map(|s| s.to_string())
+                                    .or_else(|| created_by.clone());
+
+                                let issue = state_manager.issues.create_attributed_issue(
+                                    project_id,
+                                    title,
+                                    description,
+                                    created_by,
+                                    assigned_to,
+                                );
+
+                                (
+                                    axum::http::StatusCode::CREATED,
+                                    JsonResponse(json!({ "data": issue_json(&issue) })),
+                                )
+                                    .into_response()
+                            } else {
+                                (
+                                    axum::http::StatusCode::CREATED,
+                                    JsonResponse(json!({
+                                        "data": {
+                                            "id": "mock-issue-id",
+                                            "title": "Mock Issue",
+                                            "status": "open"
+                                        }
+                                    })),
+                                )
+                                    .into_response()
+                            }
+                        }
+                    },
+                ),
+            )
+            .overrides_spec(),
+        );
+
+        routes
+    }
+}
+
+fn issue_json(issue: &IssueInfo) -> Value {
+    json!({
+        "id": issue.id,
+        "title": issue.title,
+        "description": issue.description,
+        "status": issue.status,
+        "createdAt": issue.created_at,
+        "createdBy": issue.created_by,
+        "assignedTo": issue.assigned_to
+    })
+}
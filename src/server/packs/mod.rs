@@ -0,0 +1,452 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+//! Route packs: hand-written routes for a service, registered next to the
+//! routes generated from the OpenAPI specs.
+//!
+//! Every built-in service is a pack, and library users can add their own
+//! through [`crate::MockServer::with_route_packs`], e.g. to mock an APS
+//! service raps-mock does not cover. Routes only fill gaps: a method and path
+//! already registered, from a spec or an earlier pack, is skipped. User packs
+//! are registered before the built-in ones and so take precedence over them.
+//!
+//! The exception are routes marked [`PackRoute::overrides_spec`]: while their
+//! pack has state they replace the spec operation, so e.g. object uploads are
+//! stored rather than answered with a generated example.
+
+use crate::config::{MockMode, MockServerConfig, Service};
+use crate::openapi::schemas::SchemaRegistry;
+use crate::openapi::types::HttpMethod;
+use crate::server::catalog::{RouteCatalog, RouteInfo, RouteSource, SkipReason, SkippedRoute};
+use crate::server::consistency::{RouteDrift, SpecRoutes, shape};
+use crate::server::params::ParamNames;
+use crate::state::StateManager;
+use crate::state::users::UserInfo;
+use axum::Router;
+use axum::http::{HeaderMap, header::AUTHORIZATION};
+use axum::routing::MethodRouter;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+mod account_admin;
+mod authentication;
+mod data_connector;
+mod data_management;
+mod issues;
+mod model_derivative;
+mod oss;
+mod webhooks;
+
+pub use account_admin::AccountAdminPack;
+pub use authentication::AuthenticationPack;
+pub use data_connector::DataConnectorPack;
+pub use data_management::DataManagementPack;
+pub use issues::IssuesPack;
+pub use model_derivative::ModelDerivativePack;
+pub use oss::OssPack;
+pub use webhooks::WebhooksPack;
+
+/// When a pack's routes are handed the [`StateManager`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StateRequirement {
+    /// Never; the routes answer statelessly
+    None,
+    /// When the service runs statefully (see `--service-mode`)
+    Service(Service),
+    /// Whenever the server keeps state
+    Any,
+}
+
+/// What a pack builds its routes with
+pub struct PackContext<'a> {
+    /// Shared state, `None` unless the pack's [`StateRequirement`] is met
+    pub state: Option<StateManager>,
+    pub config: &'a MockServerConfig,
+    /// Component schemas of the loaded specs
+    pub schemas: &'a Arc<SchemaRegistry>,
+}
+
+/// A route contributed by a pack
+pub struct PackRoute {
+    /// Axum path template, e.g. `/oss/v2/buckets/:bucket_key`
+    pub path: String,
+    pub method: HttpMethod,
+    pub handler: MethodRouter,
+    /// Whether the route is callable without a Bearer token
+    pub public: bool,
+    /// Whether the route replaces a spec operation on the same path while
+    /// the pack has state
+    pub overrides_spec: bool,
+}
+
+impl PackRoute {
+    pub fn new(path: impl Into<String>, method: HttpMethod, handler: MethodRouter) -> Self {
+        Self {
+            path: path.into(),
+            method,
+            handler,
+            public: false,
+            overrides_spec: false,
+        }
+    }
+
+    /// Make the route callable without a Bearer token
+    pub fn public(mut self) -> Self {
+        self.public = true;
+        self
+    }
+
+    /// Serve the route instead of the spec operation for the same method and
+    /// path when the pack was handed state, for operations whose generated
+    /// response would drop what the client sent
+    pub fn overrides_spec(mut self) -> Self {
+        self.overrides_spec = true;
+        self
+    }
+}
+
+/// A set of hand-written routes for a service
+pub trait RoutePack: Send + Sync {
+    /// Name used in logs
+    fn name(&self) -> &str;
+
+    fn state_requirement(&self) -> StateRequirement;
+
+    fn routes(&self, context: &PackContext<'_>) -> Vec<PackRoute>;
+}
+
+/// The packs for the services raps-mock simulates
+pub fn builtin() -> Vec<Arc<dyn RoutePack>> {
+    vec![
+        Arc::new(AuthenticationPack),
+        Arc::new(OssPack),
+        Arc::new(DataManagementPack),
+        Arc::new(ModelDerivativePack),
+        Arc::new(IssuesPack),
+        Arc::new(AccountAdminPack),
+        Arc::new(WebhooksPack),
+        Arc::new(DataConnectorPack),
+    ]
+}
+
+/// A pack route, built with the state its pack was given
+pub(crate) struct ResolvedRoute {
+    pack: String,
+    stateful: bool,
+    route: PackRoute,
+}
+
+/// Build the routes of every pack
+pub(crate) fn resolve(
+    packs: &[Arc<dyn RoutePack>],
+    state: Option<StateManager>,
+    config: &MockServerConfig,
+    schemas: &Arc<SchemaRegistry>,
+) -> Vec<ResolvedRoute> {
+    let mut routes = Vec::new();
+    for pack in packs {
+        let state = match pack.state_requirement() {
+            StateRequirement::None => None,
+            StateRequirement::Service(service) => state
+                .clone()
+                .filter(|_| config.mode_for(service) == MockMode::Stateful),
+            StateRequirement::Any => state.clone(),
+        };
+        let stateful = state.is_some();
+        let context = PackContext {
+            state,
+            config,
+            schemas,
+        };
+        routes.extend(
+            pack.routes(&context)
+                .into_iter()
+                .map(|route| ResolvedRoute {
+                    pack: pack.name().to_string(),
+                    stateful,
+                    route,
+                }),
+        );
+    }
+    routes
+}
+
+/// The pack taking over the spec operation at `path`, if any
+pub(crate) fn overriding<'a>(
+    routes: &'a [ResolvedRoute],
+    path: &str,
+    method: HttpMethod,
+) -> Option<&'a str> {
+    let path = shape(path);
+    routes
+        .iter()
+        .find(|r| {
+            r.stateful
+                && r.route.overrides_spec
+                && r.route.method == method
+                && shape(&r.route.path) == path
+        })
+        .map(|r| r.pack.as_str())
+}
+
+/// Add the pack routes not already in `catalog`, logging the ones that
+/// drifted from the spec routes
+///
+/// `overridden` holds the spec operations skipped for an overriding route,
+/// by shape and method: the route keeps their security, tags and operationId.
+pub(crate) fn register(
+    mut router: Router,
+    routes: Vec<ResolvedRoute>,
+    specs: &SpecRoutes,
+    overridden: &HashMap<(String, HttpMethod), RouteInfo>,
+    params: &mut ParamNames,
+    catalog: &mut RouteCatalog,
+) -> Router {
+    let mut drifts = Vec::new();
+    for ResolvedRoute { pack, route, .. } in routes {
+        if let Some(drift) = specs.check(&route.path, route.method) {
+            drifts.push(RouteDrift {
+                pack: pack.clone(),
+                method: route.method,
+                path: route.path.clone(),
+                drift,
+            });
+        }
+        let path = params.canonicalize(&route.path);
+        let mut info = match overridden.get(&(shape(&path), route.method)) {
+            Some(spec) if route.overrides_spec => RouteInfo {
+                path: path.clone(),
+                source: RouteSource::Builtin,
+                ..spec.clone()
+            },
+            _ => RouteInfo::builtin(&path, route.method),
+        };
+        info.origin = Some(pack.clone());
+        if route.public {
+            info = info.public();
+        }
+        if let Err(e) = catalog.claim_path(&path) {
+            catalog.skip(SkippedRoute::route(
+                route.method.as_str(),
+                &path,
+                Some(pack),
+                SkipReason::Conflict,
+                e,
+            ));
+            continue;
+        }
+        if catalog.register(info) {
+            router = router.route(&path, route.handler);
+        }
+    }
+    if !drifts.is_empty() {
+        tracing::info!(
+            "{} route pack routes drift from the loaded specs",
+            drifts.len()
+        );
+        for drift in &drifts {
+            tracing::info!("  {}", drift);
+        }
+    }
+    router
+}
+
+/// Whether `state` handed out the token-less `/__mock/...` URL at `path`,
+/// i.e. holds the upload, job or translation the URL names
+pub(crate) fn minted_by(state: &StateManager, path: &str) -> bool {
+    let Some(rest) = path.strip_prefix("/__mock/") else {
+        return false;
+    };
+    let segments: Vec<&str> = rest.split('/').collect();
+    match segments.as_slice() {
+        ["s3", upload_key, ..] => state.uploads.get_session(upload_key).is_some(),
+        ["downloads", job_id] | ["data-connector", _, job_id, ..] => {
+            state.jobs.get(job_id).is_some()
+        }
+        ["derivatives", urn, ..] => model_derivative::find_translation(state, urn).is_some(),
+        _ => false,
+    }
+}
+
+/// Client ID of the app whose Bearer token authorizes the request
+pub(crate) fn request_client(state: &StateManager, headers: &HeaderMap) -> Option<String> {
+    let token = headers
+        .get(AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")?;
+    state.auth.lookup_token(token).map(|info| info.client_id)
+}
+
+/// The user the request's 3-legged Bearer token acts for
+pub(crate) fn request_user(state: &StateManager, headers: &HeaderMap) -> Option<UserInfo> {
+    let token = headers
+        .get(AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")?;
+    state.token_user(token)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use axum::routing::get;
+    use tower::ServiceExt;
+
+    struct StatusPack;
+
+    impl RoutePack for StatusPack {
+        fn name(&self) -> &str {
+            "status"
+        }
+
+        fn state_requirement(&self) -> StateRequirement {
+            StateRequirement::None
+        }
+
+        fn routes(&self, _context: &PackContext<'_>) -> Vec<PackRoute> {
+            vec![
+                PackRoute::new("/status/v1/ping", HttpMethod::Get, get(|| async { "pong" }))
+                    .public(),
+                // Replaces the built-in formats route
+                PackRoute::new(
+                    "/modelderivative/v2/designdata/formats",
+                    HttpMethod::Get,
+                    get(|| async { "custom" }),
+                ),
+            ]
+        }
+    }
+
+    #[tokio::test]
+    async fn test_custom_pack_routes_are_served() {
+        let packs: Vec<Arc<dyn RoutePack>> = vec![Arc::new(StatusPack)];
+        let (router, catalog) = crate::server::router::build_router(
+            Vec::new(),
+            Vec::new(),
+            None,
+            &MockServerConfig::default(),
+            &packs,
+        )
+        .unwrap();
+
+        let ping = catalog.get("/status/v1/ping", "GET").unwrap();
+        assert_eq!(ping.origin.as_deref(), Some("status"));
+
+        let get = |uri: &str| {
+            Request::get(uri)
+                .header("authorization", "Bearer token")
+                .body(Body::empty())
+                .unwrap()
+        };
+        let ping = Request::get("/status/v1/ping").body(Body::empty()).unwrap();
+        let response = router.clone().oneshot(ping).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = router
+            .clone()
+            .oneshot(get("/modelderivative/v2/designdata/formats"))
+            .await
+            .unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(&body[..], b"custom");
+
+        // Built-in packs still fill in the rest
+        let response = router.oneshot(get("/oss/v2/buckets")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    /// A router over an OSS spec whose object upload the oss pack overrides
+    fn upload_spec_router(state: &StateManager) -> (Router, Arc<RouteCatalog>) {
+        let spec: crate::openapi::types::OpenApiSpec = serde_yaml::from_str(
+            r##"
+openapi: 3.0.0
+info: { title: OSS, version: "2.0" }
+security:
+  - oauth: [data:write]
+paths:
+  /oss/v2/buckets/{bucketKey}/objects/{objectKey}:
+    put:
+      operationId: upload-object
+      tags: [Objects]
+      requestBody:
+        content:
+          application/octet-stream:
+            schema: { type: string, format: binary }
+      responses:
+        "200": { description: OK }
+components:
+  securitySchemes:
+    oauth:
+      type: oauth2
+      flows: {}
+"##,
+        )
+        .unwrap();
+        let routes = crate::openapi::OpenApiParser::extract_routes(&spec);
+        crate::server::router::build_router(
+            routes,
+            Vec::new(),
+            Some(state.clone()),
+            &MockServerConfig::default(),
+            &[],
+        )
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_overriding_route_keeps_the_spec_operation_security() {
+        let state = StateManager::new();
+        let (router, catalog) = upload_spec_router(&state);
+
+        let put = catalog
+            .get("/oss/v2/buckets/:bucket_key/objects/:object_key", "PUT")
+            .unwrap();
+        assert_eq!(put.source, RouteSource::Builtin);
+        assert_eq!(put.operation_id.as_deref(), Some("upload-object"));
+        assert_eq!(put.tags, vec!["Objects".to_string()]);
+        assert_eq!(put.required_scopes, vec![vec!["data:write".to_string()]]);
+
+        let token = state
+            .auth
+            .generate_token("client", 3600, Some("data:read".into()));
+        let request = Request::put("/oss/v2/buckets/b1/objects/model.rvt")
+            .header("authorization", format!("Bearer {}", token.access_token))
+            .body(Body::from("model"))
+            .unwrap();
+        let response = router.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        assert!(state.objects.get_object("b1", "model.rvt").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_stateful_object_upload_overrides_spec_operation() {
+        let state = StateManager::new();
+        let (router, catalog) = upload_spec_router(&state);
+
+        let put = catalog
+            .get("/oss/v2/buckets/:bucket_key/objects/:object_key", "PUT")
+            .unwrap();
+        assert_eq!(put.origin.as_deref(), Some("oss"));
+
+        let token = state
+            .auth
+            .generate_token("client", 3600, Some("data:write".into()));
+        let request = Request::put("/oss/v2/buckets/b1/objects/model.rvt")
+            .header("authorization", format!("Bearer {}", token.access_token))
+            .header("content-type", "application/vnd.autodesk.revit")
+            .body(Body::from(&b"\x00\x01{not json"[..]))
+            .unwrap();
+        let response = router.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let object = state.objects.get_object("b1", "model.rvt").unwrap();
+        assert_eq!(object.content_type, "application/vnd.autodesk.revit");
+        assert_eq!(object.size, 11);
+    }
+}
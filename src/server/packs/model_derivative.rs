@@ -0,0 +1,573 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+//! Model Derivative translation jobs, manifests and derivative downloads.
+//!
+//! A successful translation's manifest lists one derivative resource, whose
+//! URN downloads a generated placeholder file of `--derivative-size` bytes,
+//! either directly or through signed cookies. Its thumbnail is a generated
+//! PNG of the requested size.
+
+use axum::{
+    body::{Body, Bytes},
+    extract::{Json, Path, Query},
+    http::{HeaderMap, HeaderValue, StatusCode, header},
+    response::{IntoResponse, Json as JsonResponse, Response},
+    routing::{get, post},
+};
+use base64::Engine as _;
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::convert::Infallible;
+
+use crate::artifacts;
+use crate::config::Service;
+use crate::openapi::types::HttpMethod;
+use crate::server::packs::{PackContext, PackRoute, RoutePack, StateRequirement};
+use crate::state::StateManager;
+use crate::state::translations::{TranslationJob, TranslationStatus};
+
+/// Output formats and the input file extensions each accepts, as listed by
+/// `GET /modelderivative/v2/designdata/formats`
+pub(crate) const FORMATS: &[(&str, &[&str])] = &[
+    ("svf", VIEWABLE),
+    ("svf2", VIEWABLE),
+    ("thumbnail", VIEWABLE),
+    ("dwg", &["f2d", "f3d", "rvt"]),
+    ("fbx", &["f3d"]),
+    ("ifc", &["rvt"]),
+    ("iges", &["f3d", "fbx", "iam", "ipt", "wire"]),
+    ("obj", &["f3d", "iam", "igs", "iges", "ipt", "step", "stp"]),
+    ("step", &["f3d", "fbx", "iam", "ipt", "wire"]),
+    ("stl", &["f3d", "fbx", "iam", "ipt", "wire"]),
+];
+
+const VIEWABLE: &[&str] = &[
+    "3dm", "dwf", "dwg", "dxf", "f3d", "fbx", "iam", "ifc", "iges", "igs", "ipt", "nwc", "nwd",
+    "obj", "rvt", "step", "stp", "stl", "wire",
+];
+
+/// File formats a design can be exported to once translated: every
+/// non-viewable output accepting its extension
+pub(crate) fn export_formats(file_name: &str) -> Vec<&'static str> {
+    let Some((_, extension)) = file_name.rsplit_once('.') else {
+        return Vec::new();
+    };
+    let extension = extension.to_ascii_lowercase();
+    FORMATS
+        .iter()
+        .filter(|(output, _)| !matches!(*output, "svf" | "svf2" | "thumbnail"))
+        .filter(|(_, inputs)| inputs.contains(&extension.as_str()))
+        .map(|(output, _)| *output)
+        .collect()
+}
+
+/// Built-in Model Derivative translation jobs and manifests
+#[derive(Debug, Default)]
+pub struct ModelDerivativePack;
+
+impl RoutePack for ModelDerivativePack {
+    fn name(&self) -> &str {
+        "model-derivative"
+    }
+
+    fn state_requirement(&self) -> StateRequirement {
+        StateRequirement::Service(Service::ModelDerivative)
+    }
+
+    fn routes(&self, context: &PackContext<'_>) -> Vec<PackRoute> {
+        let mut routes = Vec::new();
+
+        let md_state = context.state.clone();
+        routes.push(
+            PackRoute::new(
+                "/modelderivative/v2/designdata/job",
+                HttpMethod::Post,
+                post(move |Json(body_value): Json<Value>| {
+                    let state_inner = md_state.clone();
+                    async move {
+                        if let Some(ref state_manager) = state_inner {
+                            let input_urn = body_value
+                                .get("input")
+                                .and_then(|i| i.get("urn"))
+                                .and_then(|v| v.as_str())
+                                .unwrap_or("");
+
+                            let output_type = body_value
+                                .get("output")
+                                .and_then(|o| o.get("formats"))
+                                .and_then(|v| v.as_array())
+                                .and_then(|arr| arr.first())
+                                .and_then(|f| f.get("type"))
+                                .and_then(|v| v.as_str())
+                                .unwrap_or("svf2");
+
+                            let job = state_manager.translations.create_job(input_urn.to_string());
+
+                            (
+                                axum::http::StatusCode::OK,
+                                JsonResponse(json!({
+                                    "result": "success",
+                                    "urn": job.urn,
+                                    "acceptedJobs": { "type": output_type }
+                                })),
+                            )
+                                .into_response()
+                        } else {
+                            (
+                                axum::http::StatusCode::OK,
+                                JsonResponse(json!({ "result": "success" })),
+                            )
+                                .into_response()
+                        }
+                    }
+                }),
+            )
+            .overrides_spec(),
+        );
+
+        routes.push(PackRoute::new(
+            "/modelderivative/v2/designdata/formats",
+            HttpMethod::Get,
+            get(|| async {
+                let formats: serde_json::Map<String, Value> = FORMATS
+                    .iter()
+                    .map(|(output, inputs)| (output.to_string(), json!(inputs)))
+                    .collect();
+                JsonResponse(json!({ "formats": formats }))
+            }),
+        ));
+
+        let md_state = context.state.clone();
+        routes.push(PackRoute::new(
+            "/modelderivative/v2/designdata/:urn/manifest",
+            HttpMethod::Get,
+            get(move |Path(urn): Path<String>| {
+                let state_inner = md_state.clone();
+                async move {
+                    let decoded_urn = decode_urn(&urn);
+
+                    if let Some(ref state_manager) = state_inner {
+                        if let Some(job) = find_translation(state_manager, &urn) {
+                            let status_str = match job.status {
+                                crate::state::translations::TranslationStatus::Pending => "pending",
+                                crate::state::translations::TranslationStatus::InProgress => {
+                                    "inprogress"
+                                }
+                                crate::state::translations::TranslationStatus::Success => "success",
+                                crate::state::translations::TranslationStatus::Failed => "failed",
+                                crate::state::translations::TranslationStatus::Timeout => {
+                                    "timeout"
+                                }
+                            };
+
+                            let manifest = json!({
+                                "type": "manifest",
+                                "hasThumbnail": status_str == "success",
+                                "status": status_str,
+                                "progress": job.progress,
+                                "region": "US",
+                                "urn": decoded_urn,
+                                "version": "1.0",
+                                "derivatives": match status_str {
+                                    "success" => vec![json!({
+                                        "status": "success",
+                                        "progress": "complete",
+                                        "outputType": "svf2",
+                                        "children": [{
+                                            "guid": "geometry-3d",
+                                            "type": "geometry",
+                                            "role": "3d",
+                                            "name": "3D",
+                                            "status": "success",
+                                            "progress": "complete",
+                                            "children": [{
+                                                "guid": "geometry-3d-svf",
+                                                "type": "resource",
+                                                "role": "graphics",
+                                                "mime": "application/autodesk-svf",
+                                                "urn": derivative_urn(&urn)
+                                            }]
+                                        }]
+                                    })],
+                                    "failed" | "timeout" => vec![json!({
+                                        "status": status_str,
+                                        "progress": "complete",
+                                        "outputType": "svf2",
+                                        "messages": [{
+                                            "type": "error",
+                                            "code": "TranslationWorker-InternalFailure",
+                                            "message": job.message
+                                        }],
+                                        "children": []
+                                    })],
+                                    _ => vec![],
+                                }
+                            });
+
+                            (axum::http::StatusCode::OK, JsonResponse(manifest)).into_response()
+                        } else {
+                            (
+                                axum::http::StatusCode::NOT_FOUND,
+                                JsonResponse(json!({
+                                    "reason": format!("Translation job for URN {} not found", decoded_urn)
+                                })),
+                            )
+                                .into_response()
+                        }
+                    } else {
+                        (
+                            axum::http::StatusCode::OK,
+                            JsonResponse(json!({
+                                "type": "manifest",
+                                "hasThumbnail": false,
+                                "status": "pending",
+                                "progress": "0%",
+                                "region": "US",
+                                "urn": decoded_urn,
+                                "derivatives": []
+                            })),
+                        )
+                            .into_response()
+                    }
+                }
+            }),
+        ).overrides_spec());
+
+        let md_state = context.state.clone();
+        routes.push(
+            PackRoute::new(
+                "/modelderivative/v2/designdata/:urn/thumbnail",
+                HttpMethod::Get,
+                get(
+                    move |Path(urn): Path<String>, Query(query): Query<ThumbnailQuery>| {
+                        let state_inner = md_state.clone();
+                        async move {
+                            let width = query.width.unwrap_or(DEFAULT_THUMBNAIL_SIZE);
+                            let height = query.height.unwrap_or(width);
+                            if !(1..=MAX_THUMBNAIL_SIZE).contains(&width)
+                                || !(1..=MAX_THUMBNAIL_SIZE).contains(&height)
+                            {
+                                return error(
+                                    StatusCode::BAD_REQUEST,
+                                    &format!(
+                                        "width and height must be between 1 and {}",
+                                        MAX_THUMBNAIL_SIZE
+                                    ),
+                                );
+                            }
+                            // Without state there is no translation to wait for
+                            if state_inner.is_some()
+                                && let Err(message) = translated(state_inner.as_ref(), &urn)
+                            {
+                                return error(StatusCode::NOT_FOUND, &message);
+                            }
+                            match artifacts::thumbnail(&decode_urn(&urn), width, height) {
+                                Ok(png) => {
+                                    (StatusCode::OK, [(header::CONTENT_TYPE, "image/png")], png)
+                                        .into_response()
+                                }
+                                Err(e) => error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()),
+                            }
+                        }
+                    },
+                ),
+            )
+            .overrides_spec(),
+        );
+
+        let md_state = context.state.clone();
+        let size = context.config.derivative_size;
+        routes.push(PackRoute::new(
+            "/modelderivative/v2/designdata/:urn/manifest/:derivative_urn",
+            HttpMethod::Get,
+            get(move |Path((urn, derivative_urn)): Path<(String, String)>| {
+                let state_inner = md_state.clone();
+                async move {
+                    if let Err(message) = translated(state_inner.as_ref(), &urn) {
+                        return error(StatusCode::NOT_FOUND, &message);
+                    }
+                    placeholder(&derivative_urn, size)
+                }
+            }),
+        ));
+
+        let md_state = context.state.clone();
+        routes.push(PackRoute::new(
+            "/modelderivative/v2/designdata/:urn/manifest/:derivative_urn/signedcookies",
+            HttpMethod::Get,
+            get(
+                move |Path((urn, derivative_urn)): Path<(String, String)>,
+                      headers: HeaderMap| {
+                    let state_inner = md_state.clone();
+                    async move {
+                        let state_manager = match translated(state_inner.as_ref(), &urn) {
+                            Ok(state_manager) => state_manager,
+                            Err(message) => return error(StatusCode::NOT_FOUND, &message),
+                        };
+                        let host = headers
+                            .get(header::HOST)
+                            .and_then(|h| h.to_str().ok())
+                            .unwrap_or("localhost");
+                        let expiration = state_manager.clock.now_millis() + SIGNED_COOKIE_TTL_MILLIS;
+                        let body = json!({
+                            "etag": format!("{:x}", xxhash_rust::xxh3::xxh3_64(derivative_urn.as_bytes())),
+                            "size": size,
+                            "url": format!(
+                                "http://{}/__mock/derivatives/{}/{}",
+                                host,
+                                urn,
+                                form_urlencoded::byte_serialize(derivative_urn.as_bytes())
+                                    .collect::<String>()
+                            ),
+                            "content-type": content_type(&derivative_urn),
+                            "expiration": expiration
+                        });
+                        let mut response = (StatusCode::OK, JsonResponse(body)).into_response();
+                        for (name, value) in [
+                            ("CloudFront-Policy", "mock-policy"),
+                            ("CloudFront-Key-Pair-Id", "mock-key-pair"),
+                            ("CloudFront-Signature", "mock-signature"),
+                        ] {
+                            let cookie = format!("{}={}; Path=/; Secure; HttpOnly", name, value);
+                            if let Ok(cookie) = HeaderValue::from_str(&cookie) {
+                                response.headers_mut().append(header::SET_COOKIE, cookie);
+                            }
+                        }
+                        response
+                    }
+                },
+            ),
+        ));
+
+        // Target of the signed-cookie URLs handed out above
+        let md_state = context.state.clone();
+        routes.push(
+            PackRoute::new(
+                "/__mock/derivatives/:urn/:derivative_urn",
+                HttpMethod::Get,
+                get(
+                    move |Path((urn, derivative_urn)): Path<(String, String)>,
+                          headers: HeaderMap| {
+                        let state_inner = md_state.clone();
+                        async move {
+                            let signed = headers
+                                .get_all(header::COOKIE)
+                                .iter()
+                                .filter_map(|h| h.to_str().ok())
+                                .any(|cookies| cookies.contains("CloudFront-Signature="));
+                            if !signed {
+                                return error(StatusCode::FORBIDDEN, "Missing signed cookies");
+                            }
+                            if let Err(message) = translated(state_inner.as_ref(), &urn) {
+                                return error(StatusCode::NOT_FOUND, &message);
+                            }
+                            placeholder(&derivative_urn, size)
+                        }
+                    },
+                ),
+            )
+            .public(),
+        );
+
+        routes
+    }
+}
+
+/// Side of thumbnails requested without a `width`, in pixels
+const DEFAULT_THUMBNAIL_SIZE: u32 = 200;
+
+/// Largest thumbnail side APS serves, in pixels
+const MAX_THUMBNAIL_SIZE: u32 = 400;
+
+#[derive(Debug, Deserialize)]
+struct ThumbnailQuery {
+    width: Option<u32>,
+    /// The width when absent
+    height: Option<u32>,
+}
+
+/// How long signed cookies for a derivative download stay valid
+const SIGNED_COOKIE_TTL_MILLIS: i64 = 60 * 60 * 1000;
+
+/// Size of the chunks generated derivative files are streamed in
+const PLACEHOLDER_CHUNK: usize = 64 * 1024;
+
+/// A design URN as sent (URL-safe base64 without padding, or standard
+/// base64), decoded; the URN itself when it does not decode
+fn decode_urn(urn: &str) -> String {
+    match base64::engine::general_purpose::STANDARD
+        .decode(urn)
+        .or_else(|_| base64::engine::general_purpose::URL_SAFE_NO_PAD.decode(urn))
+    {
+        Ok(bytes) => String::from_utf8_lossy(&bytes).to_string(),
+        Err(_) => urn.to_string(),
+    }
+}
+
+/// Translation of `urn`, registered under the URN as the job was posted
+/// with, encoded or not
+pub(super) fn find_translation(state: &StateManager, urn: &str) -> Option<TranslationJob> {
+    state
+        .translations
+        .get_job(urn)
+        .or_else(|| state.translations.get_job(&decode_urn(urn)))
+}
+
+/// URN of the derivative resource a successful translation of `urn` lists
+fn derivative_urn(urn: &str) -> String {
+    format!("urn:adsk.viewing:fs.file:{}/output/0/0.svf", urn)
+}
+
+fn content_type(derivative_urn: &str) -> String {
+    mime_guess::from_path(derivative_urn)
+        .first_or_octet_stream()
+        .to_string()
+}
+
+fn error(status: StatusCode, message: &str) -> Response {
+    (status, JsonResponse(json!({ "diagnostic": message }))).into_response()
+}
+
+/// The state, when `urn` has a successful translation to download from;
+/// why not otherwise
+fn translated<'a>(state: Option<&'a StateManager>, urn: &str) -> Result<&'a StateManager, String> {
+    let Some(state) = state else {
+        return Err("No translations without state".to_string());
+    };
+    match find_translation(state, urn) {
+        Some(job) if job.status == TranslationStatus::Success => Ok(state),
+        Some(_) => Err(format!("Translation of {} has not succeeded", urn)),
+        None => Err(format!("Translation job for URN {} not found", urn)),
+    }
+}
+
+/// A generated file of `size` bytes standing in for a derivative, streamed
+/// in chunks so large sizes are not held in memory
+fn placeholder(derivative_urn: &str, size: u64) -> Response {
+    let line = format!("raps-mock placeholder for {}\n", derivative_urn);
+    let chunk = Bytes::from(
+        line.bytes()
+            .cycle()
+            .take(PLACEHOLDER_CHUNK)
+            .collect::<Vec<u8>>(),
+    );
+    let chunk_size = PLACEHOLDER_CHUNK as u64;
+    let chunks = size.div_ceil(chunk_size);
+    let body = Body::from_stream(futures_util::stream::iter((0..chunks).map(move |index| {
+        let len = (size - index * chunk_size).min(chunk_size) as usize;
+        Ok::<_, Infallible>(chunk.slice(..len))
+    })));
+    (
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, content_type(derivative_urn)),
+            (header::CONTENT_LENGTH, size.to_string()),
+        ],
+        body,
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::MockServerConfig;
+    use crate::state::StateManager;
+    use crate::state::jobs::JobOutcome;
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode, header};
+    use base64::Engine as _;
+    use serde_json::Value;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_derivatives_download_directly_and_with_signed_cookies() {
+        let state = StateManager::new();
+        let config = MockServerConfig {
+            derivative_size: 100_000,
+            ..MockServerConfig::default()
+        };
+        let (router, _) = crate::server::router::build_router(
+            Vec::new(),
+            Vec::new(),
+            Some(state.clone()),
+            &config,
+            &[],
+        )
+        .unwrap();
+        let token = state.auth.generate_token("client", 3600, None);
+        let bearer = format!("Bearer {}", token.access_token);
+        let get = |uri: String, cookie: Option<&str>| {
+            let router = router.clone();
+            let mut request = Request::get(uri)
+                .header("authorization", &bearer)
+                .header("host", "mock.test");
+            if let Some(cookie) = cookie {
+                request = request.header(header::COOKIE, cookie);
+            }
+            let request = request.body(Body::empty()).unwrap();
+            async move {
+                let response = router.oneshot(request).await.unwrap();
+                let status = response.status();
+                let headers = response.headers().clone();
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                (status, headers, body)
+            }
+        };
+
+        let urn = base64::engine::general_purpose::URL_SAFE_NO_PAD
+            .encode("urn:adsk.objects:os.object:bucket/tower.rvt");
+        let job = state.translations.create_job(urn.clone());
+        let download = |derivative: &str| {
+            format!(
+                "/modelderivative/v2/designdata/{}/manifest/{}",
+                urn,
+                form_urlencoded::byte_serialize(derivative.as_bytes()).collect::<String>()
+            )
+        };
+        let (status, _, _) = get(download("urn:adsk.viewing:fs.file:x"), None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        state.jobs.settle(&job.job_id, JobOutcome::Success);
+        let (_, _, manifest) = get(
+            format!("/modelderivative/v2/designdata/{}/manifest", urn),
+            None,
+        )
+        .await;
+        let manifest: Value = serde_json::from_slice(&manifest).unwrap();
+        let derivative = manifest["derivatives"][0]["children"][0]["children"][0]["urn"]
+            .as_str()
+            .unwrap()
+            .to_string();
+
+        let (status, headers, body) = get(download(&derivative), None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(headers[header::CONTENT_LENGTH], "100000");
+        assert_eq!(body.len(), 100_000);
+
+        let (status, headers, cookies) =
+            get(format!("{}/signedcookies", download(&derivative)), None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(headers.get_all(header::SET_COOKIE).iter().count(), 3);
+        let cookies: Value = serde_json::from_slice(&cookies).unwrap();
+        assert_eq!(cookies["size"], 100_000);
+        let url = cookies["url"].as_str().unwrap();
+        let path = url.strip_prefix("http://mock.test").unwrap().to_string();
+
+        let (status, _, _) = get(path.clone(), None).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, _, body) = get(path, Some("CloudFront-Signature=mock-signature")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body.len(), 100_000);
+
+        let thumbnail = format!("/modelderivative/v2/designdata/{}/thumbnail", urn);
+        let (status, headers, png) = get(format!("{}?width=100", thumbnail), None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(headers[header::CONTENT_TYPE], "image/png");
+        assert_eq!(&png[16..24], &[0, 0, 0, 100, 0, 0, 0, 100]);
+        let (status, _, _) = get(format!("{}?width=4000", thumbnail), None).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+}
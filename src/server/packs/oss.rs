@@ -0,0 +1,623 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+//! OSS buckets, objects and signed S3 uploads.
+
+use axum::{
+    body::Bytes,
+    extract::{Json, Path, Query},
+    http::HeaderMap,
+    response::{IntoResponse, Json as JsonResponse},
+    routing::{get, post, put},
+};
+use serde_json::{Value, json};
+
+use crate::config::Service;
+use crate::middleware::determinism::{self, RequestFingerprint};
+use crate::openapi::types::HttpMethod;
+use crate::server::packs::{PackContext, PackRoute, RoutePack, StateRequirement};
+
+/// Built-in OSS buckets, objects and signed S3 uploads
+#[derive(Debug, Default)]
+pub struct OssPack;
+
+impl RoutePack for OssPack {
+    fn name(&self) -> &str {
+        "oss"
+    }
+
+    fn state_requirement(&self) -> StateRequirement {
+        StateRequirement::Service(Service::Oss)
+    }
+
+    fn routes(&self, context: &PackContext<'_>) -> Vec<PackRoute> {
+        let mut routes = Vec::new();
+
+        let oss_state = context.state.clone();
+        routes.push(
+            PackRoute::new(
+                "/oss/v2/buckets",
+                HttpMethod::Get,
+                get(move || {
+                    let state_inner = oss_state.clone();
+                    async move {
+                        if let Some(ref state_manager) = state_inner {
+                            let buckets = state_manager.buckets.list_buckets();
+                            let items: Vec<Value> = buckets
+                                .into_iter()
+                                .map(|b| {
+                                    json!({
+                                        "bucketKey": b.bucket_key,
+                                        "createdDate": b.created_date,
+                                        "policyKey": b.policy_key
+                                    })
+                                })
+                                .collect();
+                            (
+                                axum::http::StatusCode::OK,
+                                JsonResponse(json!({ "items": items })),
+                            )
+                                .into_response()
+                        } else {
+                            (
+                                axum::http::StatusCode::OK,
+                                JsonResponse(json!({ "items": [] })),
+                            )
+                                .into_response()
+                        }
+                    }
+                }),
+            )
+            .overrides_spec(),
+        );
+
+        let oss_state = context.state.clone();
+        routes.push(PackRoute::new(
+            "/oss/v2/buckets",
+            HttpMethod::Post,
+            post(
+                move |fingerprint: Option<axum::Extension<RequestFingerprint>>,
+                      Json(body_value): Json<Value>| {
+                    let state_inner = oss_state.clone();
+                    async move {
+                        if let Some(ref state_manager) = state_inner {
+                            let bucket_key = body_value
+                                .get("bucketKey")
+                                .and_then(|v| v.as_str())
+                                .unwrap_or("default-bucket");
+
+                            let policy_key = body_value
+                                .get("policyKey")
+                                .and_then(|v| v.as_str())
+                                .unwrap_or("transient");
+
+                            let bucket = state_manager
+                                .buckets
+                                .create_bucket(bucket_key.to_string(), policy_key.to_string());
+
+                            (axum::http::StatusCode::OK, JsonResponse(json!(bucket))).into_response()
+                        } else {
+                            (
+                            axum::http::StatusCode::OK,
+                            JsonResponse(json!({
+                                "bucketKey": "mock-bucket",
+                                "createdDate": determinism::now(fingerprint.as_deref()).timestamp_millis(),
+                                "policyKey": "transient"
+                            })),
+                        )
+                            .into_response()
+                        }
+                    }
+                },
+            ),
+        ).overrides_spec());
+
+        let oss_state = context.state.clone();
+        routes.push(
+            PackRoute::new(
+                "/oss/v2/buckets/:bucket_key/objects",
+                HttpMethod::Get,
+                get(move |Path(bucket_key): Path<String>| {
+                    let state_inner = oss_state.clone();
+                    async move {
+                        if let Some(ref state_manager) = state_inner {
+                            let objects = state_manager.objects.list_objects(&bucket_key);
+                            let items: Vec<Value> = objects
+                                .into_iter()
+                                .map(|o| {
+                                    json!({
+                                        "bucketKey": o.bucket_key,
+                                        "objectKey": o.object_key,
+                                        "objectId": o.object_id,
+                                        "sha1": o.sha1,
+                                        "size": o.size,
+                                        "contentType": o.content_type,
+                                        "location": o.location
+                                    })
+                                })
+                                .collect();
+                            (
+                                axum::http::StatusCode::OK,
+                                JsonResponse(json!({ "items": items })),
+                            )
+                                .into_response()
+                        } else {
+                            (
+                                axum::http::StatusCode::OK,
+                                JsonResponse(json!({ "items": [] })),
+                            )
+                                .into_response()
+                        }
+                    }
+                }),
+            )
+            .overrides_spec(),
+        );
+
+        // Object content is stored as sent: any Content-Type is accepted and
+        // recorded as declared, the body is never parsed
+        let oss_state = context.state.clone();
+        routes.push(
+            PackRoute::new(
+                "/oss/v2/buckets/:bucket_key/objects/:object_key",
+                HttpMethod::Put,
+                put(
+                    move |Path((bucket_key, object_key)): Path<(String, String)>,
+                          headers: HeaderMap,
+                          body: Bytes| {
+                        let state_inner = oss_state.clone();
+                        async move {
+                            let content_type = headers
+                                .get(axum::http::header::CONTENT_TYPE)
+                                .and_then(|v| v.to_str().ok())
+                                .map(|s| s.to_string());
+                            let metadata = metadata_from_headers(&headers);
+
+                            if let Some(ref state_manager) = state_inner {
+                                let object = state_manager.objects.put_object(
+                                    bucket_key,
+                                    object_key,
+                                    body,
+                                    content_type,
+                                    metadata,
+                                );
+                                (
+                                    axum::http::StatusCode::OK,
+                                    JsonResponse(object_json(&object)),
+                                )
+                                    .into_response()
+                            } else {
+                                (
+                                axum::http::StatusCode::OK,
+                                JsonResponse(json!({
+                                    "bucketKey": bucket_key,
+                                    "objectKey": object_key,
+                                    "objectId": format!(
+                                        "urn:adsk.objects:os.object:{}/{}",
+                                        bucket_key, object_key
+                                    ),
+                                    "size": body.len(),
+                                    "contentType": content_type
+                                        .unwrap_or_else(|| "application/octet-stream".to_string())
+                                })),
+                            )
+                                .into_response()
+                            }
+                        }
+                    },
+                ),
+            )
+            .overrides_spec(),
+        );
+
+        let oss_state = context.state.clone();
+        routes.push(
+            PackRoute::new(
+                "/oss/v2/buckets/:bucket_key/objects/:object_key",
+                HttpMethod::Get,
+                get(
+                    move |Path((bucket_key, object_key)): Path<(String, String)>| {
+                        let state_inner = oss_state.clone();
+                        async move {
+                            let Some(ref state_manager) = state_inner else {
+                                return axum::http::StatusCode::OK.into_response();
+                            };
+                            match (
+                                state_manager.objects.get_object(&bucket_key, &object_key),
+                                state_manager.objects.get_content(&bucket_key, &object_key),
+                            ) {
+                                (Some(object), Some(content)) => {
+                                    let mut response = (
+                                        axum::http::StatusCode::OK,
+                                        [(
+                                            axum::http::header::CONTENT_TYPE,
+                                            object.content_type.clone(),
+                                        )],
+                                        content,
+                                    )
+                                        .into_response();
+                                    append_metadata_headers(&mut response, &object.metadata);
+                                    response
+                                }
+                                _ => object_not_found(&bucket_key, &object_key),
+                            }
+                        }
+                    },
+                ),
+            )
+            .overrides_spec(),
+        );
+
+        let oss_state = context.state.clone();
+        routes.push(
+            PackRoute::new(
+                "/oss/v2/buckets/:bucket_key/objects/:object_key/details",
+                HttpMethod::Get,
+                get(
+                    move |Path((bucket_key, object_key)): Path<(String, String)>| {
+                        let state_inner = oss_state.clone();
+                        async move {
+                            if let Some(ref state_manager) = state_inner {
+                                match state_manager.objects.get_object(&bucket_key, &object_key) {
+                                    Some(object) => {
+                                        let mut response = (
+                                            axum::http::StatusCode::OK,
+                                            JsonResponse(object_json(&object)),
+                                        )
+                                            .into_response();
+                                        append_metadata_headers(&mut response, &object.metadata);
+                                        response
+                                    }
+                                    None => object_not_found(&bucket_key, &object_key),
+                                }
+                            } else {
+                                (
+                                    axum::http::StatusCode::OK,
+                                    JsonResponse(json!({
+                                        "bucketKey": bucket_key,
+                                        "objectKey": object_key,
+                                        "size": 0,
+                                        "contentType": "application/octet-stream"
+                                    })),
+                                )
+                                    .into_response()
+                            }
+                        }
+                    },
+                ),
+            )
+            .overrides_spec(),
+        );
+
+        // OSS signed S3 uploads (single and multipart)
+        let oss_state = context.state.clone();
+        let upload_expiration_secs = context.config.upload_expiration_secs;
+        routes.push(
+            PackRoute::new(
+                "/oss/v2/buckets/:bucket_key/objects/:object_key/signeds3upload",
+                HttpMethod::Get,
+                get(
+                    move |Path((bucket_key, object_key)): Path<(String, String)>,
+                          Query(query): Query<std::collections::HashMap<String, String>>,
+                          fingerprint: Option<axum::Extension<RequestFingerprint>>,
+                          headers: HeaderMap| {
+                        let state_inner = oss_state.clone();
+                        async move {
+                            let fingerprint = fingerprint.as_deref();
+                            let parts: u32 = query
+                                .get("parts")
+                                .and_then(|v| v.parse().ok())
+                                .unwrap_or(1)
+                                .clamp(1, 25);
+                            let first_part: u32 = query
+                                .get("firstPart")
+                                .and_then(|v| v.parse().ok())
+                                .unwrap_or(1)
+                                .max(1);
+                            let minutes_expiration: i64 = query
+                                .get("minutesExpiration")
+                                .and_then(|v| v.parse().ok())
+                                .unwrap_or(2)
+                                .clamp(1, 60);
+
+                            let (upload_key, expires_at) = if let Some(ref state_manager) =
+                                state_inner
+                            {
+                                let existing = query
+                                    .get("uploadKey")
+                                    .and_then(|key| state_manager.uploads.get_session(key));
+                                let session = match existing {
+                                    Some(session) => state_manager
+                                        .uploads
+                                        .extend_parts(&session.upload_key, first_part + parts - 1)
+                                        .unwrap_or(session),
+                                    None => state_manager.uploads.create_session(
+                                        bucket_key,
+                                        object_key,
+                                        first_part + parts - 1,
+                                        upload_expiration_secs,
+                                    ),
+                                };
+                                (session.upload_key, session.expires_at)
+                            } else {
+                                (
+                                    determinism::uuid(fingerprint).simple().to_string(),
+                                    determinism::now(fingerprint).timestamp_millis()
+                                        + (upload_expiration_secs as i64) * 1000,
+                                )
+                            };
+
+                            let host = headers
+                                .get(axum::http::header::HOST)
+                                .and_then(|h| h.to_str().ok())
+                                .unwrap_or("localhost");
+                            let urls: Vec<String> = (first_part..first_part + parts)
+                                .map(|part| {
+                                    format!("http://{}/__mock/s3/{}/{}", host, upload_key, part)
+                                })
+                                .collect();
+                            let url_expiration = determinism::now(fingerprint)
+                                + chrono::Duration::minutes(minutes_expiration);
+
+                            (
+                                axum::http::StatusCode::OK,
+                                JsonResponse(json!({
+                                    "uploadKey": upload_key,
+                                    "uploadExpiration": millis_to_rfc3339(expires_at),
+                                    "urlExpiration": url_expiration.to_rfc3339(),
+                                    "urls": urls
+                                })),
+                            )
+                                .into_response()
+                        }
+                    },
+                ),
+            )
+            .overrides_spec(),
+        );
+
+        let oss_state = context.state.clone();
+        routes.push(
+            PackRoute::new(
+                "/oss/v2/buckets/:bucket_key/objects/:object_key/signeds3upload",
+                HttpMethod::Post,
+                post(
+                    move |Path((bucket_key, object_key)): Path<(String, String)>,
+                          headers: HeaderMap,
+                          Json(body_value): Json<Value>| {
+                        let state_inner = oss_state.clone();
+                        async move {
+                            let upload_key = body_value
+                                .get("uploadKey")
+                                .and_then(|v| v.as_str())
+                                .unwrap_or_default()
+                                .to_string();
+
+                            if let Some(ref state_manager) = state_inner {
+                                let session = match state_manager.uploads.get_session(&upload_key) {
+                                    Some(session)
+                                        if session.bucket_key == bucket_key
+                                            && session.object_key == object_key =>
+                                    {
+                                        session
+                                    }
+                                    _ => {
+                                        return (
+                                            axum::http::StatusCode::BAD_REQUEST,
+                                            JsonResponse(json!({
+                                                "reason": format!(
+                                                    "Upload key {} is invalid or has expired",
+                                                    upload_key
+                                                )
+                                            })),
+                                        )
+                                            .into_response();
+                                    }
+                                };
+                                state_manager.uploads.complete_session(&session.upload_key);
+
+                                let metadata = metadata_from_headers(&headers);
+                                let content_type = metadata.get("content-type").cloned();
+                                let mut object = state_manager.objects.upload_object(
+                                    bucket_key,
+                                    object_key,
+                                    session.uploaded_size(),
+                                    content_type,
+                                );
+                                if !metadata.is_empty() {
+                                    object.metadata = metadata;
+                                    state_manager.objects.insert_object(object.clone());
+                                }
+                                (
+                                    axum::http::StatusCode::OK,
+                                    JsonResponse(object_json(&object)),
+                                )
+                                    .into_response()
+                            } else {
+                                (
+                                    axum::http::StatusCode::OK,
+                                    JsonResponse(json!({
+                                        "bucketKey": bucket_key,
+                                        "objectKey": object_key,
+                                        "objectId": format!(
+                                            "urn:adsk.objects:os.object:{}/{}",
+                                            bucket_key, object_key
+                                        ),
+                                        "size": 0,
+                                        "contentType": "application/octet-stream"
+                                    })),
+                                )
+                                    .into_response()
+                            }
+                        }
+                    },
+                ),
+            )
+            .overrides_spec(),
+        );
+
+        // Target of the signed URLs handed out above, standing in for S3
+        let s3_state = context.state.clone();
+        routes.push(PackRoute::new(
+            "/__mock/s3/:upload_key/:part",
+            HttpMethod::Put,
+            put(
+                move |Path((upload_key, part)): Path<(String, u32)>, body: Bytes| {
+                    let state_inner = s3_state.clone();
+                    async move {
+                        if let Some(ref state_manager) = state_inner
+                            && !state_manager.uploads.record_part(
+                                &upload_key,
+                                part,
+                                body.len() as u64,
+                            )
+                        {
+                            return (
+                                axum::http::StatusCode::FORBIDDEN,
+                                "Request has expired or the upload key is unknown",
+                            )
+                                .into_response();
+                        }
+                        axum::http::StatusCode::OK.into_response()
+                    }
+                },
+            ),
+        ));
+
+        routes
+    }
+}
+
+fn millis_to_rfc3339(millis: i64) -> String {
+    chrono::DateTime::from_timestamp_millis(millis)
+        .unwrap_or_default()
+        .to_rfc3339()
+}
+
+fn metadata_from_headers(headers: &HeaderMap) -> std::collections::BTreeMap<String, String> {
+    use crate::state::objects::METADATA_HEADER_PREFIX;
+
+    headers
+        .iter()
+        .filter_map(|(name, value)| {
+            let key = name.as_str().strip_prefix(METADATA_HEADER_PREFIX)?;
+            Some((key.to_string(), value.to_str().ok()?.to_string()))
+        })
+        .collect()
+}
+
+fn append_metadata_headers(
+    response: &mut axum::response::Response,
+    metadata: &std::collections::BTreeMap<String, String>,
+) {
+    use crate::state::objects::METADATA_HEADER_PREFIX;
+
+    for (key, value) in metadata {
+        let name = format!("{}{}", METADATA_HEADER_PREFIX, key);
+        if let (Ok(name), Ok(value)) = (
+            axum::http::HeaderName::try_from(name),
+            axum::http::HeaderValue::from_str(value),
+        ) {
+            response.headers_mut().insert(name, value);
+        }
+    }
+}
+
+fn object_json(object: &crate::state::objects::ObjectInfo) -> Value {
+    json!({
+        "bucketKey": object.bucket_key,
+        "objectKey": object.object_key,
+        "objectId": object.object_id,
+        "sha1": object.sha1,
+        "size": object.size,
+        "contentType": object.content_type,
+        "location": object.location,
+        "metadata": object.metadata
+    })
+}
+
+fn object_not_found(bucket_key: &str, object_key: &str) -> axum::response::Response {
+    (
+        axum::http::StatusCode::NOT_FOUND,
+        JsonResponse(json!({
+            "reason": format!("Object {} not found in bucket {}", object_key, bucket_key)
+        })),
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::MockServerConfig;
+    use crate::state::StateManager;
+    use axum::body::Body;
+    use axum::http::{Method, Request, StatusCode};
+    use serde_json::Value;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_object_metadata_round_trips() {
+        let state = StateManager::new();
+        let (router, _) = crate::server::router::build_router(
+            Vec::new(),
+            Vec::new(),
+            Some(state.clone()),
+            &MockServerConfig::default(),
+            &[],
+        )
+        .unwrap();
+        let token = state.auth.generate_token("client", 3600, None).access_token;
+        let call = |method: Method, uri: &str, metadata: &[(&str, &str)]| {
+            let mut request = Request::builder()
+                .method(method)
+                .uri(uri)
+                .header("authorization", format!("Bearer {}", token))
+                .header("content-type", "text/plain");
+            for (name, value) in metadata {
+                request = request.header(*name, *value);
+            }
+            let request = request.body(Body::from("plan")).unwrap();
+            router.clone().oneshot(request)
+        };
+        let object = "/oss/v2/buckets/b1/objects/plan.txt";
+
+        let uploaded = call(
+            Method::PUT,
+            object,
+            &[
+                ("x-ads-meta-project", "tower"),
+                ("x-ads-meta-revision", "3"),
+            ],
+        )
+        .await
+        .unwrap();
+        assert_eq!(uploaded.status(), StatusCode::OK);
+        let metadata = state.objects.get_object("b1", "plan.txt").unwrap().metadata;
+        assert_eq!(metadata["project"], "tower");
+
+        for method in [Method::GET, Method::HEAD] {
+            let response = call(method.clone(), object, &[]).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK, "{}", method);
+            assert_eq!(response.headers()["x-ads-meta-project"], "tower");
+            assert_eq!(response.headers()["x-ads-meta-revision"], "3");
+        }
+        let content = call(Method::GET, object, &[]).await.unwrap();
+        let content = axum::body::to_bytes(content.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(&content[..], b"plan");
+
+        let details = call(Method::GET, &format!("{}/details", object), &[])
+            .await
+            .unwrap();
+        assert_eq!(details.headers()["x-ads-meta-project"], "tower");
+        let details = axum::body::to_bytes(details.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let details: Value = serde_json::from_slice(&details).unwrap();
+        assert_eq!(
+            details["metadata"],
+            serde_json::json!({ "project": "tower", "revision": "3" })
+        );
+    }
+}
@@ -0,0 +1,549 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+//! Webhook subscriptions.
+//!
+//! Hooks are listed per event or for the whole app (the hooks created with
+//! the caller's tokens, plus those created without one), and addressed
+//! individually under the system and event they were created for. Listings
+//! have the Webhooks API's `links`/`data` shape, all on one page.
+//!
+//! `/webhooks/v1/tokens` manages the secret notifications to the calling
+//! app's hooks are signed with, the same one `/__admin/webhooks/secrets`
+//! sets.
+
+use axum::{
+    extract::{Json, Path},
+    http::HeaderMap,
+    response::{IntoResponse, Json as JsonResponse, Response},
+    routing::{delete, get, patch, post},
+};
+use serde_json::{Value, json};
+
+use crate::config::Service;
+use crate::openapi::types::HttpMethod;
+use crate::server::packs::{PackContext, PackRoute, RoutePack, StateRequirement, request_client};
+use crate::state::webhooks::{STATUS_ACTIVE, STATUS_INACTIVE, WebhookSubscription};
+use crate::webhooks;
+
+/// Built-in Webhook subscriptions
+#[derive(Debug, Default)]
+pub struct WebhooksPack;
+
+impl RoutePack for WebhooksPack {
+    fn name(&self) -> &str {
+        "webhooks"
+    }
+
+    fn state_requirement(&self) -> StateRequirement {
+        StateRequirement::Service(Service::Webhooks)
+    }
+
+    fn routes(&self, context: &PackContext<'_>) -> Vec<PackRoute> {
+        let mut routes = Vec::new();
+
+        let webhooks_state = context.state.clone();
+        routes.push(
+            PackRoute::new(
+                "/webhooks/v1/hooks",
+                HttpMethod::Get,
+                get(move |headers: HeaderMap| {
+                    let state_inner = webhooks_state.clone();
+                    async move {
+                        let Some(ref state_manager) = state_inner else {
+                            return hooks_page(Vec::new());
+                        };
+                        let client = request_client(state_manager, &headers);
+                        hooks_page(
+                            state_manager
+                                .webhooks
+                                .list_subscriptions()
+                                .into_iter()
+                                .filter(|s| s.client_id.is_none() || s.client_id == client)
+                                .collect(),
+                        )
+                    }
+                }),
+            )
+            .overrides_spec(),
+        );
+
+        let webhooks_state = context.state.clone();
+        routes.push(
+            PackRoute::new(
+                "/webhooks/v1/systems/:system/events/:event/hooks",
+                HttpMethod::Get,
+                get(move |Path((system, event)): Path<(String, String)>| {
+                    let state_inner = webhooks_state.clone();
+                    async move {
+                        let Some(ref state_manager) = state_inner else {
+                            return hooks_page(Vec::new());
+                        };
+                        hooks_page(
+                            state_manager
+                                .webhooks
+                                .list_subscriptions()
+                                .into_iter()
+                                .filter(|s| s.system == system && s.event == event)
+                                .collect(),
+                        )
+                    }
+                }),
+            )
+            .overrides_spec(),
+        );
+
+        let webhooks_state = context.state.clone();
+        routes.push(
+            PackRoute::new(
+                "/webhooks/v1/systems/:system/events/:event/hooks",
+                HttpMethod::Post,
+                post(
+                    move |Path((system, event)): Path<(String, String)>,
+                          headers: HeaderMap,
+                          Json(body_value): Json<Value>| {
+                        let state_inner = webhooks_state.clone();
+                        async move {
+                            if let Some(ref state_manager) = state_inner {
+                                let callback_url = body_value
+                                    .get("callbackUrl")
+                                    .and_then(|v| v.as_str())
+                                    .unwrap_or("https://example.com/webhook")
+                                    .to_string();
+
+                                let scope = crate::state::webhooks::WebhookScope {
+                                    folder: body_value
+                                        .get("scope")
+                                        .and_then(|s| s.get("folder"))
+                                        .and_then(|v| v.as_str())
+                                        .map(|s| s.to_string()),
+                                    project: body_value
+                                        .get("scope")
+                                        .and_then(|s| s.get("project"))
+                                        .and_then(|v| v.as_str())
+                                        .map(|s| s.to_string()),
+                                    workflow: body_value
+                                        .get("scope")
+                                        .and_then(|s| s.get("workflow"))
+                                        .and_then(|v| v.as_str())
+                                        .map(|s| s.to_string()),
+                                };
+
+                                let mut subscription = state_manager.webhooks.create_subscription(
+                                    system.clone(),
+                                    event,
+                                    system,
+                                    callback_url,
+                                    scope,
+                                    body_value.get("hookAttribute").cloned(),
+                                );
+                                let client_id = request_client(state_manager, &headers);
+                                let filter = body_value
+                                    .get("filter")
+                                    .and_then(|v| v.as_str())
+                                    .map(|s| s.to_string());
+                                if client_id.is_some() || filter.is_some() {
+                                    subscription.client_id = client_id;
+                                    subscription.filter = filter;
+                                    state_manager
+                                        .webhooks
+                                        .insert_subscription(subscription.clone());
+                                }
+
+                                (
+                                    axum::http::StatusCode::CREATED,
+                                    JsonResponse(webhooks::hook_json(&subscription)),
+                                )
+                                    .into_response()
+                            } else {
+                                (
+                                    axum::http::StatusCode::CREATED,
+                                    JsonResponse(json!({
+                                        "hookId": "mock-hook-id",
+                                        "status": "active"
+                                    })),
+                                )
+                                    .into_response()
+                            }
+                        }
+                    },
+                ),
+            )
+            .overrides_spec(),
+        );
+
+        let webhooks_state = context.state.clone();
+        routes.push(
+            PackRoute::new(
+                "/webhooks/v1/systems/:system/events/:event/hooks/:hook_id",
+                HttpMethod::Get,
+                get(
+                    move |Path((system, event, hook_id)): Path<(String, String, String)>| {
+                        let state_inner = webhooks_state.clone();
+                        async move {
+                            let Some(ref state_manager) = state_inner else {
+                                return (
+                                    axum::http::StatusCode::OK,
+                                    JsonResponse(json!({ "hookId": hook_id, "status": "active" })),
+                                )
+                                    .into_response();
+                            };
+                            match find_hook(state_manager, &system, &event, &hook_id) {
+                                Some(subscription) => (
+                                    axum::http::StatusCode::OK,
+                                    JsonResponse(webhooks::hook_json(&subscription)),
+                                )
+                                    .into_response(),
+                                None => hook_not_found(&hook_id),
+                            }
+                        }
+                    },
+                ),
+            )
+            .overrides_spec(),
+        );
+
+        // Deactivates and reactivates hooks (e.g. after too many failed
+        // deliveries) and updates their attribute and filter
+        let webhooks_state = context.state.clone();
+        routes.push(
+            PackRoute::new(
+                "/webhooks/v1/systems/:system/events/:event/hooks/:hook_id",
+                HttpMethod::Patch,
+                patch(
+                    move |Path((system, event, hook_id)): Path<(String, String, String)>,
+                          Json(body_value): Json<Value>| {
+                        let state_inner = webhooks_state.clone();
+                        async move {
+                            let Some(ref state_manager) = state_inner else {
+                                return (axum::http::StatusCode::OK, JsonResponse(json!({})))
+                                    .into_response();
+                            };
+                            let Some(mut subscription) =
+                                find_hook(state_manager, &system, &event, &hook_id)
+                            else {
+                                return hook_not_found(&hook_id);
+                            };
+                            let active = match body_value.get("status").and_then(|v| v.as_str()) {
+                                Some(STATUS_ACTIVE) => Some(true),
+                                Some(STATUS_INACTIVE) => Some(false),
+                                Some(other) => {
+                                    return bad_request(format!(
+                                        "status must be '{}' or '{}', got '{}'",
+                                        STATUS_ACTIVE, STATUS_INACTIVE, other
+                                    ));
+                                }
+                                None => None,
+                            };
+                            let filter = match body_value.get("filter") {
+                                None => None,
+                                Some(Value::Null) => Some(None),
+                                Some(Value::String(filter)) => Some(Some(filter.clone())),
+                                Some(_) => {
+                                    return bad_request("filter must be a string or null".into());
+                                }
+                            };
+                            let attribute = body_value.get("hookAttribute");
+                            if attribute.is_some() || filter.is_some() {
+                                if let Some(attribute) = attribute {
+                                    subscription.hook_attribute = Some(attribute.clone());
+                                }
+                                if let Some(filter) = filter {
+                                    subscription.filter = filter;
+                                }
+                                state_manager
+                                    .webhooks
+                                    .insert_subscription(subscription.clone());
+                            }
+                            if let Some(active) = active
+                                && let Some(updated) =
+                                    state_manager.webhooks.set_status(&hook_id, active)
+                            {
+                                subscription = updated;
+                            }
+                            (
+                                axum::http::StatusCode::OK,
+                                JsonResponse(webhooks::hook_json(&subscription)),
+                            )
+                                .into_response()
+                        }
+                    },
+                ),
+            )
+            .overrides_spec(),
+        );
+
+        let webhooks_state = context.state.clone();
+        routes.push(
+            PackRoute::new(
+                "/webhooks/v1/systems/:system/events/:event/hooks/:hook_id",
+                HttpMethod::Delete,
+                delete(
+                    move |Path((system, event, hook_id)): Path<(String, String, String)>| {
+                        let state_inner = webhooks_state.clone();
+                        async move {
+                            if let Some(ref state_manager) = state_inner {
+                                if find_hook(state_manager, &system, &event, &hook_id).is_some()
+                                    && state_manager.webhooks.delete_subscription(&hook_id)
+                                {
+                                    (axum::http::StatusCode::NO_CONTENT, JsonResponse(json!({})))
+                                        .into_response()
+                                } else {
+                                    hook_not_found(&hook_id)
+                                }
+                            } else {
+                                (axum::http::StatusCode::NO_CONTENT, JsonResponse(json!({})))
+                                    .into_response()
+                            }
+                        }
+                    },
+                ),
+            )
+            .overrides_spec(),
+        );
+
+        for method in [HttpMethod::Post, HttpMethod::Put, HttpMethod::Delete] {
+            let webhooks_state = context.state.clone();
+            let handler = move |headers: HeaderMap, body: Option<Json<Value>>| {
+                let state_inner = webhooks_state.clone();
+                async move {
+                    let Some(ref state_manager) = state_inner else {
+                        return secret_changed(method);
+                    };
+                    let Some(client_id) = request_client(state_manager, &headers) else {
+                        return bad_request("A token of the app is required".into());
+                    };
+                    let token = body
+                        .as_ref()
+                        .and_then(|Json(body)| body.get("token"))
+                        .and_then(Value::as_str)
+                        .map(str::to_string);
+                    let hooks = &state_manager.webhooks;
+                    match (method, token) {
+                        (HttpMethod::Delete, _) => {
+                            if !hooks.remove_secret(&client_id) {
+                                return secret_not_found();
+                            }
+                        }
+                        (_, None) => return bad_request("token is required".into()),
+                        (_, Some(token)) if token.is_empty() || token.len() > MAX_SECRET_LEN => {
+                            return bad_request(format!(
+                                "token must be 1 to {} characters long",
+                                MAX_SECRET_LEN
+                            ));
+                        }
+                        (HttpMethod::Post, Some(token)) => {
+                            if hooks.secret(&client_id).is_some() {
+                                return bad_request(
+                                    "The app already has a secret token; update it with PUT".into(),
+                                );
+                            }
+                            hooks.set_secret(&client_id, token);
+                        }
+                        (_, Some(token)) => {
+                            if hooks.secret(&client_id).is_none() {
+                                return secret_not_found();
+                            }
+                            hooks.set_secret(&client_id, token);
+                        }
+                    }
+                    secret_changed(method)
+                }
+            };
+            let handler = match method {
+                HttpMethod::Post => post(handler),
+                HttpMethod::Put => axum::routing::put(handler),
+                _ => delete(handler),
+            };
+            routes.push(PackRoute::new("/webhooks/v1/tokens", method, handler).overrides_spec());
+        }
+
+        routes
+    }
+}
+
+/// Longest secret token the Webhooks API accepts
+const MAX_SECRET_LEN: usize = 50;
+
+/// Response to creating, updating or deleting the app's secret token
+fn secret_changed(method: HttpMethod) -> Response {
+    match method {
+        HttpMethod::Post => (
+            axum::http::StatusCode::OK,
+            JsonResponse(json!({
+                "status": 200,
+                "detail": ["Secret token successfully created"]
+            })),
+        )
+            .into_response(),
+        _ => axum::http::StatusCode::NO_CONTENT.into_response(),
+    }
+}
+
+fn secret_not_found() -> Response {
+    (
+        axum::http::StatusCode::NOT_FOUND,
+        JsonResponse(json!({
+            "reason": "The app has no secret token; create it with POST"
+        })),
+    )
+        .into_response()
+}
+
+/// The hook `hook_id`, if it was created for `system` and `event`
+fn find_hook(
+    state: &crate::state::StateManager,
+    system: &str,
+    event: &str,
+    hook_id: &str,
+) -> Option<WebhookSubscription> {
+    state
+        .webhooks
+        .get_subscription(hook_id)
+        .filter(|s| s.system == system && s.event == event)
+}
+
+/// A listing of hooks in the Webhooks API's paginated shape
+fn hooks_page(hooks: Vec<WebhookSubscription>) -> Response {
+    let data: Vec<Value> = hooks.iter().map(webhooks::hook_json).collect();
+    (
+        axum::http::StatusCode::OK,
+        JsonResponse(json!({
+            "links": { "next": null },
+            "data": data
+        })),
+    )
+        .into_response()
+}
+
+fn bad_request(reason: String) -> Response {
+    (
+        axum::http::StatusCode::BAD_REQUEST,
+        JsonResponse(json!({ "reason": reason })),
+    )
+        .into_response()
+}
+
+fn hook_not_found(hook_id: &str) -> Response {
+    (
+        axum::http::StatusCode::NOT_FOUND,
+        JsonResponse(json!({
+            "reason": format!("Webhook {} not found", hook_id)
+        })),
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::MockServerConfig;
+    use crate::state::StateManager;
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use serde_json::{Value, json};
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_hooks_are_listed_per_app_and_patched_by_id() {
+        let state = StateManager::new();
+        let (router, _) = crate::server::router::build_router(
+            Vec::new(),
+            Vec::new(),
+            Some(state.clone()),
+            &MockServerConfig::default(),
+            &[],
+        )
+        .unwrap();
+        let mine = state.auth.generate_token("mine", 3600, None);
+        let theirs = state.auth.generate_token("theirs", 3600, None);
+        let send = |method: &str, uri: &str, token: &str, body: Value| {
+            let request = Request::builder()
+                .method(method)
+                .uri(uri)
+                .header("authorization", format!("Bearer {}", token))
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap();
+            let router = router.clone();
+            async move {
+                let response = router.oneshot(request).await.unwrap();
+                let status = response.status();
+                let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                (
+                    status,
+                    serde_json::from_slice(&bytes).unwrap_or(Value::Null),
+                )
+            }
+        };
+        let hooks = "/webhooks/v1/systems/data/events/dm.version.added/hooks";
+        let callback = json!({ "callbackUrl": "https://example.com/cb", "scope": {} });
+        let (_, created) = send("POST", hooks, &mine.access_token, callback.clone()).await;
+        send("POST", hooks, &theirs.access_token, callback).await;
+        let hook_id = created["hookId"].as_str().unwrap();
+
+        let (status, page) = send("GET", "/webhooks/v1/hooks", &mine.access_token, json!({})).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(page["links"]["next"], Value::Null);
+        assert_eq!(page["data"].as_array().unwrap().len(), 1);
+        assert_eq!(page["data"][0]["hookId"], hook_id);
+        let (_, page) = send("GET", hooks, &mine.access_token, json!({})).await;
+        assert_eq!(page["data"].as_array().unwrap().len(), 2);
+
+        let hook = format!("{}/{}", hooks, hook_id);
+        let update = json!({ "status": "inactive", "filter": "$[?(@.ext=='rvt')]" });
+        let (status, patched) = send("PATCH", &hook, &mine.access_token, update).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(patched["status"], "inactive");
+        assert_eq!(patched["filter"], "$[?(@.ext=='rvt')]");
+        let (_, fetched) = send("GET", &hook, &mine.access_token, json!({})).await;
+        assert_eq!(fetched, patched);
+
+        // Hooks are only found under the event they were created for
+        let elsewhere = format!(
+            "/webhooks/v1/systems/data/events/dm.folder.added/hooks/{}",
+            hook_id
+        );
+        let (status, _) = send("GET", &elsewhere, &mine.access_token, json!({})).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let (status, _) = send("PATCH", &hook, &mine.access_token, json!({ "status": "x" })).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_secret_tokens_are_managed_per_app() {
+        let state = StateManager::new();
+        let (router, _) = crate::server::router::build_router(
+            Vec::new(),
+            Vec::new(),
+            Some(state.clone()),
+            &MockServerConfig::default(),
+            &[],
+        )
+        .unwrap();
+        let token = state.auth.generate_token("app", 3600, None);
+        let send = |method: &str, secret: &str| {
+            let request = Request::builder()
+                .method(method)
+                .uri("/webhooks/v1/tokens")
+                .header("authorization", format!("Bearer {}", token.access_token))
+                .header("content-type", "application/json")
+                .body(Body::from(json!({ "token": secret }).to_string()))
+                .unwrap();
+            let router = router.clone();
+            async move { router.oneshot(request).await.unwrap().status() }
+        };
+
+        assert_eq!(send("PUT", "early").await, StatusCode::NOT_FOUND);
+        assert_eq!(send("POST", "first").await, StatusCode::OK);
+        assert_eq!(send("POST", "again").await, StatusCode::BAD_REQUEST);
+        assert_eq!(state.webhooks.secret("app").as_deref(), Some("first"));
+        assert_eq!(send("PUT", "second").await, StatusCode::NO_CONTENT);
+        assert_eq!(state.webhooks.secret("app").as_deref(), Some("second"));
+        assert_eq!(send("PUT", &"x".repeat(51)).await, StatusCode::BAD_REQUEST);
+        assert_eq!(send("DELETE", "").await, StatusCode::NO_CONTENT);
+        assert_eq!(state.webhooks.secret("app"), None);
+        assert_eq!(send("DELETE", "").await, StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,89 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+//! Canonical path parameter names.
+//!
+//! axum refuses two routes that name the parameter at the same position
+//! differently (`/projects/:project_id/issues` next to
+//! `/projects/:projectid/users`), which happens when specs written by
+//! different teams are loaded together. Parameters are therefore renamed by
+//! position: the first route registered under a prefix decides the name
+//! every later route uses there. Handlers are unaffected, since spec handlers
+//! read parameters through the spec's own path and pack handlers extract
+//! them by position.
+
+use std::collections::HashMap;
+
+/// Parameter names already in use, by the path prefix leading to them
+#[derive(Debug, Default)]
+pub struct ParamNames {
+    /// Map of parameter-erased prefix (`/projects/:`) -> name in use
+    names: HashMap<String, String>,
+}
+
+impl ParamNames {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `path` with its parameters renamed to the names earlier routes gave
+    /// the same positions; names not seen before are adopted as they are
+    pub fn canonicalize(&mut self, path: &str) -> String {
+        let mut prefix = String::new();
+        let mut segments = Vec::new();
+        for segment in path.split('/') {
+            let renamed = match segment.chars().next() {
+                Some(kind @ (':' | '*')) => {
+                    prefix.push(kind);
+                    let name = self
+                        .names
+                        .entry(prefix.clone())
+                        .or_insert_with(|| segment[1..].to_string());
+                    format!("{}{}", kind, name)
+                }
+                _ => {
+                    prefix.push_str(segment);
+                    segment.to_string()
+                }
+            };
+            prefix.push('/');
+            segments.push(renamed);
+        }
+        let canonical = segments.join("/");
+        if canonical != path {
+            tracing::debug!(
+                "Registering {} as {} to match the parameter names of earlier routes",
+                path,
+                canonical
+            );
+        }
+        canonical
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parameters_are_renamed_by_position() {
+        let mut names = ParamNames::new();
+        assert_eq!(
+            names.canonicalize("/projects/:project_id/issues/:issue_id"),
+            "/projects/:project_id/issues/:issue_id"
+        );
+        assert_eq!(
+            names.canonicalize("/projects/:projectid/users/:userid"),
+            "/projects/:project_id/users/:userid"
+        );
+        assert_eq!(
+            names.canonicalize("/projects/:pid/issues/:id/*rest"),
+            "/projects/:project_id/issues/:issue_id/*rest"
+        );
+        // A static segment in between starts a different prefix
+        assert_eq!(
+            names.canonicalize("/hubs/:hub/projects/:projectid"),
+            "/hubs/:hub/projects/:projectid"
+        );
+    }
+}
@@ -0,0 +1,250 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+//! Audit log of state mutations (`--audit-log ENTRIES`), served under
+//! `/__admin/audit`.
+//!
+//! Every record a request creates, changes or deletes becomes an entry with
+//! the caller, the time, the record and its value before and after. Entries
+//! are kept in a ring buffer, the oldest dropped first. Object contents are
+//! not audited; their metadata records are.
+
+use crate::state::backend::Records;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::RwLock;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+/// What happened to a record
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditAction {
+    Created,
+    Updated,
+    Deleted,
+}
+
+/// A record changed by a request
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEntry {
+    /// Position in the log, increasing across the server's lifetime
+    pub seq: u64,
+    /// When the request was received (epoch millis)
+    pub timestamp: i64,
+    /// Client ID of the Bearer token, `admin` for admin calls, `anonymous`
+    /// otherwise
+    pub actor: String,
+    pub method: String,
+    pub path: String,
+    /// State file collection of the record (`buckets`, `issues`, ...)
+    pub kind: String,
+    pub id: String,
+    pub action: AuditAction,
+    pub old: Option<Value>,
+    pub new: Option<Value>,
+}
+
+/// A record that differs between two captures of the state
+#[derive(Debug, Clone)]
+pub struct Change {
+    /// State file collection of the record
+    pub kind: String,
+    pub id: String,
+    pub action: AuditAction,
+    pub old: Option<Value>,
+    pub new: Option<Value>,
+}
+
+/// Every record created, updated or deleted between two captures of the
+/// state; object contents are left out
+pub fn diff(before: &Records, after: &Records) -> Vec<Change> {
+    let empty = BTreeMap::new();
+    let parse = |json: &String| serde_json::from_str(json).ok();
+    let mut changes = Vec::new();
+    let kinds = before
+        .keys()
+        .chain(after.keys().filter(|k| !before.contains_key(*k)));
+    for kind in kinds.filter(|kind| kind.as_str() != "contents") {
+        let old = before.get(kind).unwrap_or(&empty);
+        let new = after.get(kind).unwrap_or(&empty);
+        let change = |id: &String, action, old: Option<&String>, new: Option<&String>| Change {
+            kind: kind.clone(),
+            id: id.clone(),
+            action,
+            old: old.and_then(parse),
+            new: new.and_then(parse),
+        };
+        for (id, json) in new {
+            match old.get(id) {
+                None => changes.push(change(id, AuditAction::Created, None, Some(json))),
+                Some(previous) if previous != json => {
+                    changes.push(change(id, AuditAction::Updated, Some(previous), Some(json)))
+                }
+                Some(_) => {}
+            }
+        }
+        for (id, json) in old.iter().filter(|(id, _)| !new.contains_key(*id)) {
+            changes.push(change(id, AuditAction::Deleted, Some(json), None));
+        }
+    }
+    changes
+}
+
+/// The request a set of changes is attributed to
+pub struct AuditContext {
+    pub timestamp: i64,
+    pub actor: String,
+    pub method: String,
+    pub path: String,
+}
+
+/// Ring buffer of audit entries; disabled while its capacity is 0
+pub struct AuditLog {
+    capacity: AtomicUsize,
+    next_seq: AtomicU64,
+    entries: RwLock<VecDeque<AuditEntry>>,
+    /// Held while a mutating request runs, so changes are attributed to the
+    /// request that made them
+    pub(crate) writer: tokio::sync::Mutex<()>,
+}
+
+impl AuditLog {
+    pub fn new() -> Self {
+        Self {
+            capacity: AtomicUsize::new(0),
+            next_seq: AtomicU64::new(1),
+            entries: RwLock::new(VecDeque::new()),
+            writer: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// Keep the last `capacity` entries; 0 disables the log
+    pub fn set_capacity(&self, capacity: usize) {
+        self.capacity.store(capacity, Ordering::Relaxed);
+        if let Ok(mut entries) = self.entries.write() {
+            while entries.len() > capacity {
+                entries.pop_front();
+            }
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.capacity.load(Ordering::Relaxed) > 0
+    }
+
+    /// Record `changes` as made by the request `context` describes
+    pub fn record_changes(&self, context: &AuditContext, changes: &[Change]) {
+        let capacity = self.capacity.load(Ordering::Relaxed);
+        if capacity == 0 {
+            return;
+        }
+        let Ok(mut entries) = self.entries.write() else {
+            return;
+        };
+        for change in changes {
+            entries.push_back(AuditEntry {
+                seq: self.next_seq.fetch_add(1, Ordering::Relaxed),
+                timestamp: context.timestamp,
+                actor: context.actor.clone(),
+                method: context.method.clone(),
+                path: context.path.clone(),
+                kind: change.kind.clone(),
+                id: change.id.clone(),
+                action: change.action,
+                old: change.old.clone(),
+                new: change.new.clone(),
+            });
+            if entries.len() > capacity {
+                entries.pop_front();
+            }
+        }
+    }
+
+    /// Entries recorded at or after `since` (epoch millis), oldest first
+    pub fn entries(&self, since: Option<i64>) -> Vec<AuditEntry> {
+        self.entries
+            .read()
+            .map(|entries| {
+                entries
+                    .iter()
+                    .filter(|entry| since.is_none_or(|since| entry.timestamp >= since))
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn clear(&self) {
+        if let Ok(mut entries) = self.entries.write() {
+            entries.clear();
+        }
+    }
+}
+
+impl Default for AuditLog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn records(entries: &[(&str, &str, &str)]) -> Records {
+        let mut records = Records::new();
+        for (kind, id, json) in entries {
+            records
+                .entry(kind.to_string())
+                .or_default()
+                .insert(id.to_string(), json.to_string());
+        }
+        records
+    }
+
+    fn context(timestamp: i64) -> AuditContext {
+        AuditContext {
+            timestamp,
+            actor: "app".into(),
+            method: "POST".into(),
+            path: "/test".into(),
+        }
+    }
+
+    #[test]
+    fn test_changes_are_kept_in_a_ring_buffer() {
+        let log = AuditLog::new();
+        let before = records(&[("buckets", "a", r#"{"v":1}"#), ("buckets", "b", "{}")]);
+        let after = records(&[
+            ("buckets", "a", r#"{"v":2}"#),
+            ("buckets", "c", "{}"),
+            ("contents", "c/x", r#""AA==""#),
+        ]);
+        let changes = diff(&before, &after);
+        assert_eq!(changes.len(), 3);
+        // Disabled until given a capacity
+        log.record_changes(&context(1), &changes);
+        assert!(log.entries(None).is_empty());
+
+        log.set_capacity(2);
+        log.record_changes(&context(2), &changes);
+        let entries = log.entries(None);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(
+            (entries[0].id.as_str(), entries[0].action),
+            ("c", AuditAction::Created)
+        );
+        assert_eq!(
+            (entries[1].id.as_str(), entries[1].action),
+            ("b", AuditAction::Deleted)
+        );
+        assert_eq!(entries[1].old, Some(serde_json::json!({})));
+        assert_eq!(entries[1].seq, 3);
+
+        log.record_changes(&context(5), &diff(&after, &before));
+        assert!(log.entries(Some(5)).iter().all(|e| e.timestamp == 5));
+        assert_eq!(log.entries(Some(6)).len(), 0);
+    }
+}
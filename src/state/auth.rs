@@ -1,9 +1,16 @@
 // SPDX-License-Identifier: Apache-2.0
 // Copyright 2024-2025 Dmytro Yemelianov
 
+use crate::state::clock::MockClock;
 use dashmap::DashMap;
 use serde::{Deserialize, Serialize};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::collections::BTreeSet;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+
+/// Lifetime of the tokens the token endpoint issues unless configured
+/// otherwise, in seconds
+pub const DEFAULT_EXPIRES_IN: u64 = 3600;
 
 /// OAuth token information
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -15,6 +22,57 @@ pub struct TokenInfo {
     pub refresh_token: Option<String>,
     pub scope: Option<String>,
     pub client_id: String,
+    /// Scope originally granted by the user (3-legged only); refreshes may
+    /// narrow it but never widen it
+    #[serde(default)]
+    pub granted_scope: Option<String>,
+    /// Whether the token was obtained through a 3-legged flow
+    #[serde(default)]
+    pub three_legged: bool,
+    /// User who signed in (3-legged only)
+    #[serde(default)]
+    pub user_id: Option<String>,
+}
+
+/// Errors returned by OAuth token operations
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum TokenError {
+    #[error("The refresh token is invalid or has been revoked")]
+    InvalidGrant,
+    #[error("The requested scope exceeds the scope originally granted: {0}")]
+    InvalidScope(String),
+}
+
+impl TokenError {
+    /// OAuth 2.0 `error` code for the response body
+    pub fn code(&self) -> &'static str {
+        match self {
+            TokenError::InvalidGrant => "invalid_grant",
+            TokenError::InvalidScope(_) => "invalid_scope",
+        }
+    }
+}
+
+/// How often a Bearer token has been presented to the mock
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenUsage {
+    pub access_token: String,
+    /// Client the token was issued to; `None` for tokens the mock never
+    /// issued (or has forgotten)
+    pub client_id: Option<String>,
+    pub requests: u64,
+    /// Requests rejected because the token was unknown or expired
+    pub rejected: u64,
+    /// Epoch millis
+    pub first_used_at: i64,
+    /// Epoch millis
+    pub last_used_at: i64,
+}
+
+/// Split a space-separated scope string into a set
+fn scope_set(scope: Option<&str>) -> BTreeSet<&str> {
+    scope.unwrap_or_default().split_whitespace().collect()
 }
 
 /// OAuth authentication state
@@ -23,21 +81,62 @@ pub struct AuthState {
     tokens_by_client: DashMap<String, TokenInfo>,
     /// Index: access_token -> client_id for O(1) token validation
     token_index: DashMap<String, String>,
+    /// Index: refresh_token -> client_id
+    refresh_index: DashMap<String, String>,
+    /// Usage of every access token presented, by token
+    usage: DashMap<String, TokenUsage>,
+    /// Seconds the clock expiry is checked against runs ahead of the one
+    /// tokens are issued with (negative: behind)
+    clock_skew_secs: AtomicI64,
+    /// Lifetime of the tokens the token endpoint issues, in seconds
+    expires_in_secs: AtomicU64,
+    clock: Arc<MockClock>,
 }
 
 impl AuthState {
     pub fn new() -> Self {
+        Self::with_clock(Arc::new(MockClock::new()))
+    }
+
+    /// Create the token store, issuing and expiring tokens by `clock`
+    pub fn with_clock(clock: Arc<MockClock>) -> Self {
         Self {
             tokens_by_client: DashMap::new(),
             token_index: DashMap::new(),
+            refresh_index: DashMap::new(),
+            usage: DashMap::new(),
+            clock_skew_secs: AtomicI64::new(0),
+            expires_in_secs: AtomicU64::new(DEFAULT_EXPIRES_IN),
+            clock,
         }
     }
 
-    fn current_timestamp() -> u64 {
-        SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .map(|d| d.as_secs())
-            .unwrap_or(0)
+    /// Check expiry against a clock `secs` ahead of the one tokens are
+    /// issued with (behind when negative), like a server whose clock drifted
+    /// from the client's: tokens are rejected `secs` before the `expires_in`
+    /// reported when they were issued runs out, or accepted that long after
+    pub fn set_clock_skew(&self, secs: i64) {
+        self.clock_skew_secs.store(secs, Ordering::Relaxed);
+    }
+
+    pub fn clock_skew(&self) -> i64 {
+        self.clock_skew_secs.load(Ordering::Relaxed)
+    }
+
+    /// Issue tokens from the token endpoint that expire after `secs`
+    pub fn set_expires_in(&self, secs: u64) {
+        self.expires_in_secs.store(secs, Ordering::Relaxed);
+    }
+
+    pub fn expires_in(&self) -> u64 {
+        self.expires_in_secs.load(Ordering::Relaxed)
+    }
+
+    /// Current time of the clock expiry is checked against
+    fn skewed_timestamp(&self) -> u64 {
+        self.clock
+            .now_secs()
+            .saturating_add_signed(self.clock_skew())
     }
 
     /// Generate a new access token
@@ -47,32 +146,146 @@ impl AuthState {
         expires_in: u64,
         scope: Option<String>,
     ) -> TokenInfo {
-        let now = Self::current_timestamp();
+        self.issue_token(client_id, expires_in, scope, None, None)
+    }
+
+    /// Generate a 3-legged token acting for `user_id` with the scope the
+    /// user granted
+    pub fn generate_three_legged_token(
+        &self,
+        client_id: &str,
+        expires_in: u64,
+        scope: Option<String>,
+        user_id: &str,
+    ) -> TokenInfo {
+        let granted = scope.clone().unwrap_or_default();
+        self.issue_token(
+            client_id,
+            expires_in,
+            scope,
+            Some(granted),
+            Some(user_id.to_string()),
+        )
+    }
+
+    /// Exchange a refresh token for a new token. The requested scope may only
+    /// narrow the originally granted scope; omitting it keeps the current one.
+    pub fn refresh_token(
+        &self,
+        refresh_token: &str,
+        requested_scope: Option<&str>,
+        expires_in: u64,
+    ) -> Result<TokenInfo, TokenError> {
+        let client_id = self
+            .refresh_index
+            .get(refresh_token)
+            .map(|c| c.value().clone())
+            .ok_or(TokenError::InvalidGrant)?;
+        let current = self
+            .tokens_by_client
+            .get(&client_id)
+            .map(|t| t.clone())
+            .ok_or(TokenError::InvalidGrant)?;
+
+        let granted = current
+            .granted_scope
+            .clone()
+            .or_else(|| current.scope.clone());
+        let scope = match requested_scope {
+            Some(requested) => {
+                let allowed = scope_set(granted.as_deref());
+                let extra: Vec<&str> = scope_set(Some(requested))
+                    .into_iter()
+                    .filter(|s| !allowed.contains(s))
+                    .collect();
+                if !extra.is_empty() {
+                    return Err(TokenError::InvalidScope(extra.join(" ")));
+                }
+                Some(requested.to_string())
+            }
+            None => current.scope.clone(),
+        };
+
+        Ok(self.issue_token(
+            &client_id,
+            expires_in,
+            scope,
+            granted,
+            current.user_id.clone(),
+        ))
+    }
+
+    fn issue_token(
+        &self,
+        client_id: &str,
+        expires_in: u64,
+        scope: Option<String>,
+        granted_scope: Option<String>,
+        user_id: Option<String>,
+    ) -> TokenInfo {
+        let now = self.clock.now_secs();
         let expires_at = now + expires_in;
+        // Keeps tokens issued within the same second distinct
+        let nonce = &uuid::Uuid::new_v4().simple().to_string()[..8];
 
-        // Remove old token from index if exists
+        // Remove old tokens from the indexes if they exist (refresh tokens rotate)
         if let Some(old_token) = self.tokens_by_client.get(client_id) {
             self.token_index.remove(&old_token.access_token);
+            if let Some(ref old_refresh) = old_token.refresh_token {
+                self.refresh_index.remove(old_refresh);
+            }
         }
 
         let token = TokenInfo {
-            access_token: format!("mock_token_{}_{}", client_id, now),
+            access_token: format!("mock_token_{}_{}_{}", client_id, now, nonce),
             token_type: "Bearer".to_string(),
             expires_in,
             expires_at,
-            refresh_token: Some(format!("mock_refresh_{}_{}", client_id, now)),
+            refresh_token: Some(format!("mock_refresh_{}_{}_{}", client_id, now, nonce)),
             scope,
             client_id: client_id.to_string(),
+            three_legged: granted_scope.is_some(),
+            granted_scope,
+            user_id,
         };
 
-        // Update both maps
-        self.token_index
-            .insert(token.access_token.clone(), client_id.to_string());
-        self.tokens_by_client
-            .insert(client_id.to_string(), token.clone());
+        self.index_token(token.clone());
         token
     }
 
+    fn index_token(&self, token: TokenInfo) {
+        self.token_index
+            .insert(token.access_token.clone(), token.client_id.clone());
+        if let Some(ref refresh) = token.refresh_token {
+            self.refresh_index
+                .insert(refresh.clone(), token.client_id.clone());
+        }
+        self.tokens_by_client.insert(token.client_id.clone(), token);
+    }
+
+    /// Insert a token record as given, replacing the client's current token
+    pub fn insert_token(&self, token: TokenInfo) {
+        if let Some((_, old_token)) = self.tokens_by_client.remove(&token.client_id) {
+            self.token_index.remove(&old_token.access_token);
+            if let Some(ref old_refresh) = old_token.refresh_token {
+                self.refresh_index.remove(old_refresh);
+            }
+        }
+        self.index_token(token);
+    }
+
+    /// Current token of every client, expired ones included, ordered by
+    /// client
+    pub fn list_tokens(&self) -> Vec<TokenInfo> {
+        let mut tokens: Vec<TokenInfo> = self
+            .tokens_by_client
+            .iter()
+            .map(|t| t.value().clone())
+            .collect();
+        tokens.sort_by(|a, b| a.client_id.cmp(&b.client_id));
+        tokens
+    }
+
     /// Get token info for a client
     pub fn get_token(&self, client_id: &str) -> Option<TokenInfo> {
         self.tokens_by_client.get(client_id).map(|t| t.clone())
@@ -80,21 +293,91 @@ impl AuthState {
 
     /// Validate an access token - O(1) lookup
     pub fn validate_token(&self, token: &str) -> bool {
-        let now = Self::current_timestamp();
+        self.lookup_token(token).is_some()
+    }
+
+    /// Get the info for a live (non-expired) access token
+    pub fn lookup_token(&self, token: &str) -> Option<TokenInfo> {
+        let now = self.skewed_timestamp();
 
         self.token_index
             .get(token)
             .and_then(|client_id| self.tokens_by_client.get(client_id.value()))
-            .map(|token_info| token_info.expires_at > now)
-            .unwrap_or(false)
+            .filter(|token_info| token_info.expires_at > now)
+            .map(|token_info| token_info.clone())
+    }
+
+    /// Record a request presenting `token`; `info` is what the token resolved
+    /// to, `None` if it was rejected
+    pub fn record_usage(&self, token: &str, info: Option<&TokenInfo>) {
+        let now = self.clock.now_millis();
+        let mut usage = self
+            .usage
+            .entry(token.to_string())
+            .or_insert_with(|| TokenUsage {
+                access_token: token.to_string(),
+                client_id: None,
+                requests: 0,
+                rejected: 0,
+                first_used_at: now,
+                last_used_at: now,
+            });
+        usage.requests += 1;
+        usage.last_used_at = now;
+        match info {
+            Some(info) => usage.client_id = Some(info.client_id.clone()),
+            None => usage.rejected += 1,
+        }
+    }
+
+    /// Usage of every token presented so far, most recently used first
+    pub fn usage(&self) -> Vec<TokenUsage> {
+        let mut usage: Vec<TokenUsage> = self.usage.iter().map(|u| u.value().clone()).collect();
+        usage.sort_by(|a, b| {
+            b.last_used_at
+                .cmp(&a.last_used_at)
+                .then_with(|| a.access_token.cmp(&b.access_token))
+        });
+        usage
+    }
+
+    pub fn clear_usage(&self) {
+        self.usage.clear();
+    }
+
+    /// Make `token` expire now, as if its lifetime had run out; unlike a
+    /// revoked token, its refresh token still works. Returns whether the
+    /// token is known.
+    pub fn expire_token(&self, token: &str) -> bool {
+        let now = self.skewed_timestamp();
+        let Some(client_id) = self.token_index.get(token).map(|c| c.value().clone()) else {
+            return false;
+        };
+        match self.tokens_by_client.get_mut(&client_id) {
+            Some(mut info) => {
+                info.expires_at = info.expires_at.min(now);
+                true
+            }
+            None => false,
+        }
     }
 
     /// Revoke a token
     pub fn revoke_token(&self, token: &str) {
-        if let Some((_, client_id)) = self.token_index.remove(token) {
-            self.tokens_by_client.remove(&client_id);
+        if let Some((_, client_id)) = self.token_index.remove(token)
+            && let Some((_, info)) = self.tokens_by_client.remove(&client_id)
+            && let Some(refresh) = info.refresh_token
+        {
+            self.refresh_index.remove(&refresh);
         }
     }
+
+    /// Drop every token; usage counters stay
+    pub fn clear(&self) {
+        self.tokens_by_client.clear();
+        self.token_index.clear();
+        self.refresh_index.clear();
+    }
 }
 
 impl Default for AuthState {
@@ -102,3 +385,103 @@ impl Default for AuthState {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clock_skew_shifts_expiry() {
+        let auth = AuthState::new();
+        let token = auth.generate_token("app", 60, None);
+        assert!(auth.validate_token(&token.access_token));
+
+        // A clock running ahead rejects the token before its lifetime ends
+        auth.set_clock_skew(61);
+        assert!(!auth.validate_token(&token.access_token));
+
+        // One running behind accepts it after it ended
+        auth.set_clock_skew(-120);
+        let mut expired = token.clone();
+        expired.expires_at -= 90;
+        auth.insert_token(expired);
+        assert!(auth.validate_token(&token.access_token));
+        auth.set_clock_skew(0);
+        assert!(!auth.validate_token(&token.access_token));
+    }
+
+    #[test]
+    fn test_expired_token_can_still_be_refreshed() {
+        let auth = AuthState::new();
+        let token = auth.generate_three_legged_token("client", 3600, None, "user");
+        assert!(auth.expire_token(&token.access_token));
+        assert!(!auth.validate_token(&token.access_token));
+        assert!(!auth.expire_token("unknown"));
+
+        let refresh = token.refresh_token.unwrap();
+        let refreshed = auth.refresh_token(&refresh, None, 3600).unwrap();
+        assert!(auth.validate_token(&refreshed.access_token));
+    }
+
+    #[test]
+    fn test_refresh_can_narrow_but_not_widen_scope() {
+        let auth = AuthState::new();
+        let token = auth.generate_three_legged_token(
+            "app",
+            3600,
+            Some("data:read data:write".to_string()),
+            "mock-user",
+        );
+        let refresh = token.refresh_token.unwrap();
+
+        let narrowed = auth
+            .refresh_token(&refresh, Some("data:read"), 3600)
+            .unwrap();
+        assert_eq!(narrowed.scope.as_deref(), Some("data:read"));
+        assert!(!auth.validate_token(&token.access_token));
+
+        // Widening back within the original grant is allowed, beyond it is not
+        let refresh = narrowed.refresh_token.unwrap();
+        let err = auth
+            .refresh_token(&refresh, Some("data:read bucket:create"), 3600)
+            .unwrap_err();
+        assert_eq!(err, TokenError::InvalidScope("bucket:create".to_string()));
+        assert!(
+            auth.refresh_token(&refresh, Some("data:write"), 3600)
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_rotated_refresh_token_is_rejected() {
+        let auth = AuthState::new();
+        let token = auth.generate_three_legged_token("app", 3600, None, "mock-user");
+        let refresh = token.refresh_token.unwrap();
+        auth.refresh_token(&refresh, None, 3600).unwrap();
+        assert_eq!(
+            auth.refresh_token(&refresh, None, 3600).unwrap_err(),
+            TokenError::InvalidGrant
+        );
+    }
+
+    #[test]
+    fn test_token_usage_is_tracked() {
+        let auth = AuthState::new();
+        let token = auth.generate_token("app", 3600, None);
+        for _ in 0..2 {
+            let info = auth.lookup_token(&token.access_token);
+            auth.record_usage(&token.access_token, info.as_ref());
+        }
+        auth.record_usage("leaked", auth.lookup_token("leaked").as_ref());
+
+        let usage = auth.usage();
+        let issued = usage
+            .iter()
+            .find(|u| u.access_token == token.access_token)
+            .unwrap();
+        assert_eq!((issued.requests, issued.rejected), (2, 0));
+        assert_eq!(issued.client_id.as_deref(), Some("app"));
+        let leaked = usage.iter().find(|u| u.access_token == "leaked").unwrap();
+        assert_eq!((leaked.client_id.as_deref(), leaked.rejected), (None, 1));
+    }
+}
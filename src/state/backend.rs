@@ -0,0 +1,161 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+//! Stores the state is loaded from on startup and saved to on shutdown and
+//! autosave (`--state-file`, `--state-backend`).
+//!
+//! Every backend keeps a [`StateFile`]; they differ only in where it goes.
+//! The JSON file is the default. The SQLite database (`sqlite-state`
+//! feature) keeps one table per record kind, so other processes can query a
+//! running mock's state with standard tooling. Redis (`redis-state`
+//! feature) is shared: several servers behind a load balancer keep their
+//! state in step through it (see [`crate::middleware::shared_state`]).
+
+use crate::config::StateBackendSpec;
+use crate::error::{MockError, Result};
+use crate::state::persistence::StateFile;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+#[cfg(feature = "redis-state")]
+mod redis;
+#[cfg(feature = "sqlite-state")]
+mod sqlite;
+
+#[cfg(feature = "redis-state")]
+pub use self::redis::RedisBackend;
+#[cfg(feature = "sqlite-state")]
+pub use sqlite::SqliteBackend;
+
+/// Somewhere a [`StateFile`] is kept between runs
+pub trait StateBackend: Send + Sync {
+    /// Read the saved state; `None` when nothing was saved yet
+    fn load(&self) -> Result<Option<StateFile>>;
+
+    /// Replace the saved state
+    fn save(&self, state: &StateFile) -> Result<()>;
+
+    /// Where the state is kept, for log messages
+    fn location(&self) -> String;
+
+    /// Whether other servers save to the same store, so it has to be
+    /// re-read when they do and written after every change
+    fn is_shared(&self) -> bool {
+        false
+    }
+
+    /// Whether another server saved since this one last loaded or saved;
+    /// stores only this server writes are never stale
+    fn is_stale(&self) -> Result<bool> {
+        Ok(false)
+    }
+}
+
+/// Open the backend a spec names
+pub fn open(spec: &StateBackendSpec) -> Result<Arc<dyn StateBackend>> {
+    match spec {
+        StateBackendSpec::File(path) => Ok(Arc::new(JsonFileBackend::new(path.clone()))),
+        #[cfg(feature = "sqlite-state")]
+        StateBackendSpec::Sqlite(path) => Ok(Arc::new(SqliteBackend::open(path.clone())?)),
+        #[cfg(not(feature = "sqlite-state"))]
+        StateBackendSpec::Sqlite(_) => Err(MockError::State(
+            "the SQLite state backend is not compiled in; build with --features sqlite-state"
+                .into(),
+        )),
+        #[cfg(feature = "redis-state")]
+        StateBackendSpec::Redis(url) => Ok(Arc::new(RedisBackend::open(url)?)),
+        #[cfg(not(feature = "redis-state"))]
+        StateBackendSpec::Redis(_) => Err(MockError::State(
+            "the Redis state backend is not compiled in; build with --features redis-state".into(),
+        )),
+    }
+}
+
+/// Names and empty values of the lists and maps in a state file, the
+/// collections backends store record by record
+#[cfg_attr(
+    not(any(feature = "sqlite-state", feature = "redis-state")),
+    allow(dead_code)
+)]
+pub(crate) fn collections() -> Vec<(String, Value)> {
+    match serde_json::to_value(StateFile::default()) {
+        Ok(Value::Object(fields)) => fields
+            .into_iter()
+            .filter(|(_, shape)| shape.is_array() || shape.is_object())
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Map of collection -> record ID -> record JSON
+pub(crate) type Records = BTreeMap<String, BTreeMap<String, String>>;
+
+/// Every record of a state file by collection and ID
+pub(crate) fn records(state: &StateFile) -> Result<Records> {
+    let Value::Object(document) =
+        serde_json::to_value(state).map_err(|e| MockError::State(e.to_string()))?
+    else {
+        unreachable!("a state file serializes to an object");
+    };
+    let mut records = Records::new();
+    for (kind, value) in document {
+        let entries: BTreeMap<String, String> = match value {
+            Value::Array(list) => list
+                .iter()
+                .filter_map(|record| Some((record_id(&kind, record)?, record.to_string())))
+                .collect(),
+            Value::Object(map) => map
+                .iter()
+                .map(|(id, record)| (id.clone(), record.to_string()))
+                .collect(),
+            _ => continue,
+        };
+        records.insert(kind, entries);
+    }
+    Ok(records)
+}
+
+/// Identity of a record in a state file list
+fn record_id(kind: &str, record: &Value) -> Option<String> {
+    let field = |name: &str| record.get(name).and_then(Value::as_str).map(str::to_string);
+    match kind {
+        "tokens" => field("access_token"),
+        "buckets" => field("bucket_key"),
+        "objects" => Some(format!("{}/{}", field("bucket_key")?, field("object_key")?)),
+        "contents" => Some(format!("{}/{}", field("bucketKey")?, field("objectKey")?)),
+        "hooks" => field("hook_id"),
+        "uploads" => field("upload_key"),
+        _ => field("id"),
+    }
+}
+
+/// The state as one JSON document
+#[derive(Debug, Clone)]
+pub struct JsonFileBackend {
+    path: PathBuf,
+}
+
+impl JsonFileBackend {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+}
+
+impl StateBackend for JsonFileBackend {
+    fn load(&self) -> Result<Option<StateFile>> {
+        if !self.path.exists() {
+            return Ok(None);
+        }
+        StateFile::read(&self.path).map(Some)
+    }
+
+    fn save(&self, state: &StateFile) -> Result<()> {
+        state.write(&self.path)
+    }
+
+    fn location(&self) -> String {
+        self.path.display().to_string()
+    }
+}
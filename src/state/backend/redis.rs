@@ -0,0 +1,234 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+//! Redis state backend (`--state-backend redis://HOST[:PORT][/DB]`), shared
+//! by every mock server pointed at the same database.
+//!
+//! Each list and map of the [`StateFile`] is a hash under
+//! `raps-mock:<collection>` mapping record IDs to record JSON, next to
+//! `raps-mock:version` and a `raps-mock:revision` counter every save bumps.
+//! Saves write only the records that changed since this server last loaded
+//! or saved, so servers changing different records do not overwrite each
+//! other; the last write to the same record wins.
+
+use crate::error::{MockError, Result};
+use crate::state::backend::{Records, StateBackend, collections, records};
+use crate::state::persistence::StateFile;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+/// Prefix of every key the backend writes
+const PREFIX: &str = "raps-mock";
+
+/// The state in a Redis database several servers share
+pub struct RedisBackend {
+    client: redis::Client,
+    connection: Mutex<Option<redis::Connection>>,
+    /// Records and revision as of this server's last load or save
+    synced: Mutex<(Records, u64)>,
+}
+
+impl RedisBackend {
+    /// Connect to the server at `url`
+    pub fn open(url: &str) -> Result<Self> {
+        let client = redis::Client::open(url).map_err(|e| error(url, e))?;
+        let backend = Self {
+            client,
+            connection: Mutex::new(None),
+            synced: Mutex::new((Records::new(), 0)),
+        };
+        // Fail at startup rather than on the first request
+        backend.with_connection(|connection| redis::cmd("PING").query::<String>(connection))?;
+        Ok(backend)
+    }
+
+    /// Run commands on the cached connection, reconnecting after errors
+    fn with_connection<T>(
+        &self,
+        run: impl FnOnce(&mut redis::Connection) -> redis::RedisResult<T>,
+    ) -> Result<T> {
+        let mut slot = self.connection.lock().unwrap_or_else(|e| e.into_inner());
+        if slot.is_none() {
+            *slot = Some(
+                self.client
+                    .get_connection()
+                    .map_err(|e| error(&self.location(), e))?,
+            );
+        }
+        let connection = slot.as_mut().expect("connection was just opened");
+        run(connection).map_err(|e| {
+            *slot = None;
+            error(&self.location(), e)
+        })
+    }
+
+    fn revision(&self) -> Result<u64> {
+        self.with_connection(|connection| {
+            redis::cmd("GET")
+                .arg(key("revision"))
+                .query::<Option<u64>>(connection)
+        })
+        .map(Option::unwrap_or_default)
+    }
+}
+
+impl StateBackend for RedisBackend {
+    fn load(&self) -> Result<Option<StateFile>> {
+        let kinds = collections();
+        // One transaction, so the records match the revision
+        let (version, revision, hashes) = self.with_connection(|connection| {
+            let mut pipe = redis::pipe();
+            pipe.atomic().get(key("version")).get(key("revision"));
+            for (kind, _) in &kinds {
+                pipe.hgetall(key(kind));
+            }
+            let mut values: Vec<redis::Value> = pipe.query(connection)?;
+            let hashes = values
+                .split_off(2)
+                .into_iter()
+                .map(|value| redis::from_redis_value::<BTreeMap<String, String>>(&value))
+                .collect::<redis::RedisResult<Vec<_>>>()?;
+            let version: Option<u32> = redis::from_redis_value(&values[0])?;
+            let revision: Option<u64> = redis::from_redis_value(&values[1])?;
+            Ok((version, revision.unwrap_or_default(), hashes))
+        })?;
+        let Some(version) = version else {
+            return Ok(None);
+        };
+
+        let mut document = Map::new();
+        document.insert("version".into(), version.into());
+        let mut records = Records::new();
+        for ((kind, shape), hash) in kinds.into_iter().zip(hashes) {
+            let value = if shape.is_array() {
+                Value::Array(
+                    hash.values()
+                        .filter_map(|json| parse(&kind, json))
+                        .collect(),
+                )
+            } else {
+                Value::Object(
+                    hash.iter()
+                        .filter_map(|(id, json)| Some((id.clone(), parse(&kind, json)?)))
+                        .collect(),
+                )
+            };
+            document.insert(kind.clone(), value);
+            records.insert(kind, hash);
+        }
+        let file = serde_json::from_value(Value::Object(document))
+            .map_err(|e| MockError::State(format!("{}: {}", self.location(), e)))?;
+        *self.synced.lock().unwrap_or_else(|e| e.into_inner()) = (records, revision);
+        Ok(Some(file))
+    }
+
+    fn save(&self, state: &StateFile) -> Result<()> {
+        let current = records(state)?;
+        let mut synced = self.synced.lock().unwrap_or_else(|e| e.into_inner());
+        let (previous, seen) = &*synced;
+
+        let mut pipe = redis::pipe();
+        pipe.atomic().set(key("version"), state.version).ignore();
+        let mut changes = 0;
+        let empty = BTreeMap::new();
+        for (kind, records) in &current {
+            let before = previous.get(kind).unwrap_or(&empty);
+            for (id, json) in records {
+                if before.get(id) != Some(json) {
+                    pipe.hset(key(kind), id, json).ignore();
+                    changes += 1;
+                }
+            }
+            for id in before.keys().filter(|id| !records.contains_key(*id)) {
+                pipe.hdel(key(kind), id).ignore();
+                changes += 1;
+            }
+        }
+        if changes == 0 && *seen > 0 {
+            return Ok(());
+        }
+        pipe.incr(key("revision"), 1);
+        let (revision,): (u64,) = self.with_connection(|connection| pipe.query(connection))?;
+
+        // Another server saving in between leaves this one stale
+        let seen = if revision == seen + 1 {
+            revision
+        } else {
+            *seen
+        };
+        *synced = (current, seen);
+        Ok(())
+    }
+
+    fn location(&self) -> String {
+        let info = self.client.get_connection_info();
+        format!("redis://{}/{}", info.addr, info.redis.db)
+    }
+
+    fn is_shared(&self) -> bool {
+        true
+    }
+
+    fn is_stale(&self) -> Result<bool> {
+        let seen = self.synced.lock().unwrap_or_else(|e| e.into_inner()).1;
+        Ok(self.revision()? != seen)
+    }
+}
+
+fn key(name: &str) -> String {
+    format!("{}:{}", PREFIX, name)
+}
+
+fn parse(kind: &str, json: &str) -> Option<Value> {
+    serde_json::from_str(json)
+        .inspect_err(|e| tracing::warn!("Skipping an unreadable {} record in Redis: {}", kind, e))
+        .ok()
+}
+
+fn error(location: &str, e: redis::RedisError) -> MockError {
+    MockError::State(format!("{}: {}", location, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::StateManager;
+
+    #[test]
+    fn test_every_record_has_an_id() {
+        let state = StateManager::new();
+        state.buckets.create_bucket("b1".into(), "transient".into());
+        state.objects.put_object(
+            "b1".into(),
+            "a/b.rvt".into(),
+            bytes::Bytes::from_static(b"rvt"),
+            None,
+            BTreeMap::new(),
+        );
+        state.auth.generate_token("app", 3600, None);
+        state.webhooks.create_subscription(
+            "data".into(),
+            "dm.version.added".into(),
+            "tenant".into(),
+            "http://localhost/hook".into(),
+            Default::default(),
+            None,
+        );
+        state
+            .issues
+            .create_issue("b.default-project".into(), "Leak".into(), None);
+        let file = StateFile::capture(&state);
+        let records = records(&file).unwrap();
+
+        // Nothing is dropped for want of an ID
+        let count = |kind: &str| records.get(kind).map_or(0, BTreeMap::len);
+        assert_eq!(count("tokens"), file.tokens.len());
+        assert_eq!(count("buckets"), 1);
+        assert!(records["objects"].contains_key("b1/a/b.rvt"));
+        assert!(records["contents"].contains_key("b1/a/b.rvt"));
+        assert_eq!(count("hooks"), 1);
+        assert_eq!(count("issues"), 1);
+        assert_eq!(count("hubs"), file.hubs.len());
+    }
+}
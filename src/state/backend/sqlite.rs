@@ -0,0 +1,225 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+//! SQLite state backend (`--state-backend sqlite:PATH`).
+//!
+//! Each list in the [`StateFile`] gets a table of `(seq, data)` rows and
+//! each map a table of `(key, data)` rows, `data` being the record's JSON;
+//! the format version lives in `meta`. So
+//! `SELECT json_extract(data, '$.bucket_key') FROM buckets` lists the
+//! buckets of a running mock. The database runs in WAL mode, so readers do
+//! not block saves. Every save replaces all rows in one transaction.
+
+use crate::error::{MockError, Result};
+use crate::state::backend::{StateBackend, collections};
+use crate::state::persistence::StateFile;
+use rusqlite::{Connection, OptionalExtension, params};
+use serde_json::{Map, Value};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// The state in a SQLite database
+pub struct SqliteBackend {
+    path: PathBuf,
+    connection: Mutex<Connection>,
+}
+
+impl SqliteBackend {
+    /// Open or create the database
+    pub fn open(path: PathBuf) -> Result<Self> {
+        let connection = Connection::open(&path).map_err(|e| error(&path, e))?;
+        connection
+            .execute_batch(
+                "PRAGMA journal_mode = WAL;
+                 PRAGMA busy_timeout = 5000;
+                 CREATE TABLE IF NOT EXISTS meta (key TEXT PRIMARY KEY, value TEXT NOT NULL);",
+            )
+            .map_err(|e| error(&path, e))?;
+        Ok(Self {
+            path,
+            connection: Mutex::new(connection),
+        })
+    }
+
+    fn load_tables(&self, connection: &Connection) -> rusqlite::Result<Option<Value>> {
+        let version: Option<String> = connection
+            .query_row("SELECT value FROM meta WHERE key = 'version'", [], |row| {
+                row.get(0)
+            })
+            .optional()?;
+        let Some(version) = version else {
+            return Ok(None);
+        };
+
+        let mut document = Map::new();
+        document.insert("version".into(), json_or_string(version));
+        for (table, shape) in collections() {
+            let exists: bool = connection.query_row(
+                "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1)",
+                [table.as_str()],
+                |row| row.get(0),
+            )?;
+            if !exists {
+                continue;
+            }
+            let value = match shape {
+                Value::Array(_) => {
+                    let mut statement = connection
+                        .prepare(&format!("SELECT data FROM \"{}\" ORDER BY seq", table))?;
+                    let rows = statement.query_map([], |row| row.get::<_, String>(0))?;
+                    Value::Array(
+                        rows.map(|r| r.map(json_or_string))
+                            .collect::<rusqlite::Result<_>>()?,
+                    )
+                }
+                _ => {
+                    let mut statement = connection
+                        .prepare(&format!("SELECT key, data FROM \"{}\" ORDER BY key", table))?;
+                    let rows = statement.query_map([], |row| {
+                        Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+                    })?;
+                    Value::Object(
+                        rows.map(|r| r.map(|(key, data)| (key, json_or_string(data))))
+                            .collect::<rusqlite::Result<_>>()?,
+                    )
+                }
+            };
+            document.insert(table, value);
+        }
+        Ok(Some(Value::Object(document)))
+    }
+
+    fn save_tables(
+        &self,
+        connection: &mut Connection,
+        document: Map<String, Value>,
+    ) -> rusqlite::Result<()> {
+        let transaction = connection.transaction()?;
+        for (table, value) in document {
+            match value {
+                Value::Array(records) => {
+                    transaction.execute_batch(&format!(
+                        "CREATE TABLE IF NOT EXISTS \"{0}\" (seq INTEGER PRIMARY KEY, data TEXT NOT NULL);
+                         DELETE FROM \"{0}\";",
+                        table
+                    ))?;
+                    let mut insert = transaction.prepare(&format!(
+                        "INSERT INTO \"{}\" (seq, data) VALUES (?1, ?2)",
+                        table
+                    ))?;
+                    for (seq, record) in records.iter().enumerate() {
+                        insert.execute(params![seq as i64, record.to_string()])?;
+                    }
+                }
+                Value::Object(records) => {
+                    transaction.execute_batch(&format!(
+                        "CREATE TABLE IF NOT EXISTS \"{0}\" (key TEXT PRIMARY KEY, data TEXT NOT NULL);
+                         DELETE FROM \"{0}\";",
+                        table
+                    ))?;
+                    let mut insert = transaction.prepare(&format!(
+                        "INSERT INTO \"{}\" (key, data) VALUES (?1, ?2)",
+                        table
+                    ))?;
+                    for (key, record) in &records {
+                        insert.execute(params![key, record.to_string()])?;
+                    }
+                }
+                scalar => {
+                    transaction.execute(
+                        "INSERT OR REPLACE INTO meta (key, value) VALUES (?1, ?2)",
+                        params![table, scalar.to_string()],
+                    )?;
+                }
+            }
+        }
+        transaction.commit()
+    }
+}
+
+impl StateBackend for SqliteBackend {
+    fn load(&self) -> Result<Option<StateFile>> {
+        let connection = self.connection.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(document) = self
+            .load_tables(&connection)
+            .map_err(|e| error(&self.path, e))?
+        else {
+            return Ok(None);
+        };
+        serde_json::from_value(document)
+            .map(Some)
+            .map_err(|e| MockError::State(format!("{}: {}", self.path.display(), e)))
+    }
+
+    fn save(&self, state: &StateFile) -> Result<()> {
+        let document = match serde_json::to_value(state) {
+            Ok(Value::Object(document)) => document,
+            Ok(_) => unreachable!("a state file serializes to an object"),
+            Err(e) => return Err(MockError::State(format!("{}: {}", self.path.display(), e))),
+        };
+        let mut connection = self.connection.lock().unwrap_or_else(|e| e.into_inner());
+        self.save_tables(&mut connection, document)
+            .map_err(|e| error(&self.path, e))
+    }
+
+    fn location(&self) -> String {
+        format!("sqlite:{}", self.path.display())
+    }
+}
+
+fn json_or_string(data: String) -> Value {
+    serde_json::from_str(&data).unwrap_or(Value::String(data))
+}
+
+fn error(path: &std::path::Path, e: rusqlite::Error) -> MockError {
+    MockError::State(format!("{}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::StateManager;
+
+    #[test]
+    fn test_state_round_trips_through_sqlite() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.db");
+
+        let backend = SqliteBackend::open(path.clone()).unwrap();
+        assert!(backend.load().unwrap().is_none());
+
+        let state = StateManager::new();
+        state
+            .buckets
+            .create_bucket("shared".into(), "persistent".into());
+        state
+            .issues
+            .create_issue("b.default-project".into(), "Crack".into(), None);
+        state.save_to(&backend).unwrap();
+        // Saving again replaces rows rather than appending
+        state.buckets.delete_bucket("shared");
+        state
+            .buckets
+            .create_bucket("kept".into(), "transient".into());
+        state.save_to(&backend).unwrap();
+
+        // Another connection sees the records as plain JSON rows
+        let reader = Connection::open(&path).unwrap();
+        let keys: Vec<String> = reader
+            .prepare("SELECT json_extract(data, '$.bucket_key') FROM buckets")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        assert_eq!(keys, ["kept"]);
+
+        let restored = StateManager::new();
+        restored
+            .load_from(&SqliteBackend::open(path).unwrap())
+            .unwrap();
+        assert!(restored.buckets.get_bucket("kept").is_some());
+        assert!(restored.buckets.get_bucket("shared").is_none());
+        assert_eq!(restored.issues.list_issues("b.default-project").len(), 1);
+    }
+}
@@ -0,0 +1,285 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+//! Byte storage shared by every feature that keeps uploaded or generated
+//! content (`--blob-store`, `--blob-store-max-bytes`).
+//!
+//! Blobs are addressed by `/`-separated keys whose first segment names the
+//! owner, e.g. `oss/<bucketKey>/<objectKey>` for OSS object contents, so one
+//! store can hold everything and list one owner's blobs by prefix. Records
+//! describing a blob (sizes, content types) stay in the state modules; a
+//! missing blob reads as empty content.
+
+use crate::config::BlobStoreSpec;
+use crate::error::Result;
+use bytes::Bytes;
+use dashmap::DashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::io;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+/// Storage for blobs
+pub trait BlobStore: Send + Sync {
+    /// Content of a blob
+    fn get(&self, key: &str) -> Option<Bytes>;
+
+    /// Create or replace a blob
+    fn put(&self, key: &str, data: Bytes) -> io::Result<()>;
+
+    /// Delete a blob, returning whether it existed
+    fn delete(&self, key: &str) -> bool;
+
+    /// Keys of every blob starting with `prefix`, sorted
+    fn keys(&self, prefix: &str) -> Vec<String>;
+}
+
+/// Open the store a spec names, capped at `max_bytes` when given
+pub fn open(spec: &BlobStoreSpec, max_bytes: Option<u64>) -> Result<Arc<dyn BlobStore>> {
+    let store: Arc<dyn BlobStore> = match spec {
+        BlobStoreSpec::Memory => Arc::new(MemoryBlobStore::new()),
+        BlobStoreSpec::Disk(dir) => Arc::new(DiskBlobStore::open(dir.clone())?),
+    };
+    Ok(match max_bytes {
+        Some(max_bytes) => Arc::new(LruBlobStore::new(store, max_bytes)),
+        None => store,
+    })
+}
+
+/// Blobs in memory, the default
+#[derive(Debug, Default)]
+pub struct MemoryBlobStore {
+    blobs: DashMap<String, Bytes>,
+}
+
+impl MemoryBlobStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl BlobStore for MemoryBlobStore {
+    fn get(&self, key: &str) -> Option<Bytes> {
+        self.blobs.get(key).map(|b| b.clone())
+    }
+
+    fn put(&self, key: &str, data: Bytes) -> io::Result<()> {
+        self.blobs.insert(key.to_string(), data);
+        Ok(())
+    }
+
+    fn delete(&self, key: &str) -> bool {
+        self.blobs.remove(key).is_some()
+    }
+
+    fn keys(&self, prefix: &str) -> Vec<String> {
+        let mut keys: Vec<String> = self
+            .blobs
+            .iter()
+            .filter(|b| b.key().starts_with(prefix))
+            .map(|b| b.key().clone())
+            .collect();
+        keys.sort();
+        keys
+    }
+}
+
+/// Blobs as files in one directory, named after their percent-encoded keys
+#[derive(Debug)]
+pub struct DiskBlobStore {
+    dir: PathBuf,
+}
+
+impl DiskBlobStore {
+    /// Use `dir`, creating it if needed; blobs already there are kept
+    pub fn open(dir: PathBuf) -> io::Result<Self> {
+        std::fs::create_dir_all(&dir)?;
+        Ok(Self { dir })
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        // Encoding `/` and `.` keeps every key a plain file name
+        let name: String = form_urlencoded::byte_serialize(key.as_bytes())
+            .collect::<String>()
+            .replace('.', "%2E");
+        self.dir.join(format!("{}.blob", name))
+    }
+}
+
+impl BlobStore for DiskBlobStore {
+    fn get(&self, key: &str) -> Option<Bytes> {
+        std::fs::read(self.path(key)).ok().map(Bytes::from)
+    }
+
+    fn put(&self, key: &str, data: Bytes) -> io::Result<()> {
+        let path = self.path(key);
+        let mut temp = path.as_os_str().to_owned();
+        temp.push(".tmp");
+        std::fs::write(&temp, &data)?;
+        std::fs::rename(&temp, &path)
+    }
+
+    fn delete(&self, key: &str) -> bool {
+        std::fs::remove_file(self.path(key)).is_ok()
+    }
+
+    fn keys(&self, prefix: &str) -> Vec<String> {
+        let Ok(entries) = std::fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+        let mut keys: Vec<String> = entries
+            .filter_map(|entry| {
+                let name = entry.ok()?.file_name().into_string().ok()?;
+                let encoded = name.strip_suffix(".blob")?;
+                // A percent-encoded name decodes as a lone form field name
+                form_urlencoded::parse(encoded.as_bytes())
+                    .next()
+                    .map(|(key, _)| key.into_owned())
+            })
+            .filter(|key| key.starts_with(prefix))
+            .collect();
+        keys.sort();
+        keys
+    }
+}
+
+/// Another store capped at a total size; putting a blob over the cap
+/// evicts the least recently read or written blobs
+pub struct LruBlobStore {
+    inner: Arc<dyn BlobStore>,
+    max_bytes: u64,
+    usage: Mutex<LruUsage>,
+}
+
+#[derive(Default)]
+struct LruUsage {
+    /// Map of key -> (last use, size)
+    entries: HashMap<String, (u64, u64)>,
+    /// Map of last use -> key, oldest first
+    order: BTreeMap<u64, String>,
+    total: u64,
+    clock: u64,
+}
+
+impl LruUsage {
+    fn touch(&mut self, key: &str, size: u64) {
+        self.clock += 1;
+        if let Some((used, old_size)) = self.entries.remove(key) {
+            self.order.remove(&used);
+            self.total -= old_size;
+        }
+        self.entries.insert(key.to_string(), (self.clock, size));
+        self.order.insert(self.clock, key.to_string());
+        self.total += size;
+    }
+
+    fn forget(&mut self, key: &str) {
+        if let Some((used, size)) = self.entries.remove(key) {
+            self.order.remove(&used);
+            self.total -= size;
+        }
+    }
+}
+
+impl LruBlobStore {
+    /// Cap `inner`; blobs it already holds are counted, oldest key first
+    pub fn new(inner: Arc<dyn BlobStore>, max_bytes: u64) -> Self {
+        let mut usage = LruUsage::default();
+        for key in inner.keys("") {
+            let size = inner.get(&key).map_or(0, |b| b.len() as u64);
+            usage.touch(&key, size);
+        }
+        Self {
+            inner,
+            max_bytes,
+            usage: Mutex::new(usage),
+        }
+    }
+
+    /// Total size of the stored blobs
+    pub fn total_bytes(&self) -> u64 {
+        self.usage.lock().unwrap_or_else(|e| e.into_inner()).total
+    }
+}
+
+impl BlobStore for LruBlobStore {
+    fn get(&self, key: &str) -> Option<Bytes> {
+        let data = self.inner.get(key)?;
+        self.usage
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .touch(key, data.len() as u64);
+        Some(data)
+    }
+
+    fn put(&self, key: &str, data: Bytes) -> io::Result<()> {
+        let size = data.len() as u64;
+        self.inner.put(key, data)?;
+        let mut usage = self.usage.lock().unwrap_or_else(|e| e.into_inner());
+        usage.touch(key, size);
+        // The blob just written stays even when it alone exceeds the cap
+        while usage.total > self.max_bytes {
+            let Some((_, oldest)) = usage.order.first_key_value() else {
+                break;
+            };
+            if oldest == key {
+                break;
+            }
+            let oldest = oldest.clone();
+            usage.forget(&oldest);
+            self.inner.delete(&oldest);
+            tracing::debug!("Evicted blob {} from the size-capped store", oldest);
+        }
+        Ok(())
+    }
+
+    fn delete(&self, key: &str) -> bool {
+        self.usage
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .forget(key);
+        self.inner.delete(key)
+    }
+
+    fn keys(&self, prefix: &str) -> Vec<String> {
+        self.inner.keys(prefix)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_disk_store_behind_a_size_cap() {
+        let dir = tempfile::tempdir().unwrap();
+        let disk = Arc::new(DiskBlobStore::open(dir.path().to_path_buf()).unwrap());
+        let store = LruBlobStore::new(disk.clone(), 10);
+
+        store
+            .put("oss/b1/../a b.rvt", Bytes::from_static(b"1234"))
+            .unwrap();
+        store
+            .put("oss/b1/two", Bytes::from_static(b"5678"))
+            .unwrap();
+        assert_eq!(store.keys("oss/b1/"), ["oss/b1/../a b.rvt", "oss/b1/two"]);
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 2);
+
+        // Reading the first blob makes the second the least recently used
+        assert_eq!(
+            store.get("oss/b1/../a b.rvt").unwrap(),
+            Bytes::from_static(b"1234")
+        );
+        store
+            .put("issues/p1/att", Bytes::from_static(b"abcd"))
+            .unwrap();
+        assert!(store.get("oss/b1/two").is_none());
+        assert_eq!(store.total_bytes(), 8);
+
+        // A reopened cap counts what the directory already holds
+        let reopened = LruBlobStore::new(disk, 10);
+        assert_eq!(reopened.total_bytes(), 8);
+        assert!(reopened.delete("issues/p1/att"));
+        assert_eq!(reopened.keys(""), ["oss/b1/../a b.rvt"]);
+    }
+}
@@ -1,8 +1,11 @@
 // SPDX-License-Identifier: Apache-2.0
 // Copyright 2024-2025 Dmytro Yemelianov
 
+use crate::state::clock::MockClock;
+use crate::state::snapshot::SnapshotLock;
 use dashmap::DashMap;
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
 
 /// OSS bucket information
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -23,18 +26,27 @@ pub struct Permission {
 /// OSS bucket state
 pub struct BucketState {
     buckets: DashMap<String, BucketInfo>,
+    snapshot: SnapshotLock,
+    clock: Arc<MockClock>,
 }
 
 impl BucketState {
     pub fn new() -> Self {
+        Self::with_clock(Arc::new(MockClock::new()))
+    }
+
+    /// Create the bucket store, dating buckets by `clock`
+    pub fn with_clock(clock: Arc<MockClock>) -> Self {
         Self {
             buckets: DashMap::new(),
+            snapshot: SnapshotLock::new(),
+            clock,
         }
     }
 
     /// Create a new bucket
     pub fn create_bucket(&self, bucket_key: String, policy_key: String) -> BucketInfo {
-        let now = chrono::Utc::now().timestamp_millis();
+        let now = self.clock.now_millis();
         let bucket = BucketInfo {
             bucket_key: bucket_key.clone(),
             bucket_owner: "mock-owner".to_string(),
@@ -42,6 +54,7 @@ impl BucketState {
             policy_key,
             permissions: vec![],
         };
+        let _guard = self.snapshot.mutate();
         self.buckets.insert(bucket_key, bucket.clone());
         bucket
     }
@@ -51,15 +64,58 @@ impl BucketState {
         self.buckets.get(bucket_key).map(|b| b.clone())
     }
 
-    /// List all buckets
+    /// List all buckets, ordered by key
     pub fn list_buckets(&self) -> Vec<BucketInfo> {
-        self.buckets.iter().map(|e| e.value().clone()).collect()
+        let mut buckets: Vec<BucketInfo> = {
+            let _guard = self.snapshot.snapshot();
+            self.buckets.iter().map(|e| e.value().clone()).collect()
+        };
+        buckets.sort_by(|a, b| a.bucket_key.cmp(&b.bucket_key));
+        buckets
     }
 
     /// Delete a bucket
     pub fn delete_bucket(&self, bucket_key: &str) -> bool {
+        let _guard = self.snapshot.mutate();
         self.buckets.remove(bucket_key).is_some()
     }
+
+    /// Insert or replace a bucket record
+    pub fn insert_bucket(&self, bucket: BucketInfo) {
+        let _guard = self.snapshot.mutate();
+        self.buckets.insert(bucket.bucket_key.clone(), bucket);
+    }
+
+    /// Insert or replace many bucket records, returning how many were given
+    pub fn import_buckets(&self, buckets: impl IntoIterator<Item = BucketInfo>) -> usize {
+        let _guard = self.snapshot.mutate();
+        let mut count = 0;
+        for bucket in buckets {
+            self.buckets.insert(bucket.bucket_key.clone(), bucket);
+            count += 1;
+        }
+        count
+    }
+
+    /// Iterate over a snapshot of all buckets, ordered by key
+    pub fn iter(&self) -> impl Iterator<Item = BucketInfo> + use<> {
+        self.list_buckets().into_iter()
+    }
+
+    /// Number of buckets
+    pub fn len(&self) -> usize {
+        self.buckets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buckets.is_empty()
+    }
+
+    /// Drop every bucket
+    pub fn clear(&self) {
+        let _guard = self.snapshot.mutate();
+        self.buckets.clear();
+    }
 }
 
 impl Default for BucketState {
@@ -0,0 +1,86 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+//! APS applications allowed to request tokens. While none is registered the
+//! token endpoint issues tokens to any client ID; once one is, it only
+//! issues them to registered clients presenting their secret, for the scopes
+//! they are permitted.
+//!
+//! Clients are settings rather than records: they come from the `clients`
+//! key of the configuration file or `/__admin/state/clients` and survive
+//! resets.
+
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+
+/// Credentials of a registered APS application
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClientCredentials {
+    pub client_id: String,
+    pub client_secret: String,
+    /// Scopes the client may request; empty permits every scope
+    #[serde(default)]
+    pub scopes: Vec<String>,
+}
+
+impl ClientCredentials {
+    /// Scopes of `requested` (space-separated) the client is not permitted
+    pub fn forbidden_scopes<'a>(&self, requested: &'a str) -> Vec<&'a str> {
+        if self.scopes.is_empty() {
+            return Vec::new();
+        }
+        requested
+            .split_whitespace()
+            .filter(|scope| !self.scopes.iter().any(|s| s == scope))
+            .collect()
+    }
+}
+
+/// Registered clients
+pub struct ClientRegistry {
+    clients: DashMap<String, ClientCredentials>,
+}
+
+impl ClientRegistry {
+    pub fn new() -> Self {
+        Self {
+            clients: DashMap::new(),
+        }
+    }
+
+    /// Whether client IDs are checked at all, i.e. any client is registered
+    pub fn is_enforced(&self) -> bool {
+        !self.clients.is_empty()
+    }
+
+    /// The client `client_id` if it is registered with `client_secret`
+    pub fn authenticate(&self, client_id: &str, client_secret: &str) -> Option<ClientCredentials> {
+        self.clients
+            .get(client_id)
+            .filter(|client| client.client_secret == client_secret)
+            .map(|client| client.clone())
+    }
+
+    /// Every client, sorted by ID
+    pub fn list_clients(&self) -> Vec<ClientCredentials> {
+        let mut clients: Vec<ClientCredentials> =
+            self.clients.iter().map(|c| c.value().clone()).collect();
+        clients.sort_by(|a, b| a.client_id.cmp(&b.client_id));
+        clients
+    }
+
+    /// Register a client, replacing its previous credentials
+    pub fn insert_client(&self, client: ClientCredentials) {
+        self.clients.insert(client.client_id.clone(), client);
+    }
+
+    pub fn delete_client(&self, client_id: &str) -> bool {
+        self.clients.remove(client_id).is_some()
+    }
+}
+
+impl Default for ClientRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,134 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+//! Virtual clock behind token expiry, upload sessions, job progress and
+//! record timestamps, controlled under `/__admin/clock`.
+//!
+//! The clock follows the system clock until a test freezes it or moves it
+//! forward, so it can go past a token's `expires_at` or a job's duration
+//! without sleeping.
+
+use serde::{Deserialize, Serialize};
+use std::sync::RwLock;
+
+/// Where the clock stands
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClockStatus {
+    /// Current time (epoch millis)
+    pub now: i64,
+    /// Whether the clock is stopped
+    pub frozen: bool,
+    /// How far the clock is ahead of the system clock (millis, negative:
+    /// behind)
+    pub offset_millis: i64,
+}
+
+#[derive(Debug, Default)]
+struct Reading {
+    offset_millis: i64,
+    frozen_at: Option<i64>,
+}
+
+/// Clock shared by the state modules; follows the system clock until
+/// frozen or advanced
+#[derive(Debug, Default)]
+pub struct MockClock {
+    reading: RwLock<Reading>,
+}
+
+impl MockClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn system_millis() -> i64 {
+        chrono::Utc::now().timestamp_millis()
+    }
+
+    /// Current time (epoch millis)
+    pub fn now_millis(&self) -> i64 {
+        let reading = self.reading.read().unwrap_or_else(|e| e.into_inner());
+        reading
+            .frozen_at
+            .unwrap_or_else(|| Self::system_millis() + reading.offset_millis)
+    }
+
+    /// Current time (epoch seconds)
+    pub fn now_secs(&self) -> u64 {
+        u64::try_from(self.now_millis() / 1000).unwrap_or(0)
+    }
+
+    /// Stop the clock at `at` (epoch millis), or where it stands now
+    pub fn freeze(&self, at: Option<i64>) -> ClockStatus {
+        let now = at.unwrap_or_else(|| self.now_millis());
+        let mut reading = self.reading.write().unwrap_or_else(|e| e.into_inner());
+        reading.frozen_at = Some(now);
+        reading.offset_millis = now - Self::system_millis();
+        drop(reading);
+        self.status()
+    }
+
+    /// Let a frozen clock run again from where it stopped
+    pub fn resume(&self) -> ClockStatus {
+        let mut reading = self.reading.write().unwrap_or_else(|e| e.into_inner());
+        if let Some(frozen_at) = reading.frozen_at.take() {
+            reading.offset_millis = frozen_at - Self::system_millis();
+        }
+        drop(reading);
+        self.status()
+    }
+
+    /// Move the clock `millis` forward (backward when negative), frozen or
+    /// not
+    pub fn advance(&self, millis: i64) -> ClockStatus {
+        let mut reading = self.reading.write().unwrap_or_else(|e| e.into_inner());
+        reading.offset_millis += millis;
+        if let Some(frozen_at) = reading.frozen_at.as_mut() {
+            *frozen_at += millis;
+        }
+        drop(reading);
+        self.status()
+    }
+
+    /// Follow the system clock again
+    pub fn reset(&self) {
+        *self.reading.write().unwrap_or_else(|e| e.into_inner()) = Reading::default();
+    }
+
+    pub fn status(&self) -> ClockStatus {
+        let reading = self.reading.read().unwrap_or_else(|e| e.into_inner());
+        let system = Self::system_millis();
+        let now = reading.frozen_at.unwrap_or(system + reading.offset_millis);
+        ClockStatus {
+            now,
+            frozen: reading.frozen_at.is_some(),
+            offset_millis: now - system,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frozen_clock_only_moves_when_advanced() {
+        let clock = MockClock::new();
+        let status = clock.freeze(Some(1_000_000));
+        assert!(status.frozen);
+        assert_eq!(clock.now_millis(), 1_000_000);
+        assert_eq!(clock.now_secs(), 1_000);
+
+        clock.advance(3_600_000);
+        assert_eq!(clock.now_millis(), 4_600_000);
+
+        // Resumes from where it stopped
+        clock.resume();
+        let now = clock.now_millis();
+        assert!((4_600_000..4_700_000).contains(&now), "{}", now);
+
+        clock.reset();
+        assert_eq!(clock.status().offset_millis, 0);
+    }
+}
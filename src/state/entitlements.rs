@@ -0,0 +1,158 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// ACC products that can be activated on a project
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Product {
+    Docs,
+    Build,
+    Cost,
+}
+
+impl Product {
+    pub const ALL: [Product; 3] = [Product::Docs, Product::Build, Product::Cost];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Product::Docs => "docs",
+            Product::Build => "build",
+            Product::Cost => "cost",
+        }
+    }
+
+    /// Display name used in error messages
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            Product::Docs => "Docs",
+            Product::Build => "Build",
+            Product::Cost => "Cost",
+        }
+    }
+}
+
+impl std::str::FromStr for Product {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "docs" => Ok(Product::Docs),
+            "build" => Ok(Product::Build),
+            "cost" => Ok(Product::Cost),
+            _ => Err(format!("Unknown product: {}", s)),
+        }
+    }
+}
+
+/// Product activation status on a project
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProductStatus {
+    Active,
+    Inactive,
+}
+
+/// ACC Account Admin product activations per project
+///
+/// Projects without an explicit entry are treated as having every product
+/// active, so existing workflows keep working until a test opts in.
+pub struct EntitlementState {
+    /// Map of project_id (without the `b.` prefix) -> product activations
+    products: DashMap<String, BTreeMap<Product, ProductStatus>>,
+}
+
+impl EntitlementState {
+    pub fn new() -> Self {
+        Self {
+            products: DashMap::new(),
+        }
+    }
+
+    /// ACC Admin IDs omit the `b.` prefix used by Data Management
+    fn normalize(project_id: &str) -> &str {
+        project_id.strip_prefix("b.").unwrap_or(project_id)
+    }
+
+    /// Get the activation status of every product on a project
+    pub fn get_products(&self, project_id: &str) -> BTreeMap<Product, ProductStatus> {
+        self.products
+            .get(Self::normalize(project_id))
+            .map(|p| p.clone())
+            .unwrap_or_else(|| {
+                Product::ALL
+                    .iter()
+                    .map(|p| (*p, ProductStatus::Active))
+                    .collect()
+            })
+    }
+
+    /// Set the activation status of a product on a project
+    pub fn set_product_status(&self, project_id: &str, product: Product, status: ProductStatus) {
+        let mut entry = self
+            .products
+            .entry(Self::normalize(project_id).to_string())
+            .or_insert_with(|| {
+                Product::ALL
+                    .iter()
+                    .map(|p| (*p, ProductStatus::Active))
+                    .collect()
+            });
+        entry.insert(product, status);
+    }
+
+    /// Product activations of every project with an explicit entry
+    pub fn all(&self) -> BTreeMap<String, BTreeMap<Product, ProductStatus>> {
+        self.products
+            .iter()
+            .map(|p| (p.key().clone(), p.value().clone()))
+            .collect()
+    }
+
+    /// Replace the product activations of a project
+    pub fn insert_products(&self, project_id: &str, products: BTreeMap<Product, ProductStatus>) {
+        self.products
+            .insert(Self::normalize(project_id).to_string(), products);
+    }
+
+    /// Check whether a product is active on a project
+    pub fn is_active(&self, project_id: &str, product: Product) -> bool {
+        self.get_products(project_id)
+            .get(&product)
+            .is_some_and(|s| *s == ProductStatus::Active)
+    }
+
+    /// Drop every product status, so all products are active again
+    pub fn clear(&self) {
+        self.products.clear();
+    }
+}
+
+impl Default for EntitlementState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_products_active_by_default() {
+        let state = EntitlementState::new();
+        assert!(state.is_active("b.some-project", Product::Build));
+        assert!(state.is_active("some-project", Product::Cost));
+    }
+
+    #[test]
+    fn test_deactivation_ignores_b_prefix() {
+        let state = EntitlementState::new();
+        state.set_product_status("project-1", Product::Build, ProductStatus::Inactive);
+        assert!(!state.is_active("b.project-1", Product::Build));
+        assert!(state.is_active("b.project-1", Product::Docs));
+    }
+}
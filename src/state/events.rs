@@ -0,0 +1,101 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+//! Changes to buckets, objects, issues, jobs and hooks, streamed to
+//! `GET /__admin/events` subscribers as server-sent events.
+//!
+//! Events come from the same before/after comparison as the audit log, so
+//! they are only produced while someone is subscribed. Job progress is
+//! computed when a job is read, not stored, so jobs produce events when
+//! they are started, settled or removed rather than on every phase change.
+
+use crate::state::audit::{AuditAction, Change};
+use serde::Serialize;
+use serde_json::Value;
+use tokio::sync::broadcast;
+
+/// Events a slow subscriber may fall behind by before it misses some
+const CAPACITY: usize = 1024;
+
+/// A change to a record subscribers are told about
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StateEvent {
+    /// `bucket`, `object`, `issue`, `job` or `hook`
+    pub resource: &'static str,
+    pub id: String,
+    pub action: AuditAction,
+    /// When the change was made (epoch millis)
+    pub timestamp: i64,
+    /// The record after the change, or before it for deletions
+    pub value: Option<Value>,
+}
+
+impl StateEvent {
+    /// SSE event name, e.g. `bucket.created`
+    pub fn name(&self) -> String {
+        let action = match self.action {
+            AuditAction::Created => "created",
+            AuditAction::Updated => "updated",
+            AuditAction::Deleted => "deleted",
+        };
+        format!("{}.{}", self.resource, action)
+    }
+}
+
+/// Fan-out of state events to every subscriber
+pub struct EventBus {
+    sender: broadcast::Sender<StateEvent>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        Self {
+            sender: broadcast::channel(CAPACITY).0,
+        }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<StateEvent> {
+        self.sender.subscribe()
+    }
+
+    pub fn has_subscribers(&self) -> bool {
+        self.sender.receiver_count() > 0
+    }
+
+    /// Send the changes to resources subscribers are told about
+    pub fn publish(&self, timestamp: i64, changes: &[Change]) {
+        for change in changes {
+            let Some(resource) = resource(&change.kind) else {
+                continue;
+            };
+            let value = change.new.clone().or_else(|| change.old.clone());
+            // Fails only when nobody is subscribed
+            let _ = self.sender.send(StateEvent {
+                resource,
+                id: change.id.clone(),
+                action: change.action,
+                timestamp,
+                value,
+            });
+        }
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Resource name of a state file collection, if events are sent for it
+fn resource(kind: &str) -> Option<&'static str> {
+    match kind {
+        "buckets" => Some("bucket"),
+        "objects" => Some("object"),
+        "issues" => Some("issue"),
+        "jobs" => Some("job"),
+        "hooks" => Some("hook"),
+        _ => None,
+    }
+}
@@ -0,0 +1,452 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+//! Fixtures (`--seed-dir`): declarative YAML or JSON files describing the
+//! buckets, objects, hubs, projects, issues and hooks a server starts with.
+//!
+//! Every `*.yaml`, `*.yml` and `*.json` file in the directory is read in
+//! file name order and all of them are applied together, so a file may
+//! refer to a bucket or hub another one declares. Every section is optional:
+//!
+//! ```yaml
+//! buckets:
+//!   - bucketKey: designs
+//!     policyKey: persistent          # default transient
+//! objects:
+//!   - bucketKey: designs
+//!     objectKey: house.rvt
+//!     file: models/house.rvt         # or `content: text` or `base64: ...`
+//! hubs:
+//!   - id: b.acme
+//!     name: ACME
+//! projects:
+//!   - id: b.tower                    # generated when omitted
+//!     hubId: b.acme
+//!     name: Tower
+//!     folders:                       # as in project templates
+//!       - name: Project Files
+//!         models:
+//!           - name: Tower.rvt
+//! issues:
+//!   - projectId: tower
+//!     title: Clash in level 2
+//! hooks:
+//!   - system: data
+//!     event: dm.version.added
+//!     callbackUrl: http://localhost:8080/hook
+//!     scope:
+//!       folder: urn:adsk.wipprod:fs.folder:co.abc
+//! ```
+//!
+//! Fixtures are applied after any saved state is loaded and replace records
+//! with the same identifiers; give issues and hooks an `id`/`hookId` so a
+//! restart with a state file does not duplicate them. The folders and models
+//! of a project are only created when the project does not exist yet.
+
+use crate::error::{MockError, Result};
+use crate::state::StateManager;
+use crate::state::issues::IssueInfo;
+use crate::state::projects::{HubInfo, ProjectInfo};
+use crate::state::templates::{FolderTemplate, IssueTemplate, ProjectTemplate};
+use crate::state::webhooks::{STATUS_ACTIVE, WebhookScope, WebhookSubscription};
+use base64::Engine as _;
+use base64::engine::general_purpose::STANDARD;
+use bytes::Bytes;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Records declared by one or more fixture files
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default, deny_unknown_fields)]
+pub struct Fixtures {
+    pub buckets: Vec<BucketFixture>,
+    pub objects: Vec<ObjectFixture>,
+    pub hubs: Vec<HubFixture>,
+    pub projects: Vec<ProjectFixture>,
+    pub issues: Vec<IssueFixture>,
+    pub hooks: Vec<HookFixture>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct BucketFixture {
+    pub bucket_key: String,
+    #[serde(default = "default_policy_key")]
+    pub policy_key: String,
+}
+
+fn default_policy_key() -> String {
+    "transient".to_string()
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ObjectFixture {
+    pub bucket_key: String,
+    pub object_key: String,
+    /// Text content
+    #[serde(default)]
+    pub content: Option<String>,
+    /// Binary content, base64 (standard alphabet, padded)
+    #[serde(default)]
+    pub base64: Option<String>,
+    /// File holding the content, relative to the fixture file
+    #[serde(default)]
+    pub file: Option<PathBuf>,
+    #[serde(default)]
+    pub content_type: Option<String>,
+    #[serde(default)]
+    pub metadata: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct HubFixture {
+    pub id: String,
+    pub name: String,
+    #[serde(default = "default_region")]
+    pub region: String,
+}
+
+fn default_region() -> String {
+    "US".to_string()
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ProjectFixture {
+    /// Project ID with or without the `b.` prefix
+    #[serde(default)]
+    pub id: Option<String>,
+    pub hub_id: String,
+    pub name: String,
+    #[serde(default)]
+    pub folders: Vec<FolderTemplate>,
+    #[serde(default)]
+    pub issues: Vec<IssueTemplate>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct IssueFixture {
+    #[serde(default)]
+    pub id: Option<String>,
+    /// Project ID with or without the `b.` prefix
+    pub project_id: String,
+    pub title: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default = "default_issue_status")]
+    pub status: String,
+    /// User IDs the issue is attributed to
+    #[serde(default)]
+    pub created_by: Option<String>,
+    #[serde(default)]
+    pub assigned_to: Option<String>,
+}
+
+fn default_issue_status() -> String {
+    "open".to_string()
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct HookFixture {
+    #[serde(default)]
+    pub hook_id: Option<String>,
+    pub system: String,
+    pub event: String,
+    pub callback_url: String,
+    #[serde(default)]
+    pub scope: WebhookScope,
+    #[serde(default)]
+    pub hook_attribute: Option<serde_json::Value>,
+}
+
+impl Fixtures {
+    /// Read every fixture file in `dir`, in file name order
+    pub fn load_dir(dir: &Path) -> Result<Self> {
+        let entries = std::fs::read_dir(dir)
+            .map_err(|e| MockError::Config(format!("seed dir {}: {}", dir.display(), e)))?;
+        let mut paths: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| {
+                path.is_file()
+                    && path
+                        .extension()
+                        .is_some_and(|ext| ext == "json" || ext == "yaml" || ext == "yml")
+            })
+            .collect();
+        paths.sort();
+
+        let mut fixtures = Self::default();
+        for path in paths {
+            fixtures.extend(Self::load(&path)?);
+        }
+        Ok(fixtures)
+    }
+
+    /// Read one fixture file; content files are resolved against its
+    /// directory
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+        // YAML is a superset of JSON
+        let mut fixtures: Self = serde_yaml::from_str(&content)
+            .map_err(|e| MockError::Config(format!("{}: {}", path.display(), e)))?;
+        let base = path.parent().unwrap_or(Path::new("."));
+        for object in &mut fixtures.objects {
+            if let Some(file) = object.file.take() {
+                object.file = Some(base.join(file));
+            }
+        }
+        Ok(fixtures)
+    }
+
+    fn extend(&mut self, other: Self) {
+        self.buckets.extend(other.buckets);
+        self.objects.extend(other.objects);
+        self.hubs.extend(other.hubs);
+        self.projects.extend(other.projects);
+        self.issues.extend(other.issues);
+        self.hooks.extend(other.hooks);
+    }
+
+    /// Number of records the fixtures declare
+    pub fn len(&self) -> usize {
+        self.buckets.len()
+            + self.objects.len()
+            + self.hubs.len()
+            + self.projects.len()
+            + self.issues.len()
+            + self.hooks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Insert every record into `state`, buckets and hubs before the objects
+    /// and projects in them
+    pub fn apply(&self, state: &StateManager) -> Result<()> {
+        for bucket in &self.buckets {
+            state
+                .buckets
+                .create_bucket(bucket.bucket_key.clone(), bucket.policy_key.clone());
+        }
+        for object in &self.objects {
+            if state.buckets.get_bucket(&object.bucket_key).is_none() {
+                return Err(MockError::Config(format!(
+                    "fixture object {} is in unknown bucket {}",
+                    object.object_key, object.bucket_key
+                )));
+            }
+            state.objects.put_object(
+                object.bucket_key.clone(),
+                object.object_key.clone(),
+                object.content()?,
+                object.content_type.clone(),
+                object.metadata.clone(),
+            );
+        }
+
+        for hub in &self.hubs {
+            state.projects.insert_hub(HubInfo {
+                id: hub.id.clone(),
+                name: hub.name.clone(),
+                region: hub.region.clone(),
+            });
+        }
+        for project in &self.projects {
+            if state.projects.get_hub(&project.hub_id).is_none() {
+                return Err(MockError::Config(format!(
+                    "fixture project {} is in unknown hub {}",
+                    project.name, project.hub_id
+                )));
+            }
+            let id = match &project.id {
+                Some(id) => dm_project_id(id),
+                None => format!("b.{}", uuid::Uuid::new_v4()),
+            };
+            let existed = state.projects.get_project(&id).is_some();
+            state.projects.insert_project(ProjectInfo {
+                id: id.clone(),
+                hub_id: project.hub_id.clone(),
+                name: project.name.clone(),
+            });
+            // Folders and issues get new IDs, so a project is populated once
+            if existed {
+                continue;
+            }
+            let template = ProjectTemplate {
+                folders: project.folders.clone(),
+                issues: project.issues.clone(),
+            };
+            state.populate_project(&id, &template);
+        }
+
+        let now = state.clock.now_millis();
+        state.issues.import_issues(self.issues.iter().map(|issue| {
+            IssueInfo {
+                id: issue
+                    .id
+                    .clone()
+                    .unwrap_or_else(|| uuid::Uuid::new_v4().to_string()),
+                // ACC services address projects without the `b.` prefix
+                project_id: issue
+                    .project_id
+                    .strip_prefix("b.")
+                    .unwrap_or(&issue.project_id)
+                    .to_string(),
+                title: issue.title.clone(),
+                description: issue.description.clone(),
+                status: issue.status.clone(),
+                created_at: now,
+                created_by: issue.created_by.clone(),
+                assigned_to: issue.assigned_to.clone(),
+            }
+        }));
+        state
+            .webhooks
+            .import_subscriptions(self.hooks.iter().map(|hook| {
+                WebhookSubscription {
+                    hook_id: hook
+                        .hook_id
+                        .clone()
+                        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string()),
+                    system: hook.system.clone(),
+                    event: hook.event.clone(),
+                    tenant: hook.system.clone(),
+                    callback_url: hook.callback_url.clone(),
+                    scope: hook.scope.clone(),
+                    hook_attribute: hook.hook_attribute.clone(),
+                    status: STATUS_ACTIVE.to_string(),
+                    created_at: now,
+                    consecutive_failures: 0,
+                    client_id: None,
+                    filter: None,
+                }
+            }));
+        Ok(())
+    }
+}
+
+impl ObjectFixture {
+    fn content(&self) -> Result<Bytes> {
+        let name = || format!("{}/{}", self.bucket_key, self.object_key);
+        match (&self.content, &self.base64, &self.file) {
+            (Some(text), None, None) => Ok(Bytes::from(text.clone())),
+            (None, Some(encoded), None) => STANDARD
+                .decode(encoded)
+                .map(Bytes::from)
+                .map_err(|e| MockError::Config(format!("fixture object {}: {}", name(), e))),
+            (None, None, Some(path)) => std::fs::read(path).map(Bytes::from).map_err(|e| {
+                MockError::Config(format!(
+                    "fixture object {}: {}: {}",
+                    name(),
+                    path.display(),
+                    e
+                ))
+            }),
+            (None, None, None) => Ok(Bytes::new()),
+            _ => Err(MockError::Config(format!(
+                "fixture object {} has more than one of content, base64 and file",
+                name()
+            ))),
+        }
+    }
+}
+
+/// Data Management project ID, with the `b.` prefix
+fn dm_project_id(id: &str) -> String {
+    if id.starts_with("b.") {
+        id.to_string()
+    } else {
+        format!("b.{}", id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seed_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("models")).unwrap();
+        std::fs::write(dir.path().join("models/house.rvt"), b"rvt").unwrap();
+        std::fs::write(
+            dir.path().join("10-oss.yaml"),
+            r#"
+buckets:
+  - bucketKey: designs
+objects:
+  - bucketKey: designs
+    objectKey: house.rvt
+    file: models/house.rvt
+  - bucketKey: designs
+    objectKey: notes.txt
+    content: hello
+"#,
+        )
+        .unwrap();
+        // A later file may use what an earlier one declares
+        std::fs::write(
+            dir.path().join("20-acc.json"),
+            r#"{
+  "hubs": [{"id": "b.acme", "name": "ACME"}],
+  "projects": [{
+    "id": "tower", "hubId": "b.acme", "name": "Tower",
+    "folders": [{"name": "Project Files", "models": [{"name": "Tower.rvt"}]}]
+  }],
+  "issues": [{"id": "i1", "projectId": "b.tower", "title": "Leak"}],
+  "hooks": [{
+    "hookId": "h1", "system": "data", "event": "dm.version.added",
+    "callbackUrl": "http://localhost/hook"
+  }]
+}"#,
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("README.md"), "not a fixture").unwrap();
+
+        let fixtures = Fixtures::load_dir(dir.path()).unwrap();
+        assert_eq!(fixtures.len(), 7);
+        let state = StateManager::new();
+        fixtures.apply(&state).unwrap();
+        // Applying again replaces rather than duplicates identified records
+        fixtures.apply(&state).unwrap();
+
+        assert_eq!(
+            state.objects.get_content("designs", "house.rvt").unwrap(),
+            Bytes::from_static(b"rvt")
+        );
+        assert_eq!(
+            state
+                .objects
+                .get_object("designs", "notes.txt")
+                .unwrap()
+                .size,
+            5
+        );
+        let project = state.projects.get_project("b.tower").unwrap();
+        assert_eq!(project.hub_id, "b.acme");
+        assert_eq!(state.projects.top_folders("b.tower").len(), 1);
+        assert_eq!(state.issues.list_issues("tower").len(), 1);
+        assert_eq!(
+            state.webhooks.subscribers("data", "dm.version.added").len(),
+            1
+        );
+
+        // Objects need their bucket
+        std::fs::write(
+            dir.path().join("30-orphan.yaml"),
+            "objects:\n  - bucketKey: missing\n    objectKey: a\n",
+        )
+        .unwrap();
+        let error = Fixtures::load_dir(dir.path())
+            .unwrap()
+            .apply(&StateManager::new())
+            .unwrap_err();
+        assert!(error.to_string().contains("unknown bucket missing"));
+    }
+}
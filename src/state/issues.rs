@@ -1,8 +1,11 @@
 // SPDX-License-Identifier: Apache-2.0
 // Copyright 2024-2025 Dmytro Yemelianov
 
+use crate::state::clock::MockClock;
+use crate::state::snapshot::SnapshotLock;
 use dashmap::DashMap;
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
 
 /// ACC Issue information
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -13,18 +16,33 @@ pub struct IssueInfo {
     pub description: Option<String>,
     pub status: String,
     pub created_at: i64,
+    /// ID of the user whose token created the issue
+    #[serde(default)]
+    pub created_by: Option<String>,
+    /// ID of the user the issue is assigned to
+    #[serde(default)]
+    pub assigned_to: Option<String>,
 }
 
 /// ACC Issues state
 pub struct IssuesState {
     /// Map of project_id -> issues
     issues: DashMap<String, DashMap<String, IssueInfo>>,
+    snapshot: SnapshotLock,
+    clock: Arc<MockClock>,
 }
 
 impl IssuesState {
     pub fn new() -> Self {
+        Self::with_clock(Arc::new(MockClock::new()))
+    }
+
+    /// Create the issue store, dating issues by `clock`
+    pub fn with_clock(clock: Arc<MockClock>) -> Self {
         Self {
             issues: DashMap::new(),
+            snapshot: SnapshotLock::new(),
+            clock,
         }
     }
 
@@ -34,9 +52,21 @@ impl IssuesState {
         project_id: String,
         title: String,
         description: Option<String>,
+    ) -> IssueInfo {
+        self.create_attributed_issue(project_id, title, description, None, None)
+    }
+
+    /// Create a new issue on behalf of the user `created_by`
+    pub fn create_attributed_issue(
+        &self,
+        project_id: String,
+        title: String,
+        description: Option<String>,
+        created_by: Option<String>,
+        assigned_to: Option<String>,
     ) -> IssueInfo {
         let issue_id = uuid::Uuid::new_v4().to_string();
-        let now = chrono::Utc::now().timestamp_millis();
+        let now = self.clock.now_millis();
         let issue = IssueInfo {
             id: issue_id.clone(),
             project_id: project_id.clone(),
@@ -44,8 +74,11 @@ impl IssuesState {
             description,
             status: "open".to_string(),
             created_at: now,
+            created_by,
+            assigned_to,
         };
 
+        let _guard = self.snapshot.mutate();
         let project_issues = self.issues.entry(project_id).or_default();
         project_issues.insert(issue_id, issue.clone());
         issue
@@ -59,16 +92,26 @@ impl IssuesState {
             .map(|i| i.clone())
     }
 
-    /// List issues for a project
+    /// List issues for a project, oldest first
     pub fn list_issues(&self, project_id: &str) -> Vec<IssueInfo> {
-        self.issues
-            .get(project_id)
-            .map(|project_issues| project_issues.iter().map(|i| i.value().clone()).collect())
-            .unwrap_or_default()
+        let mut issues: Vec<IssueInfo> = {
+            let _guard = self.snapshot.snapshot();
+            self.issues
+                .get(project_id)
+                .map(|project_issues| project_issues.iter().map(|i| i.value().clone()).collect())
+                .unwrap_or_default()
+        };
+        issues.sort_by(|a, b| {
+            a.created_at
+                .cmp(&b.created_at)
+                .then_with(|| a.id.cmp(&b.id))
+        });
+        issues
     }
 
     /// Update issue status
     pub fn update_issue_status(&self, project_id: &str, issue_id: &str, status: String) -> bool {
+        let _guard = self.snapshot.mutate();
         self.issues
             .get(project_id)
             .and_then(|project_issues| {
@@ -79,6 +122,73 @@ impl IssuesState {
             })
             .unwrap_or(false)
     }
+
+    /// Delete an issue
+    pub fn delete_issue(&self, project_id: &str, issue_id: &str) -> bool {
+        let _guard = self.snapshot.mutate();
+        self.issues
+            .get(project_id)
+            .and_then(|project_issues| project_issues.remove(issue_id))
+            .is_some()
+    }
+
+    /// Insert or replace an issue record
+    pub fn insert_issue(&self, issue: IssueInfo) {
+        self.import_issues([issue]);
+    }
+
+    /// Insert or replace many issue records, returning how many were given
+    pub fn import_issues(&self, issues: impl IntoIterator<Item = IssueInfo>) -> usize {
+        let _guard = self.snapshot.mutate();
+        let mut count = 0;
+        for issue in issues {
+            self.issues
+                .entry(issue.project_id.clone())
+                .or_default()
+                .insert(issue.id.clone(), issue);
+            count += 1;
+        }
+        count
+    }
+
+    /// Iterate over a snapshot of the issues of every project, ordered by
+    /// project, then oldest first
+    pub fn iter(&self) -> impl Iterator<Item = IssueInfo> + use<> {
+        let mut issues: Vec<IssueInfo> = {
+            let _guard = self.snapshot.snapshot();
+            self.issues
+                .iter()
+                .flat_map(|project_issues| {
+                    project_issues
+                        .iter()
+                        .map(|i| i.value().clone())
+                        .collect::<Vec<_>>()
+                })
+                .collect()
+        };
+        issues.sort_by(|a, b| {
+            (&a.project_id, a.created_at, &a.id).cmp(&(&b.project_id, b.created_at, &b.id))
+        });
+        issues.into_iter()
+    }
+
+    /// Number of issues across all projects
+    pub fn len(&self) -> usize {
+        self.issues
+            .iter()
+            .map(|project_issues| project_issues.len())
+            .sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Drop every issue
+    pub fn clear(&self) {
+        let _guard = self.snapshot.mutate();
+        self.issues.clear();
+    }
 }
 
 impl Default for IssuesState {
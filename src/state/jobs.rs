@@ -0,0 +1,557 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+//! Simulator for asynchronous APS jobs (translations, Design Automation
+//! workitems, Data Connector extracts, downloads).
+//!
+//! A job is `pending` for its kind's pending duration, then `inprogress`
+//! for the in-progress duration, and then settles on its outcome. Status is
+//! derived from the clock when read, so no background task is needed and
+//! every async pattern progresses the same way.
+//!
+//! Clients waiting for a job either poll its status or are notified of its
+//! progress. [`JobEngine::observe`] compares every job's status with the one
+//! last reported and records a [`JobNotification`] for each start, each
+//! quarter of progress and each finish; the server calls it periodically.
+
+use crate::state::clock::MockClock;
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+
+/// Kind of asynchronous job
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum JobKind {
+    /// Model Derivative translation
+    Translation,
+    /// Design Automation workitem
+    WorkItem,
+    /// Data Connector extract
+    DataExtract,
+    /// Derivative or file download preparation
+    Download,
+}
+
+impl JobKind {
+    pub const ALL: [JobKind; 4] = [
+        JobKind::Translation,
+        JobKind::WorkItem,
+        JobKind::DataExtract,
+        JobKind::Download,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            JobKind::Translation => "translation",
+            JobKind::WorkItem => "work-item",
+            JobKind::DataExtract => "data-extract",
+            JobKind::Download => "download",
+        }
+    }
+}
+
+impl std::str::FromStr for JobKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        JobKind::ALL
+            .into_iter()
+            .find(|kind| kind.as_str() == s)
+            .ok_or_else(|| format!("Unknown job kind: {}", s))
+    }
+}
+
+/// Phase of a job
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobPhase {
+    Pending,
+    InProgress,
+    Success,
+    Failed,
+    /// Gave up for taking too long
+    Timeout,
+}
+
+impl JobPhase {
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self,
+            JobPhase::Success | JobPhase::Failed | JobPhase::Timeout
+        )
+    }
+}
+
+/// How long a job spends in each phase, in milliseconds
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobTiming {
+    pub pending_ms: u64,
+    pub in_progress_ms: u64,
+}
+
+impl Default for JobTiming {
+    fn default() -> Self {
+        Self {
+            pending_ms: 1000,
+            in_progress_ms: 3000,
+        }
+    }
+}
+
+/// Final result of a job
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "result", rename_all = "lowercase")]
+pub enum JobOutcome {
+    Success,
+    Failed { message: String },
+    Timeout { message: String },
+}
+
+/// A simulated job
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Job {
+    pub id: String,
+    pub kind: JobKind,
+    /// Resource the job works on (URN, workitem id, ...)
+    pub key: String,
+    /// Epoch millis
+    pub created_at: i64,
+    pub timing: JobTiming,
+    pub outcome: JobOutcome,
+    /// Set when the job was settled ahead of its schedule (epoch millis)
+    #[serde(default)]
+    pub settled_at: Option<i64>,
+}
+
+/// Point-in-time view of a job
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobStatus {
+    pub phase: JobPhase,
+    /// 0-100
+    pub progress: u8,
+    /// Failure message once the job has failed
+    pub message: Option<String>,
+}
+
+impl Job {
+    /// Status of the job at `now` (epoch millis)
+    pub fn status_at(&self, now: i64) -> JobStatus {
+        let elapsed = (now - self.created_at).max(0) as u64;
+        let settled = self.settled_at.is_some_and(|at| at <= now);
+        let finished = settled || elapsed >= self.timing.pending_ms + self.timing.in_progress_ms;
+
+        if finished {
+            return match &self.outcome {
+                JobOutcome::Success => JobStatus {
+                    phase: JobPhase::Success,
+                    progress: 100,
+                    message: None,
+                },
+                JobOutcome::Failed { message } => JobStatus {
+                    phase: JobPhase::Failed,
+                    progress: 100,
+                    message: Some(message.clone()),
+                },
+                JobOutcome::Timeout { message } => JobStatus {
+                    phase: JobPhase::Timeout,
+                    progress: 100,
+                    message: Some(message.clone()),
+                },
+            };
+        }
+        if elapsed < self.timing.pending_ms {
+            return JobStatus {
+                phase: JobPhase::Pending,
+                progress: 0,
+                message: None,
+            };
+        }
+        let running = elapsed - self.timing.pending_ms;
+        JobStatus {
+            phase: JobPhase::InProgress,
+            // Never report 100% before the job has actually finished
+            progress: (running * 100 / self.timing.in_progress_ms.max(1)).min(99) as u8,
+            message: None,
+        }
+    }
+}
+
+/// Progress a running job makes between two notifications, in percent
+const PROGRESS_STEP: u8 = 25;
+
+/// Notifications kept for polling; the oldest are dropped first
+const NOTIFICATION_CAPACITY: usize = 1000;
+
+/// What a job notification reports
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobEvent {
+    /// The job was queued, started or made progress
+    Progress,
+    /// The job succeeded or failed
+    Finished,
+}
+
+/// A change in a job's status clients are notified of
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobNotification {
+    /// Position in the feed, increasing across the server's lifetime
+    pub seq: u64,
+    pub event: JobEvent,
+    pub job_id: String,
+    pub kind: JobKind,
+    pub key: String,
+    pub phase: JobPhase,
+    pub progress: u8,
+    pub message: Option<String>,
+    /// When the change was observed (epoch millis)
+    pub timestamp: i64,
+}
+
+/// Last reported status of a job: its phase and progress step
+type Reported = (JobPhase, u8);
+
+fn reported(status: &JobStatus) -> Reported {
+    (status.phase, status.progress / PROGRESS_STEP)
+}
+
+/// Decides at creation whether a job fails, returning the failure message
+pub type FailureHook = Arc<dyn Fn(&Job) -> Option<String> + Send + Sync>;
+
+/// Pending forced failures for the next jobs of a kind
+struct ForcedFailures {
+    remaining: u32,
+    message: String,
+}
+
+/// Engine shared by every asynchronous APS pattern
+pub struct JobEngine {
+    jobs: DashMap<String, Job>,
+    timings: DashMap<JobKind, JobTiming>,
+    hooks: DashMap<JobKind, FailureHook>,
+    forced_failures: DashMap<JobKind, ForcedFailures>,
+    /// Status each job was last reported in, by job id
+    reported: DashMap<String, Reported>,
+    notifications: RwLock<VecDeque<JobNotification>>,
+    next_seq: AtomicU64,
+    clock: Arc<MockClock>,
+}
+
+impl JobEngine {
+    pub fn new() -> Self {
+        Self::with_clock(Arc::new(MockClock::new()))
+    }
+
+    /// Create an engine whose jobs progress by `clock`
+    pub fn with_clock(clock: Arc<MockClock>) -> Self {
+        Self {
+            jobs: DashMap::new(),
+            timings: DashMap::new(),
+            hooks: DashMap::new(),
+            forced_failures: DashMap::new(),
+            reported: DashMap::new(),
+            notifications: RwLock::new(VecDeque::new()),
+            next_seq: AtomicU64::new(1),
+            clock,
+        }
+    }
+
+    fn now(&self) -> i64 {
+        self.clock.now_millis()
+    }
+
+    /// Start a job of `kind` working on `key`
+    pub fn start(&self, kind: JobKind, key: impl Into<String>) -> Job {
+        self.start_as(kind, key, self.timing(kind), None)
+    }
+
+    /// Start a job of `kind` working on `key` with its own `timing` and,
+    /// when given, `outcome`. A pending [`Self::fail_next`] still wins over
+    /// `outcome`; the kind's failure hook only decides without one.
+    pub fn start_as(
+        &self,
+        kind: JobKind,
+        key: impl Into<String>,
+        timing: JobTiming,
+        outcome: Option<JobOutcome>,
+    ) -> Job {
+        let mut job = Job {
+            id: uuid::Uuid::new_v4().to_string(),
+            kind,
+            key: key.into(),
+            created_at: self.now(),
+            timing,
+            outcome: JobOutcome::Success,
+            settled_at: None,
+        };
+        if let Some(message) = self.take_forced_failure(kind) {
+            job.outcome = JobOutcome::Failed { message };
+        } else if let Some(outcome) = outcome {
+            job.outcome = outcome;
+        } else if let Some(message) = self
+            .hooks
+            .get(&kind)
+            .and_then(|hook| (hook.value().as_ref())(&job))
+        {
+            job.outcome = JobOutcome::Failed { message };
+        }
+        self.jobs.insert(job.id.clone(), job.clone());
+        job
+    }
+
+    fn take_forced_failure(&self, kind: JobKind) -> Option<String> {
+        let mut forced = self.forced_failures.get_mut(&kind)?;
+        forced.remaining = forced.remaining.checked_sub(1)?;
+        let message = forced.message.clone();
+        if forced.remaining == 0 {
+            drop(forced);
+            self.forced_failures.remove(&kind);
+        }
+        Some(message)
+    }
+
+    pub fn get(&self, id: &str) -> Option<Job> {
+        self.jobs.get(id).map(|j| j.clone())
+    }
+
+    /// Insert or replace a job record as given. Its current status counts
+    /// as reported, so restored jobs are not notified again.
+    pub fn insert(&self, job: Job) {
+        self.reported
+            .insert(job.id.clone(), reported(&job.status_at(self.now())));
+        self.jobs.insert(job.id.clone(), job);
+    }
+
+    pub fn remove(&self, id: &str) -> bool {
+        self.reported.remove(id);
+        self.jobs.remove(id).is_some()
+    }
+
+    /// Current status of a job
+    pub fn status(&self, id: &str) -> Option<JobStatus> {
+        self.jobs.get(id).map(|j| j.status_at(self.now()))
+    }
+
+    /// All jobs, optionally of one kind, oldest first
+    pub fn list(&self, kind: Option<JobKind>) -> Vec<Job> {
+        let mut jobs: Vec<Job> = self
+            .jobs
+            .iter()
+            .filter(|j| kind.is_none_or(|k| j.kind == k))
+            .map(|j| j.value().clone())
+            .collect();
+        jobs.sort_by(|a, b| {
+            a.created_at
+                .cmp(&b.created_at)
+                .then_with(|| a.id.cmp(&b.id))
+        });
+        jobs
+    }
+
+    /// Phase durations new jobs of `kind` get
+    pub fn timing(&self, kind: JobKind) -> JobTiming {
+        self.timings.get(&kind).map(|t| *t).unwrap_or_default()
+    }
+
+    pub fn set_timing(&self, kind: JobKind, timing: JobTiming) {
+        self.timings.insert(kind, timing);
+    }
+
+    /// Install a hook deciding which new jobs of `kind` fail
+    pub fn set_failure_hook(&self, kind: JobKind, hook: FailureHook) {
+        self.hooks.insert(kind, hook);
+    }
+
+    pub fn clear_failure_hook(&self, kind: JobKind) {
+        self.hooks.remove(&kind);
+    }
+
+    /// Make the next `count` jobs of `kind` fail with `message`
+    pub fn fail_next(&self, kind: JobKind, count: u32, message: impl Into<String>) {
+        if count == 0 {
+            self.forced_failures.remove(&kind);
+            return;
+        }
+        self.forced_failures.insert(
+            kind,
+            ForcedFailures {
+                remaining: count,
+                message: message.into(),
+            },
+        );
+    }
+
+    /// Forget every pending [`Self::fail_next`]
+    pub fn clear_forced_failures(&self) {
+        self.forced_failures.clear();
+    }
+
+    /// Finish a running job right away with `outcome`. Returns false if the
+    /// job is unknown or already finished.
+    pub fn settle(&self, id: &str, outcome: JobOutcome) -> bool {
+        let now = self.now();
+        match self.jobs.get_mut(id) {
+            Some(mut job) if !job.status_at(now).phase.is_finished() => {
+                job.outcome = outcome;
+                job.settled_at = Some(now);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Record a notification for every job whose status moved on since it
+    /// was last reported, returning them oldest job first
+    pub fn observe(&self) -> Vec<JobNotification> {
+        let now = self.now();
+        let mut observed = Vec::new();
+        for job in self.list(None) {
+            let status = job.status_at(now);
+            let current = reported(&status);
+            if self.reported.insert(job.id.clone(), current) == Some(current) {
+                continue;
+            }
+            observed.push(JobNotification {
+                seq: self.next_seq.fetch_add(1, Ordering::Relaxed),
+                event: if status.phase.is_finished() {
+                    JobEvent::Finished
+                } else {
+                    JobEvent::Progress
+                },
+                job_id: job.id,
+                kind: job.kind,
+                key: job.key,
+                phase: status.phase,
+                progress: status.progress,
+                message: status.message,
+                timestamp: now,
+            });
+        }
+        if !observed.is_empty()
+            && let Ok(mut notifications) = self.notifications.write()
+        {
+            notifications.extend(observed.iter().cloned());
+            let excess = notifications.len().saturating_sub(NOTIFICATION_CAPACITY);
+            notifications.drain(..excess);
+        }
+        observed
+    }
+
+    /// Notifications after sequence number `since`, oldest first
+    pub fn notifications(&self, since: Option<u64>) -> Vec<JobNotification> {
+        self.notifications
+            .read()
+            .map(|notifications| {
+                notifications
+                    .iter()
+                    .filter(|n| since.is_none_or(|since| n.seq > since))
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Drop every job and notification; timings and failure rules stay
+    pub fn clear(&self) {
+        self.jobs.clear();
+        self.reported.clear();
+        if let Ok(mut notifications) = self.notifications.write() {
+            notifications.clear();
+        }
+    }
+}
+
+impl Default for JobEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_jobs_progress_through_phases() {
+        let engine = JobEngine::new();
+        engine.set_timing(
+            JobKind::Translation,
+            JobTiming {
+                pending_ms: 100,
+                in_progress_ms: 1000,
+            },
+        );
+        engine.fail_next(JobKind::Translation, 1, "corrupt file");
+
+        let failing = engine.start(JobKind::Translation, "urn:a");
+        let job = engine.start(JobKind::Translation, "urn:b");
+        let at = |ms: i64| job.status_at(job.created_at + ms);
+        assert_eq!(at(50).phase, JobPhase::Pending);
+        assert_eq!(
+            (at(600).phase, at(600).progress),
+            (JobPhase::InProgress, 50)
+        );
+        assert_eq!(at(1100).phase, JobPhase::Success);
+
+        let failed = failing.status_at(failing.created_at + 1100);
+        assert_eq!(failed.phase, JobPhase::Failed);
+        assert_eq!(failed.message.as_deref(), Some("corrupt file"));
+
+        assert!(engine.settle(&job.id, JobOutcome::Success));
+        assert_eq!(engine.status(&job.id).unwrap().phase, JobPhase::Success);
+        assert!(!engine.settle(&job.id, JobOutcome::Success));
+    }
+
+    #[test]
+    fn test_observed_progress_is_notified_once_per_step() {
+        let clock = Arc::new(MockClock::new());
+        clock.freeze(Some(0));
+        let engine = JobEngine::with_clock(clock.clone());
+        engine.set_timing(
+            JobKind::Download,
+            JobTiming {
+                pending_ms: 100,
+                in_progress_ms: 1000,
+            },
+        );
+        let job = engine.start(JobKind::Download, "urn:a");
+
+        let observed = engine.observe();
+        assert_eq!(observed.len(), 1);
+        assert_eq!(
+            (observed[0].event, observed[0].phase),
+            (JobEvent::Progress, JobPhase::Pending)
+        );
+        assert!(engine.observe().is_empty());
+
+        // 30% in: started, then past the first quarter
+        clock.advance(400);
+        let observed = engine.observe();
+        assert_eq!(
+            (observed[0].phase, observed[0].progress),
+            (JobPhase::InProgress, 30)
+        );
+        clock.advance(100);
+        assert!(engine.observe().is_empty());
+
+        engine.settle(
+            &job.id,
+            JobOutcome::Failed {
+                message: "disk full".into(),
+            },
+        );
+        let observed = engine.observe();
+        assert_eq!(observed[0].event, JobEvent::Finished);
+        assert_eq!(observed[0].message.as_deref(), Some("disk full"));
+
+        let feed = engine.notifications(Some(1));
+        assert_eq!(feed.iter().map(|n| n.seq).collect::<Vec<_>>(), [2, 3]);
+    }
+}
@@ -0,0 +1,455 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+use crate::pattern::RoutePattern;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::ops::{RangeFrom, RangeInclusive, RangeToInclusive};
+use std::path::Path;
+use std::sync::{Mutex, RwLock};
+
+/// A request received by the mock
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JournalEntry {
+    pub id: String,
+    /// When the request was received (epoch millis)
+    pub timestamp: i64,
+    pub method: String,
+    pub path: String,
+    #[serde(default)]
+    pub query: Option<String>,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    /// Request body, when it was UTF-8 text of a known length up to
+    /// [`MAX_RECORDED_BODY`] bytes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<String>,
+    /// Response status sent back
+    pub status: u16,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub response_headers: BTreeMap<String, String>,
+    /// Response body, recorded under the same conditions as `body`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_body: Option<String>,
+    pub duration_ms: u64,
+}
+
+/// Largest request or response body recorded in the journal, in bytes
+pub const MAX_RECORDED_BODY: u64 = 64 * 1024;
+
+/// Criteria selecting journal entries; every criterion given must hold
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RequestMatcher {
+    pub method: Option<String>,
+    /// Route pattern the path must match, e.g. `/oss/v2/buckets/*/objects/**`
+    pub path: Option<String>,
+    /// `name:value` header the request must carry; the name is compared
+    /// case-insensitively
+    pub header: Option<String>,
+    /// Text the request body must contain
+    pub body_contains: Option<String>,
+    /// Response status sent back
+    pub status: Option<u16>,
+    /// Only requests received at or after this time (epoch millis)
+    pub since: Option<i64>,
+}
+
+impl RequestMatcher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn method(mut self, method: &str) -> Self {
+        self.method = Some(method.to_string());
+        self
+    }
+
+    pub fn path(mut self, pattern: &str) -> Self {
+        self.path = Some(pattern.to_string());
+        self
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.header = Some(format!("{}:{}", name, value));
+        self
+    }
+
+    pub fn body_contains(mut self, text: &str) -> Self {
+        self.body_contains = Some(text.to_string());
+        self
+    }
+
+    pub fn status(mut self, status: u16) -> Self {
+        self.status = Some(status);
+        self
+    }
+
+    /// Check the criteria can be applied, i.e. the path is a valid pattern
+    /// and the header has a name
+    pub fn validate(&self) -> Result<(), String> {
+        self.compile().map(|_| ())
+    }
+
+    fn compile(&self) -> Result<CompiledMatcher<'_>, String> {
+        let path = self.path.as_deref().map(str::parse).transpose()?;
+        let header = match self.header.as_deref() {
+            Some(header) => match header.split_once(':') {
+                Some((name, value)) if !name.trim().is_empty() => {
+                    Some((name.trim().to_lowercase(), value.trim()))
+                }
+                _ => {
+                    return Err(format!(
+                        "Invalid header criterion '{}': expected 'name:value'",
+                        header
+                    ));
+                }
+            },
+            None => None,
+        };
+        Ok(CompiledMatcher {
+            matcher: self,
+            path,
+            header,
+        })
+    }
+
+    /// Whether `entry` meets every criterion; an invalid matcher matches
+    /// nothing
+    pub fn matches(&self, entry: &JournalEntry) -> bool {
+        self.compile().is_ok_and(|compiled| compiled.matches(entry))
+    }
+}
+
+impl fmt::Display for RequestMatcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut criteria = Vec::new();
+        if let Some(ref method) = self.method {
+            criteria.push(format!("method {}", method));
+        }
+        if let Some(ref path) = self.path {
+            criteria.push(format!("path {}", path));
+        }
+        if let Some(ref header) = self.header {
+            criteria.push(format!("header {}", header));
+        }
+        if let Some(ref text) = self.body_contains {
+            criteria.push(format!("body containing '{}'", text));
+        }
+        if let Some(status) = self.status {
+            criteria.push(format!("status {}", status));
+        }
+        if let Some(since) = self.since {
+            criteria.push(format!("received since {}", since));
+        }
+        if criteria.is_empty() {
+            return f.write_str("any request");
+        }
+        write!(f, "requests with {}", criteria.join(", "))
+    }
+}
+
+/// A matcher with its pattern parsed
+struct CompiledMatcher<'a> {
+    matcher: &'a RequestMatcher,
+    path: Option<RoutePattern>,
+    header: Option<(String, &'a str)>,
+}
+
+impl CompiledMatcher<'_> {
+    fn matches(&self, entry: &JournalEntry) -> bool {
+        let m = self.matcher;
+        m.method
+            .as_ref()
+            .is_none_or(|method| method.eq_ignore_ascii_case(&entry.method))
+            && self
+                .path
+                .as_ref()
+                .is_none_or(|path| path.matches(&entry.method, &entry.path, &[]))
+            && self.header.as_ref().is_none_or(|(name, value)| {
+                entry
+                    .headers
+                    .iter()
+                    .any(|(n, v)| n.eq_ignore_ascii_case(name) && v == value)
+            })
+            && m.body_contains.as_ref().is_none_or(|text| {
+                entry
+                    .body
+                    .as_ref()
+                    .is_some_and(|body| body.contains(text.as_str()))
+            })
+            && m.status.is_none_or(|status| status == entry.status)
+            && m.since.is_none_or(|since| entry.timestamp >= since)
+    }
+}
+
+/// How many requests a verification expects
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Times {
+    Exactly(usize),
+    AtLeast(usize),
+    AtMost(usize),
+    Between(usize, usize),
+}
+
+impl Times {
+    pub fn contains(&self, count: usize) -> bool {
+        match *self {
+            Times::Exactly(n) => count == n,
+            Times::AtLeast(n) => count >= n,
+            Times::AtMost(n) => count <= n,
+            Times::Between(min, max) => (min..=max).contains(&count),
+        }
+    }
+}
+
+impl From<usize> for Times {
+    fn from(n: usize) -> Self {
+        Times::Exactly(n)
+    }
+}
+
+impl From<RangeFrom<usize>> for Times {
+    fn from(range: RangeFrom<usize>) -> Self {
+        Times::AtLeast(range.start)
+    }
+}
+
+impl From<RangeToInclusive<usize>> for Times {
+    fn from(range: RangeToInclusive<usize>) -> Self {
+        Times::AtMost(range.end)
+    }
+}
+
+impl From<RangeInclusive<usize>> for Times {
+    fn from(range: RangeInclusive<usize>) -> Self {
+        Times::Between(*range.start(), *range.end())
+    }
+}
+
+impl fmt::Display for Times {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Times::Exactly(n) => write!(f, "exactly {}", n),
+            Times::AtLeast(n) => write!(f, "at least {}", n),
+            Times::AtMost(n) => write!(f, "at most {}", n),
+            Times::Between(min, max) => write!(f, "between {} and {}", min, max),
+        }
+    }
+}
+
+/// Journal of received requests, optionally persisted as ndjson so it
+/// survives restarts
+pub struct RequestJournal {
+    entries: RwLock<Vec<JournalEntry>>,
+    sink: Mutex<Option<File>>,
+}
+
+impl RequestJournal {
+    pub fn new() -> Self {
+        Self {
+            entries: RwLock::new(Vec::new()),
+            sink: Mutex::new(None),
+        }
+    }
+
+    /// Persist the journal to an ndjson file, first loading the entries a
+    /// previous run left in it. Returns the number of entries loaded.
+    pub fn persist_to(&self, path: &Path) -> std::io::Result<usize> {
+        let mut loaded = Vec::new();
+        if path.exists() {
+            for (index, line) in BufReader::new(File::open(path)?).lines().enumerate() {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                match serde_json::from_str::<JournalEntry>(&line) {
+                    Ok(entry) => loaded.push(entry),
+                    // A crash can leave a truncated last line behind
+                    Err(e) => tracing::warn!(
+                        "Skipping journal line {} in {}: {}",
+                        index + 1,
+                        path.display(),
+                        e
+                    ),
+                }
+            }
+        }
+        let count = loaded.len();
+
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        if let Ok(mut entries) = self.entries.write() {
+            let current = std::mem::take(&mut *entries);
+            *entries = loaded;
+            entries.extend(current);
+        }
+        if let Ok(mut sink) = self.sink.lock() {
+            *sink = Some(file);
+        }
+        Ok(count)
+    }
+
+    /// Record a request
+    pub fn record(&self, entry: JournalEntry) {
+        if let Ok(mut sink) = self.sink.lock()
+            && let Some(file) = sink.as_mut()
+            && let Ok(mut line) = serde_json::to_string(&entry)
+        {
+            line.push('\n');
+            if let Err(e) = file.write_all(line.as_bytes()) {
+                tracing::warn!("Failed to append to request journal: {}", e);
+            }
+        }
+        if let Ok(mut entries) = self.entries.write() {
+            entries.push(entry);
+        }
+    }
+
+    /// All recorded requests, oldest first
+    pub fn entries(&self) -> Vec<JournalEntry> {
+        self.entries.read().map(|e| e.clone()).unwrap_or_default()
+    }
+
+    /// Recorded requests `matcher` selects, oldest first
+    pub fn find(&self, matcher: &RequestMatcher) -> Vec<JournalEntry> {
+        let Ok(compiled) = matcher.compile() else {
+            return Vec::new();
+        };
+        self.entries
+            .read()
+            .map(|entries| {
+                entries
+                    .iter()
+                    .filter(|entry| compiled.matches(entry))
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Check that `matcher` selects a number of recorded requests `times`
+    /// allows, describing the mismatch otherwise
+    pub fn verify(&self, matcher: &RequestMatcher, times: Times) -> Result<(), String> {
+        matcher.validate()?;
+        let count = self.find(matcher).len();
+        if times.contains(count) {
+            return Ok(());
+        }
+        let received: Vec<String> = self
+            .entries()
+            .iter()
+            .map(|entry| format!("{} {} -> {}", entry.method, entry.path, entry.status))
+            .collect();
+        Err(format!(
+            "expected {} {}, received {}. Requests received: [{}]",
+            times,
+            matcher,
+            count,
+            received.join(", ")
+        ))
+    }
+
+    /// Drop all recorded requests, including the persisted ones
+    pub fn clear(&self) {
+        if let Ok(mut entries) = self.entries.write() {
+            entries.clear();
+        }
+        if let Ok(mut sink) = self.sink.lock()
+            && let Some(file) = sink.as_mut()
+            && let Err(e) = file.set_len(0)
+        {
+            tracing::warn!("Failed to truncate request journal: {}", e);
+        }
+    }
+}
+
+impl Default for RequestJournal {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(path: &str) -> JournalEntry {
+        JournalEntry {
+            id: uuid::Uuid::new_v4().to_string(),
+            timestamp: 0,
+            method: "GET".to_string(),
+            path: path.to_string(),
+            query: None,
+            headers: BTreeMap::new(),
+            body: None,
+            status: 200,
+            response_headers: BTreeMap::new(),
+            response_body: None,
+            duration_ms: 1,
+        }
+    }
+
+    #[test]
+    fn test_journal_survives_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("journal.ndjson");
+
+        let journal = RequestJournal::new();
+        journal.persist_to(&path).unwrap();
+        journal.record(entry("/oss/v2/buckets"));
+        journal.record(entry("/oss/v2/buckets/b1/details"));
+
+        let restarted = RequestJournal::new();
+        assert_eq!(restarted.persist_to(&path).unwrap(), 2);
+        restarted.record(entry("/project/v1/hubs"));
+        let paths: Vec<String> = restarted.entries().into_iter().map(|e| e.path).collect();
+        assert_eq!(
+            paths,
+            [
+                "/oss/v2/buckets",
+                "/oss/v2/buckets/b1/details",
+                "/project/v1/hubs"
+            ]
+        );
+
+        restarted.clear();
+        assert_eq!(RequestJournal::new().persist_to(&path).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_matchers_select_and_verify_requests() {
+        let journal = RequestJournal::new();
+        journal.record(entry("/oss/v2/buckets"));
+        let mut upload = entry("/oss/v2/buckets/b1/objects/a.rvt/signeds3upload");
+        upload.method = "POST".into();
+        upload
+            .headers
+            .insert("content-type".into(), "application/json".into());
+        upload.body = Some(r#"{"uploadKey": "k1"}"#.into());
+        journal.record(upload);
+
+        let uploads = RequestMatcher::new()
+            .method("post")
+            .path("/oss/v2/buckets/*/objects/**")
+            .header("Content-Type", "application/json")
+            .body_contains("k1");
+        assert_eq!(journal.find(&uploads).len(), 1);
+        assert!(journal.verify(&uploads, 1.into()).is_ok());
+        assert!(journal.verify(&RequestMatcher::new(), (2..).into()).is_ok());
+
+        let error = journal
+            .verify(&RequestMatcher::new().status(404), (1..=3).into())
+            .unwrap_err();
+        assert!(error.starts_with("expected between 1 and 3 requests with status 404, received 0"));
+        assert!(error.contains("GET /oss/v2/buckets -> 200"));
+        assert!(
+            journal
+                .verify(&RequestMatcher::new().path("oss"), 0.into())
+                .is_err()
+        );
+    }
+}
@@ -2,7 +2,11 @@
 // Copyright 2024-2025 Dmytro Yemelianov
 
 use crate::error::Result;
-use crate::state::{auth, buckets, issues, objects, projects, translations, webhooks};
+use crate::state::{
+    audit, auth, backend, blobs, buckets, clients, clock, entitlements, events, issues, jobs,
+    journal, objects, persistence, projects, templates, translations, uploads, users, webhooks,
+};
+use dashmap::DashMap;
 use std::sync::Arc;
 
 /// Central state manager for all APS resources
@@ -16,38 +20,287 @@ pub struct StateManager {
     pub objects: Arc<objects::ObjectState>,
     /// Data Management projects storage
     pub projects: Arc<projects::ProjectState>,
+    /// Simulator behind every asynchronous job
+    pub jobs: Arc<jobs::JobEngine>,
     /// Model Derivative translations storage
     pub translations: Arc<translations::TranslationState>,
     /// ACC Issues storage
     pub issues: Arc<issues::IssuesState>,
     /// Webhooks storage
     pub webhooks: Arc<webhooks::WebhooksState>,
+    /// ACC product activations per project
+    pub entitlements: Arc<entitlements::EntitlementState>,
+    /// OSS signed upload sessions
+    pub uploads: Arc<uploads::UploadState>,
+    /// Applications allowed to request tokens
+    pub clients: Arc<clients::ClientRegistry>,
+    /// Users 3-legged tokens act for
+    pub users: Arc<users::UserState>,
+    /// Requests received by the mock
+    pub journal: Arc<journal::RequestJournal>,
+    /// Records changed by requests, when `--audit-log` is on
+    pub audit: Arc<audit::AuditLog>,
+    /// Subscribers to changes of buckets, objects, issues, jobs and hooks
+    pub events: Arc<events::EventBus>,
+    /// Clock behind token expiry, job progress and record timestamps
+    pub clock: Arc<clock::MockClock>,
+    /// Named captures of the state, by name
+    pub snapshots: Arc<DashMap<String, persistence::NamedSnapshot>>,
 }
 
 impl StateManager {
     /// Create a new state manager
     pub fn new() -> Self {
+        Self::with_blob_store(Arc::new(blobs::MemoryBlobStore::new()))
+    }
+
+    /// Create a state manager keeping contents in `blobs`
+    pub fn with_blob_store(blobs: Arc<dyn blobs::BlobStore>) -> Self {
+        let clock = Arc::new(clock::MockClock::new());
+        let jobs = Arc::new(jobs::JobEngine::with_clock(clock.clone()));
+        Self {
+            auth: Arc::new(auth::AuthState::with_clock(clock.clone())),
+            buckets: Arc::new(buckets::BucketState::with_clock(clock.clone())),
+            objects: Arc::new(objects::ObjectState::with_blob_store(blobs)),
+            projects: Arc::new(projects::ProjectState::new()),
+            translations: Arc::new(translations::TranslationState::new(jobs.clone())),
+            jobs,
+            issues: Arc::new(issues::IssuesState::with_clock(clock.clone())),
+            webhooks: Arc::new(webhooks::WebhooksState::with_clock(clock.clone())),
+            entitlements: Arc::new(entitlements::EntitlementState::new()),
+            uploads: Arc::new(uploads::UploadState::with_clock(clock.clone())),
+            clients: Arc::new(clients::ClientRegistry::new()),
+            users: Arc::new(users::UserState::new()),
+            journal: Arc::new(journal::RequestJournal::new()),
+            audit: Arc::new(audit::AuditLog::new()),
+            events: Arc::new(events::EventBus::new()),
+            clock,
+            snapshots: Arc::new(DashMap::new()),
+        }
+    }
+
+    /// A state with resources of its own (buckets, objects, hubs and
+    /// projects, jobs, issues, hooks, entitlements and uploads) that shares
+    /// tokens, clients, users, the clock, the request journal, the audit log
+    /// and the event bus with this one
+    pub fn partition(&self, blobs: Arc<dyn blobs::BlobStore>) -> Self {
+        let clock = self.clock.clone();
+        let jobs = Arc::new(jobs::JobEngine::with_clock(clock.clone()));
         Self {
-            auth: Arc::new(auth::AuthState::new()),
-            buckets: Arc::new(buckets::BucketState::new()),
-            objects: Arc::new(objects::ObjectState::new()),
+            auth: self.auth.clone(),
+            buckets: Arc::new(buckets::BucketState::with_clock(clock.clone())),
+            objects: Arc::new(objects::ObjectState::with_blob_store(blobs)),
             projects: Arc::new(projects::ProjectState::new()),
-            translations: Arc::new(translations::TranslationState::new()),
-            issues: Arc::new(issues::IssuesState::new()),
-            webhooks: Arc::new(webhooks::WebhooksState::new()),
+            translations: Arc::new(translations::TranslationState::new(jobs.clone())),
+            jobs,
+            issues: Arc::new(issues::IssuesState::with_clock(clock.clone())),
+            webhooks: Arc::new(webhooks::WebhooksState::with_clock(clock.clone())),
+            entitlements: Arc::new(entitlements::EntitlementState::new()),
+            uploads: Arc::new(uploads::UploadState::with_clock(clock.clone())),
+            clients: self.clients.clone(),
+            users: self.users.clone(),
+            journal: self.journal.clone(),
+            audit: self.audit.clone(),
+            events: self.events.clone(),
+            clock,
+            snapshots: Arc::new(DashMap::new()),
+        }
+    }
+
+    /// OAuth tokens
+    pub fn auth(&self) -> &auth::AuthState {
+        &self.auth
+    }
+
+    /// OSS buckets
+    pub fn buckets(&self) -> &buckets::BucketState {
+        &self.buckets
+    }
+
+    /// OSS objects
+    pub fn objects(&self) -> &objects::ObjectState {
+        &self.objects
+    }
+
+    /// Data Management hubs, projects, folders and items
+    pub fn projects(&self) -> &projects::ProjectState {
+        &self.projects
+    }
+
+    /// Model Derivative translations
+    pub fn translations(&self) -> &translations::TranslationState {
+        &self.translations
+    }
+
+    /// ACC issues
+    pub fn issues(&self) -> &issues::IssuesState {
+        &self.issues
+    }
+
+    /// Webhook subscriptions
+    pub fn webhooks(&self) -> &webhooks::WebhooksState {
+        &self.webhooks
+    }
+
+    /// Create a project in a hub, populated from `template` when given.
+    /// Returns `None` if the hub is unknown.
+    pub fn create_project(
+        &self,
+        hub_id: &str,
+        name: String,
+        template: Option<&templates::ProjectTemplate>,
+    ) -> Option<projects::ProjectInfo> {
+        let project = self.projects.create_project(hub_id, name)?;
+        if let Some(template) = template {
+            self.populate_project(&project.id, template);
         }
+        Some(project)
     }
 
-    /// Load state from a file (if provided)
-    pub fn load_from_file(&self, _path: &std::path::Path) -> Result<()> {
-        // TODO: Implement state persistence
-        Ok(())
+    /// Create the folders, models and issues of `template` in a project
+    pub fn populate_project(&self, project_id: &str, template: &templates::ProjectTemplate) {
+        for folder in &template.folders {
+            self.instantiate_folder(project_id, None, folder);
+        }
+        // ACC services address projects without the `b.` prefix
+        let acc_project_id = project_id.strip_prefix("b.").unwrap_or(project_id);
+        for issue in &template.issues {
+            let created = self.issues.create_issue(
+                acc_project_id.to_string(),
+                issue.title.clone(),
+                issue.description.clone(),
+            );
+            if issue.status != created.status {
+                self.issues
+                    .update_issue_status(acc_project_id, &created.id, issue.status.clone());
+            }
+        }
+    }
+
+    fn instantiate_folder(
+        &self,
+        project_id: &str,
+        parent_id: Option<String>,
+        template: &templates::FolderTemplate,
+    ) {
+        let folder = self
+            .projects
+            .create_folder(project_id, parent_id, template.name.clone());
+        for model in &template.models {
+            let object_key = format!("{}-{}", uuid::Uuid::new_v4().simple(), model.name);
+            let object =
+                self.objects
+                    .upload_object(templates::DM_BUCKET.to_string(), object_key, 0, None);
+            if model.translated {
+                let job = self.translations.create_job(object.object_id.clone());
+                self.jobs.settle(&job.job_id, jobs::JobOutcome::Success);
+            }
+            self.projects.create_item(
+                project_id,
+                &folder.id,
+                model.name.clone(),
+                object.object_id,
+                None,
+            );
+        }
+        for child in &template.folders {
+            self.instantiate_folder(project_id, Some(folder.id.clone()), child);
+        }
     }
 
-    /// Save state to a file (if provided)
-    pub fn save_to_file(&self, _path: &std::path::Path) -> Result<()> {
-        // TODO: Implement state persistence
-        Ok(())
+    /// Load state saved by [`Self::save_to_file`]; a missing file leaves the
+    /// state as it is
+    pub fn load_from_file(&self, path: &std::path::Path) -> Result<()> {
+        self.load_from(&backend::JsonFileBackend::new(path.to_path_buf()))
+    }
+
+    /// Save every state module to a JSON file
+    pub fn save_to_file(&self, path: &std::path::Path) -> Result<()> {
+        self.save_to(&backend::JsonFileBackend::new(path.to_path_buf()))
+    }
+
+    /// Load state saved to a backend; leaves the state as it is when nothing
+    /// was saved yet
+    pub fn load_from(&self, backend: &dyn backend::StateBackend) -> Result<()> {
+        match backend.load()? {
+            Some(file) => file.restore(self),
+            None => Ok(()),
+        }
+    }
+
+    /// Replace every record with what a backend holds; leaves the state as
+    /// it is when nothing was saved yet
+    pub fn reload_from(&self, backend: &dyn backend::StateBackend) -> Result<()> {
+        match backend.load()? {
+            Some(file) => {
+                self.clear();
+                file.restore(self)
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// The user a 3-legged access token acts for, if it is valid
+    pub fn token_user(&self, access_token: &str) -> Option<users::UserInfo> {
+        let token = self.auth.lookup_token(access_token)?;
+        self.users.get_user(token.user_id.as_deref()?)
+    }
+
+    /// Drop every record a state file holds; settings such as job timings,
+    /// token usage and the request journal stay
+    pub fn clear(&self) {
+        self.auth.clear();
+        self.buckets.clear();
+        self.objects.clear();
+        self.projects.clear();
+        self.translations.clear();
+        self.jobs.clear();
+        self.issues.clear();
+        self.webhooks.clear();
+        self.entitlements.clear();
+        self.uploads.clear();
+    }
+
+    /// Capture every state module under `name`, replacing an earlier
+    /// snapshot with that name
+    pub fn take_snapshot(&self, name: &str) -> persistence::NamedSnapshot {
+        let snapshot = persistence::NamedSnapshot {
+            name: name.to_string(),
+            created_at: self.clock.now_millis(),
+            file: persistence::StateFile::capture(self),
+        };
+        self.snapshots.insert(name.to_string(), snapshot.clone());
+        snapshot
+    }
+
+    /// Replace every record with the snapshot named `name`; `Ok(false)` if
+    /// there is none. Snapshots can be restored any number of times.
+    pub fn restore_snapshot(&self, name: &str) -> Result<bool> {
+        let Some(snapshot) = self.snapshots.get(name).map(|s| s.file.clone()) else {
+            return Ok(false);
+        };
+        self.clear();
+        snapshot.restore(self)?;
+        Ok(true)
+    }
+
+    /// Put the state back as a freshly started server has it: every record
+    /// is dropped, the default hub and project come back, and the request
+    /// journal, token usage and pending forced job failures are cleared and
+    /// the clock follows the system clock again. Job timings, failure hooks
+    /// and named snapshots stay.
+    pub fn reset(&self) {
+        self.clear();
+        self.projects.init_defaults();
+        self.jobs.clear_forced_failures();
+        self.journal.clear();
+        self.auth.clear_usage();
+        self.clock.reset();
+    }
+
+    /// Save every state module to a backend
+    pub fn save_to(&self, backend: &dyn backend::StateBackend) -> Result<()> {
+        backend.save(&persistence::StateFile::capture(self))
     }
 }
 
@@ -56,3 +309,73 @@ impl Default for StateManager {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::buckets::BucketInfo;
+    use crate::state::objects::ObjectInfo;
+    use crate::state::projects::{HubInfo, ProjectInfo};
+
+    #[test]
+    fn test_typed_accessors_import_and_iterate() {
+        let state = StateManager::new();
+        let bucket = |key: &str| BucketInfo {
+            bucket_key: key.to_string(),
+            bucket_owner: "embedder".to_string(),
+            created_date: 0,
+            policy_key: "transient".to_string(),
+            permissions: vec![],
+        };
+        assert_eq!(
+            state.buckets().import_buckets([bucket("b2"), bucket("b1")]),
+            2
+        );
+
+        let seeded = state
+            .objects()
+            .upload_object("b1".into(), "seed.rvt".into(), 3, None);
+        state.objects().insert_object(ObjectInfo {
+            object_key: "a.rvt".to_string(),
+            ..seeded.clone()
+        });
+        let keys: Vec<String> = state.objects().iter().map(|o| o.object_key).collect();
+        assert_eq!(keys, ["a.rvt", "seed.rvt"]);
+        assert_eq!(state.buckets().iter().next().unwrap().bucket_key, "b1");
+
+        state.projects().insert_hub(HubInfo {
+            id: "b.hub".to_string(),
+            name: "Hub".to_string(),
+            region: "EMEA".to_string(),
+        });
+        state.projects().insert_project(ProjectInfo {
+            id: "b.project".to_string(),
+            hub_id: "b.hub".to_string(),
+            name: "Project".to_string(),
+        });
+        assert_eq!(state.projects().list_projects("b.hub")[0].name, "Project");
+        assert_eq!(state.webhooks().len(), 0);
+    }
+
+    #[test]
+    fn test_reset_restores_a_fresh_state() {
+        let state = StateManager::new();
+        state.buckets.create_bucket("b1".into(), "transient".into());
+        state.projects.delete_hub("b.default-hub");
+        state.jobs.fail_next(jobs::JobKind::Translation, 1, "boom");
+        let token = state.auth.generate_token("app", 3600, None);
+        state.auth.record_usage(&token.access_token, Some(&token));
+        state.take_snapshot("golden");
+
+        state.reset();
+        assert!(state.buckets.list_buckets().is_empty());
+        assert!(state.auth.get_token("app").is_none());
+        assert!(state.auth.usage().is_empty());
+        assert!(state.projects.get_project("b.default-project").is_some());
+        assert_eq!(state.projects.list_hubs().len(), 1);
+        // The forced failure is gone
+        let job = state.jobs.start(jobs::JobKind::Translation, "urn");
+        assert!(matches!(job.outcome, jobs::JobOutcome::Success));
+        assert!(state.snapshots.contains_key("golden"));
+    }
+}
@@ -1,13 +1,29 @@
 // SPDX-License-Identifier: Apache-2.0
 // Copyright 2024-2025 Dmytro Yemelianov
 
+pub mod audit;
 pub mod auth;
+pub mod backend;
+pub mod blobs;
 pub mod buckets;
+pub mod clients;
+pub mod clock;
+pub mod entitlements;
+pub mod events;
+pub mod fixtures;
 pub mod issues;
+pub mod jobs;
+pub mod journal;
 pub mod manager;
 pub mod objects;
+pub mod persistence;
 pub mod projects;
+pub mod snapshot;
+pub mod synthetic;
+pub mod templates;
 pub mod translations;
+pub mod uploads;
+pub mod users;
 pub mod webhooks;
 
 pub use manager::StateManager;
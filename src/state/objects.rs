@@ -1,8 +1,16 @@
 // SPDX-License-Identifier: Apache-2.0
 // Copyright 2024-2025 Dmytro Yemelianov
 
+use crate::state::blobs::{BlobStore, MemoryBlobStore};
+use crate::state::snapshot::SnapshotLock;
+use bytes::Bytes;
 use dashmap::DashMap;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// Prefix of request/response headers carrying custom object metadata
+pub const METADATA_HEADER_PREFIX: &str = "x-ads-meta-";
 
 /// OSS object information
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -14,21 +22,124 @@ pub struct ObjectInfo {
     pub size: u64,
     pub content_type: String,
     pub location: String,
+    /// Custom metadata sent as `x-ads-meta-*` headers on upload
+    #[serde(default)]
+    pub metadata: BTreeMap<String, String>,
+}
+
+impl ObjectInfo {
+    /// Record of an object with the given key, size and content type
+    pub fn new(
+        bucket_key: &str,
+        object_key: &str,
+        size: u64,
+        content_type: Option<String>,
+    ) -> Self {
+        Self {
+            bucket_key: bucket_key.to_string(),
+            object_key: object_key.to_string(),
+            object_id: format!("urn:adsk.objects:os.object:{}/{}", bucket_key, object_key),
+            sha1: format!("sha1_{}", uuid::Uuid::new_v4()),
+            size,
+            content_type: content_type.unwrap_or_else(|| "application/octet-stream".to_string()),
+            location: format!(
+                "https://developer.api.autodesk.com/oss/v2/buckets/{}/objects/{}",
+                bucket_key, object_key
+            ),
+            metadata: BTreeMap::new(),
+        }
+    }
 }
 
 /// OSS object state
 pub struct ObjectState {
     /// Map of bucket_key -> objects
     objects: DashMap<String, DashMap<String, ObjectInfo>>,
+    /// Uploaded contents, under `oss/<bucket_key>/<object_key>`
+    blobs: Arc<dyn BlobStore>,
+    snapshot: SnapshotLock,
+}
+
+/// Blob key of an object's content
+fn blob_key(bucket_key: &str, object_key: &str) -> String {
+    format!("oss/{}/{}", bucket_key, object_key)
 }
 
 impl ObjectState {
     pub fn new() -> Self {
+        Self::with_blob_store(Arc::new(MemoryBlobStore::new()))
+    }
+
+    /// Object state keeping contents in `blobs`
+    pub fn with_blob_store(blobs: Arc<dyn BlobStore>) -> Self {
         Self {
             objects: DashMap::new(),
+            blobs,
+            snapshot: SnapshotLock::new(),
+        }
+    }
+
+    fn store_content(&self, bucket_key: &str, object_key: &str, content: Bytes) {
+        if let Err(e) = self.blobs.put(&blob_key(bucket_key, object_key), content) {
+            tracing::warn!(
+                "Storing the content of {}/{} failed: {}",
+                bucket_key,
+                object_key,
+                e
+            );
         }
     }
 
+    /// Upload an object with its content and custom metadata
+    pub fn put_object(
+        &self,
+        bucket_key: String,
+        object_key: String,
+        content: Bytes,
+        content_type: Option<String>,
+        metadata: BTreeMap<String, String>,
+    ) -> ObjectInfo {
+        let mut object =
+            ObjectInfo::new(&bucket_key, &object_key, content.len() as u64, content_type);
+        object.metadata = metadata;
+
+        let _guard = self.snapshot.mutate();
+        self.store_content(&bucket_key, &object_key, content);
+        let bucket_objects = self.objects.entry(bucket_key).or_default();
+        bucket_objects.insert(object_key, object.clone());
+        object
+    }
+
+    /// Get the uploaded content of an object (empty if only metadata is known)
+    pub fn get_content(&self, bucket_key: &str, object_key: &str) -> Option<Bytes> {
+        self.get_object(bucket_key, object_key)?;
+        Some(
+            self.blobs
+                .get(&blob_key(bucket_key, object_key))
+                .unwrap_or_default(),
+        )
+    }
+
+    /// Uploaded contents of every object, ordered by bucket and key
+    pub fn contents(&self) -> Vec<(String, String, Bytes)> {
+        let _guard = self.snapshot.snapshot();
+        self.blobs
+            .keys("oss/")
+            .into_iter()
+            .filter_map(|key| {
+                let (bucket_key, object_key) = key.strip_prefix("oss/")?.split_once('/')?;
+                let content = self.blobs.get(&key)?;
+                Some((bucket_key.to_string(), object_key.to_string(), content))
+            })
+            .collect()
+    }
+
+    /// Set the content of an object without touching its record
+    pub fn insert_content(&self, bucket_key: &str, object_key: &str, content: Bytes) {
+        let _guard = self.snapshot.mutate();
+        self.store_content(bucket_key, object_key, content);
+    }
+
     /// Upload an object
     pub fn upload_object(
         &self,
@@ -37,25 +148,21 @@ impl ObjectState {
         size: u64,
         content_type: Option<String>,
     ) -> ObjectInfo {
-        let object_id = format!("urn:adsk.objects:os.object:{}/{}", bucket_key, object_key);
-        let object = ObjectInfo {
-            bucket_key: bucket_key.clone(),
-            object_key: object_key.clone(),
-            object_id: object_id.clone(),
-            sha1: format!("sha1_{}", uuid::Uuid::new_v4()),
-            size,
-            content_type: content_type.unwrap_or_else(|| "application/octet-stream".to_string()),
-            location: format!(
-                "https://developer.api.autodesk.com/oss/v2/buckets/{}/objects/{}",
-                bucket_key, object_key
-            ),
-        };
+        let object = ObjectInfo::new(&bucket_key, &object_key, size, content_type);
 
+        let _guard = self.snapshot.mutate();
         let bucket_objects = self.objects.entry(bucket_key).or_default();
         bucket_objects.insert(object_key, object.clone());
         object
     }
 
+    /// Insert or replace an object record
+    pub fn insert_object(&self, object: ObjectInfo) {
+        let _guard = self.snapshot.mutate();
+        let bucket_objects = self.objects.entry(object.bucket_key.clone()).or_default();
+        bucket_objects.insert(object.object_key.clone(), object);
+    }
+
     /// Get an object
     pub fn get_object(&self, bucket_key: &str, object_key: &str) -> Option<ObjectInfo> {
         self.objects
@@ -64,21 +171,82 @@ impl ObjectState {
             .map(|o| o.clone())
     }
 
-    /// List objects in a bucket
+    /// List objects in a bucket, ordered by key
     pub fn list_objects(&self, bucket_key: &str) -> Vec<ObjectInfo> {
-        self.objects
-            .get(bucket_key)
-            .map(|bucket_objects| bucket_objects.iter().map(|o| o.value().clone()).collect())
-            .unwrap_or_default()
+        let mut objects: Vec<ObjectInfo> = {
+            let _guard = self.snapshot.snapshot();
+            self.objects
+                .get(bucket_key)
+                .map(|bucket_objects| bucket_objects.iter().map(|o| o.value().clone()).collect())
+                .unwrap_or_default()
+        };
+        objects.sort_by(|a, b| a.object_key.cmp(&b.object_key));
+        objects
     }
 
     /// Delete an object
     pub fn delete_object(&self, bucket_key: &str, object_key: &str) -> bool {
+        let _guard = self.snapshot.mutate();
+        self.blobs.delete(&blob_key(bucket_key, object_key));
         self.objects
             .get(bucket_key)
             .and_then(|bucket_objects| bucket_objects.remove(object_key))
             .is_some()
     }
+
+    /// Insert or replace many object records, returning how many were given
+    pub fn import_objects(&self, objects: impl IntoIterator<Item = ObjectInfo>) -> usize {
+        let _guard = self.snapshot.mutate();
+        let mut count = 0;
+        for object in objects {
+            self.objects
+                .entry(object.bucket_key.clone())
+                .or_default()
+                .insert(object.object_key.clone(), object);
+            count += 1;
+        }
+        count
+    }
+
+    /// Iterate over a snapshot of the objects in every bucket, ordered by
+    /// bucket and key
+    pub fn iter(&self) -> impl Iterator<Item = ObjectInfo> + use<> {
+        let mut objects: Vec<ObjectInfo> = {
+            let _guard = self.snapshot.snapshot();
+            self.objects
+                .iter()
+                .flat_map(|bucket_objects| {
+                    bucket_objects
+                        .iter()
+                        .map(|o| o.value().clone())
+                        .collect::<Vec<_>>()
+                })
+                .collect()
+        };
+        objects.sort_by(|a, b| (&a.bucket_key, &a.object_key).cmp(&(&b.bucket_key, &b.object_key)));
+        objects.into_iter()
+    }
+
+    /// Number of objects across all buckets
+    pub fn len(&self) -> usize {
+        self.objects
+            .iter()
+            .map(|bucket_objects| bucket_objects.len())
+            .sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Drop every object and its content
+    pub fn clear(&self) {
+        let _guard = self.snapshot.mutate();
+        for key in self.blobs.keys("oss/") {
+            self.blobs.delete(&key);
+        }
+        self.objects.clear();
+    }
 }
 
 impl Default for ObjectState {
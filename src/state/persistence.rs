@@ -0,0 +1,257 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+//! State files (`--state-file`): every state module serialized to one JSON
+//! document, so a restarted mock keeps its buckets, objects, jobs and hooks.
+//!
+//! Records are stored as the modules keep them; object contents are base64.
+//! The request journal has its own file (`--journal-file`) and token usage
+//! counters are not kept. Files are written to a temporary sibling first and
+//! renamed into place, so a crash mid-save never leaves a truncated file.
+
+use crate::error::{MockError, Result};
+use crate::state::StateManager;
+use crate::state::auth::TokenInfo;
+use crate::state::buckets::BucketInfo;
+use crate::state::entitlements::{Product, ProductStatus};
+use crate::state::issues::IssueInfo;
+use crate::state::jobs::Job;
+use crate::state::objects::ObjectInfo;
+use crate::state::projects::{FolderInfo, HubInfo, ItemInfo, ProjectInfo};
+use crate::state::uploads::UploadSession;
+use crate::state::webhooks::WebhookSubscription;
+use base64::Engine as _;
+use base64::engine::general_purpose::STANDARD;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Format version written to new files
+const VERSION: u32 = 1;
+
+/// Contents of a state file
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct StateFile {
+    pub version: u32,
+    pub tokens: Vec<TokenInfo>,
+    pub buckets: Vec<BucketInfo>,
+    pub objects: Vec<ObjectInfo>,
+    pub contents: Vec<ObjectContent>,
+    pub hubs: Vec<HubInfo>,
+    pub projects: Vec<ProjectInfo>,
+    pub folders: Vec<FolderInfo>,
+    pub items: Vec<ItemInfo>,
+    pub jobs: Vec<Job>,
+    /// Map of URN -> id of its latest translation job
+    pub translations: BTreeMap<String, String>,
+    pub issues: Vec<IssueInfo>,
+    pub hooks: Vec<WebhookSubscription>,
+    pub entitlements: BTreeMap<String, BTreeMap<Product, ProductStatus>>,
+    pub uploads: Vec<UploadSession>,
+}
+
+/// Uploaded content of an object
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ObjectContent {
+    pub bucket_key: String,
+    pub object_key: String,
+    /// Base64 (standard alphabet, padded)
+    pub data: String,
+}
+
+/// The state captured under a name (`/__admin/snapshots`)
+#[derive(Debug, Clone)]
+pub struct NamedSnapshot {
+    pub name: String,
+    /// Epoch millis
+    pub created_at: i64,
+    pub file: StateFile,
+}
+
+impl StateFile {
+    /// Copy every state module
+    pub fn capture(state: &StateManager) -> Self {
+        Self {
+            contents: state
+                .objects
+                .contents()
+                .into_iter()
+                .map(|(bucket_key, object_key, content)| ObjectContent {
+                    bucket_key,
+                    object_key,
+                    data: STANDARD.encode(content),
+                })
+                .collect(),
+            ..Self::capture_without_contents(state)
+        }
+    }
+
+    /// Copy every state module except object contents, which are costly to
+    /// encode
+    pub fn capture_without_contents(state: &StateManager) -> Self {
+        Self {
+            version: VERSION,
+            tokens: state.auth.list_tokens(),
+            buckets: state.buckets.list_buckets(),
+            objects: state.objects.iter().collect(),
+            contents: Vec::new(),
+            hubs: state.projects.list_hubs(),
+            projects: state.projects.all_projects(),
+            folders: state.projects.all_folders(),
+            items: state.projects.all_items(),
+            jobs: state.jobs.list(None),
+            translations: state.translations.links(),
+            issues: state.issues.iter().collect(),
+            hooks: state.webhooks.list_subscriptions(),
+            entitlements: state.entitlements.all(),
+            uploads: state.uploads.list_sessions(),
+        }
+    }
+
+    /// Insert every record into `state`, replacing records with the same
+    /// identifiers
+    pub fn restore(self, state: &StateManager) -> Result<()> {
+        if self.version > VERSION {
+            return Err(MockError::State(format!(
+                "state file version {} is newer than the supported version {}",
+                self.version, VERSION
+            )));
+        }
+        for token in self.tokens {
+            state.auth.insert_token(token);
+        }
+        state.buckets.import_buckets(self.buckets);
+        state.objects.import_objects(self.objects);
+        for content in self.contents {
+            let data = STANDARD.decode(&content.data).map_err(|e| {
+                MockError::State(format!(
+                    "content of {}/{}: {}",
+                    content.bucket_key, content.object_key, e
+                ))
+            })?;
+            state
+                .objects
+                .insert_content(&content.bucket_key, &content.object_key, data.into());
+        }
+        for hub in self.hubs {
+            state.projects.insert_hub(hub);
+        }
+        for project in self.projects {
+            state.projects.insert_project(project);
+        }
+        for folder in self.folders {
+            state.projects.insert_folder(folder);
+        }
+        state.projects.import_items(self.items);
+        for job in self.jobs {
+            state.jobs.insert(job);
+        }
+        for (urn, job_id) in self.translations {
+            state.translations.link(urn, job_id);
+        }
+        state.issues.import_issues(self.issues);
+        state.webhooks.import_subscriptions(self.hooks);
+        for (project_id, products) in self.entitlements {
+            state.entitlements.insert_products(&project_id, products);
+        }
+        for session in self.uploads {
+            state.uploads.insert_session(session);
+        }
+        Ok(())
+    }
+
+    /// Read a state file
+    pub fn read(path: &Path) -> Result<Self> {
+        let content = std::fs::read(path)?;
+        serde_json::from_slice(&content)
+            .map_err(|e| MockError::State(format!("{}: {}", path.display(), e)))
+    }
+
+    /// Write the file atomically
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_vec_pretty(self)
+            .map_err(|e| MockError::State(format!("{}: {}", path.display(), e)))?;
+        let mut temp = path.as_os_str().to_owned();
+        temp.push(".tmp");
+        std::fs::write(&temp, json)?;
+        std::fs::rename(&temp, path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::jobs::{JobKind, JobOutcome};
+    use bytes::Bytes;
+
+    #[test]
+    fn test_state_survives_a_save_and_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.json");
+
+        let state = StateManager::new();
+        state
+            .buckets
+            .create_bucket("keep".into(), "persistent".into());
+        state.objects.put_object(
+            "keep".into(),
+            "model.rvt".into(),
+            Bytes::from_static(b"\x00binary\xff"),
+            Some("application/octet-stream".into()),
+            BTreeMap::new(),
+        );
+        let token = state
+            .auth
+            .generate_token("app", 3600, Some("data:read".into()));
+        let translation = state.translations.create_job("urn:abc".into());
+        state.jobs.settle(
+            &translation.job_id,
+            JobOutcome::Failed {
+                message: "corrupt".into(),
+            },
+        );
+        let extract = state.jobs.start(JobKind::DataExtract, "request-1");
+        state
+            .issues
+            .create_issue("b.default-project".into(), "Leak".into(), None);
+        state.entitlements.set_product_status(
+            "b.default-project",
+            Product::Docs,
+            ProductStatus::Inactive,
+        );
+        state.save_to_file(&path).unwrap();
+
+        let restored = StateManager::new();
+        restored.load_from_file(&path).unwrap();
+        assert!(restored.buckets.get_bucket("keep").is_some());
+        assert_eq!(
+            restored.objects.get_content("keep", "model.rvt").unwrap(),
+            Bytes::from_static(b"\x00binary\xff")
+        );
+        assert!(restored.auth.validate_token(&token.access_token));
+        let job = restored.translations.get_job("urn:abc").unwrap();
+        assert_eq!(job.message.as_deref(), Some("corrupt"));
+        assert!(restored.jobs.get(&extract.id).is_some());
+        assert_eq!(restored.issues.list_issues("b.default-project").len(), 1);
+        assert!(
+            !restored
+                .entitlements
+                .is_active("default-project", Product::Docs)
+        );
+        // Saving what was loaded gives the same file
+        let saved = std::fs::read_to_string(&path).unwrap();
+        restored.save_to_file(&path).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), saved);
+
+        // A missing file is a fresh start, a corrupt one an error
+        let fresh = StateManager::new();
+        fresh
+            .load_from_file(&dir.path().join("missing.json"))
+            .unwrap();
+        std::fs::write(&path, "{ truncated").unwrap();
+        assert!(fresh.load_from_file(&path).is_err());
+    }
+}
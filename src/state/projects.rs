@@ -1,6 +1,7 @@
 // SPDX-License-Identifier: Apache-2.0
 // Copyright 2024-2025 Dmytro Yemelianov
 
+use crate::state::snapshot::SnapshotLock;
 use dashmap::DashMap;
 use serde::{Deserialize, Serialize};
 
@@ -20,12 +21,47 @@ pub struct ProjectInfo {
     pub name: String,
 }
 
+/// Folder information
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FolderInfo {
+    pub id: String,
+    pub project_id: String,
+    /// `None` for top folders
+    pub parent_id: Option<String>,
+    pub name: String,
+}
+
+/// Item (file) information
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ItemInfo {
+    pub id: String,
+    pub project_id: String,
+    pub folder_id: String,
+    pub name: String,
+    /// OSS object holding the item's content
+    pub storage_urn: String,
+    /// ID of the user whose token created the item
+    #[serde(default)]
+    pub created_by: Option<String>,
+}
+
+impl ItemInfo {
+    /// ID of the item's (only) version
+    pub fn version_id(&self) -> String {
+        format!("{}?version=1", self.id.replace("dm.lineage", "fs.file"))
+    }
+}
+
 /// Data Management state
 pub struct ProjectState {
     hubs: DashMap<String, HubInfo>,
     projects: DashMap<String, ProjectInfo>,
     /// Map of hub_id -> project_ids
     hub_projects: DashMap<String, Vec<String>>,
+    folders: DashMap<String, FolderInfo>,
+    items: DashMap<String, ItemInfo>,
+    /// Guards `hubs`, `projects` and `hub_projects`
+    snapshot: SnapshotLock,
 }
 
 impl ProjectState {
@@ -34,6 +70,9 @@ impl ProjectState {
             hubs: DashMap::new(),
             projects: DashMap::new(),
             hub_projects: DashMap::new(),
+            folders: DashMap::new(),
+            items: DashMap::new(),
+            snapshot: SnapshotLock::new(),
         };
 
         // Initialize with some default data
@@ -41,7 +80,9 @@ impl ProjectState {
         state
     }
 
-    fn init_defaults(&self) {
+    /// Insert the default hub and project every server starts with
+    pub fn init_defaults(&self) {
+        let _guard = self.snapshot.mutate();
         let hub_id = "b.default-hub".to_string();
         let hub = HubInfo {
             id: hub_id.clone(),
@@ -63,9 +104,14 @@ impl ProjectState {
             .push(project_id);
     }
 
-    /// List all hubs
+    /// List all hubs, ordered by ID
     pub fn list_hubs(&self) -> Vec<HubInfo> {
-        self.hubs.iter().map(|h| h.value().clone()).collect()
+        let mut hubs: Vec<HubInfo> = {
+            let _guard = self.snapshot.snapshot();
+            self.hubs.iter().map(|h| h.value().clone()).collect()
+        };
+        hubs.sort_by(|a, b| a.id.cmp(&b.id));
+        hubs
     }
 
     /// Get a hub by ID
@@ -73,23 +119,217 @@ impl ProjectState {
         self.hubs.get(hub_id).map(|h| h.clone())
     }
 
-    /// List projects in a hub
+    /// List projects in a hub, ordered by ID
     pub fn list_projects(&self, hub_id: &str) -> Vec<ProjectInfo> {
-        self.hub_projects
-            .get(hub_id)
-            .map(|project_ids| {
-                project_ids
-                    .iter()
-                    .filter_map(|id| self.projects.get(id).map(|p| p.clone()))
-                    .collect()
-            })
-            .unwrap_or_default()
+        let mut projects: Vec<ProjectInfo> = {
+            let _guard = self.snapshot.snapshot();
+            self.hub_projects
+                .get(hub_id)
+                .map(|project_ids| {
+                    project_ids
+                        .iter()
+                        .filter_map(|id| self.projects.get(id).map(|p| p.clone()))
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+        projects.sort_by(|a, b| a.id.cmp(&b.id));
+        projects
     }
 
     /// Get a project by ID
     pub fn get_project(&self, project_id: &str) -> Option<ProjectInfo> {
         self.projects.get(project_id).map(|p| p.clone())
     }
+
+    /// Create a project in a hub, returns `None` if the hub is unknown
+    pub fn create_project(&self, hub_id: &str, name: String) -> Option<ProjectInfo> {
+        self.hubs.get(hub_id)?;
+        let project = ProjectInfo {
+            id: format!("b.{}", uuid::Uuid::new_v4()),
+            hub_id: hub_id.to_string(),
+            name,
+        };
+        let _guard = self.snapshot.mutate();
+        self.projects.insert(project.id.clone(), project.clone());
+        self.hub_projects
+            .entry(hub_id.to_string())
+            .or_default()
+            .push(project.id.clone());
+        Some(project)
+    }
+
+    /// Create a folder, at the top of the project when `parent_id` is `None`
+    pub fn create_folder(
+        &self,
+        project_id: &str,
+        parent_id: Option<String>,
+        name: String,
+    ) -> FolderInfo {
+        let folder = FolderInfo {
+            id: format!("urn:adsk.wipprod:fs.folder:co.{}", uuid::Uuid::new_v4()),
+            project_id: project_id.to_string(),
+            parent_id,
+            name,
+        };
+        self.folders.insert(folder.id.clone(), folder.clone());
+        folder
+    }
+
+    /// Create an item backed by the OSS object `storage_urn`, attributed to
+    /// the user `created_by`
+    pub fn create_item(
+        &self,
+        project_id: &str,
+        folder_id: &str,
+        name: String,
+        storage_urn: String,
+        created_by: Option<String>,
+    ) -> ItemInfo {
+        let item = ItemInfo {
+            id: format!("urn:adsk.wipprod:dm.lineage:{}", uuid::Uuid::new_v4()),
+            project_id: project_id.to_string(),
+            folder_id: folder_id.to_string(),
+            name,
+            storage_urn,
+            created_by,
+        };
+        self.items.insert(item.id.clone(), item.clone());
+        item
+    }
+
+    /// Insert or replace a hub record
+    pub fn insert_hub(&self, hub: HubInfo) {
+        let _guard = self.snapshot.mutate();
+        self.hubs.insert(hub.id.clone(), hub);
+    }
+
+    /// Delete a hub record; its projects are kept
+    pub fn delete_hub(&self, hub_id: &str) -> bool {
+        let _guard = self.snapshot.mutate();
+        self.hubs.remove(hub_id).is_some()
+    }
+
+    /// Insert or replace a project record. Unlike [`Self::create_project`]
+    /// the hub does not need to exist.
+    pub fn insert_project(&self, project: ProjectInfo) {
+        let _guard = self.snapshot.mutate();
+        let mut hub_projects = self.hub_projects.entry(project.hub_id.clone()).or_default();
+        if !hub_projects.contains(&project.id) {
+            hub_projects.push(project.id.clone());
+        }
+        drop(hub_projects);
+        if let Some(previous) = self.projects.insert(project.id.clone(), project.clone())
+            && previous.hub_id != project.hub_id
+            && let Some(mut ids) = self.hub_projects.get_mut(&previous.hub_id)
+        {
+            ids.retain(|id| *id != project.id);
+        }
+    }
+
+    /// Insert or replace a folder record
+    pub fn insert_folder(&self, folder: FolderInfo) {
+        self.folders.insert(folder.id.clone(), folder);
+    }
+
+    /// Insert or replace an item record
+    pub fn insert_item(&self, item: ItemInfo) {
+        self.items.insert(item.id.clone(), item);
+    }
+
+    /// Insert or replace many item records, returning how many were given
+    pub fn import_items(&self, items: impl IntoIterator<Item = ItemInfo>) -> usize {
+        let mut count = 0;
+        for item in items {
+            self.items.insert(item.id.clone(), item);
+            count += 1;
+        }
+        count
+    }
+
+    /// Every project, ordered by ID
+    pub fn all_projects(&self) -> Vec<ProjectInfo> {
+        let mut projects: Vec<ProjectInfo> = {
+            let _guard = self.snapshot.snapshot();
+            self.projects.iter().map(|p| p.value().clone()).collect()
+        };
+        projects.sort_by(|a, b| a.id.cmp(&b.id));
+        projects
+    }
+
+    /// Every folder, ordered by ID
+    pub fn all_folders(&self) -> Vec<FolderInfo> {
+        let mut folders: Vec<FolderInfo> = self.folders.iter().map(|f| f.value().clone()).collect();
+        folders.sort_by(|a, b| a.id.cmp(&b.id));
+        folders
+    }
+
+    /// Every item, ordered by ID
+    pub fn all_items(&self) -> Vec<ItemInfo> {
+        let mut items: Vec<ItemInfo> = self.items.iter().map(|i| i.value().clone()).collect();
+        items.sort_by(|a, b| a.id.cmp(&b.id));
+        items
+    }
+
+    /// Get an item by ID
+    pub fn get_item(&self, item_id: &str) -> Option<ItemInfo> {
+        self.items.get(item_id).map(|i| i.clone())
+    }
+
+    /// Get the item a version ID belongs to
+    pub fn get_version(&self, version_id: &str) -> Option<ItemInfo> {
+        let (lineage, number) = version_id.split_once("?version=")?;
+        if number != "1" {
+            return None;
+        }
+        self.get_item(&lineage.replace("fs.file", "dm.lineage"))
+    }
+
+    /// Get a folder by ID
+    pub fn get_folder(&self, folder_id: &str) -> Option<FolderInfo> {
+        self.folders.get(folder_id).map(|f| f.clone())
+    }
+
+    /// List the top folders of a project
+    pub fn top_folders(&self, project_id: &str) -> Vec<FolderInfo> {
+        let mut folders: Vec<FolderInfo> = self
+            .folders
+            .iter()
+            .filter(|f| f.project_id == project_id && f.parent_id.is_none())
+            .map(|f| f.value().clone())
+            .collect();
+        folders.sort_by(|a, b| a.name.cmp(&b.name));
+        folders
+    }
+
+    /// List the subfolders and items of a folder
+    pub fn folder_contents(&self, folder_id: &str) -> (Vec<FolderInfo>, Vec<ItemInfo>) {
+        let mut folders: Vec<FolderInfo> = self
+            .folders
+            .iter()
+            .filter(|f| f.parent_id.as_deref() == Some(folder_id))
+            .map(|f| f.value().clone())
+            .collect();
+        folders.sort_by(|a, b| a.name.cmp(&b.name));
+        let mut items: Vec<ItemInfo> = self
+            .items
+            .iter()
+            .filter(|i| i.folder_id == folder_id)
+            .map(|i| i.value().clone())
+            .collect();
+        items.sort_by(|a, b| a.name.cmp(&b.name));
+        (folders, items)
+    }
+
+    /// Drop every hub, project, folder and item, including the defaults
+    pub fn clear(&self) {
+        let _guard = self.snapshot.mutate();
+        self.hubs.clear();
+        self.projects.clear();
+        self.hub_projects.clear();
+        self.folders.clear();
+        self.items.clear();
+    }
 }
 
 impl Default for ProjectState {
@@ -97,3 +337,23 @@ impl Default for ProjectState {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_projects_are_listed_by_id() {
+        let state = ProjectState::new();
+        let hub = "b.default-hub";
+        let ids: Vec<String> = (0..20)
+            .map(|i| state.create_project(hub, format!("P{}", i)).unwrap().id)
+            .collect();
+        let mut expected = ids.clone();
+        expected.push("b.default-project".to_string());
+        expected.sort();
+        let listed: Vec<String> = state.list_projects(hub).into_iter().map(|p| p.id).collect();
+        assert_eq!(listed, expected);
+        assert!(state.list_projects("b.unknown").is_empty());
+    }
+}
@@ -0,0 +1,62 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+//! Snapshot-consistent listings of `DashMap`-backed state.
+//!
+//! Iterating a `DashMap` locks one shard at a time, so a listing taken while
+//! other requests create and delete entries can mix states from different
+//! moments. Mutations hold the shared side of a [`SnapshotLock`] and still
+//! run concurrently with each other; listings hold the exclusive side while
+//! they copy, so a listing reflects every mutation that finished before it
+//! and none that started after.
+
+use std::sync::{PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+#[derive(Debug, Default)]
+pub struct SnapshotLock(RwLock<()>);
+
+impl SnapshotLock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Held while mutating the guarded maps
+    pub fn mutate(&self) -> RwLockReadGuard<'_, ()> {
+        self.0.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Held while copying the guarded maps for a listing
+    pub fn snapshot(&self) -> RwLockWriteGuard<'_, ()> {
+        self.0.write().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::state::objects::ObjectState;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_listing_sees_a_prefix_of_sequential_uploads() {
+        let objects = Arc::new(ObjectState::new());
+        let writer = {
+            let objects = objects.clone();
+            std::thread::spawn(move || {
+                for i in 0..2000 {
+                    objects.upload_object("bucket".into(), format!("{:05}", i), 1, None);
+                }
+            })
+        };
+        while !writer.is_finished() {
+            let keys: Vec<String> = objects
+                .list_objects("bucket")
+                .into_iter()
+                .map(|o| o.object_key)
+                .collect();
+            let expected: Vec<String> = (0..keys.len()).map(|i| format!("{:05}", i)).collect();
+            assert_eq!(keys, expected);
+        }
+        writer.join().unwrap();
+        assert_eq!(objects.list_objects("bucket").len(), 2000);
+    }
+}
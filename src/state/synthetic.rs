@@ -0,0 +1,113 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+//! Bulk-generated records for exercising pagination and list rendering at
+//! scale (`POST /__admin/synthetic/:kind`).
+//!
+//! Records are named `<prefix><index>` with the index zero-padded to the
+//! width of the count, so key order matches generation order. Generating
+//! objects again with the same prefix replaces them.
+
+use crate::state::issues::IssueInfo;
+use crate::state::objects::ObjectInfo;
+use crate::state::projects::{FolderInfo, ItemInfo};
+
+/// Upper bound on the records one request may generate
+pub const MAX_SYNTHETIC_RECORDS: usize = 1_000_000;
+
+/// Prefix of generated names when none is given
+pub const DEFAULT_PREFIX: &str = "synthetic-";
+
+fn names(prefix: &str, count: usize) -> impl Iterator<Item = String> + use<> {
+    let prefix = prefix.to_string();
+    let width = count.saturating_sub(1).to_string().len();
+    (0..count).map(move |index| format!("{}{:0width$}", prefix, index, width = width))
+}
+
+/// `count` objects of a bucket
+pub fn objects(
+    bucket_key: &str,
+    prefix: &str,
+    count: usize,
+) -> impl Iterator<Item = ObjectInfo> + use<> {
+    let bucket_key = bucket_key.to_string();
+    names(prefix, count).map(move |name| {
+        ObjectInfo::new(
+            &bucket_key,
+            &name,
+            1024,
+            Some("application/octet-stream".into()),
+        )
+    })
+}
+
+/// `count` open issues of a project, newest first by creation time
+pub fn issues(
+    project_id: &str,
+    prefix: &str,
+    count: usize,
+) -> impl Iterator<Item = IssueInfo> + use<> {
+    let project_id = project_id.to_string();
+    let now = chrono::Utc::now().timestamp_millis();
+    names(prefix, count)
+        .enumerate()
+        .map(move |(index, title)| IssueInfo {
+            id: uuid::Uuid::new_v4().to_string(),
+            project_id: project_id.clone(),
+            title,
+            description: None,
+            status: "open".to_string(),
+            created_at: now - index as i64,
+            created_by: None,
+            assigned_to: None,
+        })
+}
+
+/// `count` items in a folder
+pub fn items(
+    folder: &FolderInfo,
+    prefix: &str,
+    count: usize,
+) -> impl Iterator<Item = ItemInfo> + use<> {
+    let project_id = folder.project_id.clone();
+    let folder_id = folder.id.clone();
+    names(prefix, count).map(move |name| ItemInfo {
+        id: format!("urn:adsk.wipprod:dm.lineage:{}", uuid::Uuid::new_v4()),
+        project_id: project_id.clone(),
+        folder_id: folder_id.clone(),
+        storage_urn: format!("urn:adsk.objects:os.object:wip.dm.prod/{}", name),
+        name,
+        created_by: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::StateManager;
+
+    #[test]
+    fn test_synthetic_records_are_imported_in_order() {
+        let state = StateManager::new();
+        let count = state
+            .objects()
+            .import_objects(objects("big", "obj-", 10_000));
+        assert_eq!(count, 10_000);
+        let listed = state.objects.list_objects("big");
+        assert_eq!(listed.len(), 10_000);
+        assert_eq!(listed[0].object_key, "obj-0000");
+        assert_eq!(listed[9_999].object_key, "obj-9999");
+
+        let folder = state
+            .projects
+            .create_folder("p1", None, "Project Files".into());
+        state
+            .projects()
+            .import_items(items(&folder, DEFAULT_PREFIX, 3));
+        let (_, listed) = state.projects.folder_contents(&folder.id);
+        assert_eq!(listed.len(), 3);
+
+        state.issues().import_issues(issues("p1", "Issue ", 5));
+        assert_eq!(state.issues.list_issues("p1").len(), 5);
+    }
+}
@@ -0,0 +1,131 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+//! Project templates: the folders, models and issues every project created
+//! through the admin API starts with.
+//!
+//! ```yaml
+//! folders:
+//!   - name: Project Files
+//!     models:
+//!       - name: Building.rvt        # translated unless `translated: false`
+//!     folders:
+//!       - name: Archive
+//! issues:
+//!   - title: Clash in level 2
+//!     status: open
+//! ```
+
+use crate::error::Result;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// OSS bucket holding the content of Data Management items
+pub const DM_BUCKET: &str = "wip.dm.prod";
+
+/// Data every new project is populated with
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProjectTemplate {
+    #[serde(default)]
+    pub folders: Vec<FolderTemplate>,
+    #[serde(default)]
+    pub issues: Vec<IssueTemplate>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FolderTemplate {
+    pub name: String,
+    #[serde(default)]
+    pub folders: Vec<FolderTemplate>,
+    #[serde(default)]
+    pub models: Vec<ModelTemplate>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelTemplate {
+    pub name: String,
+    /// Whether the model starts with a finished translation
+    #[serde(default = "default_translated")]
+    pub translated: bool,
+}
+
+fn default_translated() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IssueTemplate {
+    pub title: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default = "default_issue_status")]
+    pub status: String,
+}
+
+fn default_issue_status() -> String {
+    "open".to_string()
+}
+
+impl ProjectTemplate {
+    /// Load a template from a YAML or JSON file
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+        Ok(serde_yaml::from_str(&content)?)
+    }
+
+    /// Number of folders, models and issues the template creates
+    pub fn counts(&self) -> (usize, usize, usize) {
+        fn walk(folders: &[FolderTemplate]) -> (usize, usize) {
+            folders.iter().fold((0, 0), |(f, m), folder| {
+                let (sub_f, sub_m) = walk(&folder.folders);
+                (f + 1 + sub_f, m + folder.models.len() + sub_m)
+            })
+        }
+        let (folders, models) = walk(&self.folders);
+        (folders, models, self.issues.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::StateManager;
+    use crate::state::translations::TranslationStatus;
+
+    #[test]
+    fn test_template_populates_new_project() {
+        let template: ProjectTemplate = serde_yaml::from_str(
+            r#"
+folders:
+  - name: Project Files
+    models:
+      - name: Building.rvt
+      - name: Site.dwg
+        translated: false
+    folders:
+      - name: Archive
+issues:
+  - title: Clash in level 2
+    status: closed
+"#,
+        )
+        .unwrap();
+        assert_eq!(template.counts(), (2, 2, 1));
+
+        let state = StateManager::new();
+        let project = state
+            .create_project("b.default-hub", "Tower".into(), Some(&template))
+            .unwrap();
+
+        let top = state.projects.top_folders(&project.id);
+        assert_eq!(top.len(), 1);
+        let (subfolders, items) = state.projects.folder_contents(&top[0].id);
+        assert_eq!(subfolders[0].name, "Archive");
+        let translated = state.translations.get_job(&items[0].storage_urn).unwrap();
+        assert_eq!(translated.status, TranslationStatus::Success);
+        assert!(state.translations.get_job(&items[1].storage_urn).is_none());
+
+        let issues = state.issues.list_issues(&project.id["b.".len()..]);
+        assert_eq!(issues[0].status, "closed");
+    }
+}
@@ -1,8 +1,22 @@
 // SPDX-License-Identifier: Apache-2.0
 // Copyright 2024-2025 Dmytro Yemelianov
 
+//! Model Derivative translations, and the rules deciding how long the
+//! translation of a URN takes and how it ends.
+//!
+//! A rule matches URNs by glob (`*` matches any characters, `?` one) or, with
+//! a `regex:` prefix, by regular expression, against the URN as given and
+//! base64-decoded. The first matching rule applies; translations no rule
+//! matches follow the job engine's timings and failure settings.
+
+use crate::state::jobs::{JobEngine, JobKind, JobOutcome, JobPhase, JobTiming};
+use base64::Engine as _;
 use dashmap::DashMap;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
 
 /// Translation job status
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -15,89 +29,293 @@ pub enum TranslationStatus {
     Success,
     #[serde(rename = "failed")]
     Failed,
+    #[serde(rename = "timeout")]
+    Timeout,
+}
+
+/// URNs a translation rule applies to: a glob, or a regex after `regex:`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct UrnPattern {
+    regex: Regex,
+    source: String,
+}
+
+impl UrnPattern {
+    /// Whether `urn`, or the URN it base64-encodes, matches
+    pub fn matches(&self, urn: &str) -> bool {
+        if self.regex.is_match(urn) {
+            return true;
+        }
+        base64::engine::general_purpose::URL_SAFE_NO_PAD
+            .decode(urn.trim_end_matches('='))
+            .ok()
+            .and_then(|bytes| String::from_utf8(bytes).ok())
+            .is_some_and(|decoded| self.regex.is_match(&decoded))
+    }
+}
+
+impl FromStr for UrnPattern {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let pattern = match s.strip_prefix("regex:") {
+            Some(regex) => regex.to_string(),
+            None => {
+                let mut pattern = String::from("^");
+                for c in s.chars() {
+                    match c {
+                        '*' => pattern.push_str(".*"),
+                        '?' => pattern.push('.'),
+                        c => pattern.push_str(&regex::escape(&c.to_string())),
+                    }
+                }
+                pattern.push('$');
+                pattern
+            }
+        };
+        Ok(Self {
+            regex: Regex::new(&pattern)
+                .map_err(|e| format!("Invalid URN pattern '{}': {}", s, e))?,
+            source: s.to_string(),
+        })
+    }
+}
+
+impl TryFrom<String> for UrnPattern {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<UrnPattern> for String {
+    fn from(pattern: UrnPattern) -> Self {
+        pattern.source
+    }
+}
+
+/// How a translation a rule applies to ends
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleOutcome {
+    #[default]
+    Success,
+    Failed,
+    Timeout,
+}
+
+/// Duration and outcome of the translations of the URNs matching `urn`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct TranslationRule {
+    pub urn: UrnPattern,
+    /// Time spent `pending`; the translation timing's when absent
+    #[serde(default, alias = "pending-ms", skip_serializing_if = "Option::is_none")]
+    pub pending_ms: Option<u64>,
+    /// Time spent `inprogress`; the translation timing's when absent
+    #[serde(
+        default,
+        alias = "in-progress-ms",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub in_progress_ms: Option<u64>,
+    #[serde(default)]
+    pub outcome: RuleOutcome,
+    /// Message a failed or timed out translation reports
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+impl TranslationRule {
+    fn job_outcome(&self) -> JobOutcome {
+        let message = |default: &str| self.message.clone().unwrap_or_else(|| default.to_string());
+        match self.outcome {
+            RuleOutcome::Success => JobOutcome::Success,
+            RuleOutcome::Failed => JobOutcome::Failed {
+                message: message("Unrecoverable exit code from extractor: -1073741831"),
+            },
+            RuleOutcome::Timeout => JobOutcome::Timeout {
+                message: message("Translation timed out"),
+            },
+        }
+    }
 }
 
 /// Translation job information
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TranslationJob {
     pub urn: String,
+    /// Id of the backing job in the [`JobEngine`]
+    pub job_id: String,
     pub status: TranslationStatus,
     pub progress: String,
+    /// Failure message once the translation has failed
+    pub message: Option<String>,
     pub created_at: i64,
 }
 
 /// Model Derivative translation state
 pub struct TranslationState {
-    jobs: DashMap<String, TranslationJob>,
+    engine: Arc<JobEngine>,
+    /// Map of URN -> id of its latest translation job
+    jobs: DashMap<String, String>,
+    rules: RwLock<Vec<TranslationRule>>,
 }
 
 impl TranslationState {
-    pub fn new() -> Self {
+    pub fn new(engine: Arc<JobEngine>) -> Self {
         Self {
+            engine,
             jobs: DashMap::new(),
+            rules: RwLock::new(Vec::new()),
         }
     }
 
-    /// Create a new translation job
+    /// Create a new translation job, replacing any previous one for the URN
     pub fn create_job(&self, urn: String) -> TranslationJob {
-        let now = chrono::Utc::now().timestamp_millis();
-        let job = TranslationJob {
-            urn: urn.clone(),
-            status: TranslationStatus::Pending,
-            progress: "0%".to_string(),
-            created_at: now,
+        let timing = self.engine.timing(JobKind::Translation);
+        let rule = self.rule_for(&urn);
+        let job = match rule {
+            Some(rule) => self.engine.start_as(
+                JobKind::Translation,
+                urn.clone(),
+                JobTiming {
+                    pending_ms: rule.pending_ms.unwrap_or(timing.pending_ms),
+                    in_progress_ms: rule.in_progress_ms.unwrap_or(timing.in_progress_ms),
+                },
+                Some(rule.job_outcome()),
+            ),
+            None => self.engine.start(JobKind::Translation, urn.clone()),
         };
-        self.jobs.insert(urn, job.clone());
-        job
+        self.jobs.insert(urn.clone(), job.id.clone());
+        self.get_job(&urn)
+            .expect("translation job was just registered")
     }
 
-    /// Get a translation job
+    /// Get a translation job, with its status as of now
     pub fn get_job(&self, urn: &str) -> Option<TranslationJob> {
-        self.jobs.get(urn).map(|j| j.clone())
-    }
-
-    /// Update job status
-    pub fn update_job_status(
-        &self,
-        urn: &str,
-        status: TranslationStatus,
-        progress: String,
-    ) -> bool {
-        if let Some(mut job) = self.jobs.get_mut(urn) {
-            job.status = status;
-            job.progress = progress;
-            true
-        } else {
-            false
-        }
+        let job_id = self.jobs.get(urn)?.clone();
+        let job = self.engine.get(&job_id)?;
+        let status = self.engine.status(&job_id)?;
+        let progress = match status.phase {
+            JobPhase::Success | JobPhase::Failed | JobPhase::Timeout => "complete".to_string(),
+            _ => format!("{}%", status.progress),
+        };
+        Some(TranslationJob {
+            urn: urn.to_string(),
+            job_id,
+            status: match status.phase {
+                JobPhase::Pending => TranslationStatus::Pending,
+                JobPhase::InProgress => TranslationStatus::InProgress,
+                JobPhase::Success => TranslationStatus::Success,
+                JobPhase::Failed => TranslationStatus::Failed,
+                JobPhase::Timeout => TranslationStatus::Timeout,
+            },
+            progress,
+            message: status.message,
+            created_at: job.created_at,
+        })
     }
 
-    /// Simulate job progression
-    pub fn simulate_progress(&self, urn: &str) {
-        if let Some(mut job) = self.jobs.get_mut(urn) {
-            match job.status {
-                TranslationStatus::Pending => {
-                    job.status = TranslationStatus::InProgress;
-                    job.progress = "25%".to_string();
-                }
-                TranslationStatus::InProgress => {
-                    let progress_num: u32 =
-                        job.progress.trim_end_matches('%').parse().unwrap_or(25);
-                    if progress_num < 100 {
-                        job.progress = format!("{}%", progress_num + 25);
-                    } else {
-                        job.status = TranslationStatus::Success;
-                        job.progress = "complete".to_string();
-                    }
-                }
-                _ => {}
-            }
+    /// Map of URN -> id of its latest translation job
+    pub fn links(&self) -> BTreeMap<String, String> {
+        self.jobs
+            .iter()
+            .map(|j| (j.key().clone(), j.value().clone()))
+            .collect()
+    }
+
+    /// Make `job_id` the latest translation of `urn`
+    pub fn link(&self, urn: String, job_id: String) {
+        self.jobs.insert(urn, job_id);
+    }
+
+    /// The first rule matching `urn`
+    fn rule_for(&self, urn: &str) -> Option<TranslationRule> {
+        self.rules
+            .read()
+            .ok()?
+            .iter()
+            .find(|rule| rule.urn.matches(urn))
+            .cloned()
+    }
+
+    pub fn rules(&self) -> Vec<TranslationRule> {
+        self.rules.read().map(|r| r.clone()).unwrap_or_default()
+    }
+
+    /// Replace the translation rules; running translations keep theirs
+    pub fn set_rules(&self, rules: Vec<TranslationRule>) {
+        if let Ok(mut current) = self.rules.write() {
+            *current = rules;
         }
     }
+
+    /// Forget every URN's translation; the jobs stay in the engine
+    pub fn clear(&self) {
+        self.jobs.clear();
+    }
 }
 
 impl Default for TranslationState {
     fn default() -> Self {
-        Self::new()
+        Self::new(Arc::new(JobEngine::new()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(json: serde_json::Value) -> TranslationRule {
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    fn test_rules_decide_translation_outcomes_by_urn() {
+        let state = TranslationState::default();
+        state.set_rules(vec![
+            rule(serde_json::json!({
+                "urn": "urn:adsk.objects:os.object:*/broken-*.rvt",
+                "outcome": "failed",
+                "message": "Corrupt file"
+            })),
+            rule(serde_json::json!({
+                "urn": "regex:slow\\.(dwg|dxf)$",
+                "pendingMs": 0,
+                "inProgressMs": 60000,
+                "outcome": "timeout"
+            })),
+        ]);
+
+        // Matched base64-encoded, as clients send URNs
+        let urn = base64::engine::general_purpose::URL_SAFE_NO_PAD
+            .encode("urn:adsk.objects:os.object:bucket/broken-1.rvt");
+        let broken = state.create_job(urn);
+        let job = state.engine.get(&broken.job_id).unwrap();
+        assert_eq!(
+            job.outcome,
+            JobOutcome::Failed {
+                message: "Corrupt file".into()
+            }
+        );
+        assert_eq!(job.timing, JobTiming::default());
+
+        let slow = state.create_job("urn:adsk.objects:os.object:bucket/slow.dwg".into());
+        let job = state.engine.get(&slow.job_id).unwrap();
+        assert_eq!(job.timing.in_progress_ms, 60000);
+        let status = job.status_at(job.created_at + 60000);
+        assert_eq!(status.phase, JobPhase::Timeout);
+        assert_eq!(status.message.as_deref(), Some("Translation timed out"));
+
+        let fine = state.create_job("urn:adsk.objects:os.object:bucket/fine.rvt".into());
+        let job = state.engine.get(&fine.job_id).unwrap();
+        assert_eq!(job.outcome, JobOutcome::Success);
+
+        let invalid: Result<TranslationRule, _> =
+            serde_json::from_value(serde_json::json!({ "urn": "regex:(" }));
+        assert!(invalid.is_err());
     }
 }
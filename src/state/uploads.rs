@@ -0,0 +1,168 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+use crate::state::clock::MockClock;
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// Signed S3 upload session (single or multipart)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UploadSession {
+    pub upload_key: String,
+    pub bucket_key: String,
+    pub object_key: String,
+    /// Number of parts the client requested URLs for
+    pub parts: u32,
+    /// Map of part number -> uploaded size in bytes
+    pub uploaded_parts: BTreeMap<u32, u64>,
+    pub created_at: i64,
+    /// Upload expiration (epoch millis); the session is garbage-collected after this
+    pub expires_at: i64,
+}
+
+impl UploadSession {
+    pub fn is_expired(&self, now: i64) -> bool {
+        self.expires_at <= now
+    }
+
+    /// Total size of all uploaded parts
+    pub fn uploaded_size(&self) -> u64 {
+        self.uploaded_parts.values().sum()
+    }
+}
+
+/// OSS signed upload session state
+pub struct UploadState {
+    sessions: DashMap<String, UploadSession>,
+    clock: Arc<MockClock>,
+}
+
+impl UploadState {
+    pub fn new() -> Self {
+        Self::with_clock(Arc::new(MockClock::new()))
+    }
+
+    /// Create the session store, expiring sessions by `clock`
+    pub fn with_clock(clock: Arc<MockClock>) -> Self {
+        Self {
+            sessions: DashMap::new(),
+            clock,
+        }
+    }
+
+    /// Start a new upload session that expires after `lifetime_secs`
+    pub fn create_session(
+        &self,
+        bucket_key: String,
+        object_key: String,
+        parts: u32,
+        lifetime_secs: u64,
+    ) -> UploadSession {
+        let now = self.clock.now_millis();
+        let session = UploadSession {
+            upload_key: uuid::Uuid::new_v4().simple().to_string(),
+            bucket_key,
+            object_key,
+            parts,
+            uploaded_parts: BTreeMap::new(),
+            created_at: now,
+            expires_at: now + (lifetime_secs as i64) * 1000,
+        };
+        self.sessions
+            .insert(session.upload_key.clone(), session.clone());
+        session
+    }
+
+    /// Get a live (non-expired) session
+    pub fn get_session(&self, upload_key: &str) -> Option<UploadSession> {
+        let now = self.clock.now_millis();
+        self.sessions
+            .get(upload_key)
+            .filter(|s| !s.is_expired(now))
+            .map(|s| s.clone())
+    }
+
+    /// Extend a session with more part URLs
+    pub fn extend_parts(&self, upload_key: &str, parts: u32) -> Option<UploadSession> {
+        let mut session = self.sessions.get_mut(upload_key)?;
+        session.parts = session.parts.max(parts);
+        Some(session.clone())
+    }
+
+    /// Record an uploaded part, returns false if the session is unknown or expired
+    pub fn record_part(&self, upload_key: &str, part: u32, size: u64) -> bool {
+        let now = self.clock.now_millis();
+        match self.sessions.get_mut(upload_key) {
+            Some(mut session) if !session.is_expired(now) => {
+                session.uploaded_parts.insert(part, size);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Complete a session, removing it from the store
+    pub fn complete_session(&self, upload_key: &str) -> Option<UploadSession> {
+        let now = self.clock.now_millis();
+        self.sessions
+            .remove(upload_key)
+            .map(|(_, s)| s)
+            .filter(|s| !s.is_expired(now))
+    }
+
+    /// List all sessions, including expired ones not yet collected
+    pub fn list_sessions(&self) -> Vec<UploadSession> {
+        let mut sessions: Vec<UploadSession> =
+            self.sessions.iter().map(|s| s.value().clone()).collect();
+        sessions.sort_by(|a, b| {
+            a.created_at
+                .cmp(&b.created_at)
+                .then_with(|| a.upload_key.cmp(&b.upload_key))
+        });
+        sessions
+    }
+
+    /// Insert or replace a session record
+    pub fn insert_session(&self, session: UploadSession) {
+        self.sessions.insert(session.upload_key.clone(), session);
+    }
+
+    /// Remove expired sessions, returning how many were dropped
+    pub fn purge_expired(&self) -> usize {
+        let now = self.clock.now_millis();
+        let before = self.sessions.len();
+        self.sessions.retain(|_, s| !s.is_expired(now));
+        before - self.sessions.len()
+    }
+
+    /// Drop every upload session
+    pub fn clear(&self) {
+        self.sessions.clear();
+    }
+}
+
+impl Default for UploadState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expired_sessions_are_purged() {
+        let state = UploadState::new();
+        let live = state.create_session("b".into(), "live".into(), 1, 3600);
+        let expired = state.create_session("b".into(), "old".into(), 1, 0);
+
+        assert!(state.get_session(&expired.upload_key).is_none());
+        assert!(!state.record_part(&expired.upload_key, 1, 10));
+        assert_eq!(state.purge_expired(), 1);
+        assert_eq!(state.list_sessions().len(), 1);
+        assert!(state.record_part(&live.upload_key, 1, 10));
+    }
+}
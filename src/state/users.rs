@@ -0,0 +1,118 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+//! Autodesk users 3-legged tokens act for. Records created with such a token
+//! are attributed to its user, and `/userinfo` describes it.
+//!
+//! Users are settings rather than records: they come from the `users` key of
+//! the configuration file or `/__admin/state/users` and survive resets.
+
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+
+/// ID of the user every server starts with, who signs in unless the
+/// authorization code names another user
+pub const DEFAULT_USER_ID: &str = "mock-user";
+
+/// An Autodesk user
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UserInfo {
+    pub id: String,
+    pub name: String,
+    pub email: String,
+    /// Given name; the first word of `name` when absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub first_name: Option<String>,
+    /// Family name; the rest of `name` when absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_name: Option<String>,
+    /// Sign-in name; the email address when absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    /// e.g. `en-US`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locale: Option<String>,
+}
+
+impl UserInfo {
+    pub fn given_name(&self) -> String {
+        self.first_name.clone().unwrap_or_else(|| {
+            self.name
+                .split_whitespace()
+                .next()
+                .unwrap_or_default()
+                .to_string()
+        })
+    }
+
+    pub fn family_name(&self) -> String {
+        self.last_name.clone().unwrap_or_else(|| {
+            self.name
+                .split_once(char::is_whitespace)
+                .map(|(_, rest)| rest.trim().to_string())
+                .unwrap_or_default()
+        })
+    }
+
+    pub fn username(&self) -> String {
+        self.username.clone().unwrap_or_else(|| self.email.clone())
+    }
+
+    pub fn locale(&self) -> String {
+        self.locale.clone().unwrap_or_else(|| "en-US".to_string())
+    }
+}
+
+/// The user every server starts with
+pub fn default_user() -> UserInfo {
+    UserInfo {
+        id: DEFAULT_USER_ID.to_string(),
+        name: "Mock User".to_string(),
+        email: "mock.user@example.com".to_string(),
+        first_name: None,
+        last_name: None,
+        username: None,
+        locale: None,
+    }
+}
+
+/// Known users
+pub struct UserState {
+    users: DashMap<String, UserInfo>,
+}
+
+impl UserState {
+    pub fn new() -> Self {
+        let state = Self {
+            users: DashMap::new(),
+        };
+        state.insert_user(default_user());
+        state
+    }
+
+    pub fn get_user(&self, user_id: &str) -> Option<UserInfo> {
+        self.users.get(user_id).map(|u| u.clone())
+    }
+
+    /// Every user, sorted by ID
+    pub fn list_users(&self) -> Vec<UserInfo> {
+        let mut users: Vec<UserInfo> = self.users.iter().map(|u| u.value().clone()).collect();
+        users.sort_by(|a, b| a.id.cmp(&b.id));
+        users
+    }
+
+    /// Insert or replace a user record
+    pub fn insert_user(&self, user: UserInfo) {
+        self.users.insert(user.id.clone(), user);
+    }
+
+    pub fn delete_user(&self, user_id: &str) -> bool {
+        self.users.remove(user_id).is_some()
+    }
+}
+
+impl Default for UserState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,56 +1,180 @@
 // SPDX-License-Identifier: Apache-2.0
 // Copyright 2024-2025 Dmytro Yemelianov
 
+use crate::state::clock::MockClock;
+use crate::state::snapshot::SnapshotLock;
 use dashmap::DashMap;
 use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+/// Consecutive failed deliveries after which a hook is deactivated
+pub const DEACTIVATE_AFTER_FAILURES: u32 = 5;
+
+/// Status of hooks that receive events
+pub const STATUS_ACTIVE: &str = "active";
+/// Status of hooks that receive no events until reactivated
+pub const STATUS_INACTIVE: &str = "inactive";
+
+/// Number of failed deliveries the dead-letter list keeps
+const DEAD_LETTER_CAPACITY: usize = 1000;
+
+/// How often and when deliveries a callback refused are tried again
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RetryPolicy {
+    /// Deliveries tried in all, the first one included
+    pub attempts: u32,
+    /// Wait before the first retry; each further retry waits twice as long
+    pub backoff_ms: u64,
+}
+
+impl RetryPolicy {
+    /// Wait before retry number `retry` (1 for the first)
+    pub fn delay(&self, retry: u32) -> Duration {
+        let factor = 1u64 << retry.saturating_sub(1).min(16);
+        Duration::from_millis(self.backoff_ms.saturating_mul(factor))
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            attempts: 3,
+            backoff_ms: 1000,
+        }
+    }
+}
+
+/// A notification no attempt could deliver
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FailedDelivery {
+    /// Position in the list, increasing across the server's lifetime
+    pub seq: u64,
+    pub hook_id: String,
+    pub system: String,
+    pub event: String,
+    pub callback_url: String,
+    pub attempts: u32,
+    /// Why the last attempt failed: the status the callback answered or the
+    /// transport error
+    pub error: String,
+    /// When the last attempt failed (epoch millis)
+    pub failed_at: i64,
+    /// The notification body
+    pub notification: serde_json::Value,
+}
 
 /// Webhook subscription information
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WebhookSubscription {
     pub hook_id: String,
+    /// Event source, e.g. `data` or `derivative`
+    #[serde(default)]
+    pub system: String,
+    /// Event name, e.g. `dm.version.added`
+    #[serde(default)]
+    pub event: String,
     pub tenant: String,
     pub callback_url: String,
     pub scope: WebhookScope,
+    /// Custom data echoed in every notification
+    #[serde(default)]
+    pub hook_attribute: Option<serde_json::Value>,
     pub status: String,
     pub created_at: i64,
+    /// Failed deliveries since the last successful one
+    #[serde(default)]
+    pub consecutive_failures: u32,
+    /// Client ID of the app whose token created the hook; notifications
+    /// are signed with the app's secret
+    #[serde(default)]
+    pub client_id: Option<String>,
+    /// JsonPath filter expression the hook was created or updated with
+    #[serde(default)]
+    pub filter: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl WebhookSubscription {
+    /// Whether events are delivered to the hook
+    pub fn is_active(&self) -> bool {
+        self.status == STATUS_ACTIVE
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct WebhookScope {
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub folder: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub project: Option<String>,
+    /// Model Derivative workflow id
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub workflow: Option<String>,
 }
 
 /// Webhooks state
 pub struct WebhooksState {
     subscriptions: DashMap<String, WebhookSubscription>,
+    /// Map of client ID -> secret notifications to the app's hooks are
+    /// signed with
+    secrets: DashMap<String, String>,
+    retry_policy: RwLock<RetryPolicy>,
+    dead_letters: RwLock<VecDeque<FailedDelivery>>,
+    next_dead_letter: AtomicU64,
+    snapshot: SnapshotLock,
+    clock: Arc<MockClock>,
 }
 
 impl WebhooksState {
     pub fn new() -> Self {
+        Self::with_clock(Arc::new(MockClock::new()))
+    }
+
+    /// Create the hook store, dating hooks by `clock`
+    pub fn with_clock(clock: Arc<MockClock>) -> Self {
         Self {
             subscriptions: DashMap::new(),
+            secrets: DashMap::new(),
+            retry_policy: RwLock::new(RetryPolicy::default()),
+            dead_letters: RwLock::new(VecDeque::new()),
+            next_dead_letter: AtomicU64::new(1),
+            snapshot: SnapshotLock::new(),
+            clock,
         }
     }
 
     /// Create a webhook subscription
     pub fn create_subscription(
         &self,
+        system: String,
+        event: String,
         tenant: String,
         callback_url: String,
         scope: WebhookScope,
+        hook_attribute: Option<serde_json::Value>,
     ) -> WebhookSubscription {
         let hook_id = uuid::Uuid::new_v4().to_string();
-        let now = chrono::Utc::now().timestamp_millis();
+        let now = self.clock.now_millis();
         let subscription = WebhookSubscription {
             hook_id: hook_id.clone(),
+            system,
+            event,
             tenant,
             callback_url,
             scope,
-            status: "active".to_string(),
+            hook_attribute,
+            status: STATUS_ACTIVE.to_string(),
             created_at: now,
+            consecutive_failures: 0,
+            client_id: None,
+            filter: None,
         };
 
+        let _guard = self.snapshot.mutate();
         self.subscriptions.insert(hook_id, subscription.clone());
         subscription
     }
@@ -60,18 +184,194 @@ impl WebhooksState {
         self.subscriptions.get(hook_id).map(|s| s.clone())
     }
 
-    /// List all subscriptions
+    /// List all subscriptions, oldest first
     pub fn list_subscriptions(&self) -> Vec<WebhookSubscription> {
-        self.subscriptions
-            .iter()
-            .map(|s| s.value().clone())
+        let mut subscriptions: Vec<WebhookSubscription> = {
+            let _guard = self.snapshot.snapshot();
+            self.subscriptions
+                .iter()
+                .map(|s| s.value().clone())
+                .collect()
+        };
+        subscriptions.sort_by(|a, b| {
+            a.created_at
+                .cmp(&b.created_at)
+                .then_with(|| a.hook_id.cmp(&b.hook_id))
+        });
+        subscriptions
+    }
+
+    /// Whether any hook would receive notifications
+    pub fn has_active(&self) -> bool {
+        self.subscriptions.iter().any(|s| s.is_active())
+    }
+
+    /// Active subscriptions to an event, oldest first; inactive hooks
+    /// receive nothing
+    pub fn subscribers(&self, system: &str, event: &str) -> Vec<WebhookSubscription> {
+        self.list_subscriptions()
+            .into_iter()
+            .filter(|s| s.system == system && s.event == event && s.is_active())
             .collect()
     }
 
+    /// Activate or deactivate a hook. Reactivating clears its failure count.
+    pub fn set_status(&self, hook_id: &str, active: bool) -> Option<WebhookSubscription> {
+        let _guard = self.snapshot.mutate();
+        let mut subscription = self.subscriptions.get_mut(hook_id)?;
+        if active {
+            subscription.status = STATUS_ACTIVE.to_string();
+            subscription.consecutive_failures = 0;
+        } else {
+            subscription.status = STATUS_INACTIVE.to_string();
+        }
+        Some(subscription.clone())
+    }
+
+    /// Record the outcome of a delivery to a hook. The
+    /// [`DEACTIVATE_AFTER_FAILURES`]th failure in a row deactivates it, like
+    /// the real service does with unreachable callbacks.
+    pub fn record_delivery(&self, hook_id: &str, delivered: bool) -> Option<WebhookSubscription> {
+        let _guard = self.snapshot.mutate();
+        let mut subscription = self.subscriptions.get_mut(hook_id)?;
+        if delivered {
+            subscription.consecutive_failures = 0;
+        } else {
+            subscription.consecutive_failures += 1;
+            if subscription.consecutive_failures >= DEACTIVATE_AFTER_FAILURES {
+                subscription.status = STATUS_INACTIVE.to_string();
+            }
+        }
+        Some(subscription.clone())
+    }
+
     /// Delete a subscription
     pub fn delete_subscription(&self, hook_id: &str) -> bool {
+        let _guard = self.snapshot.mutate();
         self.subscriptions.remove(hook_id).is_some()
     }
+
+    /// Insert or replace a subscription record
+    pub fn insert_subscription(&self, subscription: WebhookSubscription) {
+        let _guard = self.snapshot.mutate();
+        self.subscriptions
+            .insert(subscription.hook_id.clone(), subscription);
+    }
+
+    /// Insert or replace many subscription records, returning how many were
+    /// given
+    pub fn import_subscriptions(
+        &self,
+        subscriptions: impl IntoIterator<Item = WebhookSubscription>,
+    ) -> usize {
+        let _guard = self.snapshot.mutate();
+        let mut count = 0;
+        for subscription in subscriptions {
+            self.subscriptions
+                .insert(subscription.hook_id.clone(), subscription);
+            count += 1;
+        }
+        count
+    }
+
+    /// Iterate over a snapshot of all subscriptions, oldest first
+    pub fn iter(&self) -> impl Iterator<Item = WebhookSubscription> + use<> {
+        self.list_subscriptions().into_iter()
+    }
+
+    /// Number of subscriptions
+    pub fn len(&self) -> usize {
+        self.subscriptions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.subscriptions.is_empty()
+    }
+
+    /// Secret notifications to the hooks of app `client_id` are signed with
+    pub fn secret(&self, client_id: &str) -> Option<String> {
+        self.secrets.get(client_id).map(|s| s.clone())
+    }
+
+    /// Sign notifications to the hooks of app `client_id` with `secret`,
+    /// returning whether a secret was set before
+    pub fn set_secret(&self, client_id: &str, secret: String) -> bool {
+        self.secrets.insert(client_id.to_string(), secret).is_some()
+    }
+
+    /// Stop signing notifications to the hooks of app `client_id`,
+    /// returning whether it had a secret
+    pub fn remove_secret(&self, client_id: &str) -> bool {
+        self.secrets.remove(client_id).is_some()
+    }
+
+    /// Client IDs of the apps with a secret, sorted
+    pub fn secret_clients(&self) -> Vec<String> {
+        let mut clients: Vec<String> = self.secrets.iter().map(|s| s.key().clone()).collect();
+        clients.sort();
+        clients
+    }
+
+    /// The secret notifications to `hook` are signed with
+    pub fn secret_for(&self, hook: &WebhookSubscription) -> Option<String> {
+        self.secret(hook.client_id.as_deref()?)
+    }
+
+    pub fn retry_policy(&self) -> RetryPolicy {
+        self.retry_policy
+            .read()
+            .map(|policy| *policy)
+            .unwrap_or_default()
+    }
+
+    pub fn set_retry_policy(&self, policy: RetryPolicy) {
+        if let Ok(mut current) = self.retry_policy.write() {
+            *current = policy;
+        }
+    }
+
+    /// Add a delivery that failed for good to the dead-letter list,
+    /// dropping the oldest beyond its capacity. `seq` and `failed_at` are
+    /// assigned here.
+    pub fn record_dead_letter(&self, mut delivery: FailedDelivery) -> FailedDelivery {
+        delivery.seq = self.next_dead_letter.fetch_add(1, Ordering::Relaxed);
+        delivery.failed_at = self.clock.now_millis();
+        if let Ok(mut dead_letters) = self.dead_letters.write() {
+            dead_letters.push_back(delivery.clone());
+            let excess = dead_letters.len().saturating_sub(DEAD_LETTER_CAPACITY);
+            dead_letters.drain(..excess);
+        }
+        delivery
+    }
+
+    /// Deliveries that failed for good, oldest first, optionally only those
+    /// to one hook
+    pub fn dead_letters(&self, hook_id: Option<&str>) -> Vec<FailedDelivery> {
+        self.dead_letters
+            .read()
+            .map(|dead_letters| {
+                dead_letters
+                    .iter()
+                    .filter(|d| hook_id.is_none_or(|id| d.hook_id == id))
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn clear_dead_letters(&self) {
+        if let Ok(mut dead_letters) = self.dead_letters.write() {
+            dead_letters.clear();
+        }
+    }
+
+    /// Drop every subscription and failed delivery; secrets and the retry
+    /// policy stay, like app settings
+    pub fn clear(&self) {
+        let _guard = self.snapshot.mutate();
+        self.subscriptions.clear();
+        self.clear_dead_letters();
+    }
 }
 
 impl Default for WebhooksState {
@@ -79,3 +379,46 @@ impl Default for WebhooksState {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_failing_hooks_are_deactivated_until_reactivated() {
+        let state = WebhooksState::new();
+        let hook = state.create_subscription(
+            "data".into(),
+            "dm.version.added".into(),
+            "tenant".into(),
+            "https://example.com/callback".into(),
+            WebhookScope::default(),
+            None,
+        );
+        assert_eq!(state.subscribers("data", "dm.version.added").len(), 1);
+        assert!(state.subscribers("data", "dm.folder.added").is_empty());
+
+        // A success in between resets the count
+        for _ in 0..DEACTIVATE_AFTER_FAILURES - 1 {
+            state.record_delivery(&hook.hook_id, false);
+        }
+        state.record_delivery(&hook.hook_id, true);
+        for _ in 0..DEACTIVATE_AFTER_FAILURES - 1 {
+            assert!(
+                state
+                    .record_delivery(&hook.hook_id, false)
+                    .unwrap()
+                    .is_active()
+            );
+        }
+        let failed = state.record_delivery(&hook.hook_id, false).unwrap();
+        assert_eq!(failed.status, STATUS_INACTIVE);
+        assert!(state.subscribers("data", "dm.version.added").is_empty());
+
+        let reactivated = state.set_status(&hook.hook_id, true).unwrap();
+        assert!(reactivated.is_active());
+        assert_eq!(reactivated.consecutive_failures, 0);
+        assert_eq!(state.subscribers("data", "dm.version.added").len(), 1);
+        assert!(state.set_status("unknown", true).is_none());
+    }
+}
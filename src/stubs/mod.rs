@@ -0,0 +1,770 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+//! File-based response stubs.
+//!
+//! A stubs directory holds stub mappings under `mappings/` (JSON or YAML, a
+//! single stub or a `{"mappings": [...]}` list per file) and the payloads they
+//! reference through `bodyFileName` under `__files/`. Stubs take priority over
+//! OpenAPI and built-in routes.
+//!
+//! The format is a subset of WireMock's; [`wiremock`] converts full WireMock
+//! mappings into it.
+
+pub mod record;
+pub mod replay;
+pub mod wiremock;
+
+use crate::error::Result;
+use crate::handlers::query::QueryParams;
+use crate::templating::{self, TemplateContext};
+use axum::{
+    body::Body,
+    http::{HeaderMap, HeaderName, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+};
+use base64::Engine as _;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// Directory (inside the stubs directory) holding stub mappings
+pub const MAPPINGS_DIR: &str = "mappings";
+/// Directory (inside the stubs directory) holding body files
+pub const FILES_DIR: &str = "__files";
+
+/// Priority used when a stub does not set one; lower values win
+pub const DEFAULT_PRIORITY: u32 = 5;
+
+/// A stub: a request matcher and the response it produces
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StubMapping {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Lower values win when several stubs match
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<u32>,
+    /// Only match requests selecting this scenario (`X-Mock-Scenario`);
+    /// such stubs win over stubs without a scenario
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scenario: Option<String>,
+    pub request: StubRequest,
+    #[serde(default)]
+    pub response: StubResponse,
+}
+
+/// Request matcher; all given criteria must match
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StubRequest {
+    /// HTTP method, `ANY` or omitted matches every method
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub method: Option<String>,
+    /// Exact path and query string
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// Exact path
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url_path: Option<String>,
+    /// Regular expression the whole path and query string must match
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url_pattern: Option<String>,
+    /// Regular expression the whole path must match
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url_path_pattern: Option<String>,
+    /// Path with `{param}` placeholders, exposed to templates as `path.param`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url_path_template: Option<String>,
+    /// Query parameters by name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub query_parameters: BTreeMap<String, QueryParameterMatcher>,
+    /// Request headers by name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, ContentPattern>,
+    /// Patterns the request body must all match
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub body_patterns: Vec<ContentPattern>,
+}
+
+/// Matcher for a header value or the request body (WireMock's `equalTo`,
+/// `contains`, `matches`, `doesNotMatch`, `equalToJson` and `absent`); all
+/// given criteria must match
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContentPattern {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub equal_to: Option<String>,
+    /// Compare `equalTo` ignoring case
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub case_insensitive: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub contains: Option<String>,
+    /// Regular expression the whole value must match
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub matches: Option<String>,
+    /// Regular expression the whole value must not match
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub does_not_match: Option<String>,
+    /// The value parses as JSON equal to this one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub equal_to_json: Option<Value>,
+    /// The value is missing (`true`) or present (`false`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub absent: Option<bool>,
+}
+
+impl ContentPattern {
+    /// Check the regular expressions, so a broken stub is reported on load
+    fn validate(&self) -> std::result::Result<(), regex::Error> {
+        for pattern in [&self.matches, &self.does_not_match].into_iter().flatten() {
+            whole_match(pattern)?;
+        }
+        Ok(())
+    }
+
+    fn matches(&self, value: Option<&str>) -> bool {
+        if let Some(absent) = self.absent
+            && absent == value.is_some()
+        {
+            return false;
+        }
+        let Some(value) = value else {
+            // Only `absent` can match a missing value
+            return self.absent == Some(true);
+        };
+        if let Some(expected) = &self.equal_to {
+            let equal = if self.case_insensitive {
+                expected.eq_ignore_ascii_case(value)
+            } else {
+                expected == value
+            };
+            if !equal {
+                return false;
+            }
+        }
+        if self
+            .contains
+            .as_ref()
+            .is_some_and(|c| !value.contains(c.as_str()))
+        {
+            return false;
+        }
+        if let Some(pattern) = &self.matches
+            && !whole_match(pattern).is_ok_and(|r| r.is_match(value))
+        {
+            return false;
+        }
+        if let Some(pattern) = &self.does_not_match
+            && whole_match(pattern).is_ok_and(|r| r.is_match(value))
+        {
+            return false;
+        }
+        if let Some(expected) = &self.equal_to_json
+            && serde_json::from_str::<Value>(value).ok().as_ref() != Some(expected)
+        {
+            return false;
+        }
+        true
+    }
+}
+
+/// A regular expression anchored to match a whole value, as WireMock's are
+fn whole_match(pattern: &str) -> std::result::Result<Regex, regex::Error> {
+    Regex::new(&format!("^(?:{})$", pattern))
+}
+
+/// Matcher for one query parameter (the `equalTo`, `contains`, `matches`,
+/// `absent`, `includes` and `hasExactly` matchers of WireMock). Repeated parameters and
+/// comma-separated lists are both lists of values, so `ids=1&ids=2` and
+/// `ids=1,2` match the same stub.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueryParameterMatcher {
+    /// Some value equals this
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub equal_to: Option<String>,
+    /// Some value contains this
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub contains: Option<String>,
+    /// Some value matches this regular expression as a whole
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub matches: Option<String>,
+    /// The parameter is missing (`true`) or present (`false`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub absent: Option<bool>,
+    /// Every listed value is among the values
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub includes: Option<Vec<ValueMatcher>>,
+    /// The values are exactly the listed ones, in any order
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub has_exactly: Option<Vec<ValueMatcher>>,
+}
+
+/// A single value of a query parameter list
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ValueMatcher {
+    pub equal_to: String,
+}
+
+impl QueryParameterMatcher {
+    fn matches(&self, name: &str, query: &QueryParams) -> bool {
+        if let Some(absent) = self.absent
+            && absent == query.contains(name)
+        {
+            return false;
+        }
+        let values = query.values(name);
+        if let Some(expected) = &self.equal_to
+            && !query.all(name).contains(&expected.as_str())
+            && !values.contains(&expected.as_str())
+        {
+            return false;
+        }
+        // Like `equalTo`, these hold for the whole value or one list item
+        let mut candidates = query.all(name);
+        candidates.extend(&values);
+        if let Some(part) = &self.contains
+            && !candidates.iter().any(|v| v.contains(part.as_str()))
+        {
+            return false;
+        }
+        if let Some(pattern) = &self.matches
+            && !whole_match(pattern).is_ok_and(|r| candidates.iter().any(|v| r.is_match(v)))
+        {
+            return false;
+        }
+        if let Some(included) = &self.includes
+            && !included
+                .iter()
+                .all(|v| values.contains(&v.equal_to.as_str()))
+        {
+            return false;
+        }
+        if let Some(exact) = &self.has_exactly {
+            let mut expected: Vec<&str> = exact.iter().map(|v| v.equal_to.as_str()).collect();
+            let mut actual = values;
+            expected.sort_unstable();
+            actual.sort_unstable();
+            if expected != actual {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Stub response; the body comes from the first of `jsonBody`, `body`,
+/// `base64Body` and `bodyFileName` that is set
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StubResponse {
+    #[serde(default = "default_status")]
+    pub status: u16,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub json_body: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base64_body: Option<String>,
+    /// File under `__files/`, read on every request
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body_file_name: Option<String>,
+    /// Render the body as a template with the request context
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub template: bool,
+    /// Delay before answering
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fixed_delay_milliseconds: Option<u64>,
+}
+
+impl Default for StubResponse {
+    fn default() -> Self {
+        Self {
+            status: default_status(),
+            headers: BTreeMap::new(),
+            body: None,
+            json_body: None,
+            base64_body: None,
+            body_file_name: None,
+            template: false,
+            fixed_delay_milliseconds: None,
+        }
+    }
+}
+
+fn default_status() -> u16 {
+    200
+}
+
+/// Mapping file contents: one stub or a list
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum MappingFile {
+    Many { mappings: Vec<StubMapping> },
+    One(Box<StubMapping>),
+}
+
+/// A stub with its regular expressions compiled
+pub(crate) struct Stub {
+    pub(crate) mapping: StubMapping,
+    path_regex: Option<Regex>,
+    url_regex: Option<Regex>,
+}
+
+impl Stub {
+    pub(crate) fn compile(mapping: StubMapping) -> std::result::Result<Self, regex::Error> {
+        let request = &mapping.request;
+        let path_regex = request.url_path_pattern.as_deref().map(whole_match);
+        let url_regex = request.url_pattern.as_deref().map(whole_match);
+        for pattern in request.headers.values().chain(&request.body_patterns) {
+            pattern.validate()?;
+        }
+        for matcher in request.query_parameters.values() {
+            matcher.matches.as_deref().map(whole_match).transpose()?;
+        }
+        Ok(Self {
+            path_regex: path_regex.transpose()?,
+            url_regex: url_regex.transpose()?,
+            mapping,
+        })
+    }
+
+    /// Whether the stub matches a request, with the path parameters its
+    /// template captures
+    pub(crate) fn matches(&self, call: &StubCall<'_>) -> Option<HashMap<String, String>> {
+        let request = &self.mapping.request;
+        if let Some(expected) = &request.method
+            && !expected.eq_ignore_ascii_case("ANY")
+            && !expected.eq_ignore_ascii_case(call.method)
+        {
+            return None;
+        }
+        if request.url.as_ref().is_some_and(|u| *u != call.url) {
+            return None;
+        }
+        if self
+            .url_regex
+            .as_ref()
+            .is_some_and(|r| !r.is_match(&call.url))
+        {
+            return None;
+        }
+        if request.url_path.as_ref().is_some_and(|p| p != call.path) {
+            return None;
+        }
+        if self
+            .path_regex
+            .as_ref()
+            .is_some_and(|r| !r.is_match(call.path))
+        {
+            return None;
+        }
+        if !request
+            .query_parameters
+            .iter()
+            .all(|(name, matcher)| matcher.matches(name, &call.query))
+        {
+            return None;
+        }
+        if !request.headers.iter().all(|(name, pattern)| {
+            pattern.matches(call.headers.get(name).and_then(|v| v.to_str().ok()))
+        }) {
+            return None;
+        }
+        if !request
+            .body_patterns
+            .iter()
+            .all(|pattern| pattern.matches(call.body.as_deref()))
+        {
+            return None;
+        }
+        match &request.url_path_template {
+            Some(template) => match_path_template(template, call.path),
+            None => Some(HashMap::new()),
+        }
+    }
+}
+
+/// A request as stubs match it
+pub(crate) struct StubCall<'a> {
+    method: &'a str,
+    path: &'a str,
+    /// Path and query string
+    url: String,
+    query: QueryParams,
+    headers: &'a HeaderMap,
+    body: Option<Cow<'a, str>>,
+}
+
+impl<'a> StubCall<'a> {
+    /// A `body` of `None` (not buffered) matches no body pattern
+    pub(crate) fn new(
+        method: &'a str,
+        path: &'a str,
+        query: Option<&str>,
+        headers: &'a HeaderMap,
+        body: Option<&'a [u8]>,
+    ) -> Self {
+        let url = match query {
+            Some(q) if !q.is_empty() => format!("{}?{}", path, q),
+            _ => path.to_string(),
+        };
+        Self {
+            method,
+            path,
+            url,
+            query: QueryParams::parse(query),
+            headers,
+            body: body.map(String::from_utf8_lossy),
+        }
+    }
+}
+
+/// A matched stub and the path parameters captured by its template
+pub struct StubMatch<'a> {
+    pub mapping: &'a StubMapping,
+    pub path_params: HashMap<String, String>,
+}
+
+/// Loaded stubs, ordered by priority
+pub struct StubStore {
+    stubs: Vec<Stub>,
+    files_dir: PathBuf,
+}
+
+impl StubStore {
+    /// Load all mappings from a stubs directory
+    pub fn load(dir: &Path) -> Result<Self> {
+        let mut mappings = Vec::new();
+        let mappings_dir = dir.join(MAPPINGS_DIR);
+        if mappings_dir.is_dir() {
+            Self::load_mappings(&mappings_dir, &mut mappings)?;
+        } else {
+            tracing::warn!(
+                "Stub mappings directory does not exist: {}",
+                mappings_dir.display()
+            );
+        }
+        tracing::info!("Loaded {} stubs from {}", mappings.len(), dir.display());
+        Ok(Self::new(mappings, dir.join(FILES_DIR)))
+    }
+
+    fn load_mappings(dir: &Path, mappings: &mut Vec<StubMapping>) -> Result<()> {
+        let mut entries: Vec<PathBuf> = fs::read_dir(dir)?
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .collect();
+        // Stable order, so equal-priority stubs resolve predictably
+        entries.sort();
+
+        for path in entries {
+            if path.is_dir() {
+                Self::load_mappings(&path, mappings)?;
+                continue;
+            }
+            if !path
+                .extension()
+                .is_some_and(|ext| ext == "json" || ext == "yaml" || ext == "yml")
+            {
+                continue;
+            }
+            let parsed = fs::read_to_string(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|content| {
+                    serde_yaml::from_str::<MappingFile>(&content).map_err(|e| e.to_string())
+                });
+            match parsed {
+                Ok(MappingFile::Many { mappings: many }) => mappings.extend(many),
+                Ok(MappingFile::One(one)) => mappings.push(*one),
+                Err(e) => tracing::warn!("Failed to parse stub {}: {}", path.display(), e),
+            }
+        }
+        Ok(())
+    }
+
+    /// Build a store from mappings; `files_dir` is where `bodyFileName`s live
+    pub fn new(mappings: Vec<StubMapping>, files_dir: PathBuf) -> Self {
+        let mut stubs: Vec<Stub> = mappings
+            .into_iter()
+            .filter_map(|mapping| match Stub::compile(mapping) {
+                Ok(stub) => Some(stub),
+                Err(e) => {
+                    tracing::warn!("Skipping stub with invalid regular expression: {}", e);
+                    None
+                }
+            })
+            .collect();
+        // Stable sort keeps load order among equal priorities
+        stubs.sort_by_key(|s| s.mapping.priority.unwrap_or(DEFAULT_PRIORITY));
+        Self { stubs, files_dir }
+    }
+
+    /// Add mappings from another source (e.g. Pact files) after the loaded ones
+    pub fn with_mappings(self, mappings: Vec<StubMapping>) -> Self {
+        let all = self
+            .stubs
+            .into_iter()
+            .map(|s| s.mapping)
+            .chain(mappings)
+            .collect();
+        Self::new(all, self.files_dir)
+    }
+
+    pub fn len(&self) -> usize {
+        self.stubs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stubs.is_empty()
+    }
+
+    /// Every mapping, highest priority first
+    pub fn mappings(&self) -> impl Iterator<Item = &StubMapping> {
+        self.stubs.iter().map(|s| &s.mapping)
+    }
+
+    /// Whether any stub matches on the request body, which must then be
+    /// buffered before matching
+    pub fn matches_bodies(&self) -> bool {
+        self.stubs
+            .iter()
+            .any(|s| !s.mapping.request.body_patterns.is_empty())
+    }
+
+    /// Find the highest-priority stub matching a request, preferring stubs
+    /// of the selected scenario. A `body` of `None` (not buffered) matches no
+    /// body pattern.
+    pub fn find(
+        &self,
+        method: &str,
+        path: &str,
+        query: Option<&str>,
+        headers: &HeaderMap,
+        body: Option<&[u8]>,
+        scenario: Option<&str>,
+    ) -> Option<StubMatch<'_>> {
+        let scoped = self
+            .stubs
+            .iter()
+            .filter(|s| scenario.is_some() && s.mapping.scenario.as_deref() == scenario);
+        let unscoped = self.stubs.iter().filter(|s| s.mapping.scenario.is_none());
+        let call = StubCall::new(method, path, query, headers, body);
+        scoped.chain(unscoped).find_map(|stub| {
+            Some(StubMatch {
+                mapping: &stub.mapping,
+                path_params: stub.matches(&call)?,
+            })
+        })
+    }
+
+    /// Produce the response for a matched stub
+    pub async fn respond(&self, stub: &StubMatch<'_>, context: TemplateContext) -> Response {
+        let response = &stub.mapping.response;
+        let mut content_type = None;
+        if let Some(ms) = response.fixed_delay_milliseconds {
+            tokio::time::sleep(std::time::Duration::from_millis(ms)).await;
+        }
+
+        let body: Vec<u8> = if let Some(json) = &response.json_body {
+            content_type = Some("application/json".to_string());
+            json.to_string().into_bytes()
+        } else if let Some(body) = &response.body {
+            body.clone().into_bytes()
+        } else if let Some(encoded) = &response.base64_body {
+            match base64::engine::general_purpose::STANDARD.decode(encoded) {
+                Ok(bytes) => bytes,
+                Err(e) => return stub_error(format!("Invalid base64Body: {}", e)),
+            }
+        } else if let Some(file_name) = &response.body_file_name {
+            let Some(path) = self.body_file_path(file_name) else {
+                return stub_error(format!("Invalid bodyFileName: {}", file_name));
+            };
+            match tokio::fs::read(&path).await {
+                Ok(bytes) => {
+                    content_type = Some(
+                        mime_guess::from_path(&path)
+                            .first_or_octet_stream()
+                            .to_string(),
+                    );
+                    bytes
+                }
+                Err(e) => {
+                    return stub_error(format!("Cannot read body file {}: {}", path.display(), e));
+                }
+            }
+        } else {
+            Vec::new()
+        };
+
+        let body = if response.template {
+            let context = context.with_path_params(stub.path_params.clone());
+            let rendered = String::from_utf8_lossy(&body).into_owned();
+            match templating::render(&rendered, &context) {
+                Ok(rendered) => rendered.into_bytes(),
+                Err(e) => return stub_error(format!("Template error: {}", e)),
+            }
+        } else {
+            body
+        };
+
+        let status = StatusCode::from_u16(response.status).unwrap_or(StatusCode::OK);
+        let mut http_response = Response::new(Body::from(body));
+        *http_response.status_mut() = status;
+        let headers = http_response.headers_mut();
+        if let Some(content_type) = content_type
+            && let Ok(value) = HeaderValue::from_str(&content_type)
+        {
+            headers.insert(axum::http::header::CONTENT_TYPE, value);
+        }
+        // Explicit stub headers override the inferred content type
+        for (name, value) in &response.headers {
+            if let (Ok(name), Ok(value)) = (
+                HeaderName::from_bytes(name.as_bytes()),
+                HeaderValue::from_str(value),
+            ) {
+                headers.insert(name, value);
+            }
+        }
+        http_response
+    }
+
+    /// Resolve a body file name inside the files directory, refusing paths
+    /// that would escape it
+    fn body_file_path(&self, file_name: &str) -> Option<PathBuf> {
+        let relative = Path::new(file_name);
+        let escapes = relative
+            .components()
+            .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir));
+        (!escapes).then(|| self.files_dir.join(relative))
+    }
+}
+
+/// Match a path against a `{param}` template, returning the captured params
+fn match_path_template(template: &str, path: &str) -> Option<HashMap<String, String>> {
+    let expected: Vec<&str> = template.trim_matches('/').split('/').collect();
+    let actual: Vec<&str> = path.trim_matches('/').split('/').collect();
+    if expected.len() != actual.len() {
+        return None;
+    }
+
+    let mut params = HashMap::new();
+    for (pattern, segment) in expected.iter().zip(actual) {
+        match pattern.strip_prefix('{').and_then(|p| p.strip_suffix('}')) {
+            Some(name) => {
+                params.insert(name.to_string(), segment.to_string());
+            }
+            None if *pattern == segment => {}
+            None => return None,
+        }
+    }
+    Some(params)
+}
+
+/// A misconfigured stub is the test author's problem, so say so loudly
+fn stub_error(message: String) -> Response {
+    tracing::error!("{}", message);
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        axum::Json(serde_json::json!({ "message": message })),
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_body_file_is_served_and_templated() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join(FILES_DIR)).unwrap();
+        fs::create_dir_all(dir.path().join(MAPPINGS_DIR)).unwrap();
+        fs::write(
+            dir.path().join(FILES_DIR).join("manifest.json"),
+            r#"{"urn":"{{ path.urn }}","status":"success"}"#,
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join(MAPPINGS_DIR).join("manifest.json"),
+            r#"{
+                "request": {
+                    "method": "GET",
+                    "urlPathTemplate": "/modelderivative/v2/designdata/{urn}/manifest"
+                },
+                "response": { "bodyFileName": "manifest.json", "template": true }
+            }"#,
+        )
+        .unwrap();
+
+        let store = StubStore::load(dir.path()).unwrap();
+        let path = "/modelderivative/v2/designdata/dXJu/manifest";
+        assert!(
+            store
+                .find("POST", path, None, &HeaderMap::new(), None, None)
+                .is_none()
+        );
+        let stub = store
+            .find("GET", path, None, &HeaderMap::new(), None, None)
+            .unwrap();
+        let context = TemplateContext::new("GET", path, None, &HeaderMap::new());
+        let response = store.respond(&stub, context).await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["content-type"], "application/json");
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(&body[..], br#"{"urn":"dXJu","status":"success"}"#);
+    }
+
+    #[test]
+    fn test_query_parameters_match_repeated_and_comma_separated_values() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join(MAPPINGS_DIR)).unwrap();
+        fs::write(
+            dir.path().join(MAPPINGS_DIR).join("issues.yaml"),
+            r#"
+request:
+  method: GET
+  urlPath: /construction/issues/v1/projects/p1/issues
+  queryParameters:
+    "filter[status]": { hasExactly: [{ equalTo: open }, { equalTo: closed }] }
+    "filter[assignedTo]": { includes: [{ equalTo: u1 }] }
+    "page[limit]": { equalTo: "10" }
+    deleted: { absent: true }
+response: { status: 200, body: filtered }
+"#,
+        )
+        .unwrap();
+        let store = StubStore::load(dir.path()).unwrap();
+        let path = "/construction/issues/v1/projects/p1/issues";
+        let find = |query: &str| {
+            store
+                .find("GET", path, Some(query), &HeaderMap::new(), None, None)
+                .is_some()
+        };
+
+        assert!(find(
+            "filter[status]=open,closed&filter[assignedTo]=u2,u1&page[limit]=10"
+        ));
+        assert!(find(
+            "filter[status]=closed&filter[status]=open&filter[assignedTo]=u1&page[limit]=10"
+        ));
+        assert!(!find(
+            "filter[status]=open&filter[assignedTo]=u1&page[limit]=10"
+        ));
+        assert!(!find(
+            "filter[status]=open,closed&filter[assignedTo]=u1&page[limit]=10&deleted=true"
+        ));
+        assert!(!find("filter[status]=open,closed&page[limit]=10"));
+    }
+}
@@ -0,0 +1,362 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+//! Recording of proxied traffic as stubs (`--record` with `--proxy-base`).
+//!
+//! Every request forwarded upstream is written to the recording directory
+//! as a stub answering its method, path and query with the upstream's
+//! response, so one run against the live APIs leaves a fixture set for
+//! `--stubs-dir`. JSON and text bodies go into the mapping, other bodies
+//! into `__files/`. A request forwarded several times keeps its first
+//! response.
+//!
+//! Secrets are redacted before anything is written: JSON fields and query
+//! parameters named like credentials (`access_token`, `client_secret`,
+//! `X-Amz-Signature`, ...), including the query of URLs inside bodies, read
+//! `REDACTED`, and cookies are dropped. Redacted query parameters of the
+//! request match any value.
+
+use super::{
+    FILES_DIR, MAPPINGS_DIR, QueryParameterMatcher, StubMapping, StubRequest, StubResponse,
+};
+use crate::error::{MockError, Result};
+use crate::har::TRANSFER_HEADERS;
+use axum::http::{HeaderMap, header};
+use dashmap::DashSet;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// What redacted values are replaced with
+pub const REDACTED: &str = "REDACTED";
+
+/// Names (lowercase) of fields, parameters and headers holding secrets
+const SECRET_NAMES: &[&str] = &[
+    "access_token",
+    "refresh_token",
+    "id_token",
+    "token",
+    "client_secret",
+    "secret",
+    "password",
+    "api_key",
+    "apikey",
+    "authorization",
+    "signature",
+    "x-amz-signature",
+    "x-amz-credential",
+    "x-amz-security-token",
+];
+
+/// Headers carrying cookies, redacted where headers are kept
+const COOKIE_HEADERS: &[&str] = &["cookie", "set-cookie"];
+
+/// Response headers left out of recordings
+const DROPPED_HEADERS: &[&str] = &["set-cookie", "date"];
+
+/// Longest file name stem derived from a request path
+const MAX_STEM: usize = 80;
+
+/// Writes forwarded exchanges into a stubs directory
+pub struct Recorder {
+    dir: PathBuf,
+    /// `METHOD url` of the requests recorded so far
+    recorded: DashSet<String>,
+}
+
+impl Recorder {
+    /// Record into `dir`, creating its `mappings/` and `__files/`
+    pub fn new(dir: &Path) -> Result<Self> {
+        fs::create_dir_all(dir.join(MAPPINGS_DIR))?;
+        fs::create_dir_all(dir.join(FILES_DIR))?;
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            recorded: DashSet::new(),
+        })
+    }
+
+    /// Record an exchange unless its request was recorded before. Failures
+    /// are logged, never passed on to the client.
+    pub fn record(
+        &self,
+        method: &str,
+        path_and_query: &str,
+        status: u16,
+        headers: &HeaderMap,
+        body: &[u8],
+    ) {
+        // Requests differing in their secrets only are the same stub
+        let path_and_query = redact_url(path_and_query);
+        let key = format!("{} {}", method, path_and_query);
+        if !self.recorded.insert(key.clone()) {
+            return;
+        }
+        if let Err(e) = self.write(method, &path_and_query, status, headers, body) {
+            tracing::warn!("Cannot record {}: {}", key, e);
+        }
+    }
+
+    fn write(
+        &self,
+        method: &str,
+        path_and_query: &str,
+        status: u16,
+        headers: &HeaderMap,
+        body: &[u8],
+    ) -> Result<()> {
+        let (path, query) = path_and_query
+            .split_once('?')
+            .unwrap_or((path_and_query, ""));
+        let stem = file_stem(method, path_and_query);
+        // A compressed body cannot be redacted, nor replayed once its
+        // Content-Encoding is dropped
+        if let Some(encoding) = headers
+            .get(header::CONTENT_ENCODING)
+            .and_then(|v| v.to_str().ok())
+            .filter(|encoding| !encoding.eq_ignore_ascii_case("identity"))
+        {
+            return Err(MockError::Config(format!(
+                "{} encoded responses are not recorded",
+                encoding
+            )));
+        }
+
+        let mut response = StubResponse {
+            status,
+            headers: headers
+                .iter()
+                .filter(|(name, _)| {
+                    !TRANSFER_HEADERS.contains(&name.as_str())
+                        && !DROPPED_HEADERS.contains(&name.as_str())
+                })
+                .filter_map(|(name, value)| {
+                    let value = if is_secret(name.as_str()) {
+                        REDACTED.to_string()
+                    } else {
+                        value.to_str().ok()?.to_string()
+                    };
+                    Some((name.as_str().to_string(), value))
+                })
+                .collect(),
+            ..StubResponse::default()
+        };
+        let content_type = headers
+            .get(header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or_default();
+        if !body.is_empty() {
+            match (
+                serde_json::from_slice::<Value>(body),
+                std::str::from_utf8(body),
+            ) {
+                (Ok(mut json), _) if content_type.contains("json") => {
+                    redact_json(&mut json);
+                    response.json_body = Some(json);
+                }
+                (_, Ok(text)) if !content_type.starts_with("application/octet-stream") => {
+                    response.body = Some(redact_urls(text));
+                }
+                _ => {
+                    let essence = content_type.split(';').next().unwrap_or_default().trim();
+                    let extension = match essence {
+                        "application/octet-stream" => None,
+                        _ => mime_guess::get_mime_extensions_str(essence)
+                            .and_then(|extensions| extensions.first()),
+                    }
+                    .unwrap_or(&"bin");
+                    let file_name = format!("{}.{}", stem, extension);
+                    fs::write(self.dir.join(FILES_DIR).join(&file_name), body)?;
+                    response.body_file_name = Some(file_name);
+                }
+            }
+        }
+
+        let mut query_parameters = BTreeMap::new();
+        for (name, value) in form_urlencoded::parse(query.as_bytes()) {
+            let matcher = if is_secret(&name) {
+                QueryParameterMatcher {
+                    absent: Some(false),
+                    ..QueryParameterMatcher::default()
+                }
+            } else {
+                QueryParameterMatcher {
+                    equal_to: Some(value.into_owned()),
+                    ..QueryParameterMatcher::default()
+                }
+            };
+            query_parameters.insert(name.into_owned(), matcher);
+        }
+        let stub = StubMapping {
+            id: None,
+            name: Some(format!("{} {} (recorded)", method, path_and_query)),
+            priority: None,
+            scenario: None,
+            request: StubRequest {
+                method: Some(method.to_string()),
+                url_path: Some(path.to_string()),
+                query_parameters,
+                ..StubRequest::default()
+            },
+            response,
+        };
+        let json = serde_json::to_string_pretty(&stub)
+            .map_err(|e| MockError::Config(format!("Cannot serialize stub: {}", e)))?;
+        fs::write(
+            self.dir.join(MAPPINGS_DIR).join(format!("{}.json", stem)),
+            json,
+        )?;
+        tracing::debug!("Recorded {} {}", method, path);
+        Ok(())
+    }
+}
+
+fn is_secret(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    SECRET_NAMES.contains(&name.as_str())
+}
+
+/// Whether a header's value is redacted where headers are kept, e.g. in
+/// the request journal
+pub(crate) fn is_secret_header(name: &str) -> bool {
+    is_secret(name) || COOKIE_HEADERS.contains(&name.to_ascii_lowercase().as_str())
+}
+
+/// Redact the secrets of a body kept as text: JSON fields, form fields and
+/// the query of URLs. Bodies without secrets are returned as they were.
+pub(crate) fn redact_body(text: &str, content_type: &str) -> String {
+    if content_type.starts_with("application/x-www-form-urlencoded") {
+        return redact_query(text);
+    }
+    if content_type.contains("json")
+        && let Ok(json) = serde_json::from_str::<Value>(text)
+    {
+        let mut redacted = json.clone();
+        redact_json(&mut redacted);
+        return if redacted == json {
+            text.to_string()
+        } else {
+            redacted.to_string()
+        };
+    }
+    redact_urls(text)
+}
+
+/// A file name stem for a request, e.g. `get-oss-v2-buckets-1a2b3c4d`: the
+/// readable part from the path, the hash telling apart queries and
+/// truncated paths
+fn file_stem(method: &str, path_and_query: &str) -> String {
+    let path = path_and_query.split('?').next().unwrap_or_default();
+    let mut readable: String = format!("{}-{}", method, path.trim_matches('/'))
+        .to_ascii_lowercase()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' || c == '_' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    readable.truncate(MAX_STEM);
+    let hash = xxhash_rust::xxh3::xxh3_64(format!("{} {}", method, path_and_query).as_bytes());
+    format!("{}-{:08x}", readable, hash as u32)
+}
+
+/// Redact the secret query parameters of a URL (or path and query)
+fn redact_url(url: &str) -> String {
+    match url.split_once('?') {
+        Some((base, query)) => format!("{}?{}", base, redact_query(query)),
+        None => url.to_string(),
+    }
+}
+
+/// Redact the secret parameters of a query string or form body
+pub(crate) fn redact_query(query: &str) -> String {
+    query
+        .split('&')
+        .map(|pair| match pair.split_once('=') {
+            Some((name, _)) if is_secret(name) => format!("{}={}", name, REDACTED),
+            _ => pair.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+/// Redact URLs with secret query parameters anywhere in a text
+fn redact_urls(text: &str) -> String {
+    text.split_inclusive(char::is_whitespace)
+        .map(redact_url)
+        .collect()
+}
+
+fn redact_json(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (name, value) in map.iter_mut() {
+                if is_secret(name) && !value.is_null() {
+                    *value = Value::String(REDACTED.to_string());
+                } else {
+                    redact_json(value);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(redact_json),
+        Value::String(s) if s.contains('?') => *s = redact_url(s),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stubs::StubStore;
+    use axum::http::HeaderValue;
+
+    #[test]
+    fn test_recordings_replay_with_secrets_redacted() {
+        let dir = tempfile::tempdir().unwrap();
+        let recorder = Recorder::new(dir.path()).unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("application/json"),
+        );
+        headers.insert(header::SET_COOKIE, HeaderValue::from_static("session=abc"));
+        let body = serde_json::json!({
+            "access_token": "eyJhbGciOi",
+            "urls": ["https://s3.amazonaws.com/b/o?X-Amz-Signature=deadbeef&X-Amz-Expires=60"]
+        });
+        let path = "/oss/v2/buckets/b1/objects/o1/signeds3download?minutesExpiration=60&token=t1";
+        recorder.record("GET", path, 200, &headers, body.to_string().as_bytes());
+        // Later responses to the same request are not recorded
+        let other_token = path.replace("token=t1", "token=t2");
+        recorder.record("GET", &other_token, 500, &HeaderMap::new(), b"");
+
+        let store = StubStore::load(dir.path()).unwrap();
+        assert_eq!(store.len(), 1);
+        let (path, query) = other_token.split_once('?').unwrap();
+        let stub = store
+            .find("GET", path, Some(query), &HeaderMap::new(), None, None)
+            .unwrap();
+        let response = &stub.mapping.response;
+        assert_eq!(response.status, 200);
+        assert!(!response.headers.contains_key("set-cookie"));
+        let json = response.json_body.as_ref().unwrap();
+        assert_eq!(json["access_token"], REDACTED);
+        assert_eq!(
+            json["urls"][0],
+            "https://s3.amazonaws.com/b/o?X-Amz-Signature=REDACTED&X-Amz-Expires=60"
+        );
+        let mapping = fs::read_to_string(
+            fs::read_dir(dir.path().join(MAPPINGS_DIR))
+                .unwrap()
+                .next()
+                .unwrap()
+                .unwrap()
+                .path(),
+        )
+        .unwrap();
+        assert!(!mapping.contains("t1"));
+    }
+}
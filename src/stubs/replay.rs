@@ -0,0 +1,222 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+//! Offline replay of recordings (`--replay`).
+//!
+//! A recording directory written by `--record` (or any stubs directory)
+//! answers the requests `--proxy-base` would have forwarded, so a suite
+//! recorded once against the live APIs runs without network access.
+//! Requests match their recording exactly; with fuzzy matching (the
+//! default) a request whose query differs falls back to the recording of
+//! the same method and path sharing the most query parameters, marked with
+//! `x-mock-replay: fuzzy`. Requests without a recording answer `501` with
+//! the recordings closest to them, so a gap in the fixtures is obvious.
+
+use super::{StubMapping, StubMatch, StubStore};
+use crate::config::ReplayMatch;
+use crate::handlers::query::QueryParams;
+use crate::templating::TemplateContext;
+use axum::{
+    Json,
+    extract::Request,
+    http::{HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+};
+use serde_json::json;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Response header telling fuzzy matches apart
+pub const REPLAY_HEADER: &str = "x-mock-replay";
+
+/// Recordings listed in a `501` answer at most
+const MAX_CLOSEST: usize = 5;
+
+/// Recordings answering requests offline
+pub struct Replay {
+    store: StubStore,
+    matching: ReplayMatch,
+}
+
+impl Replay {
+    pub fn load(dir: &Path, matching: ReplayMatch) -> crate::error::Result<Self> {
+        Ok(Self {
+            store: StubStore::load(dir)?,
+            matching,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.store.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.store.is_empty()
+    }
+
+    /// Answer a request from its recording, or with `501`
+    pub async fn answer(&self, request: Request) -> Response {
+        let method = request.method().as_str();
+        let path = request.uri().path();
+        let query = request.uri().query();
+        let context = TemplateContext::new(method, path, query, request.headers());
+
+        if let Some(stub) = self
+            .store
+            .find(method, path, query, request.headers(), None, None)
+        {
+            return self.store.respond(&stub, context).await;
+        }
+        if self.matching == ReplayMatch::Fuzzy
+            && let Some(mapping) = self.closest_query(method, path, query)
+        {
+            let stub = StubMatch {
+                mapping,
+                path_params: HashMap::new(),
+            };
+            let mut response = self.store.respond(&stub, context).await;
+            response
+                .headers_mut()
+                .insert(REPLAY_HEADER, HeaderValue::from_static("fuzzy"));
+            return response;
+        }
+        self.not_recorded(method, &context.url, path)
+    }
+
+    /// The recording of `method` and `path` whose query parameters match
+    /// the most of the request's
+    fn closest_query(&self, method: &str, path: &str, query: Option<&str>) -> Option<&StubMapping> {
+        let query = QueryParams::parse(query);
+        let mut best: Option<(usize, &StubMapping)> = None;
+        for mapping in self.store.mappings() {
+            let request = &mapping.request;
+            if !request
+                .method
+                .as_deref()
+                .is_none_or(|m| m.eq_ignore_ascii_case(method))
+                || request.url_path.as_deref() != Some(path)
+            {
+                continue;
+            }
+            let score = request
+                .query_parameters
+                .iter()
+                .filter(|(name, matcher)| matcher.matches(name, &query))
+                .count();
+            // The first recording wins ties, like among exact matches
+            if best.is_none_or(|(best, _)| score > best) {
+                best = Some((score, mapping));
+            }
+        }
+        best.map(|(_, mapping)| mapping)
+    }
+
+    /// `501` listing the recordings sharing the longest path prefix with
+    /// the request
+    fn not_recorded(&self, method: &str, url: &str, path: &str) -> Response {
+        let shared = |other: &str| {
+            path.split('/')
+                .zip(other.split('/'))
+                .take_while(|(a, b)| a == b)
+                .count()
+        };
+        let mut candidates: Vec<(usize, &StubMapping)> = self
+            .store
+            .mappings()
+            .filter_map(|mapping| {
+                let recorded = mapping.request.url_path.as_deref()?;
+                Some((shared(recorded), mapping))
+            })
+            .filter(|(shared, _)| *shared > 1)
+            .collect();
+        // Stable, so equally close recordings keep their order
+        candidates.sort_by_key(|(shared, _)| std::cmp::Reverse(*shared));
+        let closest: Vec<_> = candidates
+            .into_iter()
+            .take(MAX_CLOSEST)
+            .map(|(_, mapping)| {
+                json!({
+                    "name": mapping.name,
+                    "method": mapping.request.method,
+                    "urlPath": mapping.request.url_path,
+                    "queryParameters": mapping.request.query_parameters,
+                })
+            })
+            .collect();
+        tracing::warn!("No recording for {} {}", method, url);
+        (
+            StatusCode::NOT_IMPLEMENTED,
+            Json(json!({
+                "developerMessage": format!(
+                    "No recording matches {} {}; record it with --proxy-base and --record",
+                    method, url
+                ),
+                "errorCode": "NOT_RECORDED",
+                "request": { "method": method, "url": url },
+                "closest": closest,
+            })),
+        )
+            .into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stubs::record::Recorder;
+    use axum::body::Body;
+    use axum::http::{HeaderMap, header};
+    use serde_json::Value;
+
+    #[tokio::test]
+    async fn test_recordings_replay_exactly_fuzzily_or_not_at_all() {
+        let dir = tempfile::tempdir().unwrap();
+        let recorder = Recorder::new(dir.path()).unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("application/json"),
+        );
+        for limit in ["1", "10"] {
+            recorder.record(
+                "GET",
+                &format!("/oss/v2/buckets?region=US&limit={}", limit),
+                200,
+                &headers,
+                format!(r#"{{"limit":{}}}"#, limit).as_bytes(),
+            );
+        }
+        let get = |uri: &str| Request::get(uri).body(Body::empty()).unwrap();
+        let json = |response: Response| async {
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            serde_json::from_slice::<Value>(&body).unwrap()
+        };
+
+        let replay = Replay::load(dir.path(), ReplayMatch::Fuzzy).unwrap();
+        assert_eq!(replay.len(), 2);
+        let exact = replay
+            .answer(get("/oss/v2/buckets?limit=10&region=US"))
+            .await;
+        assert!(exact.headers().get(REPLAY_HEADER).is_none());
+        assert_eq!(json(exact).await["limit"], 10);
+        let fuzzy = replay
+            .answer(get("/oss/v2/buckets?limit=10&region=EMEA"))
+            .await;
+        assert_eq!(fuzzy.headers()[REPLAY_HEADER], "fuzzy");
+        assert_eq!(json(fuzzy).await["limit"], 10);
+
+        let missing = replay.answer(get("/oss/v2/buckets/b1/details")).await;
+        assert_eq!(missing.status(), StatusCode::NOT_IMPLEMENTED);
+        let body = json(missing).await;
+        assert_eq!(body["errorCode"], "NOT_RECORDED");
+        assert_eq!(body["closest"][0]["urlPath"], "/oss/v2/buckets");
+
+        let strict = Replay::load(dir.path(), ReplayMatch::Exact).unwrap();
+        let response = strict
+            .answer(get("/oss/v2/buckets?limit=10&region=EMEA"))
+            .await;
+        assert_eq!(response.status(), StatusCode::NOT_IMPLEMENTED);
+    }
+}
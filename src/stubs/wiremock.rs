@@ -0,0 +1,593 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+//! Conversion of WireMock stub mappings into raps-mock stubs.
+//!
+//! A WireMock root directory (`mappings/` and `__files/`) converts into a
+//! stubs directory of the same layout ([`import`]). Most of a mapping carries
+//! over as it is; the rest is translated:
+//!
+//! - `transformers: ["response-template"]` becomes `template: true`, and the
+//!   Handlebars expressions of templated bodies and body files become
+//!   minijinja ones: `request.url`, `request.method`, `request.path`,
+//!   `request.path.[n]` (or `request.pathSegments.[n]`), `request.query.x`,
+//!   `request.headers.x`, `now` and `randomValue type='UUID'`. Other helpers
+//!   are left as they are and reported.
+//! - mappings of a WireMock scenario are imported in their `Started` state
+//!   only, since stubs have no scenario state
+//!
+//! Mappings using matchers or responses stubs cannot express (e.g.
+//! `matchesJsonPath`, `fault` or `proxyBaseUrl`) are skipped and reported
+//! rather than imported with looser matching.
+
+use super::{ContentPattern, FILES_DIR, MAPPINGS_DIR, QueryParameterMatcher};
+use super::{StubMapping, StubRequest, StubResponse};
+use crate::error::{MockError, Result};
+use regex::{Captures, Regex};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+/// WireMock's name for the initial state of a scenario
+const STARTED: &str = "Started";
+
+/// A Handlebars expression, `{{...}}` or `{{{...}}}`
+static EXPRESSION_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\{\{\{?\s*(.*?)\s*\}?\}\}").expect("Invalid template expression regex")
+});
+
+/// `request.path.[N]`, a path segment by index
+static SEGMENT_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^request\.(?:path|pathSegments)\.\[(\d+)\]$").expect("Invalid segment regex")
+});
+
+/// `request.query.NAME` or `request.headers.[NAME]`
+static NAMED_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^request\.(query|headers)\.(?:\[([^\]]+)\]|([\w-]+))$")
+        .expect("Invalid query or header regex")
+});
+
+/// `randomValue type='UUID'`
+static UUID_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"^randomValue\s+type=['"]UUID['"]$"#).expect("Invalid random UUID regex")
+});
+
+/// Keys of the WireMock value matchers a [`ContentPattern`] supports
+const CONTENT_MATCHERS: &[&str] = &[
+    "equalTo",
+    "caseInsensitive",
+    "contains",
+    "matches",
+    "doesNotMatch",
+    "equalToJson",
+    "absent",
+];
+
+/// Keys of the WireMock query matchers a [`QueryParameterMatcher`] supports
+const QUERY_MATCHERS: &[&str] = &[
+    "equalTo",
+    "contains",
+    "matches",
+    "absent",
+    "includes",
+    "hasExactly",
+];
+
+/// Response fields without a stub equivalent that change what is served
+const UNSUPPORTED_RESPONSES: &[&str] = &["fault", "proxyBaseUrl"];
+
+/// Outcome of an import
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    /// Mappings written to the stubs directory
+    pub imported: usize,
+    /// Mappings left out, with the reason
+    pub skipped: Vec<String>,
+    /// Parts of imported mappings that were dropped or not translated
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WireMockMapping {
+    #[serde(default)]
+    id: Option<String>,
+    #[serde(default)]
+    uuid: Option<String>,
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    priority: Option<u32>,
+    #[serde(default)]
+    scenario_name: Option<String>,
+    #[serde(default)]
+    required_scenario_state: Option<String>,
+    #[serde(default)]
+    new_scenario_state: Option<String>,
+    #[serde(default)]
+    request: WireMockRequest,
+    #[serde(default)]
+    response: WireMockResponse,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WireMockRequest {
+    #[serde(default)]
+    method: Option<String>,
+    #[serde(default)]
+    url: Option<String>,
+    #[serde(default)]
+    url_path: Option<String>,
+    #[serde(default)]
+    url_pattern: Option<String>,
+    #[serde(default)]
+    url_path_pattern: Option<String>,
+    #[serde(default)]
+    url_path_template: Option<String>,
+    #[serde(default)]
+    query_parameters: BTreeMap<String, Map<String, Value>>,
+    #[serde(default)]
+    headers: BTreeMap<String, Map<String, Value>>,
+    #[serde(default)]
+    body_patterns: Vec<Map<String, Value>>,
+    /// Matchers with no stub equivalent (`cookies`, `basicAuthCredentials`, ...)
+    #[serde(flatten)]
+    other: Map<String, Value>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WireMockResponse {
+    #[serde(default)]
+    status: Option<u16>,
+    #[serde(default)]
+    headers: BTreeMap<String, Value>,
+    #[serde(default)]
+    body: Option<String>,
+    #[serde(default)]
+    json_body: Option<Value>,
+    #[serde(default)]
+    base64_body: Option<String>,
+    #[serde(default)]
+    body_file_name: Option<String>,
+    #[serde(default)]
+    transformers: Vec<String>,
+    #[serde(default)]
+    fixed_delay_milliseconds: Option<u64>,
+    #[serde(flatten)]
+    other: Map<String, Value>,
+}
+
+/// A converted mapping and what of it did not carry over
+#[derive(Debug)]
+pub struct Converted {
+    pub stub: StubMapping,
+    pub warnings: Vec<String>,
+}
+
+/// Convert one WireMock mapping, or say why it cannot be
+pub fn convert(mapping: &Value) -> std::result::Result<Converted, String> {
+    let mapping: WireMockMapping =
+        serde_json::from_value(mapping.clone()).map_err(|e| e.to_string())?;
+    let mut warnings = Vec::new();
+
+    if let Some(scenario) = &mapping.scenario_name {
+        match mapping.required_scenario_state.as_deref() {
+            None | Some(STARTED) => {}
+            Some(state) => {
+                return Err(format!(
+                    "needs state {:?} of scenario {:?}; stubs have no scenario state",
+                    state, scenario
+                ));
+            }
+        }
+        if mapping.new_scenario_state.is_some() {
+            warnings.push(format!(
+                "scenario {:?} stays in its Started state",
+                scenario
+            ));
+        }
+    }
+
+    let request = mapping.request;
+    if let Some(key) = request.other.keys().next() {
+        return Err(format!("unsupported request matcher {}", key));
+    }
+    let query_parameters = request
+        .query_parameters
+        .into_iter()
+        .map(|(name, matcher)| Ok((name, query_matcher(matcher)?)))
+        .collect::<std::result::Result<_, String>>()?;
+    let headers = request
+        .headers
+        .into_iter()
+        .map(|(name, matcher)| Ok((name, content_pattern(matcher)?)))
+        .collect::<std::result::Result<_, String>>()?;
+    let body_patterns = request
+        .body_patterns
+        .into_iter()
+        .map(content_pattern)
+        .collect::<std::result::Result<_, String>>()?;
+
+    let response = mapping.response;
+    for key in response.other.keys() {
+        if UNSUPPORTED_RESPONSES.contains(&key.as_str()) {
+            return Err(format!("unsupported response {}", key));
+        }
+        warnings.push(format!("ignored response {}", key));
+    }
+    let mut template = false;
+    for transformer in &response.transformers {
+        if transformer == "response-template" {
+            template = true;
+        } else {
+            warnings.push(format!("ignored transformer {}", transformer));
+        }
+    }
+    let headers_out = response
+        .headers
+        .into_iter()
+        .map(|(name, value)| {
+            let value = match value {
+                Value::String(s) => s,
+                Value::Array(values) => values
+                    .iter()
+                    .map(|v| v.as_str().map_or_else(|| v.to_string(), str::to_string))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                other => other.to_string(),
+            };
+            if template && value.contains("{{") {
+                warnings.push(format!("header {} is not templated", name));
+            }
+            (name, value)
+        })
+        .collect();
+    let mut body = response.body;
+    let mut json_body = response.json_body;
+    if template {
+        if let Some(text) = &mut body {
+            *text = translate_template(text, &mut warnings);
+        }
+        if let Some(json) = &mut json_body {
+            translate_value(json, &mut warnings);
+        }
+    }
+
+    Ok(Converted {
+        stub: StubMapping {
+            id: mapping.id.or(mapping.uuid),
+            name: mapping.name,
+            priority: mapping.priority,
+            scenario: None,
+            request: StubRequest {
+                method: request.method,
+                url: request.url,
+                url_path: request.url_path,
+                url_pattern: request.url_pattern,
+                url_path_pattern: request.url_path_pattern,
+                url_path_template: request.url_path_template,
+                query_parameters,
+                headers,
+                body_patterns,
+            },
+            response: StubResponse {
+                status: response.status.unwrap_or(200),
+                headers: headers_out,
+                body,
+                json_body,
+                base64_body: response.base64_body,
+                body_file_name: response.body_file_name,
+                template,
+                fixed_delay_milliseconds: response.fixed_delay_milliseconds,
+            },
+        },
+        warnings,
+    })
+}
+
+fn query_matcher(
+    matcher: Map<String, Value>,
+) -> std::result::Result<QueryParameterMatcher, String> {
+    if let Some(key) = matcher
+        .keys()
+        .find(|k| !QUERY_MATCHERS.contains(&k.as_str()))
+    {
+        return Err(format!("unsupported query parameter matcher {}", key));
+    }
+    serde_json::from_value(Value::Object(matcher))
+        .map_err(|e| format!("unsupported query parameter matcher: {}", e))
+}
+
+fn content_pattern(mut matcher: Map<String, Value>) -> std::result::Result<ContentPattern, String> {
+    // Leniencies of equalToJson only matter when enabled
+    for option in ["ignoreArrayOrder", "ignoreExtraElements"] {
+        match matcher.remove(option) {
+            None | Some(Value::Bool(false)) => {}
+            Some(_) => return Err(format!("unsupported equalToJson option {}", option)),
+        }
+    }
+    if let Some(key) = matcher
+        .keys()
+        .find(|k| !CONTENT_MATCHERS.contains(&k.as_str()))
+    {
+        return Err(format!("unsupported matcher {}", key));
+    }
+    // WireMock takes the expected JSON as a document or as a string holding one
+    if let Some(Value::String(json)) = matcher.get("equalToJson") {
+        let parsed = serde_json::from_str(json)
+            .map_err(|e| format!("equalToJson is not valid JSON: {}", e))?;
+        matcher.insert("equalToJson".to_string(), parsed);
+    }
+    serde_json::from_value(Value::Object(matcher)).map_err(|e| e.to_string())
+}
+
+/// Translate the Handlebars expressions of a template into minijinja ones,
+/// reporting those without an equivalent
+pub fn translate_template(template: &str, warnings: &mut Vec<String>) -> String {
+    EXPRESSION_REGEX
+        .replace_all(template, |caps: &Captures| {
+            match translate_expression(&caps[1]) {
+                Some(translated) => format!("{{{{ {} }}}}", translated),
+                None => {
+                    warnings.push(format!("untranslated template expression {}", &caps[0]));
+                    caps[0].to_string()
+                }
+            }
+        })
+        .into_owned()
+}
+
+fn translate_value(value: &mut Value, warnings: &mut Vec<String>) {
+    match value {
+        Value::String(s) if s.contains("{{") => *s = translate_template(s, warnings),
+        Value::Array(items) => items.iter_mut().for_each(|v| translate_value(v, warnings)),
+        Value::Object(map) => map.values_mut().for_each(|v| translate_value(v, warnings)),
+        _ => {}
+    }
+}
+
+/// The minijinja equivalent of a Handlebars expression. Strings are
+/// single-quoted so the result survives inside JSON bodies.
+fn translate_expression(expression: &str) -> Option<String> {
+    match expression {
+        "request.url" | "request.requestLine.pathAndQuery" => Some("url".to_string()),
+        "request.method" | "request.requestLine.method" => Some("method".to_string()),
+        "request.path" | "request.requestLine.path" => Some("'/' ~ segments|join('/')".to_string()),
+        "now" => Some("now".to_string()),
+        _ if UUID_REGEX.is_match(expression) => Some("uuid".to_string()),
+        _ => {
+            if let Some(caps) = SEGMENT_REGEX.captures(expression) {
+                return Some(format!("segments[{}]", &caps[1]));
+            }
+            let caps = NAMED_REGEX.captures(expression)?;
+            let name = caps.get(2).or(caps.get(3))?.as_str();
+            Some(match &caps[1] {
+                // Header names are lowercased in the template context
+                "headers" => format!("headers['{}']", name.to_ascii_lowercase()),
+                _ => format!("query['{}']", name),
+            })
+        }
+    }
+}
+
+/// Convert a WireMock root directory into the stubs directory `to`: every
+/// file of `mappings/` into a file of the same name holding the converted
+/// mappings, and `__files/` copied over, with the body files of templated
+/// mappings translated
+pub fn import(from: &Path, to: &Path) -> Result<ImportReport> {
+    let mappings_dir = from.join(MAPPINGS_DIR);
+    if !mappings_dir.is_dir() {
+        return Err(MockError::Config(format!(
+            "Not a WireMock directory, {} is missing",
+            mappings_dir.display()
+        )));
+    }
+    let mut report = ImportReport::default();
+    let mut templated_files = BTreeSet::new();
+
+    for path in files(&mappings_dir)? {
+        if path.extension().is_none_or(|ext| ext != "json") {
+            continue;
+        }
+        let relative = path.strip_prefix(&mappings_dir).unwrap_or(&path);
+        let label = relative.display().to_string();
+        let content: Value = match serde_json::from_str(&fs::read_to_string(&path)?) {
+            Ok(content) => content,
+            Err(e) => {
+                report.skipped.push(format!("{}: {}", label, e));
+                continue;
+            }
+        };
+        let mappings = match content {
+            Value::Object(mut file) if file.get("mappings").is_some_and(Value::is_array) => {
+                match file.remove("mappings") {
+                    Some(Value::Array(mappings)) => mappings,
+                    _ => Vec::new(),
+                }
+            }
+            one => vec![one],
+        };
+
+        let mut stubs = Vec::new();
+        for (index, mapping) in mappings.iter().enumerate() {
+            let label = match mapping.get("name").and_then(Value::as_str) {
+                Some(name) => format!("{} ({})", label, name),
+                None if mappings.len() > 1 => format!("{} #{}", label, index + 1),
+                None => label.clone(),
+            };
+            match convert(mapping) {
+                Ok(converted) => {
+                    let response = &converted.stub.response;
+                    if response.template
+                        && let Some(file) = &response.body_file_name
+                    {
+                        templated_files.insert(PathBuf::from(file));
+                    }
+                    report.warnings.extend(
+                        converted
+                            .warnings
+                            .into_iter()
+                            .map(|w| format!("{}: {}", label, w)),
+                    );
+                    stubs.push(converted.stub);
+                }
+                Err(reason) => report.skipped.push(format!("{}: {}", label, reason)),
+            }
+        }
+        if stubs.is_empty() {
+            continue;
+        }
+        report.imported += stubs.len();
+        let target = to.join(MAPPINGS_DIR).join(relative);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_string_pretty(&BTreeMap::from([("mappings", &stubs)]))
+            .map_err(|e| MockError::Config(format!("Cannot serialize stubs: {}", e)))?;
+        fs::write(target, json)?;
+    }
+
+    let files_dir = from.join(FILES_DIR);
+    if files_dir.is_dir() {
+        for path in files(&files_dir)? {
+            let relative = path.strip_prefix(&files_dir).unwrap_or(&path);
+            let target = to.join(FILES_DIR).join(relative);
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            if templated_files.contains(relative) {
+                let mut warnings = Vec::new();
+                let translated = translate_template(&fs::read_to_string(&path)?, &mut warnings);
+                report.warnings.extend(
+                    warnings
+                        .into_iter()
+                        .map(|w| format!("{}/{}: {}", FILES_DIR, relative.display(), w)),
+                );
+                fs::write(target, translated)?;
+            } else {
+                fs::copy(&path, target)?;
+            }
+        }
+    }
+    Ok(report)
+}
+
+/// Every file below `dir`, in a stable order
+fn files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut found = Vec::new();
+    let mut entries: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .collect();
+    entries.sort();
+    for path in entries {
+        if path.is_dir() {
+            found.extend(files(&path)?);
+        } else {
+            found.push(path);
+        }
+    }
+    Ok(found)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stubs::StubStore;
+    use crate::templating::TemplateContext;
+    use axum::http::{HeaderMap, HeaderValue, StatusCode};
+
+    #[tokio::test]
+    async fn test_wiremock_directory_imports_as_stubs() {
+        let wiremock = tempfile::tempdir().unwrap();
+        let out = tempfile::tempdir().unwrap();
+        fs::create_dir_all(wiremock.path().join(MAPPINGS_DIR)).unwrap();
+        fs::create_dir_all(wiremock.path().join(FILES_DIR)).unwrap();
+        fs::write(
+            wiremock.path().join(MAPPINGS_DIR).join("objects.json"),
+            r#"{
+                "mappings": [
+                    {
+                        "name": "upload",
+                        "request": {
+                            "method": "PUT",
+                            "urlPattern": "/oss/v2/buckets/[a-z0-9-]+/objects/.*",
+                            "headers": { "Content-Type": { "contains": "json" } },
+                            "bodyPatterns": [{ "equalToJson": "{\"name\": \"model\"}" }]
+                        },
+                        "response": {
+                            "status": 200,
+                            "bodyFileName": "object.json",
+                            "transformers": ["response-template"]
+                        }
+                    },
+                    {
+                        "name": "outage",
+                        "request": { "method": "GET", "urlPath": "/oss/v2/buckets" },
+                        "response": { "fault": "CONNECTION_RESET_BY_PEER" }
+                    },
+                    {
+                        "name": "etag",
+                        "request": { "method": "HEAD", "urlPathPattern": "/oss/v2/.*" },
+                        "response": {
+                            "headers": { "ETag": "{{randomValue length=8}}" },
+                            "body": "{{randomValue length=8}}",
+                            "transformers": ["response-template"]
+                        }
+                    }
+                ]
+            }"#,
+        )
+        .unwrap();
+        fs::write(
+            wiremock.path().join(FILES_DIR).join("object.json"),
+            r#"{"bucketKey":"{{request.path.[3]}}","objectKey":"{{request.pathSegments.[5]}}","by":"{{request.headers.X-User-Id}}"}"#,
+        )
+        .unwrap();
+
+        let report = import(wiremock.path(), out.path()).unwrap();
+        assert_eq!(report.imported, 2);
+        assert_eq!(report.skipped.len(), 1);
+        assert!(report.skipped[0].contains("outage"));
+        assert_eq!(report.warnings.len(), 2);
+        assert!(report.warnings[0].contains("header ETag"));
+        assert!(report.warnings[1].contains("{{randomValue length=8}}"));
+
+        let store = StubStore::load(out.path()).unwrap();
+        let path = "/oss/v2/buckets/b1/objects/model.rvt";
+        let mut headers = HeaderMap::new();
+        headers.insert("content-type", HeaderValue::from_static("application/json"));
+        headers.insert("x-user-id", HeaderValue::from_static("u1"));
+        let body = br#"{ "name": "model" }"#;
+        assert!(
+            store
+                .find("PUT", path, None, &headers, None, None)
+                .is_none()
+        );
+        assert!(
+            store
+                .find("PUT", path, None, &HeaderMap::new(), Some(body), None)
+                .is_none()
+        );
+        let stub = store
+            .find("PUT", path, None, &headers, Some(body), None)
+            .unwrap();
+        let response = store
+            .respond(&stub, TemplateContext::new("PUT", path, None, &headers))
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body: Value = serde_json::from_slice(
+            &axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(body["bucketKey"], "b1");
+        assert_eq!(body["objectKey"], "model.rvt");
+        assert_eq!(body["by"], "u1");
+    }
+}
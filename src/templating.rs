@@ -0,0 +1,155 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+//! Rendering of templated response bodies against the incoming request.
+
+use crate::middleware::determinism::RequestFingerprint;
+use axum::http::HeaderMap;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Request data exposed to templates as `path`, `segments`, `query`,
+/// `headers`, `method` and `url`, plus `now` (RFC 3339) and `uuid` (one fresh
+/// UUID per request)
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TemplateContext {
+    /// Path parameters by name
+    pub path: BTreeMap<String, String>,
+    /// Path segments, e.g. `["oss", "v2", "buckets"]`
+    pub segments: Vec<String>,
+    /// Query parameters (first value wins)
+    pub query: BTreeMap<String, String>,
+    /// Request headers, lowercased names
+    pub headers: BTreeMap<String, String>,
+    pub method: String,
+    pub url: String,
+    pub now: String,
+    pub uuid: String,
+    /// Set in deterministic mode; seeds data synthesized for the response
+    #[serde(skip)]
+    pub fingerprint: Option<RequestFingerprint>,
+}
+
+impl TemplateContext {
+    pub fn new(method: &str, path: &str, query: Option<&str>, headers: &HeaderMap) -> Self {
+        let mut query_params = BTreeMap::new();
+        for (key, value) in form_urlencoded::parse(query.unwrap_or_default().as_bytes()) {
+            query_params
+                .entry(key.into_owned())
+                .or_insert_with(|| value.into_owned());
+        }
+        let headers = headers
+            .iter()
+            .filter_map(|(name, value)| {
+                Some((name.as_str().to_string(), value.to_str().ok()?.to_string()))
+            })
+            .collect();
+        let url = match query {
+            Some(q) if !q.is_empty() => format!("{}?{}", path, q),
+            _ => path.to_string(),
+        };
+
+        Self {
+            path: BTreeMap::new(),
+            segments: path
+                .split('/')
+                .filter(|s| !s.is_empty())
+                .map(str::to_string)
+                .collect(),
+            query: query_params,
+            headers,
+            method: method.to_string(),
+            url,
+            now: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+            uuid: uuid::Uuid::new_v4().to_string(),
+            fingerprint: None,
+        }
+    }
+
+    /// Derive `now` and `uuid` from the request fingerprint in deterministic mode
+    pub fn with_fingerprint(mut self, fingerprint: Option<&RequestFingerprint>) -> Self {
+        if let Some(fingerprint) = fingerprint {
+            self.now = fingerprint
+                .now()
+                .to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
+            self.uuid = fingerprint.uuid().to_string();
+            self.fingerprint = Some(*fingerprint);
+        }
+        self
+    }
+
+    pub fn with_path_params(mut self, params: impl IntoIterator<Item = (String, String)>) -> Self {
+        self.path.extend(params);
+        self
+    }
+}
+
+/// Render a template string with the request context
+pub fn render(template: &str, context: &TemplateContext) -> Result<String, minijinja::Error> {
+    minijinja::Environment::new().render_str(template, context)
+}
+
+/// Render every templated string inside a JSON value (e.g. an OpenAPI
+/// example), leaving the structure intact. Strings that fail to render are
+/// kept as they are.
+pub fn render_value(value: &mut serde_json::Value, context: &TemplateContext) {
+    match value {
+        serde_json::Value::String(s) if s.contains("{{") || s.contains("{%") => {
+            match render(s, context) {
+                Ok(rendered) => *s = rendered,
+                Err(e) => tracing::warn!("Failed to render example template {:?}: {}", s, e),
+            }
+        }
+        serde_json::Value::Array(items) => {
+            for item in items {
+                render_value(item, context);
+            }
+        }
+        serde_json::Value::Object(map) => {
+            for item in map.values_mut() {
+                render_value(item, context);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_request_values() {
+        let context = TemplateContext::new(
+            "GET",
+            "/oss/v2/buckets/b1",
+            Some("limit=5"),
+            &HeaderMap::new(),
+        )
+        .with_path_params([("bucketKey".to_string(), "b1".to_string())]);
+        let rendered = render(
+            r#"{"bucketKey":"{{ path.bucketKey }}","limit":{{ query.limit }}}"#,
+            &context,
+        )
+        .unwrap();
+        assert_eq!(rendered, r#"{"bucketKey":"b1","limit":5}"#);
+    }
+
+    #[test]
+    fn test_render_json_example() {
+        let context = TemplateContext::new("POST", "/oss/v2/buckets", None, &HeaderMap::new())
+            .with_path_params([("bucketKey".to_string(), "b1".to_string())]);
+        let mut example = serde_json::json!({
+            "bucketKey": "{{ path.bucketKey }}",
+            "objects": [{ "objectId": "urn:{{ uuid }}" }],
+            "size": 5
+        });
+        render_value(&mut example, &context);
+        assert_eq!(example["bucketKey"], "b1");
+        assert_eq!(
+            example["objects"][0]["objectId"],
+            format!("urn:{}", context.uuid)
+        );
+        assert_eq!(example["size"], 5);
+    }
+}
@@ -25,8 +25,12 @@
 
 use crate::config::{MockMode, MockServerConfig};
 use crate::error::Result;
+use crate::handlers::{CustomHandlerRegistry, StubBuilder};
 use crate::server::MockServer;
+use crate::state::StateManager;
+use crate::state::journal::{JournalEntry, RequestMatcher, Times};
 use std::path::PathBuf;
+use std::sync::Arc;
 use tokio::net::TcpListener;
 
 /// A test server that runs in the background on a random port.
@@ -35,6 +39,9 @@ use tokio::net::TcpListener;
 pub struct TestServer {
     /// The base URL of the running server (e.g., "http://127.0.0.1:12345")
     pub url: String,
+    /// State of the running server, `None` when every service is stateless
+    pub state: Option<StateManager>,
+    handlers: Arc<CustomHandlerRegistry>,
     /// Handle to the background task running the server
     _task: tokio::task::JoinHandle<()>,
 }
@@ -46,6 +53,8 @@ impl TestServer {
     pub async fn start(config: MockServerConfig) -> Result<Self> {
         let server = MockServer::new(config).await?;
         let app = server.router();
+        let state = server.state().cloned();
+        let handlers = server.handlers().clone();
 
         let listener = TcpListener::bind("127.0.0.1:0").await?;
         let addr = listener.local_addr()?;
@@ -56,6 +65,8 @@ impl TestServer {
 
         Ok(Self {
             url: format!("http://{}", addr),
+            state,
+            handlers,
             _task: task,
         })
     }
@@ -72,10 +83,9 @@ impl TestServer {
         let config = MockServerConfig {
             mode: MockMode::Stateful,
             openapi_dir,
-            state_file: None,
-            verbose: false,
             host: "127.0.0.1".to_string(),
             port: 0,
+            ..MockServerConfig::default()
         };
         Self::start(config).await
     }
@@ -95,6 +105,26 @@ impl TestServer {
     pub fn uri(&self) -> &str {
         &self.url
     }
+
+    /// Answer requests of `method` to `path` from code, ahead of all
+    /// routes (see [`MockServer::stub`]).
+    pub fn stub(&self, method: axum::http::Method, path: &str) -> StubBuilder {
+        self.handlers.stub(method, path)
+    }
+
+    /// Requests the server received, oldest first.
+    pub fn received_requests(&self) -> Vec<JournalEntry> {
+        self.state
+            .as_ref()
+            .map(|state| state.journal.entries())
+            .unwrap_or_default()
+    }
+
+    /// Check that `matcher` selects a number of received requests `times`
+    /// allows, e.g. `1`, `2..` or `..=3`.
+    pub fn verify(&self, matcher: &RequestMatcher, times: impl Into<Times>) -> Result<()> {
+        crate::server::verify_requests(self.state.as_ref(), matcher, times.into())
+    }
 }
 
 impl Drop for TestServer {
@@ -120,4 +150,35 @@ mod tests {
         let server = TestServer::start_default().await.unwrap();
         assert_eq!(server.uri(), &server.url);
     }
+
+    #[tokio::test]
+    async fn test_received_requests_can_be_verified() {
+        let server = TestServer::start_with_openapi_dir("/nonexistent".into())
+            .await
+            .unwrap();
+        reqwest::Client::new()
+            .post(format!("{}/authentication/v2/token", server.url))
+            .form(&[("client_id", "ci"), ("grant_type", "client_credentials")])
+            .send()
+            .await
+            .unwrap();
+
+        let requests = server.received_requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(
+            requests[0].body.as_deref(),
+            Some("client_id=ci&grant_type=client_credentials")
+        );
+        let token_requests = RequestMatcher::new()
+            .method("POST")
+            .path("/authentication/**")
+            .body_contains("client_id=ci");
+        server.verify(&token_requests, 1).unwrap();
+        server.verify(&token_requests.status(200), 1..).unwrap();
+        assert!(
+            server
+                .verify(&RequestMatcher::new().method("GET"), 1)
+                .is_err()
+        );
+    }
 }
@@ -0,0 +1,130 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+//! Access tokens for ad-hoc requests (`raps-mock token`): minted by a running
+//! mock through its token endpoint, or generated locally in the same format.
+//!
+//! A locally generated token is not known to any running mock, so it only
+//! passes authentication where the auth service is stateless.
+
+use crate::error::{MockError, Result};
+use crate::state::auth::AuthState;
+use axum::body::{Body, to_bytes};
+use axum::http::{Request, Uri, header};
+use hyper_util::rt::TokioIo;
+use serde_json::{Value, json};
+use tokio::net::TcpStream;
+
+/// Path of the token endpoint
+pub const TOKEN_PATH: &str = "/authentication/v2/token";
+
+/// Lifetime of generated tokens, as the mock issues them
+const EXPIRES_IN: u64 = 3600;
+
+/// Upper bound on the token response body
+const MAX_BODY_BYTES: usize = 1024 * 1024;
+
+/// Ask the mock at `base_url` (e.g. `http://localhost:3000`) for a
+/// client-credentials token, returning its JSON token response
+pub async fn fetch(base_url: &str, client_id: &str, scope: Option<&str>) -> Result<Value> {
+    let url = format!("{}{}", base_url.trim_end_matches('/'), TOKEN_PATH);
+    let uri: Uri = url
+        .parse()
+        .map_err(|e| MockError::Http(format!("invalid URL {}: {}", url, e)))?;
+    if uri.scheme_str() != Some("http") {
+        return Err(MockError::Http(format!(
+            "only http:// URLs are supported, got {}",
+            base_url
+        )));
+    }
+    let Some(authority) = uri.authority().cloned() else {
+        return Err(MockError::Http(format!("no host in URL {}", base_url)));
+    };
+
+    let stream = TcpStream::connect((authority.host(), authority.port_u16().unwrap_or(80)))
+        .await
+        .map_err(|e| MockError::Http(format!("cannot connect to {}: {}", authority, e)))?;
+    let (mut sender, connection) = hyper::client::conn::http1::handshake(TokioIo::new(stream))
+        .await
+        .map_err(|e| MockError::Http(e.to_string()))?;
+    tokio::spawn(connection);
+
+    let mut body = json!({
+        "client_id": client_id,
+        "grant_type": "client_credentials"
+    });
+    if let Some(scope) = scope {
+        body["scope"] = json!(scope);
+    }
+    let request = Request::post(uri.path())
+        .header(header::HOST, authority.as_str())
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(body.to_string()))
+        .map_err(|e| MockError::Http(e.to_string()))?;
+    let response = sender
+        .send_request(request)
+        .await
+        .map_err(|e| MockError::Http(format!("POST {} failed: {}", url, e)))?;
+
+    let status = response.status();
+    let bytes = to_bytes(Body::new(response.into_body()), MAX_BODY_BYTES)
+        .await
+        .map_err(|e| MockError::Http(format!("reading the response of {}: {}", url, e)))?;
+    if !status.is_success() {
+        return Err(MockError::Http(format!(
+            "POST {} answered {}: {}",
+            url,
+            status,
+            String::from_utf8_lossy(&bytes)
+        )));
+    }
+    serde_json::from_slice(&bytes)
+        .map_err(|e| MockError::Http(format!("unexpected response from {}: {}", url, e)))
+}
+
+/// Generate a token response like the mock's without asking one
+pub fn generate(client_id: &str, scope: Option<&str>) -> Value {
+    let token = AuthState::new().generate_token(client_id, EXPIRES_IN, scope.map(str::to_string));
+    json!({
+        "access_token": token.access_token,
+        "token_type": token.token_type,
+        "expires_in": token.expires_in
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestServer;
+    use crate::{MockMode, MockServerConfig};
+
+    #[tokio::test]
+    async fn test_fetched_tokens_authenticate_against_the_mock() {
+        let server = TestServer::start(MockServerConfig {
+            mode: MockMode::Stateful,
+            openapi_dir: "/nonexistent".into(),
+            ..MockServerConfig::default()
+        })
+        .await
+        .unwrap();
+
+        let token = fetch(&server.url, "cli", Some("data:read")).await.unwrap();
+        let access_token = token["access_token"].as_str().unwrap();
+        assert!(access_token.starts_with("mock_token_cli_"));
+        let auth = &server.state.as_ref().unwrap().auth;
+        assert!(auth.validate_token(access_token));
+        assert_eq!(
+            auth.lookup_token(access_token).unwrap().scope.as_deref(),
+            Some("data:read")
+        );
+
+        // Generated offline in the same shape, but unknown to the mock
+        let offline = generate("cli", None);
+        assert_eq!(offline["token_type"], token["token_type"]);
+        let offline_token = offline["access_token"].as_str().unwrap();
+        assert!(offline_token.starts_with("mock_token_cli_"));
+        assert!(!auth.validate_token(offline_token));
+
+        assert!(fetch("https://localhost:1", "cli", None).await.is_err());
+    }
+}
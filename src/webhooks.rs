@@ -0,0 +1,388 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+//! Webhook notifications in the shape APS POSTs them to callback URLs.
+//!
+//! A notification is an envelope of `version`, `resourceUrn`, the `hook` that
+//! matched and a `payload` carrying exactly the documented fields of the
+//! hook's system and event. The shapes are the `WebhookEvent` schemas of
+//! `specs/webhooks/webhooks.yaml`; the tests check every event in [`EVENTS`]
+//! against them.
+
+pub mod delivery;
+
+use crate::state::webhooks::WebhookSubscription;
+use chrono::SecondsFormat;
+use serde_json::{Map, Value, json};
+
+/// `version` of the notification envelope
+pub const EVENT_VERSION: &str = "1.0";
+
+/// Events with a documented payload, by system
+pub const EVENTS: [(&str, &[&str]); 3] = [
+    (
+        "data",
+        &[
+            "dm.version.added",
+            "dm.version.modified",
+            "dm.version.deleted",
+            "dm.version.moved",
+            "dm.version.copied",
+            "dm.lineage.reserved",
+            "dm.lineage.unreserved",
+            "dm.lineage.updated",
+            "dm.folder.added",
+            "dm.folder.modified",
+            "dm.folder.deleted",
+            "dm.folder.moved",
+            "dm.folder.copied",
+        ],
+    ),
+    ("derivative", &["extraction.updated", "extraction.finished"]),
+    (
+        "autodesk.construction.issues",
+        &[
+            "issue.created-1.0",
+            "issue.updated-1.0",
+            "issue.deleted-1.0",
+            "issue.restored-1.0",
+        ],
+    ),
+];
+
+/// Payload shape of an event
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PayloadKind {
+    /// `data` events on file versions and lineages
+    DataVersion,
+    /// `data` events on folders
+    DataFolder,
+    /// Model Derivative extraction progress
+    Derivative,
+    /// ACC issue changes
+    Issue,
+    /// Events without a documented payload, passed through as given
+    Other,
+}
+
+impl PayloadKind {
+    pub fn of(system: &str, event: &str) -> Self {
+        match system {
+            "data" if event.starts_with("dm.folder.") => PayloadKind::DataFolder,
+            "data" => PayloadKind::DataVersion,
+            "derivative" => PayloadKind::Derivative,
+            "autodesk.construction.issues" => PayloadKind::Issue,
+            _ => PayloadKind::Other,
+        }
+    }
+
+    /// Name of the spec schema describing the payload
+    pub fn schema_name(&self) -> Option<&'static str> {
+        match self {
+            PayloadKind::DataVersion => Some("DataVersionEventPayload"),
+            PayloadKind::DataFolder => Some("DataFolderEventPayload"),
+            PayloadKind::Derivative => Some("DerivativeEventPayload"),
+            PayloadKind::Issue => Some("IssueEventPayload"),
+            PayloadKind::Other => None,
+        }
+    }
+}
+
+/// A hook as the Webhooks API and notifications render it
+pub fn hook_json(hook: &WebhookSubscription) -> Value {
+    let mut value = json!({
+        "hookId": hook.hook_id,
+        "tenant": hook.tenant,
+        "callbackUrl": hook.callback_url,
+        "createdBy": CREATED_BY,
+        "event": hook.event,
+        "createdDate": millis_to_rfc3339(hook.created_at),
+        "system": hook.system,
+        "creatorType": "Application",
+        "status": hook.status,
+        "scope": hook.scope,
+        "urn": format!("urn:adsk.webhooks:events.hook:{}", hook.hook_id),
+        "__self__": format!(
+            "/systems/{}/events/{}/hooks/{}",
+            hook.system, hook.event, hook.hook_id
+        )
+    });
+    if let Some(attribute) = &hook.hook_attribute {
+        value["hookAttribute"] = attribute.clone();
+    }
+    if let Some(filter) = &hook.filter {
+        value["filter"] = Value::from(filter.clone());
+    }
+    value
+}
+
+/// The notification `hook` receives for an event on `resource_urn`
+///
+/// `details` override the payload's defaults. Keys the event's payload does
+/// not document are dropped, except for [`PayloadKind::Other`] events, whose
+/// payload is `details` as given.
+pub fn notification(
+    hook: &WebhookSubscription,
+    resource_urn: &str,
+    details: &Map<String, Value>,
+) -> Value {
+    json!({
+        "version": EVENT_VERSION,
+        "resourceUrn": resource_urn,
+        "hook": hook_json(hook),
+        "payload": payload(hook, resource_urn, details)
+    })
+}
+
+/// The `payload` of a notification, see [`notification`]
+pub fn payload(
+    hook: &WebhookSubscription,
+    resource_urn: &str,
+    details: &Map<String, Value>,
+) -> Value {
+    let kind = PayloadKind::of(&hook.system, &hook.event);
+    let mut payload = defaults(kind, hook, resource_urn);
+    for (key, value) in details {
+        if kind == PayloadKind::Other || payload.contains_key(key) {
+            payload.insert(key.clone(), value.clone());
+        }
+    }
+    Value::Object(payload)
+}
+
+/// Who the mock's hooks and events are created by
+const CREATED_BY: &str = "mock-user";
+
+/// Every documented field of a payload, with plausible values
+fn defaults(
+    kind: PayloadKind,
+    hook: &WebhookSubscription,
+    resource_urn: &str,
+) -> Map<String, Value> {
+    let now = chrono::Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true);
+    let project_id = hook
+        .scope
+        .project
+        .clone()
+        .unwrap_or_else(|| "b.default-project".to_string());
+    let parent_folder = hook
+        .scope
+        .folder
+        .clone()
+        .unwrap_or_else(|| "urn:adsk.wipprod:fs.folder:co.mock-folder".to_string());
+
+    let value = match kind {
+        PayloadKind::DataVersion => json!({
+            "source": resource_urn,
+            "name": "Model.rvt",
+            "version": "1",
+            "ext": "rvt",
+            "mimeType": "application/vnd.autodesk.revit",
+            "sizeInBytes": 0,
+            "createdTime": now,
+            "modifiedTime": now,
+            "creator": CREATED_BY,
+            "lineageUrn": lineage_urn(resource_urn),
+            "hidden": hook.event == "dm.version.deleted",
+            "indexable": true,
+            "projectId": project_id,
+            "tenant": hook.tenant,
+            "custom-metadata": {},
+            "state": "CONTENT_AVAILABLE",
+            "parentFolderUrn": parent_folder,
+            "user_info": { "id": CREATED_BY }
+        }),
+        PayloadKind::DataFolder => json!({
+            "source": resource_urn,
+            "name": "Project Files",
+            "createdTime": now,
+            "modifiedTime": now,
+            "creator": CREATED_BY,
+            "hidden": hook.event == "dm.folder.deleted",
+            "indexable": true,
+            "projectId": project_id,
+            "tenant": hook.tenant,
+            "custom-metadata": {},
+            "parentFolderUrn": parent_folder,
+            "user_info": { "id": CREATED_BY }
+        }),
+        PayloadKind::Derivative => {
+            let finished = hook.event == "extraction.finished";
+            json!({
+                "URN": resource_urn,
+                "Status": if finished { "success" } else { "inprogress" },
+                "Progress": if finished { "complete" } else { "0% complete" },
+                "ActivityId": uuid::Uuid::new_v4().to_string(),
+                "WorkflowId": hook.scope.workflow.clone().unwrap_or_default(),
+                "WorkflowAttribute": hook.hook_attribute.clone().unwrap_or_else(|| json!({}))
+            })
+        }
+        PayloadKind::Issue => json!({
+            "id": uuid::Uuid::new_v4().to_string(),
+            "containerId": uuid::Uuid::new_v4().to_string(),
+            "projectId": project_id.trim_start_matches("b."),
+            "title": "Mock issue",
+            "status": if hook.event == "issue.deleted-1.0" { "void" } else { "open" },
+            "createdBy": CREATED_BY,
+            "createdAt": now,
+            "updatedAt": now
+        }),
+        PayloadKind::Other => json!({}),
+    };
+    match value {
+        Value::Object(map) => map,
+        _ => Map::new(),
+    }
+}
+
+/// Lineage of a version URN (`...:fs.file:vf.abc?version=2` →
+/// `...:dm.lineage:abc`)
+fn lineage_urn(version_urn: &str) -> String {
+    let id = version_urn
+        .split('?')
+        .next()
+        .unwrap_or_default()
+        .rsplit(':')
+        .next()
+        .unwrap_or_default();
+    format!(
+        "urn:adsk.wipprod:dm.lineage:{}",
+        id.strip_prefix("vf.").unwrap_or(id)
+    )
+}
+
+fn millis_to_rfc3339(millis: i64) -> String {
+    chrono::DateTime::from_timestamp_millis(millis)
+        .unwrap_or_default()
+        .to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handlers::validation::SchemaValidator;
+    use crate::openapi::types::{OpenApiSpec, Schema};
+    use crate::state::webhooks::WebhookScope;
+
+    /// Keys of `value` the schema does not declare, as paths
+    fn undeclared(spec: &OpenApiSpec, schema: &Schema, value: &Value, path: &str) -> Vec<String> {
+        let schemas = spec.components.as_ref().unwrap().schemas.as_ref().unwrap();
+        let schema = match schema {
+            Schema::Ref { ref_path } => &schemas[ref_path.rsplit('/').next().unwrap()],
+            schema => schema,
+        };
+        let (
+            Schema::Object {
+                properties: Some(properties),
+                ..
+            },
+            Value::Object(map),
+        ) = (schema, value)
+        else {
+            return Vec::new();
+        };
+        map.iter()
+            .flat_map(|(key, value)| {
+                let path = format!("{}.{}", path, key);
+                match properties.get(key) {
+                    Some(property) => undeclared(spec, property, value, &path),
+                    None => vec![path],
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_notifications_match_the_documented_event_schemas() {
+        let spec: OpenApiSpec =
+            serde_yaml::from_str(include_str!("../specs/webhooks/webhooks.yaml")).unwrap();
+        let components = spec.components.as_ref().unwrap();
+        let schemas = components.schemas.as_ref().unwrap();
+        let validator = SchemaValidator::new(Some(components));
+
+        for (system, events) in EVENTS {
+            for event in events {
+                let hook = WebhookSubscription {
+                    hook_id: uuid::Uuid::new_v4().to_string(),
+                    system: system.to_string(),
+                    event: event.to_string(),
+                    tenant: "tenant".to_string(),
+                    callback_url: "https://example.com/callback".to_string(),
+                    scope: WebhookScope {
+                        folder: Some("urn:adsk.wipprod:fs.folder:co.abc".to_string()),
+                        ..WebhookScope::default()
+                    },
+                    hook_attribute: Some(json!({ "build": 7 })),
+                    status: "active".to_string(),
+                    created_at: 0,
+                    consecutive_failures: 0,
+                    client_id: None,
+                    filter: None,
+                };
+                let body = notification(
+                    &hook,
+                    "urn:adsk.wipprod:fs.file:vf.abc?version=2",
+                    &Map::new(),
+                );
+
+                let envelope = &schemas["WebhookEvent"];
+                assert_eq!(validator.validate(envelope, &body, ""), [], "{}", event);
+                assert_eq!(undeclared(&spec, envelope, &body, ""), Vec::<String>::new());
+
+                let kind = PayloadKind::of(system, event);
+                let payload_schema = &schemas[kind.schema_name().unwrap()];
+                let payload = &body["payload"];
+                assert_eq!(
+                    validator.validate(payload_schema, payload, ""),
+                    [],
+                    "{}",
+                    event
+                );
+                assert_eq!(
+                    undeclared(&spec, payload_schema, payload, ""),
+                    Vec::<String>::new(),
+                    "{}",
+                    event
+                );
+                // Every documented field is present
+                let Schema::Object {
+                    properties: Some(properties),
+                    ..
+                } = payload_schema
+                else {
+                    panic!("{} has no properties", event);
+                };
+                assert_eq!(
+                    payload.as_object().unwrap().len(),
+                    properties.len(),
+                    "{}",
+                    event
+                );
+            }
+        }
+
+        let hook = WebhookSubscription {
+            hook_id: "h".to_string(),
+            system: "data".to_string(),
+            event: "dm.version.added".to_string(),
+            tenant: "t".to_string(),
+            callback_url: "https://example.com".to_string(),
+            scope: WebhookScope::default(),
+            hook_attribute: None,
+            status: "active".to_string(),
+            created_at: 0,
+            consecutive_failures: 0,
+            client_id: None,
+            filter: None,
+        };
+        let details = Map::from_iter([
+            ("name".to_string(), json!("Tower.rvt")),
+            ("undocumented".to_string(), json!(true)),
+        ]);
+        let payload = payload(&hook, "urn:adsk.wipprod:fs.file:vf.xyz?version=1", &details);
+        assert_eq!(payload["name"], "Tower.rvt");
+        assert_eq!(payload["lineageUrn"], "urn:adsk.wipprod:dm.lineage:xyz");
+        assert!(payload.get("undocumented").is_none());
+        assert!(hook_json(&hook).get("hookAttribute").is_none());
+    }
+}
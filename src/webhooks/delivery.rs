@@ -0,0 +1,528 @@
+// SPDX-License-Identifier: Apache-2.0
+// Copyright 2024-2025 Dmytro Yemelianov
+
+//! Delivery of webhook notifications to the callback URLs of active hooks.
+//!
+//! Events come from two places. The state changes of a mutating request
+//! (compared by the audit middleware while any hook is active) announce DM
+//! versions and folders and ACC issues being created, modified or deleted.
+//! Job progress notifications, which the job watcher collects, announce
+//! translation progress and completion as `derivative` events. OSS has no
+//! events of its own: an upload is announced by `dm.version.added` once a
+//! version is created from it.
+//!
+//! Each delivery is a POST of the notification [`super::notification`]
+//! builds, made in the background; its outcome goes to
+//! [`WebhooksState::record_delivery`], after the retries a refused delivery
+//! gets. When the app that created the hook
+//! has a secret, the body is signed with it in a [`SIGNATURE_HEADER`].
+
+use super::notification;
+use crate::state::audit::{AuditAction, Change};
+use crate::state::issues::IssueInfo;
+use crate::state::jobs::{JobEvent, JobKind, JobNotification};
+use crate::state::projects::{FolderInfo, ItemInfo};
+use crate::state::webhooks::{FailedDelivery, WebhookSubscription, WebhooksState};
+use hmac::{Hmac, Mac};
+use reqwest::header::CONTENT_TYPE;
+use serde_json::{Map, Value, json};
+use sha1::Sha1;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+/// How long a callback may take to answer before the delivery fails
+pub const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Header carrying the HMAC-SHA1 of a notification body, keyed with the
+/// secret of the app that created the hook
+pub const SIGNATURE_HEADER: &str = "x-adsk-signature";
+
+/// Value of the [`SIGNATURE_HEADER`] of `body` signed with `secret`:
+/// `sha1hash=` and the hex-encoded HMAC-SHA1
+pub fn signature(secret: &str, body: &[u8]) -> String {
+    // HMAC accepts keys of any length
+    let mut mac = Hmac::<Sha1>::new_from_slice(secret.as_bytes()).expect("HMAC key");
+    mac.update(body);
+    let digest = mac.finalize().into_bytes();
+    let hex: String = digest.iter().map(|b| format!("{:02x}", b)).collect();
+    format!("sha1hash={}", hex)
+}
+
+/// Something that happened that hooks may be notified of
+#[derive(Debug, Clone, PartialEq)]
+pub struct WebhookEvent {
+    /// Event source, e.g. `data`
+    pub system: String,
+    /// Event name, e.g. `dm.version.added`
+    pub event: String,
+    pub resource_urn: String,
+    /// Payload fields known from the record the event is about
+    pub details: Map<String, Value>,
+    /// Folder the resource is in, matched against hooks' folder scope
+    pub folder: Option<String>,
+    /// Project the resource is in, matched against hooks' project scope
+    pub project: Option<String>,
+}
+
+impl WebhookEvent {
+    /// Events the state changes of one request amount to
+    pub fn from_changes(changes: &[Change]) -> Vec<WebhookEvent> {
+        changes.iter().filter_map(Self::from_change).collect()
+    }
+
+    fn from_change(change: &Change) -> Option<WebhookEvent> {
+        let record = change.new.clone().or_else(|| change.old.clone())?;
+        let verb = |added: &'static str| match change.action {
+            AuditAction::Created => added,
+            AuditAction::Updated => "modified",
+            AuditAction::Deleted => "deleted",
+        };
+        let mut details = Map::new();
+        match change.kind.as_str() {
+            "items" => {
+                let item: ItemInfo = serde_json::from_value(record).ok()?;
+                if let Some((_, ext)) = item.name.rsplit_once('.') {
+                    details.insert("ext".into(), Value::from(ext));
+                }
+                if let Some(creator) = &item.created_by {
+                    details.insert("creator".into(), Value::from(creator.clone()));
+                    details.insert("user_info".into(), json!({ "id": creator }));
+                }
+                details.insert("name".into(), Value::from(item.name.clone()));
+                details.insert(
+                    "parentFolderUrn".into(),
+                    Value::from(item.folder_id.clone()),
+                );
+                details.insert("projectId".into(), Value::from(item.project_id.clone()));
+                Some(WebhookEvent {
+                    system: "data".into(),
+                    event: format!("dm.version.{}", verb("added")),
+                    resource_urn: item.version_id(),
+                    details,
+                    folder: Some(item.folder_id),
+                    project: Some(item.project_id),
+                })
+            }
+            "folders" => {
+                let folder: FolderInfo = serde_json::from_value(record).ok()?;
+                details.insert("name".into(), Value::from(folder.name));
+                details.insert("projectId".into(), Value::from(folder.project_id.clone()));
+                if let Some(parent) = &folder.parent_id {
+                    details.insert("parentFolderUrn".into(), Value::from(parent.clone()));
+                }
+                Some(WebhookEvent {
+                    system: "data".into(),
+                    event: format!("dm.folder.{}", verb("added")),
+                    resource_urn: folder.id,
+                    details,
+                    folder: folder.parent_id,
+                    project: Some(folder.project_id),
+                })
+            }
+            "issues" => {
+                let issue: IssueInfo = serde_json::from_value(record).ok()?;
+                details.insert("id".into(), Value::from(issue.id.clone()));
+                details.insert("projectId".into(), Value::from(issue.project_id.clone()));
+                details.insert("title".into(), Value::from(issue.title));
+                details.insert("status".into(), Value::from(issue.status));
+                if let Some(creator) = issue.created_by {
+                    details.insert("createdBy".into(), Value::from(creator));
+                }
+                let event = match change.action {
+                    AuditAction::Created => "issue.created-1.0",
+                    AuditAction::Updated => "issue.updated-1.0",
+                    AuditAction::Deleted => "issue.deleted-1.0",
+                };
+                Some(WebhookEvent {
+                    system: "autodesk.construction.issues".into(),
+                    event: event.into(),
+                    resource_urn: issue.id,
+                    details,
+                    folder: None,
+                    project: Some(issue.project_id),
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// Event a job notification amounts to: translation progress and
+    /// completion
+    pub fn from_job(notification: &JobNotification) -> Option<WebhookEvent> {
+        if notification.kind != JobKind::Translation {
+            return None;
+        }
+        let finished = notification.event == JobEvent::Finished;
+        let mut details = Map::new();
+        details.insert("URN".into(), Value::from(notification.key.clone()));
+        details.insert(
+            "Status".into(),
+            serde_json::to_value(notification.phase).unwrap_or_default(),
+        );
+        details.insert(
+            "Progress".into(),
+            Value::from(if finished {
+                "complete".to_string()
+            } else {
+                format!("{}% complete", notification.progress)
+            }),
+        );
+        Some(WebhookEvent {
+            system: "derivative".into(),
+            event: if finished {
+                "extraction.finished"
+            } else {
+                "extraction.updated"
+            }
+            .into(),
+            resource_urn: notification.key.clone(),
+            details,
+            folder: None,
+            project: None,
+        })
+    }
+
+    /// Whether `hook`'s scope covers the event; a scope the event says
+    /// nothing about does not restrict it
+    fn in_scope(&self, hook: &WebhookSubscription) -> bool {
+        let project_id = |id: &str| id.trim_start_matches("b.").to_string();
+        let folder_ok = match (&hook.scope.folder, &self.folder) {
+            (Some(scope), Some(folder)) => scope == folder,
+            _ => true,
+        };
+        let project_ok = match (&hook.scope.project, &self.project) {
+            (Some(scope), Some(project)) => project_id(scope) == project_id(project),
+            _ => true,
+        };
+        folder_ok && project_ok
+    }
+}
+
+fn client() -> &'static reqwest::Client {
+    static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+    CLIENT.get_or_init(|| {
+        reqwest::Client::builder()
+            .timeout(DELIVERY_TIMEOUT)
+            .build()
+            .unwrap_or_default()
+    })
+}
+
+/// The active hooks subscribed to `event` whose scope covers it
+pub fn recipients(hooks: &WebhooksState, event: &WebhookEvent) -> Vec<WebhookSubscription> {
+    hooks
+        .subscribers(&event.system, &event.event)
+        .into_iter()
+        .filter(|hook| event.in_scope(hook))
+        .collect()
+}
+
+/// POST `event` to every active hook subscribed to it, in the background.
+/// A callback that refuses the notification or does not answer gets it
+/// again as the hooks' [`RetryPolicy`](crate::state::webhooks::RetryPolicy)
+/// says; a notification no attempt delivered ends up in the dead-letter
+/// list. The tasks resolve to whether the callback accepted it.
+pub fn deliver(hooks: &Arc<WebhooksState>, event: &WebhookEvent) -> Vec<JoinHandle<bool>> {
+    let policy = hooks.retry_policy();
+    recipients(hooks, event)
+        .into_iter()
+        .map(|hook| {
+            let notification = notification(&hook, &event.resource_urn, &event.details);
+            let body = notification.to_string();
+            let signature = hooks
+                .secret_for(&hook)
+                .map(|secret| signature(&secret, body.as_bytes()));
+            let hooks = hooks.clone();
+            tokio::spawn(async move {
+                let attempts = policy.attempts.max(1);
+                let mut error = String::new();
+                for attempt in 1..=attempts {
+                    if attempt > 1 {
+                        tokio::time::sleep(policy.delay(attempt - 1)).await;
+                    }
+                    match post(&hook.callback_url, &body, signature.as_deref()).await {
+                        Ok(()) => {
+                            hooks.record_delivery(&hook.hook_id, true);
+                            return true;
+                        }
+                        Err(e) => {
+                            tracing::warn!(
+                                "Hook {} callback {} failed (attempt {} of {}): {}",
+                                hook.hook_id,
+                                hook.callback_url,
+                                attempt,
+                                attempts,
+                                e
+                            );
+                            error = e;
+                        }
+                    }
+                }
+                hooks.record_delivery(&hook.hook_id, false);
+                hooks.record_dead_letter(FailedDelivery {
+                    seq: 0,
+                    hook_id: hook.hook_id,
+                    system: hook.system,
+                    event: hook.event,
+                    callback_url: hook.callback_url,
+                    attempts,
+                    error,
+                    failed_at: 0,
+                    notification,
+                });
+                false
+            })
+        })
+        .collect()
+}
+
+/// Make one delivery attempt, failing with the status the callback answered
+/// or the transport error
+async fn post(callback_url: &str, body: &str, signature: Option<&str>) -> Result<(), String> {
+    let mut request = client()
+        .post(callback_url)
+        .header(CONTENT_TYPE, "application/json")
+        .body(body.to_string());
+    if let Some(signature) = signature {
+        request = request.header(SIGNATURE_HEADER, signature);
+    }
+    match request.send().await {
+        Ok(response) if response.status().is_success() => Ok(()),
+        Ok(response) => Err(format!("Callback answered {}", response.status())),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::StateManager;
+    use crate::state::jobs::JobPhase;
+    use crate::state::webhooks::{RetryPolicy, WebhookScope};
+    use tokio::sync::mpsc;
+
+    #[tokio::test]
+    async fn test_events_are_posted_to_subscribed_callbacks() {
+        let (sender, mut received) = mpsc::unbounded_channel::<Value>();
+        let receiver = axum::Router::new().route(
+            "/callback",
+            axum::routing::post(move |axum::Json(body): axum::Json<Value>| {
+                let sender = sender.clone();
+                async move {
+                    sender.send(body).unwrap();
+                    "ok"
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let callback = format!("http://{}/callback", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, receiver).await.unwrap() });
+
+        let state = StateManager::new();
+        state.webhooks.set_retry_policy(RetryPolicy {
+            attempts: 1,
+            backoff_ms: 0,
+        });
+        let subscribe = |event: &str, callback: &str, folder: Option<&str>| {
+            state.webhooks.create_subscription(
+                "derivative".into(),
+                event.into(),
+                "tenant".into(),
+                callback.into(),
+                WebhookScope {
+                    folder: folder.map(str::to_string),
+                    ..WebhookScope::default()
+                },
+                None,
+            )
+        };
+        subscribe("extraction.finished", &callback, None);
+        let unreachable = subscribe("extraction.finished", "http://127.0.0.1:9/", None);
+        subscribe("extraction.updated", &callback, None);
+
+        let event = WebhookEvent::from_job(&JobNotification {
+            seq: 1,
+            event: JobEvent::Finished,
+            job_id: "job".into(),
+            kind: JobKind::Translation,
+            key: "dXJu".into(),
+            phase: JobPhase::Success,
+            progress: 100,
+            message: None,
+            timestamp: 0,
+        })
+        .unwrap();
+        let mut outcomes = Vec::new();
+        for task in deliver(&state.webhooks, &event) {
+            outcomes.push(task.await.unwrap());
+        }
+        outcomes.sort();
+        assert_eq!(outcomes, [false, true]);
+
+        let body = received.recv().await.unwrap();
+        assert_eq!(body["resourceUrn"], "dXJu");
+        assert_eq!(body["hook"]["event"], "extraction.finished");
+        assert_eq!(body["payload"]["Status"], "success");
+        assert_eq!(body["payload"]["Progress"], "complete");
+        assert!(received.try_recv().is_err());
+        let unreachable = state
+            .webhooks
+            .get_subscription(&unreachable.hook_id)
+            .unwrap();
+        assert_eq!(unreachable.consecutive_failures, 1);
+
+        // Version events only reach hooks on the version's folder
+        let change = Change {
+            kind: "items".into(),
+            id: "urn:adsk.wipprod:dm.lineage:abc".into(),
+            action: AuditAction::Created,
+            old: None,
+            new: Some(serde_json::json!({
+                "id": "urn:adsk.wipprod:dm.lineage:abc",
+                "project_id": "b.project",
+                "folder_id": "urn:folder:a",
+                "name": "Tower.rvt",
+                "storage_urn": "urn:adsk.objects:os.object:bucket/tower.rvt"
+            })),
+        };
+        let events = WebhookEvent::from_changes(&[change]);
+        assert_eq!(events[0].event, "dm.version.added");
+        assert_eq!(
+            events[0].resource_urn,
+            "urn:adsk.wipprod:fs.file:abc?version=1"
+        );
+        let hook = |folder: &str| WebhookSubscription {
+            scope: WebhookScope {
+                folder: Some(folder.into()),
+                ..WebhookScope::default()
+            },
+            ..unreachable.clone()
+        };
+        assert!(events[0].in_scope(&hook("urn:folder:a")));
+        assert!(!events[0].in_scope(&hook("urn:folder:b")));
+    }
+
+    #[tokio::test]
+    async fn test_notifications_are_signed_with_the_app_secret() {
+        assert_eq!(
+            signature("key", b"The quick brown fox jumps over the lazy dog"),
+            "sha1hash=de7c9b85b8b78aa6bc8a7a36f70a90701c9db4d9"
+        );
+
+        let (sender, mut received) = mpsc::unbounded_channel::<(Option<String>, String)>();
+        let receiver = axum::Router::new().route(
+            "/callback",
+            axum::routing::post(move |headers: axum::http::HeaderMap, body: String| {
+                let sender = sender.clone();
+                async move {
+                    let signature = headers
+                        .get(SIGNATURE_HEADER)
+                        .and_then(|v| v.to_str().ok())
+                        .map(str::to_string);
+                    sender.send((signature, body)).unwrap();
+                    "ok"
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let callback = format!("http://{}/callback", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, receiver).await.unwrap() });
+
+        let state = StateManager::new();
+        let mut hook = state.webhooks.create_subscription(
+            "data".into(),
+            "dm.folder.added".into(),
+            "data".into(),
+            callback,
+            WebhookScope::default(),
+            None,
+        );
+        let event = WebhookEvent {
+            system: "data".into(),
+            event: "dm.folder.added".into(),
+            resource_urn: "urn:folder".into(),
+            details: Map::new(),
+            folder: None,
+            project: None,
+        };
+        state.webhooks.set_secret("app", "s3cret".into());
+
+        // Hooks created without an app's token are not signed
+        deliver(&state.webhooks, &event).remove(0).await.unwrap();
+        let (unsigned, _) = received.recv().await.unwrap();
+        assert_eq!(unsigned, None);
+
+        hook.client_id = Some("app".into());
+        state.webhooks.insert_subscription(hook);
+        deliver(&state.webhooks, &event).remove(0).await.unwrap();
+        let (signed, body) = received.recv().await.unwrap();
+        assert_eq!(signed.unwrap(), signature("s3cret", body.as_bytes()));
+    }
+
+    #[tokio::test]
+    async fn test_refused_deliveries_are_retried_then_dead_lettered() {
+        let policy = RetryPolicy {
+            attempts: 3,
+            backoff_ms: 10,
+        };
+        assert_eq!(policy.delay(1), Duration::from_millis(10));
+        assert_eq!(policy.delay(3), Duration::from_millis(40));
+
+        // Refuses the first two attempts of every notification
+        let calls = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counter = calls.clone();
+        let receiver = axum::Router::new().route(
+            "/flaky",
+            axum::routing::post(move || {
+                let counter = counter.clone();
+                async move {
+                    if counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst) % 3 < 2 {
+                        axum::http::StatusCode::SERVICE_UNAVAILABLE
+                    } else {
+                        axum::http::StatusCode::OK
+                    }
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let flaky = format!("http://{}/flaky", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, receiver).await.unwrap() });
+
+        let state = StateManager::new();
+        state.webhooks.set_retry_policy(policy);
+        let hook = state.webhooks.create_subscription(
+            "data".into(),
+            "dm.folder.added".into(),
+            "data".into(),
+            flaky,
+            WebhookScope::default(),
+            None,
+        );
+        let event = WebhookEvent {
+            system: "data".into(),
+            event: "dm.folder.added".into(),
+            resource_urn: "urn:folder".into(),
+            details: Map::new(),
+            folder: None,
+            project: None,
+        };
+        assert!(deliver(&state.webhooks, &event).remove(0).await.unwrap());
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 3);
+        assert!(state.webhooks.dead_letters(None).is_empty());
+
+        state.webhooks.set_retry_policy(RetryPolicy {
+            attempts: 2,
+            ..policy
+        });
+        assert!(!deliver(&state.webhooks, &event).remove(0).await.unwrap());
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 5);
+        let dead = state.webhooks.dead_letters(Some(&hook.hook_id));
+        assert_eq!(dead.len(), 1);
+        assert_eq!(dead[0].attempts, 2);
+        assert_eq!(dead[0].error, "Callback answered 503 Service Unavailable");
+        assert_eq!(dead[0].notification["resourceUrn"], "urn:folder");
+        let hook = state.webhooks.get_subscription(&hook.hook_id).unwrap();
+        assert_eq!(hook.consecutive_failures, 1);
+    }
+}
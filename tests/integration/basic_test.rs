@@ -13,6 +13,7 @@ async fn test_server_creation() {
         verbose: false,
         host: "127.0.0.1".to_string(),
         port: 0, // Let OS choose port
+        ..MockServerConfig::default()
     };
 
     let server = MockServer::new(config).await;
@@ -28,8 +29,33 @@ async fn test_stateless_mode() {
         verbose: false,
         host: "127.0.0.1".to_string(),
         port: 0,
+        ..MockServerConfig::default()
     };
 
     let server = MockServer::new(config).await;
     assert!(server.is_ok());
 }
+
+#[tokio::test]
+async fn test_plan_describes_the_server() {
+    let config = MockServerConfig {
+        openapi_dir: PathBuf::from("/nonexistent"),
+        control_headers: true,
+        port: 8080,
+        ..MockServerConfig::default()
+    };
+    let server = MockServer::new(config).await.unwrap();
+    let plan = server.plan();
+
+    assert_eq!(plan["listen"], "0.0.0.0:8080");
+    assert_eq!(plan["services"]["oss"], "stateful");
+    assert_eq!(plan["behaviors"]["controlHeaders"], true);
+    assert_eq!(plan["state"]["records"]["projects"], 1);
+    let routes = plan["routes"].as_array().unwrap();
+    assert_eq!(routes.len(), plan["routeCount"]);
+    assert!(
+        routes
+            .iter()
+            .any(|r| r["path"] == "/oss/v2/buckets" && r["method"] == "GET")
+    );
+}
@@ -1,9 +1,9 @@
 // SPDX-License-Identifier: Apache-2.0
 // Copyright 2024-2025 Dmytro Yemelianov
 
+use raps_mock::server::catalog::SkipReason;
 use raps_mock::{MockMode, MockServer, MockServerConfig};
 use std::fs;
-use std::path::PathBuf;
 use tempfile::tempdir;
 
 #[tokio::test]
@@ -39,9 +39,10 @@ paths:
         verbose: true,
         host: "127.0.0.1".to_string(),
         port: 0, // Random port
+        ..MockServerConfig::default()
     };
 
-    let server = MockServer::new(config)
+    let _server = MockServer::new(config)
         .await
         .expect("Failed to create server");
 
@@ -54,3 +55,140 @@ paths:
 
     tracing::info!("Server initialized successfully with dynamic routes");
 }
+
+#[tokio::test]
+async fn test_specs_naming_a_parameter_differently_coexist() {
+    let dir = tempdir().unwrap();
+    let spec = |path: &str, param: &str, message: &str| {
+        format!(
+            r#"
+openapi: 3.0.0
+info:
+  title: Test API
+  version: 1.0.0
+paths:
+  {path}:
+    get:
+      parameters:
+        - name: {param}
+          in: path
+          required: true
+          schema:
+            type: string
+      responses:
+        "200":
+          description: OK
+          content:
+            application/json:
+              example:
+                message: {message}
+"#
+        )
+    };
+    fs::write(
+        dir.path().join("issues.yaml"),
+        spec("/projects/{projectId}/issues", "projectId", "issues"),
+    )
+    .unwrap();
+    fs::write(
+        dir.path().join("users.yaml"),
+        spec("/projects/{projectid}/users", "projectid", "users"),
+    )
+    .unwrap();
+
+    let config = MockServerConfig {
+        mode: MockMode::Stateless,
+        openapi_dir: dir.path().to_path_buf(),
+        ..MockServerConfig::default()
+    };
+    let server = MockServer::new(config)
+        .await
+        .expect("Failed to create server");
+
+    let mut paths: Vec<&str> = server
+        .catalog()
+        .routes()
+        .iter()
+        .map(|route| route.path.as_str())
+        .filter(|path| path.starts_with("/projects/"))
+        .collect();
+    paths.sort();
+    assert_eq!(
+        paths,
+        [
+            "/projects/:project_id/issues",
+            "/projects/:project_id/users"
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_skipped_routes_are_listed_with_reasons() {
+    let dir = tempdir().unwrap();
+    let spec = |paths: &[(&str, &str)]| {
+        let mut spec =
+            "openapi: 3.0.0\ninfo:\n  title: Test API\n  version: 1.0.0\npaths:\n".to_string();
+        for (path, methods) in paths {
+            spec.push_str(&format!("  {path}:\n"));
+            for method in methods.split(',') {
+                spec.push_str(&format!(
+                    "    {method}:\n      responses:\n        \"200\":\n          description: OK\n"
+                ));
+            }
+        }
+        spec
+    };
+    fs::write(
+        dir.path().join("a.yaml"),
+        spec(&[("/files/{id}", "get,trace")]),
+    )
+    .unwrap();
+    fs::write(
+        dir.path().join("b.yaml"),
+        spec(&[("/files/{fileId}", "get")]),
+    )
+    .unwrap();
+    // Declares itself a spec, so it is not taken for a $ref fragment
+    fs::write(dir.path().join("c.yaml"), "openapi: 3.0.0\npaths: [oops\n").unwrap();
+
+    let config = MockServerConfig {
+        mode: MockMode::Stateless,
+        openapi_dir: dir.path().to_path_buf(),
+        ..MockServerConfig::default()
+    };
+    let server = MockServer::new(config)
+        .await
+        .expect("Failed to create server");
+
+    let mut skipped: Vec<_> = server
+        .catalog()
+        .skipped()
+        .iter()
+        .filter(|route| {
+            route.reason != SkipReason::Duplicate || route.origin.as_deref() == Some("b")
+        })
+        .map(|route| {
+            (
+                route.reason.as_str(),
+                route.method.as_deref(),
+                route.path.as_deref(),
+            )
+        })
+        .collect();
+    skipped.sort();
+    assert_eq!(
+        skipped,
+        [
+            ("duplicate", Some("GET"), Some("/files/:id")),
+            ("parse-failure", None, None),
+            ("unsupported-method", Some("TRACE"), Some("/files/:id")),
+        ]
+    );
+    let duplicate = server
+        .catalog()
+        .skipped()
+        .iter()
+        .find(|route| route.origin.as_deref() == Some("b"))
+        .unwrap();
+    assert_eq!(duplicate.detail, "already served by spec a");
+}
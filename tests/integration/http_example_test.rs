@@ -37,6 +37,7 @@ paths:
         verbose: false,
         host: "127.0.0.1".to_string(),
         port: 0,
+        ..MockServerConfig::default()
     };
 
     let server = MockServer::new(config).await.expect("server");
@@ -59,4 +60,3 @@ paths:
     // Cleanup: cancel the server task
     server_task.abort();
 }
-